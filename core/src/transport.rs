@@ -36,13 +36,16 @@ use std::{
 };
 
 pub mod and_then;
+pub mod bandwidth_probe;
 pub mod choice;
 pub mod dummy;
 pub mod global_only;
+pub mod liveness;
 pub mod map;
 pub mod map_err;
 pub mod memory;
 pub mod timeout;
+pub mod tunnel;
 pub mod upgrade;
 
 mod boxed;
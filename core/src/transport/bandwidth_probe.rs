@@ -0,0 +1,642 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Transports that estimate the achievable throughput of idle connections.
+//!
+//! [`BandwidthProbe`] wraps a transport producing multiplexed connections. Whenever a connection
+//! did not see any stream traffic for [`Config::with_interval`], it opens a stream, uploads a small
+//! burst of data using the [perf protocol](https://github.com/libp2p/specs/blob/master/perf/perf.md)
+//! and times how long the remote takes to receive it. The resulting estimates can be queried
+//! through the [`BandwidthEstimates`] handle, e.g. by behaviours or applications selecting peers.
+//!
+//! Connections to peers that don't support the perf protocol are never probed again.
+
+use crate::{
+    muxing::{StreamMuxer, StreamMuxerEvent},
+    transport::{DialOpts, ListenerId, TransportError, TransportEvent},
+    Multiaddr, Transport,
+};
+use futures::{
+    future::{BoxFuture, Either, MapOk, TryFutureExt},
+    io::{IoSlice, IoSliceMut},
+    prelude::*,
+    ready,
+};
+use futures_timer::Delay;
+use libp2p_identity::PeerId;
+use multistream_select::{NegotiationError, Version};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt, io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use web_time::Instant;
+
+const PROTOCOL_NAME: &str = "/perf/1.0.0";
+
+/// Configuration of a [`BandwidthProbe`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    interval: Duration,
+    burst_size: usize,
+    timeout: Duration,
+}
+
+impl Config {
+    /// Creates a new [`Config`] with the following default settings:
+    ///
+    ///   * [`Config::with_interval`] 60s
+    ///   * [`Config::with_burst_size`] 64 KiB
+    ///   * [`Config::with_timeout`] 10s
+    pub fn new() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            burst_size: 64 * 1024,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Sets how long a connection has to be idle before it is probed, which is also the minimum
+    /// time between two probes of the same connection.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the number of bytes uploaded per probe.
+    pub fn with_burst_size(mut self, burst_size: usize) -> Self {
+        self.burst_size = burst_size;
+        self
+    }
+
+    /// Sets the time after which a probe is aborted.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The throughput estimate of a single connection.
+#[derive(Debug, Clone)]
+pub struct BandwidthEstimate {
+    /// The address of the remote of the probed connection.
+    pub remote_address: Multiaddr,
+    /// The measured upload throughput in bytes per second.
+    pub bytes_per_second: f64,
+    /// When the measurement was taken.
+    pub measured_at: Instant,
+}
+
+/// Shared handle to the latest [`BandwidthEstimate`]s of all open connections of a
+/// [`BandwidthProbe`].
+///
+/// Estimates are removed once their connection is closed.
+#[derive(Debug, Clone, Default)]
+pub struct BandwidthEstimates {
+    inner: Arc<Mutex<HashMap<PeerId, HashMap<u64, BandwidthEstimate>>>>,
+    next_connection: Arc<AtomicU64>,
+}
+
+impl BandwidthEstimates {
+    /// Returns the estimates of all probed connections to the given peer.
+    pub fn connections(&self, peer: &PeerId) -> Vec<BandwidthEstimate> {
+        self.inner
+            .lock()
+            .get(peer)
+            .map(|connections| connections.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the highest estimate across all probed connections to the given peer.
+    pub fn peer(&self, peer: &PeerId) -> Option<BandwidthEstimate> {
+        self.inner.lock().get(peer).and_then(|connections| {
+            connections
+                .values()
+                .max_by(|a, b| a.bytes_per_second.total_cmp(&b.bytes_per_second))
+                .cloned()
+        })
+    }
+
+    fn insert(&self, peer: PeerId, connection: u64, estimate: BandwidthEstimate) {
+        self.inner
+            .lock()
+            .entry(peer)
+            .or_default()
+            .insert(connection, estimate);
+    }
+
+    fn remove(&self, peer: &PeerId, connection: u64) {
+        let mut inner = self.inner.lock();
+        if let Some(connections) = inner.get_mut(peer) {
+            connections.remove(&connection);
+            if connections.is_empty() {
+                inner.remove(peer);
+            }
+        }
+    }
+}
+
+/// A [`Transport`] that periodically estimates the throughput of idle connections, see the
+/// [module-level documentation](self).
+#[derive(Debug, Clone)]
+#[pin_project::pin_project]
+pub struct BandwidthProbe<T> {
+    #[pin]
+    transport: T,
+    config: Config,
+    estimates: BandwidthEstimates,
+}
+
+impl<T> BandwidthProbe<T> {
+    /// Wraps around a [`Transport`] to probe the throughput of its connections.
+    pub fn new(transport: T, config: Config) -> Self {
+        Self {
+            transport,
+            config,
+            estimates: BandwidthEstimates::default(),
+        }
+    }
+
+    /// Returns a handle to the estimates of all connections of this transport.
+    pub fn estimates(&self) -> BandwidthEstimates {
+        self.estimates.clone()
+    }
+}
+
+type MapMuxer<M> = Box<dyn FnOnce((PeerId, M)) -> (PeerId, Muxer<M>) + Send>;
+
+impl<T, M> Transport for BandwidthProbe<T>
+where
+    T: Transport<Output = (PeerId, M)>,
+    M: StreamMuxer + Send + 'static,
+    M::Substream: Unpin + Send + 'static,
+{
+    type Output = (PeerId, Muxer<M>);
+    type Error = T::Error;
+    type ListenerUpgrade = MapOk<T::ListenerUpgrade, MapMuxer<M>>;
+    type Dial = MapOk<T::Dial, MapMuxer<M>>;
+
+    fn listen_on(
+        &mut self,
+        id: ListenerId,
+        addr: Multiaddr,
+    ) -> Result<(), TransportError<Self::Error>> {
+        self.transport.listen_on(id, addr)
+    }
+
+    fn remove_listener(&mut self, id: ListenerId) -> bool {
+        self.transport.remove_listener(id)
+    }
+
+    fn dial(
+        &mut self,
+        addr: Multiaddr,
+        opts: DialOpts,
+    ) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let config = self.config.clone();
+        let estimates = self.estimates.clone();
+        Ok(self
+            .transport
+            .dial(addr.clone(), opts)?
+            .map_ok(Box::new(move |(peer, muxer)| {
+                (peer, Muxer::new(muxer, peer, addr, config, estimates))
+            })))
+    }
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+        let this = self.project();
+        match ready!(this.transport.poll(cx)) {
+            TransportEvent::Incoming {
+                listener_id,
+                upgrade,
+                local_addr,
+                send_back_addr,
+            } => {
+                let config = this.config.clone();
+                let estimates = this.estimates.clone();
+                let remote_address = send_back_addr.clone();
+                Poll::Ready(TransportEvent::Incoming {
+                    listener_id,
+                    upgrade: upgrade.map_ok(Box::new(move |(peer, muxer)| {
+                        (
+                            peer,
+                            Muxer::new(muxer, peer, remote_address, config, estimates),
+                        )
+                    })),
+                    local_addr,
+                    send_back_addr,
+                })
+            }
+            other => Poll::Ready(other.map_upgrade(|_| unreachable!("case already matched"))),
+        }
+    }
+}
+
+/// The probing state of a connection.
+enum ProbeState {
+    /// Waiting for the connection to become idle.
+    Waiting(Delay),
+    /// Waiting for the stream to probe the connection on.
+    Opening,
+    /// Probing the connection.
+    Probing(BoxFuture<'static, Result<Duration, ProbeError>>),
+    /// The remote does not support probing.
+    Unsupported,
+}
+
+#[derive(Debug)]
+enum ProbeError {
+    Unsupported,
+    Timeout,
+    Io(io::Error),
+}
+
+impl fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProbeError::Unsupported => write!(f, "remote does not support {PROTOCOL_NAME}"),
+            ProbeError::Timeout => write!(f, "probe timed out"),
+            ProbeError::Io(e) => write!(f, "I/O error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ProbeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProbeError::Io(e) => Some(e),
+            ProbeError::Unsupported | ProbeError::Timeout => None,
+        }
+    }
+}
+
+/// Wraps around a [`StreamMuxer`] and probes the throughput of the connection while it is idle.
+#[pin_project::pin_project(PinnedDrop)]
+pub struct Muxer<M> {
+    #[pin]
+    inner: M,
+    peer: PeerId,
+    id: u64,
+    remote_address: Multiaddr,
+    config: Config,
+    estimates: BandwidthEstimates,
+    /// Whether any stream saw traffic since the last check.
+    activity: Arc<AtomicBool>,
+    state: ProbeState,
+}
+
+impl<M> Muxer<M> {
+    fn new(
+        inner: M,
+        peer: PeerId,
+        remote_address: Multiaddr,
+        config: Config,
+        estimates: BandwidthEstimates,
+    ) -> Self {
+        Self {
+            inner,
+            peer,
+            id: estimates.next_connection.fetch_add(1, Ordering::Relaxed),
+            remote_address,
+            state: ProbeState::Waiting(Delay::new(config.interval)),
+            config,
+            estimates,
+            activity: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+#[pin_project::pinned_drop]
+impl<M> PinnedDrop for Muxer<M> {
+    fn drop(self: Pin<&mut Self>) {
+        self.estimates.remove(&self.peer, self.id);
+    }
+}
+
+impl<M> StreamMuxer for Muxer<M>
+where
+    M: StreamMuxer,
+    M::Substream: Unpin + Send + 'static,
+{
+    type Substream = Substream<M::Substream>;
+    type Error = M::Error;
+
+    fn poll_inbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.project();
+        let stream = ready!(this.inner.poll_inbound(cx))?;
+        Poll::Ready(Ok(Substream::new(stream, this.activity.clone())))
+    }
+
+    fn poll_outbound(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.project();
+        let stream = ready!(this.inner.poll_outbound(cx))?;
+        Poll::Ready(Ok(Substream::new(stream, this.activity.clone())))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project().inner.poll_close(cx)
+    }
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
+        let mut this = self.project();
+
+        loop {
+            match this.state {
+                ProbeState::Waiting(delay) => {
+                    if delay.poll_unpin(cx).is_pending() {
+                        break;
+                    }
+                    if this.activity.swap(false, Ordering::Relaxed) {
+                        delay.reset(this.config.interval);
+                        continue;
+                    }
+                    *this.state = ProbeState::Opening;
+                }
+                ProbeState::Opening => match this.inner.as_mut().poll_outbound(cx)? {
+                    Poll::Ready(stream) => {
+                        let probe = probe(stream, this.config.burst_size);
+                        let timeout = Delay::new(this.config.timeout);
+                        *this.state = ProbeState::Probing(
+                            future::select(probe.boxed(), timeout)
+                                .map(|result| match result {
+                                    Either::Left((result, _)) => result,
+                                    Either::Right(((), _)) => Err(ProbeError::Timeout),
+                                })
+                                .boxed(),
+                        );
+                    }
+                    Poll::Pending => break,
+                },
+                ProbeState::Probing(probe) => {
+                    let Poll::Ready(result) = probe.poll_unpin(cx) else {
+                        break;
+                    };
+                    match result {
+                        Ok(elapsed) => {
+                            let estimate = BandwidthEstimate {
+                                remote_address: this.remote_address.clone(),
+                                bytes_per_second: this.config.burst_size as f64
+                                    / elapsed.as_secs_f64(),
+                                measured_at: Instant::now(),
+                            };
+                            tracing::trace!(
+                                peer=%this.peer,
+                                bytes_per_second=%estimate.bytes_per_second,
+                                "Probed connection bandwidth"
+                            );
+                            this.estimates.insert(*this.peer, *this.id, estimate);
+                        }
+                        Err(ProbeError::Unsupported) => {
+                            tracing::debug!(peer=%this.peer, "Remote does not support bandwidth probes");
+                            *this.state = ProbeState::Unsupported;
+                            continue;
+                        }
+                        Err(e) => {
+                            tracing::debug!(peer=%this.peer, "Bandwidth probe failed: {e}");
+                        }
+                    }
+                    // The probe itself is not accounted as activity.
+                    this.activity.store(false, Ordering::Relaxed);
+                    *this.state = ProbeState::Waiting(Delay::new(this.config.interval));
+                }
+                ProbeState::Unsupported => break,
+            }
+        }
+
+        this.inner.poll(cx)
+    }
+}
+
+/// Uploads `burst_size` bytes using the perf protocol and returns how long it took until the
+/// remote confirmed their receipt by closing the stream.
+async fn probe<S>(stream: S, burst_size: usize) -> Result<Duration, ProbeError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (_, mut stream) =
+        multistream_select::dialer_select_proto(stream, [PROTOCOL_NAME], Version::V1)
+            .await
+            .map_err(|e| match e {
+                NegotiationError::Failed => ProbeError::Unsupported,
+                NegotiationError::ProtocolError(e) => ProbeError::Io(e.into()),
+            })?;
+
+    let start = Instant::now();
+
+    // We don't want to download anything.
+    stream
+        .write_all(&0u64.to_be_bytes())
+        .await
+        .map_err(ProbeError::Io)?;
+    let chunk = [0u8; 1024];
+    let mut sent = 0;
+    while sent < burst_size {
+        let n = std::cmp::min(burst_size - sent, chunk.len());
+        sent += stream.write(&chunk[..n]).await.map_err(ProbeError::Io)?;
+    }
+    stream.close().await.map_err(ProbeError::Io)?;
+
+    // The remote closes its side once it received everything.
+    let mut buf = [0u8; 64];
+    while stream.read(&mut buf).await.map_err(ProbeError::Io)? != 0 {}
+
+    Ok(start.elapsed())
+}
+
+/// Wraps around an [`AsyncRead`] + [`AsyncWrite`] and records whether any data went through it.
+#[pin_project::pin_project]
+pub struct Substream<S> {
+    #[pin]
+    inner: S,
+    activity: Arc<AtomicBool>,
+}
+
+impl<S> Substream<S> {
+    fn new(inner: S, activity: Arc<AtomicBool>) -> Self {
+        Self { inner, activity }
+    }
+}
+
+impl<S: AsyncRead> AsyncRead for Substream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let n = ready!(this.inner.poll_read(cx, buf))?;
+        if n > 0 {
+            this.activity.store(true, Ordering::Relaxed);
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let n = ready!(this.inner.poll_read_vectored(cx, bufs))?;
+        if n > 0 {
+            this.activity.store(true, Ordering::Relaxed);
+        }
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<S: AsyncWrite> AsyncWrite for Substream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let n = ready!(this.inner.poll_write(cx, buf))?;
+        if n > 0 {
+            this.activity.store(true, Ordering::Relaxed);
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let n = ready!(this.inner.poll_write_vectored(cx, bufs))?;
+        if n > 0 {
+            this.activity.store(true, Ordering::Relaxed);
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::net::{TcpListener, TcpStream};
+
+    async fn connected_streams() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (dialer, listener) = future::join(TcpStream::connect(addr), listener.accept()).await;
+        (dialer.unwrap(), listener.unwrap().0)
+    }
+
+    #[async_std::test]
+    async fn probe_measures_upload_to_perf_server() {
+        let (dialer, listener) = connected_streams().await;
+
+        let server = async move {
+            let (_, mut stream) =
+                multistream_select::listener_select_proto(listener, [PROTOCOL_NAME])
+                    .await
+                    .unwrap();
+            let mut received = vec![0u8; 8 + 4096];
+            stream.read_exact(&mut received).await.unwrap();
+            stream.close().await.unwrap();
+            received
+        };
+        let (elapsed, received) = future::join(probe(dialer, 4096), server).await;
+
+        assert!(elapsed.is_ok());
+        // We asked the server not to send anything back.
+        assert_eq!(received[..8], 0u64.to_be_bytes());
+    }
+
+    #[async_std::test]
+    async fn probe_detects_unsupported_remote() {
+        let (dialer, listener) = connected_streams().await;
+
+        let server = multistream_select::listener_select_proto(listener, ["/other/1.0.0"]);
+        let (result, _) = future::join(probe(dialer, 4096), server).await;
+
+        assert!(matches!(result, Err(ProbeError::Unsupported)));
+    }
+
+    #[async_std::test]
+    async fn substream_records_activity() {
+        let (dialer, _listener) = connected_streams().await;
+        let activity = Arc::new(AtomicBool::new(false));
+        let mut stream = Substream::new(dialer, activity.clone());
+
+        stream.flush().await.unwrap();
+        assert!(!activity.load(Ordering::Relaxed));
+
+        stream.write_all(b"ping").await.unwrap();
+        assert!(activity.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn estimates_are_removed_with_their_connection() {
+        let estimates = BandwidthEstimates::default();
+        let peer = PeerId::random();
+        let estimate = |bytes_per_second| BandwidthEstimate {
+            remote_address: Multiaddr::empty(),
+            bytes_per_second,
+            measured_at: Instant::now(),
+        };
+
+        estimates.insert(peer, 0, estimate(100.0));
+        estimates.insert(peer, 1, estimate(300.0));
+        assert_eq!(estimates.connections(&peer).len(), 2);
+        assert_eq!(estimates.peer(&peer).unwrap().bytes_per_second, 300.0);
+
+        estimates.remove(&peer, 1);
+        assert_eq!(estimates.peer(&peer).unwrap().bytes_per_second, 100.0);
+        estimates.remove(&peer, 0);
+        assert!(estimates.peer(&peer).is_none());
+    }
+}
@@ -0,0 +1,562 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Transports that periodically verify that their listeners accept connections.
+//!
+//! [`LivenessCheck`] wraps a transport and, every [`Config::with_interval`], dials each of the
+//! addresses its listeners reported via [`TransportEvent::NewAddress`], the same way a remote
+//! would. A dial that fails or doesn't succeed within [`Config::with_timeout`] is reported as
+//! [`TransportEvent::ListenerError`] of the respective listener, catching listeners that silently
+//! stopped accepting connections. The listener itself is left open.
+//!
+//! The wrapper has to be applied to the raw transport, i.e. before upgrading it, such that a
+//! self-dial succeeds once the connection is accepted. The inbound connection resulting from a
+//! self-dial is recognized by its remote host matching the dialed address and is not reported.
+
+use crate::{
+    connection::Endpoint,
+    multiaddr::Protocol,
+    transport::{DialOpts, ListenerId, PortUse, TransportError, TransportEvent},
+    Multiaddr, Transport,
+};
+use futures::{
+    future::{MapErr, TryFutureExt},
+    prelude::*,
+    ready,
+    stream::FuturesUnordered,
+};
+use futures_timer::Delay;
+use std::{
+    collections::{HashMap, HashSet},
+    error, fmt,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// Configuration of a [`LivenessCheck`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    interval: Duration,
+    timeout: Duration,
+}
+
+impl Config {
+    /// Creates a new [`Config`] with the following default settings:
+    ///
+    ///   * [`Config::with_interval`] 5min
+    ///   * [`Config::with_timeout`] 10s
+    pub fn new() -> Self {
+        Self {
+            interval: Duration::from_secs(5 * 60),
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Sets the time between two checks of the listen addresses.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the time after which a self-dial is considered failed.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A [`Transport`] that periodically dials its own listen addresses, see the
+/// [module-level documentation](self).
+pub struct LivenessCheck<T: Transport> {
+    transport: T,
+    config: Config,
+    /// The addresses of each listener.
+    listen_addresses: HashMap<ListenerId, HashSet<Multiaddr>>,
+    next_check: Delay,
+    probes: FuturesUnordered<Probe<T::Dial>>,
+    /// The addresses currently being dialed by `probes`.
+    probing: HashSet<Multiaddr>,
+    /// The listeners and remote hosts of inbound connections expected from self-dials.
+    expected_inbound: Vec<(ListenerId, Protocol<'static>)>,
+}
+
+impl<T: Transport> LivenessCheck<T> {
+    /// Wraps around a [`Transport`] to check the liveness of its listeners.
+    pub fn new(transport: T, config: Config) -> Self {
+        Self {
+            transport,
+            next_check: Delay::new(config.interval),
+            config,
+            listen_addresses: Default::default(),
+            probes: Default::default(),
+            probing: Default::default(),
+            expected_inbound: Default::default(),
+        }
+    }
+}
+
+impl<T: Transport + fmt::Debug> fmt::Debug for LivenessCheck<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LivenessCheck")
+            .field("transport", &self.transport)
+            .field("config", &self.config)
+            .field("listen_addresses", &self.listen_addresses)
+            .finish()
+    }
+}
+
+type MapErrFn<E> = fn(E) -> LivenessError<E>;
+
+impl<T> Transport for LivenessCheck<T>
+where
+    T: Transport + Unpin,
+    T::Error: 'static,
+{
+    type Output = T::Output;
+    type Error = LivenessError<T::Error>;
+    type ListenerUpgrade = MapErr<T::ListenerUpgrade, MapErrFn<T::Error>>;
+    type Dial = MapErr<T::Dial, MapErrFn<T::Error>>;
+
+    fn listen_on(
+        &mut self,
+        id: ListenerId,
+        addr: Multiaddr,
+    ) -> Result<(), TransportError<Self::Error>> {
+        self.transport
+            .listen_on(id, addr)
+            .map_err(|err| err.map(LivenessError::Other))
+    }
+
+    fn remove_listener(&mut self, id: ListenerId) -> bool {
+        self.transport.remove_listener(id)
+    }
+
+    fn dial(
+        &mut self,
+        addr: Multiaddr,
+        opts: DialOpts,
+    ) -> Result<Self::Dial, TransportError<Self::Error>> {
+        Ok(self
+            .transport
+            .dial(addr, opts)
+            .map_err(|err| err.map(LivenessError::Other))?
+            .map_err(LivenessError::Other as MapErrFn<T::Error>))
+    }
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Poll::Ready(Some((listener_id, address, result))) =
+                this.probes.poll_next_unpin(cx)
+            {
+                this.probing.remove(&address);
+                match result {
+                    Ok(()) => {
+                        tracing::trace!(listener=?listener_id, %address, "Listener accepted self-dial");
+                    }
+                    Err(error) if this.listen_addresses.contains_key(&listener_id) => {
+                        tracing::debug!(listener=?listener_id, %address, "Listener failed to accept self-dial");
+                        return Poll::Ready(TransportEvent::ListenerError { listener_id, error });
+                    }
+                    // The listener was closed in the meantime.
+                    Err(_) => {}
+                }
+                continue;
+            }
+
+            if this.next_check.poll_unpin(cx).is_ready() {
+                this.next_check.reset(this.config.interval);
+                this.start_probes();
+                continue;
+            }
+
+            match ready!(Pin::new(&mut this.transport).poll(cx)) {
+                TransportEvent::Incoming {
+                    listener_id,
+                    upgrade,
+                    local_addr,
+                    send_back_addr,
+                } => {
+                    if let Some(index) = host(&send_back_addr).and_then(|host| {
+                        this.expected_inbound
+                            .iter()
+                            .position(|expected| *expected == (listener_id, host.clone()))
+                    }) {
+                        // Drop the inbound side of a self-dial.
+                        this.expected_inbound.swap_remove(index);
+                        continue;
+                    }
+
+                    return Poll::Ready(TransportEvent::Incoming {
+                        listener_id,
+                        upgrade: upgrade.map_err(LivenessError::Other as MapErrFn<T::Error>),
+                        local_addr,
+                        send_back_addr,
+                    });
+                }
+                TransportEvent::NewAddress {
+                    listener_id,
+                    listen_addr,
+                } => {
+                    this.listen_addresses
+                        .entry(listener_id)
+                        .or_default()
+                        .insert(listen_addr.clone());
+                    return Poll::Ready(TransportEvent::NewAddress {
+                        listener_id,
+                        listen_addr,
+                    });
+                }
+                TransportEvent::AddressExpired {
+                    listener_id,
+                    listen_addr,
+                } => {
+                    if let Some(addresses) = this.listen_addresses.get_mut(&listener_id) {
+                        addresses.remove(&listen_addr);
+                    }
+                    return Poll::Ready(TransportEvent::AddressExpired {
+                        listener_id,
+                        listen_addr,
+                    });
+                }
+                TransportEvent::ListenerClosed {
+                    listener_id,
+                    reason,
+                } => {
+                    this.listen_addresses.remove(&listener_id);
+                    this.expected_inbound.retain(|(id, _)| *id != listener_id);
+                    return Poll::Ready(TransportEvent::ListenerClosed {
+                        listener_id,
+                        reason: reason.map_err(LivenessError::Other),
+                    });
+                }
+                TransportEvent::ListenerError { listener_id, error } => {
+                    return Poll::Ready(TransportEvent::ListenerError {
+                        listener_id,
+                        error: LivenessError::Other(error),
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl<T: Transport> LivenessCheck<T> {
+    /// Dials all listen addresses that aren't already being probed.
+    fn start_probes(&mut self) {
+        // Inbound connections of previous self-dials that did not arrive by now never will.
+        self.expected_inbound.clear();
+
+        for (listener_id, addresses) in &self.listen_addresses {
+            for address in addresses {
+                if !self.probing.insert(address.clone()) {
+                    continue;
+                }
+
+                let opts = DialOpts {
+                    role: Endpoint::Dialer,
+                    port_use: PortUse::New,
+                };
+                let dial = match self.transport.dial(address.clone(), opts) {
+                    Ok(dial) => dial,
+                    // The transport can't dial its own address, e.g. for relayed addresses.
+                    Err(TransportError::MultiaddrNotSupported(_)) => {
+                        self.probing.remove(address);
+                        continue;
+                    }
+                    Err(TransportError::Other(error)) => {
+                        self.probes.push(Probe {
+                            listener_id: *listener_id,
+                            address: address.clone(),
+                            state: ProbeState::Failed(Some(error)),
+                            timeout: Delay::new(self.config.timeout),
+                        });
+                        continue;
+                    }
+                };
+                if let Some(host) = host(address) {
+                    self.expected_inbound.push((*listener_id, host));
+                }
+                self.probes.push(Probe {
+                    listener_id: *listener_id,
+                    address: address.clone(),
+                    state: ProbeState::Dialing(dial),
+                    timeout: Delay::new(self.config.timeout),
+                });
+            }
+        }
+    }
+}
+
+/// Returns the host of `address`, i.e. its first component.
+fn host(address: &Multiaddr) -> Option<Protocol<'static>> {
+    address.iter().next().map(|protocol| protocol.acquire())
+}
+
+#[pin_project::pin_project(project = ProbeStateProj)]
+enum ProbeState<F, E> {
+    Dialing(#[pin] F),
+    Failed(Option<E>),
+}
+
+/// A self-dial of a listen address.
+#[pin_project::pin_project]
+struct Probe<F: TryFuture> {
+    listener_id: ListenerId,
+    address: Multiaddr,
+    #[pin]
+    state: ProbeState<F, F::Error>,
+    timeout: Delay,
+}
+
+impl<F: TryFuture> Future for Probe<F> {
+    type Output = (ListenerId, Multiaddr, Result<(), LivenessError<F::Error>>);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let result = match this.state.project() {
+            ProbeStateProj::Dialing(dial) => match dial.try_poll(cx) {
+                // Close the connection right away.
+                Poll::Ready(Ok(_)) => Ok(()),
+                Poll::Ready(Err(error)) => Err(LivenessError::Unreachable {
+                    address: this.address.clone(),
+                    error,
+                }),
+                Poll::Pending => {
+                    ready!(this.timeout.poll_unpin(cx));
+                    Err(LivenessError::Timeout {
+                        address: this.address.clone(),
+                    })
+                }
+            },
+            ProbeStateProj::Failed(error) => Err(LivenessError::Unreachable {
+                address: this.address.clone(),
+                error: error
+                    .take()
+                    .expect("future not to be polled after completion"),
+            }),
+        };
+
+        Poll::Ready((*this.listener_id, this.address.clone(), result))
+    }
+}
+
+/// Error of a [`LivenessCheck`] transport.
+#[derive(Debug)]
+pub enum LivenessError<TErr> {
+    /// Dialing a listen address failed, i.e. the listener does not accept connections.
+    Unreachable { address: Multiaddr, error: TErr },
+    /// Dialing a listen address did not succeed within [`Config::with_timeout`].
+    Timeout { address: Multiaddr },
+    /// Any other error of the wrapped transport.
+    Other(TErr),
+}
+
+impl<TErr> fmt::Display for LivenessError<TErr>
+where
+    TErr: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LivenessError::Unreachable { address, error } => {
+                write!(f, "Listener unreachable at {address}: {error}")
+            }
+            LivenessError::Timeout { address } => {
+                write!(f, "Listener unreachable at {address}: timeout")
+            }
+            LivenessError::Other(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl<TErr> error::Error for LivenessError<TErr>
+where
+    TErr: error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LivenessError::Unreachable { error, .. } => Some(error),
+            LivenessError::Timeout { .. } => None,
+            LivenessError::Other(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::MemoryTransport;
+    use futures::future::BoxFuture;
+    use std::{collections::VecDeque, io};
+
+    /// A transport reporting the addresses it is asked to listen on, dialing which yields the
+    /// result of `dial`.
+    struct Listener {
+        events: VecDeque<TransportEvent<future::Ready<Result<(), io::Error>>, io::Error>>,
+        dial: fn() -> BoxFuture<'static, Result<(), io::Error>>,
+    }
+
+    impl Listener {
+        fn new(dial: fn() -> BoxFuture<'static, Result<(), io::Error>>) -> Self {
+            Self {
+                events: VecDeque::new(),
+                dial,
+            }
+        }
+    }
+
+    impl Transport for Listener {
+        type Output = ();
+        type Error = io::Error;
+        type ListenerUpgrade = future::Ready<Result<(), io::Error>>;
+        type Dial = BoxFuture<'static, Result<(), io::Error>>;
+
+        fn listen_on(
+            &mut self,
+            listener_id: ListenerId,
+            listen_addr: Multiaddr,
+        ) -> Result<(), TransportError<Self::Error>> {
+            self.events.push_back(TransportEvent::NewAddress {
+                listener_id,
+                listen_addr,
+            });
+            Ok(())
+        }
+
+        fn remove_listener(&mut self, _: ListenerId) -> bool {
+            false
+        }
+
+        fn dial(
+            &mut self,
+            _: Multiaddr,
+            _: DialOpts,
+        ) -> Result<Self::Dial, TransportError<Self::Error>> {
+            Ok((self.dial)())
+        }
+
+        fn poll(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+            match self.get_mut().events.pop_front() {
+                Some(event) => Poll::Ready(event),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    fn config() -> Config {
+        Config::new()
+            .with_interval(Duration::from_millis(10))
+            .with_timeout(Duration::from_millis(50))
+    }
+
+    async fn next_event<T: Transport + Unpin>(
+        transport: &mut T,
+    ) -> TransportEvent<T::ListenerUpgrade, T::Error> {
+        future::poll_fn(|cx| Pin::new(&mut *transport).poll(cx)).await
+    }
+
+    async fn listen<T>(transport: &mut LivenessCheck<T>, addr: &str) -> (ListenerId, Multiaddr)
+    where
+        T: Transport + Unpin,
+        T::Error: fmt::Debug + 'static,
+    {
+        let id = ListenerId::next();
+        transport.listen_on(id, addr.parse().unwrap()).unwrap();
+        match next_event(transport).await {
+            TransportEvent::NewAddress {
+                listener_id,
+                listen_addr,
+            } => {
+                assert_eq!(listener_id, id);
+                (id, listen_addr)
+            }
+            _ => panic!("expected new listen address"),
+        }
+    }
+
+    #[async_std::test]
+    async fn reports_unreachable_listener() {
+        let mut transport = LivenessCheck::new(
+            Listener::new(|| {
+                future::ready(Err(io::Error::from(io::ErrorKind::ConnectionRefused))).boxed()
+            }),
+            config(),
+        );
+        let (id, addr) = listen(&mut transport, "/memory/1234").await;
+
+        match next_event(&mut transport).await {
+            TransportEvent::ListenerError {
+                listener_id,
+                error: LivenessError::Unreachable { address, error },
+            } => {
+                assert_eq!(listener_id, id);
+                assert_eq!(address, addr);
+                assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+            }
+            _ => panic!("expected unreachable listener"),
+        }
+    }
+
+    #[async_std::test]
+    async fn reports_listener_not_accepting_in_time() {
+        let mut transport =
+            LivenessCheck::new(Listener::new(|| future::pending().boxed()), config());
+        let (id, addr) = listen(&mut transport, "/memory/1234").await;
+
+        match next_event(&mut transport).await {
+            TransportEvent::ListenerError {
+                listener_id,
+                error: LivenessError::Timeout { address },
+            } => {
+                assert_eq!(listener_id, id);
+                assert_eq!(address, addr);
+            }
+            _ => panic!("expected listener timeout"),
+        }
+    }
+
+    #[async_std::test]
+    async fn accepting_listener_is_not_reported() {
+        let mut transport = LivenessCheck::new(MemoryTransport::default(), config());
+        listen(&mut transport, "/memory/0").await;
+
+        // The self-dial is accepted without an error of the listener.
+        match next_event(&mut transport).await {
+            TransportEvent::Incoming { .. } => {}
+            TransportEvent::ListenerError { error, .. } => panic!("unexpected error: {error}"),
+            _ => panic!("expected incoming self-dial"),
+        }
+    }
+}
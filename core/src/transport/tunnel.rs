@@ -0,0 +1,265 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Routing of dials through an external tunneling layer.
+//!
+//! Overlay deployments often reach part of the network only through a tunnel, e.g. a WireGuard
+//! interface or a VPN socket provider. Instead of forking the TCP or QUIC transport, implement
+//! [`Tunnel`] for the tunneling layer and wrap the raw transport in a [`Transport`]: dials to
+//! addresses within one of the configured [`IpPrefix`]es are handed to the tunnel, all other
+//! dials as well as listening are handled by the wrapped transport.
+//!
+//! The wrapper has to be applied to the raw transport, i.e. before upgrading it, such that
+//! connections opened through the tunnel are secured and multiplexed like any other
+//! connection. Since addresses are matched by IP, it also has to be applied below a DNS
+//! transport. With the `SwarmBuilder` of `libp2p`, this is done in
+//! `with_other_transport`:
+//!
+//! ```text
+//! .with_other_transport(|key| {
+//!     tunnel::Transport::new(tcp::tokio::Transport::default(), wireguard)
+//!         .with_prefix(IpPrefix::new("10.8.0.0".parse()?, 16).expect("valid prefix"))
+//!         .upgrade(upgrade::Version::V1Lazy)
+//!         .authenticate(noise::Config::new(key)?)
+//!         .multiplex(yamux::Config::default())
+//! })?
+//! ```
+
+use crate::{
+    either::EitherFuture,
+    multiaddr::Protocol,
+    transport::{DialOpts, ListenerId, TransportError, TransportEvent},
+    Multiaddr,
+};
+use either::Either;
+use futures::future;
+use std::{
+    future::Future,
+    net::IpAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// An external tunneling layer that connections can be opened through.
+///
+/// Implemented for closures `FnMut(Multiaddr, DialOpts) -> impl Future<Output = Result<_, _>>`.
+pub trait Tunnel {
+    /// The connection opened through the tunnel, e.g. a TCP stream over a VPN interface.
+    type Output;
+    /// An error that occurred while opening a connection through the tunnel.
+    type Error: std::error::Error;
+    /// A pending connection through the tunnel.
+    type Dial: Future<Output = Result<Self::Output, Self::Error>>;
+
+    /// Opens a connection to the given address through the tunnel.
+    fn dial(&mut self, addr: Multiaddr, opts: DialOpts) -> Self::Dial;
+}
+
+impl<F, Fut, O, E> Tunnel for F
+where
+    F: FnMut(Multiaddr, DialOpts) -> Fut,
+    Fut: Future<Output = Result<O, E>>,
+    E: std::error::Error,
+{
+    type Output = O;
+    type Error = E;
+    type Dial = Fut;
+
+    fn dial(&mut self, addr: Multiaddr, opts: DialOpts) -> Self::Dial {
+        self(addr, opts)
+    }
+}
+
+/// A range of IP addresses sharing a common prefix, e.g. `10.8.0.0/16`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IpPrefix {
+    addr: IpAddr,
+    len: u8,
+}
+
+impl IpPrefix {
+    /// Creates a new [`IpPrefix`] from an address and the length of the prefix in bits.
+    ///
+    /// Returns `None` if the length exceeds the length of the address.
+    pub fn new(addr: IpAddr, len: u8) -> Option<Self> {
+        let max_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if len > max_len {
+            return None;
+        }
+
+        Some(Self { addr, len })
+    }
+
+    /// Whether the given address lies within this prefix.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(prefix), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(self.len)).unwrap_or(0);
+                u32::from(prefix) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(prefix), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.len))
+                    .unwrap_or(0);
+                u128::from(prefix) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Routes dials to addresses within the configured [`IpPrefix`]es through a [`Tunnel`].
+///
+/// See the [module documentation](self) for details.
+#[derive(Debug, Clone)]
+#[pin_project::pin_project]
+pub struct Transport<T, U> {
+    #[pin]
+    inner: T,
+    tunnel: U,
+    prefixes: Vec<IpPrefix>,
+}
+
+impl<T, U> Transport<T, U> {
+    /// Wraps `inner`, routing no addresses through `tunnel` until prefixes are added via
+    /// [`Transport::with_prefix`].
+    pub fn new(inner: T, tunnel: U) -> Self {
+        Self {
+            inner,
+            tunnel,
+            prefixes: Vec::new(),
+        }
+    }
+
+    /// Routes dials to addresses within `prefix` through the tunnel.
+    pub fn with_prefix(mut self, prefix: IpPrefix) -> Self {
+        self.prefixes.push(prefix);
+        self
+    }
+
+    fn is_tunneled(&self, addr: &Multiaddr) -> bool {
+        let ip = match addr.iter().next() {
+            Some(Protocol::Ip4(ip)) => IpAddr::from(ip),
+            Some(Protocol::Ip6(ip)) => IpAddr::from(ip),
+            _ => return false,
+        };
+
+        self.prefixes.iter().any(|prefix| prefix.contains(&ip))
+    }
+}
+
+impl<T, U> crate::Transport for Transport<T, U>
+where
+    T: crate::Transport,
+    U: Tunnel,
+{
+    type Output = future::Either<T::Output, U::Output>;
+    type Error = Either<T::Error, U::Error>;
+    type ListenerUpgrade = EitherFuture<T::ListenerUpgrade, U::Dial>;
+    type Dial = EitherFuture<T::Dial, U::Dial>;
+
+    fn listen_on(
+        &mut self,
+        id: ListenerId,
+        addr: Multiaddr,
+    ) -> Result<(), TransportError<Self::Error>> {
+        self.inner
+            .listen_on(id, addr)
+            .map_err(|e| e.map(Either::Left))
+    }
+
+    fn remove_listener(&mut self, id: ListenerId) -> bool {
+        self.inner.remove_listener(id)
+    }
+
+    fn dial(
+        &mut self,
+        addr: Multiaddr,
+        opts: DialOpts,
+    ) -> Result<Self::Dial, TransportError<Self::Error>> {
+        if self.is_tunneled(&addr) {
+            tracing::debug!(address=%addr, "Dialing through tunnel");
+            return Ok(EitherFuture::Second(self.tunnel.dial(addr, opts)));
+        }
+
+        self.inner
+            .dial(addr, opts)
+            .map(EitherFuture::First)
+            .map_err(|e| e.map(Either::Left))
+    }
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+        self.project()
+            .inner
+            .poll(cx)
+            .map(|event| event.map_upgrade(EitherFuture::First).map_err(Either::Left))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        connection::Endpoint,
+        transport::{MemoryTransport, PortUse},
+        Transport as _,
+    };
+    use std::io;
+
+    const OPTS: DialOpts = DialOpts {
+        role: Endpoint::Dialer,
+        port_use: PortUse::Reuse,
+    };
+
+    #[test]
+    fn prefix_contains() {
+        let prefix = IpPrefix::new("10.8.0.0".parse().unwrap(), 16).unwrap();
+
+        assert!(prefix.contains(&"10.8.3.4".parse().unwrap()));
+        assert!(!prefix.contains(&"10.9.0.1".parse().unwrap()));
+        assert!(!prefix.contains(&"::1".parse().unwrap()));
+        assert!(IpPrefix::new("10.0.0.0".parse().unwrap(), 33).is_none());
+    }
+
+    #[test]
+    fn dials_within_prefix_use_tunnel() {
+        let tunnel = |addr: Multiaddr, _: DialOpts| future::ready(Ok::<_, io::Error>(addr));
+        let mut transport = Transport::new(MemoryTransport::default(), tunnel)
+            .with_prefix(IpPrefix::new("10.8.0.0".parse().unwrap(), 16).unwrap());
+
+        let tunneled: Multiaddr = "/ip4/10.8.0.1/tcp/4001".parse().unwrap();
+        let dial = transport.dial(tunneled.clone(), OPTS).unwrap();
+        match futures::executor::block_on(dial) {
+            Ok(future::Either::Right(addr)) => assert_eq!(addr, tunneled),
+            _ => panic!("expected dial through tunnel"),
+        }
+
+        assert!(matches!(
+            transport.dial("/ip4/192.0.2.1/tcp/4001".parse().unwrap(), OPTS),
+            Err(TransportError::MultiaddrNotSupported(_))
+        ));
+    }
+}
@@ -32,7 +32,7 @@ use crate::{
     },
     upgrade::{
         self, apply_inbound, apply_outbound, InboundConnectionUpgrade, InboundUpgradeApply,
-        OutboundConnectionUpgrade, OutboundUpgradeApply, UpgradeError,
+        OutboundConnectionUpgrade, OutboundUpgradeApply, SelectedMuxer, UpgradeError,
     },
     Negotiated,
 };
@@ -43,6 +43,10 @@ use std::{
     error::Error,
     fmt,
     pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
     time::Duration,
 };
@@ -284,6 +288,68 @@ where
             }
         }))
     }
+
+    /// Like [`Authenticated::multiplex`] but uses the stream multiplexer selected during the
+    /// security handshake, if any.
+    ///
+    /// If the security handshake selected one of the multiplexers offered by `upgrade` (see
+    /// [`SelectedMuxer`]), it is applied right away, saving the round-trip of negotiating it via
+    /// multistream-select. Otherwise, e.g. if the remote does not support selecting the
+    /// multiplexer during the handshake, the multiplexer is negotiated as usual. Connections
+    /// that skip the negotiation are counted in `statistics`.
+    ///
+    /// ## Transitions
+    ///
+    ///   * I/O upgrade: `C -> M`.
+    ///   * Transport output: `(PeerId, C) -> (PeerId, M)`.
+    pub fn multiplex_pipelined<C, M, U, E>(
+        self,
+        upgrade: U,
+        statistics: PipeliningStatistics,
+    ) -> Multiplexed<AndThen<T, impl FnOnce((PeerId, C), ConnectedPoint) -> Multiplex<C, U> + Clone>>
+    where
+        T: Transport<Output = (PeerId, C)>,
+        C: SelectedMuxer + AsyncRead + AsyncWrite + Unpin,
+        M: StreamMuxer,
+        U: InboundConnectionUpgrade<Negotiated<C>, Output = M, Error = E>,
+        U: OutboundConnectionUpgrade<Negotiated<C>, Output = M, Error = E> + Clone,
+        E: Error + 'static,
+    {
+        let version = self.0.version;
+        Multiplexed(self.0.inner.and_then(move |(i, c), endpoint| {
+            let selected = c.selected_muxer().and_then(|muxer| {
+                upgrade
+                    .protocol_info()
+                    .into_iter()
+                    .find(|info| info.as_ref() == muxer)
+            });
+            let upgrade = match selected {
+                Some(info) => {
+                    statistics.round_trips_saved.fetch_add(1, Ordering::Relaxed);
+                    upgrade::apply_selected(c, upgrade, info, endpoint)
+                }
+                None => upgrade::apply(c, upgrade, endpoint, version),
+            };
+            Multiplex {
+                peer_id: Some(i),
+                upgrade,
+            }
+        }))
+    }
+}
+
+/// Shared handle to the statistics of [`Authenticated::multiplex_pipelined`].
+#[derive(Debug, Clone, Default)]
+pub struct PipeliningStatistics {
+    round_trips_saved: Arc<AtomicU64>,
+}
+
+impl PipeliningStatistics {
+    /// Returns the number of round-trips saved by applying the stream multiplexer selected
+    /// during the security handshake, i.e. one per connection that skipped its negotiation.
+    pub fn round_trips_saved(&self) -> u64 {
+        self.round_trips_saved.load(Ordering::Relaxed)
+    }
 }
 
 /// A authenticated and multiplexed transport, obtained from
@@ -66,7 +66,7 @@ mod ready;
 mod select;
 
 pub(crate) use apply::{
-    apply, apply_inbound, apply_outbound, InboundUpgradeApply, OutboundUpgradeApply,
+    apply, apply_inbound, apply_outbound, apply_selected, InboundUpgradeApply, OutboundUpgradeApply,
 };
 pub(crate) use error::UpgradeError;
 use futures::future::Future;
@@ -152,3 +152,14 @@ pub trait OutboundConnectionUpgrade<T>: UpgradeInfo {
     /// The `info` is the identifier of the protocol, as produced by `protocol_info`.
     fn upgrade_outbound(self, socket: T, info: Self::Info) -> Self::Future;
 }
+
+/// A secured connection whose security handshake may have selected the stream multiplexer.
+///
+/// Security protocols like Noise can agree on the stream multiplexer as part of their handshake,
+/// saving the round-trip of negotiating it via multistream-select afterwards. See
+/// [`Authenticated::multiplex_pipelined`](crate::transport::upgrade::Authenticated::multiplex_pipelined).
+pub trait SelectedMuxer {
+    /// Returns the protocol of the stream multiplexer selected during the security handshake,
+    /// if any.
+    fn selected_muxer(&self) -> Option<&str>;
+}
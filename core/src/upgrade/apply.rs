@@ -46,6 +46,38 @@ where
     }
 }
 
+/// Applies an upgrade whose protocol was already agreed on, skipping the negotiation.
+pub(crate) fn apply_selected<C, U>(
+    conn: C,
+    up: U,
+    info: U::Info,
+    cp: ConnectedPoint,
+) -> Either<InboundUpgradeApply<C, U>, OutboundUpgradeApply<C, U>>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+    U: InboundConnectionUpgrade<Negotiated<C>> + OutboundConnectionUpgrade<Negotiated<C>>,
+{
+    let name = info.as_ref().to_owned();
+    let io = Negotiated::completed(conn);
+
+    match cp {
+        ConnectedPoint::Dialer { role_override, .. } if role_override.is_dialer() => {
+            Either::Right(OutboundUpgradeApply {
+                inner: OutboundUpgradeApplyState::Upgrade {
+                    future: Box::pin(up.upgrade_outbound(io, info)),
+                    name,
+                },
+            })
+        }
+        _ => Either::Left(InboundUpgradeApply {
+            inner: InboundUpgradeApplyState::Upgrade {
+                future: Box::pin(up.upgrade_inbound(io, info)),
+                name,
+            },
+        }),
+    }
+}
+
 /// Tries to perform an upgrade on an inbound connection or substream.
 pub(crate) fn apply_inbound<C, U>(conn: C, up: U) -> InboundUpgradeApply<C, U>
 where
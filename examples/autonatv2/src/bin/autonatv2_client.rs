@@ -83,8 +83,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
             })) => {
                 println!("Tested {tested_addr} with {server}. Sent {bytes_sent} bytes for verification. Failed with {e:?}.");
             }
-            SwarmEvent::ExternalAddrConfirmed { address } => {
-                println!("External address confirmed: {address}");
+            SwarmEvent::ExternalAddrConfirmed { address, source } => {
+                println!("External address confirmed: {address} ({source:?})");
             }
             _ => {}
         }
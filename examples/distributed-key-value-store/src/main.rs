@@ -119,7 +119,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     kad::QueryResult::GetRecord(Err(err)) => {
                         eprintln!("Failed to get record: {err:?}");
                     }
-                    kad::QueryResult::PutRecord(Ok(kad::PutRecordOk { key })) => {
+                    kad::QueryResult::PutRecord(Ok(kad::PutRecordOk { key, .. })) => {
                         println!(
                             "Successfully put record {:?}",
                             std::str::from_utf8(key.as_ref()).unwrap()
@@ -77,6 +77,32 @@ impl DecodingError {
         }
     }
 
+    #[cfg(all(
+        any(feature = "openssh", feature = "jwk"),
+        any(feature = "ed25519", feature = "ecdsa")
+    ))]
+    pub(crate) fn invalid_format(format: &'static str, reason: &'static str) -> Self {
+        Self {
+            msg: format!("invalid {format} key: {reason}"),
+            source: None,
+        }
+    }
+
+    #[cfg(all(
+        any(feature = "openssh", feature = "jwk"),
+        any(feature = "ed25519", feature = "ecdsa"),
+        any(
+            all(feature = "rsa", not(target_arch = "wasm32")),
+            feature = "secp256k1"
+        )
+    ))]
+    pub(crate) fn format_unsupported(format: &'static str, key_type: KeyType) -> Self {
+        Self {
+            msg: format!("encoding {key_type} key to {format} is unsupported"),
+            source: None,
+        }
+    }
+
     #[cfg(all(feature = "rsa", not(target_arch = "wasm32")))]
     pub(crate) fn encoding_unsupported(key_type: &'static str) -> Self {
         Self {
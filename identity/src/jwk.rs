@@ -0,0 +1,221 @@
+// Copyright 2023 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Encoding of Ed25519 and ECDSA keys as JSON Web Keys.
+//!
+//! Ed25519 keys use the `OKP` key type of [RFC 8037](https://datatracker.ietf.org/doc/html/rfc8037)
+//! and ECDSA keys the `EC` key type with the `P-256` curve of
+//! [RFC 7518](https://datatracker.ietf.org/doc/html/rfc7518#section-6.2).
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde_json::{json, Map, Value};
+use zeroize::Zeroizing;
+
+#[cfg(feature = "ecdsa")]
+use crate::ecdsa;
+#[cfg(feature = "ed25519")]
+use crate::ed25519;
+use crate::error::DecodingError;
+use crate::{Keypair, PublicKey};
+
+const FORMAT: &str = "JWK";
+
+#[cfg(feature = "ed25519")]
+pub(crate) fn encode_ed25519_public(key: &ed25519::PublicKey) -> String {
+    ed25519_jwk(key).to_string()
+}
+
+#[cfg(feature = "ecdsa")]
+pub(crate) fn encode_ecdsa_public(key: &ecdsa::PublicKey) -> String {
+    ecdsa_jwk(key).to_string()
+}
+
+#[cfg(feature = "ed25519")]
+pub(crate) fn encode_ed25519_keypair(keypair: &ed25519::Keypair) -> String {
+    let mut jwk = ed25519_jwk(&keypair.public());
+    jwk["d"] = Value::String(URL_SAFE_NO_PAD.encode(keypair.secret()));
+    jwk.to_string()
+}
+
+#[cfg(feature = "ecdsa")]
+pub(crate) fn encode_ecdsa_keypair(keypair: &ecdsa::Keypair) -> String {
+    let mut jwk = ecdsa_jwk(keypair.public());
+    let secret = Zeroizing::new(keypair.secret().to_bytes());
+    jwk["d"] = Value::String(URL_SAFE_NO_PAD.encode(&secret[..]));
+    jwk.to_string()
+}
+
+/// Decodes the public key of a JWK. A private key component is ignored.
+pub(crate) fn decode_public(s: &str) -> Result<PublicKey, DecodingError> {
+    read_public(&parse(s)?)
+}
+
+/// Decodes a JWK that contains the private key component.
+pub(crate) fn decode_keypair(s: &str) -> Result<Keypair, DecodingError> {
+    let jwk = parse(s)?;
+    let public = read_public(&jwk)?;
+    let secret = Zeroizing::new(read_bytes(&jwk, "d")?);
+
+    #[cfg(feature = "ed25519")]
+    if public.clone().try_into_ed25519().is_ok() {
+        let mut secret = secret;
+        let keypair = Keypair::from(ed25519::Keypair::from(ed25519::SecretKey::try_from_bytes(
+            &mut secret[..],
+        )?));
+        return check_public(keypair, &public);
+    }
+
+    #[cfg(feature = "ecdsa")]
+    if public.clone().try_into_ecdsa().is_ok() {
+        let keypair = Keypair::from(ecdsa::Keypair::from(ecdsa::SecretKey::try_from_bytes(
+            &secret[..],
+        )?));
+        return check_public(keypair, &public);
+    }
+
+    Err(DecodingError::invalid_format(
+        FORMAT,
+        "unsupported key type",
+    ))
+}
+
+#[cfg(feature = "ed25519")]
+fn ed25519_jwk(key: &ed25519::PublicKey) -> Value {
+    json!({
+        "kty": "OKP",
+        "crv": "Ed25519",
+        "x": URL_SAFE_NO_PAD.encode(key.to_bytes()),
+    })
+}
+
+#[cfg(feature = "ecdsa")]
+fn ecdsa_jwk(key: &ecdsa::PublicKey) -> Value {
+    // Uncompressed SEC1 encoding: 0x04 || x || y.
+    let point = key.to_bytes();
+    json!({
+        "kty": "EC",
+        "crv": "P-256",
+        "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+        "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+    })
+}
+
+fn parse(s: &str) -> Result<Map<String, Value>, DecodingError> {
+    serde_json::from_str(s).map_err(|e| DecodingError::failed_to_parse("JWK", e))
+}
+
+fn read_public(jwk: &Map<String, Value>) -> Result<PublicKey, DecodingError> {
+    let kty = jwk.get("kty").and_then(Value::as_str);
+    let crv = jwk.get("crv").and_then(Value::as_str);
+    match (kty, crv) {
+        #[cfg(feature = "ed25519")]
+        (Some("OKP"), Some("Ed25519")) => {
+            Ok(ed25519::PublicKey::try_from_bytes(&read_bytes(jwk, "x")?)?.into())
+        }
+        #[cfg(feature = "ecdsa")]
+        (Some("EC"), Some("P-256")) => {
+            let x = read_bytes(jwk, "x")?;
+            let y = read_bytes(jwk, "y")?;
+            if x.len() != 32 || y.len() != 32 {
+                return Err(DecodingError::invalid_format(
+                    FORMAT,
+                    "malformed coordinates",
+                ));
+            }
+            let mut point = Vec::with_capacity(65);
+            point.push(0x04);
+            point.extend_from_slice(&x);
+            point.extend_from_slice(&y);
+            Ok(ecdsa::PublicKey::try_from_bytes(&point)?.into())
+        }
+        _ => Err(DecodingError::invalid_format(
+            FORMAT,
+            "unsupported key type",
+        )),
+    }
+}
+
+fn read_bytes(jwk: &Map<String, Value>, member: &'static str) -> Result<Vec<u8>, DecodingError> {
+    let value = jwk
+        .get(member)
+        .and_then(Value::as_str)
+        .ok_or_else(|| DecodingError::invalid_format(FORMAT, "missing key component"))?;
+    URL_SAFE_NO_PAD
+        .decode(value)
+        .map_err(|e| DecodingError::failed_to_parse("JWK key component", e))
+}
+
+fn check_public(keypair: Keypair, public: &PublicKey) -> Result<Keypair, DecodingError> {
+    if keypair.public() != *public {
+        return Err(DecodingError::invalid_format(
+            FORMAT,
+            "public key does not match private key",
+        ));
+    }
+    Ok(keypair)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "ed25519")]
+    fn ed25519_rfc8037_test_vector() {
+        // RFC 8037, appendix A.1 and A.2.
+        let jwk = r#"{"kty":"OKP","crv":"Ed25519",
+            "d":"nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A",
+            "x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}"#;
+        let keypair = Keypair::from_jwk(jwk).unwrap();
+        let public = PublicKey::from_jwk(jwk).unwrap();
+        assert_eq!(keypair.public(), public);
+
+        let decoded = Keypair::from_jwk(&keypair.to_jwk().unwrap()).unwrap();
+        assert_eq!(decoded.public(), public);
+        assert_eq!(
+            PublicKey::from_jwk(&public.to_jwk().unwrap()).unwrap(),
+            public
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "ecdsa", feature = "rand"))]
+    fn ecdsa_roundtrip() {
+        let keypair = Keypair::generate_ecdsa();
+        let public = keypair.public();
+
+        let decoded = Keypair::from_jwk(&keypair.to_jwk().unwrap()).unwrap();
+        let msg = b"hello world";
+        assert!(public.verify(msg, &decoded.sign(msg).unwrap()));
+        assert_eq!(
+            PublicKey::from_jwk(&public.to_jwk().unwrap()).unwrap(),
+            public
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "ed25519")]
+    fn rejects_public_key_without_private_key() {
+        let jwk =
+            r#"{"kty":"OKP","crv":"Ed25519","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}"#;
+        assert!(PublicKey::from_jwk(jwk).is_ok());
+        assert!(Keypair::from_jwk(jwk).is_err());
+    }
+}
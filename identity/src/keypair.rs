@@ -57,6 +57,10 @@ use crate::secp256k1;
 
 #[cfg(feature = "ecdsa")]
 use crate::ecdsa;
+#[cfg(all(feature = "jwk", any(feature = "ed25519", feature = "ecdsa")))]
+use crate::jwk;
+#[cfg(all(feature = "openssh", any(feature = "ed25519", feature = "ecdsa")))]
+use crate::openssh;
 use crate::KeyType;
 
 /// Identity keypair of a node.
@@ -174,6 +178,59 @@ impl Keypair {
         })
     }
 
+    /// Encode an Ed25519 or ECDSA keypair in the unencrypted OpenSSH private key format, as
+    /// written by `ssh-keygen`.
+    #[cfg(all(feature = "openssh", any(feature = "ed25519", feature = "ecdsa")))]
+    pub fn to_openssh(&self) -> Result<String, DecodingError> {
+        match self.keypair {
+            #[cfg(feature = "ed25519")]
+            KeyPairInner::Ed25519(ref kp) => Ok(openssh::encode_ed25519_keypair(kp)),
+            #[cfg(feature = "ecdsa")]
+            KeyPairInner::Ecdsa(ref kp) => Ok(openssh::encode_ecdsa_keypair(kp)),
+            #[cfg(all(feature = "rsa", not(target_arch = "wasm32")))]
+            KeyPairInner::Rsa(_) => Err(DecodingError::format_unsupported(
+                "OpenSSH",
+                self.key_type(),
+            )),
+            #[cfg(feature = "secp256k1")]
+            KeyPairInner::Secp256k1(_) => Err(DecodingError::format_unsupported(
+                "OpenSSH",
+                self.key_type(),
+            )),
+        }
+    }
+
+    /// Decode an Ed25519 or ECDSA (P-256) keypair from the unencrypted OpenSSH private key
+    /// format, e.g. generated with `ssh-keygen -t ed25519 -N ""`.
+    #[cfg(all(feature = "openssh", any(feature = "ed25519", feature = "ecdsa")))]
+    pub fn from_openssh(s: &str) -> Result<Keypair, DecodingError> {
+        openssh::decode_keypair(s)
+    }
+
+    /// Encode an Ed25519 or ECDSA keypair as JSON Web Key, including the private key.
+    #[cfg(all(feature = "jwk", any(feature = "ed25519", feature = "ecdsa")))]
+    pub fn to_jwk(&self) -> Result<String, DecodingError> {
+        match self.keypair {
+            #[cfg(feature = "ed25519")]
+            KeyPairInner::Ed25519(ref kp) => Ok(jwk::encode_ed25519_keypair(kp)),
+            #[cfg(feature = "ecdsa")]
+            KeyPairInner::Ecdsa(ref kp) => Ok(jwk::encode_ecdsa_keypair(kp)),
+            #[cfg(all(feature = "rsa", not(target_arch = "wasm32")))]
+            KeyPairInner::Rsa(_) => Err(DecodingError::format_unsupported("JWK", self.key_type())),
+            #[cfg(feature = "secp256k1")]
+            KeyPairInner::Secp256k1(_) => {
+                Err(DecodingError::format_unsupported("JWK", self.key_type()))
+            }
+        }
+    }
+
+    /// Decode an Ed25519 (`OKP`) or ECDSA (`EC`, `P-256`) keypair from a JSON Web Key that
+    /// contains the private key.
+    #[cfg(all(feature = "jwk", any(feature = "ed25519", feature = "ecdsa")))]
+    pub fn from_jwk(s: &str) -> Result<Keypair, DecodingError> {
+        jwk::decode_keypair(s)
+    }
+
     /// Sign a message using the private key of this keypair, producing
     /// a signature that can be verified using the corresponding public key.
     #[allow(unused_variables)]
@@ -601,6 +658,60 @@ impl PublicKey {
         unreachable!()
     }
 
+    /// Encode an Ed25519 or ECDSA public key in the OpenSSH `authorized_keys` format.
+    #[cfg(all(feature = "openssh", any(feature = "ed25519", feature = "ecdsa")))]
+    pub fn to_openssh(&self) -> Result<String, DecodingError> {
+        match self.publickey {
+            #[cfg(feature = "ed25519")]
+            PublicKeyInner::Ed25519(ref key) => Ok(openssh::encode_ed25519_public(key)),
+            #[cfg(feature = "ecdsa")]
+            PublicKeyInner::Ecdsa(ref key) => Ok(openssh::encode_ecdsa_public(key)),
+            #[cfg(all(feature = "rsa", not(target_arch = "wasm32")))]
+            PublicKeyInner::Rsa(_) => Err(DecodingError::format_unsupported(
+                "OpenSSH",
+                self.key_type(),
+            )),
+            #[cfg(feature = "secp256k1")]
+            PublicKeyInner::Secp256k1(_) => Err(DecodingError::format_unsupported(
+                "OpenSSH",
+                self.key_type(),
+            )),
+        }
+    }
+
+    /// Decode an Ed25519 or ECDSA (P-256) public key from the OpenSSH `authorized_keys` format,
+    /// e.g. the content of an `id_ed25519.pub` file.
+    #[cfg(all(feature = "openssh", any(feature = "ed25519", feature = "ecdsa")))]
+    pub fn from_openssh(s: &str) -> Result<PublicKey, DecodingError> {
+        openssh::decode_public(s)
+    }
+
+    /// Encode an Ed25519 or ECDSA public key as JSON Web Key.
+    #[cfg(all(feature = "jwk", any(feature = "ed25519", feature = "ecdsa")))]
+    pub fn to_jwk(&self) -> Result<String, DecodingError> {
+        match self.publickey {
+            #[cfg(feature = "ed25519")]
+            PublicKeyInner::Ed25519(ref key) => Ok(jwk::encode_ed25519_public(key)),
+            #[cfg(feature = "ecdsa")]
+            PublicKeyInner::Ecdsa(ref key) => Ok(jwk::encode_ecdsa_public(key)),
+            #[cfg(all(feature = "rsa", not(target_arch = "wasm32")))]
+            PublicKeyInner::Rsa(_) => {
+                Err(DecodingError::format_unsupported("JWK", self.key_type()))
+            }
+            #[cfg(feature = "secp256k1")]
+            PublicKeyInner::Secp256k1(_) => {
+                Err(DecodingError::format_unsupported("JWK", self.key_type()))
+            }
+        }
+    }
+
+    /// Decode an Ed25519 (`OKP`) or ECDSA (`EC`, `P-256`) public key from a JSON Web Key.
+    /// Private key components are ignored.
+    #[cfg(all(feature = "jwk", any(feature = "ed25519", feature = "ecdsa")))]
+    pub fn from_jwk(s: &str) -> Result<PublicKey, DecodingError> {
+        jwk::decode_public(s)
+    }
+
     /// Decode a public key from a protobuf structure, e.g. read from storage
     /// or received from another node.
     #[allow(unused_variables)]
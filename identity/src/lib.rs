@@ -59,9 +59,15 @@ pub mod rsa;
 pub mod secp256k1;
 
 mod error;
+#[cfg(all(feature = "jwk", any(feature = "ed25519", feature = "ecdsa")))]
+mod jwk;
 mod keypair;
+#[cfg(all(feature = "openssh", any(feature = "ed25519", feature = "ecdsa")))]
+mod openssh;
 #[cfg(feature = "peerid")]
 mod peer_id;
+#[cfg(feature = "peerid")]
+pub mod peer_set;
 
 #[cfg(any(
     feature = "ecdsa",
@@ -0,0 +1,304 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Memory-efficient collections of [`PeerId`]s.
+//!
+//! Nodes with a high degree tend to keep many sets of peers around, e.g. to remember which
+//! peers already sent a given message. [`PeerSet`] is an exact replacement for
+//! `HashSet<PeerId>` without the per-entry overhead of a hash table, while [`PeerFilter`]
+//! trades exactness for a fixed, small memory footprint.
+
+use crate::PeerId;
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hash, Hasher},
+};
+
+/// An exact set of [`PeerId`]s, stored as a sorted vector.
+///
+/// Lookups are `O(log n)` and insertions are `O(n)`, which makes this a good fit for the
+/// small-to-medium sets that are created and dropped frequently.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PeerSet {
+    peers: Vec<PeerId>,
+}
+
+impl PeerSet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty set with room for `capacity` peers.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            peers: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Adds a peer to the set.
+    ///
+    /// Returns `true` if the peer was not already present.
+    pub fn insert(&mut self, peer: PeerId) -> bool {
+        match self.peers.binary_search(&peer) {
+            Ok(_) => false,
+            Err(index) => {
+                self.peers.insert(index, peer);
+                true
+            }
+        }
+    }
+
+    /// Removes a peer from the set.
+    ///
+    /// Returns `true` if the peer was present.
+    pub fn remove(&mut self, peer: &PeerId) -> bool {
+        match self.peers.binary_search(peer) {
+            Ok(index) => {
+                self.peers.remove(index);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Returns `true` if the set contains the given peer.
+    pub fn contains(&self, peer: &PeerId) -> bool {
+        self.peers.binary_search(peer).is_ok()
+    }
+
+    /// Returns the number of peers in the set.
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// Returns `true` if the set contains no peers.
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    /// Removes all peers from the set, keeping the allocated memory.
+    pub fn clear(&mut self) {
+        self.peers.clear()
+    }
+
+    /// Shrinks the allocated memory as much as possible.
+    pub fn shrink_to_fit(&mut self) {
+        self.peers.shrink_to_fit()
+    }
+
+    /// Iterates over the peers in the set, in ascending order.
+    pub fn iter(&self) -> std::slice::Iter<'_, PeerId> {
+        self.peers.iter()
+    }
+}
+
+impl FromIterator<PeerId> for PeerSet {
+    fn from_iter<I: IntoIterator<Item = PeerId>>(iter: I) -> Self {
+        let mut peers = iter.into_iter().collect::<Vec<_>>();
+        peers.sort_unstable();
+        peers.dedup();
+        Self { peers }
+    }
+}
+
+impl Extend<PeerId> for PeerSet {
+    fn extend<I: IntoIterator<Item = PeerId>>(&mut self, iter: I) {
+        self.peers.extend(iter);
+        self.peers.sort_unstable();
+        self.peers.dedup();
+    }
+}
+
+impl IntoIterator for PeerSet {
+    type Item = PeerId;
+    type IntoIter = std::vec::IntoIter<PeerId>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.peers.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a PeerSet {
+    type Item = &'a PeerId;
+    type IntoIter = std::slice::Iter<'a, PeerId>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.peers.iter()
+    }
+}
+
+/// A bloom filter keyed by [`PeerId`].
+///
+/// The filter uses a fixed amount of memory, determined by the expected number of peers and the
+/// acceptable false positive rate. [`PeerFilter::contains`] never returns `false` for a peer
+/// that was inserted, but may return `true` for a peer that was not. Peers cannot be removed;
+/// use [`PeerFilter::clear`] to reset the filter instead.
+///
+/// The hash function is randomly keyed per filter, such that remote peers cannot craft
+/// [`PeerId`]s that collide with other peers.
+#[derive(Clone)]
+pub struct PeerFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+    len: usize,
+    hasher: RandomState,
+}
+
+impl PeerFilter {
+    /// Creates a filter sized for `capacity` peers with the given false positive rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `false_positive_rate` is not within `(0, 1)`.
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "false positive rate must be within (0, 1)"
+        );
+
+        let ln2 = std::f64::consts::LN_2;
+        let capacity = capacity.max(1) as f64;
+        let num_bits = (-capacity * false_positive_rate.ln() / (ln2 * ln2)).ceil() as u64;
+        let num_bits = num_bits.max(64).next_multiple_of(64);
+        let num_hashes = ((num_bits as f64 / capacity) * ln2).round().max(1.0) as u32;
+
+        Self {
+            bits: vec![0; (num_bits / 64) as usize],
+            num_bits,
+            num_hashes,
+            len: 0,
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Adds a peer to the filter.
+    ///
+    /// Returns `true` if the peer was definitely not present before.
+    pub fn insert(&mut self, peer: &PeerId) -> bool {
+        let mut inserted = false;
+        for bit in self.bit_indices(peer) {
+            let (word, mask) = ((bit / 64) as usize, 1 << (bit % 64));
+            if self.bits[word] & mask == 0 {
+                self.bits[word] |= mask;
+                inserted = true;
+            }
+        }
+        if inserted {
+            self.len += 1;
+        }
+        inserted
+    }
+
+    /// Returns `true` if the peer may have been inserted, `false` if it definitely wasn't.
+    pub fn contains(&self, peer: &PeerId) -> bool {
+        self.bit_indices(peer)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Returns the number of peers that have been inserted.
+    ///
+    /// Peers for which [`PeerFilter::insert`] reported a (false) positive are not counted.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no peer has been inserted.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all peers from the filter.
+    pub fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|word| *word = 0);
+        self.len = 0;
+    }
+
+    /// Computes the bit positions of a peer using double hashing.
+    fn bit_indices(&self, peer: &PeerId) -> impl Iterator<Item = u64> {
+        let mut hasher = self.hasher.build_hasher();
+        peer.hash(&mut hasher);
+        let h1 = hasher.finish();
+        // Hashing a second time continues from the state of the first hash, yielding an
+        // independent second value.
+        peer.hash(&mut hasher);
+        let h2 = hasher.finish() | 1;
+        let num_bits = self.num_bits;
+
+        (0..u64::from(self.num_hashes)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+impl fmt::Debug for PeerFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PeerFilter")
+            .field("num_bits", &self.num_bits)
+            .field("num_hashes", &self.num_hashes)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "rand"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peer_set_is_sorted_and_deduplicated() {
+        let peers = (0..10).map(|_| PeerId::random()).collect::<Vec<_>>();
+        let mut set = peers
+            .iter()
+            .chain(peers.iter())
+            .copied()
+            .collect::<PeerSet>();
+
+        assert_eq!(set.len(), peers.len());
+        assert!(set.iter().zip(set.iter().skip(1)).all(|(a, b)| a < b));
+        assert!(peers.iter().all(|p| set.contains(p)));
+
+        assert!(!set.insert(peers[0]));
+        assert!(set.remove(&peers[0]));
+        assert!(!set.contains(&peers[0]));
+        assert!(set.insert(peers[0]));
+        assert!(!set.contains(&PeerId::random()));
+    }
+
+    #[test]
+    fn peer_filter_has_no_false_negatives() {
+        let mut filter = PeerFilter::new(1000, 0.01);
+        let peers = (0..1000).map(|_| PeerId::random()).collect::<Vec<_>>();
+        for peer in &peers {
+            filter.insert(peer);
+        }
+
+        assert!(peers.iter().all(|p| filter.contains(p)));
+
+        let false_positives = (0..1000)
+            .filter(|_| filter.contains(&PeerId::random()))
+            .count();
+        assert!(false_positives < 50, "{false_positives} false positives");
+
+        filter.clear();
+        assert!(filter.is_empty());
+        assert!(!filter.contains(&peers[0]));
+    }
+}
@@ -4,6 +4,8 @@ mod phase;
 mod select_muxer;
 mod select_security;
 
+pub use phase::SwarmPreset;
+
 /// Build a [`Swarm`](libp2p_swarm::Swarm) by combining an identity, a set of
 /// [`Transport`](libp2p_core::Transport)s and a
 /// [`NetworkBehaviour`](libp2p_swarm::NetworkBehaviour).
@@ -64,6 +66,7 @@ mod select_security;
 /// ```
 pub struct SwarmBuilder<Provider, Phase> {
     keypair: libp2p_identity::Keypair,
+    preset: Option<SwarmPreset>,
     phantom: PhantomData<Provider>,
     phase: Phase,
 }
@@ -71,6 +74,13 @@ pub struct SwarmBuilder<Provider, Phase> {
 #[cfg(test)]
 mod tests {
     use crate::SwarmBuilder;
+    #[cfg(all(
+        feature = "tokio",
+        feature = "tcp",
+        feature = "noise",
+        feature = "yamux",
+    ))]
+    use libp2p_core::transport::upgrade::PipeliningStatistics;
     use libp2p_core::{muxing::StreamMuxerBox, transport::dummy::DummyTransport};
     use libp2p_identity::PeerId;
     use libp2p_swarm::NetworkBehaviour;
@@ -97,6 +107,134 @@ mod tests {
             .build();
     }
 
+    #[tokio::test]
+    #[cfg(all(
+        feature = "tokio",
+        feature = "tcp",
+        feature = "noise",
+        feature = "yamux",
+    ))]
+    async fn tcp_with_dial_pipelining() {
+        let dialer_statistics = PipeliningStatistics::default();
+        let listener_statistics = PipeliningStatistics::default();
+
+        connect_tcp(
+            tcp_swarm(Some(dialer_statistics.clone())),
+            tcp_swarm(Some(listener_statistics.clone())),
+        )
+        .await;
+
+        assert_eq!(dialer_statistics.round_trips_saved(), 1);
+        assert_eq!(listener_statistics.round_trips_saved(), 1);
+    }
+
+    #[tokio::test]
+    #[cfg(all(
+        feature = "tokio",
+        feature = "tcp",
+        feature = "noise",
+        feature = "yamux",
+    ))]
+    async fn tcp_with_dial_pipelining_falls_back_to_negotiation() {
+        let dialer_statistics = PipeliningStatistics::default();
+        let listener_statistics = PipeliningStatistics::default();
+
+        connect_tcp(tcp_swarm(Some(dialer_statistics.clone())), tcp_swarm(None)).await;
+        connect_tcp(
+            tcp_swarm(None),
+            tcp_swarm(Some(listener_statistics.clone())),
+        )
+        .await;
+
+        assert_eq!(dialer_statistics.round_trips_saved(), 0);
+        assert_eq!(listener_statistics.round_trips_saved(), 0);
+    }
+
+    #[cfg(all(
+        feature = "tokio",
+        feature = "tcp",
+        feature = "noise",
+        feature = "yamux",
+    ))]
+    fn tcp_swarm(
+        pipelining: Option<PipeliningStatistics>,
+    ) -> libp2p_swarm::Swarm<libp2p_swarm::dummy::Behaviour> {
+        let builder = SwarmBuilder::with_new_identity().with_tokio();
+
+        match pipelining {
+            Some(statistics) => builder
+                .with_dial_pipelining(statistics)
+                .with_tcp(
+                    Default::default(),
+                    libp2p_noise::Config::new,
+                    libp2p_yamux::Config::default,
+                )
+                .unwrap()
+                .with_behaviour(|_| libp2p_swarm::dummy::Behaviour)
+                .unwrap()
+                .with_swarm_config(|cfg| {
+                    cfg.with_idle_connection_timeout(std::time::Duration::from_secs(10))
+                })
+                .build(),
+            None => builder
+                .with_tcp(
+                    Default::default(),
+                    libp2p_noise::Config::new,
+                    libp2p_yamux::Config::default,
+                )
+                .unwrap()
+                .with_behaviour(|_| libp2p_swarm::dummy::Behaviour)
+                .unwrap()
+                .with_swarm_config(|cfg| {
+                    cfg.with_idle_connection_timeout(std::time::Duration::from_secs(10))
+                })
+                .build(),
+        }
+    }
+
+    /// Dials `listener` from `dialer` over TCP and waits until both established the connection.
+    #[cfg(all(
+        feature = "tokio",
+        feature = "tcp",
+        feature = "noise",
+        feature = "yamux",
+    ))]
+    async fn connect_tcp(
+        mut dialer: libp2p_swarm::Swarm<libp2p_swarm::dummy::Behaviour>,
+        mut listener: libp2p_swarm::Swarm<libp2p_swarm::dummy::Behaviour>,
+    ) {
+        use futures::StreamExt;
+        use libp2p_swarm::SwarmEvent;
+
+        listener
+            .listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+            .unwrap();
+        let address = loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = listener.select_next_some().await {
+                break address;
+            }
+        };
+        dialer.dial(address).unwrap();
+
+        let mut dialer_established = false;
+        let mut listener_established = false;
+        while !(dialer_established && listener_established) {
+            let event = tokio::select! {
+                event = dialer.select_next_some() => event,
+                event = listener.select_next_some() => event,
+            };
+            match event {
+                SwarmEvent::ConnectionEstablished { endpoint, .. } if endpoint.is_dialer() => {
+                    dialer_established = true
+                }
+                SwarmEvent::ConnectionEstablished { .. } => listener_established = true,
+                SwarmEvent::OutgoingConnectionError { error, .. } => panic!("{error}"),
+                SwarmEvent::IncomingConnectionError { error, .. } => panic!("{error}"),
+                _ => {}
+            }
+        }
+    }
+
     #[test]
     #[cfg(all(
         feature = "async-std",
@@ -598,4 +736,27 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    #[cfg(all(
+        feature = "tokio",
+        feature = "tcp",
+        feature = "tls",
+        feature = "noise",
+        feature = "yamux",
+        feature = "quic",
+        feature = "dns"
+    ))]
+    async fn server_preset() {
+        let _ = SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .server()
+            .unwrap()
+            .with_behaviour(|_| libp2p_swarm::dummy::Behaviour)
+            .unwrap()
+            .with_swarm_config(|cfg| {
+                cfg.with_idle_connection_timeout(std::time::Duration::from_secs(10))
+            })
+            .build();
+    }
 }
@@ -7,6 +7,7 @@ mod build;
 mod dns;
 mod identity;
 mod other_transport;
+mod preset;
 mod provider;
 mod quic;
 mod relay;
@@ -20,6 +21,7 @@ use behaviour::*;
 use build::*;
 use dns::*;
 use other_transport::*;
+pub use preset::SwarmPreset;
 use provider::*;
 use quic::*;
 use relay::*;
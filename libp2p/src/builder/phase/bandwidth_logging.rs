@@ -30,6 +30,7 @@ impl<T: AuthenticatedMultiplexedTransport, Provider, R>
                     transport,
                 },
                 keypair: self.keypair,
+                preset: self.preset,
                 phantom: PhantomData,
             },
             sinks,
@@ -43,6 +44,7 @@ impl<T: AuthenticatedMultiplexedTransport, Provider, R>
                 transport: self.phase.transport,
             },
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
         }
     }
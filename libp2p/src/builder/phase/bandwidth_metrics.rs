@@ -26,6 +26,7 @@ impl<T: AuthenticatedMultiplexedTransport, Provider, R>
                     .map(|(peer_id, conn), _| (peer_id, StreamMuxerBox::new(conn))),
             },
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
         }
     }
@@ -39,6 +40,7 @@ impl<T, Provider, R> SwarmBuilder<Provider, BandwidthMetricsPhase<T, R>> {
                 transport: self.phase.transport,
             },
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
         }
     }
@@ -22,6 +22,7 @@ impl<T, Provider> SwarmBuilder<Provider, BehaviourPhase<T, libp2p_relay::client:
                 transport: self.phase.transport,
             },
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
         })
     }
@@ -41,6 +42,7 @@ impl<T, Provider> SwarmBuilder<Provider, BehaviourPhase<T, NoRelayBehaviour>> {
                 transport: self.phase.transport,
             },
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
         })
     }
@@ -19,6 +19,7 @@ impl<T: AuthenticatedMultiplexedTransport> SwarmBuilder<super::provider::AsyncSt
     > {
         Ok(SwarmBuilder {
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
             phase: WebsocketPhase {
                 transport: libp2p_dns::async_std::Transport::system2(self.phase.transport)?,
@@ -40,6 +41,7 @@ impl<T: AuthenticatedMultiplexedTransport> SwarmBuilder<super::provider::Tokio,
     > {
         Ok(SwarmBuilder {
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
             phase: WebsocketPhase {
                 transport: libp2p_dns::tokio::Transport::system(self.phase.transport)?,
@@ -60,6 +62,7 @@ impl<T: AuthenticatedMultiplexedTransport> SwarmBuilder<super::provider::AsyncSt
     > {
         SwarmBuilder {
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
             phase: WebsocketPhase {
                 transport: libp2p_dns::async_std::Transport::custom2(
@@ -82,6 +85,7 @@ impl<T: AuthenticatedMultiplexedTransport> SwarmBuilder<super::provider::Tokio,
     {
         SwarmBuilder {
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
             phase: WebsocketPhase {
                 transport: libp2p_dns::tokio::Transport::custom(self.phase.transport, cfg, opts),
@@ -94,6 +98,7 @@ impl<Provider, T> SwarmBuilder<Provider, DnsPhase<T>> {
     pub(crate) fn without_dns(self) -> SwarmBuilder<Provider, WebsocketPhase<T>> {
         SwarmBuilder {
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
             phase: WebsocketPhase {
                 transport: self.phase.transport,
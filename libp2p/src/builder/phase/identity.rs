@@ -14,6 +14,7 @@ impl SwarmBuilder<NoProviderSpecified, IdentityPhase> {
     ) -> SwarmBuilder<NoProviderSpecified, ProviderPhase> {
         SwarmBuilder {
             keypair,
+            preset: None,
             phantom: PhantomData,
             phase: ProviderPhase {},
         }
@@ -53,6 +53,7 @@ impl<Provider, T: AuthenticatedMultiplexedTransport>
                     .map(|either, _| either.into_inner()),
             },
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
         })
     }
@@ -60,6 +61,7 @@ impl<Provider, T: AuthenticatedMultiplexedTransport>
     pub(crate) fn without_any_other_transports(self) -> SwarmBuilder<Provider, DnsPhase<T>> {
         SwarmBuilder {
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
             phase: DnsPhase {
                 transport: self.phase.transport,
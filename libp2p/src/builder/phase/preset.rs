@@ -0,0 +1,253 @@
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    feature = "tcp",
+    feature = "quic",
+    feature = "dns",
+    feature = "tls",
+    feature = "noise",
+    feature = "yamux",
+))]
+use super::WebsocketPhase;
+#[cfg(any(
+    all(
+        not(target_arch = "wasm32"),
+        feature = "tcp",
+        feature = "quic",
+        feature = "dns",
+        feature = "tls",
+        feature = "noise",
+        feature = "yamux",
+    ),
+    all(
+        feature = "wasm-bindgen",
+        feature = "websocket-websys",
+        feature = "noise",
+        feature = "yamux",
+    ),
+))]
+use super::{AuthenticatedMultiplexedTransport, TcpPhase};
+#[cfg(all(
+    feature = "wasm-bindgen",
+    feature = "websocket-websys",
+    feature = "noise",
+    feature = "yamux",
+))]
+use super::{OtherTransportPhase, TransportError};
+use crate::SwarmBuilder;
+#[cfg(all(
+    feature = "wasm-bindgen",
+    feature = "websocket-websys",
+    feature = "noise",
+    feature = "yamux",
+))]
+use libp2p_core::Transport;
+use std::time::Duration;
+
+/// An opinionated configuration of the transports, muxers, timeouts and limits for a common
+/// environment, applied by [`SwarmBuilder::desktop`], [`SwarmBuilder::server`] and
+/// [`SwarmBuilder::browser`].
+///
+/// The preset only provides defaults: every setting can be overridden afterwards, e.g. via
+/// [`SwarmBuilder::with_swarm_config`], whose constructor is passed the
+/// [`Config`](libp2p_swarm::Config) of the preset. When composing the transports by hand, the
+/// configurations of the preset can be used individually.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SwarmPreset {
+    /// A node on an end-user machine, typically behind a NAT and dialing more than it is dialed.
+    Desktop,
+    /// A publicly reachable node, e.g. a bootstrap node or relay, accepting many connections.
+    Server,
+    /// A node running in a browser, dialing out via WebSocket.
+    Browser,
+}
+
+impl SwarmPreset {
+    /// Applies the timeouts and limits of the preset to the given [`Config`](libp2p_swarm::Config).
+    pub fn swarm_config(self, config: libp2p_swarm::Config) -> libp2p_swarm::Config {
+        let ranker = libp2p_swarm::dial_ranking::PreferenceRanker::new();
+        match self {
+            SwarmPreset::Desktop => config
+                .with_idle_connection_timeout(Duration::from_secs(30))
+                .with_dial_ranker(ranker),
+            SwarmPreset::Server => config
+                .with_idle_connection_timeout(Duration::from_secs(60))
+                .with_max_negotiating_inbound_streams(512)
+                .with_dial_ranker(ranker),
+            SwarmPreset::Browser => config
+                .with_idle_connection_timeout(Duration::from_secs(30))
+                .with_max_concurrent_dials(std::num::NonZeroUsize::new(16).expect("16 > 0"))
+                .with_dial_ranker(ranker.with_prefer_quic(false)),
+        }
+    }
+
+    /// The TCP configuration of the preset.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "tcp"))]
+    pub fn tcp_config(self) -> libp2p_tcp::Config {
+        let config = libp2p_tcp::Config::new().nodelay(true);
+        match self {
+            SwarmPreset::Server => config.listen_backlog(4096),
+            SwarmPreset::Desktop | SwarmPreset::Browser => config,
+        }
+    }
+
+    /// Applies the limits of the preset to the given QUIC configuration.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "quic"))]
+    pub fn quic_config(self, mut config: libp2p_quic::Config) -> libp2p_quic::Config {
+        if self == SwarmPreset::Server {
+            config.max_concurrent_stream_limit = 512;
+            config.max_incoming_connection_attempts = 4096;
+            config.require_address_validation = true;
+        }
+        config
+    }
+
+    /// The yamux configuration of the preset.
+    #[cfg(feature = "yamux")]
+    pub fn yamux_config(self) -> libp2p_yamux::Config {
+        let mut config = libp2p_yamux::Config::default();
+        if self == SwarmPreset::Server {
+            config.set_max_num_streams(512);
+        }
+        config
+    }
+}
+
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    feature = "tcp",
+    feature = "quic",
+    feature = "dns",
+    feature = "tls",
+    feature = "noise",
+    feature = "yamux",
+))]
+macro_rules! impl_preset_builder {
+    ($providerKebabCase:literal, $providerPascalCase:ty) => {
+        #[cfg(feature = $providerKebabCase)]
+        impl SwarmBuilder<$providerPascalCase, TcpPhase> {
+            /// Adds TCP and QUIC transports with DNS resolution configured for a node on an
+            /// end-user machine, see [`SwarmPreset::Desktop`].
+            ///
+            /// TCP connections are secured with TLS or Noise and multiplexed with yamux.
+            ///
+            /// ``` rust
+            /// # use libp2p::SwarmBuilder;
+            /// # use std::error::Error;
+            /// # async fn build_swarm() -> Result<(), Box<dyn Error + Send + Sync>> {
+            /// let swarm = SwarmBuilder::with_new_identity()
+            ///     .with_tokio()
+            ///     .desktop()?
+            ///     .with_behaviour(|_| libp2p_swarm::dummy::Behaviour)?
+            ///     .build();
+            /// # Ok(())
+            /// # }
+            /// ```
+            pub fn desktop(
+                self,
+            ) -> Result<
+                SwarmBuilder<
+                    $providerPascalCase,
+                    WebsocketPhase<impl AuthenticatedMultiplexedTransport>,
+                >,
+                Box<dyn std::error::Error + Send + Sync>,
+            > {
+                self.with_preset(SwarmPreset::Desktop)
+            }
+
+            /// Adds TCP and QUIC transports with DNS resolution configured for a publicly
+            /// reachable node, see [`SwarmPreset::Server`].
+            ///
+            /// Compared to [`SwarmBuilder::desktop`], connections are kept longer and more
+            /// concurrent streams and pending connections are accepted. QUIC validates the
+            /// addresses of remotes before accepting their connections.
+            pub fn server(
+                self,
+            ) -> Result<
+                SwarmBuilder<
+                    $providerPascalCase,
+                    WebsocketPhase<impl AuthenticatedMultiplexedTransport>,
+                >,
+                Box<dyn std::error::Error + Send + Sync>,
+            > {
+                self.with_preset(SwarmPreset::Server)
+            }
+
+            fn with_preset(
+                mut self,
+                preset: SwarmPreset,
+            ) -> Result<
+                SwarmBuilder<
+                    $providerPascalCase,
+                    WebsocketPhase<impl AuthenticatedMultiplexedTransport>,
+                >,
+                Box<dyn std::error::Error + Send + Sync>,
+            > {
+                self.preset = Some(preset);
+
+                Ok(self
+                    .with_tcp(
+                        preset.tcp_config(),
+                        (libp2p_tls::Config::new, libp2p_noise::Config::new),
+                        move || preset.yamux_config(),
+                    )?
+                    .with_quic_config(|config| preset.quic_config(config))
+                    .with_dns()?)
+            }
+        }
+    };
+}
+
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    feature = "tcp",
+    feature = "quic",
+    feature = "dns",
+    feature = "tls",
+    feature = "noise",
+    feature = "yamux",
+))]
+impl_preset_builder!("async-std", super::provider::AsyncStd);
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    feature = "tcp",
+    feature = "quic",
+    feature = "dns",
+    feature = "tls",
+    feature = "noise",
+    feature = "yamux",
+))]
+impl_preset_builder!("tokio", super::provider::Tokio);
+
+#[cfg(all(
+    feature = "wasm-bindgen",
+    feature = "websocket-websys",
+    feature = "noise",
+    feature = "yamux",
+))]
+impl SwarmBuilder<super::provider::WasmBindgen, TcpPhase> {
+    /// Adds a WebSocket transport configured for a node running in a browser, see
+    /// [`SwarmPreset::Browser`].
+    ///
+    /// Connections are secured with Noise and multiplexed with yamux.
+    pub fn browser(
+        mut self,
+    ) -> Result<
+        SwarmBuilder<
+            super::provider::WasmBindgen,
+            OtherTransportPhase<impl AuthenticatedMultiplexedTransport>,
+        >,
+        TransportError,
+    > {
+        self.preset = Some(SwarmPreset::Browser);
+
+        self.with_other_transport(
+            |key| -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
+                Ok(libp2p_websocket_websys::Transport::default()
+                    .upgrade(libp2p_core::upgrade::Version::V1Lazy)
+                    .authenticate(libp2p_noise::Config::new(key)?)
+                    .multiplex(SwarmPreset::Browser.yamux_config()))
+            },
+        )
+    }
+}
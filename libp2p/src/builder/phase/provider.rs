@@ -31,6 +31,7 @@ impl SwarmBuilder<NoProviderSpecified, ProviderPhase> {
     pub fn with_async_std(self) -> SwarmBuilder<AsyncStd, TcpPhase> {
         SwarmBuilder {
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
             phase: TcpPhase {},
         }
@@ -42,6 +43,7 @@ impl SwarmBuilder<NoProviderSpecified, ProviderPhase> {
     pub fn with_tokio(self) -> SwarmBuilder<Tokio, TcpPhase> {
         SwarmBuilder {
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
             phase: TcpPhase {},
         }
@@ -53,6 +55,7 @@ impl SwarmBuilder<NoProviderSpecified, ProviderPhase> {
     pub fn with_wasm_bindgen(self) -> SwarmBuilder<WasmBindgen, TcpPhase> {
         SwarmBuilder {
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
             phase: TcpPhase {},
         }
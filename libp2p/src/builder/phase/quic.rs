@@ -50,6 +50,7 @@ macro_rules! impl_quic_builder {
                             .map(|either, _| either.into_inner()),
                     },
                     keypair: self.keypair,
+                    preset: self.preset,
                     phantom: PhantomData,
                 }
             }
@@ -64,6 +65,7 @@ impl<Provider, T> SwarmBuilder<Provider, QuicPhase<T>> {
     pub(crate) fn without_quic(self) -> SwarmBuilder<Provider, OtherTransportPhase<T>> {
         SwarmBuilder {
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
             phase: OtherTransportPhase {
                 transport: self.phase.transport,
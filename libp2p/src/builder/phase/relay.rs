@@ -93,6 +93,7 @@ impl<Provider, T: AuthenticatedMultiplexedTransport> SwarmBuilder<Provider, Rela
                     .map(|either, _| either.into_inner()),
             },
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
         })
     }
@@ -106,6 +107,7 @@ impl<Provider, T> SwarmBuilder<Provider, RelayPhase<T>> {
     ) -> SwarmBuilder<Provider, BandwidthLoggingPhase<T, NoRelayBehaviour>> {
         SwarmBuilder {
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
             phase: BandwidthLoggingPhase {
                 transport: self.phase.transport,
@@ -15,13 +15,20 @@ macro_rules! impl_with_swarm_config {
                 self,
                 constructor: impl FnOnce(libp2p_swarm::Config) -> libp2p_swarm::Config,
             ) -> SwarmBuilder<$providerPascalCase, BuildPhase<T, B>> {
+                let config = $config;
+                let config = match self.preset {
+                    Some(preset) => preset.swarm_config(config),
+                    None => config,
+                };
+
                 SwarmBuilder {
                     phase: BuildPhase {
                         behaviour: self.phase.behaviour,
                         transport: self.phase.transport,
-                        swarm_config: constructor($config),
+                        swarm_config: constructor(config),
                     },
                     keypair: self.keypair,
+                    preset: self.preset,
                     phantom: std::marker::PhantomData,
                 }
             }
@@ -5,6 +5,8 @@ use crate::SwarmBuilder;
     any(feature = "tcp", feature = "websocket")
 ))]
 use libp2p_core::muxing::{StreamMuxer, StreamMuxerBox};
+#[cfg(all(not(target_arch = "wasm32"), feature = "tcp", feature = "noise"))]
+use libp2p_core::transport::upgrade::PipeliningStatistics;
 #[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
 use libp2p_core::Transport;
 #[cfg(all(
@@ -18,6 +20,35 @@ use std::marker::PhantomData;
 
 pub struct TcpPhase {}
 
+/// [`TcpPhase`] selecting the stream multiplexer during the Noise handshake, see
+/// [`SwarmBuilder::with_dial_pipelining`].
+#[cfg(all(not(target_arch = "wasm32"), feature = "tcp", feature = "noise"))]
+pub struct PipelinedTcpPhase {
+    statistics: PipeliningStatistics,
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "tcp", feature = "noise"))]
+impl<Provider> SwarmBuilder<Provider, TcpPhase> {
+    /// Pipelines the selection of the stream multiplexer with the Noise handshake of TCP
+    /// connections, saving the round-trip of negotiating it via multistream-select afterwards.
+    ///
+    /// The multiplexers offered to the remote are the ones of the multiplexer upgrade passed to
+    /// [`SwarmBuilder::with_tcp`]. If the remote does not select the multiplexer during the
+    /// handshake, it is negotiated as usual. The saved round-trips are counted in `statistics`,
+    /// which can be registered via `libp2p_metrics::register_pipelining_statistics`.
+    pub fn with_dial_pipelining(
+        self,
+        statistics: PipeliningStatistics,
+    ) -> SwarmBuilder<Provider, PipelinedTcpPhase> {
+        SwarmBuilder {
+            keypair: self.keypair,
+            preset: self.preset,
+            phantom: PhantomData,
+            phase: PipelinedTcpPhase { statistics },
+        }
+    }
+}
+
 macro_rules! impl_tcp_builder {
     ($providerKebabCase:literal, $providerPascalCase:ty, $path:ident) => {
         #[cfg(all(
@@ -88,6 +119,79 @@ macro_rules! impl_tcp_builder {
                             .map(|(p, c), _| (p, StreamMuxerBox::new(c))),
                     },
                     keypair: self.keypair,
+                    preset: self.preset,
+                    phantom: PhantomData,
+                })
+            }
+        }
+
+        #[cfg(all(
+            not(target_arch = "wasm32"),
+            feature = "tcp",
+            feature = "noise",
+            feature = $providerKebabCase,
+        ))]
+        impl SwarmBuilder<$providerPascalCase, PipelinedTcpPhase> {
+            /// Adds a TCP based transport secured by Noise, which selects the stream multiplexer
+            /// during its handshake.
+            ///
+            /// ``` rust
+            /// # use libp2p::SwarmBuilder;
+            /// # use libp2p_core::transport::upgrade::PipeliningStatistics;
+            /// # use std::error::Error;
+            /// # async fn build_swarm() -> Result<(), Box<dyn Error>> {
+            /// let swarm = SwarmBuilder::with_new_identity()
+            ///     .with_tokio()
+            ///     .with_dial_pipelining(PipeliningStatistics::default())
+            ///     .with_tcp(
+            ///         Default::default(),
+            ///         libp2p_noise::Config::new,
+            ///         libp2p_yamux::Config::default,
+            ///     )?
+            /// # ;
+            /// # Ok(())
+            /// # }
+            /// ```
+            pub fn with_tcp<MuxUpgrade, MuxStream, MuxError>(
+                self,
+                tcp_config: libp2p_tcp::Config,
+                security_upgrade: impl FnOnce(&libp2p_identity::Keypair) -> Result<libp2p_noise::Config, libp2p_noise::Error>,
+                multiplexer_upgrade: MuxUpgrade,
+            ) -> Result<
+                SwarmBuilder<$providerPascalCase, QuicPhase<impl AuthenticatedMultiplexedTransport>>,
+                libp2p_noise::Error,
+            >
+            where
+                MuxStream: StreamMuxer + Send + 'static,
+                MuxStream::Substream: Send + 'static,
+                MuxStream::Error: Send + Sync + 'static,
+                MuxUpgrade: IntoMultiplexerUpgrade<libp2p_noise::Output<Negotiated<libp2p_tcp::$path::TcpStream>>>,
+                MuxUpgrade::Upgrade: InboundConnectionUpgrade<Negotiated<libp2p_noise::Output<Negotiated<libp2p_tcp::$path::TcpStream>>>, Output = MuxStream, Error = MuxError> + OutboundConnectionUpgrade<Negotiated<libp2p_noise::Output<Negotiated<libp2p_tcp::$path::TcpStream>>>, Output = MuxStream, Error = MuxError> + Clone + Send + 'static,
+                <MuxUpgrade::Upgrade as InboundConnectionUpgrade<Negotiated<libp2p_noise::Output<Negotiated<libp2p_tcp::$path::TcpStream>>>>>::Future: Send,
+                <MuxUpgrade::Upgrade as OutboundConnectionUpgrade<Negotiated<libp2p_noise::Output<Negotiated<libp2p_tcp::$path::TcpStream>>>>>::Future: Send,
+                MuxError: std::error::Error + Send + Sync + 'static,
+                <<<MuxUpgrade as IntoMultiplexerUpgrade<libp2p_noise::Output<Negotiated<libp2p_tcp::$path::TcpStream>>>>::Upgrade as UpgradeInfo>::InfoIter as IntoIterator>::IntoIter: Send,
+                <<MuxUpgrade as IntoMultiplexerUpgrade<libp2p_noise::Output<Negotiated<libp2p_tcp::$path::TcpStream>>>>::Upgrade as UpgradeInfo>::Info: Send,
+            {
+                let multiplexer_upgrade = multiplexer_upgrade.into_multiplexer_upgrade();
+                let stream_muxers = multiplexer_upgrade
+                    .protocol_info()
+                    .into_iter()
+                    .map(|info| info.as_ref().to_owned())
+                    .collect();
+
+                Ok(SwarmBuilder {
+                    phase: QuicPhase {
+                        transport: libp2p_tcp::$path::Transport::new(tcp_config)
+                            .upgrade(libp2p_core::upgrade::Version::V1Lazy)
+                            .authenticate(
+                                security_upgrade(&self.keypair)?.with_stream_muxers(stream_muxers),
+                            )
+                            .multiplex_pipelined(multiplexer_upgrade, self.phase.statistics)
+                            .map(|(p, c), _| (p, StreamMuxerBox::new(c))),
+                    },
+                    keypair: self.keypair,
+                    preset: self.preset,
                     phantom: PhantomData,
                 })
             }
@@ -104,6 +208,7 @@ impl<Provider> SwarmBuilder<Provider, TcpPhase> {
     ) -> SwarmBuilder<Provider, QuicPhase<impl AuthenticatedMultiplexedTransport>> {
         SwarmBuilder {
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
             phase: QuicPhase {
                 transport: libp2p_core::transport::dummy::DummyTransport::new(),
@@ -101,6 +101,7 @@ macro_rules! impl_websocket_builder {
 
                 Ok(SwarmBuilder {
                     keypair: self.keypair,
+                    preset: self.preset,
                     phantom: PhantomData,
                     phase: RelayPhase {
                         transport: websocket_transport
@@ -138,6 +139,7 @@ impl<Provider, T: AuthenticatedMultiplexedTransport> SwarmBuilder<Provider, Webs
     pub(crate) fn without_websocket(self) -> SwarmBuilder<Provider, RelayPhase<T>> {
         SwarmBuilder {
             keypair: self.keypair,
+            preset: self.preset,
             phantom: PhantomData,
             phase: RelayPhase {
                 transport: self.phase.transport,
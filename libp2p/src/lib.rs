@@ -84,6 +84,9 @@ pub use libp2p_metrics as metrics;
 #[cfg(feature = "noise")]
 #[doc(inline)]
 pub use libp2p_noise as noise;
+#[cfg(feature = "peer-store")]
+#[doc(inline)]
+pub use libp2p_peer_store as peer_store;
 #[cfg(feature = "ping")]
 #[doc(inline)]
 pub use libp2p_ping as ping;
@@ -146,10 +149,18 @@ mod transport_ext;
 
 pub mod bandwidth;
 
+#[cfg(all(
+    feature = "autonat",
+    feature = "relay",
+    feature = "dcutr",
+    feature = "macros"
+))]
+pub mod nat;
+
 #[cfg(doc)]
 pub mod tutorials;
 
-pub use self::builder::SwarmBuilder;
+pub use self::builder::{SwarmBuilder, SwarmPreset};
 pub use self::core::{
     transport::TransportError,
     upgrade::{InboundUpgrade, OutboundUpgrade},
@@ -0,0 +1,649 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! NAT traversal combining AutoNAT v2, the relay client and DCUtR in a single
+//! [`NetworkBehaviour`].
+//!
+//! The [`Behaviour`] coordinates the three protocols, which otherwise have to be wired up by the
+//! application:
+//!
+//! 1. The reachability of the local node is tested via [AutoNAT v2](crate::autonat::v2). Once
+//!    the configured number of probes failed without any address being confirmed, the node is
+//!    considered private.
+//! 2. While private, reservations are made on the configured relays, such that the node can be
+//!    reached through them. Reservations are released once the node becomes publicly reachable.
+//! 3. Relayed connections are upgraded to direct connections via [DCUtR](crate::dcutr), either
+//!    when a remote connects through one of our relays or on demand via
+//!    [`Behaviour::hole_punch`].
+//!
+//! The outcome is reported as a single [`Connectivity`] state. The relay client [`Transport`]
+//! has to be part of the transport stack, e.g. via `SwarmBuilder::with_relay_client`.
+//!
+//! [`Transport`]: crate::relay::client::Transport
+
+use crate::{autonat, dcutr, relay};
+use futures_timer::Delay;
+use libp2p_core::{multiaddr::Protocol, transport::PortUse, Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_swarm::{
+    behaviour::{ConnectionEstablished, DialFailure, ListenerClosed, ListenerError},
+    dial_opts::{DialOpts, DialPriority, PeerCondition},
+    ConnectionDenied, ConnectionHandlerSelect, ConnectionId, FromSwarm, ListenOpts, ListenerId,
+    NetworkBehaviour, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// The configuration of the NAT traversal [`Behaviour`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    relays: Vec<Multiaddr>,
+    max_reservations: usize,
+    confidence: usize,
+    relay_retry_interval: Duration,
+}
+
+impl Config {
+    /// Creates a new [`Config`] without any relays, making up to 2 reservations once 3 probes
+    /// failed.
+    pub fn new() -> Self {
+        Self {
+            relays: Vec::new(),
+            max_reservations: 2,
+            confidence: 3,
+            relay_retry_interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Adds a relay to make reservations on while the node is private.
+    ///
+    /// The address has to end with the `/p2p` component of the relay. Relays are used in the
+    /// order they are added.
+    pub fn with_relay(mut self, address: Multiaddr) -> Self {
+        self.relays.push(address);
+        self
+    }
+
+    /// Sets the number of relays to hold a reservation on at the same time.
+    pub fn with_max_reservations(mut self, max: usize) -> Self {
+        self.max_reservations = max;
+        self
+    }
+
+    /// Sets the number of consecutive failed AutoNAT probes after which the node is considered
+    /// private, unless an address was confirmed.
+    pub fn with_confidence(mut self, confidence: usize) -> Self {
+        self.confidence = confidence.max(1);
+        self
+    }
+
+    /// Sets after how long relays on which a reservation failed are used again.
+    pub fn with_relay_retry_interval(mut self, interval: Duration) -> Self {
+        self.relay_retry_interval = interval;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The connectivity of the local node, as determined by the NAT traversal [`Behaviour`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Not enough probes completed yet.
+    Unknown,
+    /// At least one address was confirmed to be publicly reachable.
+    Public,
+    /// The node is private and reachable through at least one relay.
+    Relayed,
+    /// The node is private and holds no reservation on a relay.
+    Private,
+}
+
+/// Events emitted by the NAT traversal [`Behaviour`].
+#[derive(Debug)]
+pub enum Event {
+    /// The [`Connectivity`] of the local node changed.
+    ConnectivityChanged {
+        old: Connectivity,
+        new: Connectivity,
+    },
+    /// A reservation was accepted by a relay and the node is now reachable via `address`.
+    ReservationAccepted {
+        relay_peer_id: PeerId,
+        address: Multiaddr,
+    },
+    /// Making or holding a reservation on the relay failed. The relay is retried after the
+    /// [relay retry interval](Config::with_relay_retry_interval).
+    ReservationFailed { relay_address: Multiaddr },
+    /// A hole punch to the remote completed, either requested via [`Behaviour::hole_punch`] or
+    /// initiated by DCUtR for a relayed connection.
+    HolePunch {
+        remote_peer_id: PeerId,
+        result: Result<ConnectionId, HolePunchError>,
+    },
+}
+
+/// Why a hole punch failed, see [`Event::HolePunch`].
+#[derive(Debug, thiserror::Error)]
+pub enum HolePunchError {
+    /// None of the given addresses of the remote is a relayed address.
+    #[error("no relayed address of the remote is known")]
+    NoRelayedAddress,
+    /// The remote could not be reached through any of its relays.
+    #[error("failed to connect to the remote through a relay")]
+    RelayedDialFailed,
+    /// The direct connection upgrade failed.
+    #[error(transparent)]
+    Dcutr(#[from] dcutr::Error),
+}
+
+#[derive(NetworkBehaviour)]
+#[behaviour(prelude = "libp2p_swarm::derive_prelude", to_swarm = "InnerEvent")]
+struct Inner {
+    autonat: autonat::v2::client::Behaviour,
+    relay: relay::client::Behaviour,
+    dcutr: dcutr::Behaviour,
+}
+
+#[derive(Debug)]
+enum InnerEvent {
+    Autonat(autonat::v2::client::Event),
+    Relay(relay::client::Event),
+    Dcutr(dcutr::Event),
+}
+
+impl From<autonat::v2::client::Event> for InnerEvent {
+    fn from(event: autonat::v2::client::Event) -> Self {
+        InnerEvent::Autonat(event)
+    }
+}
+
+impl From<relay::client::Event> for InnerEvent {
+    fn from(event: relay::client::Event) -> Self {
+        InnerEvent::Relay(event)
+    }
+}
+
+impl From<dcutr::Event> for InnerEvent {
+    fn from(event: dcutr::Event) -> Self {
+        InnerEvent::Dcutr(event)
+    }
+}
+
+/// A reservation on a relay, backed by a listener of the relay client transport.
+#[derive(Debug)]
+struct Reservation {
+    relay_address: Multiaddr,
+    /// Whether the listener was added, i.e. the reservation was requested from the relay.
+    listening: bool,
+    /// The relayed address of the local node, once the reservation was accepted.
+    address: Option<Multiaddr>,
+}
+
+/// Coordinates AutoNAT v2, the relay client and DCUtR, see the [module documentation](self).
+pub struct Behaviour {
+    inner: Inner,
+    config: Config,
+    connectivity: Connectivity,
+    /// Non-relayed addresses confirmed to be reachable.
+    reachable: HashSet<Multiaddr>,
+    /// Number of consecutive failed probes.
+    failed_probes: usize,
+    reservations: HashMap<ListenerId, Reservation>,
+    /// Relays on which a reservation failed since the last retry.
+    failed_relays: HashSet<Multiaddr>,
+    relay_retry: Option<Delay>,
+    /// Relayed dials issued by [`Behaviour::hole_punch`].
+    pending_hole_punches: HashMap<ConnectionId, PeerId>,
+    events: VecDeque<ToSwarm<Event, THandlerInEvent<Self>>>,
+}
+
+impl Behaviour {
+    /// Creates a new [`Behaviour`] from the relay client created alongside the relay client
+    /// transport.
+    pub fn new(
+        local_peer_id: PeerId,
+        relay_client: relay::client::Behaviour,
+        config: Config,
+    ) -> Self {
+        Self {
+            inner: Inner {
+                autonat: autonat::v2::client::Behaviour::default(),
+                relay: relay_client,
+                dcutr: dcutr::Behaviour::new(local_peer_id),
+            },
+            config,
+            connectivity: Connectivity::Unknown,
+            reachable: HashSet::new(),
+            failed_probes: 0,
+            reservations: HashMap::new(),
+            failed_relays: HashSet::new(),
+            relay_retry: None,
+            pending_hole_punches: HashMap::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// The current connectivity of the local node.
+    pub fn connectivity(&self) -> Connectivity {
+        self.connectivity
+    }
+
+    /// The relayed addresses of the local node, one per accepted reservation.
+    pub fn reservations(&self) -> impl Iterator<Item = &Multiaddr> {
+        self.reservations
+            .values()
+            .filter_map(|r| r.address.as_ref())
+    }
+
+    /// Adds a relay to make reservations on, see [`Config::with_relay`].
+    pub fn add_relay(&mut self, address: Multiaddr) {
+        if !self.config.relays.contains(&address) {
+            self.config.relays.push(address);
+            self.request_reservations();
+        }
+    }
+
+    /// Establishes a direct connection to `peer_id` by connecting through one of its relayed
+    /// `addresses` and upgrading the relayed connection via DCUtR.
+    ///
+    /// Addresses that are not relayed are ignored. The outcome is reported as
+    /// [`Event::HolePunch`].
+    pub fn hole_punch(&mut self, peer_id: PeerId, addresses: impl IntoIterator<Item = Multiaddr>) {
+        let addresses = addresses.into_iter().filter(is_relayed).collect::<Vec<_>>();
+        if addresses.is_empty() {
+            self.events
+                .push_back(ToSwarm::GenerateEvent(Event::HolePunch {
+                    remote_peer_id: peer_id,
+                    result: Err(HolePunchError::NoRelayedAddress),
+                }));
+            return;
+        }
+
+        let opts = DialOpts::peer_id(peer_id)
+            .addresses(addresses)
+            .condition(PeerCondition::Always)
+            .priority(DialPriority::High)
+            .build();
+        self.pending_hole_punches
+            .insert(opts.connection_id(), peer_id);
+        self.events.push_back(ToSwarm::Dial { opts });
+    }
+
+    fn on_autonat_event(&mut self, event: autonat::v2::client::Event) {
+        if is_relayed(&event.tested_addr) {
+            return;
+        }
+
+        match event.result {
+            Ok(()) => {
+                self.reachable.insert(event.tested_addr);
+                self.failed_probes = 0;
+            }
+            Err(error) => {
+                tracing::debug!(address=%event.tested_addr, %error, "Address is not reachable");
+                self.reachable.remove(&event.tested_addr);
+                self.failed_probes += 1;
+            }
+        }
+        self.update_connectivity();
+    }
+
+    fn update_connectivity(&mut self) {
+        let new = if !self.reachable.is_empty() {
+            Connectivity::Public
+        } else if self.failed_probes < self.config.confidence {
+            Connectivity::Unknown
+        } else if self.reservations().next().is_some() {
+            Connectivity::Relayed
+        } else {
+            Connectivity::Private
+        };
+
+        let old = self.connectivity;
+        if old != new {
+            self.connectivity = new;
+            self.events
+                .push_back(ToSwarm::GenerateEvent(Event::ConnectivityChanged {
+                    old,
+                    new,
+                }));
+            if new == Connectivity::Public {
+                self.release_reservations();
+            }
+        }
+
+        self.request_reservations();
+    }
+
+    fn is_private(&self) -> bool {
+        matches!(
+            self.connectivity,
+            Connectivity::Relayed | Connectivity::Private
+        )
+    }
+
+    fn request_reservations(&mut self) {
+        if !self.is_private() {
+            return;
+        }
+
+        while self.reservations.len() < self.config.max_reservations {
+            let Some(relay_address) = self
+                .config
+                .relays
+                .iter()
+                .find(|relay| {
+                    !self.failed_relays.contains(*relay)
+                        && !self
+                            .reservations
+                            .values()
+                            .any(|r| &r.relay_address == *relay)
+                })
+                .cloned()
+            else {
+                return;
+            };
+
+            let opts = ListenOpts::new(relay_address.clone().with(Protocol::P2pCircuit));
+            tracing::debug!(relay=%relay_address, "Requesting reservation");
+            self.reservations.insert(
+                opts.listener_id(),
+                Reservation {
+                    relay_address,
+                    listening: false,
+                    address: None,
+                },
+            );
+            self.events.push_back(ToSwarm::ListenOn { opts });
+        }
+    }
+
+    fn release_reservations(&mut self) {
+        for id in self.reservations.keys() {
+            self.events.push_back(ToSwarm::RemoveListener { id: *id });
+        }
+    }
+
+    fn on_reservation_failed(&mut self, id: ListenerId) {
+        let Some(reservation) = self.reservations.remove(&id) else {
+            return;
+        };
+
+        self.failed_relays.insert(reservation.relay_address.clone());
+        if self.relay_retry.is_none() {
+            self.relay_retry = Some(Delay::new(self.config.relay_retry_interval));
+        }
+        self.events
+            .push_back(ToSwarm::GenerateEvent(Event::ReservationFailed {
+                relay_address: reservation.relay_address,
+            }));
+        self.update_connectivity();
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = ConnectionHandlerSelect<
+        ConnectionHandlerSelect<
+            THandler<autonat::v2::client::Behaviour>,
+            THandler<relay::client::Behaviour>,
+        >,
+        THandler<dcutr::Behaviour>,
+    >;
+    type ToSwarm = Event;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.inner
+            .handle_pending_inbound_connection(connection_id, local_addr, remote_addr)
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        self.inner.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+        port_use: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner.handle_established_outbound_connection(
+            connection_id,
+            peer,
+            addr,
+            role_override,
+            port_use,
+        )
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        self.inner.on_swarm_event(event);
+
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished { connection_id, .. }) => {
+                // The relayed connection is established, DCUtR reports the outcome.
+                self.pending_hole_punches.remove(&connection_id);
+            }
+            FromSwarm::DialFailure(DialFailure { connection_id, .. }) => {
+                if let Some(peer_id) = self.pending_hole_punches.remove(&connection_id) {
+                    self.events
+                        .push_back(ToSwarm::GenerateEvent(Event::HolePunch {
+                            remote_peer_id: peer_id,
+                            result: Err(HolePunchError::RelayedDialFailed),
+                        }));
+                }
+            }
+            FromSwarm::NewListener(listener) => {
+                if let Some(reservation) = self.reservations.get_mut(&listener.listener_id) {
+                    reservation.listening = true;
+                }
+            }
+            FromSwarm::NewListenAddr(new) => {
+                let Some(reservation) = self.reservations.get_mut(&new.listener_id) else {
+                    return;
+                };
+                reservation.address = Some(new.addr.clone());
+                if let Some(relay_peer_id) = peer_id(&reservation.relay_address) {
+                    self.events
+                        .push_back(ToSwarm::GenerateEvent(Event::ReservationAccepted {
+                            relay_peer_id,
+                            address: new.addr.clone(),
+                        }));
+                }
+                self.update_connectivity();
+            }
+            FromSwarm::ExpiredListenAddr(expired) => {
+                if let Some(reservation) = self.reservations.get_mut(&expired.listener_id) {
+                    reservation.address = None;
+                    self.update_connectivity();
+                }
+            }
+            FromSwarm::ListenerError(ListenerError { listener_id, .. }) => {
+                // Listeners failing to start are only reported as an error.
+                if self
+                    .reservations
+                    .get(&listener_id)
+                    .map_or(false, |r| !r.listening)
+                {
+                    self.on_reservation_failed(listener_id);
+                }
+            }
+            FromSwarm::ListenerClosed(ListenerClosed {
+                listener_id,
+                reason,
+            }) => {
+                if reason.is_err() && self.is_private() {
+                    self.on_reservation_failed(listener_id);
+                } else if self.reservations.remove(&listener_id).is_some() {
+                    self.update_connectivity();
+                }
+            }
+            FromSwarm::ExternalAddrConfirmed(confirmed) => {
+                if !is_relayed(confirmed.addr) && self.reachable.insert(confirmed.addr.clone()) {
+                    self.update_connectivity();
+                }
+            }
+            FromSwarm::ExternalAddrExpired(expired) => {
+                if self.reachable.remove(expired.addr) {
+                    self.update_connectivity();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        self.inner
+            .on_connection_handler_event(peer_id, connection_id, event)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Poll::Ready(event);
+            }
+
+            if let Some(retry) = self.relay_retry.as_mut() {
+                if Pin::new(retry).poll(cx).is_ready() {
+                    self.relay_retry = None;
+                    self.failed_relays.clear();
+                    self.request_reservations();
+                    continue;
+                }
+            }
+
+            match self.inner.poll(cx) {
+                Poll::Ready(ToSwarm::GenerateEvent(InnerEvent::Autonat(event))) => {
+                    self.on_autonat_event(event);
+                }
+                Poll::Ready(ToSwarm::GenerateEvent(InnerEvent::Relay(event))) => {
+                    tracing::debug!(?event, "Relay client event");
+                }
+                Poll::Ready(ToSwarm::GenerateEvent(InnerEvent::Dcutr(event))) => {
+                    self.events
+                        .push_back(ToSwarm::GenerateEvent(Event::HolePunch {
+                            remote_peer_id: event.remote_peer_id,
+                            result: event.result.map_err(HolePunchError::from),
+                        }));
+                }
+                Poll::Ready(event) => {
+                    return Poll::Ready(event.map_out(|_| unreachable!("handled above")));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+fn is_relayed(address: &Multiaddr) -> bool {
+    address.iter().any(|p| p == Protocol::P2pCircuit)
+}
+
+fn peer_id(address: &Multiaddr) -> Option<PeerId> {
+    address.iter().find_map(|p| match p {
+        Protocol::P2p(peer_id) => Some(peer_id),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p_swarm::{behaviour::ExternalAddrConfirmed, ExternalAddressSource};
+
+    #[test]
+    fn confirmed_address_makes_node_public() {
+        let local_peer_id = PeerId::random();
+        let (_, relay_client) = relay::client::new(local_peer_id);
+        let mut behaviour = Behaviour::new(local_peer_id, relay_client, Config::new());
+        assert_eq!(behaviour.connectivity(), Connectivity::Unknown);
+
+        let relayed: Multiaddr = "/ip4/192.0.2.1/tcp/4001/p2p/12D3KooWDpJ7As7BWAwRMfu1VU2WCqNjvq387JEYKDBj4kx6nXTN/p2p-circuit".parse().unwrap();
+        behaviour.on_swarm_event(FromSwarm::ExternalAddrConfirmed(ExternalAddrConfirmed {
+            addr: &relayed,
+            source: ExternalAddressSource::Confirmed,
+        }));
+        assert_eq!(behaviour.connectivity(), Connectivity::Unknown);
+
+        let direct: Multiaddr = "/ip4/192.0.2.2/udp/4001/quic-v1".parse().unwrap();
+        behaviour.on_swarm_event(FromSwarm::ExternalAddrConfirmed(ExternalAddrConfirmed {
+            addr: &direct,
+            source: ExternalAddressSource::AutoNat,
+        }));
+        assert_eq!(behaviour.connectivity(), Connectivity::Public);
+        assert!(matches!(
+            behaviour.events.pop_front(),
+            Some(ToSwarm::GenerateEvent(Event::ConnectivityChanged {
+                old: Connectivity::Unknown,
+                new: Connectivity::Public,
+            }))
+        ));
+    }
+}
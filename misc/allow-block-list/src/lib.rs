@@ -61,6 +61,8 @@
 //! # }
 //! ```
 
+use futures::FutureExt;
+use futures_timer::Delay;
 use libp2p_core::transport::PortUse;
 use libp2p_core::{Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
@@ -68,10 +70,13 @@ use libp2p_swarm::{
     dummy, CloseConnection, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler,
     THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
+use list::{Enforce, PeerList};
 use std::collections::{HashSet, VecDeque};
 use std::fmt;
 use std::task::{Context, Poll, Waker};
+use std::time::Duration;
 use void::Void;
+use web_time::Instant;
 
 /// A [`NetworkBehaviour`] that can act as an allow or block list.
 #[derive(Default, Debug)]
@@ -79,36 +84,188 @@ pub struct Behaviour<S> {
     state: S,
     close_connections: VecDeque<PeerId>,
     waker: Option<Waker>,
+    /// Fires when the next entry expires.
+    next_expiry: Option<Delay>,
 }
 
 /// The list of explicitly allowed peers.
-#[derive(Default)]
+#[derive(Default, Debug)]
 pub struct AllowedPeers {
-    peers: HashSet<PeerId>,
+    list: PeerList,
 }
 
 /// The list of explicitly blocked peers.
-#[derive(Default)]
+#[derive(Default, Debug)]
 pub struct BlockedPeers {
-    peers: HashSet<PeerId>,
+    list: PeerList,
+}
+
+/// The expiry and reason of a peer in an allow or block list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Entry {
+    reason: Option<String>,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    /// Creates an [`Entry`] without a reason that never expires.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes the peer from the list once `ttl` has passed.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.expires_at = Some(Instant::now() + ttl);
+        self
+    }
+
+    /// Attaches a reason to the entry, e.g. the score that led to blocking a peer.
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// The reason the peer was added to the list, if any.
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    /// The time at which the peer is removed from the list, if any.
+    pub fn expires_at(&self) -> Option<Instant> {
+        self.expires_at
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+}
+
+mod list {
+    use super::Entry;
+    use libp2p_identity::PeerId;
+    use libp2p_swarm::ConnectionDenied;
+    use std::collections::{HashMap, HashSet};
+    use web_time::Instant;
+
+    /// The peers of an allow or block list, with their entries.
+    #[derive(Default, Debug)]
+    pub struct PeerList {
+        pub(crate) peers: HashSet<PeerId>,
+        pub(crate) entries: HashMap<PeerId, Entry>,
+    }
+
+    impl PeerList {
+        pub(crate) fn insert(&mut self, peer: PeerId, entry: Entry) -> bool {
+            self.entries.insert(peer, entry);
+            self.peers.insert(peer)
+        }
+
+        pub(crate) fn remove(&mut self, peer: &PeerId) -> bool {
+            self.entries.remove(peer);
+            self.peers.remove(peer)
+        }
+
+        /// The entry of the peer, unless the peer isn't in the list or its entry expired.
+        pub(crate) fn get(&self, peer: &PeerId) -> Option<&Entry> {
+            self.entries
+                .get(peer)
+                .filter(|entry| !entry.is_expired(Instant::now()))
+        }
+
+        pub(crate) fn iter(&self) -> impl Iterator<Item = (&PeerId, &Entry)> {
+            let now = Instant::now();
+            self.entries
+                .iter()
+                .filter(move |(_, entry)| !entry.is_expired(now))
+        }
+
+        /// Removes the expired entries, returning their peers.
+        pub(crate) fn remove_expired(&mut self, now: Instant) -> Vec<PeerId> {
+            let expired = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.is_expired(now))
+                .map(|(peer, _)| *peer)
+                .collect::<Vec<_>>();
+            for peer in &expired {
+                self.remove(peer);
+            }
+            expired
+        }
+
+        pub(crate) fn next_expiry(&self) -> Option<Instant> {
+            self.entries
+                .values()
+                .filter_map(|entry| entry.expires_at)
+                .min()
+        }
+    }
+
+    /// Implemented by [`AllowedPeers`](super::AllowedPeers) and
+    /// [`BlockedPeers`](super::BlockedPeers) only, as the module isn't reachable outside of the crate.
+    pub trait Enforce: 'static {
+        /// Whether the connections to peers whose entry expired are to be closed.
+        const CLOSE_ON_EXPIRY: bool;
+
+        fn enforce(&self, peer: &PeerId) -> Result<(), ConnectionDenied>;
+
+        fn list(&self) -> &PeerList;
+
+        fn list_mut(&mut self) -> &mut PeerList;
+    }
+}
+
+impl<S: Enforce> Behaviour<S> {
+    /// Schedules the removal of the next expiring entry and wakes the behaviour.
+    fn on_list_changed(&mut self) {
+        self.next_expiry = self
+            .state
+            .list()
+            .next_expiry()
+            .map(|at| Delay::new(at.saturating_duration_since(Instant::now())));
+        if let Some(waker) = self.waker.take() {
+            waker.wake()
+        }
+    }
 }
 
 impl Behaviour<AllowedPeers> {
     /// Peers that are currently allowed.
+    ///
+    /// May include peers whose entry expired but which weren't removed yet.
     pub fn allowed_peers(&self) -> &HashSet<PeerId> {
-        &self.state.peers
+        &self.state.list.peers
+    }
+
+    /// The entries of the peers that are currently allowed.
+    pub fn allowed_entries(&self) -> impl Iterator<Item = (&PeerId, &Entry)> {
+        self.state.list.iter()
+    }
+
+    /// The entry of the given peer, if it is currently allowed.
+    pub fn allowed_entry(&self, peer: &PeerId) -> Option<&Entry> {
+        self.state.list.get(peer)
     }
 
     /// Allow connections to the given peer.
     ///
     /// Returns whether the peer was newly inserted. Does nothing if the peer was already present in the set.
     pub fn allow_peer(&mut self, peer: PeerId) -> bool {
-        let inserted = self.state.peers.insert(peer);
-        if inserted {
-            if let Some(waker) = self.waker.take() {
-                waker.wake()
-            }
+        if self.state.list.peers.contains(&peer) {
+            return false;
         }
+        self.allow_peer_with(peer, Entry::new())
+    }
+
+    /// Allow connections to the given peer, with the expiry and reason of the given [`Entry`].
+    ///
+    /// Once the entry expires, the peer is disallowed and all active connections to it are
+    /// closed. The entry replaces the previous entry of the peer, if any.
+    ///
+    /// Returns whether the peer was newly inserted.
+    pub fn allow_peer_with(&mut self, peer: PeerId, entry: Entry) -> bool {
+        let inserted = self.state.list.insert(peer, entry);
+        self.on_list_changed();
         inserted
     }
 
@@ -118,12 +275,10 @@ impl Behaviour<AllowedPeers> {
     ///
     /// Returns whether the peer was present in the set. Does nothing if the peer was not present in the set.
     pub fn disallow_peer(&mut self, peer: PeerId) -> bool {
-        let removed = self.state.peers.remove(&peer);
+        let removed = self.state.list.remove(&peer);
         if removed {
             self.close_connections.push_back(peer);
-            if let Some(waker) = self.waker.take() {
-                waker.wake()
-            }
+            self.on_list_changed();
         }
         removed
     }
@@ -131,8 +286,20 @@ impl Behaviour<AllowedPeers> {
 
 impl Behaviour<BlockedPeers> {
     /// Peers that are currently blocked.
+    ///
+    /// May include peers whose entry expired but which weren't removed yet.
     pub fn blocked_peers(&self) -> &HashSet<PeerId> {
-        &self.state.peers
+        &self.state.list.peers
+    }
+
+    /// The entries of the peers that are currently blocked.
+    pub fn blocked_entries(&self) -> impl Iterator<Item = (&PeerId, &Entry)> {
+        self.state.list.iter()
+    }
+
+    /// The entry of the given peer, if it is currently blocked.
+    pub fn blocked_entry(&self, peer: &PeerId) -> Option<&Entry> {
+        self.state.list.get(peer)
     }
 
     /// Block connections to a given peer.
@@ -141,13 +308,24 @@ impl Behaviour<BlockedPeers> {
     ///
     /// Returns whether the peer was newly inserted. Does nothing if the peer was already present in the set.
     pub fn block_peer(&mut self, peer: PeerId) -> bool {
-        let inserted = self.state.peers.insert(peer);
+        if self.state.list.peers.contains(&peer) {
+            return false;
+        }
+        self.block_peer_with(peer, Entry::new())
+    }
+
+    /// Block connections to a given peer, with the expiry and reason of the given [`Entry`].
+    ///
+    /// All active connections to this peer will be closed immediately. Once the entry expires,
+    /// the peer is unblocked. The entry replaces the previous entry of the peer, if any.
+    ///
+    /// Returns whether the peer was newly inserted.
+    pub fn block_peer_with(&mut self, peer: PeerId, entry: Entry) -> bool {
+        let inserted = self.state.list.insert(peer, entry);
         if inserted {
             self.close_connections.push_back(peer);
-            if let Some(waker) = self.waker.take() {
-                waker.wake()
-            }
         }
+        self.on_list_changed();
         inserted
     }
 
@@ -155,11 +333,9 @@ impl Behaviour<BlockedPeers> {
     ///
     /// Returns whether the peer was present in the set. Does nothing if the peer was not present in the set.
     pub fn unblock_peer(&mut self, peer: PeerId) -> bool {
-        let removed = self.state.peers.remove(&peer);
+        let removed = self.state.list.remove(&peer);
         if removed {
-            if let Some(waker) = self.waker.take() {
-                waker.wake()
-            }
+            self.on_list_changed();
         }
         removed
     }
@@ -183,38 +359,69 @@ impl std::error::Error for NotAllowed {}
 #[derive(Debug)]
 pub struct Blocked {
     peer: PeerId,
+    reason: Option<String>,
+}
+
+impl Blocked {
+    /// The reason the peer was blocked, if any.
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
 }
 
 impl fmt::Display for Blocked {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "peer {} is in the block list", self.peer)
+        write!(f, "peer {} is in the block list", self.peer)?;
+        if let Some(reason) = &self.reason {
+            write!(f, ": {reason}")?;
+        }
+        Ok(())
     }
 }
 
 impl std::error::Error for Blocked {}
 
-trait Enforce: 'static {
-    fn enforce(&self, peer: &PeerId) -> Result<(), ConnectionDenied>;
-}
-
 impl Enforce for AllowedPeers {
+    const CLOSE_ON_EXPIRY: bool = true;
+
     fn enforce(&self, peer: &PeerId) -> Result<(), ConnectionDenied> {
-        if !self.peers.contains(peer) {
+        if self.list.get(peer).is_none() {
             return Err(ConnectionDenied::new(NotAllowed { peer: *peer }));
         }
 
         Ok(())
     }
+
+    fn list(&self) -> &PeerList {
+        &self.list
+    }
+
+    fn list_mut(&mut self) -> &mut PeerList {
+        &mut self.list
+    }
 }
 
 impl Enforce for BlockedPeers {
+    const CLOSE_ON_EXPIRY: bool = false;
+
     fn enforce(&self, peer: &PeerId) -> Result<(), ConnectionDenied> {
-        if self.peers.contains(peer) {
-            return Err(ConnectionDenied::new(Blocked { peer: *peer }));
+        if let Some(entry) = self.list.get(peer) {
+            return Err(ConnectionDenied::new(Blocked {
+                peer: *peer,
+                reason: entry.reason.clone(),
+            }));
         }
 
         Ok(())
     }
+
+    fn list(&self) -> &PeerList {
+        &self.list
+    }
+
+    fn list_mut(&mut self) -> &mut PeerList {
+        &mut self.list
+    }
 }
 
 impl<S> NetworkBehaviour for Behaviour<S>
@@ -278,11 +485,25 @@ where
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
-        if let Some(peer) = self.close_connections.pop_front() {
-            return Poll::Ready(ToSwarm::CloseConnection {
-                peer_id: peer,
-                connection: CloseConnection::All,
-            });
+        loop {
+            if let Some(peer) = self.close_connections.pop_front() {
+                return Poll::Ready(ToSwarm::CloseConnection {
+                    peer_id: peer,
+                    connection: CloseConnection::All,
+                });
+            }
+
+            let Some(next_expiry) = self.next_expiry.as_mut() else {
+                break;
+            };
+            if next_expiry.poll_unpin(cx).is_pending() {
+                break;
+            }
+            let expired = self.state.list_mut().remove_expired(Instant::now());
+            if S::CLOSE_ON_EXPIRY {
+                self.close_connections.extend(expired);
+            }
+            self.on_list_changed();
         }
 
         self.waker = Some(cx.waker().clone());
@@ -324,6 +545,39 @@ mod tests {
         dial(&mut dialer, &listener).unwrap();
     }
 
+    #[async_std::test]
+    async fn blocked_peer_gets_unblocked_after_ttl() {
+        let mut dialer = Swarm::new_ephemeral(|_| Behaviour::<BlockedPeers>::default());
+        let mut listener = Swarm::new_ephemeral(|_| Behaviour::<BlockedPeers>::default());
+        listener.listen().with_memory_addr_external().await;
+        let ttl = Duration::from_millis(100);
+
+        dialer.behaviour_mut().block_peer_with(
+            *listener.local_peer_id(),
+            Entry::new().with_ttl(ttl).with_reason("low score"),
+        );
+        assert_eq!(
+            dialer
+                .behaviour()
+                .blocked_entry(listener.local_peer_id())
+                .and_then(Entry::reason),
+            Some("low score")
+        );
+
+        let DialError::Denied { cause } = dial(&mut dialer, &listener).unwrap_err() else {
+            panic!("unexpected dial error")
+        };
+        assert_eq!(
+            cause.downcast::<Blocked>().unwrap().reason(),
+            Some("low score")
+        );
+
+        async_std::task::sleep(ttl).await;
+
+        assert!(dialer.behaviour().blocked_entries().next().is_none());
+        dial(&mut dialer, &listener).unwrap();
+    }
+
     #[async_std::test]
     async fn blocked_peer_cannot_dial_us() {
         let mut dialer = Swarm::new_ephemeral(|_| Behaviour::<BlockedPeers>::default());
@@ -18,7 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use libp2p_core::{transport::PortUse, ConnectedPoint, Endpoint, Multiaddr};
+use libp2p_core::{multiaddr::Protocol, transport::PortUse, ConnectedPoint, Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_swarm::{
     behaviour::{ConnectionEstablished, DialFailure, ListenFailure},
@@ -27,6 +27,7 @@ use libp2p_swarm::{
 };
 use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::net::IpAddr;
 use std::task::{Context, Poll};
 use void::Void;
 
@@ -66,6 +67,9 @@ pub struct Behaviour {
     established_inbound_connections: HashSet<ConnectionId>,
     established_outbound_connections: HashSet<ConnectionId>,
     established_per_peer: HashMap<PeerId, HashSet<ConnectionId>>,
+    established_per_ip: HashMap<IpAddr, HashSet<ConnectionId>>,
+    /// Remote IP address of each established connection that is not relayed.
+    established_ips: HashMap<ConnectionId, IpAddr>,
 }
 
 impl Behaviour {
@@ -77,6 +81,8 @@ impl Behaviour {
             established_inbound_connections: Default::default(),
             established_outbound_connections: Default::default(),
             established_per_peer: Default::default(),
+            established_per_ip: Default::default(),
+            established_ips: Default::default(),
         }
     }
 
@@ -85,6 +91,49 @@ impl Behaviour {
     pub fn limits_mut(&mut self) -> &mut ConnectionLimits {
         &mut self.limits
     }
+
+    /// Checks the per-IP and per-subnet limits for a new connection to or from `remote_addr`.
+    fn check_ip_limits(&self, remote_addr: &Multiaddr) -> Result<(), ConnectionDenied> {
+        let Some(ip) = remote_ip(remote_addr) else {
+            return Ok(());
+        };
+
+        check_limit(
+            self.limits.max_established_per_ip,
+            self.established_per_ip
+                .get(&ip)
+                .map(|connections| connections.len())
+                .unwrap_or(0),
+            Kind::EstablishedPerIp,
+        )?;
+        for subnet_limit in &self.limits.max_established_per_subnet {
+            let subnet = subnet_limit.subnet(ip);
+            check_limit(
+                Some(subnet_limit.limit),
+                self.established_per_ip
+                    .iter()
+                    .filter(|(other, _)| subnet_limit.subnet(**other) == subnet)
+                    .map(|(_, connections)| connections.len())
+                    .sum(),
+                Kind::EstablishedPerSubnet,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the IP address of `addr`, unless the address is relayed.
+fn remote_ip(addr: &Multiaddr) -> Option<IpAddr> {
+    if addr.iter().any(|p| p == Protocol::P2pCircuit) {
+        return None;
+    }
+
+    match addr.iter().next()? {
+        Protocol::Ip4(ip) => Some(ip.into()),
+        Protocol::Ip6(ip) => Some(ip.into()),
+        _ => None,
+    }
 }
 
 fn check_limit(limit: Option<u32>, current: usize, kind: Kind) -> Result<(), ConnectionDenied> {
@@ -128,6 +177,8 @@ enum Kind {
     EstablishedIncoming,
     EstablishedOutgoing,
     EstablishedPerPeer,
+    EstablishedPerIp,
+    EstablishedPerSubnet,
     EstablishedTotal,
 }
 
@@ -139,6 +190,8 @@ impl fmt::Display for Kind {
             Kind::EstablishedIncoming => write!(f, "established incoming connections"),
             Kind::EstablishedOutgoing => write!(f, "established outgoing connections"),
             Kind::EstablishedPerPeer => write!(f, "established connections per peer"),
+            Kind::EstablishedPerIp => write!(f, "established connections per IP address"),
+            Kind::EstablishedPerSubnet => write!(f, "established connections per subnet"),
             Kind::EstablishedTotal => write!(f, "established connections"),
         }
     }
@@ -154,9 +207,39 @@ pub struct ConnectionLimits {
     max_established_incoming: Option<u32>,
     max_established_outgoing: Option<u32>,
     max_established_per_peer: Option<u32>,
+    max_established_per_ip: Option<u32>,
+    max_established_per_subnet: Vec<SubnetLimit>,
     max_established_total: Option<u32>,
 }
 
+/// A limit on the established connections from or to a single subnet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SubnetLimit {
+    ipv4_prefix_len: u8,
+    ipv6_prefix_len: u8,
+    limit: u32,
+}
+
+impl SubnetLimit {
+    /// Returns `ip` with all bits after the prefix of its subnet cleared.
+    fn subnet(&self, ip: IpAddr) -> IpAddr {
+        match ip {
+            IpAddr::V4(ip) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.ipv4_prefix_len.min(32)))
+                    .unwrap_or(0);
+                IpAddr::V4((u32::from(ip) & mask).into())
+            }
+            IpAddr::V6(ip) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.ipv6_prefix_len.min(128)))
+                    .unwrap_or(0);
+                IpAddr::V6((u128::from(ip) & mask).into())
+            }
+        }
+    }
+}
+
 impl ConnectionLimits {
     /// Configures the maximum number of concurrently incoming connections being established.
     pub fn with_max_pending_incoming(mut self, limit: Option<u32>) -> Self {
@@ -199,6 +282,41 @@ impl ConnectionLimits {
         self.max_established_per_peer = limit;
         self
     }
+
+    /// Configures the maximum number of concurrent established connections per remote
+    /// IP address, regardless of direction (incoming or outgoing).
+    ///
+    /// Relayed connections are not counted.
+    pub fn with_max_established_per_ip(mut self, limit: Option<u32>) -> Self {
+        self.max_established_per_ip = limit;
+        self
+    }
+
+    /// Configures the maximum number of concurrent established connections per remote
+    /// subnet, regardless of direction (incoming or outgoing).
+    ///
+    /// A subnet is given by the length of its prefix for IPv4 and IPv6 addresses, e.g.
+    /// `24` and `64`. Limits for several prefix lengths can be configured by calling
+    /// this method multiple times; passing `None` removes the limit for the given
+    /// prefix lengths. Relayed connections are not counted.
+    pub fn with_max_established_per_subnet(
+        mut self,
+        ipv4_prefix_len: u8,
+        ipv6_prefix_len: u8,
+        limit: Option<u32>,
+    ) -> Self {
+        self.max_established_per_subnet.retain(|l| {
+            l.ipv4_prefix_len != ipv4_prefix_len || l.ipv6_prefix_len != ipv6_prefix_len
+        });
+        if let Some(limit) = limit {
+            self.max_established_per_subnet.push(SubnetLimit {
+                ipv4_prefix_len,
+                ipv6_prefix_len,
+                limit,
+            });
+        }
+        self
+    }
 }
 
 impl NetworkBehaviour for Behaviour {
@@ -209,13 +327,15 @@ impl NetworkBehaviour for Behaviour {
         &mut self,
         connection_id: ConnectionId,
         _: &Multiaddr,
-        _: &Multiaddr,
+        remote_addr: &Multiaddr,
     ) -> Result<(), ConnectionDenied> {
         check_limit(
             self.limits.max_pending_incoming,
             self.pending_inbound_connections.len(),
             Kind::PendingIncoming,
         )?;
+        // Deny connections from hosts that are already at their limit before any handshake.
+        self.check_ip_limits(remote_addr)?;
 
         self.pending_inbound_connections.insert(connection_id);
 
@@ -227,7 +347,7 @@ impl NetworkBehaviour for Behaviour {
         connection_id: ConnectionId,
         peer: PeerId,
         _: &Multiaddr,
-        _: &Multiaddr,
+        remote_addr: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
         self.pending_inbound_connections.remove(&connection_id);
 
@@ -244,6 +364,7 @@ impl NetworkBehaviour for Behaviour {
                 .unwrap_or(0),
             Kind::EstablishedPerPeer,
         )?;
+        self.check_ip_limits(remote_addr)?;
         check_limit(
             self.limits.max_established_total,
             self.established_inbound_connections.len()
@@ -276,7 +397,7 @@ impl NetworkBehaviour for Behaviour {
         &mut self,
         connection_id: ConnectionId,
         peer: PeerId,
-        _: &Multiaddr,
+        remote_addr: &Multiaddr,
         _: Endpoint,
         _: PortUse,
     ) -> Result<THandler<Self>, ConnectionDenied> {
@@ -295,6 +416,7 @@ impl NetworkBehaviour for Behaviour {
                 .unwrap_or(0),
             Kind::EstablishedPerPeer,
         )?;
+        self.check_ip_limits(remote_addr)?;
         check_limit(
             self.limits.max_established_total,
             self.established_inbound_connections.len()
@@ -318,6 +440,14 @@ impl NetworkBehaviour for Behaviour {
                     .entry(peer_id)
                     .or_default()
                     .remove(&connection_id);
+                if let Some(ip) = self.established_ips.remove(&connection_id) {
+                    if let Some(connections) = self.established_per_ip.get_mut(&ip) {
+                        connections.remove(&connection_id);
+                        if connections.is_empty() {
+                            self.established_per_ip.remove(&ip);
+                        }
+                    }
+                }
             }
             FromSwarm::ConnectionEstablished(ConnectionEstablished {
                 peer_id,
@@ -338,6 +468,14 @@ impl NetworkBehaviour for Behaviour {
                     .entry(peer_id)
                     .or_default()
                     .insert(connection_id);
+
+                if let Some(ip) = remote_ip(endpoint.get_remote_address()) {
+                    self.established_ips.insert(connection_id, ip);
+                    self.established_per_ip
+                        .entry(ip)
+                        .or_default()
+                        .insert(connection_id);
+                }
             }
             FromSwarm::DialFailure(DialFailure { connection_id, .. }) => {
                 self.pending_outbound_connections.remove(&connection_id);
@@ -478,6 +616,61 @@ mod tests {
         quickcheck(prop as fn(_));
     }
 
+    #[test]
+    fn max_established_per_ip() {
+        let mut swarm1 = Swarm::new_ephemeral(|_| {
+            Behaviour::new(ConnectionLimits::default().with_max_established_per_ip(Some(1)))
+        });
+        let mut swarm2 = Swarm::new_ephemeral(|_| Behaviour::new(ConnectionLimits::default()));
+
+        async_std::task::block_on(async {
+            let (_, tcp_addr) = swarm1.listen().await;
+            let peer = *swarm1.local_peer_id();
+
+            for _ in 0..2 {
+                swarm2
+                    .dial(
+                        DialOpts::peer_id(peer)
+                            .condition(PeerCondition::Always)
+                            .addresses(vec![tcp_addr.clone()])
+                            .build(),
+                    )
+                    .unwrap();
+            }
+            async_std::task::spawn(swarm2.loop_on_next());
+
+            let cause = swarm1
+                .wait(|event| match event {
+                    SwarmEvent::IncomingConnectionError {
+                        error: ListenError::Denied { cause },
+                        ..
+                    } => Some(cause),
+                    _ => None,
+                })
+                .await;
+
+            assert_eq!(cause.downcast::<Exceeded>().unwrap().limit, 1);
+        });
+    }
+
+    #[test]
+    fn subnet_masks_host_bits() {
+        let limit = SubnetLimit {
+            ipv4_prefix_len: 24,
+            ipv6_prefix_len: 48,
+            limit: 1,
+        };
+
+        assert_eq!(
+            limit.subnet("192.0.2.17".parse().unwrap()),
+            "192.0.2.0".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            limit.subnet("2001:db8:1:2::1".parse().unwrap()),
+            "2001:db8:1::".parse::<IpAddr>().unwrap()
+        );
+    }
+
     /// Another sibling [`NetworkBehaviour`] implementation might deny established connections in
     /// [`handle_established_outbound_connection`] or [`handle_established_inbound_connection`].
     /// [`Behaviour`] must not increase the established counters in
@@ -11,12 +11,19 @@ use libp2p_core::{
     Multiaddr,
 };
 use libp2p_identity::PeerId;
+use libp2p_swarm::bandwidth::BandwidthAccounting;
 use prometheus_client::{
-    encoding::{EncodeLabelSet, EncodeLabelValue},
-    metrics::{counter::Counter, family::Family},
+    collector::Collector,
+    encoding::{DescriptorEncoder, EncodeLabelSet, EncodeLabelValue, EncodeMetric},
+    metrics::{
+        counter::{ConstCounter, Counter},
+        family::Family,
+        MetricType,
+    },
     registry::{Registry, Unit},
 };
 use std::{
+    collections::HashMap,
     convert::TryFrom as _,
     io,
     pin::Pin,
@@ -47,6 +54,48 @@ impl<T> Transport<T> {
     }
 }
 
+/// Registers Prometheus metrics of the bandwidth used per stream protocol, as accounted by the
+/// given [`BandwidthAccounting`].
+///
+/// See [`libp2p_swarm::Config::with_bandwidth_accounting`]. Usage is aggregated across peers to
+/// bound the cardinality of the metrics; per-peer usage can be queried via
+/// [`BandwidthAccounting::peer_usage`].
+pub fn register_stream_bandwidth(accounting: BandwidthAccounting, registry: &mut Registry) {
+    registry
+        .sub_registry_with_prefix("libp2p")
+        .register_collector(Box::new(StreamBandwidth(accounting)));
+}
+
+#[derive(Debug)]
+struct StreamBandwidth(BandwidthAccounting);
+
+impl Collector for StreamBandwidth {
+    fn encode(&self, mut encoder: DescriptorEncoder) -> Result<(), std::fmt::Error> {
+        let mut by_protocol: HashMap<String, (u64, u64)> = HashMap::new();
+        for (_, protocol, usage) in self.0.snapshot() {
+            let (inbound, outbound) = by_protocol.entry(protocol.to_string()).or_default();
+            *inbound = inbound.saturating_add(usage.inbound());
+            *outbound = outbound.saturating_add(usage.outbound());
+        }
+
+        let mut family_encoder = encoder.encode_descriptor(
+            "stream_bandwidth",
+            "Bandwidth usage of negotiated streams by direction and stream protocol",
+            Some(&Unit::Bytes),
+            MetricType::Counter,
+        )?;
+        for (protocol, (inbound, outbound)) in by_protocol {
+            for (direction, bytes) in [("inbound", inbound), ("outbound", outbound)] {
+                let labels = [("protocol", protocol.as_str()), ("direction", direction)];
+                let metric_encoder = family_encoder.encode_family(&labels)?;
+                ConstCounter::new(bytes).encode(metric_encoder)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
 struct Labels {
     protocols: String,
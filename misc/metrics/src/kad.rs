@@ -42,6 +42,12 @@ pub(crate) struct Metrics {
     routing_updated: Family<RoutingUpdated, Counter>,
 
     inbound_requests: Family<InboundRequest, Counter>,
+    inbound_requests_refused: Family<RefusedRequest, Counter>,
+
+    rpcs: Family<Rpc, Counter>,
+    rpc_request_size: Family<Rpc, Histogram>,
+    rpc_response_size: Family<Rpc, Histogram>,
+    rpc_duration: Family<Rpc, Histogram>,
 }
 
 impl Metrics {
@@ -137,6 +143,48 @@ impl Metrics {
             inbound_requests.clone(),
         );
 
+        let inbound_requests_refused = Family::default();
+        sub_registry.register(
+            "inbound_requests_refused",
+            "Number of inbound requests refused because too many requests were waiting for an answer",
+            inbound_requests_refused.clone(),
+        );
+
+        let rpcs = Family::default();
+        sub_registry.register(
+            "rpcs",
+            "Number of completed requests by direction, type and outcome. \
+             Requires `libp2p_kad::Config::set_rpc_stats_reporting`",
+            rpcs.clone(),
+        );
+
+        let rpc_request_size: Family<_, _> =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(64.0, 2.0, 10)));
+        sub_registry.register_with_unit(
+            "rpc_request_size",
+            "Size of the requests of completed requests",
+            Unit::Bytes,
+            rpc_request_size.clone(),
+        );
+
+        let rpc_response_size: Family<_, _> =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(64.0, 2.0, 10)));
+        sub_registry.register_with_unit(
+            "rpc_response_size",
+            "Size of the responses of completed requests",
+            Unit::Bytes,
+            rpc_response_size.clone(),
+        );
+
+        let rpc_duration: Family<_, _> =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(0.001, 2.0, 14)));
+        sub_registry.register_with_unit(
+            "rpc_duration",
+            "Time to handle an inbound request or to receive the response to an outbound request",
+            Unit::Seconds,
+            rpc_duration.clone(),
+        );
+
         Self {
             query_result_get_record_ok,
             query_result_get_record_error,
@@ -155,6 +203,12 @@ impl Metrics {
             routing_updated,
 
             inbound_requests,
+            inbound_requests_refused,
+
+            rpcs,
+            rpc_request_size,
+            rpc_response_size,
+            rpc_duration,
         }
     }
 }
@@ -253,6 +307,26 @@ impl super::Recorder<libp2p_kad::Event> for Metrics {
             libp2p_kad::Event::InboundRequest { request } => {
                 self.inbound_requests.get_or_create(&request.into()).inc();
             }
+            libp2p_kad::Event::InboundRequestRefused { rpc_type, .. } => {
+                self.inbound_requests_refused
+                    .get_or_create(&RefusedRequest {
+                        r#type: (*rpc_type).into(),
+                    })
+                    .inc();
+            }
+            libp2p_kad::Event::RpcCompleted { stats, .. } => {
+                let labels = Rpc::from(stats);
+                self.rpcs.get_or_create(&labels).inc();
+                self.rpc_request_size
+                    .get_or_create(&labels)
+                    .observe(stats.request_size as f64);
+                self.rpc_response_size
+                    .get_or_create(&labels)
+                    .observe(stats.response_size as f64);
+                self.rpc_duration
+                    .get_or_create(&labels)
+                    .observe(stats.duration.as_secs_f64());
+            }
             _ => {}
         }
     }
@@ -414,3 +488,67 @@ enum Request {
     GetRecord,
     PutRecord,
 }
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+struct Rpc {
+    direction: RpcDirection,
+    r#type: RpcType,
+    outcome: RpcOutcome,
+}
+
+impl From<&libp2p_kad::RpcStats> for Rpc {
+    fn from(stats: &libp2p_kad::RpcStats) -> Self {
+        Self {
+            direction: match stats.direction {
+                libp2p_kad::RpcDirection::Inbound => RpcDirection::Inbound,
+                libp2p_kad::RpcDirection::Outbound => RpcDirection::Outbound,
+            },
+            r#type: stats.rpc_type.into(),
+            outcome: if stats.success {
+                RpcOutcome::Success
+            } else {
+                RpcOutcome::Failure
+            },
+        }
+    }
+}
+
+#[derive(EncodeLabelValue, Hash, Clone, Eq, PartialEq, Debug)]
+enum RpcDirection {
+    Inbound,
+    Outbound,
+}
+
+#[derive(EncodeLabelValue, Hash, Clone, Eq, PartialEq, Debug)]
+enum RpcType {
+    Ping,
+    FindNode,
+    GetProviders,
+    AddProvider,
+    GetValue,
+    PutValue,
+}
+
+impl From<libp2p_kad::RpcType> for RpcType {
+    fn from(rpc_type: libp2p_kad::RpcType) -> Self {
+        match rpc_type {
+            libp2p_kad::RpcType::Ping => RpcType::Ping,
+            libp2p_kad::RpcType::FindNode => RpcType::FindNode,
+            libp2p_kad::RpcType::GetProviders => RpcType::GetProviders,
+            libp2p_kad::RpcType::AddProvider => RpcType::AddProvider,
+            libp2p_kad::RpcType::GetValue => RpcType::GetValue,
+            libp2p_kad::RpcType::PutValue => RpcType::PutValue,
+        }
+    }
+}
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+struct RefusedRequest {
+    r#type: RpcType,
+}
+
+#[derive(EncodeLabelValue, Hash, Clone, Eq, PartialEq, Debug)]
+enum RpcOutcome {
+    Success,
+    Failure,
+}
@@ -38,13 +38,21 @@ mod identify;
 mod kad;
 #[cfg(feature = "ping")]
 mod ping;
+mod pipelining;
 mod protocol_stack;
 #[cfg(feature = "relay")]
 mod relay;
+#[cfg(feature = "rendezvous")]
+mod rendezvous;
+mod stream;
 mod swarm;
 
-pub use bandwidth::Transport as BandwidthTransport;
+pub use bandwidth::{register_stream_bandwidth, Transport as BandwidthTransport};
+pub use pipelining::register_pipelining_statistics;
 pub use prometheus_client::registry::Registry;
+#[cfg(feature = "relay")]
+pub use relay::register_relay_statistics;
+pub use stream::register_stream_metrics;
 
 /// Set of Swarm and protocol metrics derived from emitted events.
 pub struct Metrics {
@@ -60,6 +68,8 @@ pub struct Metrics {
     ping: ping::Metrics,
     #[cfg(feature = "relay")]
     relay: relay::Metrics,
+    #[cfg(feature = "rendezvous")]
+    rendezvous: rendezvous::Metrics,
     swarm: swarm::Metrics,
 }
 
@@ -87,6 +97,8 @@ impl Metrics {
             ping: ping::Metrics::new(sub_registry),
             #[cfg(feature = "relay")]
             relay: relay::Metrics::new(sub_registry),
+            #[cfg(feature = "rendezvous")]
+            rendezvous: rendezvous::Metrics::new(sub_registry),
             swarm: swarm::Metrics::new(sub_registry),
         }
     }
@@ -140,6 +152,13 @@ impl Recorder<libp2p_relay::Event> for Metrics {
     }
 }
 
+#[cfg(feature = "rendezvous")]
+impl Recorder<libp2p_rendezvous::server::Event> for Metrics {
+    fn record(&self, event: &libp2p_rendezvous::server::Event) {
+        self.rendezvous.record(event)
+    }
+}
+
 impl<TBvEv> Recorder<libp2p_swarm::SwarmEvent<TBvEv>> for Metrics {
     fn record(&self, event: &libp2p_swarm::SwarmEvent<TBvEv>) {
         self.swarm.record(event);
@@ -0,0 +1,54 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use libp2p_core::transport::upgrade::PipeliningStatistics;
+use prometheus_client::collector::Collector;
+use prometheus_client::encoding::{DescriptorEncoder, EncodeMetric};
+use prometheus_client::metrics::counter::ConstCounter;
+use prometheus_client::metrics::MetricType;
+use prometheus_client::registry::Registry;
+
+/// Registers a Prometheus metric of the round-trips saved by selecting the stream multiplexer
+/// during the security handshake, as counted by the given [`PipeliningStatistics`].
+///
+/// See [`Authenticated::multiplex_pipelined`](libp2p_core::transport::upgrade::Authenticated::multiplex_pipelined).
+pub fn register_pipelining_statistics(statistics: PipeliningStatistics, registry: &mut Registry) {
+    registry
+        .sub_registry_with_prefix("libp2p")
+        .sub_registry_with_prefix("swarm")
+        .register_collector(Box::new(Pipelining(statistics)));
+}
+
+#[derive(Debug)]
+struct Pipelining(PipeliningStatistics);
+
+impl Collector for Pipelining {
+    fn encode(&self, mut encoder: DescriptorEncoder) -> Result<(), std::fmt::Error> {
+        let metric_encoder = encoder.encode_descriptor(
+            "connections_establishment_round_trips_saved",
+            "Number of round-trips saved by selecting the stream multiplexer during the security handshake",
+            None,
+            MetricType::Counter,
+        )?;
+        ConstCounter::new(self.0.round_trips_saved()).encode(metric_encoder)?;
+
+        Ok(())
+    }
+}
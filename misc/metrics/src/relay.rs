@@ -18,10 +18,16 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use prometheus_client::encoding::{EncodeLabelSet, EncodeLabelValue};
-use prometheus_client::metrics::counter::Counter;
+use libp2p_relay::{DenialReason, Statistics};
+use prometheus_client::collector::Collector;
+use prometheus_client::encoding::{
+    DescriptorEncoder, EncodeLabelSet, EncodeLabelValue, EncodeMetric,
+};
+use prometheus_client::metrics::counter::{ConstCounter, Counter};
 use prometheus_client::metrics::family::Family;
-use prometheus_client::registry::Registry;
+use prometheus_client::metrics::gauge::ConstGauge;
+use prometheus_client::metrics::MetricType;
+use prometheus_client::registry::{Registry, Unit};
 
 pub(crate) struct Metrics {
     events: Family<EventLabels, Counter>,
@@ -100,3 +106,126 @@ impl super::Recorder<libp2p_relay::Event> for Metrics {
             .inc();
     }
 }
+
+/// Registers Prometheus metrics of a relay server, as tracked by the given [`Statistics`].
+///
+/// See [`libp2p_relay::Behaviour::statistics`]. The metrics cover the active reservations and
+/// circuits, the bytes relayed, the denied requests by reason and the saturation of each of the
+/// configured limits, i.e. the ratio of the current usage to the limit.
+pub fn register_relay_statistics(statistics: Statistics, registry: &mut Registry) {
+    registry
+        .sub_registry_with_prefix("libp2p")
+        .sub_registry_with_prefix("relay")
+        .register_collector(Box::new(RelayStatistics(statistics)));
+}
+
+#[derive(Debug)]
+struct RelayStatistics(Statistics);
+
+impl Collector for RelayStatistics {
+    fn encode(&self, mut encoder: DescriptorEncoder) -> Result<(), std::fmt::Error> {
+        let statistics = &self.0;
+
+        {
+            let metric_encoder = encoder.encode_descriptor(
+                "active_reservations",
+                "Number of active reservations",
+                None,
+                MetricType::Gauge,
+            )?;
+            ConstGauge::new(statistics.active_reservations() as i64).encode(metric_encoder)?;
+        }
+
+        {
+            let metric_encoder = encoder.encode_descriptor(
+                "active_circuits",
+                "Number of active circuits, including circuits being established",
+                None,
+                MetricType::Gauge,
+            )?;
+            ConstGauge::new(statistics.active_circuits() as i64).encode(metric_encoder)?;
+        }
+
+        {
+            let metric_encoder = encoder.encode_descriptor(
+                "relayed",
+                "Bytes relayed over circuits in both directions",
+                Some(&Unit::Bytes),
+                MetricType::Counter,
+            )?;
+            ConstCounter::new(statistics.bytes_relayed()).encode(metric_encoder)?;
+        }
+
+        {
+            let mut family_encoder = encoder.encode_descriptor(
+                "denials",
+                "Number of denied reservation and circuit requests by reason",
+                None,
+                MetricType::Counter,
+            )?;
+            for (request, denials) in [
+                ("reservation", statistics.reservation_denials()),
+                ("circuit", statistics.circuit_denials()),
+            ] {
+                for (reason, count) in denials {
+                    let labels = [("request", request), ("reason", denial_reason(reason))];
+                    let metric_encoder = family_encoder.encode_family(&labels)?;
+                    ConstCounter::new(count).encode(metric_encoder)?;
+                }
+            }
+        }
+
+        {
+            let mut family_encoder = encoder.encode_descriptor(
+                "limit_saturation",
+                "Ratio of the current usage to each of the configured limits, with the per-peer limits relating to the peer closest to its limit",
+                None,
+                MetricType::Gauge,
+            )?;
+            for (limit, usage, max) in [
+                (
+                    "max_reservations",
+                    statistics.active_reservations(),
+                    statistics.max_reservations(),
+                ),
+                (
+                    "max_reservations_per_peer",
+                    statistics.max_active_reservations_of_peer(),
+                    statistics.max_reservations_per_peer(),
+                ),
+                (
+                    "max_circuits",
+                    statistics.active_circuits(),
+                    statistics.max_circuits(),
+                ),
+                (
+                    "max_circuits_per_peer",
+                    statistics.max_active_circuits_of_peer(),
+                    statistics.max_circuits_per_peer(),
+                ),
+            ] {
+                // A limit of zero is always saturated.
+                let saturation = if max == 0 {
+                    1.0
+                } else {
+                    usage as f64 / max as f64
+                };
+                let labels = [("limit", limit)];
+                let metric_encoder = family_encoder.encode_family(&labels)?;
+                ConstGauge::new(saturation).encode(metric_encoder)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn denial_reason(reason: DenialReason) -> &'static str {
+    match reason {
+        DenialReason::PeerLimit => "peer_limit",
+        DenialReason::TotalLimit => "total_limit",
+        DenialReason::RateLimit => "rate_limit",
+        DenialReason::NoReservation => "no_reservation",
+        DenialReason::ConnectionFailed => "connection_failed",
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright 2021 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use libp2p_rendezvous::server::Event;
+use prometheus_client::encoding::{EncodeLabelSet, EncodeLabelValue};
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::histogram::{exponential_buckets, Histogram};
+use prometheus_client::registry::Registry;
+
+/// Metrics of a rendezvous server.
+///
+/// Namespaces are chosen by remote peers. Operators of public rendezvous points should keep in
+/// mind that the per-namespace families grow with the number of distinct namespaces registered.
+/// For a point-in-time view of active registrations, see
+/// [`libp2p_rendezvous::server::Behaviour::statistics`].
+pub(crate) struct Metrics {
+    registrations: Family<NamespaceLabels, Counter>,
+    unregistrations: Family<NamespaceLabels, Counter>,
+    registrations_expired: Family<NamespaceLabels, Counter>,
+    registrations_rejected: Family<ErrorLabels, Counter>,
+    discover_requests: Family<DiscoverLabels, Counter>,
+    discover_registrations_returned: Histogram,
+}
+
+impl Metrics {
+    pub(crate) fn new(registry: &mut Registry) -> Self {
+        let sub_registry = registry.sub_registry_with_prefix("rendezvous_server");
+
+        let registrations = Family::default();
+        sub_registry.register(
+            "registrations",
+            "Number of accepted registrations, including refreshes, by namespace",
+            registrations.clone(),
+        );
+
+        let unregistrations = Family::default();
+        sub_registry.register(
+            "unregistrations",
+            "Number of unregister requests by namespace",
+            unregistrations.clone(),
+        );
+
+        let registrations_expired = Family::default();
+        sub_registry.register(
+            "registrations_expired",
+            "Number of registrations that expired by namespace",
+            registrations_expired.clone(),
+        );
+
+        let registrations_rejected = Family::default();
+        sub_registry.register(
+            "registrations_rejected",
+            "Number of rejected registrations by error",
+            registrations_rejected.clone(),
+        );
+
+        let discover_requests = Family::default();
+        sub_registry.register(
+            "discover_requests",
+            "Number of discover requests by outcome",
+            discover_requests.clone(),
+        );
+
+        let discover_registrations_returned = Histogram::new(exponential_buckets(1.0, 2.0, 10));
+        sub_registry.register(
+            "discover_registrations_returned",
+            "Number of registrations returned per served discover request",
+            discover_registrations_returned.clone(),
+        );
+
+        Self {
+            registrations,
+            unregistrations,
+            registrations_expired,
+            registrations_rejected,
+            discover_requests,
+            discover_registrations_returned,
+        }
+    }
+}
+
+impl super::Recorder<Event> for Metrics {
+    fn record(&self, event: &Event) {
+        match event {
+            Event::PeerRegistered { registration, .. } => {
+                self.registrations
+                    .get_or_create(&NamespaceLabels::new(&registration.namespace))
+                    .inc();
+            }
+            Event::PeerNotRegistered { error, .. } => {
+                self.registrations_rejected
+                    .get_or_create(&ErrorLabels {
+                        error: (*error).into(),
+                    })
+                    .inc();
+            }
+            Event::PeerUnregistered { namespace, .. } => {
+                self.unregistrations
+                    .get_or_create(&NamespaceLabels::new(namespace))
+                    .inc();
+            }
+            Event::RegistrationExpired(registration) => {
+                self.registrations_expired
+                    .get_or_create(&NamespaceLabels::new(&registration.namespace))
+                    .inc();
+            }
+            Event::DiscoverServed { registrations, .. } => {
+                self.discover_requests
+                    .get_or_create(&DiscoverLabels {
+                        outcome: DiscoverOutcome::Served,
+                    })
+                    .inc();
+                self.discover_registrations_returned
+                    .observe(registrations.len() as f64);
+            }
+            Event::DiscoverNotServed { .. } => {
+                self.discover_requests
+                    .get_or_create(&DiscoverLabels {
+                        outcome: DiscoverOutcome::Rejected,
+                    })
+                    .inc();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct NamespaceLabels {
+    namespace: String,
+}
+
+impl NamespaceLabels {
+    fn new(namespace: &libp2p_rendezvous::Namespace) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ErrorLabels {
+    error: Error,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum Error {
+    InvalidNamespace,
+    InvalidSignedPeerRecord,
+    InvalidTtl,
+    InvalidCookie,
+    NotAuthorized,
+    Internal,
+    Unavailable,
+}
+
+impl From<libp2p_rendezvous::ErrorCode> for Error {
+    fn from(error: libp2p_rendezvous::ErrorCode) -> Self {
+        use libp2p_rendezvous::ErrorCode;
+
+        match error {
+            ErrorCode::InvalidNamespace => Error::InvalidNamespace,
+            ErrorCode::InvalidSignedPeerRecord => Error::InvalidSignedPeerRecord,
+            ErrorCode::InvalidTtl => Error::InvalidTtl,
+            ErrorCode::InvalidCookie => Error::InvalidCookie,
+            ErrorCode::NotAuthorized => Error::NotAuthorized,
+            ErrorCode::InternalError => Error::Internal,
+            ErrorCode::Unavailable => Error::Unavailable,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct DiscoverLabels {
+    outcome: DiscoverOutcome,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelValue)]
+enum DiscoverOutcome {
+    Served,
+    Rejected,
+}
@@ -0,0 +1,140 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use libp2p_swarm::stream_metrics::StreamMetrics;
+use prometheus_client::collector::Collector;
+use prometheus_client::encoding::{DescriptorEncoder, EncodeMetric};
+use prometheus_client::metrics::counter::ConstCounter;
+use prometheus_client::metrics::exemplar::Exemplar;
+use prometheus_client::metrics::gauge::ConstGauge;
+use prometheus_client::metrics::MetricType;
+use prometheus_client::registry::{Registry, Unit};
+use std::collections::HashMap;
+
+/// Registers Prometheus metrics of the streams of all connections by stream protocol, as tracked
+/// by the given [`StreamMetrics`].
+///
+/// See [`libp2p_swarm::Config::with_stream_metrics`]. The metrics cover the opened, closed and
+/// currently open streams, failed negotiations and the lifetimes of streams. Failed negotiations
+/// of inbound streams are reported with the `unknown` protocol, as the protocol requested by the
+/// remote is not supported.
+pub fn register_stream_metrics(metrics: StreamMetrics, registry: &mut Registry) {
+    registry
+        .sub_registry_with_prefix("libp2p")
+        .sub_registry_with_prefix("swarm")
+        .register_collector(Box::new(Streams(metrics)));
+}
+
+#[derive(Debug)]
+struct Streams(StreamMetrics);
+
+impl Collector for Streams {
+    fn encode(&self, mut encoder: DescriptorEncoder) -> Result<(), std::fmt::Error> {
+        let mut snapshot = self.0.snapshot();
+        snapshot.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+
+        {
+            let mut family_encoder = encoder.encode_descriptor(
+                "streams_opened",
+                "Number of negotiated streams by direction and stream protocol",
+                None,
+                MetricType::Counter,
+            )?;
+            for (protocol, stats) in &snapshot {
+                for (direction, count) in [
+                    ("inbound", stats.opened_inbound()),
+                    ("outbound", stats.opened_outbound()),
+                ] {
+                    let labels = [("protocol", protocol.as_ref()), ("direction", direction)];
+                    let metric_encoder = family_encoder.encode_family(&labels)?;
+                    ConstCounter::new(count).encode(metric_encoder)?;
+                }
+            }
+        }
+
+        {
+            let mut family_encoder = encoder.encode_descriptor(
+                "streams_closed",
+                "Number of closed negotiated streams by stream protocol",
+                None,
+                MetricType::Counter,
+            )?;
+            for (protocol, stats) in &snapshot {
+                let labels = [("protocol", protocol.as_ref())];
+                let metric_encoder = family_encoder.encode_family(&labels)?;
+                ConstCounter::new(stats.closed()).encode(metric_encoder)?;
+            }
+        }
+
+        {
+            let mut family_encoder = encoder.encode_descriptor(
+                "streams_open",
+                "Number of open negotiated streams by stream protocol",
+                None,
+                MetricType::Gauge,
+            )?;
+            for (protocol, stats) in &snapshot {
+                let labels = [("protocol", protocol.as_ref())];
+                let metric_encoder = family_encoder.encode_family(&labels)?;
+                ConstGauge::new(stats.open() as i64).encode(metric_encoder)?;
+            }
+        }
+
+        {
+            let mut family_encoder = encoder.encode_descriptor(
+                "stream_negotiation_failures",
+                "Number of streams whose protocol negotiation failed or timed out by direction and stream protocol",
+                None,
+                MetricType::Counter,
+            )?;
+            for (protocol, stats) in &snapshot {
+                let labels = [("protocol", protocol.as_ref()), ("direction", "outbound")];
+                let metric_encoder = family_encoder.encode_family(&labels)?;
+                ConstCounter::new(stats.negotiation_failures()).encode(metric_encoder)?;
+            }
+            let labels = [("protocol", "unknown"), ("direction", "inbound")];
+            let metric_encoder = family_encoder.encode_family(&labels)?;
+            ConstCounter::new(self.0.inbound_negotiation_failures()).encode(metric_encoder)?;
+        }
+
+        {
+            let mut family_encoder = encoder.encode_descriptor(
+                "stream_lifetime",
+                "Lifetime of closed negotiated streams by stream protocol",
+                Some(&Unit::Seconds),
+                MetricType::Histogram,
+            )?;
+            for (protocol, stats) in &snapshot {
+                let lifetimes = stats.lifetimes();
+                let buckets = lifetimes.buckets().collect::<Vec<_>>();
+                let labels = [("protocol", protocol.as_ref())];
+                let mut metric_encoder = family_encoder.encode_family(&labels)?;
+                metric_encoder.encode_histogram::<()>(
+                    lifetimes.sum(),
+                    lifetimes.count(),
+                    &buckets,
+                    None::<&HashMap<usize, Exemplar<(), f64>>>,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -51,6 +51,9 @@ pub(crate) struct Metrics {
     dial_attempt: Counter,
     outgoing_connection_error: Family<OutgoingConnectionErrorLabels, Counter>,
 
+    dial_funnel: Family<DialFunnelLabels, Counter>,
+    dial_funnel_failures: Family<DialFunnelFailureLabels, Counter>,
+
     connections: Arc<Mutex<HashMap<ConnectionId, Instant>>>,
 }
 
@@ -135,6 +138,20 @@ impl Metrics {
             outgoing_connection_error.clone(),
         );
 
+        let dial_funnel = Family::default();
+        sub_registry.register(
+            "dial_funnel",
+            "Number of outbound dials and dialed addresses that reached each stage of connection establishment",
+            dial_funnel.clone(),
+        );
+
+        let dial_funnel_failures = Family::default();
+        sub_registry.register(
+            "dial_funnel_failures",
+            "Number of outbound dials and dialed addresses that failed, by the stage they failed at",
+            dial_funnel_failures.clone(),
+        );
+
         let connections_established = Family::default();
         sub_registry.register(
             "connections_established",
@@ -178,11 +195,50 @@ impl Metrics {
             listener_error,
             dial_attempt,
             outgoing_connection_error,
+            dial_funnel,
+            dial_funnel_failures,
             connections_establishment_duration,
             connections_duration,
             connections: Default::default(),
         }
     }
+
+    fn record_dial_funnel(&self, stage: DialFunnelStage, n: usize) {
+        self.dial_funnel
+            .get_or_create(&DialFunnelLabels { stage })
+            .inc_by(n as u64);
+    }
+
+    fn record_dial_funnel_failure(&self, stage: DialFailureStage, n: usize) {
+        if n == 0 {
+            return;
+        }
+        self.dial_funnel_failures
+            .get_or_create(&DialFunnelFailureLabels { stage })
+            .inc_by(n as u64);
+    }
+
+    fn record_dial_error_funnel(&self, error: &DialError) {
+        match error {
+            DialError::Transport(errors) => {
+                self.record_dial_funnel(DialFunnelStage::AddressAttempted, errors.len());
+                self.record_dial_funnel_failure(DialFailureStage::Transport, errors.len());
+            }
+            // The connection was authenticated, but to an unexpected peer.
+            DialError::LocalPeerId { .. } | DialError::WrongPeerId { .. } => {
+                self.record_dial_funnel(DialFunnelStage::AddressAttempted, 1);
+                self.record_dial_funnel(DialFunnelStage::Upgraded, 1);
+                self.record_dial_funnel_failure(DialFailureStage::PeerId, 1);
+            }
+            DialError::NoAddresses | DialError::DialPeerConditionFalse(_) => {
+                self.record_dial_funnel_failure(DialFailureStage::Dial, 1)
+            }
+            DialError::Denied { .. } => {
+                self.record_dial_funnel_failure(DialFailureStage::Denied, 1)
+            }
+            DialError::Aborted => self.record_dial_funnel_failure(DialFailureStage::Aborted, 1),
+        }
+    }
 }
 
 impl<TBvEv> super::Recorder<SwarmEvent<TBvEv>> for Metrics {
@@ -193,8 +249,20 @@ impl<TBvEv> super::Recorder<SwarmEvent<TBvEv>> for Metrics {
                 endpoint,
                 established_in: time_taken,
                 connection_id,
+                concurrent_dial_errors,
                 ..
             } => {
+                if endpoint.is_dialer() {
+                    let failed_addresses = concurrent_dial_errors.as_ref().map_or(0, Vec::len);
+                    self.record_dial_funnel(
+                        DialFunnelStage::AddressAttempted,
+                        failed_addresses + 1,
+                    );
+                    self.record_dial_funnel_failure(DialFailureStage::Transport, failed_addresses);
+                    self.record_dial_funnel(DialFunnelStage::Upgraded, 1);
+                    self.record_dial_funnel(DialFunnelStage::Established, 1);
+                }
+
                 let labels = ConnectionLabels {
                     role: endpoint.into(),
                     protocols: protocol_stack::as_string(endpoint.get_remote_address()),
@@ -262,6 +330,8 @@ impl<TBvEv> super::Recorder<SwarmEvent<TBvEv>> for Metrics {
                         .inc();
                 };
 
+                self.record_dial_error_funnel(error);
+
                 match error {
                     DialError::Transport(errors) => {
                         for (_multiaddr, error) in errors {
@@ -315,6 +385,7 @@ impl<TBvEv> super::Recorder<SwarmEvent<TBvEv>> for Metrics {
             }
             SwarmEvent::Dialing { .. } => {
                 self.dial_attempt.inc();
+                self.record_dial_funnel(DialFunnelStage::Requested, 1);
             }
             SwarmEvent::NewExternalAddrCandidate { address } => {
                 self.external_addr_candidates
@@ -323,7 +394,7 @@ impl<TBvEv> super::Recorder<SwarmEvent<TBvEv>> for Metrics {
                     })
                     .inc();
             }
-            SwarmEvent::ExternalAddrConfirmed { address } => {
+            SwarmEvent::ExternalAddrConfirmed { address, .. } => {
                 self.external_addr_confirmed
                     .get_or_create(&AddressLabels {
                         protocols: protocol_stack::as_string(address),
@@ -359,6 +430,7 @@ struct ConnectionClosedLabels {
 enum ConnectionError {
     Io,
     KeepAliveTimeout,
+    HandlerPanic,
 }
 
 impl From<&libp2p_swarm::ConnectionError> for ConnectionError {
@@ -366,6 +438,7 @@ impl From<&libp2p_swarm::ConnectionError> for ConnectionError {
         match value {
             libp2p_swarm::ConnectionError::IO(_) => ConnectionError::Io,
             libp2p_swarm::ConnectionError::KeepAliveTimeout => ConnectionError::KeepAliveTimeout,
+            libp2p_swarm::ConnectionError::HandlerPanic(_) => ConnectionError::HandlerPanic,
         }
     }
 }
@@ -414,6 +487,50 @@ enum OutgoingConnectionError {
     Denied,
 }
 
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+struct DialFunnelLabels {
+    stage: DialFunnelStage,
+}
+
+/// The stages of an outbound dial, from the dial request to the established connection.
+///
+/// [`DialFunnelStage::AddressAttempted`] and [`DialFunnelStage::Upgraded`] are counted per dialed
+/// address, the other stages per dial.
+#[derive(EncodeLabelValue, Hash, Clone, Eq, PartialEq, Copy, Debug)]
+enum DialFunnelStage {
+    /// A behaviour requested the dial.
+    Requested,
+    /// The transport attempted to dial an address.
+    AddressAttempted,
+    /// The transport connected to an address and the connection was authenticated and multiplexed.
+    Upgraded,
+    /// The connection was established and handed to the behaviours.
+    Established,
+}
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+struct DialFunnelFailureLabels {
+    stage: DialFailureStage,
+}
+
+/// The stage at which an outbound dial or the dial of a single address failed.
+///
+/// The swarm dials through the fully upgraded transport, thus failures of the security or
+/// multiplexer upgrade are reported as [`DialFailureStage::Transport`] failures.
+#[derive(EncodeLabelValue, Hash, Clone, Eq, PartialEq, Copy, Debug)]
+enum DialFailureStage {
+    /// No address was dialed, e.g. because none was known or the dial condition was false.
+    Dial,
+    /// Dialing or upgrading an address failed.
+    Transport,
+    /// The upgraded connection was authenticated as an unexpected peer.
+    PeerId,
+    /// A behaviour denied the connection.
+    Denied,
+    /// The dial was aborted.
+    Aborted,
+}
+
 #[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
 struct IncomingConnectionErrorLabels {
     error: IncomingConnectionError,
@@ -446,3 +563,102 @@ impl From<&libp2p_swarm::ListenError> for IncomingConnectionError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Recorder;
+    use libp2p_core::transport::{PortUse, TransportError};
+    use libp2p_core::{ConnectedPoint, Endpoint, Multiaddr};
+    use libp2p_identity::PeerId;
+    use std::num::NonZeroU32;
+    use std::time::Duration;
+
+    impl Metrics {
+        fn dial_funnel(&self, stage: DialFunnelStage) -> u64 {
+            self.dial_funnel
+                .get_or_create(&DialFunnelLabels { stage })
+                .get()
+        }
+
+        fn dial_funnel_failures(&self, stage: DialFailureStage) -> u64 {
+            self.dial_funnel_failures
+                .get_or_create(&DialFunnelFailureLabels { stage })
+                .get()
+        }
+    }
+
+    #[test]
+    fn dial_funnel() {
+        let metrics = Metrics::new(&mut Registry::default());
+        let peer_id = PeerId::random();
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+        let transport_error = || {
+            (
+                addr.clone(),
+                TransportError::MultiaddrNotSupported(addr.clone()),
+            )
+        };
+
+        // A dial whose only address fails.
+        let connection_id = ConnectionId::new_unchecked(0);
+        Recorder::<SwarmEvent<()>>::record(
+            &metrics,
+            &SwarmEvent::Dialing {
+                peer_id: Some(peer_id),
+                connection_id,
+            },
+        );
+        Recorder::<SwarmEvent<()>>::record(
+            &metrics,
+            &SwarmEvent::OutgoingConnectionError {
+                connection_id,
+                peer_id: Some(peer_id),
+                error: DialError::Transport(vec![transport_error()]),
+            },
+        );
+
+        // A dial that succeeds on the second of its addresses.
+        let connection_id = ConnectionId::new_unchecked(1);
+        Recorder::<SwarmEvent<()>>::record(
+            &metrics,
+            &SwarmEvent::Dialing {
+                peer_id: Some(peer_id),
+                connection_id,
+            },
+        );
+        Recorder::<SwarmEvent<()>>::record(
+            &metrics,
+            &SwarmEvent::ConnectionEstablished {
+                peer_id,
+                connection_id,
+                endpoint: ConnectedPoint::Dialer {
+                    address: addr.clone(),
+                    role_override: Endpoint::Dialer,
+                    port_use: PortUse::Reuse,
+                },
+                num_established: NonZeroU32::new(1).unwrap(),
+                concurrent_dial_errors: Some(vec![transport_error()]),
+                established_in: Duration::from_millis(10),
+            },
+        );
+
+        // A dial without any address to dial.
+        Recorder::<SwarmEvent<()>>::record(
+            &metrics,
+            &SwarmEvent::OutgoingConnectionError {
+                connection_id: ConnectionId::new_unchecked(2),
+                peer_id: Some(peer_id),
+                error: DialError::NoAddresses,
+            },
+        );
+
+        assert_eq!(metrics.dial_funnel(DialFunnelStage::Requested), 2);
+        assert_eq!(metrics.dial_funnel(DialFunnelStage::AddressAttempted), 3);
+        assert_eq!(metrics.dial_funnel(DialFunnelStage::Upgraded), 1);
+        assert_eq!(metrics.dial_funnel(DialFunnelStage::Established), 1);
+        assert_eq!(metrics.dial_funnel_failures(DialFailureStage::Transport), 2);
+        assert_eq!(metrics.dial_funnel_failures(DialFailureStage::Dial), 1);
+        assert_eq!(metrics.dial_funnel_failures(DialFailureStage::PeerId), 0);
+    }
+}
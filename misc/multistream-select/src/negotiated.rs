@@ -85,8 +85,11 @@ where
 }
 
 impl<TInner> Negotiated<TInner> {
-    /// Creates a `Negotiated` in state [`State::Completed`].
-    pub(crate) fn completed(io: TInner) -> Self {
+    /// Creates a `Negotiated` whose protocol negotiation has already completed.
+    ///
+    /// This is also used for protocols agreed on out of band, e.g. a stream multiplexer
+    /// selected during the security handshake, which skip the negotiation altogether.
+    pub fn completed(io: TInner) -> Self {
         Negotiated {
             state: State::Completed { io },
         }
@@ -0,0 +1,77 @@
+use libp2p_core::upgrade::DeniedUpgrade;
+use libp2p_swarm::handler::{
+    ConnectionEvent, FullyNegotiatedInbound, FullyNegotiatedOutbound, ProtocolsChange,
+};
+use libp2p_swarm::{ConnectionHandlerEvent, StreamProtocol, SubstreamProtocol};
+use std::collections::VecDeque;
+use std::task::{Context, Poll};
+use void::Void;
+
+/// A change in the protocols supported by the remote of a connection.
+#[derive(Debug)]
+pub enum ProtocolsEvent {
+    Added(Vec<StreamProtocol>),
+    Removed(Vec<StreamProtocol>),
+}
+
+/// A [`ConnectionHandler`](libp2p_swarm::ConnectionHandler) that doesn't handle any protocols
+/// but reports the protocols supported by the remote.
+#[derive(Debug, Default)]
+pub struct Handler {
+    events: VecDeque<ProtocolsEvent>,
+}
+
+impl libp2p_swarm::ConnectionHandler for Handler {
+    type FromBehaviour = Void;
+    type ToBehaviour = ProtocolsEvent;
+    type InboundProtocol = DeniedUpgrade;
+    type OutboundProtocol = DeniedUpgrade;
+    type InboundOpenInfo = ();
+    type OutboundOpenInfo = Void;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        SubstreamProtocol::new(DeniedUpgrade, ())
+    }
+
+    fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
+        void::unreachable(event)
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<
+        ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
+    > {
+        match self.events.pop_front() {
+            Some(event) => Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(event)),
+            None => Poll::Pending,
+        }
+    }
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<
+            Self::InboundProtocol,
+            Self::OutboundProtocol,
+            Self::InboundOpenInfo,
+            Self::OutboundOpenInfo,
+        >,
+    ) {
+        match event {
+            ConnectionEvent::FullyNegotiatedInbound(FullyNegotiatedInbound {
+                protocol, ..
+            }) => void::unreachable(protocol),
+            ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound {
+                protocol, ..
+            }) => void::unreachable(protocol),
+            ConnectionEvent::RemoteProtocolsChange(ProtocolsChange::Added(added)) => self
+                .events
+                .push_back(ProtocolsEvent::Added(added.cloned().collect())),
+            ConnectionEvent::RemoteProtocolsChange(ProtocolsChange::Removed(removed)) => self
+                .events
+                .push_back(ProtocolsEvent::Removed(removed.cloned().collect())),
+            _ => {}
+        }
+    }
+}
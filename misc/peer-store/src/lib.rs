@@ -0,0 +1,427 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! A peer store (address book) for libp2p.
+//!
+//! The [`Behaviour`] records the addresses, supported protocols, agent versions and last-seen
+//! timestamps of peers and provides the known addresses of a peer when it is dialed.
+//!
+//! Addresses are learned from the [`FromSwarm::NewExternalAddrOfPeer`] events reported by other
+//! behaviours, e.g. identify or Kademlia, and from outbound connections. The protocols supported
+//! by a peer are learned from its connections. Additionally, the events of identify and Kademlia
+//! can be passed to `Behaviour::on_identify_event` and `Behaviour::on_kad_event` with the
+//! `identify` and `kad` features respectively.
+//!
+//! Records of peers that were not seen for [`Config::with_record_ttl`] are garbage-collected
+//! periodically, unless the peer is connected. The store can be persisted across restarts via
+//! [`Behaviour::save`] and [`Behaviour::load`].
+
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+mod handler;
+mod store;
+
+pub use store::{PeerRecord, PeerStore};
+
+use futures::FutureExt;
+use futures_timer::Delay;
+use libp2p_core::transport::PortUse;
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_swarm::{
+    behaviour::{ConnectionClosed, ConnectionEstablished, DialFailure, NewExternalAddrOfPeer},
+    ConnectionDenied, ConnectionId, DialError, FromSwarm, NetworkBehaviour, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+use web_time::SystemTime;
+
+/// The configuration of a [`Behaviour`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    record_ttl: Duration,
+    gc_interval: Duration,
+    max_peers: usize,
+    max_addresses_per_peer: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            record_ttl: Duration::from_secs(24 * 60 * 60),
+            gc_interval: Duration::from_secs(5 * 60),
+            max_peers: 1024,
+            max_addresses_per_peer: 8,
+        }
+    }
+}
+
+impl Config {
+    /// Sets how long the record of a peer that is not connected is kept after it was last seen.
+    ///
+    /// Defaults to 24 hours.
+    pub fn with_record_ttl(mut self, ttl: Duration) -> Self {
+        self.record_ttl = ttl;
+        self
+    }
+
+    /// Sets the interval in which stale records are garbage-collected.
+    ///
+    /// Defaults to 5 minutes.
+    pub fn with_gc_interval(mut self, interval: Duration) -> Self {
+        self.gc_interval = interval;
+        self
+    }
+
+    /// Sets the maximum number of peers to keep records of.
+    ///
+    /// On garbage collection, the least recently seen peers that are not connected are removed
+    /// until the limit is met. Defaults to 1024.
+    pub fn with_max_peers(mut self, max_peers: usize) -> Self {
+        self.max_peers = max_peers;
+        self
+    }
+
+    /// Sets the maximum number of addresses to keep per peer, dropping the least recently
+    /// reported ones first.
+    ///
+    /// Defaults to 8.
+    pub fn with_max_addresses_per_peer(mut self, max_addresses: usize) -> Self {
+        self.max_addresses_per_peer = max_addresses;
+        self
+    }
+}
+
+/// The events emitted by the [`Behaviour`].
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A record was created for a peer that was not known before.
+    PeerDiscovered { peer_id: PeerId },
+    /// The records of the given peers were garbage-collected.
+    PeersRemoved { peers: Vec<PeerId> },
+}
+
+/// A [`NetworkBehaviour`] maintaining a [`PeerStore`].
+///
+/// When a peer is dialed, its known addresses are added to the addresses of the dial. Addresses
+/// for which the dial reached a different peer are removed.
+pub struct Behaviour {
+    config: Config,
+    store: PeerStore,
+    connected: HashSet<PeerId>,
+    events: VecDeque<Event>,
+    waker: Option<Waker>,
+    next_gc: Delay,
+}
+
+impl Behaviour {
+    /// Creates a new [`Behaviour`] with an empty store.
+    pub fn new(config: Config) -> Self {
+        Self {
+            store: PeerStore::new(config.max_addresses_per_peer),
+            next_gc: Delay::new(config.gc_interval),
+            config,
+            connected: HashSet::new(),
+            events: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    /// The records of all known peers.
+    pub fn store(&self) -> &PeerStore {
+        &self.store
+    }
+
+    /// Records `address` as the most recent address of the peer.
+    pub fn add_address(&mut self, peer_id: PeerId, address: Multiaddr) {
+        if self.store.add_address(peer_id, address, SystemTime::now()) {
+            self.push_event(Event::PeerDiscovered { peer_id });
+        }
+    }
+
+    /// Removes `address` from the record of the peer.
+    pub fn remove_address(&mut self, peer_id: &PeerId, address: &Multiaddr) {
+        self.store.remove_address(peer_id, address);
+    }
+
+    /// Removes the record of the peer, returning it if the peer was known.
+    pub fn remove_peer(&mut self, peer_id: &PeerId) -> Option<PeerRecord> {
+        self.store.remove(peer_id)
+    }
+
+    /// Records the listen addresses, protocols and agent version of a peer received via
+    /// identify.
+    #[cfg(feature = "identify")]
+    pub fn on_identify_event(&mut self, event: &libp2p_identify::Event) {
+        let libp2p_identify::Event::Received { peer_id, info, .. } = event else {
+            return;
+        };
+
+        for address in &info.listen_addrs {
+            self.add_address(*peer_id, address.clone());
+        }
+        let (record, is_new) = self.store.touch(*peer_id, SystemTime::now());
+        PeerStore::set_protocols(record, info.protocols.clone());
+        PeerStore::set_agent_version(record, info.agent_version.clone());
+        if is_new {
+            self.push_event(Event::PeerDiscovered { peer_id: *peer_id });
+        }
+    }
+
+    /// Records the addresses of a peer added to or updated in the Kademlia routing table.
+    #[cfg(feature = "kad")]
+    pub fn on_kad_event(&mut self, event: &libp2p_kad::Event) {
+        let libp2p_kad::Event::RoutingUpdated {
+            peer, addresses, ..
+        } = event
+        else {
+            return;
+        };
+
+        for address in addresses.iter() {
+            self.add_address(*peer, address.clone());
+        }
+    }
+
+    /// Writes the store to the file at `path`, replacing its content.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    /// Reads the records of the file at `path` written by [`Behaviour::save`] into the store,
+    /// returning the number of records that were added or replaced.
+    ///
+    /// A record only replaces the one of a known peer if it was seen more recently.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<usize> {
+        self.read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Writes the store to `writer` in a line-based text format.
+    pub fn write_to(&self, writer: impl Write) -> io::Result<()> {
+        self.store.write_to(writer)
+    }
+
+    /// Reads records written by [`Behaviour::write_to`] into the store, see [`Behaviour::load`].
+    pub fn read_from(&mut self, reader: impl BufRead) -> io::Result<usize> {
+        self.store.read_from(reader)
+    }
+
+    fn push_event(&mut self, event: Event) {
+        self.events.push_back(event);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn collect_garbage(&mut self) {
+        let cutoff = SystemTime::now()
+            .checked_sub(self.config.record_ttl)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let connected = &self.connected;
+        let mut removed = self
+            .store
+            .remove_stale(cutoff, |peer_id| connected.contains(peer_id));
+        removed.extend(
+            self.store
+                .evict(self.config.max_peers, |peer_id| connected.contains(peer_id)),
+        );
+
+        if !removed.is_empty() {
+            tracing::debug!(count=%removed.len(), "Removed peer records");
+            self.push_event(Event::PeersRemoved { peers: removed });
+        }
+    }
+
+    fn on_connection_established(&mut self, peer_id: PeerId, address: Option<&Multiaddr>) {
+        self.connected.insert(peer_id);
+        match address {
+            Some(address) => self.add_address(peer_id, address.clone()),
+            None => {
+                if self.store.touch(peer_id, SystemTime::now()).1 {
+                    self.push_event(Event::PeerDiscovered { peer_id });
+                }
+            }
+        }
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = handler::Handler;
+    type ToSwarm = Event;
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        _: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        let Some(record) = maybe_peer.and_then(|peer_id| self.store.get(&peer_id)) else {
+            return Ok(vec![]);
+        };
+
+        Ok(record
+            .addresses()
+            .iter()
+            .filter(|address| !addresses.contains(address))
+            .cloned()
+            .collect())
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(handler::Handler::default())
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+        _: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(handler::Handler::default())
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::NewExternalAddrOfPeer(NewExternalAddrOfPeer { peer_id, addr }) => {
+                self.add_address(peer_id, addr.clone());
+            }
+            FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                peer_id, endpoint, ..
+            }) => {
+                let address = endpoint.is_dialer().then(|| endpoint.get_remote_address());
+                self.on_connection_established(peer_id, address);
+            }
+            FromSwarm::ConnectionClosed(ConnectionClosed {
+                peer_id,
+                remaining_established,
+                ..
+            }) => {
+                if self.store.touch(peer_id, SystemTime::now()).1 {
+                    self.push_event(Event::PeerDiscovered { peer_id });
+                }
+                if remaining_established == 0 {
+                    self.connected.remove(&peer_id);
+                }
+            }
+            FromSwarm::DialFailure(DialFailure {
+                peer_id: Some(peer_id),
+                error: DialError::WrongPeerId { endpoint, .. },
+                ..
+            }) => {
+                self.store
+                    .remove_address(&peer_id, endpoint.get_remote_address());
+            }
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        let (record, is_new) = self.store.touch(peer_id, SystemTime::now());
+        match event {
+            handler::ProtocolsEvent::Added(protocols) => {
+                PeerStore::add_protocols(record, &protocols)
+            }
+            handler::ProtocolsEvent::Removed(protocols) => {
+                PeerStore::remove_protocols(record, &protocols)
+            }
+        }
+        if is_new {
+            self.push_event(Event::PeerDiscovered { peer_id });
+        }
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Poll::Ready(ToSwarm::GenerateEvent(event));
+            }
+            if self.next_gc.poll_unpin(cx).is_pending() {
+                self.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            self.next_gc.reset(self.config.gc_interval);
+            self.collect_garbage();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_and_excess_records_are_removed() {
+        let mut behaviour = Behaviour::new(
+            Config::default()
+                .with_record_ttl(Duration::from_secs(60))
+                .with_max_peers(1),
+        );
+        let connected = PeerId::random();
+        let stale = PeerId::random();
+        let recent = PeerId::random();
+        let address: Multiaddr = "/ip4/192.0.2.1/tcp/4001".parse().unwrap();
+        let now = SystemTime::now();
+
+        behaviour.on_connection_established(connected, None);
+        behaviour.store.touch(stale, now - Duration::from_secs(120));
+        behaviour
+            .store
+            .add_address(recent, address.clone(), now - Duration::from_secs(30));
+        behaviour.events.clear();
+
+        behaviour.collect_garbage();
+
+        assert!(behaviour.store().get(&connected).is_some());
+        assert!(behaviour.store().get(&stale).is_none());
+        assert!(behaviour.store().get(&recent).is_none());
+        match behaviour.events.pop_front() {
+            Some(Event::PeersRemoved { mut peers }) => {
+                peers.sort();
+                let mut expected = vec![stale, recent];
+                expected.sort();
+                assert_eq!(peers, expected);
+            }
+            e => panic!("unexpected event {e:?}"),
+        }
+    }
+}
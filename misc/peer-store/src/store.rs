@@ -0,0 +1,350 @@
+use libp2p_core::Multiaddr;
+use libp2p_identity::PeerId;
+use libp2p_swarm::StreamProtocol;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+use web_time::SystemTime;
+
+/// What is known about a single peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerRecord {
+    addresses: Vec<Multiaddr>,
+    protocols: Vec<StreamProtocol>,
+    agent_version: Option<String>,
+    last_seen: SystemTime,
+}
+
+impl PeerRecord {
+    fn new(last_seen: SystemTime) -> Self {
+        Self {
+            addresses: Vec::new(),
+            protocols: Vec::new(),
+            agent_version: None,
+            last_seen,
+        }
+    }
+
+    /// The known addresses of the peer, the most recently reported first.
+    pub fn addresses(&self) -> &[Multiaddr] {
+        &self.addresses
+    }
+
+    /// The protocols the peer supports.
+    pub fn protocols(&self) -> &[StreamProtocol] {
+        &self.protocols
+    }
+
+    /// Whether the peer is known to support `protocol`.
+    pub fn supports(&self, protocol: &str) -> bool {
+        self.protocols.iter().any(|p| p.as_ref() == protocol)
+    }
+
+    /// The agent version reported by the peer via identify.
+    pub fn agent_version(&self) -> Option<&str> {
+        self.agent_version.as_deref()
+    }
+
+    /// When the record was last updated or a connection to the peer was established or closed.
+    pub fn last_seen(&self) -> SystemTime {
+        self.last_seen
+    }
+}
+
+/// The records of all known peers, see [`Behaviour::store`](crate::Behaviour::store).
+#[derive(Debug, Clone)]
+pub struct PeerStore {
+    records: HashMap<PeerId, PeerRecord>,
+    max_addresses_per_peer: usize,
+}
+
+impl PeerStore {
+    pub(crate) fn new(max_addresses_per_peer: usize) -> Self {
+        Self {
+            records: HashMap::new(),
+            max_addresses_per_peer,
+        }
+    }
+
+    /// The record of the given peer.
+    pub fn get(&self, peer_id: &PeerId) -> Option<&PeerRecord> {
+        self.records.get(peer_id)
+    }
+
+    /// Iterates over the records of all known peers.
+    pub fn iter(&self) -> impl Iterator<Item = (&PeerId, &PeerRecord)> {
+        self.records.iter()
+    }
+
+    /// Iterates over the peers known to support `protocol`.
+    pub fn peers_supporting<'a>(&'a self, protocol: &'a str) -> impl Iterator<Item = &'a PeerId> {
+        self.records
+            .iter()
+            .filter(move |(_, record)| record.supports(protocol))
+            .map(|(peer_id, _)| peer_id)
+    }
+
+    /// The number of known peers.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether no peer is known.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns the record of the peer, updating its last-seen timestamp and creating it if the
+    /// peer was not known yet, as indicated by the returned `bool`.
+    pub(crate) fn touch(&mut self, peer_id: PeerId, now: SystemTime) -> (&mut PeerRecord, bool) {
+        match self.records.entry(peer_id) {
+            Entry::Occupied(entry) => {
+                let record = entry.into_mut();
+                record.last_seen = record.last_seen.max(now);
+                (record, false)
+            }
+            Entry::Vacant(entry) => (entry.insert(PeerRecord::new(now)), true),
+        }
+    }
+
+    /// Records `address` as the most recent address of the peer, returning whether the peer was
+    /// not known yet.
+    pub(crate) fn add_address(
+        &mut self,
+        peer_id: PeerId,
+        address: Multiaddr,
+        now: SystemTime,
+    ) -> bool {
+        let max_addresses = self.max_addresses_per_peer;
+        let (record, is_new) = self.touch(peer_id, now);
+        record.addresses.retain(|a| a != &address);
+        record.addresses.insert(0, address);
+        record.addresses.truncate(max_addresses);
+
+        is_new
+    }
+
+    pub(crate) fn remove_address(&mut self, peer_id: &PeerId, address: &Multiaddr) {
+        if let Some(record) = self.records.get_mut(peer_id) {
+            record.addresses.retain(|a| a != address);
+        }
+    }
+
+    pub(crate) fn add_protocols<'a>(
+        record: &mut PeerRecord,
+        protocols: impl IntoIterator<Item = &'a StreamProtocol>,
+    ) {
+        for protocol in protocols {
+            if !record.protocols.contains(protocol) {
+                record.protocols.push(protocol.clone());
+            }
+        }
+    }
+
+    pub(crate) fn set_protocols(record: &mut PeerRecord, protocols: Vec<StreamProtocol>) {
+        record.protocols = protocols;
+    }
+
+    pub(crate) fn remove_protocols<'a>(
+        record: &mut PeerRecord,
+        protocols: impl IntoIterator<Item = &'a StreamProtocol>,
+    ) {
+        for protocol in protocols {
+            record.protocols.retain(|p| p != protocol);
+        }
+    }
+
+    pub(crate) fn set_agent_version(record: &mut PeerRecord, agent_version: String) {
+        record.agent_version = Some(agent_version);
+    }
+
+    pub(crate) fn remove(&mut self, peer_id: &PeerId) -> Option<PeerRecord> {
+        self.records.remove(peer_id)
+    }
+
+    /// Removes the records last seen before `cutoff`, unless `keep` returns `true` for the peer.
+    pub(crate) fn remove_stale(
+        &mut self,
+        cutoff: SystemTime,
+        keep: impl Fn(&PeerId) -> bool,
+    ) -> Vec<PeerId> {
+        let stale = self
+            .records
+            .iter()
+            .filter(|(peer_id, record)| record.last_seen < cutoff && !keep(peer_id))
+            .map(|(peer_id, _)| *peer_id)
+            .collect::<Vec<_>>();
+        for peer_id in &stale {
+            self.records.remove(peer_id);
+        }
+
+        stale
+    }
+
+    /// Removes the least recently seen records until at most `max_peers` remain, never removing
+    /// peers for which `keep` returns `true`.
+    pub(crate) fn evict(
+        &mut self,
+        max_peers: usize,
+        keep: impl Fn(&PeerId) -> bool,
+    ) -> Vec<PeerId> {
+        let excess = self.records.len().saturating_sub(max_peers);
+        if excess == 0 {
+            return Vec::new();
+        }
+
+        let mut candidates = self
+            .records
+            .iter()
+            .filter(|(peer_id, _)| !keep(peer_id))
+            .map(|(peer_id, record)| (record.last_seen, *peer_id))
+            .collect::<Vec<_>>();
+        candidates.sort_unstable();
+        let evicted = candidates
+            .into_iter()
+            .take(excess)
+            .map(|(_, peer_id)| peer_id)
+            .collect::<Vec<_>>();
+        for peer_id in &evicted {
+            self.records.remove(peer_id);
+        }
+
+        evicted
+    }
+
+    /// Writes all records in the line-based format read by [`PeerStore::read_from`].
+    pub(crate) fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        for (peer_id, record) in &self.records {
+            let last_seen = record
+                .last_seen
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default();
+            writeln!(writer, "peer {peer_id} {}", last_seen.as_secs())?;
+            for address in &record.addresses {
+                writeln!(writer, "addr {address}")?;
+            }
+            for protocol in &record.protocols {
+                writeln!(writer, "protocol {protocol}")?;
+            }
+            if let Some(agent_version) = record.agent_version.as_deref() {
+                let agent_version = agent_version.replace(['\r', '\n'], " ");
+                if !agent_version.trim().is_empty() {
+                    writeln!(writer, "agent {}", agent_version.trim())?;
+                }
+            }
+        }
+
+        writer.flush()
+    }
+
+    /// Reads records written by [`PeerStore::write_to`], returning the number of records that
+    /// were added or replaced.
+    ///
+    /// A record only replaces the one of a known peer if it was seen more recently.
+    pub(crate) fn read_from(&mut self, reader: impl BufRead) -> io::Result<usize> {
+        let mut records = Vec::<(PeerId, PeerRecord)>::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (kind, value) = line
+                .split_once(' ')
+                .ok_or_else(|| invalid_data(format!("malformed line `{line}`")))?;
+            if kind == "peer" {
+                let (peer_id, last_seen) = value
+                    .split_once(' ')
+                    .ok_or_else(|| invalid_data(format!("malformed line `{line}`")))?;
+                let peer_id = peer_id.parse::<PeerId>().map_err(invalid_data)?;
+                let last_seen = last_seen.parse::<u64>().map_err(invalid_data)?;
+                records.push((
+                    peer_id,
+                    PeerRecord::new(SystemTime::UNIX_EPOCH + Duration::from_secs(last_seen)),
+                ));
+                continue;
+            }
+
+            let Some((_, record)) = records.last_mut() else {
+                return Err(invalid_data(format!("`{kind}` before the first `peer`")));
+            };
+            match kind {
+                "addr" => record
+                    .addresses
+                    .push(value.parse::<Multiaddr>().map_err(invalid_data)?),
+                "protocol" => record
+                    .protocols
+                    .push(StreamProtocol::try_from_owned(value.to_owned()).map_err(invalid_data)?),
+                "agent" => record.agent_version = Some(value.to_owned()),
+                _ => return Err(invalid_data(format!("unknown entry `{kind}`"))),
+            }
+        }
+
+        let mut num_loaded = 0;
+        for (peer_id, mut record) in records {
+            record.addresses.truncate(self.max_addresses_per_peer);
+            match self.records.entry(peer_id) {
+                Entry::Occupied(entry) if entry.get().last_seen >= record.last_seen => continue,
+                Entry::Occupied(mut entry) => {
+                    entry.insert(record);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(record);
+                }
+            }
+            num_loaded += 1;
+        }
+
+        Ok(num_loaded)
+    }
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_survive_round_trip() {
+        let mut store = PeerStore::new(2);
+        let peer_id = PeerId::random();
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        for address in [
+            "/ip4/192.0.2.1/tcp/4001",
+            "/ip4/192.0.2.1/udp/4001/quic-v1",
+            "/ip6/2001:db8::1/tcp/4001",
+        ] {
+            store.add_address(peer_id, address.parse().unwrap(), now);
+        }
+        let (record, is_new) = store.touch(peer_id, now);
+        assert!(!is_new);
+        PeerStore::add_protocols(record, &[StreamProtocol::new("/ipfs/id/1.0.0")]);
+        PeerStore::set_agent_version(record, "rust-libp2p/0.54.1\n".to_owned());
+
+        let record = store.get(&peer_id).unwrap().clone();
+        assert_eq!(
+            record.addresses(),
+            &[
+                "/ip6/2001:db8::1/tcp/4001".parse::<Multiaddr>().unwrap(),
+                "/ip4/192.0.2.1/udp/4001/quic-v1".parse().unwrap(),
+            ]
+        );
+
+        let mut buffer = Vec::new();
+        store.write_to(&mut buffer).unwrap();
+        let mut loaded = PeerStore::new(2);
+        assert_eq!(loaded.read_from(buffer.as_slice()).unwrap(), 1);
+
+        let loaded = loaded.get(&peer_id).unwrap();
+        assert_eq!(loaded.addresses(), record.addresses());
+        assert!(loaded.supports("/ipfs/id/1.0.0"));
+        assert_eq!(loaded.agent_version(), Some("rust-libp2p/0.54.1"));
+        assert_eq!(loaded.last_seen(), now);
+    }
+}
@@ -29,7 +29,7 @@ use futures_timer::Delay;
 use libp2p_core::Multiaddr;
 use libp2p_identity::PeerId;
 use libp2p_request_response::{self as request_response, OutboundFailure, OutboundRequestId};
-use libp2p_swarm::{ConnectionId, ListenAddresses, ToSwarm};
+use libp2p_swarm::{ConnectionId, ExternalAddressSource, ListenAddresses, ToSwarm};
 use rand::{seq::SliceRandom, thread_rng};
 use std::{
     collections::{HashMap, HashSet, VecDeque},
@@ -144,7 +144,10 @@ impl<'a> HandleInnerEvent for AsClient<'a> {
                 }
 
                 if let Ok(address) = response.result {
-                    actions.push_back(ToSwarm::ExternalAddrConfirmed(address));
+                    actions.push_back(ToSwarm::ExternalAddrConfirmedWithSource {
+                        address,
+                        source: ExternalAddressSource::AutoNat,
+                    });
                 }
 
                 actions
@@ -23,6 +23,8 @@ use libp2p_swarm::StreamProtocol;
 pub mod client;
 pub(crate) mod protocol;
 pub mod server;
+#[cfg(any(test, feature = "wire-compat"))]
+pub mod wire_compat;
 
 pub(crate) mod generated {
     #![allow(unreachable_pub)]
@@ -1,5 +1,5 @@
 mod behaviour;
-mod handler;
+pub(crate) mod handler;
 
 pub use behaviour::Event;
-pub use behaviour::{Behaviour, Config};
+pub use behaviour::{Behaviour, Config, Probe, ProbeOutcome};
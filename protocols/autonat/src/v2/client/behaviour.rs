@@ -11,7 +11,8 @@ use libp2p_core::{transport::PortUse, Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_swarm::{
     behaviour::ConnectionEstablished, ConnectionClosed, ConnectionDenied, ConnectionHandler,
-    ConnectionId, FromSwarm, NetworkBehaviour, NewExternalAddrCandidate, NotifyHandler, ToSwarm,
+    ConnectionId, ExternalAddressSource, FromSwarm, NetworkBehaviour, NewExternalAddrCandidate,
+    NotifyHandler, ToSwarm,
 };
 use rand::prelude::*;
 use rand_core::OsRng;
@@ -73,6 +74,7 @@ where
     address_candidates: HashMap<Multiaddr, AddressInfo>,
     next_tick: Delay,
     peer_info: HashMap<ConnectionId, ConnectionInfo>,
+    probe_callback: Option<Box<dyn FnMut(&Probe) + Send>>,
 }
 
 impl<R> NetworkBehaviour for Behaviour<R>
@@ -196,11 +198,20 @@ where
                         %nonce,
                         "Server reported reachbility but we never received a dial-back"
                     );
+                    let (tested_addr, bytes_sent) = address;
+                    self.report_probe(
+                        peer_id,
+                        Some(tested_addr),
+                        ProbeOutcome::MissingDialBack { bytes_sent },
+                    );
                     return;
                 }
 
                 self.pending_events
-                    .push_back(ToSwarm::ExternalAddrConfirmed(address.0.clone()));
+                    .push_back(ToSwarm::ExternalAddrConfirmedWithSource {
+                        address: address.0.clone(),
+                        source: ExternalAddressSource::AutoNat,
+                    });
 
                 (address, Ok(()))
             }
@@ -210,7 +221,9 @@ where
                     .expect("inconsistent state")
                     .supports_autonat = false;
 
+                let tested_addr = self.addr_with_nonce(nonce);
                 self.reset_status_to(nonce, TestStatus::Untested); // Reset so it will be tried again.
+                self.report_probe(peer_id, tested_addr, ProbeOutcome::UnsupportedProtocol);
 
                 return;
             }
@@ -221,7 +234,9 @@ where
                     "Failed to complete AutoNAT probe: {e}"
                 );
 
+                let tested_addr = self.addr_with_nonce(nonce);
                 self.reset_status_to(nonce, TestStatus::Untested); // Reset so it will be tried again.
+                self.report_probe(peer_id, tested_addr, ProbeOutcome::Failed(e));
 
                 return;
             }
@@ -236,11 +251,21 @@ where
             }
         };
 
+        let result = result.map_err(|e| Error { inner: e });
+        let outcome = match &result {
+            Ok(()) => ProbeOutcome::Reachable { bytes_sent },
+            Err(error) => ProbeOutcome::Unreachable {
+                bytes_sent,
+                error: error.clone(),
+            },
+        };
+        self.report_probe(peer_id, Some(tested_addr.clone()), outcome);
+
         self.pending_events.push_back(ToSwarm::GenerateEvent(Event {
             tested_addr,
             bytes_sent,
             server: peer_id,
-            result: result.map_err(|e| Error { inner: e }),
+            result,
         }));
     }
 
@@ -278,9 +303,20 @@ where
             pending_events: VecDeque::new(),
             address_candidates: HashMap::new(),
             peer_info: HashMap::new(),
+            probe_callback: None,
         }
     }
 
+    /// Registers a callback that is invoked with the outcome of every probe, including probes
+    /// that didn't complete and are therefore not reported as an [`Event`].
+    ///
+    /// This allows applications to build their own reachability model on top of the probes sent
+    /// by this behaviour.
+    pub fn with_probe_callback(mut self, callback: impl FnMut(&Probe) + Send + 'static) -> Self {
+        self.probe_callback = Some(Box::new(callback));
+        self
+    }
+
     /// Issues dial requests to random AutoNAT servers for the most frequently reported, untested candidates.
     ///
     /// In the current implementation, we only send a single address to each AutoNAT server.
@@ -344,6 +380,29 @@ where
         Some((*conn_id, info.peer_id))
     }
 
+    /// Returns the address currently being tested with the given nonce.
+    fn addr_with_nonce(&self, nonce: Nonce) -> Option<Multiaddr> {
+        self.address_candidates
+            .iter()
+            .find(|(_, i)| i.is_pending_with_nonce(nonce) || i.is_received_with_nonce(nonce))
+            .map(|(addr, _)| addr.clone())
+    }
+
+    fn report_probe(
+        &mut self,
+        server: PeerId,
+        tested_addr: Option<Multiaddr>,
+        outcome: ProbeOutcome,
+    ) {
+        if let Some(callback) = self.probe_callback.as_mut() {
+            callback(&Probe {
+                server,
+                tested_addr,
+                outcome,
+            });
+        }
+    }
+
     fn reset_status_to(&mut self, nonce: Nonce, new_status: TestStatus) {
         let Some((_, info)) = self
             .address_candidates
@@ -371,6 +430,7 @@ impl Default for Behaviour<OsRng> {
     }
 }
 
+#[derive(Clone)]
 pub struct Error {
     pub(crate) inner: dial_request::DialBackError,
 }
@@ -402,6 +462,36 @@ pub struct Event {
     pub result: Result<(), Error>,
 }
 
+/// The outcome of a single probe, see [`Behaviour::with_probe_callback`].
+#[derive(Debug)]
+pub struct Probe {
+    /// The peer id of the server that was selected for testing.
+    pub server: PeerId,
+    /// The address that was tested, if it is still known.
+    pub tested_addr: Option<Multiaddr>,
+    /// The outcome of the probe.
+    pub outcome: ProbeOutcome,
+}
+
+/// The outcome of a probe, see [`Probe`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ProbeOutcome {
+    /// The server dialed the address back and we received the dial-back.
+    /// The address is confirmed as external address.
+    Reachable { bytes_sent: usize },
+    /// The server reported to have dialed the address back, but we never received the dial-back.
+    MissingDialBack { bytes_sent: usize },
+    /// The server failed to dial the address back.
+    Unreachable { bytes_sent: usize, error: Error },
+    /// The server doesn't support the dial-request protocol.
+    /// The address will be tested again.
+    UnsupportedProtocol,
+    /// The probe failed to complete, e.g. because the connection to the server was closed.
+    /// The address will be tested again.
+    Failed(std::io::Error),
+}
+
 struct ConnectionInfo {
     peer_id: PeerId,
     supports_autonat: bool,
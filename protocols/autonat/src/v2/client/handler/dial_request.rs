@@ -1,4 +1,4 @@
-use futures::{channel::oneshot, AsyncWrite};
+use futures::{channel::oneshot, AsyncRead, AsyncWrite};
 use futures_bounded::FuturesMap;
 use libp2p_core::{
     upgrade::{DeniedUpgrade, ReadyUpgrade},
@@ -59,7 +59,7 @@ impl From<io::Error> for Error {
     }
 }
 
-#[derive(thiserror::Error, Debug)]
+#[derive(thiserror::Error, Debug, Clone, Copy)]
 pub enum DialBackError {
     #[error("server failed to establish a connection")]
     NoConnection,
@@ -215,11 +215,21 @@ async fn start_stream_handle(
         .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?
         .map_err(|e| match e {
             StreamUpgradeError::NegotiationFailed => Error::UnsupportedProtocol,
-            StreamUpgradeError::Timeout => Error::Io(io::ErrorKind::TimedOut.into()),
+            StreamUpgradeError::Timeout | StreamUpgradeError::NegotiationTimeout => {
+                Error::Io(io::ErrorKind::TimedOut.into())
+            }
             StreamUpgradeError::Apply(v) => void::unreachable(v),
             StreamUpgradeError::Io(e) => Error::Io(e),
         })?;
 
+    handle_stream(req, stream).await
+}
+
+/// Runs the dial-request protocol for `req` on an already negotiated `stream`.
+pub(crate) async fn handle_stream(
+    req: DialRequest,
+    stream: impl AsyncRead + AsyncWrite + Unpin,
+) -> Result<(Multiaddr, usize), Error> {
     let mut coder = Coder::new(stream);
     coder.send(req.clone()).await?;
 
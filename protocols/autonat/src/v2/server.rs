@@ -1,5 +1,5 @@
 mod behaviour;
-mod handler;
+pub(crate) mod handler;
 
 pub use behaviour::Behaviour;
 pub use behaviour::Event;
@@ -101,7 +101,10 @@ impl ConnectionHandler for Handler {
                 }
             }
             ConnectionEvent::DialUpgradeError(DialUpgradeError {
-                error: StreamUpgradeError::NegotiationFailed | StreamUpgradeError::Timeout,
+                error:
+                    StreamUpgradeError::NegotiationFailed
+                    | StreamUpgradeError::Timeout
+                    | StreamUpgradeError::NegotiationTimeout,
                 ..
             }) => {
                 if let Some(cmd) = self.requested_substream_nonce.take() {
@@ -192,7 +192,7 @@ impl From<HandleFail> for DialResponse {
     }
 }
 
-async fn handle_request(
+pub(crate) async fn handle_request(
     stream: impl AsyncRead + AsyncWrite + Unpin,
     observed_multiaddr: Multiaddr,
     client: PeerId,
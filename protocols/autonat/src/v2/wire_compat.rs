@@ -0,0 +1,439 @@
+//! Harness for checking wire compatibility with other AutoNAT v2 implementations.
+//!
+//! A [`Transcript`] holds the length-prefixed protobuf frames exchanged on a dial-request stream,
+//! e.g. as recorded from go-libp2p. [`run_client`] and [`run_server`] replay the frames of the
+//! remote side against our client or server implementation and return the frames it sent in
+//! response, together with the outcome of the exchange.
+//!
+//! Transcripts can be written as text, one frame per line, prefixed by the direction the frame was
+//! sent in:
+//!
+//! ```text
+//! # Lines starting with `#` are ignored.
+//! c2s 0a0e0a0a04...
+//! s2c 0a05...
+//! ```
+
+use std::{
+    fmt, io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{channel::mpsc, io::Cursor, AsyncRead, AsyncWrite, StreamExt};
+use libp2p_core::Multiaddr;
+use libp2p_identity::PeerId;
+use rand_core::RngCore;
+
+use crate::v2::{
+    client::handler::dial_request as client_request,
+    protocol::DialRequest,
+    server::{
+        handler::dial_request::{self as server_request, DialBackStatus},
+        Event,
+    },
+    Nonce,
+};
+
+/// The direction a frame was sent in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    ClientToServer,
+    ServerToClient,
+}
+
+/// The frames exchanged on a single dial-request stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transcript {
+    frames: Vec<(Direction, Vec<u8>)>,
+}
+
+impl Transcript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a frame, including its unsigned-varint length prefix.
+    pub fn push(&mut self, direction: Direction, frame: Vec<u8>) -> &mut Self {
+        self.frames.push((direction, frame));
+        self
+    }
+
+    /// Parses a transcript from its text representation, see the [module docs](self).
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut transcript = Self::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = || ParseError { line: i + 1 };
+            let (direction, frame) = line.split_once(char::is_whitespace).ok_or_else(err)?;
+            let direction = match direction {
+                "c2s" => Direction::ClientToServer,
+                "s2c" => Direction::ServerToClient,
+                _ => return Err(err()),
+            };
+            transcript.push(direction, decode_hex(frame.trim()).ok_or_else(err)?);
+        }
+        Ok(transcript)
+    }
+
+    /// Returns the frames sent in the given direction, in order.
+    pub fn frames(&self, direction: Direction) -> impl Iterator<Item = &[u8]> {
+        self.frames
+            .iter()
+            .filter(move |(d, _)| *d == direction)
+            .map(|(_, frame)| frame.as_slice())
+    }
+
+    fn bytes(&self, direction: Direction) -> Vec<u8> {
+        self.frames(direction).flatten().copied().collect()
+    }
+}
+
+/// Error returned by [`Transcript::parse`] for a malformed line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    line: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed transcript line {}", self.line)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The outcome of replaying a transcript against our client.
+#[derive(Debug)]
+pub struct ClientOutcome {
+    /// The tested address and the number of bytes sent, or the reason the test failed.
+    pub result: Result<(Multiaddr, usize), io::Error>,
+    /// The frames sent by our client.
+    pub sent: Vec<Vec<u8>>,
+}
+
+/// Replays the server frames of `transcript` against our client requesting `addrs` to be tested
+/// with `nonce`.
+pub async fn run_client(
+    transcript: &Transcript,
+    addrs: Vec<Multiaddr>,
+    nonce: Nonce,
+) -> ClientOutcome {
+    let mut stream = ReplayStream::new(transcript.bytes(Direction::ServerToClient));
+    let result = client_request::handle_stream(DialRequest { addrs, nonce }, &mut stream)
+        .await
+        .map_err(|e| match e {
+            client_request::Error::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::Other, e),
+        });
+
+    ClientOutcome {
+        result,
+        sent: stream.sent_frames(),
+    }
+}
+
+/// The result of the dial-back performed by the server, as reported by [`run_server`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialBack {
+    Ok,
+    /// The server failed to connect to the tested address.
+    DialError,
+    /// The server connected, but the dial-back stream failed.
+    DialBackError,
+}
+
+/// The outcome of replaying a transcript against our server.
+#[derive(Debug)]
+pub struct ServerOutcome {
+    pub event: Event,
+    /// The address and nonce the server requested a dial-back for, if any.
+    pub dial_back: Option<(Multiaddr, Nonce)>,
+    /// The frames sent by our server.
+    pub sent: Vec<Vec<u8>>,
+}
+
+/// Replays the client frames of `transcript` against our server, answering a dial-back
+/// request with `dial_back`.
+///
+/// The amount of dial data requested by the server is drawn from `rng`, thus the transcript
+/// needs to contain enough data to cover it.
+pub async fn run_server(
+    transcript: &Transcript,
+    client: PeerId,
+    observed_addr: Multiaddr,
+    dial_back: DialBack,
+    rng: impl RngCore,
+) -> ServerOutcome {
+    let (dial_back_cmd_sender, mut dial_back_cmd_receiver) = mpsc::channel(1);
+    let mut stream = ReplayStream::new(transcript.bytes(Direction::ClientToServer));
+
+    let request = server_request::handle_request(
+        &mut stream,
+        observed_addr,
+        client,
+        dial_back_cmd_sender,
+        rng,
+    );
+    let dial_back_cmd = async {
+        let cmd = dial_back_cmd_receiver.next().await?;
+        let _ = cmd.back_channel.send(match dial_back {
+            DialBack::Ok => Ok(()),
+            DialBack::DialError => Err(DialBackStatus::DialErr),
+            DialBack::DialBackError => Err(DialBackStatus::DialBackErr),
+        });
+        Some((cmd.addr, cmd.nonce))
+    };
+    let (event, dial_back) = futures::join!(request, dial_back_cmd);
+
+    ServerOutcome {
+        event,
+        dial_back,
+        sent: stream.sent_frames(),
+    }
+}
+
+/// A stream that yields recorded bytes and captures everything written to it.
+struct ReplayStream {
+    inbound: Cursor<Vec<u8>>,
+    outbound: Vec<u8>,
+}
+
+impl ReplayStream {
+    fn new(inbound: Vec<u8>) -> Self {
+        Self {
+            inbound: Cursor::new(inbound),
+            outbound: Vec::new(),
+        }
+    }
+
+    /// Splits the written bytes into length-prefixed frames.
+    ///
+    /// A trailing incomplete frame is returned as is.
+    fn sent_frames(&self) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        let mut rest = self.outbound.as_slice();
+        while !rest.is_empty() {
+            let len = match decode_varint(rest) {
+                Some((len, prefix_len)) => (prefix_len + len).min(rest.len()),
+                None => rest.len(),
+            };
+            let (frame, remaining) = rest.split_at(len);
+            frames.push(frame.to_vec());
+            rest = remaining;
+        }
+        frames
+    }
+}
+
+impl AsyncRead for ReplayStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inbound).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ReplayStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.outbound.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Decodes an unsigned-varint, returning its value and encoded length.
+fn decode_varint(buf: &[u8]) -> Option<(usize, usize)> {
+    let mut value = 0usize;
+    for (i, byte) in buf.iter().enumerate().take(9) {
+        value |= usize::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::generated::structs::{
+        mod_DialResponse::ResponseStatus, mod_Message::OneOfmsg, DialDataRequest, DialDataResponse,
+        DialRequest, DialResponse, DialStatus, Message,
+    };
+    use crate::v2::protocol::{DATA_FIELD_LEN_UPPER_BOUND, DATA_LEN_UPPER_BOUND};
+    use quick_protobuf::{BytesReader, MessageRead};
+
+    fn frame(msg: OneOfmsg) -> Vec<u8> {
+        quick_protobuf::serialize_into_vec(&Message { msg }).unwrap()
+    }
+
+    fn decode(frame: &[u8]) -> Message {
+        let mut reader = BytesReader::from_bytes(frame);
+        reader.read_message(frame, Message::from_reader).unwrap()
+    }
+
+    fn addr() -> Multiaddr {
+        "/ip4/1.2.3.4/tcp/4001".parse().unwrap()
+    }
+
+    fn dial_request(nonce: u64) -> Vec<u8> {
+        frame(OneOfmsg::dialRequest(DialRequest {
+            addrs: vec![addr().to_vec()],
+            nonce,
+        }))
+    }
+
+    fn dial_response(status: ResponseStatus, dial_status: DialStatus) -> Vec<u8> {
+        frame(OneOfmsg::dialResponse(DialResponse {
+            status,
+            addrIdx: 0,
+            dialStatus: dial_status,
+        }))
+    }
+
+    fn dial_data(len: usize) -> Vec<u8> {
+        frame(OneOfmsg::dialDataResponse(DialDataResponse {
+            data: vec![0; len],
+        }))
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn parses_text_transcripts() {
+        let text = format!(
+            "# dial request\nc2s {}\n\ns2c {}\n",
+            to_hex(&dial_request(1)),
+            to_hex(&dial_response(ResponseStatus::OK, DialStatus::OK))
+        );
+        let transcript = Transcript::parse(&text).unwrap();
+
+        assert_eq!(
+            transcript
+                .frames(Direction::ClientToServer)
+                .collect::<Vec<_>>(),
+            vec![dial_request(1).as_slice()]
+        );
+        assert_eq!(
+            Transcript::parse("c2s 0a0").unwrap_err(),
+            ParseError { line: 1 }
+        );
+        assert_eq!(
+            Transcript::parse("\nc2x 00").unwrap_err(),
+            ParseError { line: 2 }
+        );
+    }
+
+    #[tokio::test]
+    async fn client_sends_dial_data_in_max_size_frames() {
+        let num_bytes = 2 * DATA_FIELD_LEN_UPPER_BOUND + 808 + 30_000;
+        let mut transcript = Transcript::new();
+        transcript
+            .push(
+                Direction::ServerToClient,
+                frame(OneOfmsg::dialDataRequest(DialDataRequest {
+                    addrIdx: 0,
+                    numBytes: num_bytes as u64,
+                })),
+            )
+            .push(
+                Direction::ServerToClient,
+                dial_response(ResponseStatus::OK, DialStatus::OK),
+            );
+
+        let outcome = run_client(&transcript, vec![addr()], 42).await;
+
+        assert_eq!(outcome.result.unwrap(), (addr(), num_bytes));
+        assert_eq!(outcome.sent[0], dial_request(42));
+        let data = outcome.sent[1..]
+            .iter()
+            .map(|frame| match decode(frame).msg {
+                OneOfmsg::dialDataResponse(DialDataResponse { data }) => data.len(),
+                other => panic!("Unexpected message {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(data.iter().sum::<usize>(), num_bytes);
+        assert!(data.iter().all(|len| *len <= DATA_FIELD_LEN_UPPER_BOUND));
+        assert_eq!(data[0], DATA_FIELD_LEN_UPPER_BOUND);
+    }
+
+    #[tokio::test]
+    async fn server_accepts_max_size_dial_data() {
+        let mut transcript = Transcript::new();
+        transcript.push(Direction::ClientToServer, dial_request(7));
+        for _ in 0..DATA_LEN_UPPER_BOUND.div_ceil(DATA_FIELD_LEN_UPPER_BOUND) {
+            transcript.push(
+                Direction::ClientToServer,
+                dial_data(DATA_FIELD_LEN_UPPER_BOUND),
+            );
+        }
+
+        let outcome = run_server(
+            &transcript,
+            PeerId::random(),
+            "/ip4/5.6.7.8/tcp/4001".parse().unwrap(),
+            DialBack::Ok,
+            rand::thread_rng(),
+        )
+        .await;
+
+        outcome.event.result.unwrap();
+        assert_eq!(outcome.dial_back, Some((addr(), 7)));
+        assert_eq!(
+            outcome.sent.last().unwrap(),
+            &dial_response(ResponseStatus::OK, DialStatus::OK)
+        );
+    }
+
+    #[tokio::test]
+    async fn server_rejects_oversized_dial_data_frames() {
+        let mut transcript = Transcript::new();
+        transcript
+            .push(Direction::ClientToServer, dial_request(7))
+            .push(Direction::ClientToServer, dial_data(4200));
+
+        let outcome = run_server(
+            &transcript,
+            PeerId::random(),
+            "/ip4/5.6.7.8/tcp/4001".parse().unwrap(),
+            DialBack::Ok,
+            rand::thread_rng(),
+        )
+        .await;
+
+        assert_eq!(outcome.dial_back, None);
+        assert_eq!(
+            outcome.sent.last().unwrap(),
+            &dial_response(ResponseStatus::E_INTERNAL_ERROR, DialStatus::UNUSED)
+        );
+    }
+}
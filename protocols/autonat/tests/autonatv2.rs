@@ -422,6 +422,56 @@ async fn dial_back_to_not_supporting() {
     handler.abort();
 }
 
+#[tokio::test]
+async fn probe_callback_receives_outcome() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut alice = new_server().await;
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut bob = Swarm::new_ephemeral(|identity| CombinedClient {
+        autonat: client::Behaviour::new(
+            OsRng,
+            Config::default().with_probe_interval(Duration::from_millis(100)),
+        )
+        .with_probe_callback(move |probe| {
+            let reachable = matches!(probe.outcome, client::ProbeOutcome::Reachable { .. });
+            let _ = tx.send((probe.server, probe.tested_addr.clone(), reachable));
+        }),
+        identify: libp2p_identify::Behaviour::new(libp2p_identify::Config::new(
+            "/libp2p-test/1.0.0".into(),
+            identity.public().clone(),
+        )),
+    });
+    bob.listen().with_tcp_addr_external().await;
+    bob.connect(&mut alice).await;
+
+    let cor_server_peer = *alice.local_peer_id();
+    let handler = tokio::spawn(async move { alice.loop_on_next().await });
+
+    let client::Event {
+        tested_addr,
+        server,
+        result,
+        ..
+    } = bob
+        .wait(|event| match event {
+            SwarmEvent::Behaviour(CombinedClientEvent::Autonat(status_update)) => {
+                Some(status_update)
+            }
+            _ => None,
+        })
+        .await;
+    assert!(result.is_ok(), "Result is {result:?}");
+
+    let (probe_server, probe_addr, reachable) = rx.try_iter().last().unwrap();
+    assert_eq!(probe_server, cor_server_peer);
+    assert_eq!(server, cor_server_peer);
+    assert_eq!(probe_addr, Some(tested_addr));
+    assert!(reachable);
+    handler.abort();
+}
+
 async fn new_server() -> Swarm<CombinedServer> {
     let mut node = Swarm::new_ephemeral(|identity| CombinedServer {
         autonat: libp2p_autonat::v2::server::Behaviour::default(),
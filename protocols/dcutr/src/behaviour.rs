@@ -269,6 +269,7 @@ impl NetworkBehaviour for Behaviour {
                 let opts = DialOpts::peer_id(event_source)
                     .addresses(remote_addrs)
                     .condition(dial_opts::PeerCondition::Always)
+                    .priority(dial_opts::DialPriority::High)
                     .build();
 
                 let maybe_direct_connection_id = opts.connection_id();
@@ -302,6 +303,7 @@ impl NetworkBehaviour for Behaviour {
                     .condition(dial_opts::PeerCondition::Always)
                     .addresses(remote_addrs)
                     .override_role()
+                    .priority(dial_opts::DialPriority::High)
                     .build();
 
                 let maybe_direct_connection_id = opts.connection_id();
@@ -167,7 +167,9 @@ impl Handler {
             StreamUpgradeError::Apply(v) => void::unreachable(v),
             StreamUpgradeError::NegotiationFailed => outbound::Error::Unsupported,
             StreamUpgradeError::Io(e) => outbound::Error::Io(e),
-            StreamUpgradeError::Timeout => outbound::Error::Io(io::ErrorKind::TimedOut.into()),
+            StreamUpgradeError::Timeout | StreamUpgradeError::NegotiationTimeout => {
+                outbound::Error::Io(io::ErrorKind::TimedOut.into())
+            }
         };
 
         self.queued_events
@@ -132,6 +132,17 @@ impl BackoffStorage {
         Self::get_backoff_time_from_backoffs(&self.backoffs, topic, peer)
     }
 
+    /// Returns the backoffs of all peers for the given topic.
+    pub(crate) fn get_backoff_times(
+        &self,
+        topic: &TopicHash,
+    ) -> impl Iterator<Item = (&PeerId, Instant)> {
+        self.backoffs
+            .get(topic)
+            .into_iter()
+            .flat_map(|m| m.iter().map(|(peer, (i, _))| (peer, *i)))
+    }
+
     fn get_backoff_time_from_backoffs(
         backoffs: &HashMap<TopicHash, HashMap<PeerId, (Instant, HeartbeatIndex)>>,
         topic: &TopicHash,
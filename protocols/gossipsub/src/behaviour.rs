@@ -20,6 +20,7 @@
 
 use std::{
     cmp::{max, Ordering},
+    collections::hash_map::Entry,
     collections::HashSet,
     collections::VecDeque,
     collections::{BTreeSet, HashMap},
@@ -36,11 +37,13 @@ use rand::{seq::SliceRandom, thread_rng};
 
 use libp2p_core::{
     multiaddr::Protocol::Ip4, multiaddr::Protocol::Ip6, transport::PortUse, Endpoint, Multiaddr,
+    PeerRecord, SignedEnvelope,
 };
 use libp2p_identity::Keypair;
-use libp2p_identity::PeerId;
+use libp2p_identity::{peer_set::PeerSet, PeerId};
 use libp2p_swarm::{
-    behaviour::{AddressChange, ConnectionClosed, ConnectionEstablished, FromSwarm},
+    behaviour::{AddressChange, ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm},
+    cache::CacheStats,
     dial_opts::DialOpts,
     ConnectionDenied, ConnectionId, NetworkBehaviour, NotifyHandler, THandler, THandlerInEvent,
     THandlerOutEvent, ToSwarm,
@@ -48,24 +51,33 @@ use libp2p_swarm::{
 use web_time::{Instant, SystemTime};
 
 use crate::backoff::BackoffStorage;
-use crate::config::{Config, ValidationMode};
+use crate::choke::ChokeState;
+use crate::config::{Config, LocalDelivery, ValidationMode};
+use crate::first_delivery::FirstDeliveries;
 use crate::gossip_promises::GossipPromises;
 use crate::handler::{Handler, HandlerEvent, HandlerIn};
+use crate::latency::DeliveryLatencies;
 use crate::mcache::MessageCache;
 use crate::metrics::{Churn, Config as MetricsConfig, Inclusion, Metrics, Penalty};
-use crate::peer_score::{PeerScore, PeerScoreParams, PeerScoreThresholds, RejectReason};
+use crate::peer_score::{
+    PeerScore, PeerScoreParams, PeerScoreReport, PeerScoreThresholds, RejectReason,
+};
 use crate::protocol::SIGNING_PREFIX;
+use crate::queue::DroppedMessages;
 use crate::subscription_filter::{AllowAllSubscriptionFilter, TopicSubscriptionFilter};
 use crate::time_cache::DuplicateCache;
 use crate::topic::{Hasher, Topic, TopicHash};
+use crate::topology::{RestoredTopology, TopologySnapshot};
+use crate::tracer::{TraceEvent, Tracer};
 use crate::transform::{DataTransform, IdentityTransform};
 use crate::types::{
-    ControlAction, Message, MessageAcceptance, MessageId, PeerInfo, RawMessage, Subscription,
-    SubscriptionAction,
+    ControlAction, Message, MessageAcceptance, MessageId, MessageIdContext, PeerInfo, RawMessage,
+    Subscription, SubscriptionAction,
 };
 use crate::types::{PeerConnections, PeerKind, RpcOut};
+use crate::validator::MessageValidator;
 use crate::{rpc_proto::proto, TopicScoreParams};
-use crate::{PublishError, SubscriptionError, ValidationError};
+use crate::{PublishError, StaleSnapshotError, SubscriptionError, ValidationError};
 use quick_protobuf::{MessageWrite, Writer};
 use std::{cmp::Ordering::Equal, fmt::Debug};
 
@@ -129,6 +141,14 @@ pub enum Event {
         /// The decompressed message itself.
         message: Message,
     },
+    /// A message published by the local node has been delivered locally, see
+    /// [`Config::local_delivery`].
+    LocalMessage {
+        /// The [`MessageId`] of the message, as returned by [`Behaviour::publish`].
+        message_id: MessageId,
+        /// The message itself.
+        message: Message,
+    },
     /// A remote subscribed to a topic.
     Subscribed {
         /// Remote that has subscribed.
@@ -143,8 +163,93 @@ pub enum Event {
         /// The topic it has subscribed from.
         topic: TopicHash,
     },
+    /// Subscriptions of a remote have been rejected by the [`TopicSubscriptionFilter`].
+    SubscriptionsFiltered {
+        /// Remote whose subscriptions have been rejected.
+        peer_id: PeerId,
+        /// The topics it tried to subscribe to.
+        topics: Vec<TopicHash>,
+    },
     /// A peer that does not support gossipsub has connected.
     GossipsubNotSupported { peer_id: PeerId },
+    /// A mesh peer has been penalized and pruned from the mesh because it was too slow to keep
+    /// up, see [`Config::slow_peer_threshold`].
+    SlowPeer {
+        /// The peer that has been pruned.
+        peer_id: PeerId,
+        /// The topics whose mesh the peer has been pruned from.
+        topics: Vec<TopicHash>,
+        /// Why the peer was considered slow during the last heartbeat.
+        reason: SlowPeerReason,
+        /// What was measured for the peer during the last heartbeat.
+        stats: SlowPeerStats,
+    },
+    /// An explicit peer is not connected anymore, either because its last connection was closed
+    /// or because dialing it failed. The behaviour keeps trying to reconnect, see
+    /// [`Config::check_explicit_peers_ticks`].
+    ExplicitPeerDown { peer_id: PeerId },
+    /// Outbound messages to a peer were dropped since the last heartbeat because its send queue
+    /// was full, see [`Config::send_queue_capacity`].
+    MessagesDropped {
+        /// The peer whose send queue was full.
+        peer_id: PeerId,
+        /// The number of dropped messages, by priority class.
+        dropped: DroppedMessages,
+    },
+    /// The score of a connected peer dropped below the graylist threshold during the last
+    /// heartbeat, see [`PeerScoreThresholds::graylist_threshold`]. Its RPCs are ignored until
+    /// [`Event::GraylistLifted`] is reported.
+    Graylisted {
+        /// The graylisted peer.
+        peer_id: PeerId,
+        /// The score of the peer.
+        score: f64,
+    },
+    /// The score of a graylisted peer recovered to at least the graylist threshold during the
+    /// last heartbeat.
+    GraylistLifted {
+        /// The peer that is no longer graylisted.
+        peer_id: PeerId,
+        /// The score of the peer.
+        score: f64,
+    },
+    /// Peers scoring above the median of a topic's mesh have been grafted during the last
+    /// heartbeat because the median score was below the opportunistic grafting threshold, see
+    /// [`Config::opportunistic_graft_ticks`].
+    OpportunisticGraft {
+        /// The topic whose mesh the peers have been grafted to.
+        topic: TopicHash,
+        /// The grafted peers.
+        peers: Vec<PeerId>,
+        /// The median score of the mesh before grafting.
+        median_score: f64,
+    },
+}
+
+/// The reason for a mesh peer to be considered slow, see [`Event::SlowPeer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowPeerReason {
+    /// The queue of RPCs to be sent to the peer was persistently full.
+    SendQueueFull,
+    /// The peer did not respond to our IWANT requests in time.
+    LateIwantResponses,
+    /// The peer delivered messages too long after other peers, see
+    /// [`Config::slow_peer_latency`].
+    HighLatency,
+}
+
+/// What was measured for a slow mesh peer during the last heartbeat, see [`Event::SlowPeer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlowPeerStats {
+    /// The number of connections to the peer whose send queue exceeded
+    /// [`Config::slow_peer_queue_len`].
+    pub congested_connections: usize,
+    /// The number of messages the peer delivered while being in our mesh. Only tracked if
+    /// [`Config::slow_peer_latency`] is set.
+    pub deliveries: usize,
+    /// The mean delay with which the peer delivered those messages, relative to their first
+    /// delivery by any peer.
+    pub mean_delivery_latency: Option<Duration>,
 }
 
 /// A data structure for storing configuration for publishing messages. See [`MessageAuthenticity`]
@@ -251,6 +356,9 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
     /// Information used for publishing messages.
     publish_config: PublishConfig,
 
+    /// Per-topic overrides of [`Behaviour::publish_config`].
+    topic_publish_configs: HashMap<TopicHash, PublishConfig>,
+
     /// An LRU Time cache for storing seen messages (based on their ID). This cache prevents
     /// duplicates from being propagated to the application and on the network.
     duplicate_cache: DuplicateCache<MessageId>,
@@ -263,6 +371,15 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
     /// forward messages to, outside of the scoring system.
     explicit_peers: HashSet<PeerId>,
 
+    /// Known addresses of explicit peers, used when (re)connecting to them.
+    explicit_peer_addresses: HashMap<PeerId, Vec<Multiaddr>>,
+
+    /// Explicit peers we reported as down and that did not reconnect yet.
+    explicit_peers_down: HashSet<PeerId>,
+
+    /// Connected peers we reported as graylisted and that did not recover yet.
+    graylisted_peers: HashSet<PeerId>,
+
     /// A list of peers that have been blacklisted by the user.
     /// Messages are not sent to and are rejected from these peers.
     blacklisted_peers: HashSet<PeerId>,
@@ -276,6 +393,9 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
     /// The last publish time for fanout topics.
     fanout_last_pub: HashMap<TopicHash, Instant>,
 
+    /// The mesh and fanout peers of an imported [`TopologySnapshot`], until it becomes stale.
+    restored_topology: Option<RestoredTopology>,
+
     ///Storage for backoffs
     backoffs: BackoffStorage,
 
@@ -295,6 +415,9 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
     /// be removed from this list which may result in a true outbound rediscovery.
     px_peers: HashSet<PeerId>,
 
+    /// Signed peer records of connected peers, included in PRUNE messages for peer exchange.
+    peer_records: HashMap<PeerId, PeerRecord>,
+
     /// Set of connected outbound peers (we only consider true outbound peers found through
     /// discovery and not by PX).
     outbound_peers: HashSet<PeerId>,
@@ -317,6 +440,16 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
     /// our own messages back if the messages are anonymous or use a random author.
     published_message_ids: DuplicateCache<MessageId>,
 
+    /// The optional asynchronous validator of received messages.
+    validator: Option<Box<dyn MessageValidator>>,
+
+    /// The optional tracer of internal events.
+    tracer: Option<Box<dyn Tracer>>,
+
+    /// Messages currently being validated by the [`MessageValidator`].
+    pending_validations:
+        futures_bounded::FuturesTupleSet<MessageAcceptance, (PeerId, MessageId, Message)>,
+
     /// The filter used to handle message subscriptions.
     subscription_filter: F,
 
@@ -327,6 +460,25 @@ pub struct Behaviour<D = IdentityTransform, F = AllowAllSubscriptionFilter> {
 
     /// Keep track of a set of internal metrics relating to gossipsub.
     metrics: Option<Metrics>,
+
+    /// The connections of each peer whose send queue is currently full.
+    congested_connections: HashMap<PeerId, HashSet<ConnectionId>>,
+
+    /// Counts the consecutive heartbeats in which a mesh peer was found to be slow.
+    slow_peer_strikes: HashMap<PeerId, usize>,
+
+    /// Tracks the delivery latency of mesh peers, if [`Config::slow_peer_latency`] is set.
+    delivery_latencies: Option<DeliveryLatencies>,
+
+    /// Outbound messages dropped per peer since the last heartbeat.
+    dropped_messages: HashMap<PeerId, DroppedMessages>,
+
+    /// Tracks which mesh peers are choked, if choking is enabled.
+    choke_state: Option<ChokeState>,
+
+    /// When recent messages were first received, if choking or slow peer detection by latency is
+    /// enabled.
+    first_deliveries: Option<FirstDeliveries>,
 }
 
 impl<D, F> Behaviour<D, F>
@@ -436,12 +588,27 @@ where
             events: VecDeque::new(),
             control_pool: HashMap::new(),
             publish_config: privacy.into(),
-            duplicate_cache: DuplicateCache::new(config.duplicate_cache_time()),
+            topic_publish_configs: HashMap::new(),
+            duplicate_cache: match config.duplicate_cache_budget() {
+                Some(budget) => DuplicateCache::with_budget(
+                    config.duplicate_cache_time(),
+                    budget.clone(),
+                    |id, _| std::mem::size_of::<MessageId>() + id.0.len(),
+                ),
+                None => DuplicateCache::new(config.duplicate_cache_time()),
+            },
+            choke_state: config.choking().then(ChokeState::default),
+            first_deliveries: (config.choking() || config.slow_peer_latency().is_some())
+                .then(|| FirstDeliveries::new(config.duplicate_cache_time())),
             explicit_peers: HashSet::new(),
+            explicit_peer_addresses: HashMap::new(),
+            explicit_peers_down: HashSet::new(),
+            graylisted_peers: HashSet::new(),
             blacklisted_peers: HashSet::new(),
             mesh: HashMap::new(),
             fanout: HashMap::new(),
             fanout_last_pub: HashMap::new(),
+            restored_topology: None,
             backoffs: BackoffStorage::new(
                 &config.prune_backoff(),
                 config.heartbeat_interval(),
@@ -454,6 +621,7 @@ where
             ),
             heartbeat_ticks: 0,
             px_peers: HashSet::new(),
+            peer_records: HashMap::new(),
             outbound_peers: HashSet::new(),
             peer_score: None,
             count_received_ihave: HashMap::new(),
@@ -461,6 +629,18 @@ where
             pending_iwant_msgs: HashSet::new(),
             connected_peers: HashMap::new(),
             published_message_ids: DuplicateCache::new(config.published_message_ids_cache_time()),
+            congested_connections: HashMap::new(),
+            slow_peer_strikes: HashMap::new(),
+            delivery_latencies: config
+                .slow_peer_latency()
+                .map(|_| DeliveryLatencies::new(config.duplicate_cache_time())),
+            dropped_messages: HashMap::new(),
+            validator: None,
+            tracer: None,
+            pending_validations: futures_bounded::FuturesTupleSet::new(
+                config.validation_timeout(),
+                config.max_concurrent_validations(),
+            ),
             config,
             subscription_filter,
             data_transform,
@@ -483,6 +663,74 @@ where
         self.mesh.get(topic_hash).into_iter().flat_map(|x| x.iter())
     }
 
+    /// Lists the fanout peers for a certain topic hash, i.e. the peers we publish to on a topic we
+    /// are not subscribed to.
+    pub fn fanout_peers(&self, topic_hash: &TopicHash) -> impl Iterator<Item = &PeerId> {
+        self.fanout
+            .get(topic_hash)
+            .into_iter()
+            .flat_map(|x| x.iter())
+    }
+
+    /// Selects the fanout peers of a topic we are not subscribed to ahead of the first publish,
+    /// so that it does not have to wait for them to be selected. The fanout is maintained in the
+    /// heartbeat and expires after [`Config::fanout_ttl_for_topic`] without publishing.
+    ///
+    /// Returns `false` if we are subscribed to the topic, in which case the mesh is used instead.
+    pub fn seed_fanout(&mut self, topic_hash: &TopicHash) -> bool {
+        if self.mesh.contains_key(topic_hash) {
+            return false;
+        }
+
+        if !self.fanout.contains_key(topic_hash) {
+            let peers = self.select_fanout_peers(topic_hash);
+            tracing::debug!(topic=%topic_hash, "Seeding fanout with {} peers", peers.len());
+            self.fanout.insert(topic_hash.clone(), peers);
+        }
+        self.fanout_last_pub
+            .insert(topic_hash.clone(), Instant::now());
+
+        true
+    }
+
+    /// Returns the hits, misses and evictions of the duplicate cache, see
+    /// [`ConfigBuilder::duplicate_cache_budget`](crate::ConfigBuilder::duplicate_cache_budget).
+    pub fn duplicate_cache_stats(&self) -> CacheStats {
+        self.duplicate_cache.stats()
+    }
+
+    /// Returns the ids of all messages in the duplicate cache, with the time they expire at.
+    ///
+    /// Together with [`Behaviour::restore_seen_messages`], this allows persisting the duplicate
+    /// cache across restarts, so that a restarted node doesn't propagate messages it has already
+    /// seen again.
+    pub fn seen_messages(&self) -> impl Iterator<Item = (&MessageId, SystemTime)> {
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        self.duplicate_cache
+            .expirations()
+            .map(move |(id, expires)| (id, system_now + expires.saturating_duration_since(now)))
+    }
+
+    /// Adds message ids to the duplicate cache, e.g. as returned by [`Behaviour::seen_messages`]
+    /// before a restart. Messages with these ids are dropped as duplicates until the given
+    /// expiration, which is capped at [`Config::duplicate_cache_time`]. Expired ids are ignored.
+    pub fn restore_seen_messages(
+        &mut self,
+        messages: impl IntoIterator<Item = (MessageId, SystemTime)>,
+    ) {
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let ttl = self.config.duplicate_cache_time();
+        for (id, expires) in messages {
+            let Ok(remaining) = expires.duration_since(system_now) else {
+                continue;
+            };
+            self.duplicate_cache
+                .insert_until(id, now + remaining.min(ttl));
+        }
+    }
+
     pub fn all_mesh_peers(&self) -> impl Iterator<Item = &PeerId> {
         let mut res = BTreeSet::new();
         for peers in self.mesh.values() {
@@ -510,6 +758,111 @@ where
             .map(|(score, ..)| score.score(peer_id))
     }
 
+    /// Returns the gossipsub score for a given peer broken down into its components, if peer
+    /// scoring is enabled.
+    pub fn peer_score_report(&self, peer_id: &PeerId) -> Option<PeerScoreReport> {
+        self.peer_score
+            .as_ref()
+            .map(|(score, ..)| score.score_report(peer_id))
+    }
+
+    /// Returns a snapshot of the score reports of all peers that are currently being scored.
+    ///
+    /// This includes recently disconnected peers whose score is retained, see
+    /// [`PeerScoreParams::retain_score`].
+    pub fn peer_score_reports(&self) -> impl Iterator<Item = (&PeerId, PeerScoreReport)> {
+        self.peer_score
+            .iter()
+            .flat_map(|(score, ..)| score.score_reports())
+    }
+
+    /// Returns a snapshot of the mesh, fanout and peer scores.
+    ///
+    /// Together with [`Behaviour::import_topology`], this allows a node that is briefly restarted
+    /// to rejoin its meshes quickly, instead of rebuilding them and the scores of its peers from
+    /// scratch.
+    pub fn export_topology(&self) -> TopologySnapshot {
+        TopologySnapshot {
+            taken_at: SystemTime::now(),
+            mesh: self.mesh.clone(),
+            fanout: self.fanout.clone(),
+            peer_scores: self
+                .peer_score
+                .iter()
+                .flat_map(|(score, ..)| score.snapshots())
+                .map(|(peer_id, snapshot)| (*peer_id, snapshot))
+                .collect(),
+        }
+    }
+
+    /// Imports a snapshot taken via [`Behaviour::export_topology`], e.g. before a restart.
+    ///
+    /// The score counters of peers that are not scored yet are restored, if peer scoring is
+    /// enabled via [`Behaviour::with_peer_score`]. They are retained for
+    /// [`PeerScoreParams::retain_score`] unless the peer reconnects.
+    /// Until the snapshot is older than `max_age`, peers of the snapshot's mesh are grafted as
+    /// soon as they subscribe to the topic, up to [`Config::mesh_n`], and peers of its fanout
+    /// are added to the fanout of the topic.
+    ///
+    /// Fails if the snapshot is already older than `max_age`.
+    pub fn import_topology(
+        &mut self,
+        snapshot: TopologySnapshot,
+        max_age: Duration,
+    ) -> Result<(), StaleSnapshotError> {
+        // Snapshots from the future, e.g. due to clock adjustments, are considered fresh.
+        let age = SystemTime::now()
+            .duration_since(snapshot.taken_at)
+            .unwrap_or_default();
+        let Some(remaining) = max_age.checked_sub(age) else {
+            return Err(StaleSnapshotError { age });
+        };
+
+        if let Some((peer_score, ..)) = &mut self.peer_score {
+            for (peer_id, peer_snapshot) in &snapshot.peer_scores {
+                peer_score.restore(*peer_id, peer_snapshot);
+            }
+        }
+
+        let without_explicit_peers = |mut peers: HashMap<TopicHash, BTreeSet<PeerId>>| {
+            for topic_peers in peers.values_mut() {
+                topic_peers.retain(|peer| !self.explicit_peers.contains(peer));
+            }
+            peers
+        };
+        self.restored_topology = Some(RestoredTopology::new(
+            Instant::now() + remaining,
+            without_explicit_peers(snapshot.mesh),
+            without_explicit_peers(snapshot.fanout),
+        ));
+
+        Ok(())
+    }
+
+    /// Returns whether the score of the given peer is below the graylist threshold, in which case
+    /// its RPCs are ignored. Always false if peer scoring is disabled.
+    pub fn is_graylisted(&self, peer_id: &PeerId) -> bool {
+        self.score_below_threshold(peer_id, |pst| pst.graylist_threshold)
+            .0
+    }
+
+    /// Returns the time until which we won't GRAFT the given peer on the given topic, or accept a
+    /// GRAFT from it, because it has been pruned from or has pruned us from the mesh.
+    pub fn backoff(&self, topic_hash: &TopicHash, peer_id: &PeerId) -> Option<Instant> {
+        self.backoffs
+            .get_backoff_time(topic_hash, peer_id)
+            .filter(|backoff| *backoff > Instant::now())
+    }
+
+    /// Lists the peers currently backed off on the given topic, with the time their backoff
+    /// expires at. See [`Behaviour::backoff`].
+    pub fn backoffs(&self, topic_hash: &TopicHash) -> impl Iterator<Item = (&PeerId, Instant)> {
+        let now = Instant::now();
+        self.backoffs
+            .get_backoff_times(topic_hash)
+            .filter(move |(_, backoff)| *backoff > now)
+    }
+
     /// Subscribe to a topic.
     ///
     /// Returns [`Ok(true)`] if the subscription worked. Returns [`Ok(false)`] if we were already
@@ -575,42 +928,7 @@ where
         topic: impl Into<TopicHash>,
         data: impl Into<Vec<u8>>,
     ) -> Result<MessageId, PublishError> {
-        let data = data.into();
-        let topic = topic.into();
-
-        // Transform the data before building a raw_message.
-        let transformed_data = self
-            .data_transform
-            .outbound_transform(&topic, data.clone())?;
-
-        let raw_message = self.build_raw_message(topic, transformed_data)?;
-
-        // calculate the message id from the un-transformed data
-        let msg_id = self.config.message_id(&Message {
-            source: raw_message.source,
-            data, // the uncompressed form
-            sequence_number: raw_message.sequence_number,
-            topic: raw_message.topic.clone(),
-        });
-
-        // check that the size doesn't exceed the max transmission size
-        if raw_message.raw_protobuf_len() > self.config.max_transmit_size() {
-            return Err(PublishError::MessageTooLarge);
-        }
-
-        // Check the if the message has been published before
-        if self.duplicate_cache.contains(&msg_id) {
-            // This message has already been seen. We don't re-publish messages that have already
-            // been published on the network.
-            tracing::warn!(
-                message=%msg_id,
-                "Not publishing a message that has already been published"
-            );
-            return Err(PublishError::Duplicate);
-        }
-
-        tracing::trace!(message=%msg_id, "Publishing message");
-
+        let (msg_id, raw_message) = self.prepare_publish(topic.into(), data.into())?;
         let topic_hash = raw_message.topic.clone();
 
         let mut peers_on_topic = self
@@ -625,7 +943,7 @@ where
         }
 
         let mut recipient_peers = HashSet::new();
-        if self.config.flood_publish() {
+        if self.config.flood_publish_for_topic(&topic_hash) {
             // Forward to all peers above score and all explicit peers
             recipient_peers.extend(peers_on_topic.filter(|p| {
                 self.explicit_peers.contains(*p)
@@ -637,7 +955,10 @@ where
                 Some(mesh_peers) => {
                     // We have a mesh set. We want to make sure to publish to at least `mesh_n`
                     // peers (if possible).
-                    let needed_extra_peers = self.config.mesh_n().saturating_sub(mesh_peers.len());
+                    let needed_extra_peers = self
+                        .config
+                        .mesh_n_for_topic(&topic_hash)
+                        .saturating_sub(mesh_peers.len());
 
                     if needed_extra_peers > 0 {
                         // We don't have `mesh_n` peers in our mesh, we will randomly select extras
@@ -659,7 +980,13 @@ where
                         recipient_peers.extend(peer_list);
                     }
 
-                    recipient_peers.extend(mesh_peers);
+                    // Choked peers are served through gossip.
+                    recipient_peers.extend(mesh_peers.iter().filter(|peer| {
+                        !self
+                            .choke_state
+                            .as_ref()
+                            .map_or(false, |c| c.is_choked(&topic_hash, peer))
+                    }));
                 }
                 // Gossipsub peers
                 None => {
@@ -671,16 +998,7 @@ where
                         }
                     } else {
                         // We have no fanout peers, select mesh_n of them and add them to the fanout
-                        let mesh_n = self.config.mesh_n();
-                        let new_peers =
-                            get_random_peers(&self.connected_peers, &topic_hash, mesh_n, {
-                                |p| {
-                                    !self.explicit_peers.contains(p)
-                                        && !self
-                                            .score_below_threshold(p, |pst| pst.publish_threshold)
-                                            .0
-                                }
-                            });
+                        let new_peers = self.select_fanout_peers(&topic_hash);
                         // Add the new peers to the fanout and recipient peers
                         self.fanout.insert(topic_hash.clone(), new_peers.clone());
                         for peer in new_peers {
@@ -710,20 +1028,131 @@ where
             }
         }
 
+        self.send_published_message(&msg_id, raw_message, recipient_peers);
+
+        Ok(msg_id)
+    }
+
+    /// Publishes a message to the given peers only, bypassing the mesh and fanout selection of
+    /// [`Behaviour::publish`].
+    ///
+    /// Peers that are not connected or don't support gossipsub are skipped, they don't need to be
+    /// subscribed to the topic. The message is still added to the message cache and the seen set,
+    /// so it is gossiped and not re-published like any other published message. Fails with
+    /// [`PublishError::InsufficientPeers`] if none of the peers is connected.
+    pub fn publish_to(
+        &mut self,
+        topic: impl Into<TopicHash>,
+        data: impl Into<Vec<u8>>,
+        peers: impl IntoIterator<Item = PeerId>,
+    ) -> Result<MessageId, PublishError> {
+        let (msg_id, raw_message) = self.prepare_publish(topic.into(), data.into())?;
+
+        let recipient_peers: HashSet<PeerId> = peers
+            .into_iter()
+            .filter(|peer_id| {
+                self.connected_peers
+                    .get(peer_id)
+                    .map_or(false, |peer| peer.kind != PeerKind::NotSupported)
+            })
+            .collect();
+
+        if recipient_peers.is_empty() {
+            return Err(PublishError::InsufficientPeers);
+        }
+
+        self.send_published_message(&msg_id, raw_message, recipient_peers);
+
+        Ok(msg_id)
+    }
+
+    /// Builds the message for [`Behaviour::publish`] and [`Behaviour::publish_to`] and checks
+    /// that it can be published.
+    fn prepare_publish(
+        &mut self,
+        topic: TopicHash,
+        data: Vec<u8>,
+    ) -> Result<(MessageId, RawMessage), PublishError> {
+        // Transform the data before building a raw_message.
+        let transformed_data = self
+            .data_transform
+            .outbound_transform(&topic, data.clone())?;
+
+        let raw_message = self.build_raw_message(topic, transformed_data)?;
+
+        let message = Message {
+            source: raw_message.source,
+            data, // the uncompressed form
+            sequence_number: raw_message.sequence_number,
+            topic: raw_message.topic.clone(),
+        };
+
+        // calculate the message id from the un-transformed data
+        let msg_id = self
+            .config
+            .message_id(&message)
+            .map_err(|_| PublishError::InvalidMessageId)?;
+
+        // check that the size doesn't exceed the max transmission size
+        if raw_message.raw_protobuf_len() > self.config.max_transmit_size() {
+            return Err(PublishError::MessageTooLarge);
+        }
+
+        // Check the if the message has been published before
+        if self.duplicate_cache.contains(&msg_id) {
+            // This message has already been seen. We don't re-publish messages that have already
+            // been published on the network.
+            tracing::warn!(
+                message=%msg_id,
+                "Not publishing a message that has already been published"
+            );
+            return Err(PublishError::Duplicate);
+        }
+
+        tracing::trace!(message=%msg_id, "Publishing message");
+
+        let topic_hash = raw_message.topic.clone();
+
+        if self.config.local_delivery() == LocalDelivery::Immediate
+            && self.mesh.contains_key(&topic_hash)
+        {
+            self.events
+                .push_back(ToSwarm::GenerateEvent(Event::LocalMessage {
+                    message_id: msg_id.clone(),
+                    message,
+                }));
+        }
+
+        Ok((msg_id, raw_message))
+    }
+
+    /// Adds a published message to the caches and sends it to the given peers.
+    fn send_published_message(
+        &mut self,
+        msg_id: &MessageId,
+        raw_message: RawMessage,
+        recipient_peers: HashSet<PeerId>,
+    ) {
+        let topic_hash = raw_message.topic.clone();
+
         // If the message isn't a duplicate and we have sent it to some peers add it to the
         // duplicate cache and memcache.
         self.duplicate_cache.insert(msg_id.clone());
-        self.mcache.put(&msg_id, raw_message.clone());
+        self.mcache.put(msg_id, raw_message.clone());
+        if let Some(first_deliveries) = &mut self.first_deliveries {
+            first_deliveries.record(msg_id);
+        }
 
         // If the message is anonymous or has a random author add it to the published message ids
         // cache.
-        if let PublishConfig::RandomAuthor | PublishConfig::Anonymous = self.publish_config {
+        if let PublishConfig::RandomAuthor | PublishConfig::Anonymous =
+            self.publish_config_for_topic(&raw_message.topic)
+        {
             if !self.config.allow_self_origin() {
                 self.published_message_ids.insert(msg_id.clone());
             }
         }
 
-        // Send to peers we know are subscribed to the topic.
         for peer_id in recipient_peers.iter() {
             tracing::trace!(peer=%peer_id, "Sending message to peer");
             self.send_message(*peer_id, RpcOut::Publish(raw_message.clone()));
@@ -731,11 +1160,79 @@ where
 
         tracing::debug!(message=%msg_id, "Published message");
 
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.trace(TraceEvent::PublishMessage {
+                message_id: msg_id.clone(),
+                topic: topic_hash.clone(),
+            });
+        }
+
         if let Some(metrics) = self.metrics.as_mut() {
             metrics.register_published_message(&topic_hash);
         }
 
-        Ok(msg_id)
+        if self.config.local_delivery() == LocalDelivery::AfterValidation
+            && self.mesh.contains_key(&topic_hash)
+        {
+            match self.data_transform.inbound_transform(raw_message) {
+                Ok(message) => {
+                    self.events
+                        .push_back(ToSwarm::GenerateEvent(Event::LocalMessage {
+                            message_id: msg_id.clone(),
+                            message,
+                        }));
+                }
+                Err(e) => {
+                    tracing::debug!(
+                        message=%msg_id,
+                        "Not delivering published message locally. Transform error: {:?}",
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    /// Returns the [`TopicSubscriptionFilter`] of the behaviour.
+    pub fn subscription_filter(&self) -> &F {
+        &self.subscription_filter
+    }
+
+    /// Returns a mutable reference to the [`TopicSubscriptionFilter`] of the behaviour, e.g. to
+    /// extend the allowed topics at runtime.
+    ///
+    /// Changes only apply to later subscriptions, existing subscriptions of the local node and of
+    /// remotes are kept.
+    pub fn subscription_filter_mut(&mut self) -> &mut F {
+        &mut self.subscription_filter
+    }
+
+    /// Replaces the [`TopicSubscriptionFilter`] of the behaviour.
+    ///
+    /// Like with [`Behaviour::subscription_filter_mut`], existing subscriptions are kept.
+    pub fn set_subscription_filter(&mut self, subscription_filter: F) {
+        self.subscription_filter = subscription_filter;
+    }
+
+    /// Installs a [`MessageValidator`] that validates received messages asynchronously.
+    ///
+    /// Received messages are then only emitted as [`Event::Message`] and forwarded once the
+    /// validator accepted them, regardless of [`Config::validate_messages()`]. Results must not be
+    /// reported through [`Behaviour::report_message_validation_result`] anymore.
+    pub fn set_message_validator(&mut self, validator: impl MessageValidator) {
+        self.validator = Some(Box::new(validator));
+    }
+
+    /// Installs a [`Tracer`] that is called with a [`TraceEvent`] for every published,
+    /// delivered, duplicate and rejected message, every mesh change and every control message
+    /// sent or received.
+    pub fn set_tracer(&mut self, tracer: impl Tracer) {
+        self.tracer = Some(Box::new(tracer));
+    }
+
+    /// Whether received messages need to be validated before being forwarded.
+    fn validates_messages(&self) -> bool {
+        self.config.validate_messages() || self.validator.is_some()
     }
 
     /// This function should be called when [`Config::validate_messages()`] is `true` after
@@ -802,6 +1299,15 @@ where
                 metrics.register_msg_validation(&raw_message.topic, &acceptance);
             }
 
+            if let Some(tracer) = self.tracer.as_mut() {
+                tracer.trace(TraceEvent::RejectMessage {
+                    message_id: Some(msg_id.clone()),
+                    topic: raw_message.topic.clone(),
+                    received_from: *propagation_source,
+                    reason: reject_reason,
+                });
+            }
+
             // Tell peer_score about reject
             // Reject the original source, and any duplicates we've seen from other peers.
             if let Some((peer_score, ..)) = &mut self.peer_score {
@@ -831,11 +1337,32 @@ where
         self.check_explicit_peer_connection(peer_id);
     }
 
+    /// Adds a new peer to the list of explicitly connected peers, together with addresses to
+    /// (re)connect to it on.
+    ///
+    /// The addresses are added to the ones previously known for this peer.
+    pub fn add_explicit_peer_with_addresses(
+        &mut self,
+        peer_id: &PeerId,
+        addresses: impl IntoIterator<Item = Multiaddr>,
+    ) {
+        let known = self.explicit_peer_addresses.entry(*peer_id).or_default();
+        for address in addresses {
+            if !known.contains(&address) {
+                known.push(address);
+            }
+        }
+
+        self.add_explicit_peer(peer_id);
+    }
+
     /// This removes the peer from explicitly connected peers, note that this does not disconnect
     /// the peer.
     pub fn remove_explicit_peer(&mut self, peer_id: &PeerId) {
         tracing::debug!(peer=%peer_id, "Removing explicit peer");
         self.explicit_peers.remove(peer_id);
+        self.explicit_peer_addresses.remove(peer_id);
+        self.explicit_peers_down.remove(peer_id);
     }
 
     /// Blacklists a peer. All messages from this peer will be rejected and any message that was
@@ -901,6 +1428,59 @@ where
         }
     }
 
+    /// Publishes messages on the given topic with a different [`MessageAuthenticity`] than the one
+    /// the behaviour was created with, e.g. to sign messages of consensus topics while keeping
+    /// telemetry topics anonymous.
+    ///
+    /// Fails if messages published with `authenticity` would be rejected by the
+    /// [`ValidationMode`] of the topic, see
+    /// [`TopicConfig::validation_mode`](crate::TopicConfig::validation_mode).
+    pub fn set_topic_message_authenticity(
+        &mut self,
+        topic: impl Into<TopicHash>,
+        authenticity: MessageAuthenticity,
+    ) -> Result<(), &'static str> {
+        let topic = topic.into();
+        validate_config(&authenticity, self.config.validation_mode_for_topic(&topic))?;
+        self.topic_publish_configs
+            .insert(topic, authenticity.into());
+        Ok(())
+    }
+
+    /// Selects `mesh_n` random peers for the fanout of the given topic.
+    fn select_fanout_peers(&self, topic_hash: &TopicHash) -> BTreeSet<PeerId> {
+        let mesh_n = self.config.mesh_n_for_topic(topic_hash);
+        get_random_peers(&self.connected_peers, topic_hash, mesh_n, |p| {
+            !self.explicit_peers.contains(p)
+                && !self.score_below_threshold(p, |pst| pst.publish_threshold).0
+        })
+    }
+
+    /// The [`PublishConfig`] used for messages on the given topic.
+    fn publish_config_for_topic(&self, topic: &TopicHash) -> &PublishConfig {
+        self.topic_publish_configs
+            .get(topic)
+            .unwrap_or(&self.publish_config)
+    }
+
+    /// Adds the signed [`PeerRecord`] of a peer, e.g. obtained through rendezvous, to be passed on
+    /// to pruned peers through peer exchange so they can dial the peer.
+    ///
+    /// A record with a lower sequence number than the current record of the peer is ignored.
+    /// Records are dropped once the peer disconnects.
+    pub fn add_peer_record(&mut self, record: PeerRecord) {
+        match self.peer_records.entry(record.peer_id()) {
+            Entry::Occupied(mut entry) => {
+                if entry.get().seq() <= record.seq() {
+                    entry.insert(record);
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(record);
+            }
+        }
+    }
+
     /// Returns a scoring parameters for a topic if existent.
     pub fn get_topic_params<H: Hasher>(&self, topic: &Topic<H>) -> Option<&TopicScoreParams> {
         self.peer_score.as_ref()?.0.get_topic_params(&topic.hash())
@@ -949,7 +1529,7 @@ where
 
             // Add up to mesh_n of them them to the mesh
             // NOTE: These aren't randomly added, currently FIFO
-            let add_peers = std::cmp::min(peers.len(), self.config.mesh_n());
+            let add_peers = std::cmp::min(peers.len(), self.config.mesh_n_for_topic(topic_hash));
             tracing::debug!(
                 topic=%topic_hash,
                 "JOIN: Adding {:?} peers from the fanout for topic",
@@ -972,12 +1552,13 @@ where
         }
 
         // check if we need to get more peers, which we randomly select
-        if added_peers.len() < self.config.mesh_n() {
+        let mesh_n = self.config.mesh_n_for_topic(topic_hash);
+        if added_peers.len() < mesh_n {
             // get the peers
             let new_peers = get_random_peers(
                 &self.connected_peers,
                 topic_hash,
-                self.config.mesh_n() - added_peers.len(),
+                mesh_n - added_peers.len(),
                 |peer| {
                     !added_peers.contains(peer)
                         && !self.explicit_peers.contains(peer)
@@ -1001,6 +1582,13 @@ where
         }
 
         for peer_id in added_peers {
+            if let Some(tracer) = self.tracer.as_mut() {
+                tracer.trace(TraceEvent::Graft {
+                    peer_id,
+                    topic: topic_hash.clone(),
+                });
+            }
+
             // Send a GRAFT control message
             tracing::debug!(peer=%peer_id, "JOIN: Sending Graft message to peer");
             if let Some((peer_score, ..)) = &mut self.peer_score {
@@ -1071,7 +1659,13 @@ where
                 |p| p != peer && !self.score_below_threshold(p, |_| 0.0).0,
             )
             .into_iter()
-            .map(|p| PeerInfo { peer_id: Some(p) })
+            .map(|p| PeerInfo {
+                peer_id: Some(p),
+                signed_peer_record: self
+                    .peer_records
+                    .get(&p)
+                    .map(|r| r.to_signed_envelope().into_protobuf_encoding()),
+            })
             .collect()
         } else {
             Vec::new()
@@ -1103,6 +1697,13 @@ where
                 m.left(topic_hash)
             }
             for peer in peers {
+                if let Some(tracer) = self.tracer.as_mut() {
+                    tracer.trace(TraceEvent::Prune {
+                        peer_id: peer,
+                        topic: topic_hash.clone(),
+                    });
+                }
+
                 // Send a PRUNE control message
                 tracing::debug!(%peer, "LEAVE: Sending PRUNE to peer");
                 let on_unsubscribe = true;
@@ -1134,6 +1735,15 @@ where
         }
     }
 
+    /// Reports an explicit peer as down, unless it has already been reported.
+    fn report_explicit_peer_down(&mut self, peer_id: PeerId) {
+        if self.explicit_peers_down.insert(peer_id) {
+            tracing::debug!(peer=%peer_id, "Explicit peer is down");
+            self.events
+                .push_back(ToSwarm::GenerateEvent(Event::ExplicitPeerDown { peer_id }));
+        }
+    }
+
     /// Determines if a peer's score is below a given `PeerScoreThreshold` chosen via the
     /// `threshold` parameter.
     fn score_below_threshold(
@@ -1187,19 +1797,22 @@ where
         }
 
         if let Some(iasked) = self.count_sent_iwant.get(peer_id) {
-            if *iasked >= self.config.max_ihave_length() {
+            if *iasked >= self.config.iwant_budget() {
                 tracing::debug!(
                     peer=%peer_id,
                     "IHAVE: peer has already advertised too many messages ({}); ignoring",
                     *iasked
                 );
+                if let Some(metrics) = self.metrics.as_mut() {
+                    metrics.iwant_budget_clamped();
+                }
                 return;
             }
         }
 
         tracing::trace!(peer=%peer_id, "Handling IHAVE for peer");
 
-        let mut iwant_ids = HashSet::new();
+        let mut iwant_ids = HashMap::new();
 
         let want_message = |id: &MessageId| {
             if self.duplicate_cache.contains(id) {
@@ -1228,7 +1841,7 @@ where
 
             for id in ids.into_iter().filter(want_message) {
                 // have not seen this message and are not currently requesting it
-                if iwant_ids.insert(id) {
+                if iwant_ids.insert(id, topic.clone()).is_none() {
                     // Register the IWANT metric
                     if let Some(metrics) = self.metrics.as_mut() {
                         metrics.register_iwant(&topic);
@@ -1240,8 +1853,11 @@ where
         if !iwant_ids.is_empty() {
             let iasked = self.count_sent_iwant.entry(*peer_id).or_insert(0);
             let mut iask = iwant_ids.len();
-            if *iasked + iask > self.config.max_ihave_length() {
-                iask = self.config.max_ihave_length().saturating_sub(*iasked);
+            if *iasked + iask > self.config.iwant_budget() {
+                iask = self.config.iwant_budget().saturating_sub(*iasked);
+                if let Some(metrics) = self.metrics.as_mut() {
+                    metrics.iwant_budget_clamped();
+                }
             }
 
             // Send the list of IWANT control messages
@@ -1253,13 +1869,19 @@ where
             );
 
             // Ask in random order
-            let mut iwant_ids_vec: Vec<_> = iwant_ids.into_iter().collect();
+            let mut iwant_ids_vec: Vec<_> = iwant_ids.keys().cloned().collect();
             let mut rng = thread_rng();
             iwant_ids_vec.partial_shuffle(&mut rng, iask);
 
             iwant_ids_vec.truncate(iask);
             *iasked += iask;
 
+            if let Some(metrics) = self.metrics.as_mut() {
+                for message_id in &iwant_ids_vec {
+                    metrics.register_iwant_sent(&iwant_ids[message_id], message_id);
+                }
+            }
+
             for message_id in &iwant_ids_vec {
                 // Add all messages to the pending list
                 self.pending_iwant_msgs.insert(message_id.clone());
@@ -1425,7 +2047,7 @@ where
 
                     // check mesh upper bound and only allow graft if the upper bound is not reached or
                     // if it is an outbound peer
-                    if peers.len() >= self.config.mesh_n_high()
+                    if peers.len() >= self.config.mesh_n_high_for_topic(&topic_hash)
                         && !self.outbound_peers.contains(peer_id)
                     {
                         to_prune_topics.insert(topic_hash.clone());
@@ -1443,6 +2065,12 @@ where
                         if let Some(m) = self.metrics.as_mut() {
                             m.peers_included(&topic_hash, Inclusion::Subscribed, 1)
                         }
+                        if let Some(tracer) = self.tracer.as_mut() {
+                            tracer.trace(TraceEvent::Graft {
+                                peer_id: *peer_id,
+                                topic: topic_hash.clone(),
+                            });
+                        }
                     }
 
                     // If the peer did not previously exist in any mesh, inform the handler
@@ -1510,6 +2138,12 @@ where
                 if let Some(m) = self.metrics.as_mut() {
                     m.peers_removed(topic_hash, reason, 1)
                 }
+                if let Some(tracer) = self.tracer.as_mut() {
+                    tracer.trace(TraceEvent::Prune {
+                        peer_id: *peer_id,
+                        topic: topic_hash.clone(),
+                    });
+                }
 
                 if let Some((peer_score, ..)) = &mut self.peer_score {
                     peer_score.prune(peer_id, topic_hash.clone());
@@ -1564,12 +2198,9 @@ where
                         continue;
                     }
 
-                    // NOTE: We cannot dial any peers from PX currently as we typically will not
-                    // know their multiaddr. Until SignedRecords are spec'd this
-                    // remains a stub. By default `config.prune_peers()` is set to zero and
-                    // this is skipped. If the user modifies this, this will only be able to
-                    // dial already known peers (from an external discovery mechanism for
-                    // example).
+                    // By default `config.prune_peers()` is set to zero and this is skipped.
+                    // Peers without a signed peer record can only be dialed if their addresses
+                    // are already known (from an external discovery mechanism for example).
                     if self.config.prune_peers() > 0 {
                         self.px_connect(px);
                     }
@@ -1581,11 +2212,12 @@ where
 
     fn px_connect(&mut self, mut px: Vec<PeerInfo>) {
         let n = self.config.prune_peers();
-        // Ignore peerInfo with no ID
-        //
-        //TODO: Once signed records are spec'd: Can we use peerInfo without any IDs if they have a
-        // signed peer record?
-        px.retain(|p| p.peer_id.is_some());
+        // Ignore peerInfo that can't be dialed
+        if self.config.px_require_signed_records() {
+            px.retain(|p| p.signed_peer_record.is_some());
+        } else {
+            px.retain(|p| p.peer_id.is_some() || p.signed_peer_record.is_some());
+        }
         if px.len() > n {
             // only use at most prune_peers many random peers
             let mut rng = thread_rng();
@@ -1594,17 +2226,35 @@ where
         }
 
         for p in px {
-            // TODO: Once signed records are spec'd: extract signed peer record if given and handle
-            // it, see https://github.com/libp2p/specs/pull/217
-            if let Some(peer_id) = p.peer_id {
-                // mark as px peer
-                self.px_peers.insert(peer_id);
-
-                // dial peer
-                self.events.push_back(ToSwarm::Dial {
-                    opts: DialOpts::peer_id(peer_id).build(),
-                });
-            }
+            let record = match p.signed_peer_record.as_deref().map(decode_peer_record) {
+                Some(Some(record)) if p.peer_id.map_or(true, |id| id == record.peer_id()) => {
+                    Some(record)
+                }
+                Some(_) => {
+                    tracing::debug!(peer=?p.peer_id, "PX: ignoring invalid signed peer record");
+                    None
+                }
+                None => None,
+            };
+
+            let opts = match (record, p.peer_id) {
+                (Some(record), _) => DialOpts::peer_id(record.peer_id())
+                    .addresses(record.addresses().to_vec())
+                    .build(),
+                (None, Some(peer_id)) if !self.config.px_require_signed_records() => {
+                    DialOpts::peer_id(peer_id).build()
+                }
+                (None, _) => continue,
+            };
+            let peer_id = opts
+                .get_peer_id()
+                .expect("PX dial options always contain a peer id");
+
+            // mark as px peer
+            self.px_peers.insert(peer_id);
+
+            // dial peer
+            self.events.push_back(ToSwarm::Dial { opts });
         }
     }
 
@@ -1660,13 +2310,16 @@ where
         // If we are not validating messages, assume this message is validated
         // This will allow the message to be gossiped without explicitly calling
         // `validate_message`.
-        if !self.config.validate_messages() {
+        if !self.validates_messages() {
             raw_message.validated = true;
         }
 
         // reject messages claiming to be from ourselves but not locally published
         let self_published = !self.config.allow_self_origin()
-            && if let Some(own_id) = self.publish_config.get_own_id() {
+            && if let Some(own_id) = self
+                .publish_config_for_topic(&raw_message.topic)
+                .get_own_id()
+            {
                 own_id != propagation_source
                     && raw_message.source.as_ref().map_or(false, |s| s == own_id)
             } else {
@@ -1715,7 +2368,18 @@ where
         };
 
         // Calculate the message id on the transformed data.
-        let msg_id = self.config.message_id(&message);
+        let Ok(msg_id) = self.config.message_id(MessageIdContext {
+            message: &message,
+            propagation_source: Some(propagation_source),
+        }) else {
+            tracing::debug!("Invalid message. Message id function rejected the message");
+            self.handle_invalid_message(
+                propagation_source,
+                &raw_message,
+                RejectReason::ValidationError(ValidationError::InvalidMessageId),
+            );
+            return;
+        };
 
         // Check the validity of the message
         // Peers get penalized if this message is invalid. We don't add it to the duplicate cache
@@ -1726,10 +2390,41 @@ where
 
         if !self.duplicate_cache.insert(msg_id.clone()) {
             tracing::debug!(message=%msg_id, "Message already received, ignoring");
+            if let Some(metrics) = self.metrics.as_mut() {
+                metrics.msg_recvd_duplicate(&message.topic);
+            }
+            if let Some(tracer) = self.tracer.as_mut() {
+                tracer.trace(TraceEvent::DuplicateMessage {
+                    message_id: msg_id.clone(),
+                    topic: message.topic.clone(),
+                    received_from: *propagation_source,
+                });
+            }
             if let Some((peer_score, ..)) = &mut self.peer_score {
                 peer_score.duplicated_message(propagation_source, &msg_id, &message.topic);
             }
             self.mcache.observe_duplicate(&msg_id, propagation_source);
+            if self
+                .mesh
+                .get(&message.topic)
+                .map_or(false, |peers| peers.contains(propagation_source))
+            {
+                let since_first = self
+                    .first_deliveries
+                    .as_mut()
+                    .and_then(|deliveries| deliveries.elapsed(&msg_id));
+                if let Some(latencies) = &mut self.delivery_latencies {
+                    latencies.duplicate_delivery(*propagation_source, since_first);
+                }
+                if let Some(choke_state) = &mut self.choke_state {
+                    choke_state.duplicate_delivery(
+                        &message.topic,
+                        *propagation_source,
+                        since_first,
+                        self.config.choke_late_after(),
+                    );
+                }
+            }
             return;
         }
         tracing::debug!(
@@ -1740,6 +2435,7 @@ where
         // Record the received message with the metrics
         if let Some(metrics) = self.metrics.as_mut() {
             metrics.msg_recvd(&message.topic);
+            metrics.iwant_fulfilled(&msg_id);
         }
 
         // Tells score that message arrived (but is maybe not fully validated yet).
@@ -1749,12 +2445,56 @@ where
             gossip_promises.message_delivered(&msg_id);
         }
 
+        // The first delivery is recorded regardless of the source, such that later deliveries by
+        // mesh peers are measured against it.
+        if let Some(first_deliveries) = &mut self.first_deliveries {
+            first_deliveries.record(&msg_id);
+        }
+
+        if self
+            .mesh
+            .get(&message.topic)
+            .map_or(false, |peers| peers.contains(propagation_source))
+        {
+            if let Some(choke_state) = &mut self.choke_state {
+                choke_state.first_delivery(&message.topic, *propagation_source);
+            }
+            if let Some(latencies) = &mut self.delivery_latencies {
+                latencies.first_delivery(*propagation_source);
+            }
+        }
+
         // Add the message to our memcache
         self.mcache.put(&msg_id, raw_message.clone());
 
         // Dispatch the message to the user if we are subscribed to any of the topics
         if self.mesh.contains_key(&message.topic) {
+            if let Some(validator) = self.validator.as_mut() {
+                let validation = validator.validate(propagation_source, &msg_id, &message);
+                let rejected = self
+                    .pending_validations
+                    .try_push(validation, (*propagation_source, msg_id, message))
+                    .err()
+                    .map(|(_, data)| data);
+                if let Some((source, msg_id, _)) = rejected {
+                    tracing::warn!(message=%msg_id, "Too many pending validations, ignoring message");
+                    let _ = self.report_message_validation_result(
+                        &msg_id,
+                        &source,
+                        MessageAcceptance::Ignore,
+                    );
+                }
+                return;
+            }
+
             tracing::debug!("Sending received message to user");
+            if let Some(tracer) = self.tracer.as_mut() {
+                tracer.trace(TraceEvent::DeliverMessage {
+                    message_id: msg_id.clone(),
+                    topic: message.topic.clone(),
+                    received_from: *propagation_source,
+                });
+            }
             self.events
                 .push_back(ToSwarm::GenerateEvent(Event::Message {
                     propagation_source: *propagation_source,
@@ -1770,13 +2510,13 @@ where
         }
 
         // forward the message to mesh peers, if no validation is required
-        if !self.config.validate_messages() {
+        if !self.validates_messages() {
             if self
                 .forward_msg(
                     &msg_id,
                     raw_message,
                     Some(propagation_source),
-                    HashSet::new(),
+                    PeerSet::new(),
                 )
                 .is_err()
             {
@@ -1793,14 +2533,40 @@ where
         raw_message: &RawMessage,
         reject_reason: RejectReason,
     ) {
+        if self.peer_score.is_none() && self.tracer.is_none() {
+            return;
+        }
+
+        let message = self
+            .data_transform
+            .inbound_transform(raw_message.clone())
+            .ok()
+            .and_then(|message| {
+                let message_id = self
+                    .config
+                    .message_id(MessageIdContext {
+                        message: &message,
+                        propagation_source: Some(propagation_source),
+                    })
+                    .ok()?;
+                Some((message, message_id))
+            });
+
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.trace(TraceEvent::RejectMessage {
+                message_id: message.as_ref().map(|(_, message_id)| message_id.clone()),
+                topic: raw_message.topic.clone(),
+                received_from: *propagation_source,
+                reason: reject_reason,
+            });
+        }
+
         if let Some((peer_score, .., gossip_promises)) = &mut self.peer_score {
             if let Some(metrics) = self.metrics.as_mut() {
                 metrics.register_invalid_message(&raw_message.topic);
             }
 
-            if let Ok(message) = self.data_transform.inbound_transform(raw_message.clone()) {
-                let message_id = self.config.message_id(&message);
-
+            if let Some((message, message_id)) = message {
                 peer_score.reject_message(
                     propagation_source,
                     &message_id,
@@ -1857,9 +2623,21 @@ where
                     "Subscription filter error: {}; ignoring RPC from peer",
                     s
                 );
+                report_filtered_subscriptions(
+                    *propagation_source,
+                    subscriptions,
+                    &HashSet::new(),
+                    &mut self.events,
+                );
                 return;
             }
         };
+        report_filtered_subscriptions(
+            *propagation_source,
+            subscriptions,
+            &filtered_topics,
+            &mut self.events,
+        );
 
         for subscription in filtered_topics {
             // get the peers from the mapping, or insert empty lists if the topic doesn't exist
@@ -1892,10 +2670,18 @@ where
                             .backoffs
                             .is_backoff_with_slack(topic_hash, propagation_source)
                     {
+                        // Peers of an imported topology are grafted up to the mesh target.
+                        let is_restored_mesh_peer =
+                            self.restored_topology.as_ref().is_some_and(|restored| {
+                                restored.is_mesh_peer(topic_hash, propagation_source)
+                            });
+                        let mesh_n = if is_restored_mesh_peer {
+                            self.config.mesh_n_for_topic(topic_hash)
+                        } else {
+                            self.config.mesh_n_low_for_topic(topic_hash)
+                        };
                         if let Some(peers) = self.mesh.get_mut(topic_hash) {
-                            if peers.len() < self.config.mesh_n_low()
-                                && peers.insert(*propagation_source)
-                            {
+                            if peers.len() < mesh_n && peers.insert(*propagation_source) {
                                 tracing::debug!(
                                     peer=%propagation_source,
                                     topic=%topic_hash,
@@ -1904,6 +2690,12 @@ where
                                 if let Some(m) = self.metrics.as_mut() {
                                     m.peers_included(topic_hash, Inclusion::Subscribed, 1)
                                 }
+                                if let Some(tracer) = self.tracer.as_mut() {
+                                    tracer.trace(TraceEvent::Graft {
+                                        peer_id: *propagation_source,
+                                        topic: topic_hash.clone(),
+                                    });
+                                }
                                 // send graft to the peer
                                 tracing::debug!(
                                     peer=%propagation_source,
@@ -1915,6 +2707,22 @@ where
                                 }
                                 topics_to_graft.push(topic_hash.clone());
                             }
+                        } else if self.restored_topology.as_ref().is_some_and(|restored| {
+                            restored.is_fanout_peer(topic_hash, propagation_source)
+                        }) {
+                            let peers = self.fanout.entry(topic_hash.clone()).or_default();
+                            if peers.len() < self.config.mesh_n_for_topic(topic_hash)
+                                && peers.insert(*propagation_source)
+                            {
+                                tracing::debug!(
+                                    peer=%propagation_source,
+                                    topic=%topic_hash,
+                                    "SUBSCRIPTION: Adding restored peer to the fanout for topic"
+                                );
+                                self.fanout_last_pub
+                                    .entry(topic_hash.clone())
+                                    .or_insert_with(Instant::now);
+                            }
                         }
                     }
                     // generates a subscription event to be polled
@@ -1985,15 +2793,140 @@ where
     }
 
     /// Applies penalties to peers that did not respond to our IWANT requests.
-    fn apply_iwant_penalties(&mut self) {
+    ///
+    /// Returns the peers that have been penalized.
+    fn apply_iwant_penalties(&mut self) -> HashSet<PeerId> {
+        let mut penalized = HashSet::new();
         if let Some((peer_score, .., gossip_promises)) = &mut self.peer_score {
             for (peer, count) in gossip_promises.get_broken_promises() {
                 peer_score.add_penalty(&peer, count);
                 if let Some(metrics) = self.metrics.as_mut() {
                     metrics.register_score_penalty(Penalty::BrokenPromise);
                 }
+                penalized.insert(peer);
+            }
+        }
+        penalized
+    }
+
+    /// Updates the number of consecutive heartbeats in which each mesh peer was slow and returns
+    /// the peers that reached [`Config::slow_peer_threshold`].
+    fn slow_mesh_peers(
+        &mut self,
+        late_peers: &HashSet<PeerId>,
+    ) -> Vec<(PeerId, SlowPeerReason, SlowPeerStats)> {
+        let threshold = self.config.slow_peer_threshold();
+        if threshold == 0 {
+            return Vec::new();
+        }
+
+        let mesh_peers = self
+            .mesh
+            .values()
+            .flatten()
+            .copied()
+            .collect::<BTreeSet<_>>();
+        self.slow_peer_strikes
+            .retain(|peer_id, _| mesh_peers.contains(peer_id));
+        if let Some(latencies) = &mut self.delivery_latencies {
+            latencies.retain_mesh_peers(&mesh_peers);
+        }
+
+        let mut slow_peers = Vec::new();
+        for peer_id in mesh_peers {
+            let latency = self
+                .delivery_latencies
+                .as_mut()
+                .map(|latencies| latencies.take(&peer_id))
+                .unwrap_or_default();
+            let stats = SlowPeerStats {
+                congested_connections: self
+                    .congested_connections
+                    .get(&peer_id)
+                    .map_or(0, |connections| connections.len()),
+                deliveries: latency.deliveries,
+                mean_delivery_latency: latency.mean(),
+            };
+
+            let reason = if stats.congested_connections > 0 {
+                SlowPeerReason::SendQueueFull
+            } else if late_peers.contains(&peer_id) {
+                SlowPeerReason::LateIwantResponses
+            } else if stats
+                .mean_delivery_latency
+                .zip(self.config.slow_peer_latency())
+                .map_or(false, |(mean, max)| mean > max)
+            {
+                SlowPeerReason::HighLatency
+            } else {
+                self.slow_peer_strikes.remove(&peer_id);
+                continue;
+            };
+
+            let strikes = self.slow_peer_strikes.entry(peer_id).or_default();
+            *strikes += 1;
+            if *strikes >= threshold {
+                self.slow_peer_strikes.remove(&peer_id);
+                slow_peers.push((peer_id, reason, stats));
+            }
+        }
+        slow_peers
+    }
+
+    /// Penalizes a slow peer and removes it from all meshes, adding the required PRUNEs to
+    /// `to_prune`.
+    fn prune_slow_peer(
+        &mut self,
+        peer_id: PeerId,
+        reason: SlowPeerReason,
+        stats: SlowPeerStats,
+        to_prune: &mut HashMap<PeerId, Vec<TopicHash>>,
+    ) {
+        tracing::debug!(
+            peer=%peer_id,
+            ?reason,
+            ?stats,
+            "HEARTBEAT: Prune slow peer"
+        );
+
+        if let Some((peer_score, ..)) = &mut self.peer_score {
+            peer_score.add_penalty(&peer_id, 1);
+            if let Some(metrics) = self.metrics.as_mut() {
+                metrics.register_score_penalty(Penalty::SlowPeer);
+            }
+        }
+
+        let mut topics = Vec::new();
+        for (topic_hash, peers) in self.mesh.iter_mut() {
+            if !peers.remove(&peer_id) {
+                continue;
+            }
+            self.backoffs
+                .update_backoff(topic_hash, &peer_id, self.config.prune_backoff());
+            if let Some(m) = self.metrics.as_mut() {
+                m.peers_removed(topic_hash, Churn::Slow, 1);
+            }
+            if let Some(tracer) = self.tracer.as_mut() {
+                tracer.trace(TraceEvent::Prune {
+                    peer_id,
+                    topic: topic_hash.clone(),
+                });
             }
+            topics.push(topic_hash.clone());
         }
+
+        to_prune
+            .entry(peer_id)
+            .or_default()
+            .extend(topics.iter().cloned());
+
+        self.events
+            .push_back(ToSwarm::GenerateEvent(Event::SlowPeer {
+                peer_id,
+                topics,
+                reason,
+                stats,
+            }));
     }
 
     /// Heartbeat function which shifts the memcache and updates the mesh.
@@ -2010,12 +2943,36 @@ where
         // clean up expired backoffs
         self.backoffs.heartbeat();
 
+        // forget the peers of an imported topology once it is stale
+        if self
+            .restored_topology
+            .as_ref()
+            .is_some_and(|restored| restored.is_expired(start))
+        {
+            self.restored_topology = None;
+        }
+
         // clean up ihave counters
         self.count_sent_iwant.clear();
         self.count_received_ihave.clear();
 
         // apply iwant penalties
-        self.apply_iwant_penalties();
+        let late_peers = self.apply_iwant_penalties();
+
+        // prune mesh peers that have been too slow for too long, without PX
+        for (peer_id, reason, stats) in self.slow_mesh_peers(&late_peers) {
+            self.prune_slow_peer(peer_id, reason, stats, &mut to_prune);
+            no_px.insert(peer_id);
+        }
+
+        // report messages that were dropped due to full send queues
+        for (peer_id, dropped) in self.dropped_messages.drain() {
+            self.events
+                .push_back(ToSwarm::GenerateEvent(Event::MessagesDropped {
+                    peer_id,
+                    dropped,
+                }));
+        }
 
         // check connections to explicit peers
         if self.heartbeat_ticks % self.config.check_explicit_peers_ticks() == 0 {
@@ -2034,6 +2991,26 @@ where
             }
         }
 
+        // report peers whose score crossed the graylist threshold
+        if let Some((_, thresholds, ..)) = &self.peer_score {
+            for (peer_id, score) in &scores {
+                let graylisted = *score < thresholds.graylist_threshold;
+                if graylisted && self.graylisted_peers.insert(**peer_id) {
+                    self.events
+                        .push_back(ToSwarm::GenerateEvent(Event::Graylisted {
+                            peer_id: **peer_id,
+                            score: *score,
+                        }));
+                } else if !graylisted && self.graylisted_peers.remove(*peer_id) {
+                    self.events
+                        .push_back(ToSwarm::GenerateEvent(Event::GraylistLifted {
+                            peer_id: **peer_id,
+                            score: *score,
+                        }));
+                }
+            }
+        }
+
         // maintain the mesh for each topic
         for (topic_hash, peers) in self.mesh.iter_mut() {
             let explicit_peers = &self.explicit_peers;
@@ -2075,16 +3052,20 @@ where
                 peers.remove(&peer_id);
             }
 
+            let mesh_n = self.config.mesh_n_for_topic(topic_hash);
+            let mesh_n_low = self.config.mesh_n_low_for_topic(topic_hash);
+            let mesh_n_high = self.config.mesh_n_high_for_topic(topic_hash);
+
             // too little peers - add some
-            if peers.len() < self.config.mesh_n_low() {
+            if peers.len() < mesh_n_low {
                 tracing::debug!(
                     topic=%topic_hash,
                     "HEARTBEAT: Mesh low. Topic contains: {} needs: {}",
                     peers.len(),
-                    self.config.mesh_n_low()
+                    mesh_n_low
                 );
                 // not enough peers - get mesh_n - current_length more
-                let desired_peers = self.config.mesh_n() - peers.len();
+                let desired_peers = mesh_n - peers.len();
                 let peer_list =
                     get_random_peers(&self.connected_peers, topic_hash, desired_peers, |peer| {
                         !peers.contains(peer)
@@ -2105,14 +3086,14 @@ where
             }
 
             // too many peers - remove some
-            if peers.len() > self.config.mesh_n_high() {
+            if peers.len() > mesh_n_high {
                 tracing::debug!(
                     topic=%topic_hash,
                     "HEARTBEAT: Mesh high. Topic contains: {} needs: {}",
                     peers.len(),
-                    self.config.mesh_n_high()
+                    mesh_n_high
                 );
-                let excess_peer_no = peers.len() - self.config.mesh_n();
+                let excess_peer_no = peers.len() - mesh_n;
 
                 // shuffle the peers and then sort by score ascending beginning with the worst
                 let mut rng = thread_rng();
@@ -2165,7 +3146,7 @@ where
             }
 
             // do we have enough outbound peers?
-            if peers.len() >= self.config.mesh_n_low() {
+            if peers.len() >= mesh_n_low {
                 // count number of outbound peers we have
                 let outbound = { peers.iter().filter(|p| outbound_peers.contains(*p)).count() };
 
@@ -2234,7 +3215,11 @@ where
 
                     // if the median score is below the threshold, select a better peer (if any) and
                     // GRAFT
-                    if median < thresholds.opportunistic_graft_threshold {
+                    let threshold = self
+                        .config
+                        .opportunistic_graft_threshold()
+                        .unwrap_or(thresholds.opportunistic_graft_threshold);
+                    if median < threshold {
                         let peer_list = get_random_peers(
                             &self.connected_peers,
                             topic_hash,
@@ -2259,6 +3244,15 @@ where
                         if let Some(m) = self.metrics.as_mut() {
                             m.peers_included(topic_hash, Inclusion::Random, peer_list.len())
                         }
+                        if !peer_list.is_empty() {
+                            self.events.push_back(ToSwarm::GenerateEvent(
+                                Event::OpportunisticGraft {
+                                    topic: topic_hash.clone(),
+                                    peers: peer_list.iter().copied().collect(),
+                                    median_score: median,
+                                },
+                            ));
+                        }
                         peers.extend(peer_list);
                     }
                 }
@@ -2272,9 +3266,9 @@ where
         // remove expired fanout topics
         {
             let fanout = &mut self.fanout; // help the borrow checker
-            let fanout_ttl = self.config.fanout_ttl();
+            let config = &self.config;
             self.fanout_last_pub.retain(|topic_hash, last_pub_time| {
-                if *last_pub_time + fanout_ttl < Instant::now() {
+                if *last_pub_time + config.fanout_ttl_for_topic(topic_hash) < Instant::now() {
                     tracing::debug!(
                         topic=%topic_hash,
                         "HEARTBEAT: Fanout topic removed due to timeout"
@@ -2318,13 +3312,14 @@ where
             }
 
             // not enough peers
-            if peers.len() < self.config.mesh_n() {
+            let mesh_n = self.config.mesh_n_for_topic(topic_hash);
+            if peers.len() < mesh_n {
                 tracing::debug!(
                     "HEARTBEAT: Fanout low. Contains: {:?} needs: {:?}",
                     peers.len(),
-                    self.config.mesh_n()
+                    mesh_n
                 );
-                let needed_peers = self.config.mesh_n() - peers.len();
+                let needed_peers = mesh_n - peers.len();
                 let explicit_peers = &self.explicit_peers;
                 let new_peers =
                     get_random_peers(&self.connected_peers, topic_hash, needed_peers, |peer_id| {
@@ -2363,6 +3358,11 @@ where
             })
         }
 
+        // re-evaluate which mesh peers are choked
+        if let Some(choke_state) = &mut self.choke_state {
+            choke_state.heartbeat(&self.mesh, &self.config);
+        }
+
         self.emit_gossip();
 
         // send graft/prunes
@@ -2380,6 +3380,7 @@ where
         if let Some(metrics) = self.metrics.as_mut() {
             let duration = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
             metrics.observe_heartbeat_duration(duration);
+            metrics.expire_iwants(self.config.iwant_followup_time());
         }
     }
 
@@ -2387,6 +3388,8 @@ where
     /// and fanout peers
     fn emit_gossip(&mut self) {
         let mut rng = thread_rng();
+        // the number of message ids advertised to each peer, to respect the IHAVE budget
+        let mut ihave_sent: HashMap<PeerId, usize> = HashMap::new();
         for (topic_hash, peers) in self.mesh.iter().chain(self.fanout.iter()) {
             let mut message_ids = self.mcache.get_gossip_message_ids(topic_hash);
             if message_ids.is_empty() {
@@ -2409,17 +3412,22 @@ where
             let n_map = |m| {
                 max(
                     self.config.gossip_lazy(),
-                    (self.config.gossip_factor() * m as f64) as usize,
+                    (self.config.gossip_factor_for_topic(topic_hash) * m as f64) as usize,
                 )
             };
             // get gossip_lazy random peers
-            let to_msg_peers =
+            let mut to_msg_peers =
                 get_random_peers_dynamic(&self.connected_peers, topic_hash, n_map, |peer| {
                     !peers.contains(peer)
                         && !self.explicit_peers.contains(peer)
                         && !self.score_below_threshold(peer, |ts| ts.gossip_threshold).0
                 });
 
+            // choked mesh peers don't receive messages eagerly, so they always get gossip
+            if let Some(choke_state) = &self.choke_state {
+                to_msg_peers.extend(choke_state.choked_peers(topic_hash));
+            }
+
             tracing::debug!("Gossiping IHAVE to {} peers", to_msg_peers.len());
 
             for peer in to_msg_peers {
@@ -2433,6 +3441,28 @@ where
                     peer_message_ids.truncate(self.config.max_ihave_length());
                 }
 
+                if let Some(budget) = self.config.ihave_budget() {
+                    let sent = ihave_sent.entry(peer).or_insert(0);
+                    let remaining = budget.saturating_sub(*sent);
+                    if peer_message_ids.len() > remaining {
+                        tracing::debug!(
+                            %peer,
+                            "IHAVE budget exhausted; advertising {} of {} messages",
+                            remaining,
+                            peer_message_ids.len()
+                        );
+                        peer_message_ids.partial_shuffle(&mut rng, remaining);
+                        peer_message_ids.truncate(remaining);
+                        if let Some(metrics) = self.metrics.as_mut() {
+                            metrics.ihave_budget_clamped();
+                        }
+                    }
+                    *sent += peer_message_ids.len();
+                    if peer_message_ids.is_empty() {
+                        continue;
+                    }
+                }
+
                 // send an IHAVE message
                 Self::control_pool_add(
                     &mut self.control_pool,
@@ -2454,6 +3484,25 @@ where
         mut to_prune: HashMap<PeerId, Vec<TopicHash>>,
         no_px: HashSet<PeerId>,
     ) {
+        if let Some(tracer) = self.tracer.as_mut() {
+            for (peer, topics) in &to_graft {
+                for topic in topics {
+                    tracer.trace(TraceEvent::Graft {
+                        peer_id: *peer,
+                        topic: topic.clone(),
+                    });
+                }
+            }
+            for (peer, topics) in &to_prune {
+                for topic in topics {
+                    tracer.trace(TraceEvent::Prune {
+                        peer_id: *peer,
+                        topic: topic.clone(),
+                    });
+                }
+            }
+        }
+
         // handle the grafts and overlapping prunes per peer
         for (peer, topics) in to_graft.into_iter() {
             for topic in &topics {
@@ -2534,7 +3583,7 @@ where
         msg_id: &MessageId,
         message: RawMessage,
         propagation_source: Option<&PeerId>,
-        originating_peers: HashSet<PeerId>,
+        originating_peers: PeerSet,
     ) -> Result<bool, PublishError> {
         // message is fully validated inform peer_score
         if let Some((peer_score, ..)) = &mut self.peer_score {
@@ -2570,6 +3619,11 @@ where
                     if Some(peer_id) != propagation_source
                         && !originating_peers.contains(peer_id)
                         && Some(peer_id) != message.source.as_ref()
+                        // Choked peers are served through gossip.
+                        && !self
+                            .choke_state
+                            .as_ref()
+                            .map_or(false, |c| c.is_choked(topic, peer_id))
                     {
                         recipient_peers.insert(*peer_id);
                     }
@@ -2598,7 +3652,11 @@ where
         topic: TopicHash,
         data: Vec<u8>,
     ) -> Result<RawMessage, PublishError> {
-        match &mut self.publish_config {
+        let publish_config = match self.topic_publish_configs.get_mut(&topic) {
+            Some(publish_config) => publish_config,
+            None => &mut self.publish_config,
+        };
+        match publish_config {
             PublishConfig::Signing {
                 ref keypair,
                 author,
@@ -2705,13 +3763,39 @@ where
         self.pending_iwant_msgs.clear();
     }
 
-    /// Send a [`RpcOut`] message to a peer. This will wrap the message in an arc if it
-    /// is not already an arc.
+    /// Send a [`RpcOut`] message to a peer. Control messages exceeding the max transmit size are
+    /// split into several messages.
     fn send_message(&mut self, peer_id: PeerId, rpc: RpcOut) {
+        if let RpcOut::Control(control) = rpc {
+            for control in control.split(self.config.max_transmit_size()) {
+                self.send_rpc(peer_id, RpcOut::Control(control));
+            }
+        } else {
+            self.send_rpc(peer_id, rpc);
+        }
+    }
+
+    /// Send a single [`RpcOut`] message to a peer.
+    fn send_rpc(&mut self, peer_id: PeerId, rpc: RpcOut) {
         if let Some(m) = self.metrics.as_mut() {
-            if let RpcOut::Publish(ref message) | RpcOut::Forward(ref message) = rpc {
-                // register bytes sent on the internal metrics.
-                m.msg_sent(&message.topic, message.raw_protobuf_len());
+            // register bytes sent on the internal metrics.
+            match rpc {
+                RpcOut::Publish(ref message) => {
+                    m.msg_published_sent(&message.topic, message.raw_protobuf_len())
+                }
+                RpcOut::Forward(ref message) => {
+                    m.msg_forwarded(&message.topic, message.raw_protobuf_len())
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(tracer) = self.tracer.as_mut() {
+            if let RpcOut::Control(ref control) = rpc {
+                tracer.trace(TraceEvent::ControlSent {
+                    peer_id,
+                    control: control.clone(),
+                });
             }
         }
 
@@ -2732,6 +3816,8 @@ where
             ..
         }: ConnectionEstablished,
     ) {
+        self.explicit_peers_down.remove(&peer_id);
+
         // Diverging from the go implementation we only want to consider a peer as outbound peer
         // if its first connection is outbound.
 
@@ -2813,6 +3899,13 @@ where
             }
         }
 
+        if let Some(connections) = self.congested_connections.get_mut(&peer_id) {
+            connections.remove(&connection_id);
+            if connections.is_empty() {
+                self.congested_connections.remove(&peer_id);
+            }
+        }
+
         if remaining_established != 0 {
             // Remove the connection from the list
             if let Some(peer) = self.connected_peers.get_mut(&peer_id) {
@@ -2862,6 +3955,12 @@ where
                                 m.peers_removed(topic, Churn::Dc, 1);
                                 m.set_mesh_peers(topic, mesh_peers.len());
                             }
+                            if let Some(tracer) = self.tracer.as_mut() {
+                                tracer.trace(TraceEvent::Prune {
+                                    peer_id,
+                                    topic: topic.clone(),
+                                });
+                            }
                         };
                     }
 
@@ -2879,6 +3978,7 @@ where
             // Forget px and outbound status for this peer
             self.px_peers.remove(&peer_id);
             self.outbound_peers.remove(&peer_id);
+            self.peer_records.remove(&peer_id);
 
             // If metrics are enabled, register the disconnection of a peer based on its protocol.
             if let Some(metrics) = self.metrics.as_mut() {
@@ -2891,10 +3991,29 @@ where
             }
 
             self.connected_peers.remove(&peer_id);
+            self.slow_peer_strikes.remove(&peer_id);
+            self.graylisted_peers.remove(&peer_id);
+            if let Some(choke_state) = &mut self.choke_state {
+                choke_state.remove_peer(&peer_id);
+            }
 
             if let Some((peer_score, ..)) = &mut self.peer_score {
                 peer_score.remove_peer(&peer_id);
             }
+
+            // Explicit peers are reconnected to during the next explicit peer check.
+            if self.explicit_peers.contains(&peer_id) {
+                self.report_explicit_peer_down(peer_id);
+            }
+        }
+    }
+
+    fn on_dial_failure(&mut self, DialFailure { peer_id, .. }: DialFailure) {
+        let Some(peer_id) = peer_id else {
+            return;
+        };
+        if self.explicit_peers.contains(&peer_id) && !self.connected_peers.contains_key(&peer_id) {
+            self.report_explicit_peer_down(peer_id);
         }
     }
 
@@ -2954,7 +4073,25 @@ where
         _: &Multiaddr,
         _: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
-        Ok(Handler::new(self.config.protocol_config()))
+        Ok(Handler::new(
+            self.config.protocol_config(),
+            self.config.slow_peer_queue_len(),
+            self.config.send_queue_capacity(),
+            self.config.batch_interval(),
+        ))
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        _: &[Multiaddr],
+        _: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        Ok(maybe_peer
+            .and_then(|peer| self.explicit_peer_addresses.get(&peer))
+            .cloned()
+            .unwrap_or_default())
     }
 
     fn handle_established_outbound_connection(
@@ -2965,16 +4102,41 @@ where
         _: Endpoint,
         _: PortUse,
     ) -> Result<THandler<Self>, ConnectionDenied> {
-        Ok(Handler::new(self.config.protocol_config()))
+        Ok(Handler::new(
+            self.config.protocol_config(),
+            self.config.slow_peer_queue_len(),
+            self.config.send_queue_capacity(),
+            self.config.batch_interval(),
+        ))
     }
 
     fn on_connection_handler_event(
         &mut self,
         propagation_source: PeerId,
-        _connection_id: ConnectionId,
+        connection_id: ConnectionId,
         handler_event: THandlerOutEvent<Self>,
     ) {
         match handler_event {
+            HandlerEvent::SendQueueFull => {
+                self.congested_connections
+                    .entry(propagation_source)
+                    .or_default()
+                    .insert(connection_id);
+            }
+            HandlerEvent::MessagesDropped(dropped) => {
+                self.dropped_messages
+                    .entry(propagation_source)
+                    .or_default()
+                    .merge(dropped);
+            }
+            HandlerEvent::SendQueueDrained => {
+                if let Some(connections) = self.congested_connections.get_mut(&propagation_source) {
+                    connections.remove(&connection_id);
+                    if connections.is_empty() {
+                        self.congested_connections.remove(&propagation_source);
+                    }
+                }
+            }
             HandlerEvent::PeerKind(kind) => {
                 // We have identified the protocol this peer is using
 
@@ -3064,6 +4226,12 @@ where
                 let mut graft_msgs = vec![];
                 let mut prune_msgs = vec![];
                 for control_msg in rpc.control_msgs {
+                    if let Some(tracer) = self.tracer.as_mut() {
+                        tracer.trace(TraceEvent::ControlReceived {
+                            peer_id: propagation_source,
+                            control: control_msg.clone(),
+                        });
+                    }
                     match control_msg {
                         ControlAction::IHave {
                             topic_hash,
@@ -3104,6 +4272,41 @@ where
             return Poll::Ready(event);
         }
 
+        while let Poll::Ready((result, (propagation_source, message_id, message))) =
+            self.pending_validations.poll_unpin(cx)
+        {
+            let acceptance = result.unwrap_or_else(|_| {
+                tracing::debug!(message=%message_id, "Message validation timed out");
+                MessageAcceptance::Ignore
+            });
+            let accepted = matches!(acceptance, MessageAcceptance::Accept);
+
+            match self.report_message_validation_result(
+                &message_id,
+                &propagation_source,
+                acceptance,
+            ) {
+                Ok(true) if accepted => {
+                    if let Some(tracer) = self.tracer.as_mut() {
+                        tracer.trace(TraceEvent::DeliverMessage {
+                            message_id: message_id.clone(),
+                            topic: message.topic.clone(),
+                            received_from: propagation_source,
+                        });
+                    }
+                    return Poll::Ready(ToSwarm::GenerateEvent(Event::Message {
+                        propagation_source,
+                        message_id,
+                        message,
+                    }));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::error!(message=%message_id, "Failed to forward validated message: {e}")
+                }
+            }
+        }
+
         // update scores
         if let Some((peer_score, _, interval, _)) = &mut self.peer_score {
             while let Poll::Ready(Some(_)) = interval.poll_next_unpin(cx) {
@@ -3127,6 +4330,7 @@ where
                 self.on_connection_closed(connection_closed)
             }
             FromSwarm::AddressChange(address_change) => self.on_address_change(address_change),
+            FromSwarm::DialFailure(dial_failure) => self.on_dial_failure(dial_failure),
             _ => {}
         }
     }
@@ -3172,6 +4376,35 @@ fn peer_added_to_mesh(
     });
 }
 
+/// Emits [`Event::SubscriptionsFiltered`] for the subscriptions of a peer that are not part of
+/// the `accepted` set of the [`TopicSubscriptionFilter`].
+fn report_filtered_subscriptions(
+    peer_id: PeerId,
+    subscriptions: &[Subscription],
+    accepted: &HashSet<&Subscription>,
+    events: &mut VecDeque<ToSwarm<Event, HandlerIn>>,
+) {
+    let mut topics = Vec::new();
+    for subscription in subscriptions {
+        if subscription.action == SubscriptionAction::Subscribe
+            && !accepted
+                .iter()
+                .any(|s| s.topic_hash == subscription.topic_hash)
+            && !topics.contains(&subscription.topic_hash)
+        {
+            topics.push(subscription.topic_hash.clone());
+        }
+    }
+
+    if !topics.is_empty() {
+        tracing::debug!(peer=%peer_id, ?topics, "Subscriptions rejected by the filter");
+        events.push_back(ToSwarm::GenerateEvent(Event::SubscriptionsFiltered {
+            peer_id,
+            topics,
+        }));
+    }
+}
+
 /// This is called when peers are removed from a mesh. It checks if the peer exists
 /// in any other mesh. If this is the last mesh they have joined, we return true, in order to
 /// notify the handler to no longer maintain a connection.
@@ -3255,6 +4488,12 @@ fn get_random_peers(
     get_random_peers_dynamic(connected_peers, topic_hash, |_| n, f)
 }
 
+/// Decodes and verifies a signed peer record received through peer exchange.
+fn decode_peer_record(bytes: &[u8]) -> Option<PeerRecord> {
+    let envelope = SignedEnvelope::from_protobuf_encoding(bytes).ok()?;
+    PeerRecord::from_signed_envelope(envelope).ok()
+}
+
 /// Validates the combination of signing, privacy and message validation to ensure the
 /// configuration will not reject published messages.
 fn validate_config(
@@ -3292,6 +4531,7 @@ impl<C: DataTransform, F: TopicSubscriptionFilter> fmt::Debug for Behaviour<C, F
             .field("events", &self.events.len())
             .field("control_pool", &self.control_pool)
             .field("publish_config", &self.publish_config)
+            .field("topic_publish_configs", &self.topic_publish_configs)
             .field("mesh", &self.mesh)
             .field("fanout", &self.fanout)
             .field("fanout_last_pub", &self.fanout_last_pub)
@@ -22,10 +22,20 @@
 
 use super::*;
 use crate::subscription_filter::WhitelistSubscriptionFilter;
-use crate::{config::ConfigBuilder, types::Rpc, IdentTopic as Topic};
+use crate::{
+    config::{ConfigBuilder, TopicConfig},
+    protocol::GossipsubCodec,
+    types::Rpc,
+    IdentTopic as Topic,
+};
 use async_std::net::Ipv4Addr;
+use asynchronous_codec::Encoder;
 use byteorder::{BigEndian, ByteOrder};
+use bytes::BytesMut;
+use futures::future::{self, FutureExt};
 use libp2p_core::ConnectedPoint;
+use libp2p_swarm::cache::MemoryBudget;
+use libp2p_swarm::{ConnectionExtensions, DialError};
 use rand::Rng;
 use std::thread::sleep;
 
@@ -271,6 +281,7 @@ where
                 endpoint: &fake_endpoint,
                 remaining_established: active_connections,
                 cause: None,
+                extensions: &ConnectionExtensions::default(),
             }));
         }
     }
@@ -336,13 +347,14 @@ fn proto_to_message(rpc: &proto::RPC) -> Rpc {
                 .peers
                 .into_iter()
                 .filter_map(|info| {
-                    info.peer_id
-                        .and_then(|id| PeerId::from_bytes(&id).ok())
-                        .map(|peer_id|
-                            //TODO signedPeerRecord, see https://github.com/libp2p/specs/pull/217
-                            PeerInfo {
-                                peer_id: Some(peer_id),
-                            })
+                    let peer_id = info.peer_id.and_then(|id| PeerId::from_bytes(&id).ok());
+                    if peer_id.is_none() && info.signed_peer_record.is_none() {
+                        return None;
+                    }
+                    Some(PeerInfo {
+                        peer_id,
+                        signed_peer_record: info.signed_peer_record,
+                    })
                 })
                 .collect::<Vec<PeerInfo>>();
 
@@ -485,6 +497,46 @@ fn test_unsubscribe() {
     }
 }
 
+#[test]
+fn test_join_with_topic_config() {
+    let topic_strings = vec![String::from("topic1"), String::from("topic2")];
+    let topics = topic_strings
+        .iter()
+        .map(|t| Topic::new(t.clone()))
+        .collect::<Vec<Topic>>();
+
+    let config = ConfigBuilder::default()
+        .set_topic_config(
+            topics[0].hash(),
+            TopicConfig {
+                mesh_n: Some(4),
+                mesh_n_low: Some(2),
+                mesh_n_high: Some(6),
+                ..TopicConfig::default()
+            },
+        )
+        .build()
+        .unwrap();
+
+    let (mut gs, _, topic_hashes) = inject_nodes1()
+        .peer_no(20)
+        .topics(topic_strings)
+        .to_subscribe(true)
+        .gs_config(config.clone())
+        .create_network();
+
+    for topic in &topics {
+        gs.unsubscribe(topic).unwrap();
+        gs.subscribe(topic).unwrap();
+    }
+
+    assert_eq!(gs.mesh.get(&topic_hashes[0]).unwrap().len(), 4);
+    assert_eq!(
+        gs.mesh.get(&topic_hashes[1]).unwrap().len(),
+        config.mesh_n()
+    );
+}
+
 #[test]
 /// Test JOIN(topic) functionality.
 fn test_join() {
@@ -669,7 +721,7 @@ fn test_publish_without_flood_publishing() {
         )
         .unwrap();
 
-    let msg_id = gs.config.message_id(message);
+    let msg_id = gs.config.message_id(message).unwrap();
 
     let config: Config = Config::default();
     assert_eq!(
@@ -684,6 +736,215 @@ fn test_publish_without_flood_publishing() {
     );
 }
 
+/// Test seeding the fanout of a topic before publishing to it
+#[test]
+fn test_seed_fanout() {
+    let config = ConfigBuilder::default()
+        .flood_publish(false)
+        .build()
+        .unwrap();
+
+    let fanout_topic = String::from("test_seed_fanout");
+    let (mut gs, _, topic_hashes) = inject_nodes1()
+        .peer_no(20)
+        .topics(vec![fanout_topic.clone()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    assert!(
+        !gs.seed_fanout(&topic_hashes[0]),
+        "Should not seed the fanout of a subscribed topic"
+    );
+    assert_eq!(gs.fanout_peers(&topic_hashes[0]).count(), 0);
+
+    gs.unsubscribe(&Topic::new(fanout_topic.clone())).unwrap();
+    assert!(gs.seed_fanout(&topic_hashes[0]));
+
+    let seeded: BTreeSet<PeerId> = gs.fanout_peers(&topic_hashes[0]).cloned().collect();
+    assert_eq!(seeded.len(), gs.config.mesh_n());
+    assert!(gs.fanout_last_pub.contains_key(&topic_hashes[0]));
+
+    gs.publish(Topic::new(fanout_topic), vec![0; 42]).unwrap();
+
+    let recipients: BTreeSet<PeerId> = gs
+        .events
+        .iter()
+        .filter_map(|e| match e {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerIn::Message(RpcOut::Publish(_)),
+                ..
+            } => Some(*peer_id),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        recipients, seeded,
+        "Should publish to the seeded fanout peers"
+    );
+}
+
+#[test]
+fn test_restore_seen_messages() {
+    let config = Config::default();
+    let (mut gs, _, topics) = inject_nodes1()
+        .peer_no(config.mesh_n())
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config.clone())
+        .create_network();
+
+    let mut seq = 0;
+    let raw_message = random_message(&mut seq, &topics);
+    let message = gs
+        .data_transform
+        .inbound_transform(raw_message.clone())
+        .unwrap();
+    let msg_id = config.message_id(&message).unwrap();
+    gs.handle_received_message(raw_message.clone(), &PeerId::random());
+
+    let seen: Vec<_> = gs
+        .seen_messages()
+        .map(|(id, expires)| (id.clone(), expires))
+        .collect();
+    assert_eq!(seen.len(), 1);
+    assert_eq!(seen[0].0, msg_id);
+    assert!(seen[0].1 > SystemTime::now());
+
+    // a restarted node
+    let (mut gs, _, _) = inject_nodes1()
+        .peer_no(config.mesh_n())
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config.clone())
+        .create_network();
+
+    let expired = MessageId::new(b"expired");
+    gs.restore_seen_messages(
+        seen.into_iter()
+            .chain([(expired.clone(), SystemTime::now() - Duration::from_secs(1))]),
+    );
+    assert!(gs.duplicate_cache.contains(&msg_id));
+    assert!(!gs.duplicate_cache.contains(&expired));
+
+    // the message is dropped as a duplicate and not forwarded
+    flush_events(&mut gs);
+    gs.handle_received_message(raw_message, &PeerId::random());
+    assert!(gs.events.is_empty());
+}
+
+#[test]
+fn test_duplicate_cache_budget() {
+    // a budget that only fits a single message id
+    let budget = MemoryBudget::new(0);
+    let config = ConfigBuilder::default()
+        .duplicate_cache_budget(budget.clone())
+        .build()
+        .unwrap();
+    let (mut gs, _, topics) = inject_nodes1()
+        .peer_no(config.mesh_n())
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    let mut seq = 0;
+    let first = random_message(&mut seq, &topics);
+    let second = random_message(&mut seq, &topics);
+    flush_events(&mut gs);
+    gs.handle_received_message(first.clone(), &PeerId::random());
+    gs.handle_received_message(second, &PeerId::random());
+    // the id of the first message was evicted, hence it is no longer detected as a duplicate
+    gs.handle_received_message(first, &PeerId::random());
+
+    let received = gs
+        .events
+        .iter()
+        .filter(|event| matches!(event, ToSwarm::GenerateEvent(Event::Message { .. })))
+        .count();
+    assert_eq!(received, 3);
+    assert_eq!(budget.stats().evictions, 2);
+    assert_eq!(gs.duplicate_cache_stats(), budget.stats());
+}
+
+#[test]
+fn test_export_and_import_topology() {
+    let config = Config::default();
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(config.mesh_n())
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config.clone())
+        .scoring(Some((
+            PeerScoreParams::default(),
+            PeerScoreThresholds::default(),
+        )))
+        .create_network();
+    // peers are grafted on subscription only up to mesh_n_low
+    assert_eq!(gs.mesh[&topics[0]].len(), config.mesh_n_low());
+    gs.peer_score.as_mut().unwrap().0.add_penalty(&peers[0], 2);
+
+    let mut snapshot = gs.export_topology();
+    assert_eq!(snapshot.mesh, gs.mesh);
+    assert_eq!(snapshot.peer_scores.len(), peers.len());
+    assert_eq!(snapshot.peer_scores[&peers[0]].behaviour_penalty, 2.0);
+    // the whole previous mesh would have been built up by the heartbeat
+    snapshot
+        .mesh
+        .insert(topics[0].clone(), peers.iter().copied().collect());
+
+    // a restarted node
+    let (mut gs, _, topics) = inject_nodes1()
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config.clone())
+        .scoring(Some((
+            PeerScoreParams::default(),
+            PeerScoreThresholds::default(),
+        )))
+        .create_network();
+
+    snapshot.taken_at = SystemTime::now() - Duration::from_secs(10);
+    assert!(gs
+        .import_topology(snapshot.clone(), Duration::from_secs(5))
+        .is_err());
+    gs.import_topology(snapshot, Duration::from_secs(60))
+        .unwrap();
+    let report = gs.peer_score_report(&peers[0]).unwrap();
+    assert!(report.behaviour_penalty < 0.0);
+
+    for peer in &peers {
+        gs.on_swarm_event(FromSwarm::ConnectionEstablished(ConnectionEstablished {
+            peer_id: *peer,
+            connection_id: ConnectionId::new_unchecked(0),
+            endpoint: &ConnectedPoint::Listener {
+                local_addr: Multiaddr::empty(),
+                send_back_addr: Multiaddr::empty(),
+            },
+            failed_addresses: &[],
+            other_established: 0,
+        }));
+        gs.on_connection_handler_event(
+            *peer,
+            ConnectionId::new_unchecked(0),
+            HandlerEvent::PeerKind(PeerKind::Gossipsubv1_1),
+        );
+        gs.handle_received_subscriptions(
+            &[Subscription {
+                action: SubscriptionAction::Subscribe,
+                topic_hash: topics[0].clone(),
+            }],
+            peer,
+        );
+    }
+
+    // the restored mesh peers are grafted up to mesh_n right away, except for the penalized one
+    let mesh = &gs.mesh[&topics[0]];
+    assert_eq!(mesh.len(), config.mesh_n() - 1);
+    assert!(!mesh.contains(&peers[0]));
+}
+
 /// Test local node publish to unsubscribed topic
 #[test]
 fn test_fanout() {
@@ -756,7 +1017,7 @@ fn test_fanout() {
         )
         .unwrap();
 
-    let msg_id = gs.config.message_id(message);
+    let msg_id = gs.config.message_id(message).unwrap();
 
     assert_eq!(
         publishes.len(),
@@ -1019,7 +1280,7 @@ fn test_handle_iwant_msg_cached() {
         .inbound_transform(raw_message.clone())
         .unwrap();
 
-    let msg_id = gs.config.message_id(message);
+    let msg_id = gs.config.message_id(message).unwrap();
     gs.mcache.put(&msg_id, raw_message);
 
     gs.handle_iwant(&peers[7], vec![msg_id.clone()]);
@@ -1042,7 +1303,7 @@ fn test_handle_iwant_msg_cached() {
         sent_messages
             .iter()
             .map(|msg| gs.data_transform.inbound_transform(msg.clone()).unwrap())
-            .any(|msg| gs.config.message_id(&msg) == msg_id),
+            .any(|msg| gs.config.message_id(&msg).unwrap() == msg_id),
         "Expected the cached message to be sent to an IWANT peer"
     );
 }
@@ -1074,7 +1335,7 @@ fn test_handle_iwant_msg_cached_shifted() {
             .inbound_transform(raw_message.clone())
             .unwrap();
 
-        let msg_id = gs.config.message_id(message);
+        let msg_id = gs.config.message_id(message).unwrap();
         gs.mcache.put(&msg_id, raw_message);
         for _ in 0..shift {
             gs.mcache.shift();
@@ -1088,11 +1349,14 @@ fn test_handle_iwant_msg_cached_shifted() {
                 event: HandlerIn::Message(RpcOut::Forward(message)),
                 ..
             } => {
-                gs.config.message_id(
-                    &gs.data_transform
-                        .inbound_transform(message.clone())
-                        .unwrap(),
-                ) == msg_id
+                gs.config
+                    .message_id(
+                        &gs.data_transform
+                            .inbound_transform(message.clone())
+                            .unwrap(),
+                    )
+                    .unwrap()
+                    == msg_id
             }
             _ => false,
         });
@@ -1161,6 +1425,56 @@ fn test_handle_ihave_subscribed_and_msg_not_cached() {
     );
 }
 
+/// Tests that control messages exceeding the max transmit size are split into several RPCs
+#[test]
+fn test_split_control_messages_exceeding_max_transmit_size() {
+    let max_transmit_size = 1000;
+    let config = ConfigBuilder::default()
+        .max_transmit_size(max_transmit_size)
+        .build()
+        .unwrap();
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    flush_events(&mut gs);
+
+    let message_ids: Vec<MessageId> = (0..200)
+        .map(|i| MessageId::new(format!("message id {i}").as_bytes()))
+        .collect();
+    gs.handle_ihave(
+        &peers[0],
+        vec![(topic_hashes[0].clone(), message_ids.clone())],
+    );
+    gs.flush_control_pool();
+
+    // A peer configured with the same max transmit size must be able to receive all RPCs.
+    let mut codec = GossipsubCodec::new(max_transmit_size, ValidationMode::Strict);
+    let mut iwant_rpcs = 0;
+    let mut requested = HashSet::new();
+    for event in gs.events {
+        if let ToSwarm::NotifyHandler {
+            event: HandlerIn::Message(RpcOut::Control(ControlAction::IWant { message_ids })),
+            ..
+        } = event
+        {
+            let rpc = RpcOut::Control(ControlAction::IWant {
+                message_ids: message_ids.clone(),
+            });
+            codec
+                .encode(rpc.into_protobuf(), &mut BytesMut::new())
+                .expect("RPC to fit into the max transmit size");
+            iwant_rpcs += 1;
+            requested.extend(message_ids);
+        }
+    }
+
+    assert!(iwant_rpcs > 1, "IWANT should be split into several RPCs");
+    assert_eq!(requested, message_ids.into_iter().collect());
+}
+
 #[test]
 // tests that an event is not created when a peer shares that it has a message that
 // we already have
@@ -1423,6 +1737,62 @@ fn test_explicit_peer_reconnects() {
     );
 }
 
+#[test]
+fn test_explicit_peer_with_addresses() {
+    let (mut gs, others, _) = inject_nodes1()
+        .peer_no(1)
+        .topics(Vec::new())
+        .to_subscribe(true)
+        .create_network();
+
+    let peer = others.first().unwrap();
+    let address: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+    gs.add_explicit_peer_with_addresses(peer, [address.clone()]);
+
+    // The known addresses are used when dialing the explicit peer.
+    assert_eq!(
+        gs.handle_pending_outbound_connection(
+            ConnectionId::new_unchecked(0),
+            Some(*peer),
+            &[],
+            Endpoint::Dialer,
+        )
+        .unwrap(),
+        vec![address]
+    );
+
+    flush_events(&mut gs);
+    disconnect_peer(&mut gs, peer);
+
+    assert!(
+        gs.events.iter().any(|e| matches!(
+            e,
+            ToSwarm::GenerateEvent(Event::ExplicitPeerDown { peer_id }) if peer_id == peer
+        )),
+        "The explicit peer was not reported as down"
+    );
+
+    // A failed redial is not reported again.
+    flush_events(&mut gs);
+    gs.on_swarm_event(FromSwarm::DialFailure(DialFailure {
+        peer_id: Some(*peer),
+        error: &DialError::Aborted,
+        connection_id: ConnectionId::new_unchecked(1),
+    }));
+    assert!(gs.events.is_empty());
+
+    gs.remove_explicit_peer(peer);
+    assert!(gs
+        .handle_pending_outbound_connection(
+            ConnectionId::new_unchecked(2),
+            Some(*peer),
+            &[],
+            Endpoint::Dialer,
+        )
+        .unwrap()
+        .is_empty());
+}
+
 #[test]
 fn test_handle_graft_explicit_peer() {
     let (mut gs, peers, topic_hashes) = inject_nodes1()
@@ -1553,6 +1923,117 @@ fn do_forward_messages_to_explicit_peers() {
     );
 }
 
+#[test]
+fn test_message_id_fn_with_context() {
+    let config = ConfigBuilder::default()
+        .message_id_fn_with_context(|context: &MessageIdContext<'_>| {
+            if context.message.data.is_empty() {
+                return Err(crate::error::InvalidMessageId);
+            }
+            let source = context
+                .propagation_source
+                .map_or_else(|| "local".to_owned(), |peer| peer.to_base58());
+            Ok(MessageId::from(format!(
+                "{}/{}",
+                context.message.topic, source
+            )))
+        })
+        .build()
+        .unwrap();
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    gs.events.clear();
+
+    for data in [vec![], vec![1]] {
+        let message = RawMessage {
+            source: Some(peers[0]),
+            data,
+            sequence_number: Some(0),
+            topic: topic_hashes[0].clone(),
+            signature: None,
+            key: None,
+            validated: true,
+        };
+        gs.handle_received_message(message, &peers[0]);
+    }
+
+    let received = gs
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            ToSwarm::GenerateEvent(Event::Message { message_id, .. }) => Some(message_id.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        received,
+        vec![MessageId::from(format!("{}/{}", topic_hashes[0], peers[0]))]
+    );
+
+    assert!(matches!(
+        gs.publish(topic_hashes[0].clone(), Vec::new()),
+        Err(PublishError::InvalidMessageId)
+    ));
+}
+
+#[test]
+fn test_message_validator() {
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(3)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .create_network();
+
+    gs.set_message_validator(|_: &PeerId, _: &MessageId, message: &Message| {
+        let acceptance = if message.data == vec![1] {
+            MessageAcceptance::Accept
+        } else {
+            MessageAcceptance::Reject
+        };
+        future::ready(acceptance).boxed()
+    });
+    // Drop the events of setting up the network, e.g. the subscriptions.
+    gs.events.clear();
+
+    for data in [vec![1], vec![2]] {
+        let message = RawMessage {
+            source: Some(peers[0]),
+            data,
+            sequence_number: Some(0),
+            topic: topic_hashes[0].clone(),
+            signature: None,
+            key: None,
+            validated: true,
+        };
+        gs.handle_received_message(message, &peers[0]);
+    }
+
+    // Nothing is emitted or forwarded before the validator is done.
+    assert!(gs.events.is_empty());
+
+    let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+    let mut received = Vec::new();
+    let mut forwarded = Vec::new();
+    while let Poll::Ready(event) = gs.poll(&mut cx) {
+        match event {
+            ToSwarm::GenerateEvent(Event::Message { message, .. }) => received.push(message.data),
+            ToSwarm::NotifyHandler {
+                event: HandlerIn::Message(RpcOut::Forward(message)),
+                ..
+            } => forwarded.push(message.data),
+            _ => {}
+        }
+    }
+
+    assert_eq!(received, vec![vec![1]]);
+    assert!(!forwarded.is_empty());
+    assert!(forwarded.iter().all(|data| data == &vec![1]));
+}
+
 #[test]
 fn explicit_peers_not_added_to_mesh_on_subscribe() {
     let (mut gs, peers, _) = inject_nodes1()
@@ -1770,6 +2251,7 @@ fn test_connect_to_px_peers_on_handle_prune() {
     for _ in 0..config.prune_peers() + 5 {
         px.push(PeerInfo {
             peer_id: Some(PeerId::random()),
+            signed_peer_record: None,
         });
     }
 
@@ -1850,9 +2332,117 @@ fn test_send_px_and_backoff_in_prune() {
 }
 
 #[test]
-fn test_prune_backoffed_peer_on_graft() {
-    let config: Config = Config::default();
-
+fn test_send_signed_peer_records_in_px() {
+    let config = ConfigBuilder::default()
+        .prune_peers(16)
+        .do_px()
+        .build()
+        .unwrap();
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    // Connect a peer whose record we can sign.
+    let keypair = Keypair::generate_ed25519();
+    let record =
+        PeerRecord::new(&keypair, vec!["/ip4/127.0.0.1/tcp/1234".parse().unwrap()]).unwrap();
+    let signed_peer = record.peer_id();
+    gs.on_swarm_event(FromSwarm::ConnectionEstablished(ConnectionEstablished {
+        peer_id: signed_peer,
+        connection_id: ConnectionId::new_unchecked(0),
+        endpoint: &ConnectedPoint::Listener {
+            local_addr: Multiaddr::empty(),
+            send_back_addr: Multiaddr::empty(),
+        },
+        failed_addresses: &[],
+        other_established: 0,
+    }));
+    gs.on_connection_handler_event(
+        signed_peer,
+        ConnectionId::new_unchecked(0),
+        HandlerEvent::PeerKind(PeerKind::Gossipsubv1_1),
+    );
+    gs.handle_received_subscriptions(
+        &[Subscription {
+            action: SubscriptionAction::Subscribe,
+            topic_hash: topics[0].clone(),
+        }],
+        &signed_peer,
+    );
+    gs.add_peer_record(record.clone());
+
+    match gs.make_prune(&topics[0], &peers[0], true, false) {
+        ControlAction::Prune { peers, .. } => assert_eq!(
+            peers,
+            vec![PeerInfo {
+                peer_id: Some(signed_peer),
+                signed_peer_record: Some(record.into_signed_envelope().into_protobuf_encoding()),
+            }]
+        ),
+        _ => panic!("Expected a PRUNE"),
+    }
+}
+
+#[test]
+fn test_require_signed_records_for_px_peers() {
+    let config = ConfigBuilder::default()
+        .prune_peers(16)
+        .px_require_signed_records(true)
+        .build()
+        .unwrap();
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    let keypair = Keypair::generate_ed25519();
+    let record =
+        PeerRecord::new(&keypair, vec!["/ip4/127.0.0.1/tcp/1234".parse().unwrap()]).unwrap();
+    let signed_peer_record = record.to_signed_envelope().into_protobuf_encoding();
+    let px = vec![
+        PeerInfo {
+            peer_id: Some(record.peer_id()),
+            signed_peer_record: Some(signed_peer_record.clone()),
+        },
+        // No signed peer record.
+        PeerInfo {
+            peer_id: Some(PeerId::random()),
+            signed_peer_record: None,
+        },
+        // Signed peer record of a different peer.
+        PeerInfo {
+            peer_id: Some(PeerId::random()),
+            signed_peer_record: Some(signed_peer_record),
+        },
+        // Invalid signed peer record.
+        PeerInfo {
+            peer_id: Some(PeerId::random()),
+            signed_peer_record: Some(vec![1, 2, 3]),
+        },
+    ];
+
+    gs.handle_prune(&peers[0], vec![(topics[0].clone(), px, None)]);
+
+    let dials: Vec<_> = gs
+        .events
+        .iter()
+        .filter_map(|e| match e {
+            ToSwarm::Dial { opts } => opts.get_peer_id(),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(dials, vec![record.peer_id()]);
+}
+
+#[test]
+fn test_prune_backoffed_peer_on_graft() {
+    let config: Config = Config::default();
+
     //build mesh with enough peers for px
     let (mut gs, peers, topics) = inject_nodes1()
         .peer_no(config.prune_peers() + 1)
@@ -1895,6 +2485,41 @@ fn test_prune_backoffed_peer_on_graft() {
     );
 }
 
+#[test]
+fn test_backoffs_are_reported() {
+    let config: Config = Config::default();
+
+    // One peer to back off and one to stay without a backoff.
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(2)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .create_network();
+
+    assert!(gs.backoff(&topics[0], &peers[0]).is_none());
+
+    //remove peer from mesh and send prune to peer => this adds a backoff for this peer
+    gs.mesh.get_mut(&topics[0]).unwrap().remove(&peers[0]);
+    gs.send_graft_prune(
+        HashMap::new(),
+        vec![(peers[0], vec![topics[0].clone()])]
+            .into_iter()
+            .collect(),
+        HashSet::new(),
+    );
+
+    let backoff = gs
+        .backoff(&topics[0], &peers[0])
+        .expect("peer to be backed off");
+    assert!(backoff > Instant::now());
+    assert!(backoff <= Instant::now() + config.prune_backoff());
+    assert_eq!(
+        gs.backoffs(&topics[0]).collect::<Vec<_>>(),
+        vec![(&peers[0], backoff)]
+    );
+    assert!(gs.backoff(&topics[0], &peers[1]).is_none());
+}
+
 #[test]
 fn test_do_not_graft_within_backoff_period() {
     let config = ConfigBuilder::default()
@@ -2099,7 +2724,7 @@ fn test_flood_publish() {
         )
         .unwrap();
 
-    let msg_id = gs.config.message_id(message);
+    let msg_id = gs.config.message_id(message).unwrap();
 
     let config: Config = Config::default();
     assert_eq!(
@@ -2144,7 +2769,7 @@ fn test_gossip_to_at_least_gossip_lazy_peers() {
     // Transform the inbound message
     let message = &gs.data_transform.inbound_transform(raw_message).unwrap();
 
-    let msg_id = gs.config.message_id(message);
+    let msg_id = gs.config.message_id(message).unwrap();
 
     //check that exactly config.gossip_lazy() many gossip messages were sent.
     assert_eq!(
@@ -2189,7 +2814,7 @@ fn test_gossip_to_at_most_gossip_factor_peers() {
     // Transform the inbound message
     let message = &gs.data_transform.inbound_transform(raw_message).unwrap();
 
-    let msg_id = gs.config.message_id(message);
+    let msg_id = gs.config.message_id(message).unwrap();
     //check that exactly config.gossip_lazy() many gossip messages were sent.
     assert_eq!(
         count_control_msgs(&gs, |_, action| match action {
@@ -2367,6 +2992,342 @@ fn test_prune_negative_scored_peers() {
     );
 }
 
+#[test]
+fn test_prune_slow_peers() {
+    let config = ConfigBuilder::default()
+        .slow_peer_threshold(2)
+        .build()
+        .unwrap();
+
+    //build mesh with two peers
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(2)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config.clone())
+        .create_network();
+
+    //the send queue of the first peer fills up
+    gs.on_connection_handler_event(
+        peers[0],
+        ConnectionId::new_unchecked(0),
+        HandlerEvent::SendQueueFull,
+    );
+
+    //a single slow heartbeat is tolerated
+    gs.heartbeat();
+    assert!(gs.mesh[&topics[0]].contains(&peers[0]));
+
+    gs.heartbeat();
+
+    //the peer should not be in the mesh anymore and must not be grafted again
+    assert!(!gs.mesh[&topics[0]].contains(&peers[0]));
+    assert!(gs.mesh[&topics[0]].contains(&peers[1]));
+    assert!(gs.backoffs.is_backoff_with_slack(&topics[0], &peers[0]));
+
+    //check prune message
+    assert_eq!(
+        count_control_msgs(&gs, |peer_id, m| peer_id == &peers[0]
+            && match m {
+                ControlAction::Prune {
+                    topic_hash,
+                    peers,
+                    backoff,
+                } =>
+                    topic_hash == &topics[0] &&
+                    //no px in this case
+                    peers.is_empty() &&
+                    backoff.unwrap() == config.prune_backoff().as_secs(),
+                _ => false,
+            }),
+        1
+    );
+
+    //check the event
+    assert!(gs.events.iter().any(|e| matches!(
+        e,
+        ToSwarm::GenerateEvent(Event::SlowPeer {
+            peer_id,
+            topics: pruned,
+            reason: SlowPeerReason::SendQueueFull,
+            stats,
+        }) if peer_id == &peers[0] && pruned == &topics && stats.congested_connections == 1
+    )));
+}
+
+#[test]
+fn test_prune_high_latency_peers() {
+    let config = ConfigBuilder::default()
+        .slow_peer_threshold(1)
+        .slow_peer_latency(Duration::from_millis(5))
+        .build()
+        .unwrap();
+
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(2)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    //the first peer delivers the message, the second one lags behind
+    let mut seq = 0;
+    let message = random_message(&mut seq, &topics);
+    gs.handle_received_message(message.clone(), &peers[0]);
+    sleep(Duration::from_millis(10));
+    gs.handle_received_message(message, &peers[1]);
+
+    gs.heartbeat();
+
+    assert!(gs.mesh[&topics[0]].contains(&peers[0]));
+    assert!(!gs.mesh[&topics[0]].contains(&peers[1]));
+    assert!(gs.backoffs.is_backoff_with_slack(&topics[0], &peers[1]));
+
+    let stats = gs
+        .events
+        .iter()
+        .find_map(|e| match e {
+            ToSwarm::GenerateEvent(Event::SlowPeer {
+                peer_id,
+                reason: SlowPeerReason::HighLatency,
+                stats,
+                ..
+            }) if peer_id == &peers[1] => Some(*stats),
+            _ => None,
+        })
+        .expect("slow peer to be reported");
+    assert_eq!(stats.congested_connections, 0);
+    assert_eq!(stats.deliveries, 1);
+    assert!(stats.mean_delivery_latency.unwrap() >= Duration::from_millis(10));
+}
+
+#[test]
+fn test_latency_is_measured_from_first_delivery_by_non_mesh_peer() {
+    let config = ConfigBuilder::default()
+        .slow_peer_threshold(1)
+        .slow_peer_latency(Duration::from_secs(30))
+        .build()
+        .unwrap();
+
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(2)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    //only the second peer is in the mesh
+    gs.mesh
+        .insert(topics[0].clone(), BTreeSet::from([peers[1]]));
+
+    //the mesh peer delivers right after the peer outside the mesh
+    let mut seq = 0;
+    let message = random_message(&mut seq, &topics);
+    gs.handle_received_message(message.clone(), &peers[0]);
+    gs.handle_received_message(message, &peers[1]);
+
+    gs.heartbeat();
+
+    assert!(gs.mesh[&topics[0]].contains(&peers[1]));
+    assert!(!gs.events.iter().any(|e| matches!(
+        e,
+        ToSwarm::GenerateEvent(Event::SlowPeer {
+            reason: SlowPeerReason::HighLatency,
+            ..
+        })
+    )));
+}
+
+#[test]
+fn test_slow_peer_strikes_reset_after_draining() {
+    let config = ConfigBuilder::default()
+        .slow_peer_threshold(2)
+        .build()
+        .unwrap();
+
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    let connection_id = ConnectionId::new_unchecked(0);
+    for _ in 0..3 {
+        gs.on_connection_handler_event(peers[0], connection_id, HandlerEvent::SendQueueFull);
+        gs.heartbeat();
+        gs.on_connection_handler_event(peers[0], connection_id, HandlerEvent::SendQueueDrained);
+        gs.heartbeat();
+    }
+
+    assert!(gs.mesh[&topics[0]].contains(&peers[0]));
+}
+
+#[test]
+fn test_dropped_messages_are_reported_on_heartbeat() {
+    let (mut gs, peers, _) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .create_network();
+    gs.events.clear();
+
+    let connection_id = ConnectionId::new_unchecked(0);
+    for dropped in [
+        DroppedMessages {
+            forward: 2,
+            ..Default::default()
+        },
+        DroppedMessages {
+            forward: 1,
+            gossip: 3,
+            ..Default::default()
+        },
+    ] {
+        gs.on_connection_handler_event(
+            peers[0],
+            connection_id,
+            HandlerEvent::MessagesDropped(dropped),
+        );
+    }
+    gs.heartbeat();
+
+    let reported = gs
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            ToSwarm::GenerateEvent(Event::MessagesDropped { peer_id, dropped }) => {
+                Some((*peer_id, *dropped))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reported,
+        vec![(
+            peers[0],
+            DroppedMessages {
+                publish: 0,
+                forward: 3,
+                gossip: 3,
+            }
+        )]
+    );
+
+    gs.events.clear();
+    gs.heartbeat();
+    assert!(!gs
+        .events
+        .iter()
+        .any(|event| matches!(event, ToSwarm::GenerateEvent(Event::MessagesDropped { .. }))));
+}
+
+#[test]
+fn test_choked_peers_are_served_through_gossip() {
+    let config = ConfigBuilder::default()
+        .choking(true)
+        .choke_min_messages(1)
+        .choke_late_after(Duration::ZERO)
+        .build()
+        .unwrap();
+
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(10)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    //fill up the mesh beyond mesh_n_low, leaving room to choke peers
+    gs.mesh
+        .insert(topics[0].clone(), peers.iter().copied().collect());
+    let mesh_peers = gs.mesh[&topics[0]].iter().copied().collect::<Vec<_>>();
+    assert!(mesh_peers.len() > gs.config.mesh_n_low());
+    let (fast, slow) = (mesh_peers[0], mesh_peers[1]);
+
+    //the slow peer only delivers a message long after the fast one
+    let mut seq = 0;
+    let m1 = random_message(&mut seq, &topics);
+    gs.handle_received_message(m1.clone(), &fast);
+    sleep(Duration::from_millis(1));
+    gs.handle_received_message(m1, &slow);
+
+    gs.heartbeat();
+    assert!(gs
+        .choke_state
+        .as_ref()
+        .unwrap()
+        .is_choked(&topics[0], &slow));
+    assert!(!gs
+        .choke_state
+        .as_ref()
+        .unwrap()
+        .is_choked(&topics[0], &fast));
+
+    gs.events.clear();
+    gs.control_pool.clear();
+
+    //the choked peer does not get new messages forwarded
+    let m2 = random_message(&mut seq, &topics);
+    gs.handle_received_message(m2, &fast);
+    let forwarded_to = |peer: PeerId| {
+        gs.events.iter().any(|e| {
+            matches!(
+                e,
+                ToSwarm::NotifyHandler {
+                    peer_id,
+                    event: HandlerIn::Message(RpcOut::Forward(_)),
+                    ..
+                } if peer_id == &peer
+            )
+        })
+    };
+    assert!(!forwarded_to(slow));
+    assert!(forwarded_to(mesh_peers[2]));
+
+    //but is told about them through gossip
+    gs.emit_gossip();
+    assert!(
+        count_control_msgs(&gs, |peer_id, m| peer_id == &slow
+            && matches!(m, ControlAction::IHave { .. }))
+            > 0
+    );
+}
+
+#[test]
+fn test_first_delivery_by_non_mesh_peer_is_tracked_for_choking() {
+    let config = ConfigBuilder::default()
+        .choking(true)
+        .choke_min_messages(1)
+        .choke_late_after(Duration::from_secs(60))
+        .build()
+        .unwrap();
+
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(10)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+
+    //all peers but the first one are in the mesh
+    gs.mesh
+        .insert(topics[0].clone(), peers[1..].iter().copied().collect());
+
+    //a mesh peer delivering shortly after a peer outside the mesh is not late
+    let mut seq = 0;
+    let m1 = random_message(&mut seq, &topics);
+    gs.handle_received_message(m1.clone(), &peers[0]);
+    gs.handle_received_message(m1, &peers[1]);
+
+    gs.heartbeat();
+    assert!(!gs
+        .choke_state
+        .as_ref()
+        .unwrap()
+        .is_choked(&topics[0], &peers[1]));
+}
+
 #[test]
 fn test_dont_graft_to_negative_scored_peers() {
     let config = Config::default();
@@ -2427,6 +3388,7 @@ fn test_ignore_px_from_negative_scored_peer() {
     //handle prune from single peer with px peers
     let px = vec![PeerInfo {
         peer_id: Some(PeerId::random()),
+        signed_peer_record: None,
     }];
 
     gs.handle_prune(
@@ -2550,7 +3512,7 @@ fn test_do_not_gossip_to_peers_below_gossip_threshold() {
     // Transform the inbound message
     let message = &gs.data_transform.inbound_transform(raw_message).unwrap();
 
-    let msg_id = gs.config.message_id(message);
+    let msg_id = gs.config.message_id(message).unwrap();
 
     // Emit gossip
     gs.emit_gossip();
@@ -2627,7 +3589,7 @@ fn test_iwant_msg_from_peer_below_gossip_threshold_gets_ignored() {
     // Transform the inbound message
     let message = &gs.data_transform.inbound_transform(raw_message).unwrap();
 
-    let msg_id = gs.config.message_id(message);
+    let msg_id = gs.config.message_id(message).unwrap();
 
     gs.handle_iwant(&p1, vec![msg_id.clone()]);
     gs.handle_iwant(&p2, vec![msg_id.clone()]);
@@ -2653,7 +3615,7 @@ fn test_iwant_msg_from_peer_below_gossip_threshold_gets_ignored() {
             peer_id,
             gs.data_transform.inbound_transform(msg.clone()).unwrap()
         ))
-        .any(|(peer_id, msg)| peer_id == &p2 && gs.config.message_id(&msg) == msg_id));
+        .any(|(peer_id, msg)| peer_id == &p2 && gs.config.message_id(&msg).unwrap() == msg_id));
     //the message got not sent to p1
     assert!(sent_messages
         .iter()
@@ -2661,7 +3623,7 @@ fn test_iwant_msg_from_peer_below_gossip_threshold_gets_ignored() {
             peer_id,
             gs.data_transform.inbound_transform(msg.clone()).unwrap()
         ))
-        .all(|(peer_id, msg)| !(peer_id == &p1 && gs.config.message_id(&msg) == msg_id)));
+        .all(|(peer_id, msg)| !(peer_id == &p1 && gs.config.message_id(&msg).unwrap() == msg_id)));
 }
 
 #[test]
@@ -2714,7 +3676,7 @@ fn test_ihave_msg_from_peer_below_gossip_threshold_gets_ignored() {
     // Transform the inbound message
     let message = &gs.data_transform.inbound_transform(raw_message).unwrap();
 
-    let msg_id = gs.config.message_id(message);
+    let msg_id = gs.config.message_id(message).unwrap();
 
     gs.handle_ihave(&p1, vec![(topics[0].clone(), vec![msg_id.clone()])]);
     gs.handle_ihave(&p2, vec![(topics[0].clone(), vec![msg_id.clone()])]);
@@ -2933,7 +3895,7 @@ fn test_ignore_rpc_from_peers_below_graylist_threshold() {
 
     let control_action = ControlAction::IHave {
         topic_hash: topics[0].clone(),
-        message_ids: vec![config.message_id(message2)],
+        message_ids: vec![config.message_id(message2).unwrap()],
     };
 
     //clear events
@@ -2962,7 +3924,7 @@ fn test_ignore_rpc_from_peers_below_graylist_threshold() {
 
     let control_action = ControlAction::IHave {
         topic_hash: topics[0].clone(),
-        message_ids: vec![config.message_id(message4)],
+        message_ids: vec![config.message_id(message4).unwrap()],
     };
 
     //receive from p2
@@ -2979,8 +3941,58 @@ fn test_ignore_rpc_from_peers_below_graylist_threshold() {
         },
     );
 
-    //events got processed
-    assert!(gs.events.len() > 1);
+    //events got processed
+    assert!(gs.events.len() > 1);
+}
+
+#[test]
+fn test_graylist_events() {
+    let config = Config::default();
+    let mut peer_score_params = PeerScoreParams::default();
+    peer_score_params.app_specific_weight = 1.0;
+    let peer_score_thresholds = PeerScoreThresholds {
+        gossip_threshold: -1.0,
+        publish_threshold: -2.0,
+        graylist_threshold: -10.0,
+        ..PeerScoreThresholds::default()
+    };
+
+    let (mut gs, peers, _) = inject_nodes1()
+        .peer_no(2)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .scoring(Some((peer_score_params, peer_score_thresholds)))
+        .create_network();
+
+    gs.set_application_score(&peers[0], -20.0);
+    assert!(gs.is_graylisted(&peers[0]));
+    assert!(!gs.is_graylisted(&peers[1]));
+
+    let graylist_events = |gs: &mut Behaviour| {
+        gs.events
+            .drain(..)
+            .filter_map(|e| match e {
+                ToSwarm::GenerateEvent(Event::Graylisted { peer_id, .. }) => Some((peer_id, true)),
+                ToSwarm::GenerateEvent(Event::GraylistLifted { peer_id, .. }) => {
+                    Some((peer_id, false))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    gs.heartbeat();
+    assert_eq!(graylist_events(&mut gs), vec![(peers[0], true)]);
+
+    //no further events while the peer stays graylisted
+    gs.heartbeat();
+    assert!(graylist_events(&mut gs).is_empty());
+
+    gs.set_application_score(&peers[0], 0.0);
+    assert!(!gs.is_graylisted(&peers[0]));
+    gs.heartbeat();
+    assert_eq!(graylist_events(&mut gs), vec![(peers[0], false)]);
 }
 
 #[test]
@@ -3009,6 +4021,7 @@ fn test_ignore_px_from_peers_below_accept_px_threshold() {
     // Handle prune from peer peers[0] with px peers
     let px = vec![PeerInfo {
         peer_id: Some(PeerId::random()),
+        signed_peer_record: None,
     }];
     gs.handle_prune(
         &peers[0],
@@ -3031,6 +4044,7 @@ fn test_ignore_px_from_peers_below_accept_px_threshold() {
     //handle prune from peer peers[1] with px peers
     let px = vec![PeerInfo {
         peer_id: Some(PeerId::random()),
+        signed_peer_record: None,
     }];
     gs.handle_prune(
         &peers[1],
@@ -3524,7 +4538,7 @@ fn test_scoring_p4_valid_message() {
 
     //message m1 gets validated
     gs.report_message_validation_result(
-        &config.message_id(message1),
+        &config.message_id(message1).unwrap(),
         &peers[0],
         MessageAcceptance::Accept,
     )
@@ -3691,7 +4705,7 @@ fn test_scoring_p4_ignored_message() {
 
     //message m1 gets ignored
     gs.report_message_validation_result(
-        &config.message_id(message1),
+        &config.message_id(message1).unwrap(),
         &peers[0],
         MessageAcceptance::Ignore,
     )
@@ -3750,7 +4764,7 @@ fn test_scoring_p4_application_invalidated_message() {
 
     //message m1 gets rejected
     gs.report_message_validation_result(
-        &config.message_id(message1),
+        &config.message_id(message1).unwrap(),
         &peers[0],
         MessageAcceptance::Reject,
     )
@@ -3816,7 +4830,7 @@ fn test_scoring_p4_application_invalid_message_from_two_peers() {
 
     //message m1 gets rejected
     gs.report_message_validation_result(
-        &config.message_id(message1),
+        &config.message_id(message1).unwrap(),
         &peers[0],
         MessageAcceptance::Reject,
     )
@@ -3891,19 +4905,19 @@ fn test_scoring_p4_three_application_invalid_messages() {
 
     //messages gets rejected
     gs.report_message_validation_result(
-        &config.message_id(message1),
+        &config.message_id(message1).unwrap(),
         &peers[0],
         MessageAcceptance::Reject,
     )
     .unwrap();
     gs.report_message_validation_result(
-        &config.message_id(message2),
+        &config.message_id(message2).unwrap(),
         &peers[0],
         MessageAcceptance::Reject,
     )
     .unwrap();
     gs.report_message_validation_result(
-        &config.message_id(message3),
+        &config.message_id(message3).unwrap(),
         &peers[0],
         MessageAcceptance::Reject,
     )
@@ -3965,7 +4979,7 @@ fn test_scoring_p4_decay() {
 
     //message m1 gets rejected
     gs.report_message_validation_result(
-        &config.message_id(message1),
+        &config.message_id(message1).unwrap(),
         &peers[0],
         MessageAcceptance::Reject,
     )
@@ -4285,6 +5299,7 @@ fn test_opportunistic_grafting() {
         "should not apply opportunistic grafting after first tick"
     );
 
+    gs.events.clear();
     gs.heartbeat();
 
     assert_eq!(
@@ -4293,6 +5308,29 @@ fn test_opportunistic_grafting() {
         "opportunistic grafting should have added 2 peers"
     );
 
+    let grafted: Vec<_> = gs
+        .events
+        .iter()
+        .filter_map(|e| match e {
+            ToSwarm::GenerateEvent(Event::OpportunisticGraft {
+                topic,
+                peers,
+                median_score,
+            }) => {
+                assert_eq!(topic, &topics[0]);
+                assert_eq!(*median_score, 1.0);
+                Some(peers.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        grafted.len(),
+        1,
+        "should report opportunistic grafting once"
+    );
+    assert!(grafted[0].iter().all(|p| gs.mesh[&topics[0]].contains(p)));
+
     assert!(
         gs.mesh[&topics[0]].is_superset(&peers.iter().cloned().collect()),
         "old peers are still part of the mesh"
@@ -4304,6 +5342,62 @@ fn test_opportunistic_grafting() {
     );
 }
 
+#[test]
+fn test_opportunistic_graft_threshold_overrides_score_thresholds() {
+    let config = ConfigBuilder::default()
+        .mesh_n_low(3)
+        .mesh_n(5)
+        .mesh_n_high(7)
+        .mesh_outbound_min(0) //deactivate outbound handling
+        .opportunistic_graft_ticks(1)
+        .opportunistic_graft_threshold(0.5)
+        .build()
+        .unwrap();
+    let peer_score_params = PeerScoreParams {
+        app_specific_weight: 1.0,
+        ..Default::default()
+    };
+    let thresholds = PeerScoreThresholds {
+        opportunistic_graft_threshold: 20.0,
+        ..Default::default()
+    };
+
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(5)
+        .topics(vec!["test".into()])
+        .to_subscribe(false)
+        .gs_config(config)
+        .explicit(0)
+        .outbound(0)
+        .scoring(Some((peer_score_params, thresholds)))
+        .create_network();
+
+    for peer in &peers {
+        gs.handle_graft(peer, topics.clone());
+    }
+    let others: Vec<_> = (0..5)
+        .map(|_| add_peer(&mut gs, &topics, false, false))
+        .collect();
+    for peer in &peers {
+        gs.set_application_score(peer, 1.0);
+    }
+    for peer in &others {
+        gs.set_application_score(peer, 10.0);
+    }
+
+    //the median of 1.0 is below the score threshold but not below the configured override
+    gs.heartbeat();
+    assert_eq!(
+        gs.mesh[&topics[0]].len(),
+        5,
+        "should not apply opportunistic grafting"
+    );
+    assert!(!gs
+        .events
+        .iter()
+        .any(|e| matches!(e, ToSwarm::GenerateEvent(Event::OpportunisticGraft { .. }))));
+}
+
 #[test]
 fn test_ignore_graft_from_unknown_topic() {
     //build gossipsub without subscribing to any topics
@@ -4344,7 +5438,7 @@ fn test_ignore_too_many_iwants_from_same_peer_for_same_message() {
     // Transform the inbound message
     let message1 = &gs.data_transform.inbound_transform(m1.clone()).unwrap();
 
-    let id = config.message_id(message1);
+    let id = config.message_id(message1).unwrap();
 
     gs.handle_received_message(m1, &PeerId::random());
 
@@ -4404,7 +5498,7 @@ fn test_ignore_too_many_ihaves() {
 
         gs.handle_ihave(
             &peer,
-            vec![(topics[0].clone(), vec![config.message_id(message)])],
+            vec![(topics[0].clone(), vec![config.message_id(message).unwrap()])],
         );
     }
 
@@ -4412,7 +5506,7 @@ fn test_ignore_too_many_ihaves() {
         .iter()
         .take(10)
         .map(|msg| gs.data_transform.inbound_transform(msg.clone()).unwrap())
-        .map(|m| config.message_id(&m))
+        .map(|m| config.message_id(&m).unwrap())
         .collect();
 
     //we send iwant only for the first 10 messages
@@ -4434,7 +5528,7 @@ fn test_ignore_too_many_ihaves() {
 
         gs.handle_ihave(
             &peer,
-            vec![(topics[0].clone(), vec![config.message_id(message)])],
+            vec![(topics[0].clone(), vec![config.message_id(message).unwrap()])],
         );
     }
 
@@ -4470,7 +5564,7 @@ fn test_ignore_too_many_messages_in_ihave() {
     let message_ids: Vec<_> = (0..20)
         .map(|_| random_message(&mut seq, &topics))
         .map(|msg| gs.data_transform.inbound_transform(msg).unwrap())
-        .map(|msg| config.message_id(&msg))
+        .map(|msg| config.message_id(&msg).unwrap())
         .collect();
 
     //peer sends us three ihaves
@@ -4608,6 +5702,90 @@ fn test_limit_number_of_message_ids_inside_ihave() {
     );
 }
 
+#[test]
+fn test_ihave_budget_limits_message_ids_across_topics() {
+    let config = ConfigBuilder::default()
+        .ihave_budget(Some(15))
+        .build()
+        .unwrap();
+    //build gossipsub with full mesh
+    let (mut gs, peers, topics) = inject_nodes1()
+        .peer_no(config.mesh_n_high())
+        .topics(vec!["test1".into(), "test2".into()])
+        .to_subscribe(false)
+        .gs_config(config)
+        .create_network();
+
+    //graft to all peers to really fill the mesh with all the peers
+    for peer in peers {
+        gs.handle_graft(&peer, topics.clone());
+    }
+
+    //add another peer not in the mesh
+    let p1 = add_peer(&mut gs, &topics, false, false);
+
+    //receive 10 messages on each topic from another peer
+    let mut seq = 0;
+    for topic in &topics {
+        for _ in 0..10 {
+            gs.handle_received_message(
+                random_message(&mut seq, std::slice::from_ref(topic)),
+                &PeerId::random(),
+            );
+        }
+    }
+
+    gs.emit_gossip();
+
+    let mut sum = 0;
+    count_control_msgs(&gs, |p, action| match action {
+        ControlAction::IHave { message_ids, .. } if p == &p1 => {
+            sum += message_ids.len();
+            true
+        }
+        _ => false,
+    });
+    assert_eq!(sum, 15, "should have advertised at most the budget to p1");
+}
+
+#[test]
+fn test_iwant_budget_limits_requested_message_ids() {
+    let config = ConfigBuilder::default()
+        .max_ihave_length(10)
+        .iwant_budget(5)
+        .build()
+        .unwrap();
+    //build gossipsub with full mesh
+    let (mut gs, _, topics) = inject_nodes1()
+        .peer_no(config.mesh_n_high())
+        .topics(vec!["test".into()])
+        .to_subscribe(false)
+        .gs_config(config.clone())
+        .create_network();
+
+    //add another peer not in the mesh
+    let peer = add_peer(&mut gs, &topics, false, false);
+
+    let mut seq = 0;
+    let message_ids: Vec<_> = (0..8)
+        .map(|_| random_message(&mut seq, &topics))
+        .map(|msg| gs.data_transform.inbound_transform(msg).unwrap())
+        .map(|msg| config.message_id(&msg).unwrap())
+        .collect();
+
+    gs.handle_ihave(&peer, vec![(topics[0].clone(), message_ids)]);
+
+    let mut sum = 0;
+    count_control_msgs(&gs, |p, action| match action {
+        ControlAction::IWant { message_ids } if p == &peer => {
+            sum += message_ids.len();
+            true
+        }
+        _ => false,
+    });
+    assert_eq!(sum, 5, "should have requested at most the budget");
+}
+
 #[test]
 fn test_iwant_penalties() {
     use tracing_subscriber::EnvFilter;
@@ -4666,7 +5844,10 @@ fn test_iwant_penalties() {
             peer,
             vec![(
                 topics[0].clone(),
-                vec![config.message_id(message1), config.message_id(message2)],
+                vec![
+                    config.message_id(message1).unwrap(),
+                    config.message_id(message2).unwrap(),
+                ],
             )],
         );
     }
@@ -5122,3 +6303,237 @@ fn test_graft_without_subscribe() {
     // We unsubscribe from the topic.
     let _ = gs.unsubscribe(&Topic::new(topic));
 }
+
+#[test]
+fn test_topic_message_authenticity() {
+    let telemetry = Topic::new("telemetry").hash();
+    let config = ConfigBuilder::default()
+        .set_topic_config(
+            telemetry.clone(),
+            TopicConfig {
+                validation_mode: Some(ValidationMode::Anonymous),
+                ..TopicConfig::default()
+            },
+        )
+        .build()
+        .unwrap();
+    let (mut gs, _, topic_hashes) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec!["consensus".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    let consensus = topic_hashes[0].clone();
+
+    // Signed messages would be rejected on the anonymous topic.
+    let keypair = libp2p_identity::Keypair::generate_ed25519();
+    assert!(gs
+        .set_topic_message_authenticity(telemetry.clone(), MessageAuthenticity::Signed(keypair))
+        .is_err());
+    gs.set_topic_message_authenticity(telemetry.clone(), MessageAuthenticity::Anonymous)
+        .unwrap();
+
+    let message = gs.build_raw_message(telemetry, vec![1]).unwrap();
+    assert!(message.source.is_none());
+    assert!(message.signature.is_none());
+    assert!(message.sequence_number.is_none());
+
+    let message = gs.build_raw_message(consensus, vec![1]).unwrap();
+    assert!(message.source.is_some());
+    assert!(message.signature.is_some());
+}
+
+#[test]
+fn test_local_delivery_of_published_messages() {
+    let local_messages = |gs: &Behaviour| {
+        gs.events
+            .iter()
+            .filter(|e| matches!(e, ToSwarm::GenerateEvent(Event::LocalMessage { .. })))
+            .count()
+    };
+
+    // Immediate delivery happens even if the message can't be sent to any peer.
+    let config = ConfigBuilder::default()
+        .local_delivery(LocalDelivery::Immediate)
+        .build()
+        .unwrap();
+    let (mut gs, _, topic_hashes) = inject_nodes1()
+        .peer_no(0)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    assert!(matches!(
+        gs.publish(topic_hashes[0].clone(), vec![1]),
+        Err(PublishError::InsufficientPeers)
+    ));
+    assert_eq!(local_messages(&gs), 1);
+
+    // Delivery after validation requires the message to be published.
+    let config = ConfigBuilder::default()
+        .local_delivery(LocalDelivery::AfterValidation)
+        .build()
+        .unwrap();
+    let (mut gs, _, topic_hashes) = inject_nodes1()
+        .peer_no(0)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config.clone())
+        .create_network();
+    assert!(gs.publish(topic_hashes[0].clone(), vec![1]).is_err());
+    assert_eq!(local_messages(&gs), 0);
+
+    let (mut gs, _, topic_hashes) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .gs_config(config)
+        .create_network();
+    let message_id = gs.publish(topic_hashes[0].clone(), vec![1]).unwrap();
+    let delivered = gs.events.iter().find_map(|e| match e {
+        ToSwarm::GenerateEvent(Event::LocalMessage {
+            message_id,
+            message,
+        }) => Some((message_id.clone(), message.data.clone())),
+        _ => None,
+    });
+    assert_eq!(delivered, Some((message_id, vec![1])));
+
+    // Messages are not delivered by default.
+    let (mut gs, _, topic_hashes) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .create_network();
+    gs.publish(topic_hashes[0].clone(), vec![1]).unwrap();
+    assert_eq!(local_messages(&gs), 0);
+}
+
+#[test]
+fn test_publish_to_peer_subset() {
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(10)
+        .topics(vec!["test".into()])
+        .to_subscribe(true)
+        .create_network();
+
+    let unknown_peer = PeerId::random();
+    assert!(matches!(
+        gs.publish_to(topic_hashes[0].clone(), vec![1], [unknown_peer]),
+        Err(PublishError::InsufficientPeers)
+    ));
+
+    let targets = [peers[0], peers[1], unknown_peer];
+    let msg_id = gs
+        .publish_to(topic_hashes[0].clone(), vec![1], targets)
+        .unwrap();
+
+    let recipients: HashSet<_> = gs
+        .events
+        .iter()
+        .filter_map(|e| match e {
+            ToSwarm::NotifyHandler {
+                peer_id,
+                event: HandlerIn::Message(RpcOut::Publish(_)),
+                ..
+            } => Some(*peer_id),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(recipients, HashSet::from([peers[0], peers[1]]));
+
+    assert!(gs.mcache.get(&msg_id).is_some());
+    assert!(gs.duplicate_cache.contains(&msg_id));
+}
+
+#[test]
+fn test_tracer() {
+    let (mut gs, peers, topic_hashes) = inject_nodes1()
+        .peer_no(1)
+        .topics(vec![String::from("topic1")])
+        .to_subscribe(true)
+        .create_network();
+
+    let traced = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    gs.set_tracer({
+        let traced = traced.clone();
+        move |event| traced.lock().unwrap().push(event)
+    });
+
+    let message_id = gs.publish(topic_hashes[0].clone(), vec![1]).unwrap();
+
+    let message = RawMessage {
+        source: Some(peers[0]),
+        data: vec![2],
+        sequence_number: Some(0),
+        topic: topic_hashes[0].clone(),
+        signature: None,
+        key: None,
+        validated: true,
+    };
+    gs.handle_received_message(message.clone(), &peers[0]);
+    gs.handle_received_message(message, &peers[0]);
+
+    gs.remove_peer_from_mesh(&peers[0], &topic_hashes[0], None, false, Churn::Unsub);
+
+    let traced = traced.lock().unwrap();
+    assert!(matches!(
+        &traced[0],
+        TraceEvent::PublishMessage { message_id: id, .. } if *id == message_id
+    ));
+    assert!(matches!(
+        &traced[1],
+        TraceEvent::DeliverMessage { received_from, .. } if *received_from == peers[0]
+    ));
+    assert!(matches!(
+        &traced[2],
+        TraceEvent::DuplicateMessage { received_from, .. } if *received_from == peers[0]
+    ));
+    assert!(matches!(
+        &traced[3],
+        TraceEvent::Prune { peer_id, topic } if *peer_id == peers[0] && *topic == topic_hashes[0]
+    ));
+    assert_eq!(traced.len(), 4);
+}
+
+#[test]
+fn test_update_subscription_filter() {
+    let t1 = Topic::new("t1").hash();
+    let t2 = Topic::new("t2").hash();
+    let (mut gs, _, _) = inject_nodes::<IdentityTransform, _>()
+        .subscription_filter(WhitelistSubscriptionFilter(
+            vec![t1.clone()].into_iter().collect(),
+        ))
+        .to_subscribe(false)
+        .create_network();
+    let peer = add_peer(&mut gs, &[], false, false);
+    gs.events.clear();
+
+    let subscriptions = [t1.clone(), t2.clone()].map(|topic_hash| Subscription {
+        action: SubscriptionAction::Subscribe,
+        topic_hash,
+    });
+    gs.handle_received_subscriptions(&subscriptions, &peer);
+
+    assert!(gs.events.iter().any(|e| matches!(
+        e,
+        ToSwarm::GenerateEvent(Event::SubscriptionsFiltered { peer_id, topics })
+            if *peer_id == peer && *topics == vec![t2.clone()]
+    )));
+    assert!(!gs.connected_peers[&peer].topics.contains(&t2));
+
+    // Allow the second topic at runtime.
+    gs.subscription_filter_mut().0.insert(t2.clone());
+    gs.events.clear();
+    gs.handle_received_subscriptions(&subscriptions, &peer);
+
+    assert!(!gs.events.iter().any(|e| matches!(
+        e,
+        ToSwarm::GenerateEvent(Event::SubscriptionsFiltered { .. })
+    )));
+    assert!(gs.connected_peers[&peer].topics.contains(&t2));
+    assert!(gs.subscribe(&Topic::new("t2")).is_ok());
+
+    gs.set_subscription_filter(WhitelistSubscriptionFilter::default());
+    assert!(gs.subscribe(&Topic::new("t1")).is_err());
+}
@@ -0,0 +1,202 @@
+// Copyright 2024 Sigma Prime Pty Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Episub-style choking of lagging mesh peers.
+//!
+//! Mesh peers that mostly deliver messages we have already received from someone else a while
+//! ago are lagging behind the rest of the mesh. Choked peers no longer receive eagerly forwarded
+//! messages and are served through `IHAVE` gossip instead, until their deliveries catch up again.
+
+use crate::config::Config;
+use crate::topic::TopicHash;
+use libp2p_identity::PeerId;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::Duration;
+
+/// Delivery statistics of a mesh peer since the last evaluation.
+#[derive(Debug, Default)]
+struct DeliveryStats {
+    /// The number of messages received from the peer.
+    messages: usize,
+    /// The number of messages the peer delivered late.
+    late: usize,
+}
+
+/// Tracks the delivery statistics of mesh peers and which of them are choked.
+#[derive(Default)]
+pub(crate) struct ChokeState {
+    /// Delivery statistics per topic and mesh peer.
+    stats: HashMap<TopicHash, HashMap<PeerId, DeliveryStats>>,
+    /// The choked mesh peers per topic.
+    choked: HashMap<TopicHash, HashSet<PeerId>>,
+}
+
+impl ChokeState {
+    /// Records that the mesh peer `peer` was the first to deliver a message.
+    pub(crate) fn first_delivery(&mut self, topic: &TopicHash, peer: PeerId) {
+        self.stats
+            .entry(topic.clone())
+            .or_default()
+            .entry(peer)
+            .or_default()
+            .messages += 1;
+    }
+
+    /// Records that the mesh peer `peer` delivered a message we had already received
+    /// `since_first` ago, see [`FirstDeliveries`](crate::first_delivery::FirstDeliveries). The
+    /// delivery counts as late if that was more than `late_after` ago.
+    pub(crate) fn duplicate_delivery(
+        &mut self,
+        topic: &TopicHash,
+        peer: PeerId,
+        since_first: Option<Duration>,
+        late_after: Duration,
+    ) {
+        // We no longer remember when the message was first seen, so it must be very late.
+        let late = since_first.map_or(true, |elapsed| elapsed > late_after);
+
+        let stats = self
+            .stats
+            .entry(topic.clone())
+            .or_default()
+            .entry(peer)
+            .or_default();
+        stats.messages += 1;
+        if late {
+            stats.late += 1;
+        }
+    }
+
+    /// Returns whether `peer` is choked for `topic`.
+    pub(crate) fn is_choked(&self, topic: &TopicHash, peer: &PeerId) -> bool {
+        self.choked
+            .get(topic)
+            .map_or(false, |peers| peers.contains(peer))
+    }
+
+    /// Returns the choked peers of `topic`.
+    pub(crate) fn choked_peers(&self, topic: &TopicHash) -> impl Iterator<Item = &PeerId> {
+        self.choked.get(topic).into_iter().flatten()
+    }
+
+    /// Re-evaluates which mesh peers are choked based on the statistics gathered since the last
+    /// evaluation. Peers that are not part of the mesh anymore are forgotten.
+    pub(crate) fn heartbeat(
+        &mut self,
+        mesh: &HashMap<TopicHash, BTreeSet<PeerId>>,
+        config: &Config,
+    ) {
+        self.stats.retain(|topic, _| mesh.contains_key(topic));
+        self.choked.retain(|topic, _| mesh.contains_key(topic));
+
+        for (topic, mesh_peers) in mesh {
+            let stats = self.stats.entry(topic.clone()).or_default();
+            let choked = self.choked.entry(topic.clone()).or_default();
+            stats.retain(|peer, _| mesh_peers.contains(peer));
+            choked.retain(|peer| mesh_peers.contains(peer));
+
+            for (peer, peer_stats) in stats.iter_mut() {
+                if peer_stats.messages < config.choke_min_messages() {
+                    continue;
+                }
+                let late_ratio = peer_stats.late as f64 / peer_stats.messages as f64;
+                *peer_stats = DeliveryStats::default();
+
+                if choked.contains(peer) {
+                    if late_ratio < config.unchoke_threshold() {
+                        tracing::debug!(%peer, %topic, "Unchoking mesh peer");
+                        choked.remove(peer);
+                    }
+                } else if late_ratio >= config.choke_threshold()
+                    // Always push eagerly to at least `mesh_n_low` peers.
+                    && mesh_peers.len() - choked.len() > config.mesh_n_low_for_topic(topic)
+                {
+                    tracing::debug!(%peer, %topic, %late_ratio, "Choking lagging mesh peer");
+                    choked.insert(*peer);
+                }
+            }
+        }
+    }
+
+    /// Forgets everything about a disconnected peer.
+    pub(crate) fn remove_peer(&mut self, peer: &PeerId) {
+        for stats in self.stats.values_mut() {
+            stats.remove(peer);
+        }
+        for choked in self.choked.values_mut() {
+            choked.remove(peer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ConfigBuilder;
+
+    fn config() -> Config {
+        ConfigBuilder::default()
+            .choking(true)
+            .choke_min_messages(4)
+            .mesh_n_low(1)
+            .mesh_outbound_min(0)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn chokes_lagging_peers_and_unchokes_them_once_they_catch_up() {
+        let config = config();
+        let topic = TopicHash::from_raw("topic");
+        let fast = PeerId::random();
+        let slow = PeerId::random();
+        let mesh = HashMap::from([(topic.clone(), BTreeSet::from([fast, slow]))]);
+        let mut state = ChokeState::default();
+
+        for _ in 0..4 {
+            state.first_delivery(&topic, fast);
+            state.duplicate_delivery(&topic, slow, Some(Duration::from_millis(1)), Duration::ZERO);
+        }
+        state.heartbeat(&mesh, &config);
+        assert!(state.is_choked(&topic, &slow));
+        assert!(!state.is_choked(&topic, &fast));
+
+        for _ in 0..4 {
+            state.first_delivery(&topic, slow);
+        }
+        state.heartbeat(&mesh, &config);
+        assert!(!state.is_choked(&topic, &slow));
+    }
+
+    #[test]
+    fn keeps_mesh_n_low_peers_unchoked() {
+        let config = config();
+        let topic = TopicHash::from_raw("topic");
+        let peer = PeerId::random();
+        let mesh = HashMap::from([(topic.clone(), BTreeSet::from([peer]))]);
+        let mut state = ChokeState::default();
+
+        for _ in 0..4 {
+            state.duplicate_delivery(&topic, peer, None, Duration::ZERO);
+        }
+        state.heartbeat(&mesh, &config);
+        assert!(!state.is_choked(&topic, &peer));
+    }
+}
@@ -0,0 +1,302 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! A [`DataTransform`] compressing the data of messages on selected topics.
+//!
+//! The data of messages on a compressed topic is prefixed with a single byte identifying the
+//! algorithm it was compressed with, or that it was left uncompressed because it was smaller than
+//! the configured threshold. All nodes on such a topic have to use a [`CompressionTransform`]
+//! configured for it. To keep nodes that don't from meshing with nodes that do, advertise the
+//! compression through [`crate::ConfigBuilder::protocol_id_suffix`], e.g. with
+//! [`Compression::protocol_suffix`].
+
+use std::collections::HashMap;
+use std::io;
+
+use crate::transform::{DataTransform, IdentityTransform};
+use crate::{Message, RawMessage, TopicHash};
+
+/// Tag of data that was left uncompressed.
+const TAG_UNCOMPRESSED: u8 = 0;
+/// Tag of data compressed with snappy.
+#[cfg(feature = "snappy")]
+const TAG_SNAPPY: u8 = 1;
+/// Tag of data compressed with zstd.
+#[cfg(feature = "zstd")]
+const TAG_ZSTD: u8 = 2;
+
+/// The default maximum size of decompressed data.
+const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 1024 * 1024;
+
+/// A compression algorithm for message data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Snappy, in its raw format.
+    #[cfg(feature = "snappy")]
+    Snappy,
+    /// Zstandard with the given compression level.
+    #[cfg(feature = "zstd")]
+    Zstd { level: i32 },
+}
+
+impl Compression {
+    /// A suffix advertising this compression, to be used with
+    /// [`crate::ConfigBuilder::protocol_id_suffix`].
+    pub fn protocol_suffix(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "snappy")]
+            Compression::Snappy => "snappy",
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { .. } => "zstd",
+        }
+    }
+
+    fn tag(&self) -> u8 {
+        match self {
+            #[cfg(feature = "snappy")]
+            Compression::Snappy => TAG_SNAPPY,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { .. } => TAG_ZSTD,
+        }
+    }
+
+    fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "snappy")]
+            Compression::Snappy => snap::raw::Encoder::new()
+                .compress_vec(data)
+                .map_err(io::Error::other),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { level } => zstd::bulk::compress(data, *level),
+        }
+    }
+}
+
+/// Compression of a topic.
+#[derive(Debug, Clone, Copy)]
+struct TopicCompression {
+    compression: Compression,
+    threshold: usize,
+}
+
+/// A [`DataTransform`] compressing the data of messages on the configured topics, before applying
+/// the wrapped transform.
+///
+/// Messages on other topics are passed to the wrapped transform unchanged.
+#[derive(Debug, Clone)]
+pub struct CompressionTransform<D = IdentityTransform> {
+    inner: D,
+    topics: HashMap<TopicHash, TopicCompression>,
+    max_decompressed_size: usize,
+}
+
+impl Default for CompressionTransform {
+    fn default() -> Self {
+        Self::new(IdentityTransform)
+    }
+}
+
+impl<D> CompressionTransform<D> {
+    /// Creates a transform wrapping `inner`, without any compressed topics.
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            topics: HashMap::new(),
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        }
+    }
+
+    /// Compresses the data of messages on `topic` with `compression`, if the data is at least
+    /// `threshold` bytes long.
+    pub fn with_topic(
+        mut self,
+        topic: TopicHash,
+        compression: Compression,
+        threshold: usize,
+    ) -> Self {
+        self.topics.insert(
+            topic,
+            TopicCompression {
+                compression,
+                threshold,
+            },
+        );
+        self
+    }
+
+    /// The maximum size of the data of a received message after decompression. Larger messages are
+    /// rejected. The default is 1 MiB.
+    pub fn with_max_decompressed_size(mut self, max_decompressed_size: usize) -> Self {
+        self.max_decompressed_size = max_decompressed_size;
+        self
+    }
+
+    fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let (tag, data) = data
+            .split_first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing compression tag"))?;
+
+        let decompressed = match *tag {
+            TAG_UNCOMPRESSED => data.to_vec(),
+            #[cfg(feature = "snappy")]
+            TAG_SNAPPY => {
+                let len = snap::raw::decompress_len(data)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                if len > self.max_decompressed_size {
+                    return Err(too_large());
+                }
+                snap::raw::Decoder::new()
+                    .decompress_vec(data)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            }
+            #[cfg(feature = "zstd")]
+            TAG_ZSTD => zstd::bulk::decompress(data, self.max_decompressed_size)?,
+            tag => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown compression tag {tag}"),
+                ))
+            }
+        };
+
+        if decompressed.len() > self.max_decompressed_size {
+            return Err(too_large());
+        }
+
+        Ok(decompressed)
+    }
+}
+
+impl<D: DataTransform> DataTransform for CompressionTransform<D> {
+    fn inbound_transform(&self, mut raw_message: RawMessage) -> Result<Message, io::Error> {
+        if self.topics.contains_key(&raw_message.topic) {
+            raw_message.data = self.decompress(&raw_message.data)?;
+        }
+        self.inner.inbound_transform(raw_message)
+    }
+
+    fn outbound_transform(&self, topic: &TopicHash, data: Vec<u8>) -> Result<Vec<u8>, io::Error> {
+        let data = self.inner.outbound_transform(topic, data)?;
+
+        let Some(config) = self.topics.get(topic) else {
+            return Ok(data);
+        };
+
+        if data.len() < config.threshold {
+            let mut tagged = Vec::with_capacity(data.len() + 1);
+            tagged.push(TAG_UNCOMPRESSED);
+            tagged.extend_from_slice(&data);
+            return Ok(tagged);
+        }
+
+        let compressed = config.compression.compress(&data)?;
+        let mut tagged = Vec::with_capacity(compressed.len() + 1);
+        tagged.push(config.compression.tag());
+        tagged.extend_from_slice(&compressed);
+        Ok(tagged)
+    }
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "decompressed message data is too large",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_message(topic: &TopicHash, data: Vec<u8>) -> RawMessage {
+        RawMessage {
+            source: None,
+            data,
+            sequence_number: None,
+            topic: topic.clone(),
+            signature: None,
+            key: None,
+            validated: false,
+        }
+    }
+
+    fn compressions() -> Vec<Compression> {
+        vec![
+            #[cfg(feature = "snappy")]
+            Compression::Snappy,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { level: 3 },
+        ]
+    }
+
+    #[test]
+    fn roundtrip() {
+        let compressed = TopicHash::from_raw("compressed");
+        let plain = TopicHash::from_raw("plain");
+
+        for compression in compressions() {
+            let transform =
+                CompressionTransform::default().with_topic(compressed.clone(), compression, 64);
+
+            let small = b"small".to_vec();
+            let large = b"{\"key\": \"value\"}".repeat(64);
+
+            let data = transform
+                .outbound_transform(&compressed, small.clone())
+                .unwrap();
+            assert_eq!(data[0], TAG_UNCOMPRESSED);
+            let message = transform
+                .inbound_transform(raw_message(&compressed, data))
+                .unwrap();
+            assert_eq!(message.data, small);
+
+            let data = transform
+                .outbound_transform(&compressed, large.clone())
+                .unwrap();
+            assert_eq!(data[0], compression.tag());
+            assert!(data.len() < large.len());
+            let message = transform
+                .inbound_transform(raw_message(&compressed, data))
+                .unwrap();
+            assert_eq!(message.data, large);
+
+            let data = transform.outbound_transform(&plain, large.clone()).unwrap();
+            assert_eq!(data, large);
+        }
+    }
+
+    #[test]
+    fn rejects_too_large_data() {
+        let topic = TopicHash::from_raw("compressed");
+
+        for compression in compressions() {
+            let sender = CompressionTransform::default().with_topic(topic.clone(), compression, 0);
+            let receiver = CompressionTransform::default()
+                .with_topic(topic.clone(), compression, 0)
+                .with_max_decompressed_size(1024);
+
+            let data = sender.outbound_transform(&topic, vec![0; 4096]).unwrap();
+            assert!(receiver
+                .inbound_transform(raw_message(&topic, data))
+                .is_err());
+        }
+    }
+}
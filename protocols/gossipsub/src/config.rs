@@ -19,18 +19,21 @@
 // DEALINGS IN THE SOFTWARE.
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::error::ConfigBuilderError;
+use crate::error::{ConfigBuilderError, InvalidMessageId};
 use crate::protocol::{ProtocolConfig, ProtocolId, FLOODSUB_PROTOCOL};
-use crate::types::{Message, MessageId, PeerKind};
+use crate::topic::TopicHash;
+use crate::types::{Message, MessageId, MessageIdContext, PeerKind};
 
 use libp2p_identity::PeerId;
+use libp2p_swarm::cache::MemoryBudget;
 use libp2p_swarm::StreamProtocol;
 
 /// The types of message validation that can be employed by gossipsub.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ValidationMode {
     /// This is the default setting. This requires the message author to be a valid [`PeerId`] and to
     /// be present as well as the sequence number. All messages must have valid signatures.
@@ -59,6 +62,53 @@ pub enum Version {
     V1_1,
 }
 
+/// Whether and when messages published by the local node are delivered to the local
+/// application, see [`ConfigBuilder::local_delivery`].
+///
+/// Locally delivered messages are emitted as [`crate::Event::LocalMessage`] and only if the node
+/// is subscribed to the topic of the message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LocalDelivery {
+    /// Published messages are not delivered locally. This is the default.
+    #[default]
+    Disabled,
+    /// Published messages are delivered as soon as they have been accepted by
+    /// [`crate::Behaviour::publish`], before they are sent to any peer. The message is delivered
+    /// even if publishing fails afterwards, e.g. with [`crate::PublishError::InsufficientPeers`].
+    Immediate,
+    /// Published messages are delivered once they have been sent to the network, in the form
+    /// remote subscribers receive them, i.e. after the inbound [`crate::DataTransform`] has been
+    /// applied. Messages that fail to publish or whose inbound transform fails are not delivered.
+    AfterValidation,
+}
+
+/// Per-topic overrides of the mesh and gossip parameters of a [`Config`], see
+/// [`ConfigBuilder::set_topic_config`].
+///
+/// Parameters that are `None` fall back to the global value of the [`Config`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TopicConfig {
+    /// Overrides [`Config::mesh_n`].
+    pub mesh_n: Option<usize>,
+    /// Overrides [`Config::mesh_n_low`].
+    pub mesh_n_low: Option<usize>,
+    /// Overrides [`Config::mesh_n_high`].
+    pub mesh_n_high: Option<usize>,
+    /// Overrides [`Config::gossip_factor`].
+    pub gossip_factor: Option<f64>,
+    /// Overrides [`Config::fanout_ttl`].
+    pub fanout_ttl: Option<Duration>,
+    /// Overrides [`Config::flood_publish`].
+    pub flood_publish: Option<bool>,
+    /// Overrides [`Config::validation_mode`].
+    ///
+    /// Messages published by the local node on this topic are signed according to the
+    /// [`MessageAuthenticity`](crate::MessageAuthenticity) set via
+    /// [`Behaviour::set_topic_message_authenticity`](crate::Behaviour::set_topic_message_authenticity),
+    /// which needs to be compatible with this mode.
+    pub validation_mode: Option<ValidationMode>,
+}
+
 /// Configuration parameters that define the performance of the gossipsub network.
 #[derive(Clone)]
 pub struct Config {
@@ -77,24 +127,48 @@ pub struct Config {
     check_explicit_peers_ticks: u64,
     duplicate_cache_time: Duration,
     validate_messages: bool,
-    message_id_fn: Arc<dyn Fn(&Message) -> MessageId + Send + Sync + 'static>,
+    message_id_fn: Arc<
+        dyn Fn(&MessageIdContext<'_>) -> Result<MessageId, InvalidMessageId>
+            + Send
+            + Sync
+            + 'static,
+    >,
     allow_self_origin: bool,
     do_px: bool,
     prune_peers: usize,
+    px_require_signed_records: bool,
     prune_backoff: Duration,
     unsubscribe_backoff: Duration,
     backoff_slack: u32,
     flood_publish: bool,
+    local_delivery: LocalDelivery,
     graft_flood_threshold: Duration,
     mesh_outbound_min: usize,
     opportunistic_graft_ticks: u64,
     opportunistic_graft_peers: usize,
+    opportunistic_graft_threshold: Option<f64>,
     gossip_retransimission: u32,
     max_messages_per_rpc: Option<usize>,
     max_ihave_length: usize,
     max_ihave_messages: usize,
+    ihave_budget: Option<usize>,
+    iwant_budget: Option<usize>,
     iwant_followup_time: Duration,
     published_message_ids_cache_time: Duration,
+    duplicate_cache_budget: Option<MemoryBudget>,
+    slow_peer_queue_len: usize,
+    send_queue_capacity: usize,
+    batch_interval: Option<Duration>,
+    slow_peer_threshold: usize,
+    slow_peer_latency: Option<Duration>,
+    choking: bool,
+    choke_late_after: Duration,
+    choke_threshold: f64,
+    unchoke_threshold: f64,
+    choke_min_messages: usize,
+    validation_timeout: Duration,
+    max_concurrent_validations: usize,
+    topic_configs: HashMap<TopicHash, TopicConfig>,
 }
 
 impl Config {
@@ -211,10 +285,14 @@ impl Config {
     /// addressing, where this function may be set to `hash(message)`. This would prevent messages
     /// of the same content from being duplicated.
     ///
-    /// The function takes a [`Message`] as input and outputs a String to be interpreted as
-    /// the message id.
-    pub fn message_id(&self, message: &Message) -> MessageId {
-        (self.message_id_fn)(message)
+    /// The function takes a [`MessageIdContext`], or just a [`Message`] for locally published
+    /// messages, as input and outputs a String to be interpreted as the message id. Functions set
+    /// through [`ConfigBuilder::message_id_fn_with_context`] may reject the message instead.
+    pub fn message_id<'a>(
+        &self,
+        context: impl Into<MessageIdContext<'a>>,
+    ) -> Result<MessageId, InvalidMessageId> {
+        (self.message_id_fn)(&context.into())
     }
 
     /// By default, gossipsub will reject messages that are sent to us that have the same message
@@ -227,8 +305,8 @@ impl Config {
     /// Whether Peer eXchange is enabled; this should be enabled in bootstrappers and other well
     /// connected/trusted nodes. The default is false.
     ///
-    /// Note: Peer exchange is not implemented today, see
-    /// <https://github.com/libp2p/rust-libp2p/issues/2398>.
+    /// Signed peer records of the exchanged peers are only included if they were provided through
+    /// [`Behaviour::add_peer_record`](crate::Behaviour::add_peer_record).
     pub fn do_px(&self) -> bool {
         self.do_px
     }
@@ -237,12 +315,20 @@ impl Config {
     /// When we prune a peer that's eligible for PX (has a good score, etc), we will try to
     /// send them signed peer records for up to `prune_peers` other peers that we
     /// know of. It is recommended that this value is larger than `mesh_n_high` so that the pruned
-    /// peer can reliably form a full mesh. The default is typically 16 however until signed
-    /// records are spec'd this is disabled and set to 0.
+    /// peer can reliably form a full mesh. The default is 0, which disables connecting to peers
+    /// received through PX.
     pub fn prune_peers(&self) -> usize {
         self.prune_peers
     }
 
+    /// Whether peers received through Peer eXchange are only dialed if they come with a valid
+    /// signed peer record, whose addresses are then used for dialing. If disabled, peers without
+    /// a signed peer record are dialed by their [`PeerId`] alone, which only succeeds if their
+    /// addresses are known otherwise. The default is false.
+    pub fn px_require_signed_records(&self) -> bool {
+        self.px_require_signed_records
+    }
+
     /// Controls the backoff time for pruned peers. This is how long
     /// a peer must wait before attempting to graft into our mesh again after being pruned.
     /// When pruning a peer, we send them our value of `prune_backoff` so they know
@@ -279,6 +365,12 @@ impl Config {
         self.flood_publish
     }
 
+    /// Whether and when messages published by this node are delivered to the local application.
+    /// See [`LocalDelivery`]. The default is [`LocalDelivery::Disabled`].
+    pub fn local_delivery(&self) -> LocalDelivery {
+        self.local_delivery
+    }
+
     /// If a GRAFT comes before `graft_flood_threshold` has elapsed since the last PRUNE,
     /// then there is an extra score penalty applied to the peer through P7.
     pub fn graft_flood_threshold(&self) -> Duration {
@@ -313,6 +405,13 @@ impl Config {
         self.opportunistic_graft_peers
     }
 
+    /// The median mesh score below which opportunistic grafting is applied. If set, this overrides
+    /// [`PeerScoreThresholds::opportunistic_graft_threshold`](crate::PeerScoreThresholds). The
+    /// default is None.
+    pub fn opportunistic_graft_threshold(&self) -> Option<f64> {
+        self.opportunistic_graft_threshold
+    }
+
     /// The maximum number of messages we will process in a given RPC. If this is unset, there is
     /// no limit. The default is None.
     pub fn max_messages_per_rpc(&self) -> Option<usize> {
@@ -321,7 +420,7 @@ impl Config {
 
     /// The maximum number of messages to include in an IHAVE message.
     /// Also controls the maximum number of IHAVE ids we will accept and request with IWANT from a
-    /// peer within a heartbeat, to protect from IHAVE floods. You should adjust this value from the
+    /// peer within a heartbeat, to protect from IHAVE floods, unless an IWANT budget is set. You should adjust this value from the
     /// default if your system is pushing more than 5000 messages in GossipSubHistoryGossip
     /// heartbeats; with the defaults this is 1666 messages/s. The default is 5000.
     pub fn max_ihave_length(&self) -> usize {
@@ -334,6 +433,19 @@ impl Config {
         self.max_ihave_messages
     }
 
+    /// The maximum number of message ids we advertise to a single peer with IHAVE within a
+    /// heartbeat, across all topics. If this is unset, only [`Config::max_ihave_length`] per topic
+    /// applies. The default is None.
+    pub fn ihave_budget(&self) -> Option<usize> {
+        self.ihave_budget
+    }
+
+    /// The maximum number of message ids we request from a single peer with IWANT within a
+    /// heartbeat. Defaults to [`Config::max_ihave_length`].
+    pub fn iwant_budget(&self) -> usize {
+        self.iwant_budget.unwrap_or(self.max_ihave_length)
+    }
+
     /// Time to wait for a message requested through IWANT following an IHAVE advertisement.
     /// If the message is not received within this window, a broken promise is declared and
     /// the router may apply behavioural penalties. The default is 3 seconds.
@@ -350,6 +462,156 @@ impl Config {
     pub fn published_message_ids_cache_time(&self) -> Duration {
         self.published_message_ids_cache_time
     }
+
+    /// The memory budget the ids of seen messages are accounted against, if any. Once the
+    /// budget is exceeded, the oldest ids are forgotten before [`Config::duplicate_cache_time`]
+    /// elapsed. The default is `None`.
+    pub fn duplicate_cache_budget(&self) -> Option<&MemoryBudget> {
+        self.duplicate_cache_budget.as_ref()
+    }
+
+    /// The number of outbound RPCs queued on a connection above which the peer is considered to
+    /// be congested during a heartbeat. The default is 256.
+    pub fn slow_peer_queue_len(&self) -> usize {
+        self.slow_peer_queue_len
+    }
+
+    /// The maximum number of published, forwarded and gossip messages each that can be queued on
+    /// a connection. Further messages of a full class are dropped and reported through
+    /// [`Event::MessagesDropped`](crate::Event::MessagesDropped). Control messages are always
+    /// queued and sent before any other message. The default is 1024.
+    pub fn send_queue_capacity(&self) -> usize {
+        self.send_queue_capacity
+    }
+
+    /// The interval during which outbound messages to a peer are collected before they are sent
+    /// together in a single RPC, up to [`Config::max_transmit_size`]. Control messages are never
+    /// delayed. Batching reduces the per-message overhead of topics with many small messages at
+    /// the cost of latency. The default is `None`, which sends every message on its own.
+    pub fn batch_interval(&self) -> Option<Duration> {
+        self.batch_interval
+    }
+
+    /// The number of consecutive heartbeats in which a mesh peer had a congested send queue or
+    /// broke an IWANT promise after which it is penalized and pruned from the mesh, see
+    /// [`Event::SlowPeer`](crate::Event::SlowPeer). Broken IWANT promises are only tracked when
+    /// peer scoring is enabled. A value of 0 disables slow peer detection. The default is 0.
+    pub fn slow_peer_threshold(&self) -> usize {
+        self.slow_peer_threshold
+    }
+
+    /// The mean delay, relative to the first delivery of the same message by any peer, above
+    /// which a mesh peer delivering messages during a heartbeat counts as slow for
+    /// [`Config::slow_peer_threshold`]. The default is `None`, which disables latency based
+    /// detection.
+    pub fn slow_peer_latency(&self) -> Option<Duration> {
+        self.slow_peer_latency
+    }
+
+    /// Whether lagging mesh peers are choked, i.e. served through `IHAVE` gossip instead of
+    /// eagerly forwarded messages. This is mostly useful for large meshes. The default is false.
+    pub fn choking(&self) -> bool {
+        self.choking
+    }
+
+    /// The time after the first delivery of a message after which a mesh peer delivering the same
+    /// message is considered to be late. The default is 200 milliseconds.
+    pub fn choke_late_after(&self) -> Duration {
+        self.choke_late_after
+    }
+
+    /// The fraction of late deliveries at or above which a mesh peer is choked. The default is
+    /// 0.75.
+    pub fn choke_threshold(&self) -> f64 {
+        self.choke_threshold
+    }
+
+    /// The fraction of late deliveries below which a choked mesh peer is unchoked. The default is
+    /// 0.25.
+    pub fn unchoke_threshold(&self) -> f64 {
+        self.unchoke_threshold
+    }
+
+    /// The number of messages that need to be received from a mesh peer before deciding whether
+    /// to choke or unchoke it. The default is 16.
+    pub fn choke_min_messages(&self) -> usize {
+        self.choke_min_messages
+    }
+
+    /// The time a [`MessageValidator`](crate::MessageValidator) has to validate a message. Messages
+    /// that are not validated in time are ignored. The default is 5 seconds.
+    pub fn validation_timeout(&self) -> Duration {
+        self.validation_timeout
+    }
+
+    /// The maximum number of messages a [`MessageValidator`](crate::MessageValidator) validates
+    /// concurrently. Messages received while this limit is reached are ignored. The default is
+    /// 1024.
+    pub fn max_concurrent_validations(&self) -> usize {
+        self.max_concurrent_validations
+    }
+
+    /// The [`Config::mesh_n`] of the given topic, taking [`TopicConfig`] overrides into account.
+    pub fn mesh_n_for_topic(&self, topic: &TopicHash) -> usize {
+        self.topic_configs
+            .get(topic)
+            .and_then(|c| c.mesh_n)
+            .unwrap_or(self.mesh_n)
+    }
+
+    /// The [`Config::mesh_n_low`] of the given topic, taking [`TopicConfig`] overrides into
+    /// account.
+    pub fn mesh_n_low_for_topic(&self, topic: &TopicHash) -> usize {
+        self.topic_configs
+            .get(topic)
+            .and_then(|c| c.mesh_n_low)
+            .unwrap_or(self.mesh_n_low)
+    }
+
+    /// The [`Config::mesh_n_high`] of the given topic, taking [`TopicConfig`] overrides into
+    /// account.
+    pub fn mesh_n_high_for_topic(&self, topic: &TopicHash) -> usize {
+        self.topic_configs
+            .get(topic)
+            .and_then(|c| c.mesh_n_high)
+            .unwrap_or(self.mesh_n_high)
+    }
+
+    /// The [`Config::gossip_factor`] of the given topic, taking [`TopicConfig`] overrides into
+    /// account.
+    pub fn gossip_factor_for_topic(&self, topic: &TopicHash) -> f64 {
+        self.topic_configs
+            .get(topic)
+            .and_then(|c| c.gossip_factor)
+            .unwrap_or(self.gossip_factor)
+    }
+
+    /// The [`Config::fanout_ttl`] of the given topic, taking [`TopicConfig`] overrides into
+    /// account.
+    pub fn fanout_ttl_for_topic(&self, topic: &TopicHash) -> Duration {
+        self.topic_configs
+            .get(topic)
+            .and_then(|c| c.fanout_ttl)
+            .unwrap_or(self.fanout_ttl)
+    }
+
+    /// The [`Config::flood_publish`] of the given topic, taking [`TopicConfig`] overrides into
+    /// account.
+    pub fn flood_publish_for_topic(&self, topic: &TopicHash) -> bool {
+        self.topic_configs
+            .get(topic)
+            .and_then(|c| c.flood_publish)
+            .unwrap_or(self.flood_publish)
+    }
+
+    /// The [`Config::validation_mode`] of the given topic, taking [`TopicConfig`] overrides into
+    /// account.
+    pub fn validation_mode_for_topic(&self, topic: &TopicHash) -> &ValidationMode {
+        self.protocol
+            .topic_validation_modes
+            .get(topic)
+            .unwrap_or(&self.protocol.validation_mode)
+    }
 }
 
 impl Default for Config {
@@ -386,9 +648,10 @@ impl Default for ConfigBuilder {
                 check_explicit_peers_ticks: 300,
                 duplicate_cache_time: Duration::from_secs(60),
                 validate_messages: false,
-                message_id_fn: Arc::new(|message| {
+                message_id_fn: Arc::new(|context: &MessageIdContext<'_>| {
                     // default message id is: source + sequence number
                     // NOTE: If either the peer_id or source is not provided, we set to 0;
+                    let message = context.message;
                     let mut source_string = if let Some(peer_id) = message.source.as_ref() {
                         peer_id.to_base58()
                     } else {
@@ -398,25 +661,44 @@ impl Default for ConfigBuilder {
                     };
                     source_string
                         .push_str(&message.sequence_number.unwrap_or_default().to_string());
-                    MessageId::from(source_string)
+                    Ok(MessageId::from(source_string))
                 }),
                 allow_self_origin: false,
                 do_px: false,
-                prune_peers: 0, // NOTE: Increasing this currently has little effect until Signed records are implemented.
+                prune_peers: 0,
+                px_require_signed_records: false,
                 prune_backoff: Duration::from_secs(60),
                 unsubscribe_backoff: Duration::from_secs(10),
                 backoff_slack: 1,
                 flood_publish: true,
+                local_delivery: LocalDelivery::Disabled,
                 graft_flood_threshold: Duration::from_secs(10),
                 mesh_outbound_min: 2,
                 opportunistic_graft_ticks: 60,
                 opportunistic_graft_peers: 2,
+                opportunistic_graft_threshold: None,
                 gossip_retransimission: 3,
                 max_messages_per_rpc: None,
                 max_ihave_length: 5000,
                 max_ihave_messages: 10,
+                ihave_budget: None,
+                iwant_budget: None,
                 iwant_followup_time: Duration::from_secs(3),
                 published_message_ids_cache_time: Duration::from_secs(10),
+                duplicate_cache_budget: None,
+                slow_peer_queue_len: 256,
+                send_queue_capacity: 1024,
+                batch_interval: None,
+                slow_peer_threshold: 0,
+                slow_peer_latency: None,
+                choking: false,
+                choke_late_after: Duration::from_millis(200),
+                choke_threshold: 0.75,
+                unchoke_threshold: 0.25,
+                choke_min_messages: 16,
+                validation_timeout: Duration::from_secs(5),
+                max_concurrent_validations: 1024,
+                topic_configs: HashMap::new(),
             },
             invalid_protocol: false,
         }
@@ -490,6 +772,25 @@ impl ConfigBuilder {
         self
     }
 
+    /// Appends `/{suffix}` to the protocol ids configured so far, e.g. to advertise an extension
+    /// like compression that all peers of the node have to support. Peers without the same suffix
+    /// can't negotiate the protocol with us. Floodsub is left untouched.
+    pub fn protocol_id_suffix(&mut self, suffix: impl Into<Cow<'static, str>>) -> &mut Self {
+        let cow = suffix.into();
+
+        for protocol_id in self.config.protocol.protocol_ids.iter_mut() {
+            if *protocol_id == FLOODSUB_PROTOCOL {
+                continue;
+            }
+            match StreamProtocol::try_from_owned(format!("{}/{}", protocol_id.protocol, cow)) {
+                Ok(protocol) => protocol_id.protocol = protocol,
+                Err(_) => self.invalid_protocol = true,
+            }
+        }
+
+        self
+    }
+
     /// Number of heartbeats to keep in the `memcache` (default is 5).
     pub fn history_length(&mut self, history_length: usize) -> &mut Self {
         self.config.history_length = history_length;
@@ -613,6 +914,22 @@ impl ConfigBuilder {
     pub fn message_id_fn<F>(&mut self, id_fn: F) -> &mut Self
     where
         F: Fn(&Message) -> MessageId + Send + Sync + 'static,
+    {
+        self.config.message_id_fn =
+            Arc::new(move |context: &MessageIdContext<'_>| Ok(id_fn(context.message)));
+        self
+    }
+
+    /// Like [`ConfigBuilder::message_id_fn`], but the function is given a [`MessageIdContext`]
+    /// that additionally contains the peer that forwarded the message, and it may reject the
+    /// message by returning [`InvalidMessageId`].
+    ///
+    /// This allows e.g. to compute content-addressed message ids in a topic-specific way. Rejected
+    /// messages received from the network are treated as invalid and penalize the forwarding peer,
+    /// publishing a rejected message fails with [`PublishError::InvalidMessageId`](crate::PublishError::InvalidMessageId).
+    pub fn message_id_fn_with_context<F>(&mut self, id_fn: F) -> &mut Self
+    where
+        F: Fn(&MessageIdContext<'_>) -> Result<MessageId, InvalidMessageId> + Send + Sync + 'static,
     {
         self.config.message_id_fn = Arc::new(id_fn);
         self
@@ -621,8 +938,8 @@ impl ConfigBuilder {
     /// Enables Peer eXchange. This should be enabled in bootstrappers and other well
     /// connected/trusted nodes. The default is false.
     ///
-    /// Note: Peer exchange is not implemented today, see
-    /// <https://github.com/libp2p/rust-libp2p/issues/2398>.
+    /// Signed peer records of the exchanged peers are only included if they were provided through
+    /// [`Behaviour::add_peer_record`](crate::Behaviour::add_peer_record).
     pub fn do_px(&mut self) -> &mut Self {
         self.config.do_px = true;
         self
@@ -633,12 +950,19 @@ impl ConfigBuilder {
     /// When we prune a peer that's eligible for PX (has a good score, etc), we will try to
     /// send them signed peer records for up to [`Self::prune_peers] other peers that we
     /// know of. It is recommended that this value is larger than [`Self::mesh_n_high`] so that the
-    /// pruned peer can reliably form a full mesh. The default is 16.
+    /// pruned peer can reliably form a full mesh. The default is 0.
     pub fn prune_peers(&mut self, prune_peers: usize) -> &mut Self {
         self.config.prune_peers = prune_peers;
         self
     }
 
+    /// Only dial peers received through Peer eXchange if they come with a valid signed peer
+    /// record. The default is false.
+    pub fn px_require_signed_records(&mut self, px_require_signed_records: bool) -> &mut Self {
+        self.config.px_require_signed_records = px_require_signed_records;
+        self
+    }
+
     /// Controls the backoff time for pruned peers. This is how long
     /// a peer must wait before attempting to graft into our mesh again after being pruned.
     /// When pruning a peer, we send them our value of [`Self::prune_backoff`] so they know
@@ -679,6 +1003,13 @@ impl ConfigBuilder {
         self
     }
 
+    /// Whether and when messages published by this node are delivered to the local application.
+    /// See [`LocalDelivery`]. The default is [`LocalDelivery::Disabled`].
+    pub fn local_delivery(&mut self, local_delivery: LocalDelivery) -> &mut Self {
+        self.config.local_delivery = local_delivery;
+        self
+    }
+
     /// If a GRAFT comes before `graft_flood_threshold` has elapsed since the last PRUNE,
     /// then there is an extra score penalty applied to the peer through P7.
     pub fn graft_flood_threshold(&mut self, graft_flood_threshold: Duration) -> &mut Self {
@@ -718,6 +1049,17 @@ impl ConfigBuilder {
         self
     }
 
+    /// The median mesh score below which opportunistic grafting is applied, overriding
+    /// [`PeerScoreThresholds::opportunistic_graft_threshold`](crate::PeerScoreThresholds). Must
+    /// not be negative.
+    pub fn opportunistic_graft_threshold(
+        &mut self,
+        opportunistic_graft_threshold: f64,
+    ) -> &mut Self {
+        self.config.opportunistic_graft_threshold = Some(opportunistic_graft_threshold);
+        self
+    }
+
     /// The maximum number of messages we will process in a given RPC. If this is unset, there is
     /// no limit. The default is None.
     pub fn max_messages_per_rpc(&mut self, max: Option<usize>) -> &mut Self {
@@ -727,7 +1069,7 @@ impl ConfigBuilder {
 
     /// The maximum number of messages to include in an IHAVE message.
     /// Also controls the maximum number of IHAVE ids we will accept and request with IWANT from a
-    /// peer within a heartbeat, to protect from IHAVE floods. You should adjust this value from the
+    /// peer within a heartbeat, to protect from IHAVE floods, unless an IWANT budget is set. You should adjust this value from the
     /// default if your system is pushing more than 5000 messages in GossipSubHistoryGossip
     /// heartbeats; with the defaults this is 1666 messages/s. The default is 5000.
     pub fn max_ihave_length(&mut self, max_ihave_length: usize) -> &mut Self {
@@ -742,6 +1084,21 @@ impl ConfigBuilder {
         self
     }
 
+    /// The maximum number of message ids we advertise to a single peer with IHAVE within a
+    /// heartbeat, across all topics. If this is unset, only
+    /// [`ConfigBuilder::max_ihave_length`] per topic applies. The default is None.
+    pub fn ihave_budget(&mut self, ihave_budget: Option<usize>) -> &mut Self {
+        self.config.ihave_budget = ihave_budget;
+        self
+    }
+
+    /// The maximum number of message ids we request from a single peer with IWANT within a
+    /// heartbeat. Defaults to [`ConfigBuilder::max_ihave_length`].
+    pub fn iwant_budget(&mut self, iwant_budget: usize) -> &mut Self {
+        self.config.iwant_budget = Some(iwant_budget);
+        self
+    }
+
     /// By default, gossipsub will reject messages that are sent to us that has the same message
     /// source as we have specified locally. Enabling this, allows these messages and prevents
     /// penalizing the peer that sent us the message. Default is false.
@@ -782,6 +1139,123 @@ impl ConfigBuilder {
         self
     }
 
+    /// Accounts the ids of seen messages against the given memory budget, which may be shared
+    /// with other caches of the node, e.g. the Kademlia record store. Once the budget is
+    /// exceeded, the oldest ids are forgotten early, at the risk of forwarding a message that
+    /// was already seen again.
+    pub fn duplicate_cache_budget(&mut self, budget: MemoryBudget) -> &mut Self {
+        self.config.duplicate_cache_budget = Some(budget);
+        self
+    }
+
+    /// The number of outbound RPCs queued on a connection above which the peer is considered to
+    /// be congested during a heartbeat. The default is 256.
+    pub fn slow_peer_queue_len(&mut self, slow_peer_queue_len: usize) -> &mut Self {
+        self.config.slow_peer_queue_len = slow_peer_queue_len;
+        self
+    }
+
+    /// The maximum number of published, forwarded and gossip messages each that can be queued on
+    /// a connection. Further messages of a full class are dropped and reported through
+    /// [`Event::MessagesDropped`](crate::Event::MessagesDropped). Control messages are always
+    /// queued and sent before any other message. The default is 1024.
+    pub fn send_queue_capacity(&mut self, send_queue_capacity: usize) -> &mut Self {
+        self.config.send_queue_capacity = send_queue_capacity;
+        self
+    }
+
+    /// The interval during which outbound messages to a peer are collected before they are sent
+    /// together in a single RPC, up to [`Config::max_transmit_size`]. Control messages are never
+    /// delayed. By default, every message is sent on its own.
+    pub fn batch_interval(&mut self, batch_interval: Duration) -> &mut Self {
+        self.config.batch_interval = Some(batch_interval);
+        self
+    }
+
+    /// The number of consecutive heartbeats in which a mesh peer had a congested send queue or
+    /// broke an IWANT promise after which it is penalized and pruned from the mesh, see
+    /// [`Event::SlowPeer`](crate::Event::SlowPeer). Broken IWANT promises are only tracked when
+    /// peer scoring is enabled. A value of 0 disables slow peer detection. The default is 0.
+    pub fn slow_peer_threshold(&mut self, slow_peer_threshold: usize) -> &mut Self {
+        self.config.slow_peer_threshold = slow_peer_threshold;
+        self
+    }
+
+    /// The mean delay, relative to the first delivery of the same message by any peer, above
+    /// which a mesh peer delivering messages during a heartbeat counts as slow for
+    /// [`Config::slow_peer_threshold`]. By default, latency based detection is disabled.
+    pub fn slow_peer_latency(&mut self, slow_peer_latency: Duration) -> &mut Self {
+        self.config.slow_peer_latency = Some(slow_peer_latency);
+        self
+    }
+
+    /// Whether lagging mesh peers are choked, i.e. served through `IHAVE` gossip instead of
+    /// eagerly forwarded messages. This is mostly useful for large meshes. The default is false.
+    pub fn choking(&mut self, choking: bool) -> &mut Self {
+        self.config.choking = choking;
+        self
+    }
+
+    /// The time after the first delivery of a message after which a mesh peer delivering the same
+    /// message is considered to be late. The default is 200 milliseconds.
+    pub fn choke_late_after(&mut self, choke_late_after: Duration) -> &mut Self {
+        self.config.choke_late_after = choke_late_after;
+        self
+    }
+
+    /// The fraction of late deliveries at or above which a mesh peer is choked. The default is
+    /// 0.75.
+    pub fn choke_threshold(&mut self, choke_threshold: f64) -> &mut Self {
+        self.config.choke_threshold = choke_threshold;
+        self
+    }
+
+    /// The fraction of late deliveries below which a choked mesh peer is unchoked. The default is
+    /// 0.25.
+    pub fn unchoke_threshold(&mut self, unchoke_threshold: f64) -> &mut Self {
+        self.config.unchoke_threshold = unchoke_threshold;
+        self
+    }
+
+    /// The number of messages that need to be received from a mesh peer before deciding whether
+    /// to choke or unchoke it. The default is 16.
+    pub fn choke_min_messages(&mut self, choke_min_messages: usize) -> &mut Self {
+        self.config.choke_min_messages = choke_min_messages;
+        self
+    }
+
+    /// The time a [`MessageValidator`](crate::MessageValidator) has to validate a message. Messages
+    /// that are not validated in time are ignored. The default is 5 seconds.
+    pub fn validation_timeout(&mut self, validation_timeout: Duration) -> &mut Self {
+        self.config.validation_timeout = validation_timeout;
+        self
+    }
+
+    /// The maximum number of messages a [`MessageValidator`](crate::MessageValidator) validates
+    /// concurrently. Messages received while this limit is reached are ignored. The default is
+    /// 1024.
+    pub fn max_concurrent_validations(&mut self, max_concurrent_validations: usize) -> &mut Self {
+        self.config.max_concurrent_validations = max_concurrent_validations;
+        self
+    }
+
+    /// Overrides the mesh and gossip parameters for the given topic. This allows e.g. running a
+    /// latency-critical topic with a denser mesh next to bulk topics with cheaper settings.
+    pub fn set_topic_config(&mut self, topic: TopicHash, config: TopicConfig) -> &mut Self {
+        let topic_validation_modes =
+            Arc::make_mut(&mut self.config.protocol.topic_validation_modes);
+        match &config.validation_mode {
+            Some(mode) => {
+                topic_validation_modes.insert(topic.clone(), mode.clone());
+            }
+            None => {
+                topic_validation_modes.remove(&topic);
+            }
+        }
+        self.config.topic_configs.insert(topic, config);
+        self
+    }
+
     /// Constructs a [`Config`] from the given configuration and validates the settings.
     pub fn build(&self) -> Result<Config, ConfigBuilderError> {
         // check all constraints on config
@@ -805,10 +1279,43 @@ impl ConfigBuilder {
             return Err(ConfigBuilderError::MeshOutboundInvalid);
         }
 
+        for topic in self.config.topic_configs.keys() {
+            let mesh_n = self.config.mesh_n_for_topic(topic);
+            if !(self.config.mesh_outbound_min <= self.config.mesh_n_low_for_topic(topic)
+                && self.config.mesh_n_low_for_topic(topic) <= mesh_n
+                && mesh_n <= self.config.mesh_n_high_for_topic(topic))
+            {
+                return Err(ConfigBuilderError::MeshParametersInvalid);
+            }
+
+            if self.config.mesh_outbound_min * 2 > mesh_n {
+                return Err(ConfigBuilderError::MeshOutboundInvalid);
+            }
+        }
+
         if self.config.unsubscribe_backoff.as_millis() == 0 {
             return Err(ConfigBuilderError::UnsubscribeBackoffIsZero);
         }
 
+        if !(0.0 <= self.config.unchoke_threshold
+            && self.config.unchoke_threshold <= self.config.choke_threshold
+            && self.config.choke_threshold <= 1.0)
+        {
+            return Err(ConfigBuilderError::ChokeThresholdsInvalid);
+        }
+
+        if self.config.opportunistic_graft_ticks == 0 {
+            return Err(ConfigBuilderError::OpportunisticGraftTicksIsZero);
+        }
+
+        if self
+            .config
+            .opportunistic_graft_threshold
+            .map_or(false, |threshold| threshold < 0.0)
+        {
+            return Err(ConfigBuilderError::OpportunisticGraftThresholdInvalid);
+        }
+
         if self.invalid_protocol {
             return Err(ConfigBuilderError::InvalidProtocol);
         }
@@ -837,21 +1344,46 @@ impl std::fmt::Debug for Config {
         let _ = builder.field("allow_self_origin", &self.allow_self_origin);
         let _ = builder.field("do_px", &self.do_px);
         let _ = builder.field("prune_peers", &self.prune_peers);
+        let _ = builder.field("px_require_signed_records", &self.px_require_signed_records);
         let _ = builder.field("prune_backoff", &self.prune_backoff);
         let _ = builder.field("backoff_slack", &self.backoff_slack);
         let _ = builder.field("flood_publish", &self.flood_publish);
+        let _ = builder.field("local_delivery", &self.local_delivery);
         let _ = builder.field("graft_flood_threshold", &self.graft_flood_threshold);
         let _ = builder.field("mesh_outbound_min", &self.mesh_outbound_min);
         let _ = builder.field("opportunistic_graft_ticks", &self.opportunistic_graft_ticks);
         let _ = builder.field("opportunistic_graft_peers", &self.opportunistic_graft_peers);
+        let _ = builder.field(
+            "opportunistic_graft_threshold",
+            &self.opportunistic_graft_threshold,
+        );
         let _ = builder.field("max_messages_per_rpc", &self.max_messages_per_rpc);
         let _ = builder.field("max_ihave_length", &self.max_ihave_length);
         let _ = builder.field("max_ihave_messages", &self.max_ihave_messages);
+        let _ = builder.field("ihave_budget", &self.ihave_budget);
+        let _ = builder.field("iwant_budget", &self.iwant_budget);
         let _ = builder.field("iwant_followup_time", &self.iwant_followup_time);
         let _ = builder.field(
             "published_message_ids_cache_time",
             &self.published_message_ids_cache_time,
         );
+        let _ = builder.field("duplicate_cache_budget", &self.duplicate_cache_budget);
+        let _ = builder.field("slow_peer_queue_len", &self.slow_peer_queue_len);
+        let _ = builder.field("send_queue_capacity", &self.send_queue_capacity);
+        let _ = builder.field("batch_interval", &self.batch_interval);
+        let _ = builder.field("slow_peer_threshold", &self.slow_peer_threshold);
+        let _ = builder.field("slow_peer_latency", &self.slow_peer_latency);
+        let _ = builder.field("choking", &self.choking);
+        let _ = builder.field("choke_late_after", &self.choke_late_after);
+        let _ = builder.field("choke_threshold", &self.choke_threshold);
+        let _ = builder.field("unchoke_threshold", &self.unchoke_threshold);
+        let _ = builder.field("choke_min_messages", &self.choke_min_messages);
+        let _ = builder.field("validation_timeout", &self.validation_timeout);
+        let _ = builder.field(
+            "max_concurrent_validations",
+            &self.max_concurrent_validations,
+        );
+        let _ = builder.field("topic_configs", &self.topic_configs);
         builder.finish()
     }
 }
@@ -865,6 +1397,50 @@ mod test {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
 
+    #[test]
+    fn topic_config_overrides_mesh_parameters() {
+        let topic = TopicHash::from_raw("topic");
+        let config = ConfigBuilder::default()
+            .set_topic_config(
+                topic.clone(),
+                TopicConfig {
+                    mesh_n: Some(12),
+                    mesh_n_high: Some(16),
+                    flood_publish: Some(false),
+                    ..TopicConfig::default()
+                },
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(config.mesh_n_for_topic(&topic), 12);
+        assert_eq!(config.mesh_n_high_for_topic(&topic), 16);
+        assert_eq!(config.mesh_n_low_for_topic(&topic), config.mesh_n_low());
+        assert!(!config.flood_publish_for_topic(&topic));
+
+        let other = TopicHash::from_raw("other");
+        assert_eq!(config.mesh_n_for_topic(&other), config.mesh_n());
+        assert!(config.flood_publish_for_topic(&other));
+        assert_eq!(
+            config.validation_mode_for_topic(&other),
+            config.validation_mode()
+        );
+
+        let invalid = ConfigBuilder::default()
+            .set_topic_config(
+                topic,
+                TopicConfig {
+                    mesh_n: Some(20),
+                    ..TopicConfig::default()
+                },
+            )
+            .build();
+        assert!(matches!(
+            invalid,
+            Err(ConfigBuilderError::MeshParametersInvalid)
+        ));
+    }
+
     #[test]
     fn create_config_with_message_id_as_plain_function() {
         let config = ConfigBuilder::default()
@@ -872,7 +1448,7 @@ mod test {
             .build()
             .unwrap();
 
-        let result = config.message_id(&get_gossipsub_message());
+        let result = config.message_id(&get_gossipsub_message()).unwrap();
 
         assert_eq!(result, get_expected_message_id());
     }
@@ -890,7 +1466,7 @@ mod test {
             .build()
             .unwrap();
 
-        let result = config.message_id(&get_gossipsub_message());
+        let result = config.message_id(&get_gossipsub_message()).unwrap();
 
         assert_eq!(result, get_expected_message_id());
     }
@@ -910,7 +1486,7 @@ mod test {
             .build()
             .unwrap();
 
-        let result = config.message_id(&get_gossipsub_message());
+        let result = config.message_id(&get_gossipsub_message()).unwrap();
 
         assert_eq!(result, get_expected_message_id());
     }
@@ -940,6 +1516,32 @@ mod test {
         assert_eq!(protocol_ids[1].kind, PeerKind::Gossipsub);
     }
 
+    #[test]
+    fn create_config_with_protocol_id_suffix() {
+        let protocol_config = ConfigBuilder::default()
+            .protocol_id_prefix("/purple")
+            .protocol_id_suffix("snappy")
+            .build()
+            .unwrap()
+            .protocol_config();
+
+        let protocol_ids = protocol_config.protocol_info();
+
+        assert_eq!(protocol_ids.len(), 2);
+
+        assert_eq!(
+            protocol_ids[0].protocol,
+            StreamProtocol::new("/purple/1.1.0/snappy")
+        );
+        assert_eq!(protocol_ids[0].kind, PeerKind::Gossipsubv1_1);
+
+        assert_eq!(
+            protocol_ids[1].protocol,
+            StreamProtocol::new("/purple/1.0.0/snappy")
+        );
+        assert_eq!(protocol_ids[1].kind, PeerKind::Gossipsub);
+    }
+
     #[test]
     fn create_config_with_custom_protocol_id() {
         let protocol_config = ConfigBuilder::default()
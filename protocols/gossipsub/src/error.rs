@@ -21,6 +21,7 @@
 //! Error types that can result from gossipsub.
 
 use libp2p_identity::SigningError;
+use std::time::Duration;
 
 /// Error associated with publishing a gossipsub message.
 #[derive(Debug)]
@@ -36,6 +37,8 @@ pub enum PublishError {
     MessageTooLarge,
     /// The compression algorithm failed.
     TransformFailed(std::io::Error),
+    /// The message id function rejected the message.
+    InvalidMessageId,
 }
 
 impl std::fmt::Display for PublishError {
@@ -105,6 +108,8 @@ pub enum ValidationError {
     MessageSourcePresent,
     /// The data transformation failed.
     TransformFailed,
+    /// The message id function rejected the message.
+    InvalidMessageId,
 }
 
 impl std::fmt::Display for ValidationError {
@@ -121,6 +126,19 @@ impl From<std::io::Error> for PublishError {
     }
 }
 
+/// Returned by a message id function to reject a malformed message, see
+/// [`ConfigBuilder::message_id_fn_with_context`](crate::ConfigBuilder::message_id_fn_with_context).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidMessageId;
+
+impl std::fmt::Display for InvalidMessageId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "The message id function rejected the message")
+    }
+}
+
+impl std::error::Error for InvalidMessageId {}
+
 /// Error associated with Config building.
 #[derive(Debug)]
 pub enum ConfigBuilderError {
@@ -136,6 +154,12 @@ pub enum ConfigBuilderError {
     UnsubscribeBackoffIsZero,
     /// Invalid protocol
     InvalidProtocol,
+    /// The inequality doesn't hold 0 <= unchoke_threshold <= choke_threshold <= 1
+    ChokeThresholdsInvalid,
+    /// opportunistic_graft_ticks is zero
+    OpportunisticGraftTicksIsZero,
+    /// opportunistic_graft_threshold is negative
+    OpportunisticGraftThresholdInvalid,
 }
 
 impl std::error::Error for ConfigBuilderError {}
@@ -151,6 +175,25 @@ impl std::fmt::Display for ConfigBuilderError {
             Self::MeshOutboundInvalid => write!(f, "The inequality doesn't hold mesh_outbound_min <= self.config.mesh_n / 2"),
             Self::UnsubscribeBackoffIsZero => write!(f, "unsubscribe_backoff is zero"),
             Self::InvalidProtocol => write!(f, "Invalid protocol"),
+            Self::ChokeThresholdsInvalid => write!(f, "The inequality doesn't hold 0 <= unchoke_threshold <= choke_threshold <= 1"),
+            Self::OpportunisticGraftTicksIsZero => write!(f, "opportunistic_graft_ticks is zero"),
+            Self::OpportunisticGraftThresholdInvalid => write!(f, "opportunistic_graft_threshold is negative"),
         }
     }
 }
+
+/// Error when importing a [`TopologySnapshot`](crate::TopologySnapshot) that is older than the
+/// accepted maximum age.
+#[derive(Debug)]
+pub struct StaleSnapshotError {
+    /// The age of the snapshot.
+    pub age: Duration,
+}
+
+impl std::fmt::Display for StaleSnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Topology snapshot is stale, taken {:?} ago", self.age)
+    }
+}
+
+impl std::error::Error for StaleSnapshotError {}
@@ -0,0 +1,77 @@
+// Copyright 2024 Sigma Prime Pty Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Tracking of when recent messages were first received, from any peer.
+//!
+//! Shared by the choking of lagging mesh peers and the slow peer detection, both of which measure
+//! the deliveries of mesh peers relative to the first delivery of a message.
+
+use crate::time_cache::{Entry, TimeCache};
+use crate::MessageId;
+use std::time::Duration;
+use web_time::Instant;
+
+/// Remembers the instant at which each recent message was first received.
+pub(crate) struct FirstDeliveries {
+    first_seen: TimeCache<MessageId, Instant>,
+}
+
+impl FirstDeliveries {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        FirstDeliveries {
+            first_seen: TimeCache::new(ttl),
+        }
+    }
+
+    /// Records the first delivery of `message`, regardless of the peer it came from.
+    pub(crate) fn record(&mut self, message: &MessageId) {
+        if let Entry::Vacant(entry) = self.first_seen.entry(message.clone()) {
+            entry.insert(Instant::now());
+        }
+    }
+
+    /// Returns how long ago `message` was first received, or `None` if it is no longer
+    /// remembered.
+    pub(crate) fn elapsed(&mut self, message: &MessageId) -> Option<Duration> {
+        match self.first_seen.entry(message.clone()) {
+            Entry::Occupied(entry) => Some(entry.into_mut().elapsed()),
+            Entry::Vacant(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_time_since_first_delivery() {
+        let mut deliveries = FirstDeliveries::new(Duration::from_secs(60));
+        let message = MessageId::new(b"message");
+
+        deliveries.record(&message);
+        std::thread::sleep(Duration::from_millis(10));
+        // Later deliveries don't reset the first one.
+        deliveries.record(&message);
+
+        assert!(deliveries.elapsed(&message).unwrap() >= Duration::from_millis(10));
+        assert_eq!(deliveries.elapsed(&MessageId::new(b"unknown")), None);
+    }
+}
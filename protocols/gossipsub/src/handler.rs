@@ -19,6 +19,7 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::protocol::{GossipsubCodec, ProtocolConfig};
+use crate::queue::{DroppedMessages, SendQueue};
 use crate::rpc_proto::proto;
 use crate::types::{PeerKind, RawMessage, Rpc, RpcOut};
 use crate::ValidationError;
@@ -26,16 +27,17 @@ use asynchronous_codec::Framed;
 use futures::future::Either;
 use futures::prelude::*;
 use futures::StreamExt;
+use futures_timer::Delay;
 use libp2p_core::upgrade::DeniedUpgrade;
 use libp2p_swarm::handler::{
     ConnectionEvent, ConnectionHandler, ConnectionHandlerEvent, DialUpgradeError,
     FullyNegotiatedInbound, FullyNegotiatedOutbound, StreamUpgradeError, SubstreamProtocol,
 };
 use libp2p_swarm::Stream;
-use smallvec::SmallVec;
 use std::{
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 use web_time::Instant;
 
@@ -55,6 +57,13 @@ pub enum HandlerEvent {
     /// An inbound or outbound substream has been established with the peer and this informs over
     /// which protocol. This message only occurs once per connection.
     PeerKind(PeerKind),
+    /// The number of RPCs queued for sending to the peer exceeded the configured limit.
+    SendQueueFull,
+    /// The send queue dropped back below the configured limit after a
+    /// [`HandlerEvent::SendQueueFull`].
+    SendQueueDrained,
+    /// Outbound messages were dropped because their class of the send queue was full.
+    MessagesDropped(DroppedMessages),
 }
 
 /// A message sent from the behaviour to the handler.
@@ -95,7 +104,20 @@ pub struct EnabledHandler {
     inbound_substream: Option<InboundSubstreamState>,
 
     /// Queue of values that we want to send to the remote.
-    send_queue: SmallVec<[proto::RPC; 16]>,
+    send_queue: SendQueue,
+
+    /// The length of the send queue above which the connection is reported as congested.
+    max_send_queue_len: usize,
+
+    /// Keeps track of whether the behaviour has been told that the send queue is full.
+    send_queue_full: bool,
+
+    /// The interval during which outbound messages are collected into a single RPC, if batching
+    /// is enabled.
+    batch_interval: Option<Duration>,
+
+    /// Fires when the messages collected for the current batch are due to be sent.
+    batch_timer: Option<Delay>,
 
     /// Flag indicating that an outbound substream is being established to prevent duplicate
     /// requests.
@@ -159,7 +181,12 @@ enum OutboundSubstreamState {
 
 impl Handler {
     /// Builds a new [`Handler`].
-    pub fn new(protocol_config: ProtocolConfig) -> Self {
+    pub fn new(
+        protocol_config: ProtocolConfig,
+        max_send_queue_len: usize,
+        send_queue_capacity: usize,
+        batch_interval: Option<Duration>,
+    ) -> Self {
         Handler::Enabled(EnabledHandler {
             listen_protocol: protocol_config,
             inbound_substream: None,
@@ -167,7 +194,11 @@ impl Handler {
             outbound_substream_establishing: false,
             outbound_substream_attempts: 0,
             inbound_substream_attempts: 0,
-            send_queue: SmallVec::new(),
+            send_queue: SendQueue::new(send_queue_capacity),
+            max_send_queue_len,
+            send_queue_full: false,
+            batch_interval,
+            batch_timer: None,
             peer_kind: None,
             peer_kind_sent: false,
             last_io_activity: Instant::now(),
@@ -212,6 +243,49 @@ impl EnabledHandler {
         self.outbound_substream = Some(OutboundSubstreamState::WaitingOutput(substream));
     }
 
+    /// Reports dropped messages and the send queue crossing `max_send_queue_len` in either
+    /// direction.
+    fn poll_send_queue(&mut self) -> Option<HandlerEvent> {
+        if let Some(dropped) = self.send_queue.take_dropped() {
+            return Some(HandlerEvent::MessagesDropped(dropped));
+        }
+
+        let full = self.send_queue.len() > self.max_send_queue_len;
+        if full == self.send_queue_full {
+            return None;
+        }
+        self.send_queue_full = full;
+
+        if full {
+            tracing::debug!(
+                queued=%self.send_queue.len(),
+                "Send queue exceeded its limit"
+            );
+            Some(HandlerEvent::SendQueueFull)
+        } else {
+            Some(HandlerEvent::SendQueueDrained)
+        }
+    }
+
+    /// Returns whether the queued messages are due to be sent. When batching, messages are held
+    /// back until the batch interval elapsed, unless control messages are waiting.
+    fn poll_batch_timer(&mut self, cx: &mut Context<'_>) -> bool {
+        let Some(interval) = self.batch_interval else {
+            return true;
+        };
+        if self.send_queue.has_control() {
+            self.batch_timer = None;
+            return true;
+        }
+
+        let timer = self.batch_timer.get_or_insert_with(|| Delay::new(interval));
+        if timer.poll_unpin(cx).is_pending() {
+            return false;
+        }
+        self.batch_timer = None;
+        true
+    }
+
     fn poll(
         &mut self,
         cx: &mut Context<'_>,
@@ -222,6 +296,10 @@ impl EnabledHandler {
             <Handler as ConnectionHandler>::ToBehaviour,
         >,
     > {
+        if let Some(event) = self.poll_send_queue() {
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(event));
+        }
+
         if !self.peer_kind_sent {
             if let Some(peer_kind) = self.peer_kind.as_ref() {
                 self.peer_kind_sent = true;
@@ -250,8 +328,19 @@ impl EnabledHandler {
             ) {
                 // outbound idle state
                 Some(OutboundSubstreamState::WaitingOutput(substream)) => {
-                    if let Some(message) = self.send_queue.pop() {
-                        self.send_queue.shrink_to_fit();
+                    if self.send_queue.is_empty() || !self.poll_batch_timer(cx) {
+                        self.outbound_substream =
+                            Some(OutboundSubstreamState::WaitingOutput(substream));
+                        break;
+                    }
+
+                    let message = match self.batch_interval {
+                        Some(_) => self
+                            .send_queue
+                            .pop_batch(self.listen_protocol.max_transmit_size),
+                        None => self.send_queue.pop(),
+                    };
+                    if let Some(message) = message {
                         self.outbound_substream =
                             Some(OutboundSubstreamState::PendingSend(substream, message));
                         continue;
@@ -383,6 +472,10 @@ impl EnabledHandler {
             }
         }
 
+        if let Some(event) = self.poll_send_queue() {
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(event));
+        }
+
         Poll::Pending
     }
 }
@@ -409,7 +502,9 @@ impl ConnectionHandler for Handler {
     fn on_behaviour_event(&mut self, message: HandlerIn) {
         match self {
             Handler::Enabled(handler) => match message {
-                HandlerIn::Message(m) => handler.send_queue.push(m.into_protobuf()),
+                HandlerIn::Message(m) => {
+                    handler.send_queue.push(m);
+                }
                 HandlerIn::JoinedMesh => {
                     handler.in_mesh = true;
                 }
@@ -499,7 +594,7 @@ impl ConnectionHandler for Handler {
                         handler.on_fully_negotiated_outbound(fully_negotiated_outbound)
                     }
                     ConnectionEvent::DialUpgradeError(DialUpgradeError {
-                        error: StreamUpgradeError::Timeout,
+                        error: StreamUpgradeError::Timeout | StreamUpgradeError::NegotiationTimeout,
                         ..
                     }) => {
                         tracing::debug!("Dial upgrade error: Protocol negotiation timeout");
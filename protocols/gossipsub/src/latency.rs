@@ -0,0 +1,111 @@
+// Copyright 2024 Sigma Prime Pty Ltd.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Tracking of the delivery latency of mesh peers, relative to the first delivery of each
+//! message, for slow peer detection.
+
+use libp2p_identity::PeerId;
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
+/// Delivery latency of a mesh peer since the last heartbeat.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct PeerLatency {
+    /// The number of messages received from the peer.
+    pub(crate) deliveries: usize,
+    /// The sum of the delays with which the peer delivered those messages.
+    pub(crate) total: Duration,
+}
+
+impl PeerLatency {
+    /// The mean delay with which the peer delivered messages, if it delivered any.
+    pub(crate) fn mean(&self) -> Option<Duration> {
+        (self.deliveries > 0).then(|| self.total / self.deliveries as u32)
+    }
+}
+
+/// Tracks how long after the first delivery each mesh peer delivers messages.
+pub(crate) struct DeliveryLatencies {
+    /// How long messages are remembered, used as latency of messages that were forgotten.
+    ttl: Duration,
+    /// Latencies per mesh peer since the last heartbeat.
+    peers: HashMap<PeerId, PeerLatency>,
+}
+
+impl DeliveryLatencies {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        DeliveryLatencies {
+            ttl,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Records that the mesh peer `peer` was the first to deliver a message.
+    pub(crate) fn first_delivery(&mut self, peer: PeerId) {
+        self.peers.entry(peer).or_default().deliveries += 1;
+    }
+
+    /// Records that the mesh peer `peer` delivered a message we had already received
+    /// `since_first` ago, see [`FirstDeliveries`](crate::first_delivery::FirstDeliveries).
+    pub(crate) fn duplicate_delivery(&mut self, peer: PeerId, since_first: Option<Duration>) {
+        // We no longer remember when the message was first seen, so it must be very late.
+        let latency = since_first.unwrap_or(self.ttl);
+
+        let stats = self.peers.entry(peer).or_default();
+        stats.deliveries += 1;
+        stats.total += latency;
+    }
+
+    /// Returns and resets the latencies of `peer` since the last heartbeat.
+    pub(crate) fn take(&mut self, peer: &PeerId) -> PeerLatency {
+        self.peers.remove(peer).unwrap_or_default()
+    }
+
+    /// Forgets the latencies of peers that are not part of `mesh_peers` anymore.
+    pub(crate) fn retain_mesh_peers(&mut self, mesh_peers: &BTreeSet<PeerId>) {
+        self.peers.retain(|peer, _| mesh_peers.contains(peer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_latency_relative_to_first_delivery() {
+        let fast = PeerId::random();
+        let slow = PeerId::random();
+        let mut latencies = DeliveryLatencies::new(Duration::from_secs(60));
+
+        latencies.first_delivery(fast);
+        latencies.duplicate_delivery(slow, Some(Duration::from_millis(10)));
+        latencies.duplicate_delivery(slow, None);
+
+        let fast_latency = latencies.take(&fast);
+        assert_eq!(fast_latency.deliveries, 1);
+        assert_eq!(fast_latency.mean(), Some(Duration::ZERO));
+
+        let slow_latency = latencies.take(&slow);
+        assert_eq!(slow_latency.deliveries, 2);
+        assert!(slow_latency.total >= Duration::from_secs(60) + Duration::from_millis(10));
+
+        assert_eq!(latencies.take(&slow).mean(), None);
+    }
+}
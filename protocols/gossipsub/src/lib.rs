@@ -95,37 +95,60 @@
 
 mod backoff;
 mod behaviour;
+mod choke;
+#[cfg(any(feature = "snappy", feature = "zstd"))]
+mod compression;
 mod config;
 mod error;
+mod first_delivery;
 mod gossip_promises;
 mod handler;
+mod latency;
 mod mcache;
 mod metrics;
 mod peer_score;
 mod protocol;
+mod queue;
 mod rpc_proto;
 mod subscription_filter;
 mod time_cache;
 mod topic;
+mod topology;
+mod tracer;
 mod transform;
 mod types;
+mod validator;
 
-pub use self::behaviour::{Behaviour, Event, MessageAuthenticity};
-pub use self::config::{Config, ConfigBuilder, ValidationMode, Version};
-pub use self::error::{ConfigBuilderError, PublishError, SubscriptionError, ValidationError};
+pub use self::behaviour::{Behaviour, Event, MessageAuthenticity, SlowPeerReason, SlowPeerStats};
+#[cfg(any(feature = "snappy", feature = "zstd"))]
+pub use self::compression::{Compression, CompressionTransform};
+pub use self::config::{
+    Config, ConfigBuilder, LocalDelivery, TopicConfig, ValidationMode, Version,
+};
+pub use self::error::{
+    ConfigBuilderError, InvalidMessageId, PublishError, StaleSnapshotError, SubscriptionError,
+    ValidationError,
+};
 pub use self::metrics::Config as MetricsConfig;
 pub use self::peer_score::{
-    score_parameter_decay, score_parameter_decay_with_base, PeerScoreParams, PeerScoreThresholds,
-    TopicScoreParams,
+    score_parameter_decay, score_parameter_decay_with_base, PeerScoreParams, PeerScoreReport,
+    PeerScoreSnapshot, PeerScoreThresholds, RejectReason, TopicScoreParams, TopicScoreReport,
+    TopicScoreSnapshot,
 };
+pub use self::queue::DroppedMessages;
 pub use self::subscription_filter::{
     AllowAllSubscriptionFilter, CallbackSubscriptionFilter, CombinedSubscriptionFilters,
     MaxCountSubscriptionFilter, RegexSubscriptionFilter, TopicSubscriptionFilter,
     WhitelistSubscriptionFilter,
 };
 pub use self::topic::{Hasher, Topic, TopicHash};
+pub use self::topology::TopologySnapshot;
+pub use self::tracer::{TraceEvent, Tracer};
 pub use self::transform::{DataTransform, IdentityTransform};
-pub use self::types::{Message, MessageAcceptance, MessageId, RawMessage};
+pub use self::types::{
+    ControlAction, Message, MessageAcceptance, MessageId, MessageIdContext, PeerInfo, RawMessage,
+};
+pub use self::validator::MessageValidator;
 
 #[deprecated(note = "Will be removed from the public API.")]
 pub type Rpc = self::types::Rpc;
@@ -20,13 +20,10 @@
 
 use crate::topic::TopicHash;
 use crate::types::{MessageId, RawMessage};
-use libp2p_identity::PeerId;
+use libp2p_identity::{peer_set::PeerSet, PeerId};
 use std::collections::hash_map::Entry;
 use std::fmt::Debug;
-use std::{
-    collections::{HashMap, HashSet},
-    fmt,
-};
+use std::{collections::HashMap, fmt};
 
 /// CacheEntry stored in the history.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -38,7 +35,7 @@ pub(crate) struct CacheEntry {
 /// MessageCache struct holding history of messages.
 #[derive(Clone)]
 pub(crate) struct MessageCache {
-    msgs: HashMap<MessageId, (RawMessage, HashSet<PeerId>)>,
+    msgs: HashMap<MessageId, (RawMessage, PeerSet)>,
     /// For every message and peer the number of times this peer asked for the message
     iwant_counts: HashMap<MessageId, HashMap<PeerId, u32>>,
     history: Vec<Vec<CacheEntry>>,
@@ -83,7 +80,7 @@ impl MessageCache {
                     mid: message_id.clone(),
                     topic: msg.topic.clone(),
                 };
-                entry.insert((msg, HashSet::default()));
+                entry.insert((msg, PeerSet::default()));
                 self.history[0].push(cache_entry);
 
                 tracing::trace!(message=?message_id, "Put message in mcache");
@@ -139,10 +136,7 @@ impl MessageCache {
     /// Gets a message with [`MessageId`] and tags it as validated.
     /// This function also returns the known peers that have sent us this message. This is used to
     /// prevent us sending redundant messages to peers who have already propagated it.
-    pub(crate) fn validate(
-        &mut self,
-        message_id: &MessageId,
-    ) -> Option<(&RawMessage, HashSet<PeerId>)> {
+    pub(crate) fn validate(&mut self, message_id: &MessageId) -> Option<(&RawMessage, PeerSet)> {
         self.msgs.get_mut(message_id).map(|(message, known_peers)| {
             message.validated = true;
             // Clear the known peers list (after a message is validated, it is forwarded and we no
@@ -206,10 +200,7 @@ impl MessageCache {
     }
 
     /// Removes a message from the cache and returns it if existent
-    pub(crate) fn remove(
-        &mut self,
-        message_id: &MessageId,
-    ) -> Option<(RawMessage, HashSet<PeerId>)> {
+    pub(crate) fn remove(&mut self, message_id: &MessageId) -> Option<(RawMessage, PeerSet)> {
         //We only remove the message from msgs and iwant_count and keep the message_id in the
         // history vector. Zhe id in the history vector will simply be ignored on popping.
 
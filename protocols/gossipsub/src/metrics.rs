@@ -21,17 +21,19 @@
 //! A set of metrics used to help track and diagnose the network behaviour of the gossipsub
 //! protocol.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
 use prometheus_client::encoding::{EncodeLabelSet, EncodeLabelValue};
 use prometheus_client::metrics::counter::Counter;
 use prometheus_client::metrics::family::{Family, MetricConstructor};
 use prometheus_client::metrics::gauge::Gauge;
-use prometheus_client::metrics::histogram::{linear_buckets, Histogram};
+use prometheus_client::metrics::histogram::{exponential_buckets, linear_buckets, Histogram};
 use prometheus_client::registry::Registry;
+use web_time::Instant;
 
 use crate::topic::TopicHash;
-use crate::types::{MessageAcceptance, PeerKind};
+use crate::types::{MessageAcceptance, MessageId, PeerKind};
 
 // Default value that limits for how many topics do we store metrics.
 const DEFAULT_MAX_TOPICS: usize = 300;
@@ -51,6 +53,9 @@ pub struct Config {
     pub max_never_subscribed_topics: usize,
     /// Buckets used for the score histograms.
     pub score_buckets: Vec<f64>,
+    /// If set, per-topic metrics are only recorded for the topics in this list, in which case
+    /// `max_topics` and `max_never_subscribed_topics` are not applied.
+    pub topic_allow_list: Option<HashSet<TopicHash>>,
 }
 
 impl Config {
@@ -91,6 +96,7 @@ impl Default for Config {
             max_topics: DEFAULT_MAX_TOPICS,
             max_never_subscribed_topics: DEFAULT_MAX_NEVER_SUBSCRIBED_TOPICS,
             score_buckets,
+            topic_allow_list: None,
         }
     }
 }
@@ -107,10 +113,14 @@ pub(crate) struct Metrics {
     /// have subscribed at some point. This helps keep the metrics bounded, since these topics come
     /// from received messages and not explicit application subscriptions.
     max_never_subscribed_topics: usize,
+    /// If set, the only topics for which we store metrics.
+    topic_allow_list: Option<HashSet<TopicHash>>,
 
     /* Auxiliary variables */
     /// Information needed to decide if a topic is allowed or not.
     topic_info: HashMap<TopicHash, EverSubscribed>,
+    /// The IWANT requests we are waiting on, to measure how long it takes to fulfill them.
+    pending_iwants: HashMap<MessageId, (TopicHash, Instant)>,
 
     /* Metrics per known topic */
     /// Status of our subscription to this topic. This metric allows analyzing other topic metrics
@@ -144,6 +154,10 @@ pub(crate) struct Metrics {
     topic_msg_sent_bytes: Family<TopicHash, Counter>,
     /// Number of gossipsub messages published to each topic.
     topic_msg_published: Family<TopicHash, Counter>,
+    /// Bytes from messages we published, sent to each topic.
+    topic_msg_published_bytes: Family<TopicHash, Counter>,
+    /// Bytes from messages we forwarded on behalf of other peers, sent to each topic.
+    topic_msg_forwarded_bytes: Family<TopicHash, Counter>,
 
     /// Number of gossipsub messages received on each topic (without filtering duplicates).
     topic_msg_recv_counts_unfiltered: Family<TopicHash, Counter>,
@@ -151,6 +165,8 @@ pub(crate) struct Metrics {
     topic_msg_recv_counts: Family<TopicHash, Counter>,
     /// Bytes received from gossip messages for each topic.
     topic_msg_recv_bytes: Family<TopicHash, Counter>,
+    /// Number of duplicate gossipsub messages received on each topic.
+    topic_msg_recv_duplicates: Family<TopicHash, Counter>,
 
     /* Metrics related to scoring */
     /// Histogram of the scores for each mesh topic.
@@ -171,9 +187,17 @@ pub(crate) struct Metrics {
     /// message expires from the memcache before it can be validated, we count this a cache miss
     /// and it is an indicator that the memcache size should be increased.
     memcache_misses: Counter,
+    /// The number of times the IHAVE budget of a peer limited the message ids we advertised to it
+    /// within a heartbeat.
+    ihave_budget_clamped: Counter,
+    /// The number of times the IWANT budget of a peer limited the message ids we requested from
+    /// it within a heartbeat.
+    iwant_budget_clamped: Counter,
     /// The number of times we have decided that an IWANT control message is required for this
     /// topic. A very high metric might indicate an underperforming network.
     topic_iwant_msgs: Family<TopicHash, Counter>,
+    /// The time between sending an IWANT for a message and receiving the message, in seconds.
+    topic_iwant_latency: Family<TopicHash, Histogram, fn() -> Histogram>,
 }
 
 impl Metrics {
@@ -183,6 +207,7 @@ impl Metrics {
             max_topics,
             max_never_subscribed_topics,
             score_buckets,
+            topic_allow_list,
         } = config;
 
         macro_rules! register_family {
@@ -246,6 +271,14 @@ impl Metrics {
             "topic_msg_sent_bytes",
             "Bytes from gossip messages sent to each topic"
         );
+        let topic_msg_published_bytes = register_family!(
+            "topic_msg_published_bytes",
+            "Bytes from gossip messages we published, sent to each topic"
+        );
+        let topic_msg_forwarded_bytes = register_family!(
+            "topic_msg_forwarded_bytes",
+            "Bytes from gossip messages we forwarded, sent to each topic"
+        );
 
         let topic_msg_recv_counts_unfiltered = register_family!(
             "topic_msg_recv_counts_unfiltered",
@@ -260,6 +293,10 @@ impl Metrics {
             "topic_msg_recv_bytes",
             "Bytes received from gossip messages for each topic"
         );
+        let topic_msg_recv_duplicates = register_family!(
+            "topic_msg_recv_duplicates",
+            "Number of duplicate gossip messages received on each topic"
+        );
 
         let hist_builder = HistBuilder {
             buckets: score_buckets,
@@ -292,6 +329,13 @@ impl Metrics {
             "topic_iwant_msgs",
             "Number of times we have decided an IWANT is required for this topic"
         );
+        let topic_iwant_latency: Family<_, _, fn() -> Histogram> =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(0.005, 2.0, 10)));
+        registry.register(
+            "topic_iwant_latency",
+            "Histogram of the time in seconds until an IWANT is fulfilled, per topic",
+            topic_iwant_latency.clone(),
+        );
         let memcache_misses = {
             let metric = Counter::default();
             registry.register(
@@ -301,11 +345,31 @@ impl Metrics {
             );
             metric
         };
+        let ihave_budget_clamped = {
+            let metric = Counter::default();
+            registry.register(
+                "ihave_budget_clamped",
+                "Number of times the IHAVE budget of a peer limited the message ids advertised",
+                metric.clone(),
+            );
+            metric
+        };
+        let iwant_budget_clamped = {
+            let metric = Counter::default();
+            registry.register(
+                "iwant_budget_clamped",
+                "Number of times the IWANT budget of a peer limited the message ids requested",
+                metric.clone(),
+            );
+            metric
+        };
 
         Self {
             max_topics,
             max_never_subscribed_topics,
+            topic_allow_list,
             topic_info: HashMap::default(),
+            pending_iwants: HashMap::default(),
             topic_subscription_status,
             topic_peers_count,
             invalid_messages,
@@ -318,15 +382,21 @@ impl Metrics {
             topic_msg_sent_counts,
             topic_msg_sent_bytes,
             topic_msg_published,
+            topic_msg_published_bytes,
+            topic_msg_forwarded_bytes,
             topic_msg_recv_counts_unfiltered,
             topic_msg_recv_counts,
             topic_msg_recv_bytes,
+            topic_msg_recv_duplicates,
             score_per_mesh,
             scoring_penalties,
             peers_per_protocol,
             heartbeat_duration,
             memcache_misses,
+            ihave_budget_clamped,
+            iwant_budget_clamped,
             topic_iwant_msgs,
+            topic_iwant_latency,
         }
     }
 
@@ -341,6 +411,13 @@ impl Metrics {
     fn register_topic(&mut self, topic: &TopicHash) -> Result<(), ()> {
         if self.topic_info.contains_key(topic) {
             Ok(())
+        } else if let Some(allowed) = &self.topic_allow_list {
+            if !allowed.contains(topic) {
+                return Err(());
+            }
+            self.topic_info.insert(topic.clone(), false);
+            self.topic_subscription_status.get_or_create(topic).set(0);
+            Ok(())
         } else if self.topic_info.len() < self.max_topics
             && self.non_subscription_topics_count() < self.max_never_subscribed_topics
         {
@@ -374,7 +451,11 @@ impl Metrics {
     /// Registers the subscription to a topic if the configured limits allow it.
     /// Sets the registered number of peers in the mesh to 0.
     pub(crate) fn joined(&mut self, topic: &TopicHash) {
-        if self.topic_info.contains_key(topic) || self.topic_info.len() < self.max_topics {
+        let allowed = match &self.topic_allow_list {
+            Some(allowed) => allowed.contains(topic),
+            None => self.topic_info.contains_key(topic) || self.topic_info.len() < self.max_topics,
+        };
+        if allowed {
             self.topic_info.insert(topic.clone(), true);
             let was_subscribed = self.topic_subscription_status.get_or_create(topic).set(1);
             debug_assert_eq!(was_subscribed, 0);
@@ -447,8 +528,28 @@ impl Metrics {
         }
     }
 
+    /// Register sending a message we published over a topic.
+    pub(crate) fn msg_published_sent(&mut self, topic: &TopicHash, bytes: usize) {
+        if self.register_topic(topic).is_ok() {
+            self.topic_msg_published_bytes
+                .get_or_create(topic)
+                .inc_by(bytes as u64);
+            self.msg_sent(topic, bytes);
+        }
+    }
+
+    /// Register forwarding a message over a topic.
+    pub(crate) fn msg_forwarded(&mut self, topic: &TopicHash, bytes: usize) {
+        if self.register_topic(topic).is_ok() {
+            self.topic_msg_forwarded_bytes
+                .get_or_create(topic)
+                .inc_by(bytes as u64);
+            self.msg_sent(topic, bytes);
+        }
+    }
+
     /// Register sending a message over a topic.
-    pub(crate) fn msg_sent(&mut self, topic: &TopicHash, bytes: usize) {
+    fn msg_sent(&mut self, topic: &TopicHash, bytes: usize) {
         if self.register_topic(topic).is_ok() {
             self.topic_msg_sent_counts.get_or_create(topic).inc();
             self.topic_msg_sent_bytes
@@ -464,6 +565,13 @@ impl Metrics {
         }
     }
 
+    /// Register that a duplicate message was received.
+    pub(crate) fn msg_recvd_duplicate(&mut self, topic: &TopicHash) {
+        if self.register_topic(topic).is_ok() {
+            self.topic_msg_recv_duplicates.get_or_create(topic).inc();
+        }
+    }
+
     /// Register that a message was received (could have been a duplicate).
     pub(crate) fn msg_recvd_unfiltered(&mut self, topic: &TopicHash, bytes: usize) {
         if self.register_topic(topic).is_ok() {
@@ -495,6 +603,16 @@ impl Metrics {
         self.memcache_misses.inc();
     }
 
+    /// Register the IHAVE budget of a peer limiting the message ids advertised to it.
+    pub(crate) fn ihave_budget_clamped(&mut self) {
+        self.ihave_budget_clamped.inc();
+    }
+
+    /// Register the IWANT budget of a peer limiting the message ids requested from it.
+    pub(crate) fn iwant_budget_clamped(&mut self) {
+        self.iwant_budget_clamped.inc();
+    }
+
     /// Register sending an IWANT msg for this topic.
     pub(crate) fn register_iwant(&mut self, topic: &TopicHash) {
         if self.register_topic(topic).is_ok() {
@@ -502,6 +620,29 @@ impl Metrics {
         }
     }
 
+    /// Register that we sent an IWANT for a message on this topic.
+    pub(crate) fn register_iwant_sent(&mut self, topic: &TopicHash, message_id: &MessageId) {
+        if self.register_topic(topic).is_ok() {
+            self.pending_iwants
+                .insert(message_id.clone(), (topic.clone(), Instant::now()));
+        }
+    }
+
+    /// Register that a message arrived, fulfilling a pending IWANT for it if there is one.
+    pub(crate) fn iwant_fulfilled(&mut self, message_id: &MessageId) {
+        if let Some((topic, sent)) = self.pending_iwants.remove(message_id) {
+            self.topic_iwant_latency
+                .get_or_create(&topic)
+                .observe(sent.elapsed().as_secs_f64());
+        }
+    }
+
+    /// Forgets about the IWANTs that have not been fulfilled within `timeout`.
+    pub(crate) fn expire_iwants(&mut self, timeout: Duration) {
+        self.pending_iwants
+            .retain(|_, (_, sent)| sent.elapsed() < timeout);
+    }
+
     /// Observes a heartbeat duration.
     pub(crate) fn observe_heartbeat_duration(&mut self, millis: u64) {
         self.heartbeat_duration.observe(millis as f64);
@@ -559,6 +700,8 @@ pub(crate) enum Churn {
     Unsub,
     /// Too many peers.
     Excess,
+    /// Peer was too slow.
+    Slow,
 }
 
 /// Kinds of reasons a peer's score has been penalized
@@ -572,6 +715,8 @@ pub(crate) enum Penalty {
     MessageDeficit,
     /// Too many peers under one IP address.
     IPColocation,
+    /// A mesh peer was too slow to keep up.
+    SlowPeer,
 }
 
 /// Label for the mesh inclusion event metrics.
@@ -610,3 +755,59 @@ impl MetricConstructor<Histogram> for HistBuilder {
         Histogram::new(self.buckets.clone().into_iter())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus_client::encoding::text::encode;
+
+    fn encoded(registry: &Registry) -> String {
+        let mut buffer = String::new();
+        encode(&mut buffer, registry).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn topic_allow_list_limits_labels() {
+        let allowed = TopicHash::from_raw("allowed");
+        let other = TopicHash::from_raw("other");
+
+        let mut registry = Registry::default();
+        let mut metrics = Metrics::new(
+            &mut registry,
+            Config {
+                topic_allow_list: Some(HashSet::from([allowed.clone()])),
+                ..Config::default()
+            },
+        );
+
+        metrics.joined(&other);
+        metrics.msg_recvd_duplicate(&allowed);
+        metrics.msg_recvd_duplicate(&other);
+        metrics.msg_forwarded(&allowed, 10);
+        metrics.msg_published_sent(&other, 10);
+
+        let output = encoded(&registry);
+        assert!(output.contains("topic_msg_recv_duplicates_total{hash=\"allowed\"} 1"));
+        assert!(output.contains("topic_msg_forwarded_bytes_total{hash=\"allowed\"} 10"));
+        assert!(!output.contains("\"other\""));
+    }
+
+    #[test]
+    fn iwant_latency_is_observed_once() {
+        let topic = TopicHash::from_raw("topic");
+        let message_id = MessageId::new(b"message");
+
+        let mut registry = Registry::default();
+        let mut metrics = Metrics::new(&mut registry, Config::default());
+
+        metrics.register_iwant_sent(&topic, &message_id);
+        metrics.iwant_fulfilled(&message_id);
+        metrics.iwant_fulfilled(&message_id);
+        assert!(encoded(&registry).contains("topic_iwant_latency_count{hash=\"topic\"} 1"));
+
+        metrics.register_iwant_sent(&topic, &message_id);
+        metrics.expire_iwants(Duration::ZERO);
+        assert!(metrics.pending_iwants.is_empty());
+    }
+}
@@ -55,6 +55,68 @@ pub(crate) struct PeerScore {
     message_delivery_time_callback: Option<fn(&PeerId, &TopicHash, f64)>,
 }
 
+/// A peer's score broken down into its weighted components, see
+/// [`Behaviour::peer_score_report`](crate::Behaviour::peer_score_report).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeerScoreReport {
+    /// The overall score of the peer.
+    pub score: f64,
+    /// The score components of each scored topic.
+    ///
+    /// The sum of the topic scores is capped by [`PeerScoreParams::topic_score_cap`].
+    pub topics: HashMap<TopicHash, TopicScoreReport>,
+    /// The application-specific score (P5).
+    pub application_score: f64,
+    /// The penalty for too many peers sharing the same IP address (P6).
+    pub ip_colocation_penalty: f64,
+    /// The penalty for misbehaviour, e.g. broken IWANT promises (P7).
+    pub behaviour_penalty: f64,
+}
+
+/// The weighted components of a peer's score in a single topic, see [`PeerScoreReport`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TopicScoreReport {
+    /// The score of the topic, i.e. the sum of the components multiplied by the topic weight.
+    pub score: f64,
+    /// The score for the time spent in the mesh (P1).
+    pub time_in_mesh: f64,
+    /// The score for first message deliveries (P2).
+    pub first_message_deliveries: f64,
+    /// The penalty for a mesh message delivery deficit (P3).
+    pub mesh_message_deliveries: f64,
+    /// The sticky penalty for mesh message delivery failures (P3b).
+    pub mesh_failure_penalty: f64,
+    /// The penalty for invalid messages (P4).
+    pub invalid_message_deliveries: f64,
+}
+
+/// The persistable score state of a peer, see
+/// [`Behaviour::export_topology`](crate::Behaviour::export_topology).
+///
+/// Unlike a [`PeerScoreReport`], these are the raw counters the score is computed from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeerScoreSnapshot {
+    /// The counters of each scored topic.
+    pub topics: HashMap<TopicHash, TopicScoreSnapshot>,
+    /// The application-specific score.
+    pub application_score: f64,
+    /// The behaviour penalty counter.
+    pub behaviour_penalty: f64,
+}
+
+/// The persistable counters of a peer in a single topic, see [`PeerScoreSnapshot`].
+///
+/// The time spent in the mesh is not included, as peers are grafted anew after a restart.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TopicScoreSnapshot {
+    /// The number of first message deliveries.
+    pub first_message_deliveries: f64,
+    /// The sticky mesh message delivery failure penalty.
+    pub mesh_failure_penalty: f64,
+    /// The number of invalid message deliveries.
+    pub invalid_message_deliveries: f64,
+}
+
 /// General statistics for a given gossipsub peer.
 struct PeerStats {
     /// Connection status of the peer.
@@ -219,9 +281,96 @@ impl PeerScore {
 
     /// Returns the score for a peer, logging metrics. This is called from the heartbeat and
     /// increments the metric counts for penalties.
-    pub(crate) fn metric_score(&self, peer_id: &PeerId, mut metrics: Option<&mut Metrics>) -> f64 {
+    pub(crate) fn metric_score(&self, peer_id: &PeerId, metrics: Option<&mut Metrics>) -> f64 {
+        self.compute_score(peer_id, metrics, false).score
+    }
+
+    /// Returns the score for a peer together with its components.
+    pub(crate) fn score_report(&self, peer_id: &PeerId) -> PeerScoreReport {
+        self.compute_score(peer_id, None, true)
+    }
+
+    /// Returns the score reports of all peers that are currently being scored.
+    pub(crate) fn score_reports(&self) -> impl Iterator<Item = (&PeerId, PeerScoreReport)> {
+        self.peer_stats
+            .keys()
+            .map(|peer_id| (peer_id, self.score_report(peer_id)))
+    }
+
+    /// Returns the raw score counters of all peers that are currently being scored.
+    pub(crate) fn snapshots(&self) -> impl Iterator<Item = (&PeerId, PeerScoreSnapshot)> {
+        self.peer_stats.iter().map(|(peer_id, peer_stats)| {
+            let topics = peer_stats
+                .topics
+                .iter()
+                .map(|(topic, topic_stats)| {
+                    (
+                        topic.clone(),
+                        TopicScoreSnapshot {
+                            first_message_deliveries: topic_stats.first_message_deliveries,
+                            mesh_failure_penalty: topic_stats.mesh_failure_penalty,
+                            invalid_message_deliveries: topic_stats.invalid_message_deliveries,
+                        },
+                    )
+                })
+                .collect();
+            let snapshot = PeerScoreSnapshot {
+                topics,
+                application_score: peer_stats.application_score,
+                behaviour_penalty: peer_stats.behaviour_penalty,
+            };
+            (peer_id, snapshot)
+        })
+    }
+
+    /// Restores the score counters of a peer that is not being scored yet, e.g. as exported
+    /// before a restart. The peer is treated as disconnected until it connects, i.e. its counters
+    /// are dropped after [`PeerScoreParams::retain_score`] if it doesn't reconnect.
+    ///
+    /// Returns `false` if the peer is already being scored.
+    pub(crate) fn restore(&mut self, peer_id: PeerId, snapshot: &PeerScoreSnapshot) -> bool {
+        let hash_map::Entry::Vacant(entry) = self.peer_stats.entry(peer_id) else {
+            return false;
+        };
+        let topics = snapshot
+            .topics
+            .iter()
+            .filter(|(topic, _)| self.params.topics.contains_key(*topic))
+            .map(|(topic, topic_snapshot)| {
+                (
+                    topic.clone(),
+                    TopicStats {
+                        first_message_deliveries: topic_snapshot.first_message_deliveries,
+                        mesh_failure_penalty: topic_snapshot.mesh_failure_penalty,
+                        invalid_message_deliveries: topic_snapshot.invalid_message_deliveries,
+                        ..TopicStats::default()
+                    },
+                )
+            })
+            .collect();
+        entry.insert(PeerStats {
+            status: ConnectionStatus::Disconnected {
+                expire: Instant::now() + self.params.retain_score,
+            },
+            topics,
+            known_ips: HashSet::new(),
+            behaviour_penalty: snapshot.behaviour_penalty,
+            application_score: snapshot.application_score,
+        });
+        true
+    }
+
+    /// Computes the score for a peer. The per-topic components are only included in the returned
+    /// report if `with_topics` is set.
+    fn compute_score(
+        &self,
+        peer_id: &PeerId,
+        mut metrics: Option<&mut Metrics>,
+        with_topics: bool,
+    ) -> PeerScoreReport {
+        let mut report = PeerScoreReport::default();
         let Some(peer_stats) = self.peer_stats.get(peer_id) else {
-            return 0.0;
+            return report;
         };
         let mut score = 0.0;
 
@@ -232,7 +381,7 @@ impl PeerScore {
                 // we are tracking the topic
 
                 // the topic score
-                let mut topic_score = 0.0;
+                let mut topic_report = TopicScoreReport::default();
 
                 // P1: time in mesh
                 if let MeshStatus::Active { mesh_time, .. } = topic_stats.mesh_status {
@@ -245,7 +394,7 @@ impl PeerScore {
                             topic_params.time_in_mesh_cap
                         }
                     };
-                    topic_score += p1 * topic_params.time_in_mesh_weight;
+                    topic_report.time_in_mesh = p1 * topic_params.time_in_mesh_weight;
                 }
 
                 // P2: first message deliveries
@@ -257,7 +406,8 @@ impl PeerScore {
                         topic_params.first_message_deliveries_cap
                     }
                 };
-                topic_score += p2 * topic_params.first_message_deliveries_weight;
+                topic_report.first_message_deliveries =
+                    p2 * topic_params.first_message_deliveries_weight;
 
                 // P3: mesh message deliveries
                 if topic_stats.mesh_message_deliveries_active
@@ -267,7 +417,8 @@ impl PeerScore {
                     let deficit = topic_params.mesh_message_deliveries_threshold
                         - topic_stats.mesh_message_deliveries;
                     let p3 = deficit * deficit;
-                    topic_score += p3 * topic_params.mesh_message_deliveries_weight;
+                    topic_report.mesh_message_deliveries =
+                        p3 * topic_params.mesh_message_deliveries_weight;
                     if let Some(metrics) = metrics.as_mut() {
                         metrics.register_score_penalty(Penalty::MessageDeficit);
                     }
@@ -275,7 +426,7 @@ impl PeerScore {
                         peer=%peer_id,
                         %topic,
                         %deficit,
-                        penalty=%topic_score,
+                        penalty=%topic_report.mesh_message_deliveries,
                         "[Penalty] The peer has a mesh deliveries deficit and will be penalized"
                     );
                 }
@@ -283,16 +434,27 @@ impl PeerScore {
                 // P3b:
                 // NOTE: the weight of P3b is negative (validated in TopicScoreParams.validate), so this detracts.
                 let p3b = topic_stats.mesh_failure_penalty;
-                topic_score += p3b * topic_params.mesh_failure_penalty_weight;
+                topic_report.mesh_failure_penalty = p3b * topic_params.mesh_failure_penalty_weight;
 
                 // P4: invalid messages
                 // NOTE: the weight of P4 is negative (validated in TopicScoreParams.validate), so this detracts.
                 let p4 =
                     topic_stats.invalid_message_deliveries * topic_stats.invalid_message_deliveries;
-                topic_score += p4 * topic_params.invalid_message_deliveries_weight;
+                topic_report.invalid_message_deliveries =
+                    p4 * topic_params.invalid_message_deliveries_weight;
 
                 // update score, mixing with topic weight
-                score += topic_score * topic_params.topic_weight;
+                topic_report.score = (topic_report.time_in_mesh
+                    + topic_report.first_message_deliveries
+                    + topic_report.mesh_message_deliveries
+                    + topic_report.mesh_failure_penalty
+                    + topic_report.invalid_message_deliveries)
+                    * topic_params.topic_weight;
+                score += topic_report.score;
+
+                if with_topics {
+                    report.topics.insert(topic.clone(), topic_report);
+                }
             }
         }
 
@@ -303,7 +465,8 @@ impl PeerScore {
 
         // P5: application-specific score
         let p5 = peer_stats.application_score;
-        score += p5 * self.params.app_specific_weight;
+        report.application_score = p5 * self.params.app_specific_weight;
+        score += report.application_score;
 
         // P6: IP collocation factor
         for ip in peer_stats.known_ips.iter() {
@@ -328,18 +491,22 @@ impl PeerScore {
                         surplus=%surplus,
                         "[Penalty] The peer gets penalized because of too many peers with the same ip"
                     );
-                    score += p6 * self.params.ip_colocation_factor_weight;
+                    report.ip_colocation_penalty += p6 * self.params.ip_colocation_factor_weight;
                 }
             }
         }
+        score += report.ip_colocation_penalty;
 
         // P7: behavioural pattern penalty
         if peer_stats.behaviour_penalty > self.params.behaviour_penalty_threshold {
             let excess = peer_stats.behaviour_penalty - self.params.behaviour_penalty_threshold;
             let p7 = excess * excess;
-            score += p7 * self.params.behaviour_penalty_weight;
+            report.behaviour_penalty = p7 * self.params.behaviour_penalty_weight;
+            score += report.behaviour_penalty;
         }
-        score
+
+        report.score = score;
+        report
     }
 
     pub(crate) fn add_penalty(&mut self, peer_id: &PeerId, count: usize) {
@@ -895,8 +1062,8 @@ impl PeerScore {
 }
 
 /// The reason a Gossipsub message has been rejected.
-#[derive(Clone, Copy)]
-pub(crate) enum RejectReason {
+#[derive(Debug, Clone, Copy)]
+pub enum RejectReason {
     /// The message failed the configured validation during decoding.
     ValidationError(ValidationError),
     /// The message source is us.
@@ -976,3 +976,65 @@ fn test_score_retention() {
         "Score should be the application specific score"
     );
 }
+
+#[test]
+fn test_score_report() {
+    let topic = Topic::new("test");
+    let topic_hash = topic.hash();
+    let mut params = PeerScoreParams {
+        app_specific_weight: 1.0,
+        behaviour_penalty_weight: -1.0,
+        behaviour_penalty_threshold: 0.0,
+        ..Default::default()
+    };
+
+    let topic_params = TopicScoreParams {
+        topic_weight: 0.5,
+        time_in_mesh_weight: 0.0,
+        first_message_deliveries_weight: 1.0,
+        first_message_deliveries_decay: 1.0,
+        first_message_deliveries_cap: 2000.0,
+        mesh_message_deliveries_weight: 0.0,
+        invalid_message_deliveries_weight: -1.0,
+        invalid_message_deliveries_decay: 1.0,
+        ..Default::default()
+    };
+
+    params.topics.insert(topic_hash.clone(), topic_params);
+
+    let peer_id = PeerId::random();
+
+    let mut peer_score = PeerScore::new(params);
+    peer_score.add_peer(peer_id);
+    peer_score.graft(&peer_id, topic);
+
+    for seq in 0..10 {
+        let (id, msg) = make_test_message(seq);
+        peer_score.validate_message(&peer_id, &id, &msg.topic);
+        peer_score.deliver_message(&peer_id, &id, &msg.topic);
+    }
+    for _ in 0..2 {
+        peer_score.reject_invalid_message(&peer_id, &topic_hash);
+    }
+    peer_score.set_application_score(&peer_id, 2.0);
+    peer_score.add_penalty(&peer_id, 2);
+
+    let report = peer_score.score_report(&peer_id);
+    assert_eq!(
+        report.topics[&topic_hash],
+        TopicScoreReport {
+            score: 3.0,
+            first_message_deliveries: 10.0,
+            invalid_message_deliveries: -4.0,
+            ..Default::default()
+        }
+    );
+    assert_eq!(report.application_score, 2.0);
+    assert_eq!(report.behaviour_penalty, -4.0);
+    assert_eq!(report.ip_colocation_penalty, 0.0);
+    assert_eq!(report.score, 1.0);
+    assert_eq!(report.score, peer_score.score(&peer_id));
+
+    let reports = peer_score.score_reports().collect::<Vec<_>>();
+    assert_eq!(reports, vec![(&peer_id, report)]);
+}
@@ -34,7 +34,9 @@ use libp2p_core::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
 use libp2p_identity::{PeerId, PublicKey};
 use libp2p_swarm::StreamProtocol;
 use quick_protobuf::Writer;
+use std::collections::HashMap;
 use std::pin::Pin;
+use std::sync::Arc;
 use void::Void;
 
 pub(crate) const SIGNING_PREFIX: &[u8] = b"libp2p-pubsub:";
@@ -61,6 +63,8 @@ pub struct ProtocolConfig {
     pub(crate) max_transmit_size: usize,
     /// Determines the level of validation to be done on incoming messages.
     pub(crate) validation_mode: ValidationMode,
+    /// Overrides of the validation mode for specific topics.
+    pub(crate) topic_validation_modes: Arc<HashMap<TopicHash, ValidationMode>>,
 }
 
 impl Default for ProtocolConfig {
@@ -68,6 +72,7 @@ impl Default for ProtocolConfig {
         Self {
             max_transmit_size: 65536,
             validation_mode: ValidationMode::Strict,
+            topic_validation_modes: Default::default(),
             protocol_ids: vec![GOSSIPSUB_1_1_0_PROTOCOL, GOSSIPSUB_1_0_0_PROTOCOL],
         }
     }
@@ -109,7 +114,8 @@ where
         Box::pin(future::ok((
             Framed::new(
                 socket,
-                GossipsubCodec::new(self.max_transmit_size, self.validation_mode),
+                GossipsubCodec::new(self.max_transmit_size, self.validation_mode)
+                    .with_topic_validation_modes(self.topic_validation_modes),
             ),
             protocol_id.kind,
         )))
@@ -128,7 +134,8 @@ where
         Box::pin(future::ok((
             Framed::new(
                 socket,
-                GossipsubCodec::new(self.max_transmit_size, self.validation_mode),
+                GossipsubCodec::new(self.max_transmit_size, self.validation_mode)
+                    .with_topic_validation_modes(self.topic_validation_modes),
             ),
             protocol_id.kind,
         )))
@@ -140,6 +147,8 @@ where
 pub struct GossipsubCodec {
     /// Determines the level of validation performed on incoming messages.
     validation_mode: ValidationMode,
+    /// Overrides of the validation mode for specific topics.
+    topic_validation_modes: Arc<HashMap<TopicHash, ValidationMode>>,
     /// The codec to handle common encoding/decoding of protobuf messages
     codec: quick_protobuf_codec::Codec<proto::RPC>,
 }
@@ -149,10 +158,20 @@ impl GossipsubCodec {
         let codec = quick_protobuf_codec::Codec::new(max_length);
         GossipsubCodec {
             validation_mode,
+            topic_validation_modes: Default::default(),
             codec,
         }
     }
 
+    /// Validates messages of the given topics with a different [`ValidationMode`].
+    pub(crate) fn with_topic_validation_modes(
+        mut self,
+        topic_validation_modes: Arc<HashMap<TopicHash, ValidationMode>>,
+    ) -> Self {
+        self.topic_validation_modes = topic_validation_modes;
+        self
+    }
+
     /// Verifies a gossipsub message. This returns either a success or failure. All errors
     /// are logged, which prevents error handling in the codec and handler. We simply drop invalid
     /// messages and log warnings, rather than propagating errors through the codec.
@@ -239,7 +258,11 @@ impl Decoder for GossipsubCodec {
             let mut verify_sequence_no = false;
             let mut verify_source = false;
 
-            match self.validation_mode {
+            let validation_mode = self
+                .topic_validation_modes
+                .get(&TopicHash::from_raw(message.topic.clone()))
+                .unwrap_or(&self.validation_mode);
+            match validation_mode {
                 ValidationMode::Strict => {
                     // Validate everything
                     verify_signature = true;
@@ -445,19 +468,22 @@ impl Decoder for GossipsubCodec {
             let mut prune_msgs = Vec::new();
 
             for prune in rpc_control.prune {
-                // filter out invalid peers
+                // filter out invalid peers, a signed peer record also identifies the peer
                 let peers = prune
                     .peers
                     .into_iter()
                     .filter_map(|info| {
-                        info.peer_id
+                        let peer_id = info
+                            .peer_id
                             .as_ref()
-                            .and_then(|id| PeerId::from_bytes(id).ok())
-                            .map(|peer_id|
-                                    //TODO signedPeerRecord, see https://github.com/libp2p/specs/pull/217
-                                    PeerInfo {
-                                        peer_id: Some(peer_id),
-                                    })
+                            .and_then(|id| PeerId::from_bytes(id).ok());
+                        if peer_id.is_none() && info.signed_peer_record.is_none() {
+                            return None;
+                        }
+                        Some(PeerInfo {
+                            peer_id,
+                            signed_peer_record: info.signed_peer_record,
+                        })
                     })
                     .collect::<Vec<PeerInfo>>();
 
@@ -585,6 +611,48 @@ mod tests {
         QuickCheck::new().quickcheck(prop as fn(_) -> _)
     }
 
+    #[test]
+    fn topic_validation_mode_overrides_global_mode() {
+        let telemetry = TopicHash::from_raw("telemetry");
+        let consensus = TopicHash::from_raw("consensus");
+        let anonymous_message = |topic: &TopicHash| RawMessage {
+            source: None,
+            data: vec![1, 2, 3],
+            sequence_number: None,
+            topic: topic.clone(),
+            signature: None,
+            key: None,
+            validated: false,
+        };
+        let rpc = Rpc {
+            messages: vec![anonymous_message(&telemetry), anonymous_message(&consensus)],
+            subscriptions: vec![],
+            control_msgs: vec![],
+        };
+
+        let topic_validation_modes = Arc::new(HashMap::from([(
+            telemetry.clone(),
+            ValidationMode::Anonymous,
+        )]));
+        let mut codec = GossipsubCodec::new(u32::MAX as usize, ValidationMode::Strict)
+            .with_topic_validation_modes(topic_validation_modes);
+        let mut buf = BytesMut::new();
+        codec.encode(rpc.into_protobuf(), &mut buf).unwrap();
+
+        match codec.decode(&mut buf).unwrap().unwrap() {
+            HandlerEvent::Message {
+                rpc,
+                invalid_messages,
+            } => {
+                assert_eq!(rpc.messages.len(), 1);
+                assert_eq!(rpc.messages[0].topic, telemetry);
+                assert_eq!(invalid_messages.len(), 1);
+                assert_eq!(invalid_messages[0].0.topic, consensus);
+            }
+            _ => panic!("Must decode a message"),
+        }
+    }
+
     #[test]
     fn support_floodsub_with_custom_protocol() {
         let protocol_config = ConfigBuilder::default()
@@ -0,0 +1,281 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Bounded queue of RPCs waiting to be sent on a connection.
+
+use crate::rpc_proto::proto;
+use crate::types::{ControlAction, RpcOut};
+use quick_protobuf::MessageWrite;
+use std::collections::VecDeque;
+
+/// The priority class of an outbound RPC.
+///
+/// Classes are sent in declaration order, i.e. control messages are always sent before any
+/// message of a lower class that is waiting in the queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Priority {
+    /// Subscriptions and control messages other than `IHAVE`.
+    Control,
+    /// Messages published by the local node.
+    Publish,
+    /// Messages forwarded on behalf of other peers, including `IWANT` responses.
+    Forward,
+    /// `IHAVE` gossip.
+    Gossip,
+}
+
+impl Priority {
+    pub(crate) fn of(rpc: &RpcOut) -> Self {
+        match rpc {
+            RpcOut::Publish(_) => Priority::Publish,
+            RpcOut::Forward(_) => Priority::Forward,
+            RpcOut::Control(ControlAction::IHave { .. }) => Priority::Gossip,
+            RpcOut::Subscribe(_) | RpcOut::Unsubscribe(_) | RpcOut::Control(_) => Priority::Control,
+        }
+    }
+}
+
+/// The number of outbound messages that were dropped because the send queue of a peer was full,
+/// by priority class. See [`Event::MessagesDropped`](crate::Event::MessagesDropped).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DroppedMessages {
+    /// Number of dropped messages published by the local node.
+    pub publish: usize,
+    /// Number of dropped messages forwarded on behalf of other peers.
+    pub forward: usize,
+    /// Number of dropped `IHAVE` gossip messages.
+    pub gossip: usize,
+}
+
+impl DroppedMessages {
+    /// The total number of dropped messages.
+    pub fn total(&self) -> usize {
+        self.publish + self.forward + self.gossip
+    }
+
+    pub(crate) fn merge(&mut self, other: DroppedMessages) {
+        self.publish += other.publish;
+        self.forward += other.forward;
+        self.gossip += other.gossip;
+    }
+}
+
+/// Per-connection queue of RPCs, split into one FIFO queue per [`Priority`].
+///
+/// Every class but [`Priority::Control`] holds at most `capacity` RPCs; further RPCs of a full
+/// class are dropped and accounted for in [`DroppedMessages`]. Control messages are small and
+/// required to keep the mesh state consistent between peers, hence they are never dropped.
+pub(crate) struct SendQueue {
+    control: VecDeque<proto::RPC>,
+    publish: VecDeque<proto::RPC>,
+    forward: VecDeque<proto::RPC>,
+    gossip: VecDeque<proto::RPC>,
+    capacity: usize,
+    dropped: DroppedMessages,
+}
+
+impl SendQueue {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            control: VecDeque::new(),
+            publish: VecDeque::new(),
+            forward: VecDeque::new(),
+            gossip: VecDeque::new(),
+            capacity,
+            dropped: DroppedMessages::default(),
+        }
+    }
+
+    /// Queues the given RPC, returning `false` if it was dropped because its class is full.
+    pub(crate) fn push(&mut self, rpc: RpcOut) -> bool {
+        let priority = Priority::of(&rpc);
+        let (queue, dropped) = match priority {
+            Priority::Control => {
+                self.control.push_back(rpc.into_protobuf());
+                return true;
+            }
+            Priority::Publish => (&mut self.publish, &mut self.dropped.publish),
+            Priority::Forward => (&mut self.forward, &mut self.dropped.forward),
+            Priority::Gossip => (&mut self.gossip, &mut self.dropped.gossip),
+        };
+
+        if queue.len() >= self.capacity {
+            tracing::trace!(?priority, "Send queue full, dropping message");
+            *dropped += 1;
+            return false;
+        }
+
+        queue.push_back(rpc.into_protobuf());
+        true
+    }
+
+    /// Removes the oldest RPC of the highest non-empty priority class.
+    pub(crate) fn pop(&mut self) -> Option<proto::RPC> {
+        self.control
+            .pop_front()
+            .or_else(|| self.publish.pop_front())
+            .or_else(|| self.forward.pop_front())
+            .or_else(|| self.gossip.pop_front())
+    }
+
+    /// Removes the next RPC like [`SendQueue::pop`]. If it carries messages, the messages of the
+    /// following published and forwarded RPCs are merged into it for as long as the encoded batch
+    /// does not exceed `max_size` bytes.
+    pub(crate) fn pop_batch(&mut self, max_size: usize) -> Option<proto::RPC> {
+        let mut batch = self.pop()?;
+        if batch.publish.is_empty() || !batch.subscriptions.is_empty() || batch.control.is_some() {
+            return Some(batch);
+        }
+
+        let mut size = batch.get_size();
+        loop {
+            let queue = if self.publish.is_empty() {
+                &mut self.forward
+            } else {
+                &mut self.publish
+            };
+            let Some(next_size) = queue.front().map(|rpc| rpc.get_size()) else {
+                break;
+            };
+            if size + next_size > max_size {
+                break;
+            }
+            let next = queue.pop_front().expect("queue to be non-empty");
+            batch.publish.extend(next.publish);
+            size += next_size;
+        }
+        Some(batch)
+    }
+
+    /// Whether control messages are waiting to be sent.
+    pub(crate) fn has_control(&self) -> bool {
+        !self.control.is_empty()
+    }
+
+    /// The total number of queued RPCs.
+    pub(crate) fn len(&self) -> usize {
+        self.control.len() + self.publish.len() + self.forward.len() + self.gossip.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the messages dropped since the last call, if any.
+    pub(crate) fn take_dropped(&mut self) -> Option<DroppedMessages> {
+        if self.dropped.total() == 0 {
+            return None;
+        }
+
+        Some(std::mem::take(&mut self.dropped))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TopicHash;
+
+    fn message(data: u8) -> crate::RawMessage {
+        crate::RawMessage {
+            source: None,
+            data: vec![data],
+            sequence_number: None,
+            topic: TopicHash::from_raw("topic"),
+            signature: None,
+            key: None,
+            validated: true,
+        }
+    }
+
+    #[test]
+    fn pops_by_priority_then_fifo() {
+        let mut queue = SendQueue::new(10);
+        queue.push(RpcOut::Control(ControlAction::IHave {
+            topic_hash: TopicHash::from_raw("topic"),
+            message_ids: Vec::new(),
+        }));
+        queue.push(RpcOut::Forward(message(1)));
+        queue.push(RpcOut::Forward(message(2)));
+        queue.push(RpcOut::Publish(message(3)));
+        queue.push(RpcOut::Subscribe(TopicHash::from_raw("topic")));
+
+        let mut popped = Vec::new();
+        while let Some(rpc) = queue.pop() {
+            popped.push(rpc);
+        }
+
+        assert_eq!(popped.len(), 5);
+        assert_eq!(popped[0].subscriptions.len(), 1);
+        assert_eq!(popped[1].publish[0].data, Some(vec![3]));
+        assert_eq!(popped[2].publish[0].data, Some(vec![1]));
+        assert_eq!(popped[3].publish[0].data, Some(vec![2]));
+        assert!(popped[4].control.is_some());
+    }
+
+    #[test]
+    fn drops_messages_of_full_classes_only() {
+        let mut queue = SendQueue::new(1);
+        assert!(queue.push(RpcOut::Forward(message(1))));
+        assert!(!queue.push(RpcOut::Forward(message(2))));
+        assert!(queue.push(RpcOut::Publish(message(3))));
+        assert!(queue.push(RpcOut::Subscribe(TopicHash::from_raw("a"))));
+        assert!(queue.push(RpcOut::Subscribe(TopicHash::from_raw("b"))));
+
+        assert_eq!(queue.len(), 4);
+        assert_eq!(
+            queue.take_dropped(),
+            Some(DroppedMessages {
+                forward: 1,
+                ..Default::default()
+            })
+        );
+        assert_eq!(queue.take_dropped(), None);
+    }
+
+    #[test]
+    fn batches_messages_up_to_max_size() {
+        let mut queue = SendQueue::new(10);
+        queue.push(RpcOut::Subscribe(TopicHash::from_raw("topic")));
+        queue.push(RpcOut::Forward(message(1)));
+        queue.push(RpcOut::Forward(message(2)));
+        queue.push(RpcOut::Publish(message(3)));
+        queue.push(RpcOut::Forward(message(4)));
+
+        let message_size = RpcOut::Forward(message(0)).into_protobuf().get_size();
+
+        // control messages are never batched
+        let control = queue.pop_batch(usize::MAX).unwrap();
+        assert_eq!(control.subscriptions.len(), 1);
+        assert!(control.publish.is_empty());
+
+        let batch = queue.pop_batch(3 * message_size).unwrap();
+        let data = batch
+            .publish
+            .iter()
+            .map(|m| m.data.clone().unwrap()[0])
+            .collect::<Vec<_>>();
+        assert_eq!(data, vec![3, 1, 2]);
+
+        let batch = queue.pop_batch(3 * message_size).unwrap();
+        assert_eq!(batch.publish.len(), 1);
+        assert!(queue.is_empty());
+    }
+}
@@ -19,8 +19,12 @@
 // DEALINGS IN THE SOFTWARE.
 
 //! This implements a time-based LRU cache for checking gossipsub message duplicates.
+//!
+//! [`DuplicateCache`] is built on the shared [`Cache`], such that its memory can be bounded
+//! together with other caches of the node.
 
 use fnv::FnvHashMap;
+use libp2p_swarm::cache::{Cache, CacheStats, MemoryBudget};
 use std::collections::hash_map::{
     self,
     Entry::{Occupied, Vacant},
@@ -149,20 +153,28 @@ where
         self.map.clear();
         self.list.clear();
     }
-
-    pub(crate) fn contains_key(&self, key: &Key) -> bool {
-        self.map.contains_key(key)
-    }
 }
 
-pub(crate) struct DuplicateCache<Key>(TimeCache<Key, ()>);
+pub(crate) struct DuplicateCache<Key>(Cache<Key, ()>);
 
 impl<Key> DuplicateCache<Key>
 where
     Key: Eq + std::hash::Hash + Clone,
 {
     pub(crate) fn new(ttl: Duration) -> Self {
-        Self(TimeCache::new(ttl))
+        Self::with_budget(ttl, MemoryBudget::unlimited(), |_, _| {
+            std::mem::size_of::<Key>()
+        })
+    }
+
+    /// Creates a cache whose keys are accounted against `budget`. Once the budget is exceeded,
+    /// the least recently inserted keys are forgotten before they expire.
+    pub(crate) fn with_budget(
+        ttl: Duration,
+        budget: MemoryBudget,
+        weigh: fn(&Key, &()) -> usize,
+    ) -> Self {
+        Self(Cache::new(budget, weigh).with_ttl(ttl))
     }
 
     // Inserts new elements and removes any expired elements.
@@ -170,16 +182,43 @@ where
     // If the key was not present this returns `true`. If the value was already present this
     // returns `false`.
     pub(crate) fn insert(&mut self, key: Key) -> bool {
-        if let Entry::Vacant(entry) = self.0.entry(key) {
-            entry.insert(());
-            true
-        } else {
-            false
+        if self.0.contains(&key) {
+            return false;
+        }
+        self.0.insert(key, ());
+        true
+    }
+
+    // Inserts an element that expires at the given time, unless it is already present with a
+    // later expiration. Returns `false` if the key was already present or the expiration has
+    // passed.
+    pub(crate) fn insert_until(&mut self, key: Key, expires: Instant) -> bool {
+        if expires <= Instant::now() {
+            return false;
+        }
+        match self.0.expiration(&key) {
+            Some(current) if current >= expires => false,
+            Some(_) => {
+                self.0.insert_until(key, (), expires);
+                false
+            }
+            None => {
+                self.0.insert_until(key, (), expires);
+                true
+            }
         }
     }
 
     pub(crate) fn contains(&self, key: &Key) -> bool {
-        self.0.contains_key(key)
+        self.0.contains(key)
+    }
+
+    pub(crate) fn expirations(&self) -> impl Iterator<Item = (&Key, Instant)> {
+        self.0.expirations()
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        self.0.stats()
     }
 }
 
@@ -216,4 +255,24 @@ mod test {
         // should be removed from the cache
         assert!(cache.insert("t"));
     }
+
+    #[test]
+    fn cache_entries_with_expiration() {
+        let mut cache = DuplicateCache::new(Duration::from_secs(10));
+        let now = Instant::now();
+
+        assert!(cache.insert_until("t", now + Duration::from_millis(100)));
+        assert!(!cache.insert_until("e", now));
+        assert!(!cache.insert_until("t", now + Duration::from_millis(50)));
+        assert_eq!(
+            cache.expirations().collect::<Vec<_>>(),
+            vec![(&"t", now + Duration::from_millis(100))]
+        );
+
+        // sleep until the restored entry expired, but not the default ttl
+        cache.insert("s");
+        std::thread::sleep(Duration::from_millis(101));
+        assert!(cache.insert("t"));
+        assert!(!cache.insert("s"));
+    }
 }
@@ -0,0 +1,83 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Export and import of the mesh topology and peer scores, allowing a restarted node to rejoin its
+//! meshes quickly.
+
+use crate::peer_score::PeerScoreSnapshot;
+use crate::TopicHash;
+use libp2p_identity::PeerId;
+use std::collections::{BTreeSet, HashMap};
+use web_time::{Instant, SystemTime};
+
+/// A snapshot of the mesh, fanout and peer scores of a [`Behaviour`](crate::Behaviour), see
+/// [`Behaviour::export_topology`](crate::Behaviour::export_topology).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopologySnapshot {
+    /// When the snapshot was taken.
+    pub taken_at: SystemTime,
+    /// The mesh peers of each subscribed topic.
+    pub mesh: HashMap<TopicHash, BTreeSet<PeerId>>,
+    /// The fanout peers of each topic published to without being subscribed.
+    pub fanout: HashMap<TopicHash, BTreeSet<PeerId>>,
+    /// The score counters of each scored peer, empty if peer scoring is disabled.
+    pub peer_scores: HashMap<PeerId, PeerScoreSnapshot>,
+}
+
+/// The mesh and fanout peers of an imported [`TopologySnapshot`], preferred when they
+/// resubscribe until the snapshot becomes stale.
+#[derive(Debug)]
+pub(crate) struct RestoredTopology {
+    expires: Instant,
+    mesh: HashMap<TopicHash, BTreeSet<PeerId>>,
+    fanout: HashMap<TopicHash, BTreeSet<PeerId>>,
+}
+
+impl RestoredTopology {
+    pub(crate) fn new(
+        expires: Instant,
+        mesh: HashMap<TopicHash, BTreeSet<PeerId>>,
+        fanout: HashMap<TopicHash, BTreeSet<PeerId>>,
+    ) -> Self {
+        Self {
+            expires,
+            mesh,
+            fanout,
+        }
+    }
+
+    pub(crate) fn is_expired(&self, now: Instant) -> bool {
+        now >= self.expires
+    }
+
+    /// Whether `peer` was in the mesh of `topic`.
+    pub(crate) fn is_mesh_peer(&self, topic: &TopicHash, peer: &PeerId) -> bool {
+        self.mesh
+            .get(topic)
+            .is_some_and(|peers| peers.contains(peer))
+    }
+
+    /// Whether `peer` was in the fanout of `topic`.
+    pub(crate) fn is_fanout_peer(&self, topic: &TopicHash, peer: &PeerId) -> bool {
+        self.fanout
+            .get(topic)
+            .is_some_and(|peers| peers.contains(peer))
+    }
+}
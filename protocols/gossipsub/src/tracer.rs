@@ -0,0 +1,94 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Tracing hooks for the internal events of the behaviour.
+//!
+//! A [`Tracer`] installed through [`Behaviour::set_tracer`](crate::Behaviour::set_tracer) is
+//! called synchronously with a [`TraceEvent`] whenever a message is published, delivered,
+//! rejected or received again, when the mesh changes and when control messages are exchanged.
+//! This allows to implement the libp2p pubsub tracing format or to feed other telemetry
+//! pipelines.
+
+use crate::{ControlAction, MessageId, RejectReason, TopicHash};
+use libp2p_identity::PeerId;
+
+/// An event traced by the behaviour, see [`Tracer`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum TraceEvent {
+    /// A message has been published by the local node.
+    PublishMessage {
+        message_id: MessageId,
+        topic: TopicHash,
+    },
+    /// A received message has been delivered to the application.
+    DeliverMessage {
+        message_id: MessageId,
+        topic: TopicHash,
+        received_from: PeerId,
+    },
+    /// A message has been received again.
+    DuplicateMessage {
+        message_id: MessageId,
+        topic: TopicHash,
+        received_from: PeerId,
+    },
+    /// A received message has been rejected, either because it is invalid or because the
+    /// validation rejected or ignored it. The id is missing if it could not be computed.
+    RejectMessage {
+        message_id: Option<MessageId>,
+        topic: TopicHash,
+        received_from: PeerId,
+        reason: RejectReason,
+    },
+    /// A peer has been added to the mesh of a topic.
+    Graft { peer_id: PeerId, topic: TopicHash },
+    /// A peer has been removed from the mesh of a topic.
+    Prune { peer_id: PeerId, topic: TopicHash },
+    /// A control message has been sent to a peer.
+    ControlSent {
+        peer_id: PeerId,
+        control: ControlAction,
+    },
+    /// A control message has been received from a peer.
+    ControlReceived {
+        peer_id: PeerId,
+        control: ControlAction,
+    },
+}
+
+/// Receives the [`TraceEvent`]s of the behaviour.
+///
+/// The tracer is called from within the behaviour and should therefore not block.
+///
+/// The trait is implemented for closures with a matching signature.
+pub trait Tracer: Send + 'static {
+    /// Traces the given event.
+    fn trace(&mut self, event: TraceEvent);
+}
+
+impl<F> Tracer for F
+where
+    F: FnMut(TraceEvent) + Send + 'static,
+{
+    fn trace(&mut self, event: TraceEvent) {
+        self(event)
+    }
+}
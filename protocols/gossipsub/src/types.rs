@@ -178,6 +178,26 @@ impl fmt::Debug for Message {
     }
 }
 
+/// The context passed to the function computing a [`MessageId`], see
+/// [`ConfigBuilder::message_id_fn_with_context`](crate::ConfigBuilder::message_id_fn_with_context).
+#[derive(Debug, Clone, Copy)]
+pub struct MessageIdContext<'a> {
+    /// The message after the inbound data transform, which includes its topic, source and
+    /// sequence number.
+    pub message: &'a Message,
+    /// The peer that forwarded the message to us, or `None` if the message is published locally.
+    pub propagation_source: Option<&'a PeerId>,
+}
+
+impl<'a> From<&'a Message> for MessageIdContext<'a> {
+    fn from(message: &'a Message) -> Self {
+        Self {
+            message,
+            propagation_source: None,
+        }
+    }
+}
+
 /// A subscription received by the gossipsub system.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Subscription {
@@ -196,12 +216,14 @@ pub enum SubscriptionAction {
     Unsubscribe,
 }
 
+/// A peer proposed through peer exchange in a PRUNE control message.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PeerInfo {
+    /// The id of the peer.
     pub peer_id: Option<PeerId>,
-    //TODO add this when RFC: Signed Address Records got added to the spec (see pull request
-    // https://github.com/libp2p/specs/pull/217)
-    //pub signed_peer_record: ?,
+    /// The protobuf encoded [`SignedEnvelope`](libp2p_core::SignedEnvelope) of the peer's
+    /// [`PeerRecord`](libp2p_core::PeerRecord). Not verified yet.
+    pub signed_peer_record: Option<Vec<u8>>,
 }
 
 /// A Control message received by the gossipsub system.
@@ -235,6 +257,94 @@ pub enum ControlAction {
     },
 }
 
+impl ControlAction {
+    /// Splits the control message into control messages whose RPC encodes to at most `max_size`
+    /// bytes, by distributing the listed message ids or peers among them.
+    ///
+    /// Message ids which don't fit into an RPC of their own are dropped, as are the peers of a
+    /// PRUNE that only fits without them.
+    pub(crate) fn split(self, max_size: usize) -> Vec<ControlAction> {
+        if RpcOut::Control(self.clone()).into_protobuf().get_size() <= max_size {
+            return vec![self];
+        }
+
+        let halves = match self {
+            ControlAction::IHave {
+                topic_hash,
+                mut message_ids,
+            } if message_ids.len() > 1 => {
+                let second = message_ids.split_off(message_ids.len() / 2);
+                [
+                    ControlAction::IHave {
+                        topic_hash: topic_hash.clone(),
+                        message_ids,
+                    },
+                    ControlAction::IHave {
+                        topic_hash,
+                        message_ids: second,
+                    },
+                ]
+            }
+            ControlAction::IWant { mut message_ids } if message_ids.len() > 1 => {
+                let second = message_ids.split_off(message_ids.len() / 2);
+                [
+                    ControlAction::IWant { message_ids },
+                    ControlAction::IWant {
+                        message_ids: second,
+                    },
+                ]
+            }
+            ControlAction::Prune {
+                topic_hash,
+                mut peers,
+                backoff,
+            } if peers.len() > 1 => {
+                let second = peers.split_off(peers.len() / 2);
+                [
+                    ControlAction::Prune {
+                        topic_hash: topic_hash.clone(),
+                        peers,
+                        backoff,
+                    },
+                    ControlAction::Prune {
+                        topic_hash,
+                        peers: second,
+                        backoff,
+                    },
+                ]
+            }
+            ControlAction::Prune {
+                topic_hash,
+                peers,
+                backoff,
+            } if !peers.is_empty() => {
+                tracing::debug!(
+                    topic=%topic_hash,
+                    "Dropping peer exchange exceeding the max transmit size"
+                );
+                return ControlAction::Prune {
+                    topic_hash,
+                    peers: Vec::new(),
+                    backoff,
+                }
+                .split(max_size);
+            }
+            control => {
+                tracing::warn!(
+                    ?control,
+                    "Dropping control message exceeding the max transmit size"
+                );
+                return Vec::new();
+            }
+        };
+
+        halves
+            .into_iter()
+            .flat_map(|control| control.split(max_size))
+            .collect()
+    }
+}
+
 /// A Gossipsub RPC message sent.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum RpcOut {
@@ -332,29 +442,26 @@ impl From<RpcOut> for proto::RPC {
                 topic_hash,
                 peers,
                 backoff,
-            }) => {
-                proto::RPC {
-                    publish: Vec::new(),
-                    subscriptions: vec![],
-                    control: Some(proto::ControlMessage {
-                        ihave: vec![],
-                        iwant: vec![],
-                        graft: vec![],
-                        prune: vec![proto::ControlPrune {
-                            topic_id: Some(topic_hash.into_string()),
-                            peers: peers
-                                .into_iter()
-                                .map(|info| proto::PeerInfo {
-                                    peer_id: info.peer_id.map(|id| id.to_bytes()),
-                                    // TODO, see https://github.com/libp2p/specs/pull/217
-                                    signed_peer_record: None,
-                                })
-                                .collect(),
-                            backoff,
-                        }],
-                    }),
-                }
-            }
+            }) => proto::RPC {
+                publish: Vec::new(),
+                subscriptions: vec![],
+                control: Some(proto::ControlMessage {
+                    ihave: vec![],
+                    iwant: vec![],
+                    graft: vec![],
+                    prune: vec![proto::ControlPrune {
+                        topic_id: Some(topic_hash.into_string()),
+                        peers: peers
+                            .into_iter()
+                            .map(|info| proto::PeerInfo {
+                                peer_id: info.peer_id.map(|id| id.to_bytes()),
+                                signed_peer_record: info.signed_peer_record,
+                            })
+                            .collect(),
+                        backoff,
+                    }],
+                }),
+            },
         }
     }
 }
@@ -453,8 +560,7 @@ impl From<Rpc> for proto::RPC {
                             .into_iter()
                             .map(|info| proto::PeerInfo {
                                 peer_id: info.peer_id.map(|id| id.to_bytes()),
-                                // TODO, see https://github.com/libp2p/specs/pull/217
-                                signed_peer_record: None,
+                                signed_peer_record: info.signed_peer_record,
                             })
                             .collect(),
                         backoff,
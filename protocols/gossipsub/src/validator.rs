@@ -0,0 +1,68 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Asynchronous validation of received messages.
+//!
+//! Instead of reporting validation results manually through
+//! [`Behaviour::report_message_validation_result`](crate::Behaviour::report_message_validation_result),
+//! applications can install a [`MessageValidator`] that returns a future per received message.
+//! The behaviour polls these futures itself, bounded by [`Config::max_concurrent_validations`]
+//! and [`Config::validation_timeout`](crate::Config::validation_timeout).
+//!
+//! [`Config::max_concurrent_validations`]: crate::Config::max_concurrent_validations
+
+use crate::{Message, MessageAcceptance, MessageId};
+use futures::future::BoxFuture;
+use libp2p_identity::PeerId;
+
+/// Validates received messages asynchronously, e.g. by checking signatures or looking up
+/// application state.
+///
+/// Only messages on subscribed topics are validated. Accepted messages are forwarded to the mesh
+/// and emitted as [`Event::Message`](crate::Event::Message), rejected messages penalize the
+/// propagation source and ignored messages are dropped silently. Messages whose validation times
+/// out are ignored.
+///
+/// The trait is implemented for closures with a matching signature.
+pub trait MessageValidator: Send + 'static {
+    /// Starts validating the given message, received from `propagation_source`.
+    fn validate(
+        &mut self,
+        propagation_source: &PeerId,
+        message_id: &MessageId,
+        message: &Message,
+    ) -> BoxFuture<'static, MessageAcceptance>;
+}
+
+impl<F> MessageValidator for F
+where
+    F: FnMut(&PeerId, &MessageId, &Message) -> BoxFuture<'static, MessageAcceptance>
+        + Send
+        + 'static,
+{
+    fn validate(
+        &mut self,
+        propagation_source: &PeerId,
+        message_id: &MessageId,
+        message: &Message,
+    ) -> BoxFuture<'static, MessageAcceptance> {
+        self(propagation_source, message_id, message)
+    }
+}
@@ -19,17 +19,20 @@
 // DEALINGS IN THE SOFTWARE.
 
 use crate::handler::{self, Handler, InEvent};
-use crate::protocol::{Info, UpgradeError};
+use crate::protocol::{AddressProvenance, Info, UpgradeError};
 use libp2p_core::multiaddr::Protocol;
 use libp2p_core::transport::PortUse;
 use libp2p_core::{multiaddr, ConnectedPoint, Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_identity::PublicKey;
-use libp2p_swarm::behaviour::{ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm};
+use libp2p_swarm::behaviour::{
+    rank_by_source_and_recency, ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm,
+    NewExternalAddrCandidate,
+};
 use libp2p_swarm::{
-    ConnectionDenied, DialError, ExternalAddresses, ListenAddresses, NetworkBehaviour,
-    NotifyHandler, PeerAddresses, StreamUpgradeError, THandlerInEvent, ToSwarm,
-    _address_translation,
+    _address_translation, AddressRanking, ConnectionDenied, DialError, ExternalAddresses,
+    ListenAddresses, NetworkBehaviour, NotifyHandler, PeerAddresses, StreamUpgradeError,
+    THandlerInEvent, ToSwarm,
 };
 use libp2p_swarm::{ConnectionId, THandler, THandlerOutEvent};
 
@@ -42,6 +45,9 @@ use std::{
     time::Duration,
 };
 
+/// The maximum number of recent external address candidates shared with peers.
+const MAX_ADDRESS_CANDIDATES: usize = 8;
+
 /// Whether an [`Multiaddr`] is a valid for the QUIC transport.
 fn is_quic_addr(addr: &Multiaddr, v1: bool) -> bool {
     use Protocol::*;
@@ -109,6 +115,9 @@ pub struct Behaviour {
 
     listen_addresses: ListenAddresses,
     external_addresses: ExternalAddresses,
+    /// The most recent external address candidates, shared if
+    /// [`Config::address_provenance`] is enabled.
+    address_candidates: VecDeque<Multiaddr>,
 }
 
 /// Configuration for the [`identify::Behaviour`](Behaviour).
@@ -153,6 +162,19 @@ pub struct Config {
     ///
     /// Disabled by default.
     pub hide_listen_addrs: bool,
+
+    /// Whether to share the provenance of our addresses with peers, i.e. whether each address
+    /// is a listen address, a confirmed external address or an external address candidate.
+    ///
+    /// This is a rust-libp2p extension of the identify protocol. Other implementations ignore it.
+    ///
+    /// Disabled by default.
+    pub address_provenance: bool,
+
+    /// How to rank our external addresses, which are advertised before our listen addresses.
+    ///
+    /// Defaults to [`rank_by_source_and_recency`].
+    pub external_address_ranking: AddressRanking,
 }
 
 impl Config {
@@ -167,6 +189,8 @@ impl Config {
             push_listen_addr_updates: false,
             cache_size: 100,
             hide_listen_addrs: false,
+            address_provenance: false,
+            external_address_ranking: rank_by_source_and_recency,
         }
     }
 
@@ -202,6 +226,18 @@ impl Config {
         self.hide_listen_addrs = b;
         self
     }
+
+    /// Configures whether we share the provenance of our addresses with peers.
+    pub fn with_address_provenance(mut self, b: bool) -> Self {
+        self.address_provenance = b;
+        self
+    }
+
+    /// Configures how our external addresses are ranked.
+    pub fn with_external_address_ranking(mut self, ranking: AddressRanking) -> Self {
+        self.external_address_ranking = ranking;
+        self
+    }
 }
 
 impl Behaviour {
@@ -211,6 +247,8 @@ impl Behaviour {
             None => PeerCache::disabled(),
             Some(size) => PeerCache::enabled(size),
         };
+        let external_addresses =
+            ExternalAddresses::default().with_ranking(config.external_address_ranking);
 
         Self {
             config,
@@ -220,7 +258,8 @@ impl Behaviour {
             events: VecDeque::new(),
             discovered_peers,
             listen_addresses: Default::default(),
-            external_addresses: Default::default(),
+            external_addresses,
+            address_candidates: Default::default(),
         }
     }
 
@@ -270,14 +309,58 @@ impl Behaviour {
         }
     }
 
-    fn all_addresses(&self) -> HashSet<Multiaddr> {
-        let mut addrs = HashSet::from_iter(self.external_addresses.iter().cloned());
+    /// Our external addresses in order of their rank, followed by our listen addresses.
+    fn all_addresses(&self) -> Vec<Multiaddr> {
+        let mut addrs = Vec::from_iter(self.external_addresses.iter().cloned());
         if !self.config.hide_listen_addrs {
-            addrs.extend(self.listen_addresses.iter().cloned());
+            for addr in self.listen_addresses.iter() {
+                if !addrs.contains(addr) {
+                    addrs.push(addr.clone());
+                }
+            }
         };
         addrs
     }
 
+    fn address_provenance(&self) -> Vec<(Multiaddr, AddressProvenance)> {
+        if !self.config.address_provenance {
+            return Vec::new();
+        }
+
+        let mut addrs = Vec::new();
+        if !self.config.hide_listen_addrs {
+            addrs.extend(
+                self.listen_addresses
+                    .iter()
+                    .map(|a| (a.clone(), AddressProvenance::Listen)),
+            );
+        }
+        addrs.extend(
+            self.external_addresses
+                .iter()
+                .map(|a| (a.clone(), AddressProvenance::Confirmed)),
+        );
+        addrs.extend(
+            self.address_candidates
+                .iter()
+                .filter(|a| !self.external_addresses.as_slice().contains(a))
+                .map(|a| (a.clone(), AddressProvenance::Candidate)),
+        );
+        addrs
+    }
+
+    /// Records a new external address candidate, returning whether the shared candidates changed.
+    fn on_new_external_addr_candidate(&mut self, addr: &Multiaddr) -> bool {
+        if !self.config.address_provenance || self.address_candidates.contains(addr) {
+            return false;
+        }
+        if self.address_candidates.len() == MAX_ADDRESS_CANDIDATES {
+            self.address_candidates.pop_front();
+        }
+        self.address_candidates.push_back(addr.clone());
+        true
+    }
+
     fn emit_new_external_addr_candidate_event(
         &mut self,
         connection_id: ConnectionId,
@@ -350,6 +433,7 @@ impl NetworkBehaviour for Behaviour {
             self.config.agent_version.clone(),
             remote_addr.clone(),
             self.all_addresses(),
+            self.address_provenance(),
         ))
     }
 
@@ -382,6 +466,7 @@ impl NetworkBehaviour for Behaviour {
             self.config.agent_version.clone(),
             addr.clone(), // TODO: This is weird? That is the public address we dialed, shouldn't need to tell the other party?
             self.all_addresses(),
+            self.address_provenance(),
         ))
     }
 
@@ -396,6 +481,8 @@ impl NetworkBehaviour for Behaviour {
                 // Remove invalid multiaddrs.
                 info.listen_addrs
                     .retain(|addr| multiaddr_matches_peer_id(addr, &peer_id));
+                info.address_provenance
+                    .retain(|(addr, _)| multiaddr_matches_peer_id(addr, &peer_id));
 
                 let observed = info.observed_addr.clone();
                 self.events
@@ -486,8 +573,14 @@ impl NetworkBehaviour for Behaviour {
     fn on_swarm_event(&mut self, event: FromSwarm) {
         let listen_addr_changed = self.listen_addresses.on_swarm_event(&event);
         let external_addr_changed = self.external_addresses.on_swarm_event(&event);
+        let candidates_changed = match event {
+            FromSwarm::NewExternalAddrCandidate(NewExternalAddrCandidate { addr }) => {
+                self.on_new_external_addr_candidate(addr)
+            }
+            _ => false,
+        };
 
-        if listen_addr_changed || external_addr_changed {
+        if listen_addr_changed || external_addr_changed || candidates_changed {
             // notify all connected handlers about our changed addresses
             let change_events = self
                 .connected
@@ -496,7 +589,10 @@ impl NetworkBehaviour for Behaviour {
                 .map(|(peer_id, connection_id)| ToSwarm::NotifyHandler {
                     peer_id,
                     handler: NotifyHandler::One(*connection_id),
-                    event: InEvent::AddressesChanged(self.all_addresses()),
+                    event: InEvent::AddressesChanged {
+                        addresses: self.all_addresses(),
+                        provenance: self.address_provenance(),
+                    },
                 })
                 .collect::<Vec<_>>();
 
@@ -22,6 +22,7 @@ pub struct Identify {
     pub listenAddrs: Vec<Vec<u8>>,
     pub observedAddr: Option<Vec<u8>>,
     pub protocols: Vec<String>,
+    pub addressRecords: Vec<structs::mod_Identify::AddressRecord>,
 }
 
 impl<'a> MessageRead<'a> for Identify {
@@ -35,6 +36,7 @@ impl<'a> MessageRead<'a> for Identify {
                 Ok(18) => msg.listenAddrs.push(r.read_bytes(bytes)?.to_owned()),
                 Ok(34) => msg.observedAddr = Some(r.read_bytes(bytes)?.to_owned()),
                 Ok(26) => msg.protocols.push(r.read_string(bytes)?.to_owned()),
+                Ok(802) => msg.addressRecords.push(r.read_message::<structs::mod_Identify::AddressRecord>(bytes)?),
                 Ok(t) => { r.read_unknown(bytes, t)?; }
                 Err(e) => return Err(e),
             }
@@ -52,6 +54,7 @@ impl MessageWrite for Identify {
         + self.listenAddrs.iter().map(|s| 1 + sizeof_len((s).len())).sum::<usize>()
         + self.observedAddr.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
         + self.protocols.iter().map(|s| 1 + sizeof_len((s).len())).sum::<usize>()
+        + self.addressRecords.iter().map(|s| 2 + sizeof_len((s).get_size())).sum::<usize>()
     }
 
     fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
@@ -61,7 +64,90 @@ impl MessageWrite for Identify {
         for s in &self.listenAddrs { w.write_with_tag(18, |w| w.write_bytes(&**s))?; }
         if let Some(ref s) = self.observedAddr { w.write_with_tag(34, |w| w.write_bytes(&**s))?; }
         for s in &self.protocols { w.write_with_tag(26, |w| w.write_string(&**s))?; }
+        for s in &self.addressRecords { w.write_with_tag(802, |w| w.write_message(s))?; }
         Ok(())
     }
 }
 
+pub mod mod_Identify {
+
+use super::*;
+
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct AddressRecord {
+    pub addr: Option<Vec<u8>>,
+    pub provenance: Option<structs::mod_Identify::mod_AddressRecord::Provenance>,
+}
+
+impl<'a> MessageRead<'a> for AddressRecord {
+    fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> Result<Self> {
+        let mut msg = Self::default();
+        while !r.is_eof() {
+            match r.next_tag(bytes) {
+                Ok(10) => msg.addr = Some(r.read_bytes(bytes)?.to_owned()),
+                Ok(16) => msg.provenance = Some(r.read_enum(bytes)?),
+                Ok(t) => { r.read_unknown(bytes, t)?; }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(msg)
+    }
+}
+
+impl MessageWrite for AddressRecord {
+    fn get_size(&self) -> usize {
+        0
+        + self.addr.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
+        + self.provenance.as_ref().map_or(0, |m| 1 + sizeof_varint(*(m) as u64))
+    }
+
+    fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
+        if let Some(ref s) = self.addr { w.write_with_tag(10, |w| w.write_bytes(&**s))?; }
+        if let Some(ref s) = self.provenance { w.write_with_tag(16, |w| w.write_enum(*s as i32))?; }
+        Ok(())
+    }
+}
+
+pub mod mod_AddressRecord {
+
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Provenance {
+    LISTEN = 0,
+    CONFIRMED = 1,
+    CANDIDATE = 2,
+}
+
+impl Default for Provenance {
+    fn default() -> Self {
+        Provenance::LISTEN
+    }
+}
+
+impl From<i32> for Provenance {
+    fn from(i: i32) -> Self {
+        match i {
+            0 => Provenance::LISTEN,
+            1 => Provenance::CONFIRMED,
+            2 => Provenance::CANDIDATE,
+            _ => Self::default(),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Provenance {
+    fn from(s: &'a str) -> Self {
+        match s {
+            "LISTEN" => Provenance::LISTEN,
+            "CONFIRMED" => Provenance::CONFIRMED,
+            "CANDIDATE" => Provenance::CANDIDATE,
+            _ => Self::default(),
+        }
+    }
+}
+
+}
+
+}
+
@@ -18,7 +18,7 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::protocol::{Info, PushInfo, UpgradeError};
+use crate::protocol::{AddressProvenance, Info, PushInfo, UpgradeError};
 use crate::{protocol, PROTOCOL_NAME, PUSH_PROTOCOL_NAME};
 use either::Either;
 use futures::prelude::*;
@@ -90,13 +90,17 @@ pub struct Handler {
 
     local_supported_protocols: SupportedProtocols,
     remote_supported_protocols: HashSet<StreamProtocol>,
-    external_addresses: HashSet<Multiaddr>,
+    external_addresses: Vec<Multiaddr>,
+    address_provenance: Vec<(Multiaddr, AddressProvenance)>,
 }
 
 /// An event from `Behaviour` with the information requested by the `Handler`.
 #[derive(Debug)]
 pub enum InEvent {
-    AddressesChanged(HashSet<Multiaddr>),
+    AddressesChanged {
+        addresses: Vec<Multiaddr>,
+        provenance: Vec<(Multiaddr, AddressProvenance)>,
+    },
     Push,
 }
 
@@ -123,7 +127,8 @@ impl Handler {
         protocol_version: String,
         agent_version: String,
         observed_addr: Multiaddr,
-        external_addresses: HashSet<Multiaddr>,
+        external_addresses: Vec<Multiaddr>,
+        address_provenance: Vec<(Multiaddr, AddressProvenance)>,
     ) -> Self {
         Self {
             remote_peer_id,
@@ -143,6 +148,7 @@ impl Handler {
             remote_supported_protocols: HashSet::default(),
             remote_info: Default::default(),
             external_addresses,
+            address_provenance,
         }
     }
 
@@ -227,9 +233,10 @@ impl Handler {
             public_key: self.public_key.clone(),
             protocol_version: self.protocol_version.clone(),
             agent_version: self.agent_version.clone(),
-            listen_addrs: Vec::from_iter(self.external_addresses.iter().cloned()),
+            listen_addrs: self.external_addresses.clone(),
             protocols: Vec::from_iter(self.local_supported_protocols.iter().cloned()),
             observed_addr: self.observed_addr.clone(),
+            address_provenance: self.address_provenance.clone(),
         }
     }
 
@@ -299,8 +306,12 @@ impl ConnectionHandler for Handler {
 
     fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
         match event {
-            InEvent::AddressesChanged(addresses) => {
+            InEvent::AddressesChanged {
+                addresses,
+                provenance,
+            } => {
                 self.external_addresses = addresses;
+                self.address_provenance = provenance;
             }
             InEvent::Push => {
                 self.events
@@ -42,7 +42,9 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
 pub use self::behaviour::{Behaviour, Config, Event};
-pub use self::protocol::{Info, UpgradeError, PROTOCOL_NAME, PUSH_PROTOCOL_NAME};
+pub use self::protocol::{
+    AddressProvenance, Info, UpgradeError, PROTOCOL_NAME, PUSH_PROTOCOL_NAME,
+};
 
 mod behaviour;
 mod handler;
@@ -51,5 +53,7 @@ mod protocol;
 mod proto {
     #![allow(unreachable_pub)]
     include!("generated/mod.rs");
+    pub(crate) use self::structs::mod_Identify::mod_AddressRecord::Provenance;
+    pub(crate) use self::structs::mod_Identify::AddressRecord;
     pub(crate) use self::structs::Identify;
 }
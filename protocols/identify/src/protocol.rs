@@ -51,6 +51,12 @@ pub struct Info {
     pub protocols: Vec<StreamProtocol>,
     /// Address observed by or for the remote.
     pub observed_addr: Multiaddr,
+    /// The addresses of the peer together with how the peer learned about them.
+    ///
+    /// This is a rust-libp2p extension of the identify protocol and thus empty for peers that
+    /// don't support it, or that didn't enable
+    /// [`Config::with_address_provenance`](crate::Config::with_address_provenance).
+    pub address_provenance: Vec<(Multiaddr, AddressProvenance)>,
 }
 
 impl Info {
@@ -73,6 +79,41 @@ impl Info {
         if let Some(observed_addr) = info.observed_addr {
             self.observed_addr = observed_addr;
         }
+        if !info.address_provenance.is_empty() {
+            self.address_provenance = info.address_provenance;
+        }
+    }
+}
+
+/// How a peer learned about one of its addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressProvenance {
+    /// The peer is listening on the address.
+    Listen,
+    /// The address is a confirmed external address of the peer.
+    Confirmed,
+    /// The address is a candidate for an external address of the peer, e.g. as observed by
+    /// another peer, but has not been confirmed yet.
+    Candidate,
+}
+
+impl From<AddressProvenance> for proto::Provenance {
+    fn from(provenance: AddressProvenance) -> Self {
+        match provenance {
+            AddressProvenance::Listen => proto::Provenance::LISTEN,
+            AddressProvenance::Confirmed => proto::Provenance::CONFIRMED,
+            AddressProvenance::Candidate => proto::Provenance::CANDIDATE,
+        }
+    }
+}
+
+impl From<proto::Provenance> for AddressProvenance {
+    fn from(provenance: proto::Provenance) -> Self {
+        match provenance {
+            proto::Provenance::LISTEN => AddressProvenance::Listen,
+            proto::Provenance::CONFIRMED => AddressProvenance::Confirmed,
+            proto::Provenance::CANDIDATE => AddressProvenance::Candidate,
+        }
     }
 }
 
@@ -86,6 +127,7 @@ pub struct PushInfo {
     pub listen_addrs: Vec<Multiaddr>,
     pub protocols: Vec<StreamProtocol>,
     pub observed_addr: Option<Multiaddr>,
+    pub address_provenance: Vec<(Multiaddr, AddressProvenance)>,
 }
 
 pub(crate) async fn send_identify<T>(io: T, info: Info) -> Result<Info, UpgradeError>
@@ -105,6 +147,14 @@ where
         listenAddrs: listen_addrs,
         observedAddr: Some(info.observed_addr.to_vec()),
         protocols: info.protocols.iter().map(|p| p.to_string()).collect(),
+        addressRecords: info
+            .address_provenance
+            .iter()
+            .map(|(addr, provenance)| proto::AddressRecord {
+                addr: Some(addr.to_vec()),
+                provenance: Some((*provenance).into()),
+            })
+            .collect(),
     };
 
     let mut framed_io = FramedWrite::new(
@@ -173,6 +223,24 @@ fn parse_listen_addrs(listen_addrs: Vec<Vec<u8>>) -> Vec<Multiaddr> {
         .collect()
 }
 
+fn parse_address_records(
+    records: Vec<proto::AddressRecord>,
+) -> Vec<(Multiaddr, AddressProvenance)> {
+    records
+        .into_iter()
+        .filter_map(|record| {
+            let addr = match Multiaddr::try_from(record.addr?) {
+                Ok(a) => a,
+                Err(e) => {
+                    tracing::debug!("Unable to parse address record multiaddr: {e:?}");
+                    return None;
+                }
+            };
+            Some((addr, record.provenance.unwrap_or_default().into()))
+        })
+        .collect()
+}
+
 fn parse_protocols(protocols: Vec<String>) -> Vec<StreamProtocol> {
     protocols
         .into_iter()
@@ -225,6 +293,7 @@ impl TryFrom<proto::Identify> for Info {
             listen_addrs: parse_listen_addrs(msg.listenAddrs),
             protocols: parse_protocols(msg.protocols),
             observed_addr: parse_observed_addr(msg.observedAddr).unwrap_or(Multiaddr::empty()),
+            address_provenance: parse_address_records(msg.addressRecords),
         };
 
         Ok(info)
@@ -242,6 +311,7 @@ impl TryFrom<proto::Identify> for PushInfo {
             listen_addrs: parse_listen_addrs(msg.listenAddrs),
             protocols: parse_protocols(msg.protocols),
             observed_addr: parse_observed_addr(msg.observedAddr),
+            address_provenance: parse_address_records(msg.addressRecords),
         };
 
         Ok(info)
@@ -284,6 +354,7 @@ mod tests {
             observedAddr: None,
             protocolVersion: None,
             protocols: vec![],
+            addressRecords: vec![],
             publicKey: Some(
                 identity::Keypair::generate_ed25519()
                     .public()
@@ -295,4 +366,47 @@ mod tests {
 
         assert_eq!(info.listen_addrs, vec![valid_multiaddr])
     }
+
+    #[test]
+    fn parse_address_records_with_provenance() {
+        let confirmed: Multiaddr = "/ip4/1.2.3.4/tcp/1234".parse().unwrap();
+        let candidate: Multiaddr = "/ip4/5.6.7.8/tcp/1234".parse().unwrap();
+
+        let payload = proto::Identify {
+            agentVersion: None,
+            listenAddrs: vec![],
+            observedAddr: None,
+            protocolVersion: None,
+            protocols: vec![],
+            addressRecords: vec![
+                proto::AddressRecord {
+                    addr: Some(confirmed.to_vec()),
+                    provenance: Some(proto::Provenance::CONFIRMED),
+                },
+                proto::AddressRecord {
+                    addr: Some(vec![255; 8]),
+                    provenance: Some(proto::Provenance::LISTEN),
+                },
+                proto::AddressRecord {
+                    addr: None,
+                    provenance: Some(proto::Provenance::LISTEN),
+                },
+                proto::AddressRecord {
+                    addr: Some(candidate.to_vec()),
+                    provenance: Some(proto::Provenance::CANDIDATE),
+                },
+            ],
+            publicKey: None,
+        };
+
+        let info = PushInfo::try_from(payload).expect("not to fail");
+
+        assert_eq!(
+            info.address_provenance,
+            vec![
+                (confirmed, AddressProvenance::Confirmed),
+                (candidate, AddressProvenance::Candidate)
+            ]
+        )
+    }
 }
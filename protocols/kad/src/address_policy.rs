@@ -0,0 +1,155 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Validation of the addresses included in inbound provider records.
+
+use libp2p_core::multiaddr::{Multiaddr, Protocol};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// The policy applied to the addresses of provider records received via `ADD_PROVIDER`
+/// requests, see [`Config::set_provider_address_policy`](crate::Config::set_provider_address_policy).
+///
+/// Relayed addresses are judged by the address of the relay.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ProviderAddressPolicy {
+    /// Accept provider records regardless of their addresses.
+    #[default]
+    AcceptAll,
+    /// Reject provider records without an address that is reachable from another host,
+    /// i.e. whose addresses are all loopback, link-local or unspecified IP addresses.
+    RequireRoutable,
+    /// Reject provider records without an address that is reachable on the public internet,
+    /// i.e. a global IP address or a DNS name.
+    RequireGlobal,
+}
+
+impl ProviderAddressPolicy {
+    /// Returns whether a provider record with the given addresses is acceptable.
+    pub(crate) fn allows(&self, addresses: &[Multiaddr]) -> bool {
+        match self {
+            ProviderAddressPolicy::AcceptAll => true,
+            ProviderAddressPolicy::RequireRoutable => {
+                addresses.iter().any(|a| is_reachable(a, false))
+            }
+            ProviderAddressPolicy::RequireGlobal => addresses.iter().any(|a| is_reachable(a, true)),
+        }
+    }
+}
+
+fn is_reachable(address: &Multiaddr, global: bool) -> bool {
+    match address.iter().next() {
+        Some(Protocol::Ip4(ip)) if global => ipv4_is_global(ip),
+        Some(Protocol::Ip4(ip)) => {
+            !(ip.is_unspecified() || ip.is_loopback() || ip.is_link_local() || ip.is_broadcast())
+        }
+        Some(Protocol::Ip6(ip)) if global => ipv6_is_global(ip),
+        Some(Protocol::Ip6(ip)) => {
+            !(ip.is_unspecified() || ip.is_loopback() || (ip.segments()[0] & 0xffc0) == 0xfe80)
+        }
+        Some(
+            Protocol::Dns(host)
+            | Protocol::Dns4(host)
+            | Protocol::Dns6(host)
+            | Protocol::Dnsaddr(host),
+        ) => host != "localhost",
+        _ => false,
+    }
+}
+
+// NOTE: The below logic is a simplified version of `std::net::Ipv4Addr::is_global`, which is at
+// the time of writing behind the unstable `ip` feature.
+// See https://github.com/rust-lang/rust/issues/27709 for more info.
+fn ipv4_is_global(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    !(a == 0 // "This network"
+        || ip.is_private()
+        || (a == 100 && (b & 0b1100_0000 == 0b0100_0000)) // Shared address space
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || (a == 192 && b == 0 && c == 0) // Reserved for future protocols
+        || ip.is_documentation()
+        || (a == 198 && (b & 0xfe) == 18) // Benchmarking
+        || a & 240 == 240 // Reserved, including broadcast
+        || ip.is_multicast())
+}
+
+// NOTE: The below logic is a simplified version of `std::net::Ipv6Addr::is_global`, which is at
+// the time of writing behind the unstable `ip` feature.
+// See https://github.com/rust-lang/rust/issues/27709 for more info.
+fn ipv6_is_global(ip: Ipv6Addr) -> bool {
+    if let Some(ip) = ip.to_ipv4_mapped() {
+        return ipv4_is_global(ip);
+    }
+
+    let segments = ip.segments();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        || (segments[0] & 0xfe00) == 0xfc00 // Unique local
+        || (segments[0] & 0xffc0) == 0xfe80 // Link-local
+        || (segments[0] == 0x2001 && segments[1] == 0xdb8)) // Documentation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(addrs: &[&str]) -> Vec<Multiaddr> {
+        addrs.iter().map(|a| a.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn require_routable() {
+        let policy = ProviderAddressPolicy::RequireRoutable;
+
+        assert!(!policy.allows(&[]));
+        assert!(!policy.allows(&addrs(&[
+            "/ip4/127.0.0.1/tcp/4001",
+            "/ip4/0.0.0.0/tcp/4001",
+            "/ip6/::1/tcp/4001",
+            "/ip6/fe80::1/tcp/4001",
+            "/memory/1234",
+        ])));
+        assert!(policy.allows(&addrs(&[
+            "/ip4/127.0.0.1/tcp/4001",
+            "/ip4/192.168.1.10/tcp/4001"
+        ])));
+    }
+
+    #[test]
+    fn require_global() {
+        let policy = ProviderAddressPolicy::RequireGlobal;
+
+        assert!(!policy.allows(&addrs(&[
+            "/ip4/192.168.1.10/tcp/4001",
+            "/ip4/100.64.0.1/tcp/4001",
+            "/ip6/fd00::1/tcp/4001",
+            "/ip6/::ffff:10.0.0.1/tcp/4001",
+            "/dns4/localhost/tcp/4001",
+        ])));
+        assert!(policy.allows(&addrs(&["/ip4/1.2.3.4/tcp/4001"])));
+        assert!(policy.allows(&addrs(&["/ip6/2a00:1450::1/udp/4001/quic-v1"])));
+        assert!(policy.allows(&addrs(&["/dns4/example.com/tcp/4001"])));
+        assert!(policy.allows(&addrs(&[
+            "/ip4/1.2.3.4/tcp/4001/p2p/12D3KooWGC6TvWhfapngX6wvJHMYvKpDMXPb3ZnCZ6dMoaMtimQ5/p2p-circuit"
+        ])));
+    }
+}
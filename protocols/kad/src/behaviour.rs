@@ -22,11 +22,14 @@
 
 mod test;
 
+use crate::address_policy::ProviderAddressPolicy;
 use crate::addresses::Addresses;
-use crate::handler::{Handler, HandlerEvent, HandlerIn, RequestId};
+use crate::handler::{Handler, HandlerEvent, HandlerIn, RequestId, RpcStats, RpcType};
+use crate::health::{self, RoutingTableHealth};
 use crate::kbucket::{self, Distance, KBucketConfig, KBucketsTable, NodeStatus};
 use crate::protocol::{ConnectionType, KadPeer, ProtocolConfig};
 use crate::query::{Query, QueryConfig, QueryId, QueryPool, QueryPoolState};
+use crate::rate_limit::{InboundRateLimiter, RateLimit, RateLimitAction};
 use crate::record::{
     self,
     store::{self, RecordStore},
@@ -35,20 +38,24 @@ use crate::record::{
 use crate::{bootstrap, K_VALUE};
 use crate::{jobs::*, protocol};
 use fnv::FnvHashSet;
+use futures::FutureExt;
+use futures_timer::Delay;
 use libp2p_core::{transport::PortUse, ConnectedPoint, Endpoint, Multiaddr};
 use libp2p_identity::PeerId;
 use libp2p_swarm::behaviour::{
-    AddressChange, ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm,
+    rank_by_source_and_recency, AddressChange, ConnectionClosed, ConnectionEstablished,
+    DialFailure, FromSwarm, NewExternalAddrOfPeer,
 };
 use libp2p_swarm::{
     dial_opts::{self, DialOpts},
-    ConnectionDenied, ConnectionHandler, ConnectionId, DialError, ExternalAddresses,
-    ListenAddresses, NetworkBehaviour, NotifyHandler, StreamProtocol, THandler, THandlerInEvent,
-    THandlerOutEvent, ToSwarm,
+    AddressRanking, ConnectionDenied, ConnectionHandler, ConnectionId, DialError,
+    ExternalAddresses, ListenAddresses, NetworkBehaviour, NotifyHandler, StreamProtocol, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
 use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::num::NonZeroUsize;
+use std::sync::Arc;
 use std::task::{Context, Poll, Waker};
 use std::time::Duration;
 use std::vec;
@@ -89,6 +96,10 @@ pub struct Behaviour<TStore> {
     /// regular (value-)records.
     put_record_job: Option<PutRecordJob>,
 
+    /// Periodic job for re-replication of provider records of other
+    /// peers on churn of the local neighbourhood.
+    provider_replication_job: Option<ProviderReplicationJob>,
+
     /// The TTL of regular (value-)records.
     record_ttl: Option<Duration>,
 
@@ -118,6 +129,38 @@ pub struct Behaviour<TStore> {
 
     /// Tracks the status of the current bootstrap.
     bootstrap_status: bootstrap::Status,
+
+    /// Tracks the progress of the current bootstrap, see [`BootstrapOk`].
+    bootstrap_progress: bootstrap::Progress,
+
+    /// The targets of an interrupted bootstrap that are yet to be looked up.
+    ///
+    /// Empty if the bootstrap was interrupted during the initial self-lookup.
+    interrupted_bootstrap: Option<Vec<kbucket::Key<PeerId>>>,
+
+    /// See [`Config::set_bootstrap_min_peers`].
+    bootstrap_min_peers: usize,
+
+    /// See [`Config::set_rpc_stats_reporting`].
+    report_rpc_stats: bool,
+
+    /// See [`Config::set_max_concurrent_inbound_requests`].
+    max_concurrent_inbound_requests: Option<NonZeroUsize>,
+
+    /// Rate limiter for inbound requests, if any limit is configured.
+    inbound_rate_limiter: Option<InboundRateLimiter>,
+
+    /// See [`Config::set_learn_peer_addresses_from_swarm`].
+    learn_peer_addresses_from_swarm: bool,
+
+    /// See [`Config::set_advertised_addresses_filter`].
+    advertised_addresses_filter: Option<AdvertisedAddressesFilter>,
+
+    /// See [`Config::set_provider_address_policy`].
+    provider_address_policy: ProviderAddressPolicy,
+
+    /// See [`Config::set_health_report_interval`].
+    health_report_interval: Option<(Duration, Delay)>,
 }
 
 /// The configurable strategies for the insertion of peers
@@ -167,6 +210,27 @@ pub enum StoreInserts {
     FilterBoth,
 }
 
+/// The kind of an address of the local node, passed to the filter set via
+/// [`Config::set_advertised_addresses_filter`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LocalAddressKind {
+    /// An address the local node is listening on.
+    Listen,
+    /// A confirmed external address of the local node.
+    ConfirmedExternal,
+}
+
+#[derive(Clone)]
+struct AdvertisedAddressesFilter(
+    Arc<dyn Fn(&Multiaddr, LocalAddressKind) -> bool + Send + Sync + 'static>,
+);
+
+impl fmt::Debug for AdvertisedAddressesFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AdvertisedAddressesFilter").finish()
+    }
+}
+
 /// The configuration for the `Kademlia` behaviour.
 ///
 /// The configuration is consumed by [`Behaviour::new`].
@@ -181,10 +245,22 @@ pub struct Config {
     record_filtering: StoreInserts,
     provider_record_ttl: Option<Duration>,
     provider_publication_interval: Option<Duration>,
+    provider_replication_churn_threshold: Option<NonZeroUsize>,
     kbucket_inserts: BucketInserts,
     caching: Caching,
     periodic_bootstrap_interval: Option<Duration>,
     automatic_bootstrap_throttle: Option<Duration>,
+    bootstrap_min_peers: usize,
+    report_rpc_stats: bool,
+    inbound_peer_rate_limit: Option<RateLimit>,
+    inbound_ip_rate_limit: Option<RateLimit>,
+    inbound_rate_limit_action: RateLimitAction,
+    max_concurrent_inbound_requests: Option<NonZeroUsize>,
+    learn_peer_addresses_from_swarm: bool,
+    advertised_addresses_filter: Option<AdvertisedAddressesFilter>,
+    provider_address_policy: ProviderAddressPolicy,
+    external_address_ranking: AddressRanking,
+    health_report_interval: Option<Duration>,
 }
 
 impl Default for Config {
@@ -209,6 +285,12 @@ pub enum Caching {
     /// The write-back operation must be performed explicitly, if
     /// desired and after choosing a record from the results, via [`Behaviour::put_record_to`].
     Enabled { max_peers: u16 },
+    /// Like [`Caching::Enabled`], but the first record found is automatically
+    /// stored at the tracked peers once the lookup finishes. The peers storing
+    /// the record shorten its expiration exponentially with the number of nodes
+    /// they know between themselves and the key, so that copies cached far away
+    /// from the key expire quickly.
+    Automatic { max_peers: u16 },
 }
 
 impl Config {
@@ -224,10 +306,22 @@ impl Config {
             record_filtering: StoreInserts::Unfiltered,
             provider_publication_interval: Some(Duration::from_secs(12 * 60 * 60)),
             provider_record_ttl: Some(Duration::from_secs(48 * 60 * 60)),
+            provider_replication_churn_threshold: None,
             kbucket_inserts: BucketInserts::OnConnected,
             caching: Caching::Enabled { max_peers: 1 },
             periodic_bootstrap_interval: Some(Duration::from_secs(5 * 60)),
             automatic_bootstrap_throttle: Some(bootstrap::DEFAULT_AUTOMATIC_THROTTLE),
+            bootstrap_min_peers: K_VALUE.get(),
+            report_rpc_stats: false,
+            inbound_peer_rate_limit: None,
+            inbound_ip_rate_limit: None,
+            inbound_rate_limit_action: RateLimitAction::Refuse,
+            max_concurrent_inbound_requests: None,
+            learn_peer_addresses_from_swarm: false,
+            advertised_addresses_filter: None,
+            provider_address_policy: ProviderAddressPolicy::AcceptAll,
+            external_address_ranking: rank_by_source_and_recency,
+            health_report_interval: None,
         }
     }
 
@@ -389,6 +483,29 @@ impl Config {
         self
     }
 
+    /// Sets the number of closest peers to the local node that must have
+    /// joined or left since the last check for the node to re-announce the
+    /// provider records of other peers it stores to the current closest
+    /// peers to their keys.
+    ///
+    /// The neighbourhood is checked at the record replication interval,
+    /// see [`Config::set_replication_interval`], and only records with less
+    /// than half of the provider record TTL remaining are re-announced.
+    /// This reduces the loss of provider records in networks with high
+    /// churn, at the cost of additional queries. Only applies while the node
+    /// is in [`Mode::Server`] and requires a [`RecordStore`] that implements
+    /// [`RecordStore::third_party_providers`].
+    ///
+    /// `None` means that provider records of other peers are never
+    /// re-announced, which is the default.
+    pub fn set_provider_replication_churn_threshold(
+        &mut self,
+        threshold: Option<NonZeroUsize>,
+    ) -> &mut Self {
+        self.provider_replication_churn_threshold = threshold;
+        self
+    }
+
     /// Modifies the maximum allowed size of individual Kademlia packets.
     ///
     /// It might be necessary to increase this value if trying to put large
@@ -398,6 +515,19 @@ impl Config {
         self
     }
 
+    /// Enables zstd compression of Kademlia messages with peers supporting it.
+    ///
+    /// The compression is negotiated via the `<protocol>+zstd` variants of the configured
+    /// protocol names, falling back to uncompressed messages with other peers. This mainly
+    /// reduces the bandwidth used by responses carrying many peers and their addresses.
+    ///
+    /// * Default to `false`.
+    #[cfg(feature = "zstd")]
+    pub fn set_compression(&mut self, enabled: bool) -> &mut Self {
+        self.protocol_config.set_compression(enabled);
+        self
+    }
+
     /// Sets the k-bucket insertion strategy for the Kademlia routing table.
     pub fn set_kbucket_inserts(&mut self, inserts: BucketInserts) -> &mut Self {
         self.kbucket_inserts = inserts;
@@ -410,6 +540,10 @@ impl Config {
     /// Hence, with default settings and a lookup quorum of 1, a successful lookup
     /// will result in the record being cached at the closest node to the key that
     /// did not return the record, i.e. the standard Kademlia behaviour.
+    ///
+    /// With [`Caching::Enabled`], the record must be stored explicitly via
+    /// [`Behaviour::put_record_to`], whereas [`Caching::Automatic`] does so
+    /// for the first record found.
     pub fn set_caching(&mut self, c: Caching) -> &mut Self {
         self.caching = c;
         self
@@ -424,6 +558,28 @@ impl Config {
         self
     }
 
+    /// Sets the interval on which an [`Event::RoutingTableHealthReport`] summarizing
+    /// the routing table is emitted.
+    ///
+    /// See [`Behaviour::routing_table_health`] to obtain the summary on demand.
+    ///
+    /// * Default to `None`, i.e. no periodic report.
+    pub fn set_health_report_interval(&mut self, interval: Option<Duration>) -> &mut Self {
+        self.health_report_interval = interval;
+        self
+    }
+
+    /// Sets the minimum number of peers in the routing table below which
+    /// [`Behaviour::bootstrap`] is triggered automatically whenever a new peer
+    /// is inserted into the routing table.
+    ///
+    /// * Default to `K_VALUE`.
+    /// * Set to `0` to only trigger bootstrap periodically.
+    pub fn set_bootstrap_min_peers(&mut self, min_peers: usize) -> &mut Self {
+        self.bootstrap_min_peers = min_peers;
+        self
+    }
+
     /// Sets the configuration for the k-buckets.
     ///
     /// * Default to K_VALUE.
@@ -442,6 +598,126 @@ impl Config {
         self
     }
 
+    /// Sets whether an [`Event::RpcCompleted`] is emitted for every request
+    /// exchanged with a remote peer.
+    ///
+    /// This is useful to diagnose the load of a DHT server, e.g. through `libp2p-metrics`,
+    /// but comes at the cost of computing the encoded size of every message.
+    ///
+    /// * Default to `false`.
+    pub fn set_rpc_stats_reporting(&mut self, enabled: bool) -> &mut Self {
+        self.report_rpc_stats = enabled;
+        self
+    }
+
+    /// Sets whether addresses of peers reported by other behaviours via
+    /// [`ToSwarm::NewExternalAddrOfPeer`] are added to the routing table,
+    /// as if passed to [`Behaviour::add_address`].
+    ///
+    /// This makes peers discovered by e.g. `libp2p-mdns` available to Kademlia
+    /// without forwarding the events of the discovering behaviour manually.
+    /// Note that these peers are not known to support the Kademlia protocol.
+    /// Peers that turn out to be unreachable are eventually evicted from the
+    /// routing table as usual.
+    ///
+    /// * Default to `false`.
+    pub fn set_learn_peer_addresses_from_swarm(&mut self, enabled: bool) -> &mut Self {
+        self.learn_peer_addresses_from_swarm = enabled;
+        self
+    }
+
+    /// Sets a filter for the addresses of the local node advertised to other peers,
+    /// i.e. included in `GET_PROVIDERS` responses and `ADD_PROVIDER` requests.
+    ///
+    /// The filter is called for every address along with its [`LocalAddressKind`]
+    /// and the address is advertised if it returns `true`. This allows DHT servers
+    /// behind complex network setups to e.g. only advertise confirmed external
+    /// addresses or to not advertise relayed addresses.
+    ///
+    /// * Default to advertising all listen and confirmed external addresses.
+    pub fn set_advertised_addresses_filter<F>(&mut self, filter: F) -> &mut Self
+    where
+        F: Fn(&Multiaddr, LocalAddressKind) -> bool + Send + Sync + 'static,
+    {
+        self.advertised_addresses_filter = Some(AdvertisedAddressesFilter(Arc::new(filter)));
+        self
+    }
+
+    /// Sets the policy for the addresses of provider records received from other peers.
+    ///
+    /// Provider records announced via `ADD_PROVIDER` requests whose addresses are all
+    /// rejected by the policy are dropped, keeping unreachable providers out of the
+    /// [`RecordStore`] of public nodes.
+    ///
+    /// * Default to [`ProviderAddressPolicy::AcceptAll`].
+    pub fn set_provider_address_policy(&mut self, policy: ProviderAddressPolicy) -> &mut Self {
+        self.provider_address_policy = policy;
+        self
+    }
+
+    /// Sets how the confirmed external addresses of the local node are ranked.
+    ///
+    /// Higher ranked addresses are advertised first in provider records and in
+    /// responses listing the local node as a provider, before the listen addresses.
+    ///
+    /// * Default to [`rank_by_source_and_recency`].
+    pub fn set_external_address_ranking(&mut self, ranking: AddressRanking) -> &mut Self {
+        self.external_address_ranking = ranking;
+        self
+    }
+
+    /// Sets the rate limit for inbound requests of a single remote peer.
+    ///
+    /// Requests exceeding the limit are handled according to
+    /// [`Config::set_inbound_rate_limit_action`].
+    ///
+    /// * Default to `None`, i.e. no limit.
+    pub fn set_inbound_peer_rate_limit(&mut self, limit: Option<RateLimit>) -> &mut Self {
+        self.inbound_peer_rate_limit = limit;
+        self
+    }
+
+    /// Sets the rate limit for inbound requests of all remote peers connected
+    /// from the same IP address.
+    ///
+    /// Requests received over relayed connections are not subject to this limit.
+    /// Requests exceeding the limit are handled according to
+    /// [`Config::set_inbound_rate_limit_action`].
+    ///
+    /// * Default to `None`, i.e. no limit.
+    pub fn set_inbound_ip_rate_limit(&mut self, limit: Option<RateLimit>) -> &mut Self {
+        self.inbound_ip_rate_limit = limit;
+        self
+    }
+
+    /// Sets how inbound requests exceeding the configured rate limits are handled.
+    ///
+    /// See [`Config::set_inbound_peer_rate_limit`] and [`Config::set_inbound_ip_rate_limit`].
+    ///
+    /// * Default to [`RateLimitAction::Refuse`].
+    pub fn set_inbound_rate_limit_action(&mut self, action: RateLimitAction) -> &mut Self {
+        self.inbound_rate_limit_action = action;
+        self
+    }
+
+    /// Sets the maximum number of inbound requests of a single connection that may wait for an
+    /// answer at the same time.
+    ///
+    /// Requests exceeding the limit are refused by resetting their stream, since the Kademlia
+    /// protocol has no way of signaling that the server is busy, and reported as
+    /// [`Event::InboundRequestRefused`]. This keeps the work queued for answering requests bounded
+    /// when a remote sends requests faster than they can be answered, e.g. when they are
+    /// filtered manually via [`Config::set_record_filtering`].
+    ///
+    /// * Default to `None`, i.e. no limit besides the number of concurrent inbound streams.
+    pub fn set_max_concurrent_inbound_requests(
+        &mut self,
+        limit: Option<NonZeroUsize>,
+    ) -> &mut Self {
+        self.max_concurrent_inbound_requests = limit;
+        self
+    }
+
     /// Sets the time to wait before calling [`Behaviour::bootstrap`] after a new peer is inserted in the routing table.
     /// This prevent cascading bootstrap requests when multiple peers are inserted into the routing table "at the same time".
     /// This also allows to wait a little bit for other potential peers to be inserted into the routing table before
@@ -496,6 +772,13 @@ where
             .provider_publication_interval
             .map(AddProviderJob::new);
 
+        let provider_replication_job = config
+            .record_replication_interval
+            .zip(config.provider_replication_churn_threshold)
+            .map(|(interval, threshold)| {
+                ProviderReplicationJob::new(interval, threshold.get(), config.provider_record_ttl)
+            });
+
         Behaviour {
             store,
             caching: config.caching,
@@ -509,9 +792,11 @@ where
             connected_peers: Default::default(),
             add_provider_job,
             put_record_job,
+            provider_replication_job,
             record_ttl: config.record_ttl,
             provider_record_ttl: config.provider_record_ttl,
-            external_addresses: Default::default(),
+            external_addresses: ExternalAddresses::default()
+                .with_ranking(config.external_address_ranking),
             local_peer_id: id,
             connections: Default::default(),
             mode: Mode::Client,
@@ -521,6 +806,26 @@ where
                 config.periodic_bootstrap_interval,
                 config.automatic_bootstrap_throttle,
             ),
+            bootstrap_progress: Default::default(),
+            interrupted_bootstrap: None,
+            bootstrap_min_peers: config.bootstrap_min_peers,
+            report_rpc_stats: config.report_rpc_stats,
+            max_concurrent_inbound_requests: config.max_concurrent_inbound_requests,
+            learn_peer_addresses_from_swarm: config.learn_peer_addresses_from_swarm,
+            advertised_addresses_filter: config.advertised_addresses_filter,
+            provider_address_policy: config.provider_address_policy,
+            health_report_interval: config
+                .health_report_interval
+                .map(|interval| (interval, Delay::new(interval))),
+            inbound_rate_limiter: (config.inbound_peer_rate_limit.is_some()
+                || config.inbound_ip_rate_limit.is_some())
+            .then(|| {
+                InboundRateLimiter::new(
+                    config.inbound_peer_rate_limit,
+                    config.inbound_ip_rate_limit,
+                    config.inbound_rate_limit_action,
+                )
+            }),
         }
     }
 
@@ -645,7 +950,9 @@ where
                     }
                     kbucket::InsertResult::Pending { disconnected } => {
                         self.queued_events.push_back(ToSwarm::Dial {
-                            opts: DialOpts::peer_id(disconnected.into_preimage()).build(),
+                            opts: DialOpts::peer_id(disconnected.into_preimage())
+                                .priority(dial_opts::DialPriority::Low)
+                                .build(),
                         });
                         RoutingUpdate::Pending
                     }
@@ -714,6 +1021,12 @@ where
         self.kbuckets.iter().filter(|b| !b.is_empty())
     }
 
+    /// Returns a summary of the routing table, i.e. the address families and transports
+    /// of the peers, the fill levels of the buckets and the estimated size of the network.
+    pub fn routing_table_health(&mut self) -> RoutingTableHealth {
+        health::report(&mut self.kbuckets)
+    }
+
     /// Returns the k-bucket for the distance to the given key.
     ///
     /// Returns `None` if the given key refers to the local key.
@@ -801,12 +1114,17 @@ where
         let step = ProgressStep::first();
 
         let target = kbucket::Key::new(key.clone());
-        let info = if record.is_some() {
+        let info = if let Some(record) = &record {
+            let cache_record = match self.caching {
+                Caching::Automatic { .. } => Some(record.record.clone()),
+                Caching::Disabled | Caching::Enabled { .. } => None,
+            };
             QueryInfo::GetRecord {
                 key,
                 step: step.next(),
                 found_a_record: true,
                 cache_candidates: BTreeMap::new(),
+                cache_record,
             }
         } else {
             QueryInfo::GetRecord {
@@ -814,6 +1132,7 @@ where
                 step: step.clone(),
                 found_a_record: false,
                 cache_candidates: BTreeMap::new(),
+                cache_record: None,
             }
         };
         let peers = self.kbuckets.closest_keys(&target);
@@ -893,7 +1212,26 @@ where
     /// > method must be used to ensure the standard Kademlia
     /// > procedure of "caching" (i.e. storing) a found record at the closest
     /// > node to the key that _did not_ return it.
-    pub fn put_record_to<I>(&mut self, mut record: Record, peers: I, quorum: Quorum) -> QueryId
+    ///
+    /// The result reports the peers the record has been stored on and the peers
+    /// it could not be stored on, see [`PutRecordOk`] and [`PutRecordError`].
+    /// Once the quorum is reached, peers that have not confirmed storing the
+    /// record yet are reported as failed. To retry, call this method again
+    /// with the failed peers.
+    pub fn put_record_to<I>(&mut self, record: Record, peers: I, quorum: Quorum) -> QueryId
+    where
+        I: ExactSizeIterator<Item = PeerId>,
+    {
+        self.put_record_to_with_context(record, peers, quorum, PutRecordContext::Custom)
+    }
+
+    fn put_record_to_with_context<I>(
+        &mut self,
+        mut record: Record,
+        peers: I,
+        quorum: Quorum,
+        context: PutRecordContext,
+    ) -> QueryId
     where
         I: ExactSizeIterator<Item = PeerId>,
     {
@@ -908,7 +1246,6 @@ where
         record.expires = record
             .expires
             .or_else(|| self.record_ttl.map(|ttl| Instant::now() + ttl));
-        let context = PutRecordContext::Custom;
         let info = QueryInfo::PutRecord {
             context,
             record,
@@ -960,6 +1297,12 @@ where
     ///
     /// Returns `Err` if bootstrapping is impossible due an empty routing table.
     ///
+    /// If none of the peers contacted by a bootstrapping query respond, e.g. due to a
+    /// temporary loss of connectivity, bootstrapping is interrupted with
+    /// [`BootstrapError::Interrupted`]. The next call to [`Behaviour::bootstrap`], which is
+    /// triggered automatically once a connection is established again, resumes with the
+    /// buckets that have not been refreshed yet.
+    ///
     /// > **Note**: Bootstrapping requires at least one node of the DHT to be known.
     /// > See [`Behaviour::add_address`].
     ///
@@ -970,6 +1313,12 @@ where
     /// > This parameter is used to call [`Behaviour::bootstrap`] periodically and automatically
     /// > to ensure a healthy routing table.
     pub fn bootstrap(&mut self) -> Result<QueryId, NoKnownPeers> {
+        if let Some(targets) = self.interrupted_bootstrap.take() {
+            if !targets.is_empty() {
+                return self.resume_bootstrap(targets);
+            }
+        }
+
         let local_key = *self.kbuckets.local_key();
         let info = QueryInfo::Bootstrap {
             peer: *local_key.preimage(),
@@ -982,10 +1331,34 @@ where
             Err(NoKnownPeers())
         } else {
             self.bootstrap_status.on_started();
+            self.bootstrap_progress = Default::default();
             Ok(self.queries.add_iter_closest(local_key, peers, info))
         }
     }
 
+    /// Resumes an interrupted bootstrap with the remaining bucket refreshes.
+    fn resume_bootstrap(
+        &mut self,
+        targets: Vec<kbucket::Key<PeerId>>,
+    ) -> Result<QueryId, NoKnownPeers> {
+        let mut remaining = targets.clone().into_iter();
+        let target = remaining.next().expect("targets to be non-empty");
+        let peers = self.kbuckets.closest_keys(&target).collect::<Vec<_>>();
+        if peers.is_empty() {
+            self.interrupted_bootstrap = Some(targets);
+            self.bootstrap_status.reset_timers();
+            return Err(NoKnownPeers());
+        }
+
+        let info = QueryInfo::Bootstrap {
+            peer: *target.preimage(),
+            remaining: Some(remaining),
+            step: ProgressStep::first(),
+        };
+        self.bootstrap_status.on_started();
+        Ok(self.queries.add_iter_closest(target, peers, info))
+    }
+
     /// Establishes the local node as a provider of a value for the given key.
     ///
     /// This operation publishes a provider record with the given key and
@@ -1228,12 +1601,32 @@ where
             .collect()
     }
 
+    /// Whether the given address of the local node is advertised to other peers.
+    ///
+    /// See [`Config::set_advertised_addresses_filter`].
+    fn is_advertised(&self, address: &Multiaddr, kind: LocalAddressKind) -> bool {
+        self.advertised_addresses_filter
+            .as_ref()
+            .map_or(true, |AdvertisedAddressesFilter(filter)| {
+                filter(address, kind)
+            })
+    }
+
     /// Collects all peers who are known to be providers of the value for a given `Multihash`.
     fn provider_peers(&mut self, key: &record::Key, source: &PeerId) -> Vec<KadPeer> {
+        let local_addresses = self
+            .external_addresses
+            .iter()
+            .filter(|a| self.is_advertised(a, LocalAddressKind::ConfirmedExternal))
+            .chain(
+                self.listen_addresses
+                    .iter()
+                    .filter(|a| self.is_advertised(a, LocalAddressKind::Listen)),
+            )
+            .cloned()
+            .collect::<Vec<_>>();
         let kbuckets = &mut self.kbuckets;
         let connected = &mut self.connected_peers;
-        let listen_addresses = &self.listen_addresses;
-        let external_addresses = &self.external_addresses;
 
         self.store
             .providers(key)
@@ -1255,13 +1648,7 @@ where
                         // done before provider records were stored along with
                         // their addresses.
                         if &node_id == kbuckets.local_key().preimage() {
-                            Some(
-                                listen_addresses
-                                    .iter()
-                                    .chain(external_addresses.iter())
-                                    .cloned()
-                                    .collect::<Vec<_>>(),
-                            )
+                            Some(local_addresses.clone())
                         } else {
                             let key = kbucket::Key::from(node_id);
                             kbuckets
@@ -1298,6 +1685,28 @@ where
         self.queries.add_iter_closest(target.clone(), peers, info);
     }
 
+    /// Re-announces a provider record of another peer to the closest
+    /// peers to its key in the local routing table.
+    fn start_replicate_provider(&mut self, record: ProviderRecord) {
+        let target = kbucket::Key::new(record.key.clone());
+        let peers = self
+            .kbuckets
+            .closest_keys(&target)
+            .take(self.queries.config().replication_factor.get())
+            .map(|key| key.into_preimage())
+            .collect::<Vec<_>>();
+        let info = QueryInfo::AddProvider {
+            context: AddProviderContext::Replicate,
+            key: record.key,
+            phase: AddProviderPhase::AddProvider {
+                provider_id: record.provider,
+                external_addresses: record.addresses,
+                get_closest_peers_stats: QueryStats::empty(),
+            },
+        };
+        self.queries.add_fixed(peers, info);
+    }
+
     /// Starts an iterative `PUT_VALUE` query for the given record.
     fn start_put_record(&mut self, record: Record, quorum: Quorum, context: PutRecordContext) {
         let quorum = quorum.eval(self.queries.config().replication_factor);
@@ -1414,6 +1823,7 @@ where
                                 if !self.connected_peers.contains(disconnected.preimage()) {
                                     self.queued_events.push_back(ToSwarm::Dial {
                                         opts: DialOpts::peer_id(disconnected.into_preimage())
+                                            .priority(dial_opts::DialPriority::Low)
                                             .build(),
                                     })
                                 }
@@ -1427,14 +1837,16 @@ where
     }
 
     /// A new peer has been inserted in the routing table but we check if the routing
-    /// table is currently small (less that `K_VALUE` peers are present) and only
-    /// trigger a bootstrap in that case
+    /// table is currently small (less than [`Config::set_bootstrap_min_peers`] peers
+    /// are present) and only trigger a bootstrap in that case
     fn bootstrap_on_low_peers(&mut self) {
+        self.bootstrap_progress.peers_added += 1;
+
         if self
             .kbuckets()
             .map(|kbucket| kbucket.num_entries())
             .sum::<usize>()
-            < K_VALUE.get()
+            < self.bootstrap_min_peers
         {
             self.bootstrap_status.trigger();
         }
@@ -1450,6 +1862,35 @@ where
                 remaining,
                 mut step,
             } => {
+                if q.stats.num_successes() == 0 {
+                    // None of the contacted peers responded, most likely due to a loss of
+                    // connectivity. Stop here and resume with the current target once a
+                    // connection is established again.
+                    let num_remaining = remaining.as_ref().map(|r| r.len() as u32);
+                    self.interrupted_bootstrap = Some(match remaining {
+                        Some(remaining) => std::iter::once(kbucket::Key::from(peer))
+                            .chain(remaining)
+                            .collect(),
+                        None => Vec::new(),
+                    });
+                    step.last = true;
+                    self.bootstrap_status.on_finish();
+
+                    return Some(Event::OutboundQueryProgressed {
+                        id: query_id,
+                        stats: q.stats,
+                        result: QueryResult::Bootstrap(Err(BootstrapError::Interrupted {
+                            peer,
+                            num_remaining,
+                        })),
+                        step,
+                    });
+                }
+
+                if remaining.is_some() {
+                    self.bootstrap_progress.buckets_refreshed += 1;
+                }
+
                 let local_key = *self.kbuckets.local_key();
                 let mut remaining = remaining.unwrap_or_else(|| {
                     debug_assert_eq!(&peer, local_key.preimage());
@@ -1511,6 +1952,8 @@ where
                     result: QueryResult::Bootstrap(Ok(BootstrapOk {
                         peer,
                         num_remaining,
+                        buckets_refreshed: self.bootstrap_progress.buckets_refreshed,
+                        peers_added: self.bootstrap_progress.peers_added,
                     })),
                     step,
                 })
@@ -1551,7 +1994,12 @@ where
                 phase: AddProviderPhase::GetClosestPeers,
             } => {
                 let provider_id = self.local_peer_id;
-                let external_addresses = self.external_addresses.iter().cloned().collect();
+                let external_addresses = self
+                    .external_addresses
+                    .iter()
+                    .filter(|a| self.is_advertised(a, LocalAddressKind::ConfirmedExternal))
+                    .cloned()
+                    .collect();
                 let info = QueryInfo::AddProvider {
                     context,
                     key,
@@ -1587,6 +2035,7 @@ where
                     result: QueryResult::RepublishProvider(Ok(AddProviderOk { key })),
                     step: ProgressStep::first_and_last(),
                 }),
+                AddProviderContext::Replicate => None,
             },
 
             QueryInfo::GetRecord {
@@ -1594,9 +2043,21 @@ where
                 mut step,
                 found_a_record,
                 cache_candidates,
+                cache_record,
             } => {
                 step.last = true;
 
+                if let Some(record) = cache_record {
+                    if !cache_candidates.is_empty() {
+                        self.put_record_to_with_context(
+                            record,
+                            cache_candidates.values().copied(),
+                            Quorum::One,
+                            PutRecordContext::Cache,
+                        );
+                    }
+                }
+
                 let results = if found_a_record {
                     Ok(GetRecordOk::FinishedWithNoAdditionalRecord { cache_candidates })
                 } else {
@@ -1643,14 +2104,20 @@ where
                         get_closest_peers_stats,
                     },
             } => {
+                let failed = q.peers.into_unsuccessful_peerids_iter().collect();
                 let mk_result = |key: record::Key| {
                     if success.len() >= quorum.get() {
-                        Ok(PutRecordOk { key })
+                        Ok(PutRecordOk {
+                            key,
+                            success,
+                            failed,
+                        })
                     } else {
                         Err(PutRecordError::QuorumFailed {
                             key,
                             quorum,
                             success,
+                            failed,
                         })
                     }
                 };
@@ -1673,6 +2140,10 @@ where
                         tracing::debug!(record=?record.key, "Record replicated");
                         None
                     }
+                    PutRecordContext::Cache => {
+                        tracing::debug!(record=?record.key, "Record cached");
+                        None
+                    }
                 }
             }
         }
@@ -1718,20 +2189,21 @@ where
                 })
             }
 
-            QueryInfo::AddProvider { context, key, .. } => Some(match context {
-                AddProviderContext::Publish => Event::OutboundQueryProgressed {
+            QueryInfo::AddProvider { context, key, .. } => match context {
+                AddProviderContext::Publish => Some(Event::OutboundQueryProgressed {
                     id: query_id,
                     stats: query.stats,
                     result: QueryResult::StartProviding(Err(AddProviderError::Timeout { key })),
                     step: ProgressStep::first_and_last(),
-                },
-                AddProviderContext::Republish => Event::OutboundQueryProgressed {
+                }),
+                AddProviderContext::Republish => Some(Event::OutboundQueryProgressed {
                     id: query_id,
                     stats: query.stats,
                     result: QueryResult::RepublishProvider(Err(AddProviderError::Timeout { key })),
                     step: ProgressStep::first_and_last(),
-                },
-            }),
+                }),
+                AddProviderContext::Replicate => None,
+            },
 
             QueryInfo::GetClosestPeers { key, mut step, .. } => {
                 step.last = true;
@@ -1759,6 +2231,7 @@ where
                         PutRecordPhase::GetClosestPeers => vec![],
                         PutRecordPhase::PutRecord { ref success, .. } => success.clone(),
                     },
+                    failed: query.peers.into_unsuccessful_peerids_iter().collect(),
                 });
                 match context {
                     PutRecordContext::Publish | PutRecordContext::Custom => {
@@ -1788,6 +2261,10 @@ where
                             None
                         }
                     },
+                    PutRecordContext::Cache => {
+                        tracing::debug!("Caching record failed: {:?}", err);
+                        None
+                    }
                 }
             }
 
@@ -2030,6 +2507,11 @@ where
         // Peer's first connection.
         if other_established == 0 {
             self.connected_peers.insert(peer_id);
+
+            if self.interrupted_bootstrap.is_some() {
+                // Connectivity might be back, resume the interrupted bootstrap.
+                self.bootstrap_status.trigger();
+            }
         }
     }
 
@@ -2145,6 +2627,9 @@ where
         }: ConnectionClosed,
     ) {
         self.connections.remove(&connection_id);
+        if let Some(limiter) = self.inbound_rate_limiter.as_mut() {
+            limiter.on_connection_closed(connection_id);
+        }
 
         if remaining_established == 0 {
             for query in self.queries.iter_mut() {
@@ -2155,6 +2640,37 @@ where
         }
     }
 
+    /// Checks an inbound request against the configured rate limits.
+    ///
+    /// Returns whether the request should be handled. If not and the request expects an answer,
+    /// the stream is reset if configured via [`Config::set_inbound_rate_limit_action`].
+    fn inbound_request_allowed(
+        &mut self,
+        source: PeerId,
+        connection: ConnectionId,
+        request_id: Option<RequestId>,
+    ) -> bool {
+        let Some(limiter) = self.inbound_rate_limiter.as_mut() else {
+            return true;
+        };
+
+        if limiter.try_acquire(source, connection, Instant::now()) {
+            return true;
+        }
+
+        tracing::debug!(peer=%source, "Inbound request exceeds rate limit");
+
+        if let (RateLimitAction::Refuse, Some(request_id)) = (limiter.action(), request_id) {
+            self.queued_events.push_back(ToSwarm::NotifyHandler {
+                peer_id: source,
+                handler: NotifyHandler::One(connection),
+                event: HandlerIn::Reset(request_id),
+            });
+        }
+
+        false
+    }
+
     /// Preloads a new [`Handler`] with requests that are waiting to be sent to the newly connected peer.
     fn preload_new_handler(
         &mut self,
@@ -2200,11 +2716,17 @@ where
             send_back_addr: remote_addr.clone(),
         };
 
+        if let Some(limiter) = self.inbound_rate_limiter.as_mut() {
+            limiter.on_connection_established(connection_id, remote_addr);
+        }
+
         let mut handler = Handler::new(
             self.protocol_config.clone(),
             connected_point,
             peer,
             self.mode,
+            self.report_rpc_stats,
+            self.max_concurrent_inbound_requests,
         );
         self.preload_new_handler(&mut handler, connection_id, peer);
 
@@ -2225,11 +2747,17 @@ where
             port_use,
         };
 
+        if let Some(limiter) = self.inbound_rate_limiter.as_mut() {
+            limiter.on_connection_established(connection_id, addr);
+        }
+
         let mut handler = Handler::new(
             self.protocol_config.clone(),
             connected_point,
             peer,
             self.mode,
+            self.report_rpc_stats,
+            self.max_concurrent_inbound_requests,
         );
         self.preload_new_handler(&mut handler, connection_id, peer);
 
@@ -2300,6 +2828,10 @@ where
             }
 
             HandlerEvent::FindNodeReq { key, request_id } => {
+                if !self.inbound_request_allowed(source, connection, Some(request_id)) {
+                    return;
+                }
+
                 let closer_peers = self.find_closest(&kbucket::Key::new(key), &source);
 
                 self.queued_events
@@ -2327,6 +2859,10 @@ where
             }
 
             HandlerEvent::GetProvidersReq { key, request_id } => {
+                if !self.inbound_request_allowed(source, connection, Some(request_id)) {
+                    return;
+                }
+
                 let provider_peers = self.provider_peers(&key, &source);
                 let closer_peers = self.find_closest(&kbucket::Key::new(key), &source);
 
@@ -2405,10 +2941,27 @@ where
                     return;
                 }
 
+                if !self.inbound_request_allowed(source, connection, None) {
+                    return;
+                }
+
+                if !self.provider_address_policy.allows(&provider.multiaddrs) {
+                    tracing::debug!(
+                        peer=%source,
+                        addresses=?provider.multiaddrs,
+                        "Provider record rejected due to its addresses"
+                    );
+                    return;
+                }
+
                 self.provider_received(key, provider);
             }
 
             HandlerEvent::GetRecord { key, request_id } => {
+                if !self.inbound_request_allowed(source, connection, Some(request_id)) {
+                    return;
+                }
+
                 // Lookup the record locally.
                 let record = match self.store.get(&key) {
                     Some(record) => {
@@ -2455,10 +3008,16 @@ where
                         ref mut step,
                         ref mut found_a_record,
                         cache_candidates,
+                        cache_record,
                     } = &mut query.info
                     {
                         if let Some(record) = record {
                             *found_a_record = true;
+                            if matches!(self.caching, Caching::Automatic { .. })
+                                && cache_record.is_none()
+                            {
+                                *cache_record = Some(record.clone());
+                            }
                             let record = PeerRecord {
                                 peer: Some(source),
                                 record,
@@ -2478,7 +3037,9 @@ where
                             *step = step.next();
                         } else {
                             tracing::trace!(record=?key, %source, "Record not found at source");
-                            if let Caching::Enabled { max_peers } = self.caching {
+                            if let Caching::Enabled { max_peers }
+                            | Caching::Automatic { max_peers } = self.caching
+                            {
                                 let source_key = kbucket::Key::from(source);
                                 let target_key = kbucket::Key::from(key.clone());
                                 let distance = source_key.distance(&target_key);
@@ -2499,6 +3060,10 @@ where
             }
 
             HandlerEvent::PutRecord { record, request_id } => {
+                if !self.inbound_request_allowed(source, connection, Some(request_id)) {
+                    return;
+                }
+
                 self.record_received(source, connection, request_id, record);
             }
 
@@ -2531,6 +3096,23 @@ where
                     }
                 }
             }
+
+            HandlerEvent::RpcCompleted(stats) => {
+                self.queued_events
+                    .push_back(ToSwarm::GenerateEvent(Event::RpcCompleted {
+                        peer: source,
+                        stats,
+                    }));
+            }
+
+            HandlerEvent::InboundRequestRefused(rpc_type) => {
+                self.queued_events.push_back(ToSwarm::GenerateEvent(
+                    Event::InboundRequestRefused {
+                        peer: source,
+                        rpc_type,
+                    },
+                ));
+            }
         };
     }
 
@@ -2558,6 +3140,31 @@ where
             self.add_provider_job = Some(job);
         }
 
+        // Run the provider record replication job, if the local node is a server.
+        if self.mode == Mode::Server {
+            if let Some(mut job) = self.provider_replication_job.take() {
+                let num = usize::min(JOBS_MAX_NEW_QUERIES, jobs_query_capacity);
+                for i in 0..num {
+                    let kbuckets = &mut self.kbuckets;
+                    let neighbours = || {
+                        let local_key = *kbuckets.local_key();
+                        kbuckets
+                            .closest_keys(&local_key)
+                            .take(K_VALUE.get())
+                            .map(|key| key.into_preimage())
+                            .collect()
+                    };
+                    if let Poll::Ready(r) = job.poll(cx, &mut self.store, now, neighbours) {
+                        self.start_replicate_provider(r)
+                    } else {
+                        jobs_query_capacity -= i;
+                        break;
+                    }
+                }
+                self.provider_replication_job = Some(job);
+            }
+        }
+
         // Run the periodic record replication / publication job.
         if let Some(mut job) = self.put_record_job.take() {
             let num = usize::min(JOBS_MAX_NEW_QUERIES, jobs_query_capacity);
@@ -2584,6 +3191,25 @@ where
             }
         }
 
+        // Report the health of the routing table periodically.
+        if let Some((interval, timer)) = self.health_report_interval.as_mut() {
+            if timer.poll_unpin(cx).is_ready() {
+                timer.reset(*interval);
+                let report = health::report(&mut self.kbuckets);
+                self.queued_events.push_back(ToSwarm::GenerateEvent(
+                    Event::RoutingTableHealthReport { report },
+                ));
+            }
+        }
+
+        // Report provider records the store evicted to make room for new ones.
+        while let Some(record) = self.store.next_evicted_provider() {
+            self.queued_events
+                .push_back(ToSwarm::GenerateEvent(Event::ProviderRecordEvicted {
+                    record,
+                }));
+        }
+
         loop {
             // Drain queued events first.
             if let Some(event) = self.queued_events.pop_front() {
@@ -2686,6 +3312,11 @@ where
             }
             FromSwarm::DialFailure(dial_failure) => self.on_dial_failure(dial_failure),
             FromSwarm::AddressChange(address_change) => self.on_address_change(address_change),
+            FromSwarm::NewExternalAddrOfPeer(NewExternalAddrOfPeer { peer_id, addr })
+                if self.learn_peer_addresses_from_swarm =>
+            {
+                self.add_address(&peer_id, addr.clone());
+            }
             FromSwarm::NewListenAddr(_) if self.connected_peers.is_empty() => {
                 // A new listen addr was just discovered and we have no connected peers,
                 // it can mean that our network interfaces were not up but they are now
@@ -2820,6 +3451,38 @@ pub enum Event {
     /// This happens in response to an external
     /// address being added or removed.
     ModeChanged { new_mode: Mode },
+
+    /// A request to or from a remote peer has completed.
+    ///
+    /// Only emitted if enabled via [`Config::set_rpc_stats_reporting`].
+    RpcCompleted {
+        /// The remote peer the request was exchanged with.
+        peer: PeerId,
+        /// Statistics about the request.
+        stats: RpcStats,
+    },
+
+    /// An inbound request was refused because too many inbound requests of the
+    /// same connection were waiting for an answer.
+    ///
+    /// See [`Config::set_max_concurrent_inbound_requests`].
+    InboundRequestRefused {
+        /// The remote peer that sent the request.
+        peer: PeerId,
+        /// The type of the request.
+        rpc_type: RpcType,
+    },
+
+    /// A provider record has been evicted from the [`RecordStore`] to make
+    /// room for new provider records.
+    ///
+    /// See [`store::ProviderEviction`].
+    ProviderRecordEvicted { record: ProviderRecord },
+
+    /// A summary of the routing table.
+    ///
+    /// Only emitted if enabled via [`Config::set_health_report_interval`].
+    RoutingTableHealthReport { report: RoutingTableHealth },
 }
 
 /// Information about progress events.
@@ -2928,9 +3591,10 @@ pub enum GetRecordOk {
         /// from closest to farthest. How many of these are tracked is configured
         /// by [`Config::set_caching`].
         ///
-        /// Writing back the cache at these peers is a manual operation.
-        /// ie. you may wish to use these candidates with [`Behaviour::put_record_to`]
-        /// after selecting one of the returned records.
+        /// Unless caching is [`Caching::Automatic`], writing back the cache at
+        /// these peers is a manual operation. ie. you may wish to use these
+        /// candidates with [`Behaviour::put_record_to`] after selecting one of
+        /// the returned records.
         cache_candidates: BTreeMap<kbucket::Distance, PeerId>,
     },
 }
@@ -2981,6 +3645,11 @@ pub type PutRecordResult = Result<PutRecordOk, PutRecordError>;
 #[derive(Debug, Clone)]
 pub struct PutRecordOk {
     pub key: record::Key,
+    /// [`PeerId`]s of the peers the record was successfully stored on.
+    pub success: Vec<PeerId>,
+    /// [`PeerId`]s of the peers the record could not be stored on or that did
+    /// not confirm storing the record before the query finished.
+    pub failed: Vec<PeerId>,
 }
 
 /// The error result of [`Behaviour::put_record`].
@@ -2991,6 +3660,8 @@ pub enum PutRecordError {
         key: record::Key,
         /// [`PeerId`]s of the peers the record was successfully stored on.
         success: Vec<PeerId>,
+        /// [`PeerId`]s of the peers the record could not be stored on.
+        failed: Vec<PeerId>,
         quorum: NonZeroUsize,
     },
     #[error("the request timed out")]
@@ -2998,6 +3669,9 @@ pub enum PutRecordError {
         key: record::Key,
         /// [`PeerId`]s of the peers the record was successfully stored on.
         success: Vec<PeerId>,
+        /// [`PeerId`]s of the peers the record could not be stored on or that
+        /// did not confirm storing the record before the timeout.
+        failed: Vec<PeerId>,
         quorum: NonZeroUsize,
     },
 }
@@ -3019,6 +3693,24 @@ impl PutRecordError {
             PutRecordError::Timeout { key, .. } => key,
         }
     }
+
+    /// Gets the peers the record was successfully stored on.
+    pub fn success(&self) -> &[PeerId] {
+        match self {
+            PutRecordError::QuorumFailed { success, .. } => success,
+            PutRecordError::Timeout { success, .. } => success,
+        }
+    }
+
+    /// Gets the peers the record could not be stored on.
+    ///
+    /// The record can be stored on these peers again via [`Behaviour::put_record_to`].
+    pub fn failed(&self) -> &[PeerId] {
+        match self {
+            PutRecordError::QuorumFailed { failed, .. } => failed,
+            PutRecordError::Timeout { failed, .. } => failed,
+        }
+    }
 }
 
 /// The result of [`Behaviour::bootstrap`].
@@ -3029,6 +3721,10 @@ pub type BootstrapResult = Result<BootstrapOk, BootstrapError>;
 pub struct BootstrapOk {
     pub peer: PeerId,
     pub num_remaining: u32,
+    /// The number of buckets refreshed so far.
+    pub buckets_refreshed: u32,
+    /// The number of peers inserted into the routing table since bootstrapping started.
+    pub peers_added: usize,
 }
 
 /// The error result of [`Behaviour::bootstrap`].
@@ -3039,6 +3735,13 @@ pub enum BootstrapError {
         peer: PeerId,
         num_remaining: Option<u32>,
     },
+    /// None of the contacted peers responded. Bootstrapping is resumed by the next
+    /// call to [`Behaviour::bootstrap`].
+    #[error("no peer responded to the request")]
+    Interrupted {
+        peer: PeerId,
+        num_remaining: Option<u32>,
+    },
 }
 
 /// The result of [`Behaviour::get_closest_peers`].
@@ -3171,6 +3874,9 @@ pub enum AddProviderContext {
     /// The context is periodic republishing of provider announcements
     /// initiated earlier via [`Behaviour::start_providing`].
     Republish,
+    /// The context is re-announcing a provider record of another peer
+    /// on churn, see [`Config::set_provider_replication_churn_threshold`].
+    Replicate,
 }
 
 /// The context of a [`QueryInfo::PutRecord`] query.
@@ -3187,6 +3893,9 @@ pub enum PutRecordContext {
     /// The context is a custom store operation targeting specific
     /// peers initiated by [`Behaviour::put_record_to`].
     Custom,
+    /// The context is the automatic caching of a record found by
+    /// [`Behaviour::get_record`], see [`Caching::Automatic`].
+    Cache,
 }
 
 /// Information about a running query.
@@ -3258,6 +3967,9 @@ pub enum QueryInfo {
         /// The peers closest to the `key` that were queried but did not return a record,
         /// i.e. the peers that are candidates for caching the record.
         cache_candidates: BTreeMap<kbucket::Distance, PeerId>,
+        /// The record to store at the `cache_candidates` once the query finishes,
+        /// if caching is [`Caching::Automatic`].
+        cache_record: Option<Record>,
     },
 }
 
@@ -23,7 +23,7 @@
 use super::*;
 
 use crate::record::{store::MemoryStore, Key};
-use crate::{K_VALUE, PROTOCOL_NAME, SHA_256_MH};
+use crate::{RpcDirection, RpcType, K_VALUE, PROTOCOL_NAME, SHA_256_MH};
 use futures::{executor::block_on, future::poll_fn, prelude::*};
 use futures_timer::Delay;
 use libp2p_core::{
@@ -233,6 +233,64 @@ fn bootstrap() {
     QuickCheck::new().tests(10).quickcheck(prop as fn(_) -> _)
 }
 
+#[test]
+fn bootstrap_resumes_after_interruption() {
+    let mut cfg = Config::new(PROTOCOL_NAME);
+    cfg.set_periodic_bootstrap_interval(None);
+    cfg.set_automatic_bootstrap_throttle(None);
+    let (_, mut swarm) = build_node_with_config(cfg);
+
+    // None of the known peers is reachable.
+    for _ in 0..3 {
+        swarm
+            .behaviour_mut()
+            .add_address(&PeerId::random(), Protocol::Udp(10u16).into());
+    }
+
+    let qid = swarm.behaviour_mut().bootstrap().unwrap();
+    block_on(poll_fn(|ctx| loop {
+        match swarm.poll_next_unpin(ctx) {
+            Poll::Ready(Some(SwarmEvent::Behaviour(Event::OutboundQueryProgressed {
+                id,
+                result: QueryResult::Bootstrap(result),
+                step,
+                ..
+            }))) => {
+                assert_eq!(id, qid);
+                assert!(step.last);
+                assert!(matches!(
+                    result,
+                    Err(BootstrapError::Interrupted {
+                        num_remaining: None,
+                        ..
+                    })
+                ));
+                return Poll::Ready(());
+            }
+            // Ignore any other event.
+            Poll::Ready(Some(_)) => (),
+            e @ Poll::Ready(_) => panic!("Unexpected return value: {e:?}"),
+            Poll::Pending => return Poll::Pending,
+        }
+    }));
+    assert_eq!(swarm.behaviour().interrupted_bootstrap, Some(Vec::new()));
+
+    // Bootstrapping resumes with the bucket refreshes that were left.
+    let target = kbucket::Key::from(PeerId::random());
+    swarm.behaviour_mut().interrupted_bootstrap = Some(vec![target]);
+    let qid = swarm.behaviour_mut().bootstrap().unwrap();
+    match &swarm.behaviour().queries.get(&qid).unwrap().info {
+        QueryInfo::Bootstrap {
+            peer, remaining, ..
+        } => {
+            assert_eq!(peer, target.preimage());
+            assert_eq!(remaining.as_ref().map(|r| r.len()), Some(0));
+        }
+        info => panic!("Unexpected query {info:?}"),
+    }
+    assert!(swarm.behaviour().interrupted_bootstrap.is_none());
+}
+
 #[test]
 fn query_iter() {
     fn distances<K>(key: &kbucket::Key<K>, peers: Vec<PeerId>) -> Vec<Distance> {
@@ -318,6 +376,216 @@ fn query_iter() {
     }
 }
 
+#[test]
+fn rpc_stats_reported_for_both_directions() {
+    let mut config = Config::new(PROTOCOL_NAME);
+    config.set_periodic_bootstrap_interval(None);
+    config.set_automatic_bootstrap_throttle(None);
+    config.set_rpc_stats_reporting(true);
+    let mut swarms = build_connected_nodes_with_config(2, 1, config)
+        .into_iter()
+        .map(|(_a, s)| s)
+        .collect::<Vec<_>>();
+    let remote_peer_id = *swarms[1].local_peer_id();
+
+    swarms[0]
+        .behaviour_mut()
+        .get_closest_peers(PeerId::random());
+
+    let mut outbound = None;
+    let mut inbound = None;
+    block_on(poll_fn(move |ctx| {
+        for swarm in swarms.iter_mut() {
+            loop {
+                match swarm.poll_next_unpin(ctx) {
+                    Poll::Ready(Some(SwarmEvent::Behaviour(Event::RpcCompleted {
+                        peer,
+                        stats,
+                    }))) => match stats.direction {
+                        RpcDirection::Outbound => outbound = Some((peer, stats)),
+                        RpcDirection::Inbound => inbound = Some(stats),
+                    },
+                    // Ignore any other event.
+                    Poll::Ready(Some(_)) => (),
+                    e @ Poll::Ready(_) => panic!("Unexpected return value: {e:?}"),
+                    Poll::Pending => break,
+                }
+            }
+        }
+
+        if let (Some((peer, outbound)), Some(inbound)) = (&outbound, &inbound) {
+            assert_eq!(*peer, remote_peer_id);
+            assert_eq!(outbound.rpc_type, RpcType::FindNode);
+            assert_eq!(inbound.rpc_type, RpcType::FindNode);
+            assert!(outbound.success && inbound.success);
+            assert_eq!(outbound.request_size, inbound.request_size);
+            assert_eq!(outbound.response_size, inbound.response_size);
+            return Poll::Ready(());
+        }
+
+        Poll::Pending
+    }))
+}
+
+#[test]
+fn inbound_requests_exceeding_limit_are_refused() {
+    let mut config = Config::new(PROTOCOL_NAME);
+    config.set_periodic_bootstrap_interval(None);
+    config.set_automatic_bootstrap_throttle(None);
+    // Records need to be stored manually, thus `PUT_VALUE` requests are left unanswered.
+    config.set_record_filtering(StoreInserts::FilterBoth);
+    config.set_max_concurrent_inbound_requests(NonZeroUsize::new(1));
+    let mut swarms = build_connected_nodes_with_config(2, 1, config)
+        .into_iter()
+        .map(|(_a, s)| s)
+        .collect::<Vec<_>>();
+    let local_peer_id = *swarms[0].local_peer_id();
+    let remote_peer_id = *swarms[1].local_peer_id();
+
+    for _ in 0..2 {
+        let record = Record::new(random_multihash(), vec![4, 5, 6]);
+        swarms[0].behaviour_mut().put_record_to(
+            record,
+            std::iter::once(remote_peer_id),
+            Quorum::One,
+        );
+    }
+
+    block_on(poll_fn(move |ctx| {
+        for swarm in swarms.iter_mut() {
+            loop {
+                match swarm.poll_next_unpin(ctx) {
+                    Poll::Ready(Some(SwarmEvent::Behaviour(Event::InboundRequestRefused {
+                        peer,
+                        rpc_type,
+                    }))) => {
+                        assert_eq!(peer, local_peer_id);
+                        assert_eq!(rpc_type, RpcType::PutValue);
+                        return Poll::Ready(());
+                    }
+                    // Ignore any other event.
+                    Poll::Ready(Some(_)) => (),
+                    e @ Poll::Ready(_) => panic!("Unexpected return value: {e:?}"),
+                    Poll::Pending => break,
+                }
+            }
+        }
+
+        Poll::Pending
+    }))
+}
+
+#[test]
+fn put_record_to_reports_receipts() {
+    let mut swarms = build_connected_nodes(2, 1)
+        .into_iter()
+        .map(|(_a, s)| s)
+        .collect::<Vec<_>>();
+    let remote_peer_id = *swarms[1].local_peer_id();
+    // A peer without any known address, thus failing to be dialed.
+    let unreachable_peer_id = PeerId::random();
+
+    let record = Record::new(random_multihash(), vec![4, 5, 6]);
+    let qid = swarms[0].behaviour_mut().put_record_to(
+        record,
+        vec![remote_peer_id, unreachable_peer_id].into_iter(),
+        Quorum::All,
+    );
+
+    block_on(poll_fn(move |ctx| {
+        for swarm in swarms.iter_mut() {
+            loop {
+                match swarm.poll_next_unpin(ctx) {
+                    Poll::Ready(Some(SwarmEvent::Behaviour(Event::OutboundQueryProgressed {
+                        id,
+                        result: QueryResult::PutRecord(result),
+                        ..
+                    }))) => {
+                        assert_eq!(id, qid);
+                        match result {
+                            Err(PutRecordError::QuorumFailed {
+                                success, failed, ..
+                            }) => {
+                                assert_eq!(success, vec![remote_peer_id]);
+                                assert_eq!(failed, vec![unreachable_peer_id]);
+                            }
+                            other => panic!("Unexpected result: {other:?}"),
+                        }
+                        return Poll::Ready(());
+                    }
+                    // Ignore any other event.
+                    Poll::Ready(Some(_)) => (),
+                    e @ Poll::Ready(_) => panic!("Unexpected return value: {e:?}"),
+                    Poll::Pending => break,
+                }
+            }
+        }
+
+        Poll::Pending
+    }))
+}
+
+#[test]
+fn advertised_addresses_filter() {
+    let mut config = Config::new(PROTOCOL_NAME);
+    config.set_advertised_addresses_filter(|addr, kind| {
+        kind == LocalAddressKind::ConfirmedExternal
+            && !addr.iter().any(|p| p == Protocol::P2pCircuit)
+    });
+    // Not using a swarm, which would confirm its listen address as external.
+    let local_id = PeerId::random();
+    let mut behaviour = Behaviour::with_config(local_id, MemoryStore::new(local_id), config);
+
+    let public: Multiaddr = "/ip4/1.2.3.4/tcp/4001".parse().unwrap();
+    let relayed: Multiaddr = format!("/ip4/5.6.7.8/tcp/4001/p2p/{}/p2p-circuit", PeerId::random())
+        .parse()
+        .unwrap();
+    for addr in [&public, &relayed] {
+        behaviour.on_swarm_event(FromSwarm::ExternalAddrConfirmed(
+            swarm::behaviour::ExternalAddrConfirmed {
+                addr,
+                source: swarm::ExternalAddressSource::Confirmed,
+            },
+        ));
+    }
+
+    let key = Key::from(random_multihash());
+    behaviour.start_providing(key.clone()).unwrap();
+    let providers = behaviour.provider_peers(&key, &PeerId::random());
+
+    assert_eq!(providers.len(), 1);
+    assert_eq!(providers[0].multiaddrs, vec![public]);
+}
+
+#[test]
+fn external_addresses_are_advertised_by_rank() {
+    fn by_confidence(a: &swarm::ScoredAddress, b: &swarm::ScoredAddress) -> std::cmp::Ordering {
+        b.confidence().cmp(&a.confidence())
+    }
+    let mut config = Config::new(PROTOCOL_NAME);
+    config.set_external_address_ranking(by_confidence);
+    let (_, mut swarm) = build_node_with_config(config);
+    let behaviour = swarm.behaviour_mut();
+
+    let often: Multiaddr = "/ip4/1.2.3.4/tcp/4001".parse().unwrap();
+    let once: Multiaddr = "/ip4/5.6.7.8/tcp/4001".parse().unwrap();
+    for addr in [&often, &often, &once] {
+        behaviour.on_swarm_event(FromSwarm::ExternalAddrConfirmed(
+            swarm::behaviour::ExternalAddrConfirmed {
+                addr,
+                source: swarm::ExternalAddressSource::Confirmed,
+            },
+        ));
+    }
+
+    let key = Key::from(random_multihash());
+    behaviour.start_providing(key.clone()).unwrap();
+    let providers = behaviour.provider_peers(&key, &PeerId::random());
+
+    assert_eq!(providers.len(), 1);
+    assert_eq!(providers[0].multiaddrs[..2], [often, once]);
+}
+
 #[test]
 fn unresponsive_not_returned_direct() {
     let _ = tracing_subscriber::fmt()
@@ -864,6 +1132,55 @@ fn get_record() {
     }))
 }
 
+#[test]
+fn get_record_caches_automatically() {
+    let mut cfg = Config::new(PROTOCOL_NAME);
+    cfg.set_caching(Caching::Automatic { max_peers: 1 });
+    let mut swarms = build_nodes_with_config(3, cfg);
+
+    // Let first peer know of second peer and second peer know of third peer.
+    for i in 0..2 {
+        let (peer_id, address) = (
+            *Swarm::local_peer_id(&swarms[i + 1].1),
+            swarms[i + 1].0.clone(),
+        );
+        swarms[i].1.behaviour_mut().add_address(&peer_id, address);
+    }
+
+    // Drop the swarm addresses.
+    let mut swarms = swarms
+        .into_iter()
+        .map(|(_addr, swarm)| swarm)
+        .collect::<Vec<_>>();
+
+    let record = Record::new(random_multihash(), vec![4, 5, 6]);
+
+    swarms[2].behaviour_mut().store.put(record.clone()).unwrap();
+    swarms[0].behaviour_mut().get_record(record.key.clone());
+
+    // The second peer did not return the record and should eventually
+    // have it cached.
+    block_on(poll_fn(move |ctx| {
+        for swarm in &mut swarms {
+            loop {
+                match swarm.poll_next_unpin(ctx) {
+                    Poll::Ready(Some(_)) => (),
+                    e @ Poll::Ready(_) => panic!("Unexpected return value: {e:?}"),
+                    Poll::Pending => break,
+                }
+            }
+        }
+
+        match swarms[1].behaviour_mut().store.get(&record.key) {
+            Some(cached) => {
+                assert_eq!(cached.value, record.value);
+                Poll::Ready(())
+            }
+            None => Poll::Pending,
+        }
+    }))
+}
+
 #[test]
 fn get_record_many() {
     // TODO: Randomise
@@ -1445,6 +1762,45 @@ fn network_behaviour_on_address_change() {
     );
 }
 
+#[test]
+fn learn_peer_addresses_from_swarm() {
+    let remote_peer_id = PeerId::random();
+    let remote_address: Multiaddr = Protocol::Memory(1).into();
+    let connection_id = ConnectionId::new_unchecked(0);
+
+    for enabled in [false, true] {
+        let local_peer_id = PeerId::random();
+        let mut config = Config::new(PROTOCOL_NAME);
+        config.set_learn_peer_addresses_from_swarm(enabled);
+        let mut kademlia =
+            Behaviour::with_config(local_peer_id, MemoryStore::new(local_peer_id), config);
+
+        // Mimick another behaviour, e.g. mDNS, reporting the address of a peer.
+        kademlia.on_swarm_event(FromSwarm::NewExternalAddrOfPeer(NewExternalAddrOfPeer {
+            peer_id: remote_peer_id,
+            addr: &remote_address,
+        }));
+
+        let addresses = kademlia
+            .handle_pending_outbound_connection(
+                connection_id,
+                Some(remote_peer_id),
+                &[],
+                Endpoint::Dialer,
+            )
+            .unwrap();
+        if enabled {
+            assert_eq!(
+                addresses,
+                vec![remote_address.clone().with_p2p(remote_peer_id).unwrap()]
+            );
+            assert!(kademlia.kbucket(remote_peer_id).is_some());
+        } else {
+            assert!(addresses.is_empty());
+        }
+    }
+}
+
 #[test]
 fn get_providers_single() {
     fn prop(key: record::Key) {
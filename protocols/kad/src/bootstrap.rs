@@ -142,6 +142,15 @@ impl Status {
     }
 }
 
+/// Progress of the current bootstrap, kept when an interrupted bootstrap is resumed.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Progress {
+    /// Number of bucket refreshes that completed.
+    pub(crate) buckets_refreshed: u32,
+    /// Number of peers inserted into the routing table.
+    pub(crate) peers_added: usize,
+}
+
 /// Simple enum to indicate when the throttle timer resolves.
 /// A dedicated `Immediate` variant is necessary because creating
 /// `Delay::new(Duration::ZERO)` does not always actually resolve
@@ -35,10 +35,12 @@ use libp2p_swarm::{
     ConnectionHandler, ConnectionHandlerEvent, Stream, StreamUpgradeError, SubstreamProtocol,
     SupportedProtocols,
 };
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroUsize;
 use std::task::Waker;
 use std::time::Duration;
 use std::{error, fmt, io, marker::PhantomData, pin::Pin, task::Context, task::Poll};
+use web_time::Instant;
 
 const MAX_NUM_STREAMS: usize = 32;
 
@@ -85,6 +87,26 @@ pub struct Handler {
     protocol_status: Option<ProtocolStatus>,
 
     remote_supported_protocols: SupportedProtocols,
+
+    /// Whether to report [`RpcStats`] for every completed request.
+    report_rpc_stats: bool,
+
+    /// The maximum number of inbound requests waiting for an answer. Further requests are
+    /// refused.
+    max_concurrent_inbound_requests: Option<NonZeroUsize>,
+
+    /// Type, encoded request size and start time of the active outbound requests.
+    ///
+    /// Only tracked if `report_rpc_stats` is enabled.
+    outbound_rpcs: HashMap<QueryId, (RpcType, usize, Instant)>,
+
+    /// Type, encoded request size and start time of the inbound requests waiting for an answer.
+    ///
+    /// Only tracked if `report_rpc_stats` is enabled.
+    inbound_rpcs: HashMap<UniqueConnecId, (RpcType, usize, Instant)>,
+
+    /// Stats of completed requests that have yet to be reported to the behaviour.
+    pending_rpc_stats: VecDeque<RpcStats>,
 }
 
 /// The states of protocol confirmation that a connection
@@ -271,6 +293,82 @@ pub enum HandlerEvent {
         /// The user data passed to the `PutValue`.
         query_id: QueryId,
     },
+
+    /// A request to or from the remote has completed.
+    RpcCompleted(RpcStats),
+
+    /// An inbound request of the remote was refused because too many inbound requests were
+    /// waiting for an answer already.
+    InboundRequestRefused(RpcType),
+}
+
+impl HandlerEvent {
+    /// The id and type of the inbound request awaiting an answer reported by this event.
+    fn inbound_request(&self) -> Option<(RequestId, RpcType)> {
+        match self {
+            HandlerEvent::FindNodeReq { request_id, .. } => Some((*request_id, RpcType::FindNode)),
+            HandlerEvent::GetProvidersReq { request_id, .. } => {
+                Some((*request_id, RpcType::GetProviders))
+            }
+            HandlerEvent::GetRecord { request_id, .. } => Some((*request_id, RpcType::GetValue)),
+            HandlerEvent::PutRecord { request_id, .. } => Some((*request_id, RpcType::PutValue)),
+            _ => None,
+        }
+    }
+}
+
+/// Statistics about a single Kademlia request-response exchange with a remote peer.
+#[derive(Debug, Clone)]
+pub struct RpcStats {
+    /// Whether the request was sent by the local or the remote node.
+    pub direction: RpcDirection,
+    /// The type of the request.
+    pub rpc_type: RpcType,
+    /// Whether the exchange succeeded.
+    ///
+    /// Outbound requests fail if the stream fails or times out.
+    /// Inbound requests fail if the local node resets the stream instead of answering.
+    pub success: bool,
+    /// Size of the encoded request in bytes.
+    pub request_size: usize,
+    /// Size of the encoded response in bytes. `0` if no response was exchanged.
+    pub response_size: usize,
+    /// Time between receiving the request and answering it for inbound requests,
+    /// or between sending the request and receiving the response for outbound requests.
+    pub duration: Duration,
+}
+
+/// The direction of a Kademlia request.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RpcDirection {
+    /// The request was sent by the remote.
+    Inbound,
+    /// The request was sent by the local node.
+    Outbound,
+}
+
+/// The type of a Kademlia request.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RpcType {
+    Ping,
+    FindNode,
+    GetProviders,
+    AddProvider,
+    GetValue,
+    PutValue,
+}
+
+impl From<&KadRequestMsg> for RpcType {
+    fn from(msg: &KadRequestMsg) -> Self {
+        match msg {
+            KadRequestMsg::Ping => RpcType::Ping,
+            KadRequestMsg::FindNode { .. } => RpcType::FindNode,
+            KadRequestMsg::GetProviders { .. } => RpcType::GetProviders,
+            KadRequestMsg::AddProvider { .. } => RpcType::AddProvider,
+            KadRequestMsg::GetValue { .. } => RpcType::GetValue,
+            KadRequestMsg::PutValue { .. } => RpcType::PutValue,
+        }
+    }
 }
 
 /// Error that can happen when requesting an RPC query.
@@ -420,7 +518,7 @@ pub struct RequestId {
 }
 
 /// Unique identifier for a connection.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 struct UniqueConnecId(u64);
 
 impl Handler {
@@ -429,6 +527,8 @@ impl Handler {
         endpoint: ConnectedPoint,
         remote_peer_id: PeerId,
         mode: Mode,
+        report_rpc_stats: bool,
+        max_concurrent_inbound_requests: Option<NonZeroUsize>,
     ) -> Self {
         match &endpoint {
             ConnectedPoint::Dialer { .. } => {
@@ -462,6 +562,11 @@ impl Handler {
             pending_messages: Default::default(),
             protocol_status: None,
             remote_supported_protocols: Default::default(),
+            report_rpc_stats,
+            max_concurrent_inbound_requests,
+            outbound_rpcs: Default::default(),
+            inbound_rpcs: Default::default(),
+            pending_rpc_stats: Default::default(),
         }
     }
 
@@ -550,6 +655,10 @@ impl Handler {
 
     /// Takes the given [`KadRequestMsg`] and composes it into an outbound request-response protocol handshake using a [`oneshot::channel`].
     fn queue_new_stream(&mut self, id: QueryId, msg: KadRequestMsg) {
+        let rpc = self
+            .report_rpc_stats
+            .then(|| (RpcType::from(&msg), msg.encoded_len()));
+
         let (sender, receiver) = oneshot::channel();
 
         self.pending_streams.push_back(sender);
@@ -559,7 +668,9 @@ impl Handler {
                     .await
                     .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?
                     .map_err(|e| match e {
-                        StreamUpgradeError::Timeout => io::ErrorKind::TimedOut.into(),
+                        StreamUpgradeError::Timeout | StreamUpgradeError::NegotiationTimeout => {
+                            io::ErrorKind::TimedOut.into()
+                        }
                         StreamUpgradeError::Apply(e) => e,
                         StreamUpgradeError::NegotiationFailed => io::Error::new(
                             io::ErrorKind::ConnectionRefused,
@@ -588,6 +699,12 @@ impl Handler {
             result.is_ok(),
             "Expected to not create more streams than allowed"
         );
+
+        // Only track requests that are actually sent, anything else would never be removed.
+        if let (Ok(()), Some((rpc_type, request_size))) = (result, rpc) {
+            self.outbound_rpcs
+                .insert(id, (rpc_type, request_size, Instant::now()));
+        }
     }
 }
 
@@ -609,17 +726,19 @@ impl ConnectionHandler for Handler {
     fn on_behaviour_event(&mut self, message: HandlerIn) {
         match message {
             HandlerIn::Reset(request_id) => {
-                if let Some(state) = self
-                    .inbound_substreams
-                    .iter_mut()
-                    .find(|state| match state {
-                        InboundSubstreamState::WaitingBehaviour(conn_id, _, _) => {
-                            conn_id == &request_id.connec_unique_id
-                        }
-                        _ => false,
-                    })
+                self.close_inbound_substream(request_id);
+
+                if let Some((rpc_type, request_size, started)) =
+                    self.inbound_rpcs.remove(&request_id.connec_unique_id)
                 {
-                    state.close();
+                    self.pending_rpc_stats.push_back(RpcStats {
+                        direction: RpcDirection::Inbound,
+                        rpc_type,
+                        success: false,
+                        request_size,
+                        response_size: 0,
+                        duration: started.elapsed(),
+                    });
                 }
             }
             HandlerIn::FindNodeReq { key, query_id } => {
@@ -714,6 +833,12 @@ impl ConnectionHandler for Handler {
         ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
     > {
         loop {
+            if let Some(stats) = self.pending_rpc_stats.pop_front() {
+                return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                    HandlerEvent::RpcCompleted(stats),
+                ));
+            }
+
             match &mut self.protocol_status {
                 Some(status) if !status.reported => {
                     status.reported = true;
@@ -734,33 +859,53 @@ impl ConnectionHandler for Handler {
 
             match self.outbound_substreams.poll_unpin(cx) {
                 Poll::Ready((Ok(Ok(Some(response))), query_id)) => {
+                    self.on_outbound_rpc_completed(query_id, Some(&response));
                     return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
                         process_kad_response(response, query_id),
-                    ))
+                    ));
                 }
-                Poll::Ready((Ok(Ok(None)), _)) => {
+                Poll::Ready((Ok(Ok(None)), query_id)) => {
+                    self.on_outbound_rpc_completed(query_id, None);
                     continue;
                 }
                 Poll::Ready((Ok(Err(e)), query_id)) => {
+                    self.on_outbound_rpc_failed(query_id);
                     return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
                         HandlerEvent::QueryError {
                             error: HandlerQueryErr::Io(e),
                             query_id,
                         },
-                    ))
+                    ));
                 }
                 Poll::Ready((Err(_timeout), query_id)) => {
+                    self.on_outbound_rpc_failed(query_id);
                     return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
                         HandlerEvent::QueryError {
                             error: HandlerQueryErr::Io(io::ErrorKind::TimedOut.into()),
                             query_id,
                         },
-                    ))
+                    ));
                 }
                 Poll::Pending => {}
             }
 
             if let Poll::Ready(Some(event)) = self.inbound_substreams.poll_next_unpin(cx) {
+                if let ConnectionHandlerEvent::NotifyBehaviour(event) = &event {
+                    if let Some((request_id, rpc_type)) = event.inbound_request() {
+                        if self.inbound_requests_exceeded() {
+                            tracing::debug!(
+                                peer=%self.remote_peer_id,
+                                ?rpc_type,
+                                "Refusing inbound request exceeding the limit of concurrent requests"
+                            );
+                            self.close_inbound_substream(request_id);
+                            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                                HandlerEvent::InboundRequestRefused(rpc_type),
+                            ));
+                        }
+                    }
+                    self.on_inbound_request(event);
+                }
                 return Poll::Ready(event);
             }
 
@@ -852,7 +997,59 @@ fn compute_new_protocol_status(
 }
 
 impl Handler {
+    /// Closes the inbound substream of the given request, signaling an error to the remote.
+    fn close_inbound_substream(&mut self, request_id: RequestId) {
+        if let Some(state) = self
+            .inbound_substreams
+            .iter_mut()
+            .find(|state| match state {
+                InboundSubstreamState::WaitingBehaviour(conn_id, _, _) => {
+                    conn_id == &request_id.connec_unique_id
+                }
+                _ => false,
+            })
+        {
+            state.close();
+        }
+    }
+
+    /// Whether more inbound requests are waiting for an answer than allowed, including the
+    /// request received last.
+    fn inbound_requests_exceeded(&self) -> bool {
+        let Some(max) = self.max_concurrent_inbound_requests else {
+            return false;
+        };
+
+        let pending = self
+            .inbound_substreams
+            .iter()
+            .filter(|state| {
+                matches!(
+                    state,
+                    InboundSubstreamState::WaitingBehaviour(..)
+                        | InboundSubstreamState::PendingSend(..)
+                        | InboundSubstreamState::PendingFlush(..)
+                )
+            })
+            .count();
+
+        pending > max.get()
+    }
+
     fn answer_pending_request(&mut self, request_id: RequestId, mut msg: KadResponseMsg) {
+        if let Some((rpc_type, request_size, started)) =
+            self.inbound_rpcs.remove(&request_id.connec_unique_id)
+        {
+            self.pending_rpc_stats.push_back(RpcStats {
+                direction: RpcDirection::Inbound,
+                rpc_type,
+                success: true,
+                request_size,
+                response_size: msg.encoded_len(),
+                duration: started.elapsed(),
+            });
+        }
+
         for state in self.inbound_substreams.iter_mut() {
             match state.try_answer_with(request_id, msg) {
                 Ok(()) => return,
@@ -864,6 +1061,80 @@ impl Handler {
 
         debug_assert!(false, "Cannot find inbound substream for {request_id:?}")
     }
+
+    /// Starts tracking the stats of an inbound request reported to the behaviour.
+    fn on_inbound_request(&mut self, event: &HandlerEvent) {
+        if !self.report_rpc_stats {
+            return;
+        }
+
+        let (request, request_id) = match event {
+            HandlerEvent::FindNodeReq { key, request_id } => {
+                (KadRequestMsg::FindNode { key: key.clone() }, *request_id)
+            }
+            HandlerEvent::GetProvidersReq { key, request_id } => (
+                KadRequestMsg::GetProviders { key: key.clone() },
+                *request_id,
+            ),
+            HandlerEvent::GetRecord { key, request_id } => {
+                (KadRequestMsg::GetValue { key: key.clone() }, *request_id)
+            }
+            HandlerEvent::PutRecord { record, request_id } => (
+                KadRequestMsg::PutValue {
+                    record: record.clone(),
+                },
+                *request_id,
+            ),
+            HandlerEvent::AddProvider { key, provider } => {
+                // `AddProvider` requests are never answered.
+                let request = KadRequestMsg::AddProvider {
+                    key: key.clone(),
+                    provider: provider.clone(),
+                };
+                self.pending_rpc_stats.push_back(RpcStats {
+                    direction: RpcDirection::Inbound,
+                    rpc_type: RpcType::AddProvider,
+                    success: true,
+                    request_size: request.encoded_len(),
+                    response_size: 0,
+                    duration: Duration::ZERO,
+                });
+                return;
+            }
+            _ => return,
+        };
+
+        self.inbound_rpcs.insert(
+            request_id.connec_unique_id,
+            ((&request).into(), request.encoded_len(), Instant::now()),
+        );
+    }
+
+    fn on_outbound_rpc_completed(&mut self, query_id: QueryId, response: Option<&KadResponseMsg>) {
+        if let Some((rpc_type, request_size, started)) = self.outbound_rpcs.remove(&query_id) {
+            self.pending_rpc_stats.push_back(RpcStats {
+                direction: RpcDirection::Outbound,
+                rpc_type,
+                success: true,
+                request_size,
+                response_size: response.map(KadResponseMsg::encoded_len).unwrap_or(0),
+                duration: started.elapsed(),
+            });
+        }
+    }
+
+    fn on_outbound_rpc_failed(&mut self, query_id: QueryId) {
+        if let Some((rpc_type, request_size, started)) = self.outbound_rpcs.remove(&query_id) {
+            self.pending_rpc_stats.push_back(RpcStats {
+                direction: RpcDirection::Outbound,
+                rpc_type,
+                success: false,
+                request_size,
+                response_size: 0,
+                duration: started.elapsed(),
+            });
+        }
+    }
 }
 
 impl futures::Stream for InboundSubstreamState {
@@ -0,0 +1,306 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Summary of the composition of the routing table.
+
+use crate::kbucket::{self, Distance, KBucketsTable, NodeStatus};
+use crate::{Addresses, K_VALUE};
+use libp2p_core::multiaddr::{Multiaddr, Protocol};
+use libp2p_identity::PeerId;
+use std::collections::HashMap;
+
+/// A summary of the routing table, see [`Behaviour::routing_table_health`](crate::Behaviour::routing_table_health).
+#[derive(Debug, Clone, Default)]
+pub struct RoutingTableHealth {
+    num_peers: usize,
+    num_connected_peers: usize,
+    num_ipv4_only_peers: usize,
+    num_ipv6_only_peers: usize,
+    num_dual_stack_peers: usize,
+    num_peers_without_ip: usize,
+    peers_per_transport: HashMap<AddressTransport, usize>,
+    buckets: Vec<BucketHealth>,
+    bucket_size: usize,
+    estimated_network_size: Option<u64>,
+}
+
+impl RoutingTableHealth {
+    /// Number of peers in the routing table.
+    pub fn num_peers(&self) -> usize {
+        self.num_peers
+    }
+
+    /// Number of peers in the routing table that are considered connected.
+    pub fn num_connected_peers(&self) -> usize {
+        self.num_connected_peers
+    }
+
+    /// Number of peers with IPv4 but no IPv6 addresses.
+    pub fn num_ipv4_only_peers(&self) -> usize {
+        self.num_ipv4_only_peers
+    }
+
+    /// Number of peers with IPv6 but no IPv4 addresses.
+    pub fn num_ipv6_only_peers(&self) -> usize {
+        self.num_ipv6_only_peers
+    }
+
+    /// Number of peers with both IPv4 and IPv6 addresses.
+    pub fn num_dual_stack_peers(&self) -> usize {
+        self.num_dual_stack_peers
+    }
+
+    /// Number of peers without any IP address, e.g. only known via DNS or relayed addresses.
+    pub fn num_peers_without_ip(&self) -> usize {
+        self.num_peers_without_ip
+    }
+
+    /// Number of peers with at least one address using the given transport.
+    pub fn num_peers_with_transport(&self, transport: AddressTransport) -> usize {
+        self.peers_per_transport
+            .get(&transport)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Fill levels of the non-empty buckets, ordered by proximity to the local key.
+    pub fn buckets(&self) -> &[BucketHealth] {
+        &self.buckets
+    }
+
+    /// Maximal number of peers a bucket can contain.
+    pub fn bucket_size(&self) -> usize {
+        self.bucket_size
+    }
+
+    /// Estimated number of peers in the network, based on the distances of the
+    /// closest peers to the local key.
+    ///
+    /// `None` if the routing table is empty.
+    pub fn estimated_network_size(&self) -> Option<u64> {
+        self.estimated_network_size
+    }
+}
+
+/// The fill level of a single bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BucketHealth {
+    index: u32,
+    num_entries: usize,
+    num_connected: usize,
+    has_pending: bool,
+}
+
+impl BucketHealth {
+    /// Index of the bucket, i.e. the integer part of the base 2 logarithm of
+    /// the distances it covers.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Number of peers in the bucket.
+    pub fn num_entries(&self) -> usize {
+        self.num_entries
+    }
+
+    /// Number of peers in the bucket that are considered connected.
+    pub fn num_connected(&self) -> usize {
+        self.num_connected
+    }
+
+    /// Whether a peer is waiting to be inserted into the full bucket.
+    pub fn has_pending(&self) -> bool {
+        self.has_pending
+    }
+}
+
+/// The transport of an address in the routing table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AddressTransport {
+    Tcp,
+    Quic,
+    WebSocket,
+    WebRtc,
+    WebTransport,
+    /// Relayed addresses, i.e. containing `/p2p-circuit`.
+    Relayed,
+    Other,
+}
+
+impl AddressTransport {
+    fn of(addr: &Multiaddr) -> Self {
+        let mut transport = AddressTransport::Other;
+        for protocol in addr.iter() {
+            transport = match protocol {
+                Protocol::P2pCircuit => return AddressTransport::Relayed,
+                Protocol::Tcp(_) => AddressTransport::Tcp,
+                Protocol::Quic | Protocol::QuicV1 => AddressTransport::Quic,
+                Protocol::Ws(_) | Protocol::Wss(_) => AddressTransport::WebSocket,
+                Protocol::WebRTCDirect => AddressTransport::WebRtc,
+                Protocol::WebTransport => AddressTransport::WebTransport,
+                _ => continue,
+            };
+        }
+        transport
+    }
+}
+
+/// Summarizes the given routing table.
+pub(crate) fn report(
+    kbuckets: &mut KBucketsTable<kbucket::Key<PeerId>, Addresses>,
+) -> RoutingTableHealth {
+    let mut health = RoutingTableHealth {
+        bucket_size: kbuckets.bucket_size(),
+        ..Default::default()
+    };
+    let mut distances = Vec::new();
+    let local_key = *kbuckets.local_key();
+
+    for bucket in kbuckets.iter().filter(|b| !b.is_empty()) {
+        let mut bucket_health = BucketHealth {
+            index: bucket.range().0.ilog2().unwrap_or_default(),
+            num_entries: bucket.num_entries(),
+            num_connected: 0,
+            has_pending: bucket.has_pending(),
+        };
+
+        for entry in bucket.iter() {
+            if entry.status == NodeStatus::Connected {
+                bucket_health.num_connected += 1;
+            }
+            distances.push(local_key.distance(entry.node.key));
+            health.add_peer(entry.node.value);
+        }
+
+        health.num_connected_peers += bucket_health.num_connected;
+        health.buckets.push(bucket_health);
+    }
+
+    distances.sort();
+    distances.truncate(K_VALUE.get());
+    health.estimated_network_size = estimate_network_size(&distances);
+
+    health
+}
+
+impl RoutingTableHealth {
+    fn add_peer(&mut self, addresses: &Addresses) {
+        self.num_peers += 1;
+
+        let mut ipv4 = false;
+        let mut ipv6 = false;
+        let mut transports = Vec::new();
+        for addr in addresses.iter() {
+            match addr.iter().next() {
+                Some(Protocol::Ip4(_)) => ipv4 = true,
+                Some(Protocol::Ip6(_)) => ipv6 = true,
+                _ => {}
+            }
+            let transport = AddressTransport::of(addr);
+            if !transports.contains(&transport) {
+                transports.push(transport);
+            }
+        }
+
+        match (ipv4, ipv6) {
+            (true, true) => self.num_dual_stack_peers += 1,
+            (true, false) => self.num_ipv4_only_peers += 1,
+            (false, true) => self.num_ipv6_only_peers += 1,
+            (false, false) => self.num_peers_without_ip += 1,
+        }
+        for transport in transports {
+            *self.peers_per_transport.entry(transport).or_default() += 1;
+        }
+    }
+}
+
+/// Estimates the size of the network from the sorted distances of the closest peers.
+///
+/// In a network of `n` uniformly distributed peers, the `i`-th closest peer is
+/// expected at a normalized distance of `i / (n + 1)`.
+fn estimate_network_size(distances: &[Distance]) -> Option<u64> {
+    let keyspace = 2f64.powi(256);
+    let estimates = distances
+        .iter()
+        .enumerate()
+        .filter(|(_, d)| d.ilog2().is_some())
+        .map(|(i, d)| (i + 1) as f64 * keyspace / d.to_f64() - 1.0)
+        .collect::<Vec<_>>();
+    if estimates.is_empty() {
+        return None;
+    }
+
+    let mean = estimates.iter().sum::<f64>() / estimates.len() as f64;
+    // Account for the local peer, which is not part of the routing table.
+    Some(mean.round() as u64 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kbucket::KBucketConfig;
+
+    #[test]
+    fn classifies_address_families_and_transports() {
+        let local_key = kbucket::Key::from(PeerId::random());
+        let mut table = KBucketsTable::new(local_key, KBucketConfig::default());
+
+        let peers = [
+            vec!["/ip4/1.2.3.4/tcp/4001", "/ip6/::1/udp/4001/quic-v1"],
+            vec!["/ip4/1.2.3.4/udp/4001/quic-v1"],
+            vec!["/dns4/example.com/tcp/443/wss"],
+        ];
+        for addrs in peers {
+            let mut addrs = addrs.into_iter().map(|a| a.parse::<Multiaddr>().unwrap());
+            let mut addresses = Addresses::new(addrs.next().unwrap());
+            for addr in addrs {
+                addresses.insert(addr);
+            }
+            let key = kbucket::Key::from(PeerId::random());
+            if let Some(kbucket::Entry::Absent(entry)) = table.entry(&key) {
+                let _ = entry.insert(addresses, NodeStatus::Connected);
+            }
+        }
+
+        let health = report(&mut table);
+
+        assert_eq!(health.num_peers(), 3);
+        assert_eq!(health.num_connected_peers(), 3);
+        assert_eq!(health.num_dual_stack_peers(), 1);
+        assert_eq!(health.num_ipv4_only_peers(), 1);
+        assert_eq!(health.num_peers_without_ip(), 1);
+        assert_eq!(health.num_peers_with_transport(AddressTransport::Quic), 2);
+        assert_eq!(health.num_peers_with_transport(AddressTransport::Tcp), 1);
+        assert_eq!(
+            health.num_peers_with_transport(AddressTransport::WebSocket),
+            1
+        );
+        assert_eq!(
+            health
+                .buckets()
+                .iter()
+                .map(|b| b.num_entries())
+                .sum::<usize>(),
+            3
+        );
+        assert!(health.estimated_network_size().is_some());
+    }
+}
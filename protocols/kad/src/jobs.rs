@@ -39,13 +39,16 @@
 //! intervals should be shorter than publication intervals and
 //! publication intervals should be shorter than the TTL.
 //!
-//! This module implements two periodic jobs:
+//! This module implements three periodic jobs:
 //!
 //!   * [`PutRecordJob`]: For (re-)publication and (re-)replication of
 //!     regular (value-)records.
 //!
 //!   * [`AddProviderJob`]: For (re-)publication of provider records.
-//!     Provider records currently have no separate replication mechanism.
+//!
+//!   * [`ProviderReplicationJob`]: For optional (re-)replication of provider
+//!     records of other peers that are close to expiry, whenever the
+//!     neighbourhood of the local node changed significantly.
 //!
 //! A periodic job is driven like a `Future` or `Stream` by `poll`ing it.
 //! Once a job starts running it emits records to send to the `k` closest
@@ -333,6 +336,118 @@ impl AddProviderJob {
     }
 }
 
+//////////////////////////////////////////////////////////////////////////////
+// ProviderReplicationJob
+
+/// Periodic job for replicating provider records of other peers when the
+/// closest peers to the local node churned.
+pub(crate) struct ProviderReplicationJob {
+    churn_threshold: usize,
+    provider_record_ttl: Option<Duration>,
+    neighbours: HashSet<PeerId>,
+    inner: PeriodicJob<vec::IntoIter<ProviderRecord>>,
+}
+
+impl ProviderReplicationJob {
+    /// Creates a new periodic job that checks the neighbourhood of the local
+    /// node for churn at the given interval.
+    pub(crate) fn new(
+        interval: Duration,
+        churn_threshold: usize,
+        provider_record_ttl: Option<Duration>,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            churn_threshold,
+            provider_record_ttl,
+            neighbours: HashSet::new(),
+            inner: PeriodicJob {
+                interval,
+                state: {
+                    let deadline = now + interval;
+                    PeriodicJobState::Waiting(Delay::new(interval), deadline)
+                },
+            },
+        }
+    }
+
+    /// Checks whether the job is currently running.
+    #[cfg(test)]
+    pub(crate) fn is_running(&self) -> bool {
+        self.inner.is_running()
+    }
+
+    /// Cuts short the remaining delay, if the job is currently waiting
+    /// for the delay to expire.
+    ///
+    /// The job is guaranteed to run on the next invocation of `poll`.
+    #[cfg(test)]
+    pub(crate) fn asap(&mut self) {
+        self.inner.asap()
+    }
+
+    /// Polls the job for provider records to replicate.
+    ///
+    /// `neighbours` is only called when the job is ready to run and returns
+    /// the current closest peers to the local node. Records are only yielded
+    /// if at least `churn_threshold` of these peers joined or left since the
+    /// last run, and only if less than half of their TTL remains.
+    ///
+    /// Must be called in the context of a task. When `NotReady` is returned,
+    /// the current task is registered to be notified when the job is ready
+    /// to be run.
+    pub(crate) fn poll<T, F>(
+        &mut self,
+        cx: &mut Context<'_>,
+        store: &mut T,
+        now: Instant,
+        neighbours: F,
+    ) -> Poll<ProviderRecord>
+    where
+        T: RecordStore,
+        F: FnOnce() -> HashSet<PeerId>,
+    {
+        if self.inner.check_ready(cx, now) {
+            let neighbours = neighbours();
+            let churn = neighbours.symmetric_difference(&self.neighbours).count();
+            self.neighbours = neighbours;
+
+            let records = if churn >= self.churn_threshold {
+                store
+                    .third_party_providers()
+                    .into_iter()
+                    .filter(|r| match (r.expires, self.provider_record_ttl) {
+                        (Some(expires), Some(ttl)) => {
+                            expires.saturating_duration_since(now) < ttl / 2
+                        }
+                        _ => true,
+                    })
+                    .collect::<Vec<_>>()
+            } else {
+                Vec::new()
+            };
+            self.inner.state = PeriodicJobState::Running(records.into_iter());
+        }
+
+        if let PeriodicJobState::Running(records) = &mut self.inner.state {
+            for r in records {
+                if r.is_expired(now) {
+                    store.remove_provider(&r.key, &r.provider)
+                } else {
+                    return Poll::Ready(r);
+                }
+            }
+
+            let deadline = now + self.inner.interval;
+            let delay = Delay::new(self.inner.interval);
+            self.inner.state = PeriodicJobState::Waiting(delay, deadline);
+            assert!(!self.inner.check_ready(cx, now));
+        }
+
+        Poll::Pending
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -421,4 +536,47 @@ mod tests {
 
         quickcheck(prop as fn(_))
     }
+
+    #[test]
+    fn run_provider_replication_job_on_churn() {
+        let local_id = PeerId::random();
+        let ttl = Duration::from_secs(60);
+        let mut job = ProviderReplicationJob::new(Duration::from_secs(10), 2, Some(ttl));
+        let mut store = MemoryStore::new(local_id);
+
+        let now = Instant::now() + job.inner.interval;
+        let near_expiry = ProviderRecord {
+            expires: Some(now + ttl / 4),
+            ..ProviderRecord::new(record::Key::new(&"a"), PeerId::random(), Vec::new())
+        };
+        let far_from_expiry = ProviderRecord {
+            expires: Some(now + ttl),
+            ..ProviderRecord::new(record::Key::new(&"b"), PeerId::random(), Vec::new())
+        };
+        let provided = ProviderRecord::new(record::Key::new(&"c"), local_id, Vec::new());
+        store.add_provider(near_expiry.clone()).unwrap();
+        store.add_provider(far_from_expiry).unwrap();
+        store.add_provider(provided).unwrap();
+
+        let neighbours = HashSet::from([PeerId::random(), PeerId::random()]);
+
+        block_on(poll_fn(|ctx| {
+            // The neighbourhood churned from nothing to two peers.
+            let current = neighbours.clone();
+            assert_eq!(
+                job.poll(ctx, &mut store, now, || current),
+                Poll::Ready(near_expiry.clone())
+            );
+            assert!(job.is_running());
+            assert_eq!(job.poll(ctx, &mut store, now, HashSet::new), Poll::Pending);
+            assert!(!job.is_running());
+
+            // The neighbourhood is unchanged.
+            job.asap();
+            let current = neighbours.clone();
+            assert_eq!(job.poll(ctx, &mut store, now, || current), Poll::Pending);
+            assert!(!job.is_running());
+            Poll::Ready(())
+        }));
+    }
 }
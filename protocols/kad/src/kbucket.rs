@@ -202,6 +202,11 @@ where
         &self.local_key
     }
 
+    /// Returns the maximal number of nodes that a bucket can contain.
+    pub(crate) fn bucket_size(&self) -> usize {
+        self.bucket_size
+    }
+
     /// Returns an `Entry` for the given key, representing the state of the entry
     /// in the routing table.
     ///
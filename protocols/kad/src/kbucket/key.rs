@@ -196,6 +196,15 @@ impl Distance {
     pub fn ilog2(&self) -> Option<u32> {
         (256 - self.0.leading_zeros()).checked_sub(1)
     }
+
+    /// Returns the [`Distance`] as a floating point number, losing precision.
+    pub(crate) fn to_f64(self) -> f64 {
+        self.0
+             .0
+            .iter()
+            .rev()
+            .fold(0.0, |acc, word| acc * 2f64.powi(64) + *word as f64)
+    }
 }
 
 #[cfg(test)]
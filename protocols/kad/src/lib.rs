@@ -35,14 +35,17 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+mod address_policy;
 mod addresses;
 mod behaviour;
 mod bootstrap;
 mod handler;
+mod health;
 mod jobs;
 mod kbucket;
 mod protocol;
 mod query;
+mod rate_limit;
 mod record;
 
 mod proto {
@@ -54,6 +57,7 @@ mod proto {
     };
 }
 
+pub use address_policy::ProviderAddressPolicy;
 pub use addresses::Addresses;
 pub use behaviour::{
     AddProviderContext, AddProviderError, AddProviderOk, AddProviderPhase, AddProviderResult,
@@ -64,13 +68,17 @@ pub use behaviour::{
     QueryMut, QueryRef, QueryResult, QueryStats, RoutingUpdate,
 };
 pub use behaviour::{
-    Behaviour, BucketInserts, Caching, Config, Event, ProgressStep, Quorum, StoreInserts,
+    Behaviour, BucketInserts, Caching, Config, Event, LocalAddressKind, ProgressStep, Quorum,
+    StoreInserts,
 };
+pub use handler::{RpcDirection, RpcStats, RpcType};
+pub use health::{AddressTransport, BucketHealth, RoutingTableHealth};
 pub use kbucket::{
     Distance as KBucketDistance, EntryView, KBucketRef, Key as KBucketKey, NodeStatus,
 };
 pub use protocol::ConnectionType;
 pub use query::QueryId;
+pub use rate_limit::{RateLimit, RateLimitAction};
 pub use record::{store, Key as RecordKey, ProviderRecord, Record};
 
 use libp2p_swarm::StreamProtocol;
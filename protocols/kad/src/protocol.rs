@@ -35,6 +35,7 @@ use libp2p_core::upgrade::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
 use libp2p_core::Multiaddr;
 use libp2p_identity::PeerId;
 use libp2p_swarm::StreamProtocol;
+use quick_protobuf::MessageWrite;
 use std::marker::PhantomData;
 use std::time::Duration;
 use std::{io, iter};
@@ -45,6 +46,9 @@ use web_time::Instant;
 pub(crate) const DEFAULT_PROTO_NAME: StreamProtocol = StreamProtocol::new("/ipfs/kad/1.0.0");
 /// The default maximum size for a varint length-delimited packet.
 pub(crate) const DEFAULT_MAX_PACKET_SIZE: usize = 16 * 1024;
+/// Suffix of the protocol names of the zstd compressed protocol variants.
+#[cfg(feature = "zstd")]
+const ZSTD_PROTOCOL_SUFFIX: &str = "+zstd";
 /// Status of our connection to a node reported by the Kademlia protocol.
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
 pub enum ConnectionType {
@@ -141,6 +145,9 @@ pub struct ProtocolConfig {
     protocol_names: Vec<StreamProtocol>,
     /// Maximum allowed size of a packet.
     max_packet_size: usize,
+    /// Whether to offer the zstd compressed protocol variants.
+    #[cfg(feature = "zstd")]
+    compression: bool,
 }
 
 impl ProtocolConfig {
@@ -149,6 +156,8 @@ impl ProtocolConfig {
         ProtocolConfig {
             protocol_names: vec![protocol_name],
             max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            #[cfg(feature = "zstd")]
+            compression: false,
         }
     }
 
@@ -175,6 +184,28 @@ impl ProtocolConfig {
     pub fn set_max_packet_size(&mut self, size: usize) {
         self.max_packet_size = size;
     }
+
+    /// Enables offering the zstd compressed variants of the protocols, i.e. `<name>+zstd`.
+    ///
+    /// The compressed variants are preferred over the uncompressed ones, which remain
+    /// available for peers not supporting compression.
+    #[cfg(feature = "zstd")]
+    pub fn set_compression(&mut self, enabled: bool) {
+        self.compression = enabled;
+    }
+
+    /// Returns whether messages on a stream negotiated with `protocol` are compressed.
+    fn is_compressed(&self, protocol: &StreamProtocol) -> bool {
+        #[cfg(feature = "zstd")]
+        {
+            self.compression && protocol.as_ref().ends_with(ZSTD_PROTOCOL_SUFFIX)
+        }
+        #[cfg(not(feature = "zstd"))]
+        {
+            let _ = protocol;
+            false
+        }
+    }
 }
 
 impl Default for ProtocolConfig {
@@ -185,6 +216,8 @@ impl Default for ProtocolConfig {
         ProtocolConfig {
             protocol_names: iter::once(DEFAULT_PROTO_NAME).collect(),
             max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            #[cfg(feature = "zstd")]
+            compression: false,
         }
     }
 }
@@ -194,19 +227,47 @@ impl UpgradeInfo for ProtocolConfig {
     type InfoIter = std::vec::IntoIter<Self::Info>;
 
     fn protocol_info(&self) -> Self::InfoIter {
+        #[cfg(feature = "zstd")]
+        if self.compression {
+            return self
+                .protocol_names
+                .iter()
+                .map(|name| {
+                    StreamProtocol::try_from_owned(format!("{name}{ZSTD_PROTOCOL_SUFFIX}"))
+                        .expect("suffixed protocol name to be valid")
+                })
+                .chain(self.protocol_names.iter().cloned())
+                .collect::<Vec<_>>()
+                .into_iter();
+        }
+
         self.protocol_names.clone().into_iter()
     }
 }
 
 /// Codec for Kademlia inbound and outbound message framing.
+///
+/// If compression is enabled, every varint length-delimited message is compressed as a whole
+/// with zstd and sent as a varint length-delimited packet itself.
 pub struct Codec<A, B> {
     codec: quick_protobuf_codec::Codec<proto::Message>,
+    #[cfg(feature = "zstd")]
+    compressed: bool,
+    #[cfg(feature = "zstd")]
+    max_packet_size: usize,
     __phantom: PhantomData<(A, B)>,
 }
 impl<A, B> Codec<A, B> {
-    fn new(max_packet_size: usize) -> Self {
+    fn new(max_packet_size: usize, compressed: bool) -> Self {
+        #[cfg(not(feature = "zstd"))]
+        debug_assert!(!compressed);
+
         Codec {
             codec: quick_protobuf_codec::Codec::new(max_packet_size),
+            #[cfg(feature = "zstd")]
+            compressed,
+            #[cfg(feature = "zstd")]
+            max_packet_size,
             __phantom: PhantomData,
         }
     }
@@ -217,6 +278,21 @@ impl<A: Into<proto::Message>, B> Encoder for Codec<A, B> {
     type Item<'a> = A;
 
     fn encode(&mut self, item: Self::Item<'_>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        #[cfg(feature = "zstd")]
+        if self.compressed {
+            let mut message = BytesMut::new();
+            self.codec.encode(item.into(), &mut message)?;
+            let compressed = zstd::bulk::compress(&message, 0)?;
+
+            let mut uvi_buf = unsigned_varint::encode::usize_buffer();
+            dst.extend_from_slice(unsigned_varint::encode::usize(
+                compressed.len(),
+                &mut uvi_buf,
+            ));
+            dst.extend_from_slice(&compressed);
+            return Ok(());
+        }
+
         Ok(self.codec.encode(item.into(), dst)?)
     }
 }
@@ -225,10 +301,58 @@ impl<A, B: TryFrom<proto::Message, Error = io::Error>> Decoder for Codec<A, B> {
     type Item = B;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        #[cfg(feature = "zstd")]
+        if self.compressed {
+            let Some(packet) = self.decode_compressed(src)? else {
+                return Ok(None);
+            };
+            let mut message = BytesMut::from(&packet[..]);
+            return match self.codec.decode(&mut message)? {
+                Some(message) => B::try_from(message).map(Some),
+                None => Err(invalid_data("truncated compressed message")),
+            };
+        }
+
         self.codec.decode(src)?.map(B::try_from).transpose()
     }
 }
 
+#[cfg(feature = "zstd")]
+impl<A, B> Codec<A, B> {
+    /// Decodes and decompresses a single packet, if it was fully received.
+    fn decode_compressed(&self, src: &mut BytesMut) -> io::Result<Option<Vec<u8>>> {
+        use bytes::Buf;
+
+        let (len, remaining) = match unsigned_varint::decode::usize(src) {
+            Ok((len, remaining)) => (len, remaining),
+            Err(unsigned_varint::decode::Error::Insufficient) => return Ok(None),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+        if len > self.max_packet_size {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "message with {len}b exceeds maximum of {}b",
+                    self.max_packet_size
+                ),
+            ));
+        }
+
+        let varint_len = src.len() - remaining.len();
+        if src.len() < varint_len + len {
+            return Ok(None);
+        }
+        src.advance(varint_len);
+        let packet = src.split_to(len);
+
+        // The decompressed message is prefixed with its length.
+        let capacity = self.max_packet_size + unsigned_varint::encode::usize_buffer().len();
+        zstd::bulk::decompress(&packet, capacity)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
 /// Sink of responses and stream of requests.
 pub(crate) type KadInStreamSink<S> = Framed<S, Codec<KadResponseMsg, KadRequestMsg>>;
 /// Sink of requests and stream of responses.
@@ -242,8 +366,8 @@ where
     type Future = future::Ready<Result<Self::Output, io::Error>>;
     type Error = io::Error;
 
-    fn upgrade_inbound(self, incoming: C, _: Self::Info) -> Self::Future {
-        let codec = Codec::new(self.max_packet_size);
+    fn upgrade_inbound(self, incoming: C, protocol: Self::Info) -> Self::Future {
+        let codec = Codec::new(self.max_packet_size, self.is_compressed(&protocol));
 
         future::ok(Framed::new(incoming, codec))
     }
@@ -257,8 +381,8 @@ where
     type Future = future::Ready<Result<Self::Output, io::Error>>;
     type Error = io::Error;
 
-    fn upgrade_outbound(self, incoming: C, _: Self::Info) -> Self::Future {
-        let codec = Codec::new(self.max_packet_size);
+    fn upgrade_outbound(self, incoming: C, protocol: Self::Info) -> Self::Future {
+        let codec = Codec::new(self.max_packet_size, self.is_compressed(&protocol));
 
         future::ok(Framed::new(incoming, codec))
     }
@@ -349,6 +473,20 @@ impl From<KadResponseMsg> for proto::Message {
         resp_msg_to_proto(kad_msg)
     }
 }
+impl KadRequestMsg {
+    /// Returns the size of the protobuf encoding of this message in bytes.
+    pub(crate) fn encoded_len(&self) -> usize {
+        req_msg_to_proto(self.clone()).get_size()
+    }
+}
+
+impl KadResponseMsg {
+    /// Returns the size of the protobuf encoding of this message in bytes.
+    pub(crate) fn encoded_len(&self) -> usize {
+        resp_msg_to_proto(self.clone()).get_size()
+    }
+}
+
 impl TryFrom<proto::Message> for KadRequestMsg {
     type Error = io::Error;
 
@@ -755,4 +893,69 @@ mod tests {
             bg_thread.join().unwrap();
         }
     }*/
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn compressed_protocols_are_preferred() {
+        let mut config = ProtocolConfig::new(DEFAULT_PROTO_NAME);
+        config.set_compression(true);
+
+        let protocols = config.protocol_info().collect::<Vec<_>>();
+        assert_eq!(
+            protocols,
+            vec![
+                StreamProtocol::new("/ipfs/kad/1.0.0+zstd"),
+                DEFAULT_PROTO_NAME
+            ]
+        );
+        assert!(config.is_compressed(&protocols[0]));
+        assert!(!config.is_compressed(&protocols[1]));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn compressed_codec_roundtrip() {
+        let peers = (0..20)
+            .map(|_| KadPeer {
+                node_id: PeerId::random(),
+                multiaddrs: vec!["/ip4/1.2.3.4/tcp/4001".parse().unwrap()],
+                connection_ty: ConnectionType::Connected,
+            })
+            .collect::<Vec<_>>();
+        let response = KadResponseMsg::FindNode {
+            closer_peers: peers.clone(),
+        };
+
+        let mut compressed = BytesMut::new();
+        Codec::<KadResponseMsg, KadResponseMsg>::new(DEFAULT_MAX_PACKET_SIZE, true)
+            .encode(response.clone(), &mut compressed)
+            .unwrap();
+        let mut uncompressed = BytesMut::new();
+        Codec::<KadResponseMsg, KadResponseMsg>::new(DEFAULT_MAX_PACKET_SIZE, false)
+            .encode(response, &mut uncompressed)
+            .unwrap();
+        assert!(compressed.len() < uncompressed.len());
+
+        let mut codec = Codec::<KadResponseMsg, KadResponseMsg>::new(DEFAULT_MAX_PACKET_SIZE, true);
+        let mut partial = compressed.split_to(compressed.len() / 2);
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+        partial.unsplit(compressed);
+        match codec.decode(&mut partial).unwrap() {
+            Some(KadResponseMsg::FindNode { closer_peers }) => {
+                let expected = peers
+                    .into_iter()
+                    .map(|mut peer| {
+                        peer.multiaddrs = peer
+                            .multiaddrs
+                            .into_iter()
+                            .map(|a| a.with_p2p(peer.node_id).unwrap())
+                            .collect();
+                        peer
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(closer_peers, expected);
+            }
+            other => panic!("Unexpected message {other:?}"),
+        }
+        assert!(partial.is_empty());
+    }
 }
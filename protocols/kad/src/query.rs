@@ -310,6 +310,19 @@ impl QueryPeers {
         }
     }
 
+    /// Consumes the peers iterator, producing a final `Iterator` over the `PeerId`s of
+    /// a fixed set of peers that did not succeed, including those not contacted.
+    ///
+    /// The iterator is empty for queries not running on a fixed set of peers.
+    pub(crate) fn into_unsuccessful_peerids_iter(self) -> impl Iterator<Item = PeerId> {
+        match self.peer_iter {
+            QueryPeerIter::Fixed(iter) => Either::Left(iter.into_unsuccessful()),
+            QueryPeerIter::Closest(_) | QueryPeerIter::ClosestDisjoint(_) => {
+                Either::Right(std::iter::empty())
+            }
+        }
+    }
+
     /// Consumes the peers iterator, producing a final `Iterator` over the discovered `PeerId`s
     /// with their matching `Multiaddr`s.
     pub(crate) fn into_peerinfos_iter(mut self) -> impl Iterator<Item = PeerInfo> {
@@ -165,6 +165,19 @@ impl FixedPeersIter {
             }
         })
     }
+
+    /// Consumes the iterator, returning the peers for which no successful
+    /// result has been reported, including peers that have not been contacted.
+    pub(crate) fn into_unsuccessful(self) -> impl Iterator<Item = PeerId> {
+        let mut peers = self.peers;
+        for peer in self.iter {
+            // Peers that have not been contacted yet.
+            peers.entry(peer).or_insert(PeerState::Waiting);
+        }
+        peers
+            .into_iter()
+            .filter_map(|(p, s)| (s != PeerState::Succeeded).then_some(p))
+    }
 }
 
 #[cfg(test)]
@@ -196,4 +209,31 @@ mod test {
             _ => panic!("Expected iterator to yield peer."),
         }
     }
+
+    #[test]
+    fn into_unsuccessful_includes_peers_not_contacted() {
+        let peers = (0..3).map(|_| PeerId::random()).collect::<Vec<_>>();
+        let mut iter = FixedPeersIter::new(peers.clone(), NonZeroUsize::new(1).unwrap());
+
+        match iter.next() {
+            PeersIterState::Waiting(Some(peer)) => {
+                let peer = peer.into_owned();
+                iter.on_success(&peer);
+            }
+            _ => panic!("Expected iterator to yield peer."),
+        }
+        match iter.next() {
+            PeersIterState::Waiting(Some(peer)) => {
+                let peer = peer.into_owned();
+                iter.on_failure(&peer);
+            }
+            _ => panic!("Expected iterator to yield peer."),
+        }
+
+        let mut unsuccessful = iter.into_unsuccessful().collect::<Vec<_>>();
+        unsuccessful.sort();
+        let mut expected = peers[1..].to_vec();
+        expected.sort();
+        assert_eq!(unsuccessful, expected);
+    }
 }
@@ -0,0 +1,305 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Server-side rate limiting of inbound Kademlia requests.
+
+use libp2p_core::multiaddr::{Multiaddr, Protocol};
+use libp2p_identity::PeerId;
+use libp2p_swarm::ConnectionId;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::num::NonZeroU32;
+use std::time::Duration;
+use web_time::Instant;
+
+/// Interval after which buckets that have been refilled completely are dropped.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A token bucket rate limit for inbound requests.
+///
+/// Up to `burst` requests are allowed at once, after which requests are allowed
+/// at a steady rate of `requests_per_second`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    burst: NonZeroU32,
+    requests_per_second: f64,
+}
+
+impl RateLimit {
+    /// Creates a new [`RateLimit`] allowing bursts of `burst` requests and
+    /// `requests_per_second` requests on average.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests_per_second` is not a positive, finite number.
+    pub fn new(burst: NonZeroU32, requests_per_second: f64) -> Self {
+        assert!(
+            requests_per_second.is_finite() && requests_per_second > 0.0,
+            "rate must be a positive, finite number"
+        );
+
+        Self {
+            burst,
+            requests_per_second,
+        }
+    }
+}
+
+/// What to do with an inbound request that exceeds a [`RateLimit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitAction {
+    /// Reset the stream of the request, signaling an error to the remote right away.
+    Refuse,
+    /// Ignore the request and let it time out on the remote.
+    Drop,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst.get() as f64,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = f64::min(
+            limit.burst.get() as f64,
+            self.tokens + elapsed.as_secs_f64() * limit.requests_per_second,
+        );
+        self.last_refill = now;
+    }
+
+    fn is_full(&self, limit: &RateLimit) -> bool {
+        self.tokens >= limit.burst.get() as f64
+    }
+}
+
+/// A set of token buckets sharing the same [`RateLimit`].
+#[derive(Debug)]
+struct Buckets<K> {
+    limit: RateLimit,
+    buckets: HashMap<K, TokenBucket>,
+}
+
+impl<K: Hash + Eq> Buckets<K> {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Refills the bucket of `key` and returns whether it holds a token.
+    fn has_token(&mut self, key: K, now: Instant) -> bool {
+        let limit = &self.limit;
+        let bucket = self
+            .buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(limit, now));
+        bucket.refill(limit, now);
+        bucket.tokens >= 1.0
+    }
+
+    fn take_token(&mut self, key: &K) {
+        if let Some(bucket) = self.buckets.get_mut(key) {
+            bucket.tokens -= 1.0;
+        }
+    }
+
+    fn prune(&mut self, now: Instant) {
+        let limit = &self.limit;
+        self.buckets.retain(|_, bucket| {
+            bucket.refill(limit, now);
+            !bucket.is_full(limit)
+        });
+    }
+}
+
+/// Rate limiter for inbound requests per remote peer and per remote IP address.
+#[derive(Debug)]
+pub(crate) struct InboundRateLimiter {
+    per_peer: Option<Buckets<PeerId>>,
+    per_ip: Option<Buckets<IpAddr>>,
+    action: RateLimitAction,
+    /// The remote IP address of every connection, if any.
+    connection_ips: HashMap<ConnectionId, IpAddr>,
+    last_prune: Instant,
+}
+
+impl InboundRateLimiter {
+    pub(crate) fn new(
+        per_peer: Option<RateLimit>,
+        per_ip: Option<RateLimit>,
+        action: RateLimitAction,
+    ) -> Self {
+        Self {
+            per_peer: per_peer.map(Buckets::new),
+            per_ip: per_ip.map(Buckets::new),
+            action,
+            connection_ips: HashMap::new(),
+            last_prune: Instant::now(),
+        }
+    }
+
+    pub(crate) fn action(&self) -> RateLimitAction {
+        self.action
+    }
+
+    /// Tracks the remote IP address of a new connection.
+    ///
+    /// Relayed connections are not tracked, as their remote IP address is the one of the relay.
+    pub(crate) fn on_connection_established(
+        &mut self,
+        connection_id: ConnectionId,
+        remote_addr: &Multiaddr,
+    ) {
+        if self.per_ip.is_none() || remote_addr.iter().any(|p| p == Protocol::P2pCircuit) {
+            return;
+        }
+
+        let ip = remote_addr.iter().find_map(|p| match p {
+            Protocol::Ip4(ip) => Some(IpAddr::from(ip)),
+            Protocol::Ip6(ip) => Some(IpAddr::from(ip)),
+            _ => None,
+        });
+        if let Some(ip) = ip {
+            self.connection_ips.insert(connection_id, ip);
+        }
+    }
+
+    pub(crate) fn on_connection_closed(&mut self, connection_id: ConnectionId) {
+        self.connection_ips.remove(&connection_id);
+    }
+
+    /// Returns whether an inbound request from `peer` on `connection_id` is within
+    /// the configured limits, consuming a token from each applicable bucket if so.
+    pub(crate) fn try_acquire(
+        &mut self,
+        peer: PeerId,
+        connection_id: ConnectionId,
+        now: Instant,
+    ) -> bool {
+        if now.saturating_duration_since(self.last_prune) >= PRUNE_INTERVAL {
+            self.last_prune = now;
+            if let Some(buckets) = self.per_peer.as_mut() {
+                buckets.prune(now);
+            }
+            if let Some(buckets) = self.per_ip.as_mut() {
+                buckets.prune(now);
+            }
+        }
+
+        let ip = self.connection_ips.get(&connection_id).copied();
+
+        let peer_allowed = self
+            .per_peer
+            .as_mut()
+            .map_or(true, |buckets| buckets.has_token(peer, now));
+        let ip_allowed = match (self.per_ip.as_mut(), ip) {
+            (Some(buckets), Some(ip)) => buckets.has_token(ip, now),
+            _ => true,
+        };
+
+        if !(peer_allowed && ip_allowed) {
+            return false;
+        }
+
+        if let Some(buckets) = self.per_peer.as_mut() {
+            buckets.take_token(&peer);
+        }
+        if let (Some(buckets), Some(ip)) = (self.per_ip.as_mut(), ip) {
+            buckets.take_token(&ip);
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(per_peer: Option<RateLimit>, per_ip: Option<RateLimit>) -> InboundRateLimiter {
+        InboundRateLimiter::new(per_peer, per_ip, RateLimitAction::Refuse)
+    }
+
+    #[test]
+    fn allows_burst_then_steady_rate() {
+        let limit = RateLimit::new(NonZeroU32::new(3).unwrap(), 1.0);
+        let mut limiter = limiter(Some(limit), None);
+        let peer = PeerId::random();
+        let connection = ConnectionId::new_unchecked(0);
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.try_acquire(peer, connection, now));
+        }
+        assert!(!limiter.try_acquire(peer, connection, now));
+
+        // Other peers have their own bucket.
+        assert!(limiter.try_acquire(PeerId::random(), connection, now));
+
+        let later = now + Duration::from_secs(1);
+        assert!(limiter.try_acquire(peer, connection, later));
+        assert!(!limiter.try_acquire(peer, connection, later));
+    }
+
+    #[test]
+    fn limits_peers_sharing_an_ip() {
+        let limit = RateLimit::new(NonZeroU32::new(2).unwrap(), 1.0);
+        let mut limiter = limiter(None, Some(limit));
+        let now = Instant::now();
+
+        let addr: Multiaddr = "/ip4/1.2.3.4/tcp/4001".parse().unwrap();
+        let first = ConnectionId::new_unchecked(0);
+        let second = ConnectionId::new_unchecked(1);
+        limiter.on_connection_established(first, &addr);
+        limiter.on_connection_established(second, &addr);
+
+        assert!(limiter.try_acquire(PeerId::random(), first, now));
+        assert!(limiter.try_acquire(PeerId::random(), second, now));
+        assert!(!limiter.try_acquire(PeerId::random(), first, now));
+    }
+
+    #[test]
+    fn does_not_limit_relayed_connections_by_ip() {
+        let limit = RateLimit::new(NonZeroU32::new(1).unwrap(), 1.0);
+        let mut limiter = limiter(None, Some(limit));
+        let now = Instant::now();
+
+        let addr: Multiaddr = format!("/ip4/1.2.3.4/tcp/4001/p2p/{}/p2p-circuit", PeerId::random())
+            .parse()
+            .unwrap();
+        let connection = ConnectionId::new_unchecked(0);
+        limiter.on_connection_established(connection, &addr);
+
+        assert!(limiter.try_acquire(PeerId::random(), connection, now));
+        assert!(limiter.try_acquire(PeerId::random(), connection, now));
+    }
+}
@@ -20,7 +20,7 @@
 
 mod memory;
 
-pub use memory::{MemoryStore, MemoryStoreConfig};
+pub use memory::{MemoryStore, MemoryStoreConfig, ProviderEviction};
 use thiserror::Error;
 
 use super::*;
@@ -41,6 +41,10 @@ pub enum Error {
     #[error("the store cannot contain any more provider records")]
     MaxProvidedKeys,
 
+    /// The store is at capacity w.r.t. the total number of provider records across all keys.
+    #[error("the store cannot contain any more provider records across all keys")]
+    MaxProviderRecords,
+
     /// The store cannot store this value because it is too large.
     #[error("the value is too large to be stored")]
     ValueTooLarge,
@@ -100,4 +104,22 @@ pub trait RecordStore {
 
     /// Removes a provider record from the store.
     fn remove_provider(&mut self, k: &Key, p: &PeerId);
+
+    /// Takes the next provider record that has been evicted from the store
+    /// to make room for new provider records, if any.
+    ///
+    /// Evicted records are reported via [`Event::ProviderRecordEvicted`](crate::Event::ProviderRecordEvicted).
+    fn next_evicted_provider(&mut self) -> Option<ProviderRecord> {
+        None
+    }
+
+    /// Gets a copy of all stored provider records for which the node owning
+    /// the store is not itself the provider.
+    ///
+    /// Used to re-announce these records when the neighbourhood of the local
+    /// node changes, see [`Config::set_provider_replication_churn_threshold`](crate::Config::set_provider_replication_churn_threshold).
+    /// Stores that don't support this never have their records replicated.
+    fn third_party_providers(&self) -> Vec<ProviderRecord> {
+        Vec::new()
+    }
 }
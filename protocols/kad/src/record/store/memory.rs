@@ -21,10 +21,15 @@
 use super::*;
 
 use crate::kbucket;
+use libp2p_swarm::cache::{self, Cache, CacheStats, MemoryBudget};
 use smallvec::SmallVec;
-use std::collections::{hash_map, hash_set, HashMap, HashSet};
+use std::collections::{hash_map, hash_set, HashMap, HashSet, VecDeque};
 use std::iter;
 
+/// The maximum number of evicted provider records buffered until they are
+/// taken via [`RecordStore::next_evicted_provider`].
+const MAX_EVICTED_PROVIDERS: usize = 1024;
+
 /// In-memory implementation of a `RecordStore`.
 pub struct MemoryStore {
     /// The identity of the peer owning the store.
@@ -32,13 +37,19 @@ pub struct MemoryStore {
     /// The configuration of the store.
     config: MemoryStoreConfig,
     /// The stored (regular) records.
-    records: HashMap<Key, Record>,
+    records: Cache<Key, Record>,
     /// The stored provider records.
     providers: HashMap<Key, SmallVec<[ProviderRecord; K_VALUE.get()]>>,
     /// The set of all provider records for the node identified by `local_key`.
     ///
     /// Must be kept in sync with `providers`.
     provided: HashSet<ProviderRecord>,
+    /// The total number of stored provider records.
+    ///
+    /// Must be kept in sync with `providers`.
+    num_provider_records: usize,
+    /// Provider records evicted to make room for new ones.
+    evicted_providers: VecDeque<ProviderRecord>,
 }
 
 /// Configuration for a `MemoryStore`.
@@ -55,6 +66,17 @@ pub struct MemoryStoreConfig {
     /// The maximum number of provider records for which the
     /// local node is the provider.
     pub max_provided_keys: usize,
+    /// The maximum number of provider records across all keys.
+    pub max_provider_records: usize,
+    /// How to make room for new provider records once one of the
+    /// limits on provider records is reached.
+    pub provider_eviction: ProviderEviction,
+    /// The memory budget the (regular) records are accounted against, if any.
+    ///
+    /// The budget may be shared with other caches of the node, e.g. the
+    /// gossipsub duplicate cache. Once it is exceeded, the least recently
+    /// stored records are evicted to make room for new ones.
+    pub memory_budget: Option<MemoryBudget>,
 }
 
 impl Default for MemoryStoreConfig {
@@ -64,10 +86,30 @@ impl Default for MemoryStoreConfig {
             max_value_bytes: 65 * 1024,
             max_provided_keys: 1024,
             max_providers_per_key: K_VALUE.get(),
+            max_provider_records: 1024 * K_VALUE.get(),
+            provider_eviction: ProviderEviction::Reject,
+            memory_budget: None,
         }
     }
 }
 
+/// How a [`MemoryStore`] makes room for new provider records once one of
+/// the limits of its [`MemoryStoreConfig`] is reached.
+///
+/// Provider records for which the local node is the provider are never evicted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderEviction {
+    /// Reject new provider records.
+    Reject,
+    /// Evict the provider records expiring first, i.e. expired records and
+    /// records least recently (re-)published by their provider.
+    ///
+    /// If the providers of a key are saturated, only expired records of that
+    /// key are evicted, so that an attacker flooding the network with fake
+    /// provider records cannot displace existing providers.
+    ExpiringFirst,
+}
+
 impl MemoryStore {
     /// Creates a new `MemoryRecordStore` with a default configuration.
     pub fn new(local_id: PeerId) -> Self {
@@ -78,10 +120,15 @@ impl MemoryStore {
     pub fn with_config(local_id: PeerId, config: MemoryStoreConfig) -> Self {
         MemoryStore {
             local_key: kbucket::Key::from(local_id),
+            records: Cache::new(
+                config.memory_budget.clone().unwrap_or_default(),
+                record_size,
+            ),
             config,
-            records: HashMap::default(),
             provided: HashSet::default(),
             providers: HashMap::default(),
+            num_provider_records: 0,
+            evicted_providers: VecDeque::default(),
         }
     }
 
@@ -92,11 +139,110 @@ impl MemoryStore {
     {
         self.records.retain(f);
     }
+
+    /// Returns the hits, misses and evictions of the (regular) records,
+    /// see [`MemoryStoreConfig::memory_budget`].
+    pub fn record_stats(&self) -> CacheStats {
+        self.records.stats()
+    }
+
+    /// Removes the provider record of `provider` for `key`, returning it if it existed.
+    fn take_provider(&mut self, key: &Key, provider: &PeerId) -> Option<ProviderRecord> {
+        let hash_map::Entry::Occupied(mut e) = self.providers.entry(key.clone()) else {
+            return None;
+        };
+        let providers = e.get_mut();
+        let record = providers
+            .iter()
+            .position(|p| &p.provider == provider)
+            .map(|i| providers.remove(i));
+        if providers.is_empty() {
+            e.remove();
+        }
+
+        let record = record?;
+        self.num_provider_records -= 1;
+        if &record.provider == self.local_key.preimage() {
+            self.provided.remove(&record);
+        }
+        Some(record)
+    }
+
+    /// Evicts the provider record of a remote peer expiring first, among the
+    /// providers of `key` if given. If `now` is given, only records that are
+    /// expired at `now` are evicted.
+    ///
+    /// Returns whether a record has been evicted.
+    fn evict_provider(&mut self, key: Option<&Key>, now: Option<Instant>) -> bool {
+        let local = self.local_key.preimage();
+        let records: Box<dyn Iterator<Item = &ProviderRecord>> = match key {
+            Some(key) => Box::new(self.providers.get(key).into_iter().flatten()),
+            None => Box::new(self.providers.values().flatten()),
+        };
+        let candidate = records
+            .filter(|p| &p.provider != local)
+            .filter(|p| now.map_or(true, |now| p.is_expired(now)))
+            .min_by_key(|p| eviction_order(p))
+            .map(|p| (p.key.clone(), p.provider));
+
+        let Some((key, provider)) = candidate else {
+            return false;
+        };
+        if let Some(record) = self.take_provider(&key, &provider) {
+            self.push_evicted(record);
+        }
+        true
+    }
+
+    /// Evicts all provider records of the key whose records have least
+    /// recently been (re-)published, skipping keys provided by the local node.
+    ///
+    /// Returns whether a key has been evicted.
+    fn evict_provider_key(&mut self) -> bool {
+        let local = self.local_key.preimage();
+        let key = self
+            .providers
+            .iter()
+            .filter(|(_, ps)| ps.iter().all(|p| &p.provider != local))
+            .min_by_key(|(_, ps)| ps.iter().map(eviction_order).max())
+            .map(|(k, _)| k.clone());
+
+        let Some(providers) = key.and_then(|key| self.providers.remove(&key)) else {
+            return false;
+        };
+        self.num_provider_records -= providers.len();
+        for record in providers {
+            self.push_evicted(record);
+        }
+        true
+    }
+
+    fn push_evicted(&mut self, record: ProviderRecord) {
+        if self.evicted_providers.len() == MAX_EVICTED_PROVIDERS {
+            self.evicted_providers.pop_front();
+        }
+        self.evicted_providers.push_back(record);
+    }
+}
+
+/// Estimates the memory used by a record, see [`MemoryStoreConfig::memory_budget`].
+fn record_size(_: &Key, record: &Record) -> usize {
+    std::mem::size_of::<Record>() + record.key.as_ref().len() + record.value.len()
+}
+
+fn borrow_record<'a>((_, record): (&'a Key, &'a Record)) -> Cow<'a, Record> {
+    Cow::Borrowed(record)
+}
+
+/// The order in which provider records are evicted: records expiring first
+/// come first, records that never expire come last.
+fn eviction_order(record: &ProviderRecord) -> (bool, Option<Instant>) {
+    (record.expires.is_none(), record.expires)
 }
 
 impl RecordStore for MemoryStore {
     type RecordsIter<'a> =
-        iter::Map<hash_map::Values<'a, Key, Record>, fn(&'a Record) -> Cow<'a, Record>>;
+        iter::Map<cache::Iter<'a, Key, Record>, fn((&'a Key, &'a Record)) -> Cow<'a, Record>>;
 
     type ProvidedIter<'a> = iter::Map<
         hash_set::Iter<'a, ProviderRecord>,
@@ -112,19 +258,10 @@ impl RecordStore for MemoryStore {
             return Err(Error::ValueTooLarge);
         }
 
-        let num_records = self.records.len();
-
-        match self.records.entry(r.key.clone()) {
-            hash_map::Entry::Occupied(mut e) => {
-                e.insert(r);
-            }
-            hash_map::Entry::Vacant(e) => {
-                if num_records >= self.config.max_records {
-                    return Err(Error::MaxRecords);
-                }
-                e.insert(r);
-            }
+        if self.records.peek(&r.key).is_none() && self.records.len() >= self.config.max_records {
+            return Err(Error::MaxRecords);
         }
+        self.records.insert(r.key.clone(), r);
 
         Ok(())
     }
@@ -134,26 +271,12 @@ impl RecordStore for MemoryStore {
     }
 
     fn records(&self) -> Self::RecordsIter<'_> {
-        self.records.values().map(Cow::Borrowed)
+        self.records.iter().map(borrow_record)
     }
 
     fn add_provider(&mut self, record: ProviderRecord) -> Result<()> {
-        let num_keys = self.providers.len();
-
-        // Obtain the entry
-        let providers = match self.providers.entry(record.key.clone()) {
-            e @ hash_map::Entry::Occupied(_) => e,
-            e @ hash_map::Entry::Vacant(_) => {
-                if self.config.max_provided_keys == num_keys {
-                    return Err(Error::MaxProvidedKeys);
-                }
-                e
-            }
-        }
-        .or_insert_with(Default::default);
-
-        for p in providers.iter_mut() {
-            if p.provider == record.provider {
+        if let Some(providers) = self.providers.get_mut(&record.key) {
+            if let Some(p) = providers.iter_mut().find(|p| p.provider == record.provider) {
                 // In-place update of an existing provider record.
                 if self.local_key.preimage() == &record.provider {
                     self.provided.remove(p);
@@ -164,18 +287,41 @@ impl RecordStore for MemoryStore {
             }
         }
 
+        let evict = self.config.provider_eviction == ProviderEviction::ExpiringFirst;
+        let now = Instant::now();
+
+        if !self.providers.contains_key(&record.key)
+            && self.providers.len() >= self.config.max_provided_keys
+            && !(evict && self.evict_provider_key())
+        {
+            return Err(Error::MaxProvidedKeys);
+        }
+
         // If the providers list is full, we ignore the new provider.
         // This strategy can mitigate Sybil attacks, in which an attacker
         // floods the network with fake provider records.
-        if providers.len() == self.config.max_providers_per_key {
+        let num_providers = self.providers.get(&record.key).map_or(0, |ps| ps.len());
+        if num_providers >= self.config.max_providers_per_key
+            && !(evict && self.evict_provider(Some(&record.key), Some(now)))
+        {
             return Ok(());
         }
 
+        if self.num_provider_records >= self.config.max_provider_records
+            && !(evict && self.evict_provider(None, None))
+        {
+            return Err(Error::MaxProviderRecords);
+        }
+
         // Otherwise, insert the new provider record.
         if self.local_key.preimage() == &record.provider {
             self.provided.insert(record.clone());
         }
-        providers.push(record);
+        self.providers
+            .entry(record.key.clone())
+            .or_default()
+            .push(record);
+        self.num_provider_records += 1;
 
         Ok(())
     }
@@ -191,18 +337,20 @@ impl RecordStore for MemoryStore {
     }
 
     fn remove_provider(&mut self, key: &Key, provider: &PeerId) {
-        if let hash_map::Entry::Occupied(mut e) = self.providers.entry(key.clone()) {
-            let providers = e.get_mut();
-            if let Some(i) = providers.iter().position(|p| &p.provider == provider) {
-                let p = providers.remove(i);
-                if &p.provider == self.local_key.preimage() {
-                    self.provided.remove(&p);
-                }
-            }
-            if providers.is_empty() {
-                e.remove();
-            }
-        }
+        self.take_provider(key, provider);
+    }
+
+    fn next_evicted_provider(&mut self) -> Option<ProviderRecord> {
+        self.evicted_providers.pop_front()
+    }
+
+    fn third_party_providers(&self) -> Vec<ProviderRecord> {
+        self.providers
+            .values()
+            .flatten()
+            .filter(|p| &p.provider != self.local_key.preimage())
+            .cloned()
+            .collect()
     }
 }
 
@@ -212,6 +360,7 @@ mod tests {
     use crate::SHA_256_MH;
     use quickcheck::*;
     use rand::Rng;
+    use std::time::Duration;
 
     fn random_multihash() -> Multihash<64> {
         Multihash::wrap(SHA_256_MH, &rand::thread_rng().gen::<[u8; 32]>()).unwrap()
@@ -228,6 +377,41 @@ mod tests {
         quickcheck(prop as fn(_))
     }
 
+    #[test]
+    fn records_are_evicted_once_memory_budget_is_exceeded() {
+        let records = (0..3)
+            .map(|_| Record::new(random_multihash(), vec![0; 100]))
+            .collect::<Vec<_>>();
+        let budget = MemoryBudget::new(2 * record_size(&records[0].key, &records[0]));
+        let mut store = MemoryStore::with_config(
+            PeerId::random(),
+            MemoryStoreConfig {
+                memory_budget: Some(budget.clone()),
+                ..Default::default()
+            },
+        );
+
+        for r in &records {
+            store.put(r.clone()).unwrap();
+        }
+
+        assert!(store.get(&records[0].key).is_none());
+        assert!(store.get(&records[1].key).is_some());
+        assert!(store.get(&records[2].key).is_some());
+        assert_eq!(store.records().count(), 2);
+        assert_eq!(
+            store.record_stats(),
+            CacheStats {
+                hits: 2,
+                misses: 1,
+                evictions: 1
+            }
+        );
+
+        drop(store);
+        assert_eq!(budget.used(), 0);
+    }
+
     #[test]
     fn add_get_remove_provider() {
         fn prop(r: ProviderRecord) {
@@ -325,4 +509,92 @@ mod tests {
             _ => panic!("Unexpected result"),
         }
     }
+
+    #[test]
+    fn max_provider_records() {
+        let config = MemoryStoreConfig {
+            max_provider_records: 2,
+            ..Default::default()
+        };
+        let mut store = MemoryStore::with_config(PeerId::random(), config);
+        for _ in 0..2 {
+            let rec = ProviderRecord::new(random_multihash(), PeerId::random(), Vec::new());
+            assert!(store.add_provider(rec).is_ok());
+        }
+        let rec = ProviderRecord::new(random_multihash(), PeerId::random(), Vec::new());
+        match store.add_provider(rec) {
+            Err(Error::MaxProviderRecords) => {}
+            _ => panic!("Unexpected result"),
+        }
+        assert!(store.next_evicted_provider().is_none());
+    }
+
+    #[test]
+    fn evict_provider_expiring_first() {
+        let config = MemoryStoreConfig {
+            max_provider_records: 2,
+            provider_eviction: ProviderEviction::ExpiringFirst,
+            ..Default::default()
+        };
+        let local = PeerId::random();
+        let mut store = MemoryStore::with_config(local, config);
+        let now = Instant::now();
+
+        let mut provided = ProviderRecord::new(random_multihash(), local, Vec::new());
+        provided.expires = Some(now);
+        let mut expiring = ProviderRecord::new(random_multihash(), PeerId::random(), Vec::new());
+        expiring.expires = Some(now + Duration::from_secs(60));
+        assert!(store.add_provider(provided.clone()).is_ok());
+        assert!(store.add_provider(expiring.clone()).is_ok());
+
+        let mut rec = ProviderRecord::new(random_multihash(), PeerId::random(), Vec::new());
+        rec.expires = Some(now + Duration::from_secs(120));
+        assert!(store.add_provider(rec.clone()).is_ok());
+
+        // The record of the local node is kept despite expiring first.
+        assert!(store.providers(&provided.key).contains(&provided));
+        assert!(store.providers(&expiring.key).is_empty());
+        assert!(store.providers(&rec.key).contains(&rec));
+        assert_eq!(store.next_evicted_provider(), Some(expiring));
+        assert!(store.next_evicted_provider().is_none());
+    }
+
+    #[test]
+    fn evict_only_expired_providers_of_saturated_key() {
+        let config = MemoryStoreConfig {
+            max_providers_per_key: 1,
+            provider_eviction: ProviderEviction::ExpiringFirst,
+            ..Default::default()
+        };
+        let mut store = MemoryStore::with_config(PeerId::random(), config);
+        let key = Key::from(random_multihash());
+        let now = Instant::now();
+
+        let mut existing = ProviderRecord::new(key.clone(), PeerId::random(), Vec::new());
+        existing.expires = Some(now + Duration::from_secs(60));
+        assert!(store.add_provider(existing.clone()).is_ok());
+
+        // The existing provider has not expired and is thus not displaced.
+        let rec = ProviderRecord::new(key.clone(), PeerId::random(), Vec::new());
+        assert!(store.add_provider(rec.clone()).is_ok());
+        assert_eq!(store.providers(&key), vec![existing.clone()]);
+
+        existing.expires = Some(now);
+        assert!(store.add_provider(existing.clone()).is_ok());
+        assert!(store.add_provider(rec.clone()).is_ok());
+        assert_eq!(store.providers(&key), vec![rec]);
+        assert_eq!(store.next_evicted_provider(), Some(existing));
+    }
+
+    #[test]
+    fn third_party_providers_exclude_local_provider() {
+        let local_id = PeerId::random();
+        let mut store = MemoryStore::new(local_id);
+        let key = random_multihash();
+        let local = ProviderRecord::new(key.clone(), local_id, Vec::new());
+        let remote = ProviderRecord::new(key, PeerId::random(), Vec::new());
+        assert!(store.add_provider(local).is_ok());
+        assert!(store.add_provider(remote.clone()).is_ok());
+        assert_eq!(store.third_party_providers(), vec![remote]);
+    }
 }
@@ -46,6 +46,8 @@ pub struct Config {
     timeout: Duration,
     /// The duration between outbound pings.
     interval: Duration,
+    /// Whether to skip pings on connections that recently received other traffic.
+    passive: bool,
 }
 
 impl Config {
@@ -53,6 +55,7 @@ impl Config {
     ///
     ///   * [`Config::with_interval`] 15s
     ///   * [`Config::with_timeout`] 20s
+    ///   * [`Config::with_passive_liveness`] false
     ///
     /// These settings have the following effect:
     ///
@@ -63,6 +66,7 @@ impl Config {
         Self {
             timeout: Duration::from_secs(20),
             interval: Duration::from_secs(15),
+            passive: false,
         }
     }
 
@@ -77,6 +81,17 @@ impl Config {
         self.interval = d;
         self
     }
+
+    /// Sets whether pings are only sent on otherwise silent connections.
+    ///
+    /// When enabled, receiving data from the remote on any other protocol within the ping
+    /// interval is taken as proof of liveness and the next ping is skipped. Only connections
+    /// that saw no inbound traffic during the interval are probed. Skipped pings neither
+    /// produce an [`Event`](crate::Event) nor an RTT measurement.
+    pub fn with_passive_liveness(mut self, passive: bool) -> Self {
+        self.passive = passive;
+        self
+    }
 }
 
 impl Default for Config {
@@ -146,6 +161,8 @@ pub struct Handler {
     inbound: Option<PongFuture>,
     /// Tracks the state of our handler.
     state: State,
+    /// Whether the remote sent us data on another protocol since the last ping.
+    remote_activity: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -172,9 +189,23 @@ impl Handler {
             outbound: None,
             inbound: None,
             state: State::Active,
+            remote_activity: false,
         }
     }
 
+    /// Whether the next ping can be skipped because of recent traffic from the remote.
+    ///
+    /// Resets the interval if so.
+    fn skip_ping(&mut self) -> bool {
+        if !self.config.passive || !std::mem::take(&mut self.remote_activity) {
+            return false;
+        }
+
+        tracing::trace!("skipping ping, connection saw recent traffic");
+        self.interval.reset(self.config.interval);
+        true
+    }
+
     fn on_dial_upgrade_error(
         &mut self,
         DialUpgradeError { error, .. }: DialUpgradeError<
@@ -204,12 +235,14 @@ impl Handler {
                 return;
             }
             // Note: This timeout only covers protocol negotiation.
-            StreamUpgradeError::Timeout => Failure::Other {
-                error: Box::new(std::io::Error::new(
-                    std::io::ErrorKind::TimedOut,
-                    "ping protocol negotiation timed out",
-                )),
-            },
+            StreamUpgradeError::Timeout | StreamUpgradeError::NegotiationTimeout => {
+                Failure::Other {
+                    error: Box::new(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        "ping protocol negotiation timed out",
+                    )),
+                }
+            }
             StreamUpgradeError::Apply(e) => void::unreachable(e),
             StreamUpgradeError::Io(e) => Failure::Other { error: Box::new(e) },
         };
@@ -309,7 +342,11 @@ impl ConnectionHandler for Handler {
                         self.outbound = Some(OutboundState::Idle(stream));
                         break;
                     }
+                    Poll::Ready(()) if self.skip_ping() => {
+                        self.outbound = Some(OutboundState::Idle(stream));
+                    }
                     Poll::Ready(()) => {
+                        self.remote_activity = false;
                         self.outbound = Some(OutboundState::Ping(
                             send_ping(stream, self.config.timeout).boxed(),
                         ));
@@ -321,7 +358,9 @@ impl ConnectionHandler for Handler {
                 }
                 None => match self.interval.poll_unpin(cx) {
                     Poll::Pending => break,
+                    Poll::Ready(()) if self.skip_ping() => {}
                     Poll::Ready(()) => {
+                        self.remote_activity = false;
                         self.outbound = Some(OutboundState::OpenStream);
                         let protocol = SubstreamProtocol::new(ReadyUpgrade::new(PROTOCOL_NAME), ());
                         return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
@@ -364,6 +403,7 @@ impl ConnectionHandler for Handler {
             ConnectionEvent::DialUpgradeError(dial_upgrade_error) => {
                 self.on_dial_upgrade_error(dial_upgrade_error)
             }
+            ConnectionEvent::RemoteActivity => self.remote_activity = true,
             _ => {}
         }
     }
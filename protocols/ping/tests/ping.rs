@@ -55,6 +55,50 @@ fn ping_pong() {
     QuickCheck::new().tests(10).quickcheck(prop as fn(_))
 }
 
+#[test]
+fn passive_liveness_pings_silent_connections() {
+    let cfg = ping::Config::new()
+        .with_interval(Duration::from_millis(10))
+        .with_passive_liveness(true);
+
+    let mut swarm1 = Swarm::new_ephemeral(|_| ping::Behaviour::new(cfg.clone()));
+    let mut swarm2 = Swarm::new_ephemeral(|_| ping::Behaviour::new(cfg.clone()));
+
+    async_std::task::block_on(async {
+        swarm1.listen().with_memory_addr_external().await;
+        swarm2.connect(&mut swarm1).await;
+
+        // Ping traffic itself doesn't count as activity, so silent connections keep being pinged.
+        for _ in 0..3 {
+            let ([e1], [e2]): ([ping::Event; 1], [ping::Event; 1]) =
+                libp2p_swarm_test::drive(&mut swarm1, &mut swarm2).await;
+
+            assert_ping_rtt_less_than_50ms(e1);
+            assert_ping_rtt_less_than_50ms(e2);
+        }
+    });
+}
+
+libp2p_swarm_test::matrix_test! {
+    fn ping_pong_on_all_runtimes_and_transports(runtime, transport) {
+        let cfg = ping::Config::new().with_interval(Duration::from_millis(10));
+
+        let mut swarm1 = Swarm::new_ephemeral_on(runtime, |_| ping::Behaviour::new(cfg.clone()));
+        let mut swarm2 = Swarm::new_ephemeral_on(runtime, |_| ping::Behaviour::new(cfg.clone()));
+
+        swarm1.listen().with_addr_external(transport).await;
+        swarm2.connect(&mut swarm1).await;
+
+        let ([e1], [e2]): ([ping::Event; 1], [ping::Event; 1]) =
+            libp2p_swarm_test::drive(&mut swarm1, &mut swarm2).await;
+
+        assert_eq!(&e1.peer, swarm2.local_peer_id());
+        assert_eq!(&e2.peer, swarm1.local_peer_id());
+        assert!(e1.result.is_ok());
+        assert!(e2.result.is_ok());
+    }
+}
+
 fn assert_ping_rtt_less_than_50ms(e: ping::Event) {
     let rtt = e.result.expect("a ping success");
 
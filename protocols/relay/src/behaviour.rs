@@ -22,7 +22,9 @@
 
 pub(crate) mod handler;
 pub(crate) mod rate_limiter;
+pub(crate) mod statistics;
 use crate::behaviour::handler::Handler;
+use crate::behaviour::statistics::{DenialReason, Statistics};
 use crate::multiaddr_ext::MultiaddrExt;
 use crate::proto;
 use crate::protocol::{inbound_hop, outbound_stop};
@@ -244,6 +246,8 @@ pub struct Behaviour {
     reservations: HashMap<PeerId, HashSet<ConnectionId>>,
     circuits: CircuitsTracker,
 
+    statistics: Statistics,
+
     /// Queue of actions to return when polled.
     queued_actions: VecDeque<ToSwarm<Event, THandlerInEvent<Self>>>,
 
@@ -253,6 +257,7 @@ pub struct Behaviour {
 impl Behaviour {
     pub fn new(local_peer_id: PeerId, config: Config) -> Self {
         Self {
+            statistics: Statistics::new(&config),
             config,
             local_peer_id,
             reservations: Default::default(),
@@ -262,6 +267,34 @@ impl Behaviour {
         }
     }
 
+    /// Returns a handle to the [`Statistics`] of the relay, e.g. to export them as metrics.
+    ///
+    /// The handle stays up to date with the behaviour.
+    pub fn statistics(&self) -> Statistics {
+        self.statistics.clone()
+    }
+
+    fn update_statistics(&self) {
+        self.statistics.set_reservations(
+            self.reservations.values().map(|cs| cs.len()).sum(),
+            self.reservations
+                .values()
+                .map(|cs| cs.len())
+                .max()
+                .unwrap_or(0),
+        );
+
+        let mut circuits_of_peer = HashMap::<PeerId, usize>::new();
+        for circuit in self.circuits.circuits.values() {
+            *circuits_of_peer.entry(circuit.src_peer_id).or_default() += 1;
+            *circuits_of_peer.entry(circuit.dst_peer_id).or_default() += 1;
+        }
+        self.statistics.set_circuits(
+            self.circuits.len(),
+            circuits_of_peer.into_values().max().unwrap_or(0),
+        );
+    }
+
     fn on_connection_closed(
         &mut self,
         ConnectionClosed {
@@ -291,6 +324,8 @@ impl Behaviour {
                     error: Some(std::io::ErrorKind::ConnectionAborted.into()),
                 }));
         }
+
+        self.update_statistics();
     }
 }
 
@@ -315,6 +350,7 @@ impl NetworkBehaviour for Behaviour {
                 reservation_duration: self.config.reservation_duration,
                 max_circuit_duration: self.config.max_circuit_duration,
                 max_circuit_bytes: self.config.max_circuit_bytes,
+                bytes_relayed: self.statistics.bytes_relayed_counter(),
             },
             ConnectedPoint::Listener {
                 local_addr: local_addr.clone(),
@@ -341,6 +377,7 @@ impl NetworkBehaviour for Behaviour {
                 reservation_duration: self.config.reservation_duration,
                 max_circuit_duration: self.config.max_circuit_duration,
                 max_circuit_bytes: self.config.max_circuit_bytes,
+                bytes_relayed: self.statistics.bytes_relayed_counter(),
             },
             ConnectedPoint::Dialer {
                 address: addr.clone(),
@@ -383,30 +420,37 @@ impl NetworkBehaviour for Behaviour {
                      denies all inbound substreams."
                 );
 
-                let action = if
-                // Deny if it is a new reservation and exceeds `max_reservations_per_peer`.
-                (!renewed
+                let denial_reason = if !renewed
                     && self
                         .reservations
                         .get(&event_source)
                         .map(|cs| cs.len())
                         .unwrap_or(0)
-                        > self.config.max_reservations_per_peer)
+                        > self.config.max_reservations_per_peer
+                {
+                    // Deny if it is a new reservation and exceeds `max_reservations_per_peer`.
+                    Some(DenialReason::PeerLimit)
+                } else if self.reservations.values().map(|cs| cs.len()).sum::<usize>()
+                    >= self.config.max_reservations
+                {
                     // Deny if it exceeds `max_reservations`.
-                    || self
-                        .reservations
-                        .values()
-                        .map(|cs| cs.len())
-                        .sum::<usize>()
-                        >= self.config.max_reservations
+                    Some(DenialReason::TotalLimit)
+                } else if !self
+                    .config
+                    .reservation_rate_limiters
+                    .iter_mut()
+                    .all(|limiter| {
+                        limiter.try_next(event_source, endpoint.get_remote_address(), now)
+                    })
+                {
                     // Deny if it exceeds the allowed rate of reservations.
-                    || !self
-                        .config
-                        .reservation_rate_limiters
-                        .iter_mut()
-                        .all(|limiter| {
-                            limiter.try_next(event_source, endpoint.get_remote_address(), now)
-                        }) {
+                    Some(DenialReason::RateLimit)
+                } else {
+                    None
+                };
+
+                let action = if let Some(reason) = denial_reason {
+                    self.statistics.record_reservation_denied(reason);
                     ToSwarm::NotifyHandler {
                         handler: NotifyHandler::One(connection),
                         peer_id: event_source,
@@ -517,17 +561,28 @@ impl NetworkBehaviour for Behaviour {
                      denies all inbound substreams."
                 );
 
-                let action = if self.circuits.num_circuits_of_peer(event_source)
+                let denial_reason = if self.circuits.num_circuits_of_peer(event_source)
                     > self.config.max_circuits_per_peer
-                    || self.circuits.len() >= self.config.max_circuits
-                    || !self
-                        .config
-                        .circuit_src_rate_limiters
-                        .iter_mut()
-                        .all(|limiter| {
-                            limiter.try_next(event_source, endpoint.get_remote_address(), now)
-                        }) {
+                {
+                    Some(DenialReason::PeerLimit)
+                } else if self.circuits.len() >= self.config.max_circuits {
+                    Some(DenialReason::TotalLimit)
+                } else if !self
+                    .config
+                    .circuit_src_rate_limiters
+                    .iter_mut()
+                    .all(|limiter| {
+                        limiter.try_next(event_source, endpoint.get_remote_address(), now)
+                    })
+                {
+                    Some(DenialReason::RateLimit)
+                } else {
+                    None
+                };
+
+                let action = if let Some(reason) = denial_reason {
                     // Deny circuit exceeding limits.
+                    self.statistics.record_circuit_denied(reason);
                     ToSwarm::NotifyHandler {
                         handler: NotifyHandler::One(connection),
                         peer_id: event_source,
@@ -563,6 +618,8 @@ impl NetworkBehaviour for Behaviour {
                     }
                 } else {
                     // Deny circuit request if no reservation present.
+                    self.statistics
+                        .record_circuit_denied(DenialReason::NoReservation);
                     ToSwarm::NotifyHandler {
                         handler: NotifyHandler::One(connection),
                         peer_id: event_source,
@@ -635,6 +692,8 @@ impl NetworkBehaviour for Behaviour {
                 status,
                 error,
             } => {
+                self.statistics
+                    .record_circuit_denied(DenialReason::ConnectionFailed);
                 self.queued_actions.push_back(ToSwarm::NotifyHandler {
                     handler: NotifyHandler::One(src_connection_id),
                     peer_id: src_peer_id,
@@ -694,6 +753,8 @@ impl NetworkBehaviour for Behaviour {
                     }));
             }
         }
+
+        self.update_statistics();
     }
 
     #[tracing::instrument(level = "trace", name = "NetworkBehaviour::poll", skip(self))]
@@ -39,6 +39,8 @@ use libp2p_swarm::{
     StreamUpgradeError, SubstreamProtocol,
 };
 use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use std::{fmt, io};
@@ -52,6 +54,7 @@ pub struct Config {
     pub reservation_duration: Duration,
     pub max_circuit_duration: Duration,
     pub max_circuit_bytes: u64,
+    pub bytes_relayed: Arc<AtomicU64>,
 }
 
 pub enum In {
@@ -446,7 +449,9 @@ impl Handler {
         >,
     ) {
         let error = match error {
-            StreamUpgradeError::Timeout => outbound_stop::Error::Io(io::ErrorKind::TimedOut.into()),
+            StreamUpgradeError::Timeout | StreamUpgradeError::NegotiationTimeout => {
+                outbound_stop::Error::Io(io::ErrorKind::TimedOut.into())
+            }
             StreamUpgradeError::NegotiationFailed => outbound_stop::Error::Unsupported,
             StreamUpgradeError::Io(e) => outbound_stop::Error::Io(e),
             StreamUpgradeError::Apply(v) => void::unreachable(v),
@@ -753,6 +758,7 @@ impl ConnectionHandler for Handler {
                     } = parts;
                     let max_circuit_duration = self.config.max_circuit_duration;
                     let max_circuit_bytes = self.config.max_circuit_bytes;
+                    let bytes_relayed = self.config.bytes_relayed.clone();
 
                     let circuit = async move {
                         let (result_1, result_2) = futures::future::join(
@@ -768,6 +774,7 @@ impl ConnectionHandler for Handler {
                             dst_stream,
                             max_circuit_duration,
                             max_circuit_bytes,
+                            bytes_relayed,
                         )
                         .await?;
 
@@ -0,0 +1,193 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::behaviour::Config;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The reason a reservation or circuit request was denied by the relay.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum DenialReason {
+    /// The source peer reached [`Config::max_reservations_per_peer`] or
+    /// [`Config::max_circuits_per_peer`].
+    PeerLimit,
+    /// The relay reached [`Config::max_reservations`] or [`Config::max_circuits`].
+    TotalLimit,
+    /// The request was denied by one of the configured [`RateLimiter`](crate::RateLimiter)s.
+    RateLimit,
+    /// The destination of a circuit has no reservation with the relay.
+    NoReservation,
+    /// The relay failed to connect to the destination of a circuit.
+    ConnectionFailed,
+}
+
+/// Point-in-time statistics of a relay [`Behaviour`](crate::Behaviour), see
+/// [`Behaviour::statistics`](crate::Behaviour::statistics).
+///
+/// Clones share the same state and thus stay up to date with the behaviour.
+#[derive(Debug, Clone)]
+pub struct Statistics {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    max_reservations: usize,
+    max_reservations_per_peer: usize,
+    max_circuits: usize,
+    max_circuits_per_peer: usize,
+
+    active_reservations: AtomicUsize,
+    max_active_reservations_of_peer: AtomicUsize,
+    active_circuits: AtomicUsize,
+    max_active_circuits_of_peer: AtomicUsize,
+    bytes_relayed: Arc<AtomicU64>,
+    reservation_denials: Mutex<HashMap<DenialReason, u64>>,
+    circuit_denials: Mutex<HashMap<DenialReason, u64>>,
+}
+
+impl Statistics {
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                max_reservations: config.max_reservations,
+                max_reservations_per_peer: config.max_reservations_per_peer,
+                max_circuits: config.max_circuits,
+                max_circuits_per_peer: config.max_circuits_per_peer,
+                active_reservations: AtomicUsize::new(0),
+                max_active_reservations_of_peer: AtomicUsize::new(0),
+                active_circuits: AtomicUsize::new(0),
+                max_active_circuits_of_peer: AtomicUsize::new(0),
+                bytes_relayed: Default::default(),
+                reservation_denials: Default::default(),
+                circuit_denials: Default::default(),
+            }),
+        }
+    }
+
+    /// The number of active reservations, across all peers.
+    pub fn active_reservations(&self) -> usize {
+        self.inner.active_reservations.load(Ordering::Relaxed)
+    }
+
+    /// The number of active reservations of the peer with the most reservations.
+    pub fn max_active_reservations_of_peer(&self) -> usize {
+        self.inner
+            .max_active_reservations_of_peer
+            .load(Ordering::Relaxed)
+    }
+
+    /// The number of active circuits, including circuits that are still being established.
+    pub fn active_circuits(&self) -> usize {
+        self.inner.active_circuits.load(Ordering::Relaxed)
+    }
+
+    /// The number of active circuits of the peer with the most circuits.
+    pub fn max_active_circuits_of_peer(&self) -> usize {
+        self.inner
+            .max_active_circuits_of_peer
+            .load(Ordering::Relaxed)
+    }
+
+    /// The total number of bytes relayed over circuits, in both directions.
+    pub fn bytes_relayed(&self) -> u64 {
+        self.inner.bytes_relayed.load(Ordering::Relaxed)
+    }
+
+    /// The number of denied reservation requests, by reason.
+    pub fn reservation_denials(&self) -> HashMap<DenialReason, u64> {
+        self.inner
+            .reservation_denials
+            .lock()
+            .expect("lock not to be poisoned")
+            .clone()
+    }
+
+    /// The number of denied circuit requests, by reason.
+    pub fn circuit_denials(&self) -> HashMap<DenialReason, u64> {
+        self.inner
+            .circuit_denials
+            .lock()
+            .expect("lock not to be poisoned")
+            .clone()
+    }
+
+    /// See [`Config::max_reservations`].
+    pub fn max_reservations(&self) -> usize {
+        self.inner.max_reservations
+    }
+
+    /// See [`Config::max_reservations_per_peer`].
+    pub fn max_reservations_per_peer(&self) -> usize {
+        self.inner.max_reservations_per_peer
+    }
+
+    /// See [`Config::max_circuits`].
+    pub fn max_circuits(&self) -> usize {
+        self.inner.max_circuits
+    }
+
+    /// See [`Config::max_circuits_per_peer`].
+    pub fn max_circuits_per_peer(&self) -> usize {
+        self.inner.max_circuits_per_peer
+    }
+
+    pub(crate) fn set_reservations(&self, active: usize, max_of_peer: usize) {
+        self.inner
+            .active_reservations
+            .store(active, Ordering::Relaxed);
+        self.inner
+            .max_active_reservations_of_peer
+            .store(max_of_peer, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_circuits(&self, active: usize, max_of_peer: usize) {
+        self.inner.active_circuits.store(active, Ordering::Relaxed);
+        self.inner
+            .max_active_circuits_of_peer
+            .store(max_of_peer, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_reservation_denied(&self, reason: DenialReason) {
+        *self
+            .inner
+            .reservation_denials
+            .lock()
+            .expect("lock not to be poisoned")
+            .entry(reason)
+            .or_default() += 1;
+    }
+
+    pub(crate) fn record_circuit_denied(&self, reason: DenialReason) {
+        *self
+            .inner
+            .circuit_denials
+            .lock()
+            .expect("lock not to be poisoned")
+            .entry(reason)
+            .or_default() += 1;
+    }
+
+    /// The counter of relayed bytes, shared with the circuits driven by the handlers.
+    pub(crate) fn bytes_relayed_counter(&self) -> Arc<AtomicU64> {
+        self.inner.bytes_relayed.clone()
+    }
+}
@@ -32,6 +32,8 @@ use futures::ready;
 use futures_timer::Delay;
 use std::io;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 
@@ -42,6 +44,8 @@ pub(crate) struct CopyFuture<S, D> {
     max_circuit_duration: Delay,
     max_circuit_bytes: u64,
     bytes_sent: u64,
+    /// Counter of the bytes relayed, shared across circuits.
+    bytes_relayed: Arc<AtomicU64>,
 }
 
 impl<S: AsyncRead, D: AsyncRead> CopyFuture<S, D> {
@@ -50,6 +54,7 @@ impl<S: AsyncRead, D: AsyncRead> CopyFuture<S, D> {
         dst: D,
         max_circuit_duration: Duration,
         max_circuit_bytes: u64,
+        bytes_relayed: Arc<AtomicU64>,
     ) -> Self {
         CopyFuture {
             src: BufReader::new(src),
@@ -57,6 +62,7 @@ impl<S: AsyncRead, D: AsyncRead> CopyFuture<S, D> {
             max_circuit_duration: Delay::new(max_circuit_duration),
             max_circuit_bytes,
             bytes_sent: Default::default(),
+            bytes_relayed,
         }
     }
 }
@@ -90,6 +96,7 @@ where
                 Poll::Ready(Ok(0)) => Status::Done,
                 Poll::Ready(Ok(i)) => {
                     this.bytes_sent += i;
+                    this.bytes_relayed.fetch_add(i, Ordering::Relaxed);
                     Status::Progressed
                 }
                 Poll::Pending => Status::Pending,
@@ -100,6 +107,7 @@ where
                 Poll::Ready(Ok(0)) => Status::Done,
                 Poll::Ready(Ok(i)) => {
                     this.bytes_sent += i;
+                    this.bytes_relayed.fetch_add(i, Ordering::Relaxed);
                     Status::Progressed
                 }
                 Poll::Pending => Status::Pending,
@@ -222,15 +230,21 @@ mod tests {
                 write: Vec::new(),
             };
 
+            let bytes_relayed = Arc::new(AtomicU64::new(0));
             let mut copy_future = CopyFuture::new(
                 connection_a,
                 connection_b,
                 Duration::from_secs(60),
                 max_circuit_bytes,
+                bytes_relayed.clone(),
             );
 
             match block_on(&mut copy_future) {
                 Ok(()) => {
+                    assert_eq!(
+                        bytes_relayed.load(Ordering::Relaxed),
+                        (a.len() + b.len()) as u64
+                    );
                     assert_eq!(copy_future.src.into_inner().write, b);
                     assert_eq!(copy_future.dst.into_inner().write, a);
                 }
@@ -288,6 +302,7 @@ mod tests {
             PendingConnection {},
             Duration::from_millis(1),
             u64::MAX,
+            Default::default(),
         );
 
         std::thread::sleep(Duration::from_millis(2));
@@ -0,0 +1,298 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Experimental relaying of datagrams.
+//!
+//! Circuits relay a reliable stream of bytes. Protocols that only need to deliver small,
+//! independent messages and tolerate loss, e.g. protocols designed for QUIC datagrams or WebRTC
+//! data channels, can instead send datagrams through a relay with [`Behaviour::send`].
+//!
+//! A relay, i.e. a [`Behaviour`] with [`Config::relay`] set, forwards each datagram to its
+//! destination on a best-effort basis: it drops the datagram if it is not connected to the
+//! destination or if too many datagrams to the destination are in flight already. Datagrams are
+//! neither acknowledged nor retransmitted, and their data is not protected from the relay.
+//!
+//! The wire protocol is experimental and may change in a backwards-incompatible way.
+
+use crate::protocol::datagram::{Datagram, DatagramProtocol, HandlerEvent};
+use bytes::Bytes;
+use libp2p_core::{transport::PortUse, Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use libp2p_swarm::{
+    behaviour::{ConnectionClosed, ConnectionEstablished, FromSwarm},
+    ConnectionDenied, ConnectionId, NetworkBehaviour, NotifyHandler, OneShotHandler,
+    OneShotHandlerConfig, SubstreamProtocol, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::task::{Context, Poll};
+use thiserror::Error;
+
+pub use crate::protocol::datagram::DATAGRAM_PROTOCOL_NAME;
+
+/// Configuration for the datagram [`Behaviour`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Whether to forward datagrams received from other peers to their destination.
+    pub relay: bool,
+    /// The maximum size of the data of a datagram, both sent and received.
+    pub max_datagram_size: usize,
+    /// The maximum number of datagrams in flight to a single peer. Further datagrams to the peer
+    /// are dropped.
+    pub max_pending_datagrams: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            relay: false,
+            max_datagram_size: 1200,
+            max_pending_datagrams: 16,
+        }
+    }
+}
+
+/// The events produced by the datagram [`Behaviour`].
+#[derive(Debug)]
+pub enum Event {
+    /// A datagram has been received through a relay.
+    Received {
+        relay: PeerId,
+        src_peer_id: PeerId,
+        data: Bytes,
+    },
+    /// A datagram has been forwarded to its destination.
+    Relayed {
+        src_peer_id: PeerId,
+        dst_peer_id: PeerId,
+    },
+    /// A datagram to be forwarded has been dropped.
+    Dropped {
+        src_peer_id: PeerId,
+        dst_peer_id: PeerId,
+        reason: DropReason,
+    },
+}
+
+/// The reason for a datagram to be dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    /// We are not connected to the peer.
+    NotConnected,
+    /// Too many datagrams to the peer are in flight, see [`Config::max_pending_datagrams`].
+    TooManyPending,
+}
+
+/// The error returned by [`Behaviour::send`].
+#[derive(Debug, Error)]
+pub enum SendError {
+    #[error("Datagram exceeds the maximum size.")]
+    TooLarge,
+    #[error("Not connected to the relay.")]
+    NotConnected,
+    #[error("Too many datagrams to the relay in flight.")]
+    TooManyPending,
+}
+
+impl From<DropReason> for SendError {
+    fn from(reason: DropReason) -> Self {
+        match reason {
+            DropReason::NotConnected => SendError::NotConnected,
+            DropReason::TooManyPending => SendError::TooManyPending,
+        }
+    }
+}
+
+/// [`NetworkBehaviour`] sending, receiving and optionally relaying datagrams.
+pub struct Behaviour {
+    config: Config,
+    /// The peers we are connected to.
+    connected: HashSet<PeerId>,
+    /// The number of datagrams in flight per peer.
+    pending: HashMap<PeerId, usize>,
+    queued_events: VecDeque<ToSwarm<Event, THandlerInEvent<Self>>>,
+}
+
+impl Behaviour {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            connected: HashSet::new(),
+            pending: HashMap::new(),
+            queued_events: VecDeque::new(),
+        }
+    }
+
+    /// Sends `data` to `dst_peer_id` through `relay`, which has to be connected.
+    ///
+    /// Returning `Ok` does not imply that the datagram will be delivered.
+    pub fn send(
+        &mut self,
+        relay: PeerId,
+        dst_peer_id: PeerId,
+        data: impl Into<Bytes>,
+    ) -> Result<(), SendError> {
+        let data = data.into();
+        if data.len() > self.config.max_datagram_size {
+            return Err(SendError::TooLarge);
+        }
+        self.enqueue(
+            relay,
+            Datagram {
+                peer: dst_peer_id,
+                data,
+            },
+        )?;
+        Ok(())
+    }
+
+    fn enqueue(&mut self, peer_id: PeerId, datagram: Datagram) -> Result<(), DropReason> {
+        if !self.connected.contains(&peer_id) {
+            return Err(DropReason::NotConnected);
+        }
+        let pending = self.pending.entry(peer_id).or_default();
+        if *pending >= self.config.max_pending_datagrams {
+            return Err(DropReason::TooManyPending);
+        }
+        *pending += 1;
+        self.queued_events.push_back(ToSwarm::NotifyHandler {
+            peer_id,
+            handler: NotifyHandler::Any,
+            event: datagram,
+        });
+        Ok(())
+    }
+
+    fn on_datagram(&mut self, peer_id: PeerId, datagram: Datagram) {
+        if !self.config.relay {
+            self.queued_events
+                .push_back(ToSwarm::GenerateEvent(Event::Received {
+                    relay: peer_id,
+                    src_peer_id: datagram.peer,
+                    data: datagram.data,
+                }));
+            return;
+        }
+
+        let dst_peer_id = datagram.peer;
+        let event = match self.enqueue(
+            dst_peer_id,
+            Datagram {
+                peer: peer_id,
+                data: datagram.data,
+            },
+        ) {
+            Ok(()) => Event::Relayed {
+                src_peer_id: peer_id,
+                dst_peer_id,
+            },
+            Err(reason) => {
+                tracing::debug!(src=%peer_id, dst=%dst_peer_id, ?reason, "Dropping datagram");
+                Event::Dropped {
+                    src_peer_id: peer_id,
+                    dst_peer_id,
+                    reason,
+                }
+            }
+        };
+        self.queued_events.push_back(ToSwarm::GenerateEvent(event));
+    }
+
+    fn handler(&self) -> THandler<Self> {
+        OneShotHandler::new(
+            SubstreamProtocol::new(
+                DatagramProtocol {
+                    max_datagram_size: self.config.max_datagram_size,
+                },
+                (),
+            ),
+            OneShotHandlerConfig::default(),
+        )
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = OneShotHandler<DatagramProtocol, Datagram, HandlerEvent>;
+    type ToSwarm = Event;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(self.handler())
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+        _: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(self.handler())
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished { peer_id, .. }) => {
+                self.connected.insert(peer_id);
+            }
+            FromSwarm::ConnectionClosed(ConnectionClosed {
+                peer_id,
+                remaining_established: 0,
+                ..
+            }) => {
+                self.connected.remove(&peer_id);
+                self.pending.remove(&peer_id);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        match event {
+            Ok(HandlerEvent::Received(datagram)) => self.on_datagram(peer_id, datagram),
+            Ok(HandlerEvent::Sent) | Err(_) => {
+                if let Err(e) = &event {
+                    tracing::debug!(peer=%peer_id, "Failed to send datagram: {e}");
+                }
+                if let Some(pending) = self.pending.get_mut(&peer_id) {
+                    *pending = pending.saturating_sub(1);
+                }
+            }
+        }
+    }
+
+    fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        if let Some(event) = self.queued_events.pop_front() {
+            return Poll::Ready(event);
+        }
+
+        Poll::Pending
+    }
+}
@@ -344,3 +344,38 @@ impl MessageWrite for Limit {
     }
 }
 
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct DatagramMessage {
+    pub peer: message_v2::pb::Peer,
+    pub data: Vec<u8>,
+}
+
+impl<'a> MessageRead<'a> for DatagramMessage {
+    fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> Result<Self> {
+        let mut msg = Self::default();
+        while !r.is_eof() {
+            match r.next_tag(bytes) {
+                Ok(10) => msg.peer = r.read_message::<message_v2::pb::Peer>(bytes)?,
+                Ok(18) => msg.data = r.read_bytes(bytes)?.to_owned(),
+                Ok(t) => { r.read_unknown(bytes, t)?; }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(msg)
+    }
+}
+
+impl MessageWrite for DatagramMessage {
+    fn get_size(&self) -> usize {
+        0
+        + 1 + sizeof_len((&self.peer).get_size())
+        + 1 + sizeof_len((&self.data).len())
+    }
+
+    fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
+        w.write_with_tag(10, |w| w.write_message(&self.peer))?;
+        w.write_with_tag(18, |w| w.write_bytes(&**&self.data))?;
+        Ok(())
+    }
+}
@@ -25,6 +25,7 @@
 
 mod behaviour;
 mod copy_future;
+pub mod datagram;
 mod multiaddr_ext;
 mod priv_client;
 mod protocol;
@@ -35,11 +36,15 @@ mod proto {
     pub(crate) use self::message_v2::pb::mod_HopMessage::Type as HopMessageType;
     pub use self::message_v2::pb::mod_StopMessage::Type as StopMessageType;
     pub(crate) use self::message_v2::pb::{
-        HopMessage, Limit, Peer, Reservation, Status, StopMessage,
+        DatagramMessage, HopMessage, Limit, Peer, Reservation, Status, StopMessage,
     };
 }
 
-pub use behaviour::{rate_limiter::RateLimiter, Behaviour, CircuitId, Config, Event};
+pub use behaviour::{
+    rate_limiter::RateLimiter,
+    statistics::{DenialReason, Statistics},
+    Behaviour, CircuitId, Config, Event,
+};
 pub use protocol::{HOP_PROTOCOL_NAME, STOP_PROTOCOL_NAME};
 
 /// Types related to the relay protocol inbound.
@@ -64,7 +69,9 @@ pub mod outbound {
 
 /// Everything related to the relay protocol from a client's perspective.
 pub mod client {
-    pub use crate::priv_client::{new, transport::Transport, Behaviour, Connection, Event};
+    pub use crate::priv_client::{
+        new, transport::Transport, Behaviour, Connection, Event, RelayUsage, ReservationInfo,
+    };
 
     pub mod transport {
         pub use crate::priv_client::transport::Error;
@@ -28,27 +28,37 @@ use crate::priv_client::handler::Handler;
 use crate::protocol::{self, inbound_stop};
 use bytes::Bytes;
 use either::Either;
-use futures::channel::mpsc::Receiver;
+use futures::channel::mpsc::{self, Receiver};
 use futures::future::{BoxFuture, FutureExt};
 use futures::io::{AsyncRead, AsyncWrite};
 use futures::ready;
 use futures::stream::StreamExt;
 use libp2p_core::multiaddr::Protocol;
-use libp2p_core::transport::PortUse;
-use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_core::transport::{ListenerId, PortUse};
+use libp2p_core::{Endpoint, Multiaddr, SignedEnvelope};
 use libp2p_identity::PeerId;
-use libp2p_swarm::behaviour::{ConnectionClosed, ConnectionEstablished, FromSwarm};
-use libp2p_swarm::dial_opts::DialOpts;
+use libp2p_swarm::behaviour::{ConnectionClosed, ConnectionEstablished, FromSwarm, ListenerClosed};
+use libp2p_swarm::dial_opts::{DialOpts, DialPriority};
 use libp2p_swarm::{
-    dummy, ConnectionDenied, ConnectionHandler, ConnectionId, DialFailure, NetworkBehaviour,
-    NotifyHandler, Stream, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+    dummy, ConnectionDenied, ConnectionHandler, ConnectionId, DialFailure, ListenOpts,
+    NetworkBehaviour, NotifyHandler, Stream, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
 use std::collections::{hash_map, HashMap, VecDeque};
 use std::io::{Error, ErrorKind, IoSlice};
+use std::num::NonZeroU32;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use transport::Transport;
 use void::Void;
+use web_time::{Instant, SystemTime};
+
+/// Default fraction of a circuit's limit after which the circuit is considered to be near it.
+const DEFAULT_NEAR_LIMIT_THRESHOLD: f64 = 0.9;
+/// Default number of consecutive circuits near their limit after which we switch relays.
+const DEFAULT_MAX_CIRCUITS_NEAR_LIMIT: u32 = 3;
+/// Number of circuit usage reports buffered until the [`Behaviour`] is polled.
+const MAX_PENDING_CIRCUIT_USAGE_REPORTS: usize = 1000;
 
 /// The events produced by the client `Behaviour`.
 #[derive(Debug)]
@@ -59,6 +69,8 @@ pub enum Event {
         /// Indicates whether the request replaces an existing reservation.
         renewal: bool,
         limit: Option<protocol::Limit>,
+        /// Expiry and voucher of the accepted reservation.
+        info: ReservationInfo,
     },
     OutboundCircuitEstablished {
         relay_peer_id: PeerId,
@@ -69,6 +81,76 @@ pub enum Event {
         src_peer_id: PeerId,
         limit: Option<protocol::Limit>,
     },
+    /// Circuits via a relay consistently came close to their limits and a
+    /// reservation is being made on an alternative relay.
+    ///
+    /// See [`Behaviour::add_alternative_relay`].
+    RelaySwitchStarted {
+        from_relay_peer_id: PeerId,
+        to_relay_peer_id: PeerId,
+    },
+    /// A reservation on an alternative relay has been accepted. The address
+    /// reserved on the previous relay is no longer advertised and the
+    /// corresponding listener has been removed.
+    RelaySwitched {
+        from_relay_peer_id: PeerId,
+        to_relay_peer_id: PeerId,
+    },
+}
+
+/// Data transferred over circuits via a single relay.
+#[derive(Debug, Clone, Default)]
+pub struct RelayUsage {
+    bytes_read: u64,
+    bytes_written: u64,
+    circuits: u64,
+    circuits_near_limit: u64,
+    /// Number of most recent circuits that all came close to their limit.
+    consecutive_circuits_near_limit: u32,
+}
+
+impl RelayUsage {
+    /// Total number of bytes read from closed circuits via the relay.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Total number of bytes written to closed circuits via the relay.
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Number of closed circuits via the relay.
+    pub fn circuits(&self) -> u64 {
+        self.circuits
+    }
+
+    /// Number of closed circuits via the relay that came close to the limit set by the relay.
+    pub fn circuits_near_limit(&self) -> u64 {
+        self.circuits_near_limit
+    }
+}
+
+/// Details of a reservation accepted by a relay.
+#[derive(Debug, Clone)]
+pub struct ReservationInfo {
+    pub(crate) expires_at: SystemTime,
+    pub(crate) voucher: Option<SignedEnvelope>,
+}
+
+impl ReservationInfo {
+    /// Point in time at which the reservation expires unless renewed.
+    pub fn expires_at(&self) -> SystemTime {
+        self.expires_at
+    }
+
+    /// The reservation voucher signed by the relay, if the relay provided a valid one.
+    ///
+    /// The voucher proves to third parties that the relay accepted a reservation
+    /// for the local peer and can thus be embedded in out-of-band signaling.
+    pub fn voucher(&self) -> Option<&SignedEnvelope> {
+        self.voucher.as_ref()
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -96,11 +178,29 @@ pub struct Behaviour {
     queued_actions: VecDeque<ToSwarm<Event, Either<handler::In, Void>>>,
 
     pending_handler_commands: HashMap<ConnectionId, handler::In>,
+
+    /// Listeners for reservations, indexed by the relay they are reserved on.
+    relay_listeners: HashMap<PeerId, ListenerId>,
+
+    circuit_usage_sender: mpsc::Sender<CircuitUsageReport>,
+    circuit_usage_receiver: Receiver<CircuitUsageReport>,
+    relay_usage: HashMap<PeerId, RelayUsage>,
+    /// Details of the latest accepted reservation, indexed by relay.
+    reservation_infos: HashMap<PeerId, ReservationInfo>,
+
+    /// Relays to make a reservation on when the current relay nears its limits.
+    alternative_relays: Vec<Multiaddr>,
+    /// Ongoing switches, indexed by the relay switched to, with the relay switched from as value.
+    pending_switches: HashMap<PeerId, PeerId>,
+    near_limit_threshold: f64,
+    max_circuits_near_limit: NonZeroU32,
 }
 
 /// Create a new client relay [`Behaviour`] with it's corresponding [`Transport`].
 pub fn new(local_peer_id: PeerId) -> (Transport, Behaviour) {
     let (transport, from_transport) = Transport::new();
+    let (circuit_usage_sender, circuit_usage_receiver) =
+        mpsc::channel(MAX_PENDING_CIRCUIT_USAGE_REPORTS);
     let behaviour = Behaviour {
         local_peer_id,
         from_transport,
@@ -108,11 +208,160 @@ pub fn new(local_peer_id: PeerId) -> (Transport, Behaviour) {
         reservation_addresses: Default::default(),
         queued_actions: Default::default(),
         pending_handler_commands: Default::default(),
+        relay_listeners: Default::default(),
+        circuit_usage_sender,
+        circuit_usage_receiver,
+        relay_usage: Default::default(),
+        reservation_infos: Default::default(),
+        alternative_relays: Default::default(),
+        pending_switches: Default::default(),
+        near_limit_threshold: DEFAULT_NEAR_LIMIT_THRESHOLD,
+        max_circuits_near_limit: NonZeroU32::new(DEFAULT_MAX_CIRCUITS_NEAR_LIMIT)
+            .expect("not zero"),
     };
     (transport, behaviour)
 }
 
 impl Behaviour {
+    /// Adds a relay to make a reservation on in case circuits via a relay we
+    /// have a reservation on consistently come close to their limits.
+    ///
+    /// The address must end with `/p2p/<relay-peer-id>`. Once the reservation on
+    /// the alternative relay is accepted, the listener of the reservation on the
+    /// previous relay is removed, thus its address is no longer advertised.
+    pub fn add_alternative_relay(&mut self, relay_addr: Multiaddr) {
+        if relay_peer_id(&relay_addr).is_none() {
+            tracing::debug!(address=%relay_addr, "Ignoring alternative relay without peer ID");
+            return;
+        }
+        if !self.alternative_relays.contains(&relay_addr) {
+            self.alternative_relays.push(relay_addr);
+        }
+    }
+
+    /// Sets the fraction of a circuit's data or duration limit after which the
+    /// circuit is considered to be near its limit.
+    ///
+    /// Defaults to `0.9`.
+    pub fn set_near_limit_threshold(&mut self, threshold: f64) {
+        self.near_limit_threshold = threshold;
+    }
+
+    /// Sets the number of consecutive circuits via a relay that have to come close
+    /// to their limits before switching to an alternative relay.
+    ///
+    /// Defaults to `3`.
+    pub fn set_max_circuits_near_limit(&mut self, circuits: NonZeroU32) {
+        self.max_circuits_near_limit = circuits;
+    }
+
+    /// Returns the data transferred over closed circuits via the given relay.
+    pub fn relay_usage(&self, relay_peer_id: &PeerId) -> Option<&RelayUsage> {
+        self.relay_usage.get(relay_peer_id)
+    }
+
+    /// Returns the expiry and voucher of the currently accepted reservation on the given relay.
+    pub fn reservation_info(&self, relay_peer_id: &PeerId) -> Option<&ReservationInfo> {
+        self.reservation_infos.get(relay_peer_id)
+    }
+
+    fn on_circuit_usage(&mut self, report: CircuitUsageReport) {
+        let near_limit = report.limit.is_some_and(|limit| {
+            let data_near_limit = limit.data_in_bytes().is_some_and(|max| {
+                u64::max(report.bytes_read, report.bytes_written) as f64
+                    >= self.near_limit_threshold * max as f64
+            });
+            let duration_near_limit = limit.duration().is_some_and(|max| {
+                report.duration.as_secs_f64() >= self.near_limit_threshold * max.as_secs_f64()
+            });
+            data_near_limit || duration_near_limit
+        });
+
+        let usage = self.relay_usage.entry(report.relay_peer_id).or_default();
+        usage.bytes_read += report.bytes_read;
+        usage.bytes_written += report.bytes_written;
+        usage.circuits += 1;
+        if !near_limit {
+            usage.consecutive_circuits_near_limit = 0;
+            return;
+        }
+        usage.circuits_near_limit += 1;
+        usage.consecutive_circuits_near_limit += 1;
+
+        if usage.consecutive_circuits_near_limit >= self.max_circuits_near_limit.get() {
+            usage.consecutive_circuits_near_limit = 0;
+            self.switch_relay(report.relay_peer_id);
+        }
+    }
+
+    /// Makes a reservation on an alternative relay to replace the reservation on `from`.
+    fn switch_relay(&mut self, from: PeerId) {
+        if !self.relay_listeners.contains_key(&from)
+            || self.pending_switches.values().any(|p| *p == from)
+        {
+            return;
+        }
+
+        let candidate = self
+            .alternative_relays
+            .iter()
+            .filter_map(|addr| Some((relay_peer_id(addr)?, addr)))
+            .find(|(to, _)| {
+                !self.relay_listeners.contains_key(to) && !self.pending_switches.contains_key(to)
+            })
+            .map(|(to, addr)| (to, addr.clone()));
+        let Some((to, relay_addr)) = candidate else {
+            tracing::debug!(
+                relay=%from,
+                "Circuits via relay near their limits but no alternative relay is available"
+            );
+            return;
+        };
+
+        tracing::debug!(%from, %to, "Switching relays");
+        self.pending_switches.insert(to, from);
+        self.queued_actions.push_back(ToSwarm::ListenOn {
+            opts: ListenOpts::new(relay_addr.with(Protocol::P2pCircuit)),
+        });
+        self.queued_actions
+            .push_back(ToSwarm::GenerateEvent(Event::RelaySwitchStarted {
+                from_relay_peer_id: from,
+                to_relay_peer_id: to,
+            }));
+    }
+
+    /// Completes a switch to `to` once a reservation on it has been accepted.
+    fn complete_switch(&mut self, to: PeerId) {
+        let Some(from) = self.pending_switches.remove(&to) else {
+            return;
+        };
+
+        let connections = self
+            .directly_connected_peers
+            .get(&from)
+            .cloned()
+            .unwrap_or_default();
+        for connection_id in connections {
+            if let Some((addr, ReservationStatus::Confirmed)) =
+                self.reservation_addresses.remove(&connection_id)
+            {
+                self.queued_actions
+                    .push_back(ToSwarm::ExternalAddrExpired(addr));
+            }
+        }
+        if let Some(id) = self.relay_listeners.remove(&from) {
+            self.queued_actions
+                .push_back(ToSwarm::RemoveListener { id });
+        }
+        self.reservation_infos.remove(&from);
+
+        self.queued_actions
+            .push_back(ToSwarm::GenerateEvent(Event::RelaySwitched {
+                from_relay_peer_id: from,
+                to_relay_peer_id: to,
+            }));
+    }
+
     fn on_connection_closed(
         &mut self,
         ConnectionClosed {
@@ -143,6 +392,7 @@ impl Behaviour {
             if let Some((addr, ReservationStatus::Confirmed)) =
                 self.reservation_addresses.remove(&connection_id)
             {
+                self.reservation_infos.remove(&peer_id);
                 self.queued_actions
                     .push_back(ToSwarm::ExternalAddrExpired(addr));
             }
@@ -164,7 +414,12 @@ impl NetworkBehaviour for Behaviour {
         if local_addr.is_relayed() {
             return Ok(Either::Right(dummy::ConnectionHandler));
         }
-        let mut handler = Handler::new(self.local_peer_id, peer, remote_addr.clone());
+        let mut handler = Handler::new(
+            self.local_peer_id,
+            peer,
+            remote_addr.clone(),
+            self.circuit_usage_sender.clone(),
+        );
 
         if let Some(event) = self.pending_handler_commands.remove(&connection_id) {
             handler.on_behaviour_event(event)
@@ -185,7 +440,12 @@ impl NetworkBehaviour for Behaviour {
             return Ok(Either::Right(dummy::ConnectionHandler));
         }
 
-        let mut handler = Handler::new(self.local_peer_id, peer, addr.clone());
+        let mut handler = Handler::new(
+            self.local_peer_id,
+            peer,
+            addr.clone(),
+            self.circuit_usage_sender.clone(),
+        );
 
         if let Some(event) = self.pending_handler_commands.remove(&connection_id) {
             handler.on_behaviour_event(event)
@@ -224,6 +484,16 @@ impl NetworkBehaviour for Behaviour {
                 self.reservation_addresses.remove(&connection_id);
                 self.pending_handler_commands.remove(&connection_id);
             }
+            FromSwarm::ListenerClosed(ListenerClosed { listener_id, .. }) => {
+                let reservation_infos = &mut self.reservation_infos;
+                self.relay_listeners.retain(|relay_peer_id, id| {
+                    if *id != listener_id {
+                        return true;
+                    }
+                    reservation_infos.remove(relay_peer_id);
+                    false
+                });
+            }
             _ => {}
         }
     }
@@ -240,7 +510,11 @@ impl NetworkBehaviour for Behaviour {
         };
 
         let event = match handler_event {
-            handler::Event::ReservationReqAccepted { renewal, limit } => {
+            handler::Event::ReservationReqAccepted {
+                renewal,
+                limit,
+                info,
+            } => {
                 let (addr, status) = self
                     .reservation_addresses
                     .get_mut(&connection)
@@ -250,12 +524,16 @@ impl NetworkBehaviour for Behaviour {
                     *status = ReservationStatus::Confirmed;
                     self.queued_actions
                         .push_back(ToSwarm::ExternalAddrConfirmed(addr.clone()));
+                    self.complete_switch(event_source);
                 }
 
+                self.reservation_infos.insert(event_source, info.clone());
+
                 Event::ReservationReqAccepted {
                     relay_peer_id: event_source,
                     renewal,
                     limit,
+                    info,
                 }
             }
             handler::Event::OutboundCircuitEstablished { limit } => {
@@ -277,16 +555,23 @@ impl NetworkBehaviour for Behaviour {
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        while let Poll::Ready(Some(report)) = self.circuit_usage_receiver.poll_next_unpin(cx) {
+            self.on_circuit_usage(report);
+        }
+
         if let Some(action) = self.queued_actions.pop_front() {
             return Poll::Ready(action);
         }
 
         let action = match ready!(self.from_transport.poll_next_unpin(cx)) {
             Some(transport::TransportToBehaviourMsg::ListenReq {
+                listener_id,
                 relay_peer_id,
                 relay_addr,
                 to_listener,
             }) => {
+                self.relay_listeners.insert(relay_peer_id, listener_id);
+
                 match self
                     .directly_connected_peers
                     .get(&relay_peer_id)
@@ -314,6 +599,7 @@ impl NetworkBehaviour for Behaviour {
                         let opts = DialOpts::peer_id(relay_peer_id)
                             .addresses(vec![relay_addr.clone()])
                             .extend_addresses_through_behaviour()
+                            .priority(DialPriority::High)
                             .build();
                         let relayed_connection_id = opts.connection_id();
 
@@ -390,6 +676,84 @@ impl NetworkBehaviour for Behaviour {
 /// Internally, this uses a stream to the relay.
 pub struct Connection {
     pub(crate) state: ConnectionState,
+    pub(crate) usage: CircuitUsage,
+}
+
+/// Usage of a closed circuit, reported to the [`Behaviour`].
+pub(crate) struct CircuitUsageReport {
+    relay_peer_id: PeerId,
+    limit: Option<protocol::Limit>,
+    duration: Duration,
+    bytes_read: u64,
+    bytes_written: u64,
+}
+
+/// Tracks the data transferred over a circuit and reports it to the
+/// [`Behaviour`] once the circuit is dropped.
+pub(crate) struct CircuitUsage {
+    relay_peer_id: PeerId,
+    limit: Option<protocol::Limit>,
+    established: Instant,
+    bytes_read: u64,
+    bytes_written: u64,
+    to_behaviour: mpsc::Sender<CircuitUsageReport>,
+}
+
+impl CircuitUsage {
+    pub(crate) fn new(
+        relay_peer_id: PeerId,
+        limit: Option<protocol::Limit>,
+        to_behaviour: mpsc::Sender<CircuitUsageReport>,
+    ) -> Self {
+        Self {
+            relay_peer_id,
+            limit,
+            established: Instant::now(),
+            bytes_read: 0,
+            bytes_written: 0,
+            to_behaviour,
+        }
+    }
+
+    fn on_read(&mut self, result: &Poll<Result<usize, Error>>) {
+        if let Poll::Ready(Ok(n)) = result {
+            self.bytes_read += *n as u64;
+        }
+    }
+
+    fn on_written(&mut self, result: &Poll<Result<usize, Error>>) {
+        if let Poll::Ready(Ok(n)) = result {
+            self.bytes_written += *n as u64;
+        }
+    }
+}
+
+impl Drop for CircuitUsage {
+    fn drop(&mut self) {
+        let report = CircuitUsageReport {
+            relay_peer_id: self.relay_peer_id,
+            limit: self.limit,
+            duration: self.established.elapsed(),
+            bytes_read: self.bytes_read,
+            bytes_written: self.bytes_written,
+        };
+        if let Err(e) = self.to_behaviour.try_send(report) {
+            if e.is_full() {
+                tracing::debug!(
+                    relay=%self.relay_peer_id,
+                    "Dropping circuit usage report, behaviour is not keeping up"
+                );
+            }
+        }
+    }
+}
+
+/// Extracts the peer ID of a relay from its address.
+fn relay_peer_id(relay_addr: &Multiaddr) -> Option<PeerId> {
+    match relay_addr.iter().last()? {
+        Protocol::P2p(peer_id) => Some(peer_id),
+        _ => None,
+    }
 }
 
 pub(crate) enum ConnectionState {
@@ -438,12 +802,12 @@ impl AsyncWrite for Connection {
         loop {
             match &mut self.state {
                 ConnectionState::InboundAccepting { accept } => {
-                    *self = Connection {
-                        state: ready!(accept.poll_unpin(cx))?,
-                    };
+                    self.state = ready!(accept.poll_unpin(cx))?;
                 }
                 ConnectionState::Operational { substream, .. } => {
-                    return Pin::new(substream).poll_write(cx, buf);
+                    let result = Pin::new(substream).poll_write(cx, buf);
+                    self.usage.on_written(&result);
+                    return result;
                 }
             }
         }
@@ -452,9 +816,7 @@ impl AsyncWrite for Connection {
         loop {
             match &mut self.state {
                 ConnectionState::InboundAccepting { accept } => {
-                    *self = Connection {
-                        state: ready!(accept.poll_unpin(cx))?,
-                    };
+                    self.state = ready!(accept.poll_unpin(cx))?;
                 }
                 ConnectionState::Operational { substream, .. } => {
                     return Pin::new(substream).poll_flush(cx);
@@ -466,9 +828,7 @@ impl AsyncWrite for Connection {
         loop {
             match &mut self.state {
                 ConnectionState::InboundAccepting { accept } => {
-                    *self = Connection {
-                        state: ready!(accept.poll_unpin(cx))?,
-                    };
+                    self.state = ready!(accept.poll_unpin(cx))?;
                 }
                 ConnectionState::Operational { substream, .. } => {
                     return Pin::new(substream).poll_close(cx);
@@ -485,12 +845,12 @@ impl AsyncWrite for Connection {
         loop {
             match &mut self.state {
                 ConnectionState::InboundAccepting { accept } => {
-                    *self = Connection {
-                        state: ready!(accept.poll_unpin(cx))?,
-                    };
+                    self.state = ready!(accept.poll_unpin(cx))?;
                 }
                 ConnectionState::Operational { substream, .. } => {
-                    return Pin::new(substream).poll_write_vectored(cx, bufs);
+                    let result = Pin::new(substream).poll_write_vectored(cx, bufs);
+                    self.usage.on_written(&result);
+                    return result;
                 }
             }
         }
@@ -506,25 +866,72 @@ impl AsyncRead for Connection {
         loop {
             match &mut self.state {
                 ConnectionState::InboundAccepting { accept } => {
-                    *self = Connection {
-                        state: ready!(accept.poll_unpin(cx))?,
-                    };
+                    self.state = ready!(accept.poll_unpin(cx))?;
                 }
                 ConnectionState::Operational {
                     read_buffer,
                     substream,
                     ..
                 } => {
-                    if !read_buffer.is_empty() {
+                    let result = if !read_buffer.is_empty() {
                         let n = std::cmp::min(read_buffer.len(), buf.len());
                         let data = read_buffer.split_to(n);
                         buf[0..n].copy_from_slice(&data[..]);
-                        return Poll::Ready(Ok(n));
-                    }
-
-                    return Pin::new(substream).poll_read(cx, buf);
+                        Poll::Ready(Ok(n))
+                    } else {
+                        Pin::new(substream).poll_read(cx, buf)
+                    };
+                    self.usage.on_read(&result);
+                    return result;
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn report(relay_peer_id: PeerId) -> CircuitUsageReport {
+        CircuitUsageReport {
+            relay_peer_id,
+            limit: None,
+            duration: Duration::ZERO,
+            bytes_read: 0,
+            bytes_written: 0,
+        }
+    }
+
+    #[test]
+    fn dropped_circuit_reports_usage() {
+        let relay = PeerId::random();
+        let (sender, mut receiver) = mpsc::channel(MAX_PENDING_CIRCUIT_USAGE_REPORTS);
+
+        let mut usage = CircuitUsage::new(relay, None, sender);
+        usage.on_read(&Poll::Ready(Ok(3)));
+        usage.on_written(&Poll::Ready(Ok(5)));
+        drop(usage);
+
+        let report = receiver.try_next().unwrap().unwrap();
+        assert_eq!(report.relay_peer_id, relay);
+        assert_eq!(report.bytes_read, 3);
+        assert_eq!(report.bytes_written, 5);
+    }
+
+    #[test]
+    fn report_is_dropped_when_channel_is_full() {
+        let relay = PeerId::random();
+        let (mut sender, mut receiver) = mpsc::channel(0);
+        sender.try_send(report(relay)).unwrap();
+
+        drop(CircuitUsage::new(relay, None, sender));
+
+        assert_eq!(
+            futures::executor::block_on(receiver.by_ref().count()),
+            1,
+            "Report of the full channel to be dropped"
+        );
+    }
+}
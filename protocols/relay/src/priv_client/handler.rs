@@ -21,6 +21,7 @@
 use crate::client::Connection;
 use crate::priv_client::transport;
 use crate::priv_client::transport::ToListenerMsg;
+use crate::priv_client::{CircuitUsage, CircuitUsageReport, ReservationInfo};
 use crate::protocol::{self, inbound_stop, outbound_hop};
 use crate::{priv_client, proto, HOP_PROTOCOL_NAME, STOP_PROTOCOL_NAME};
 use futures::channel::mpsc::Sender;
@@ -82,6 +83,7 @@ pub enum Event {
         /// Indicates whether the request replaces an existing reservation.
         renewal: bool,
         limit: Option<protocol::Limit>,
+        info: ReservationInfo,
     },
     /// An outbound circuit has been established.
     OutboundCircuitEstablished { limit: Option<protocol::Limit> },
@@ -125,10 +127,18 @@ pub struct Handler {
         futures_bounded::FuturesSet<Result<(), inbound_stop::Error>>,
 
     reservation: Reservation,
+
+    /// Channel to report the usage of circuits via this relay to the [`Behaviour`](priv_client::Behaviour).
+    circuit_usage: mpsc::Sender<CircuitUsageReport>,
 }
 
 impl Handler {
-    pub fn new(local_peer_id: PeerId, remote_peer_id: PeerId, remote_addr: Multiaddr) -> Self {
+    pub(crate) fn new(
+        local_peer_id: PeerId,
+        remote_peer_id: PeerId,
+        remote_addr: Multiaddr,
+        circuit_usage: mpsc::Sender<CircuitUsageReport>,
+    ) -> Self {
         Self {
             local_peer_id,
             remote_peer_id,
@@ -152,6 +162,7 @@ impl Handler {
                 MAX_NUMBER_DENYING_CIRCUIT,
             ),
             reservation: Reservation::None,
+            circuit_usage,
         }
     }
 
@@ -178,6 +189,7 @@ impl Handler {
             .push_back(ConnectionHandlerEvent::OutboundSubstreamRequest {
                 protocol: SubstreamProtocol::new(ReadyUpgrade::new(HOP_PROTOCOL_NAME), ()),
             });
+        let relay_peer_id = self.remote_peer_id;
         let result = self.inflight_reserve_requests.try_push(
             async move {
                 let stream = receiver
@@ -185,7 +197,7 @@ impl Handler {
                     .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?
                     .map_err(into_reserve_error)?;
 
-                let reservation = outbound_hop::make_reservation(stream).await?;
+                let reservation = outbound_hop::make_reservation(stream, relay_peer_id).await?;
 
                 Ok(reservation)
             },
@@ -272,6 +284,8 @@ impl ConnectionHandler for Handler {
                         renewal_timeout,
                         addrs,
                         limit,
+                        expires_at,
+                        voucher,
                     })),
                     to_listener,
                 )) => {
@@ -282,6 +296,10 @@ impl ConnectionHandler for Handler {
                             to_listener,
                             self.local_peer_id,
                             limit,
+                            ReservationInfo {
+                                expires_at,
+                                voucher,
+                            },
                         ),
                     ));
                 }
@@ -321,6 +339,11 @@ impl ConnectionHandler for Handler {
                     if to_dialer
                         .send(Ok(priv_client::Connection {
                             state: priv_client::ConnectionState::new_outbound(stream, read_buffer),
+                            usage: CircuitUsage::new(
+                                self.remote_peer_id,
+                                limit,
+                                self.circuit_usage.clone(),
+                            ),
                         }))
                         .is_err()
                     {
@@ -369,7 +392,14 @@ impl ConnectionHandler for Handler {
 
                         pending_msgs.push_back(
                             transport::ToListenerMsg::IncomingRelayedConnection {
-                                stream: super::Connection { state: connection },
+                                stream: super::Connection {
+                                    state: connection,
+                                    usage: CircuitUsage::new(
+                                        self.remote_peer_id,
+                                        limit,
+                                        self.circuit_usage.clone(),
+                                    ),
+                                },
                                 src_peer_id,
                                 relay_peer_id: self.remote_peer_id,
                                 relay_addr: self.remote_addr.clone(),
@@ -480,6 +510,7 @@ impl Reservation {
         to_listener: mpsc::Sender<transport::ToListenerMsg>,
         local_peer_id: PeerId,
         limit: Option<protocol::Limit>,
+        info: ReservationInfo,
     ) -> Event {
         let (renewal, mut pending_msgs) = match std::mem::replace(self, Self::None) {
             Reservation::Accepted { pending_msgs, .. }
@@ -505,7 +536,11 @@ impl Reservation {
             to_listener,
         };
 
-        Event::ReservationReqAccepted { renewal, limit }
+        Event::ReservationReqAccepted {
+            renewal,
+            limit,
+            info,
+        }
     }
 
     fn is_some(&self) -> bool {
@@ -580,7 +615,7 @@ impl Reservation {
 
 fn into_reserve_error(e: StreamUpgradeError<Void>) -> outbound_hop::ReserveError {
     match e {
-        StreamUpgradeError::Timeout => {
+        StreamUpgradeError::Timeout | StreamUpgradeError::NegotiationTimeout => {
             outbound_hop::ReserveError::Io(io::ErrorKind::TimedOut.into())
         }
         StreamUpgradeError::Apply(never) => void::unreachable(never),
@@ -591,7 +626,7 @@ fn into_reserve_error(e: StreamUpgradeError<Void>) -> outbound_hop::ReserveError
 
 fn into_connect_error(e: StreamUpgradeError<Void>) -> outbound_hop::ConnectError {
     match e {
-        StreamUpgradeError::Timeout => {
+        StreamUpgradeError::Timeout | StreamUpgradeError::NegotiationTimeout => {
             outbound_hop::ConnectError::Io(io::ErrorKind::TimedOut.into())
         }
         StreamUpgradeError::Apply(never) => void::unreachable(never),
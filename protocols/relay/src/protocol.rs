@@ -22,6 +22,7 @@ use crate::proto;
 use libp2p_swarm::StreamProtocol;
 use std::time::Duration;
 
+pub(crate) mod datagram;
 pub(crate) mod inbound_hop;
 pub(crate) mod inbound_stop;
 pub(crate) mod outbound_hop;
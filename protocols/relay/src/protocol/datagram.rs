@@ -0,0 +1,178 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::proto;
+use asynchronous_codec::Framed;
+use bytes::Bytes;
+use futures::prelude::*;
+use libp2p_core::{InboundUpgrade, OutboundUpgrade, UpgradeInfo};
+use libp2p_identity::PeerId;
+use libp2p_swarm::StreamProtocol;
+use std::{io, iter, pin::Pin};
+use thiserror::Error;
+
+pub const DATAGRAM_PROTOCOL_NAME: StreamProtocol =
+    StreamProtocol::new("/libp2p/circuit/relay/0.2.0/datagram-experimental");
+
+/// Upper bound of the size of a datagram message besides its data.
+const MAX_OVERHEAD: usize = 128;
+
+/// A datagram sent over a stream of its own.
+///
+/// Towards the relay, `peer` is the destination. From the relay, `peer` is the source.
+#[derive(Debug, Clone)]
+pub struct Datagram {
+    pub(crate) peer: PeerId,
+    pub(crate) data: Bytes,
+}
+
+impl UpgradeInfo for Datagram {
+    type Info = StreamProtocol;
+    type InfoIter = iter::Once<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        iter::once(DATAGRAM_PROTOCOL_NAME)
+    }
+}
+
+impl<TSocket> OutboundUpgrade<TSocket> for Datagram
+where
+    TSocket: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    type Output = ();
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Output, Self::Error>> + Send>>;
+
+    fn upgrade_outbound(self, socket: TSocket, _: Self::Info) -> Self::Future {
+        Box::pin(async move {
+            let mut substream = Framed::new(
+                socket,
+                quick_protobuf_codec::Codec::<proto::DatagramMessage>::new(
+                    self.data.len() + MAX_OVERHEAD,
+                ),
+            );
+            substream
+                .send(proto::DatagramMessage {
+                    peer: proto::Peer {
+                        id: self.peer.to_bytes(),
+                        addrs: vec![],
+                    },
+                    data: self.data.to_vec(),
+                })
+                .await?;
+            substream.close().await?;
+            Ok(())
+        })
+    }
+}
+
+/// Accepts a single [`Datagram`] of at most `max_datagram_size` bytes of data.
+#[derive(Debug, Clone)]
+pub struct DatagramProtocol {
+    pub(crate) max_datagram_size: usize,
+}
+
+impl UpgradeInfo for DatagramProtocol {
+    type Info = StreamProtocol;
+    type InfoIter = iter::Once<Self::Info>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        iter::once(DATAGRAM_PROTOCOL_NAME)
+    }
+}
+
+impl<TSocket> InboundUpgrade<TSocket> for DatagramProtocol
+where
+    TSocket: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    type Output = Datagram;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Output, Self::Error>> + Send>>;
+
+    fn upgrade_inbound(self, socket: TSocket, _: Self::Info) -> Self::Future {
+        Box::pin(async move {
+            let mut substream = Framed::new(
+                socket,
+                quick_protobuf_codec::Codec::<proto::DatagramMessage>::new(
+                    self.max_datagram_size + MAX_OVERHEAD,
+                ),
+            );
+            let proto::DatagramMessage { peer, data } = substream
+                .next()
+                .await
+                .ok_or(Error::Io(io::ErrorKind::UnexpectedEof.into()))??;
+
+            if data.len() > self.max_datagram_size {
+                return Err(Error::Protocol(ProtocolViolation::DatagramTooLarge));
+            }
+            let peer = PeerId::from_bytes(&peer.id).map_err(|_| ProtocolViolation::ParsePeerId)?;
+
+            Ok(Datagram {
+                peer,
+                data: data.into(),
+            })
+        })
+    }
+}
+
+/// Transmission between the `OneShotHandler` and the datagram `Behaviour`.
+#[derive(Debug)]
+pub enum HandlerEvent {
+    /// A datagram has been received.
+    Received(Datagram),
+    /// A datagram has been sent.
+    Sent,
+}
+
+impl From<Datagram> for HandlerEvent {
+    fn from(datagram: Datagram) -> Self {
+        HandlerEvent::Received(datagram)
+    }
+}
+
+impl From<()> for HandlerEvent {
+    fn from(_: ()) -> Self {
+        HandlerEvent::Sent
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Protocol error")]
+    Protocol(#[from] ProtocolViolation),
+    #[error("IO error")]
+    Io(#[from] io::Error),
+}
+
+impl From<quick_protobuf_codec::Error> for Error {
+    fn from(error: quick_protobuf_codec::Error) -> Self {
+        Self::Protocol(ProtocolViolation::Codec(error))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ProtocolViolation {
+    #[error(transparent)]
+    Codec(#[from] quick_protobuf_codec::Error),
+    #[error("Failed to parse peer id.")]
+    ParsePeerId,
+    #[error("Datagram exceeds the maximum size.")]
+    DatagramTooLarge,
+}
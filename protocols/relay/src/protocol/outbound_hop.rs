@@ -28,7 +28,7 @@ use futures_timer::Delay;
 use thiserror::Error;
 use web_time::SystemTime;
 
-use libp2p_core::Multiaddr;
+use libp2p_core::{Multiaddr, SignedEnvelope};
 use libp2p_identity::PeerId;
 use libp2p_swarm::Stream;
 
@@ -101,10 +101,17 @@ impl From<quick_protobuf_codec::Error> for ReserveError {
     }
 }
 
+/// Domain separation string of reservation vouchers.
+const VOUCHER_DOMAIN: &str = "libp2p-relay-rsvp";
+/// Varint encoded multicodec `0x0302` of reservation vouchers.
+const VOUCHER_PAYLOAD_TYPE: &[u8] = &[0x82, 0x06];
+
 pub(crate) struct Reservation {
     pub(crate) renewal_timeout: Delay,
     pub(crate) addrs: Vec<Multiaddr>,
     pub(crate) limit: Option<Limit>,
+    pub(crate) expires_at: SystemTime,
+    pub(crate) voucher: Option<SignedEnvelope>,
 }
 
 pub(crate) struct Circuit {
@@ -113,7 +120,10 @@ pub(crate) struct Circuit {
     pub(crate) limit: Option<Limit>,
 }
 
-pub(crate) async fn make_reservation(stream: Stream) -> Result<Reservation, ReserveError> {
+pub(crate) async fn make_reservation(
+    stream: Stream,
+    relay_peer_id: PeerId,
+) -> Result<Reservation, ReserveError> {
     let msg = proto::HopMessage {
         type_pb: proto::HopMessageType::RESERVE,
         peer: None,
@@ -202,13 +212,41 @@ pub(crate) async fn make_reservation(stream: Stream) -> Result<Reservation, Rese
             ProtocolViolation::InvalidReservationExpiration,
         ))?;
 
+    let expires_at = SystemTime::UNIX_EPOCH + Duration::from_secs(reservation.expire);
+
+    let voucher =
+        reservation
+            .voucher
+            .and_then(|bytes| match decode_voucher(&bytes, relay_peer_id) {
+                Ok(voucher) => Some(voucher),
+                Err(e) => {
+                    tracing::debug!("Ignoring invalid reservation voucher: {e}");
+                    None
+                }
+            });
+
     Ok(Reservation {
         renewal_timeout,
         addrs,
         limit,
+        expires_at,
+        voucher,
     })
 }
 
+/// Decodes a reservation voucher and checks that it was signed by the relay.
+fn decode_voucher(bytes: &[u8], relay_peer_id: PeerId) -> Result<SignedEnvelope, String> {
+    let voucher = SignedEnvelope::from_protobuf_encoding(bytes).map_err(|e| e.to_string())?;
+    let (_, key) = voucher
+        .payload_and_signing_key(String::from(VOUCHER_DOMAIN), VOUCHER_PAYLOAD_TYPE)
+        .map_err(|e| e.to_string())?;
+    if key.to_peer_id() != relay_peer_id {
+        return Err(format!("voucher not signed by relay {relay_peer_id}"));
+    }
+
+    Ok(voucher)
+}
+
 pub(crate) async fn open_circuit(
     protocol: Stream,
     dst_peer_id: PeerId,
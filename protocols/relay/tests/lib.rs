@@ -169,7 +169,7 @@ fn new_reservation_to_same_relay_replaces_old() {
                         break;
                     }
                 }
-                SwarmEvent::ExternalAddrConfirmed { address } => {
+                SwarmEvent::ExternalAddrConfirmed { address, .. } => {
                     assert_eq!(
                         address,
                         client_addr.clone().with(Protocol::P2p(client_peer_id))
@@ -394,6 +394,125 @@ fn propagate_connect_error_to_unknown_peer_to_dialer() {
     ));
 }
 
+#[test]
+fn relay_statistics() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay();
+    let relay_peer_id = *relay.local_peer_id();
+    let statistics = relay.behaviour().relay.statistics();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+    spawn_swarm_on_pool(&pool, relay);
+
+    let client_addr = relay_addr
+        .clone()
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit);
+    let mut client = build_client();
+    let client_peer_id = *client.local_peer_id();
+
+    client.listen_on(client_addr.clone()).unwrap();
+    assert!(pool.run_until(wait_for_dial(&mut client, relay_peer_id)));
+    pool.run_until(wait_for_reservation(
+        &mut client,
+        client_addr.with(Protocol::P2p(client_peer_id)),
+        relay_peer_id,
+        false, // No renewal.
+    ));
+    pool.run_until_stalled();
+
+    assert_eq!(statistics.active_reservations(), 1);
+    assert_eq!(statistics.max_active_reservations_of_peer(), 1);
+    assert!(statistics.reservation_denials().is_empty());
+
+    // Connect to a peer without a reservation, which the relay denies.
+    let mut src = build_client();
+    let dst_peer_id = PeerId::random();
+    let dst_addr = relay_addr
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(dst_peer_id));
+    src.dial(dst_addr).unwrap();
+    pool.run_until(src.wait(|e| match e {
+        SwarmEvent::OutgoingConnectionError { peer_id, .. } if peer_id == Some(dst_peer_id) => {
+            Some(())
+        }
+        _ => None,
+    }));
+    pool.run_until_stalled();
+
+    assert_eq!(
+        statistics
+            .circuit_denials()
+            .get(&relay::DenialReason::NoReservation),
+        Some(&1)
+    );
+    assert_eq!(statistics.active_circuits(), 0);
+}
+
+#[test]
+fn reservation_info_is_exposed_to_client() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let relay_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut relay = build_relay();
+    let relay_peer_id = *relay.local_peer_id();
+
+    relay.listen_on(relay_addr.clone()).unwrap();
+    relay.add_external_address(relay_addr.clone());
+    spawn_swarm_on_pool(&pool, relay);
+
+    let client_addr = relay_addr
+        .with(Protocol::P2p(relay_peer_id))
+        .with(Protocol::P2pCircuit);
+    let mut client = build_client();
+    assert!(client
+        .behaviour()
+        .relay
+        .reservation_info(&relay_peer_id)
+        .is_none());
+
+    client.listen_on(client_addr).unwrap();
+
+    let info = pool.run_until(client.wait(|e| match e {
+        SwarmEvent::Behaviour(ClientEvent::Relay(
+            relay::client::Event::ReservationReqAccepted {
+                relay_peer_id: peer_id,
+                info,
+                ..
+            },
+        )) if peer_id == relay_peer_id => Some(info),
+        _ => None,
+    }));
+
+    // The relay is configured with a reservation duration of 2 seconds.
+    let remaining = info
+        .expires_at()
+        .duration_since(web_time::SystemTime::now())
+        .unwrap();
+    assert!(remaining <= Duration::from_secs(2));
+    // The relay doesn't sign vouchers.
+    assert!(info.voucher().is_none());
+    assert_eq!(
+        client
+            .behaviour()
+            .relay
+            .reservation_info(&relay_peer_id)
+            .unwrap()
+            .expires_at(),
+        info.expires_at()
+    );
+}
+
 #[test]
 fn reuse_connection() {
     let _ = tracing_subscriber::fmt()
@@ -433,6 +552,61 @@ fn reuse_connection() {
     ));
 }
 
+#[test]
+fn relay_datagram() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+    let mut pool = LocalPool::new();
+
+    let mut relay = Swarm::new_ephemeral(|_| {
+        relay::datagram::Behaviour::new(relay::datagram::Config {
+            relay: true,
+            ..Default::default()
+        })
+    });
+    let mut src = Swarm::new_ephemeral(|_| relay::datagram::Behaviour::new(Default::default()));
+    let mut dst = Swarm::new_ephemeral(|_| relay::datagram::Behaviour::new(Default::default()));
+    let relay_peer_id = *relay.local_peer_id();
+    let src_peer_id = *src.local_peer_id();
+    let dst_peer_id = *dst.local_peer_id();
+
+    pool.run_until(async {
+        relay.listen().with_memory_addr_external().await;
+        src.connect(&mut relay).await;
+        dst.connect(&mut relay).await;
+    });
+
+    assert!(matches!(
+        src.behaviour_mut()
+            .send(relay_peer_id, dst_peer_id, vec![0; 2000]),
+        Err(relay::datagram::SendError::TooLarge)
+    ));
+    assert!(matches!(
+        src.behaviour_mut()
+            .send(dst_peer_id, relay_peer_id, b"hello".to_vec()),
+        Err(relay::datagram::SendError::NotConnected)
+    ));
+    src.behaviour_mut()
+        .send(relay_peer_id, dst_peer_id, b"hello".to_vec())
+        .unwrap();
+
+    spawn_swarm_on_pool(&pool, relay);
+    spawn_swarm_on_pool(&pool, src);
+
+    let (relay, src, data) = pool.run_until(dst.wait(|event| match event {
+        SwarmEvent::Behaviour(relay::datagram::Event::Received {
+            relay,
+            src_peer_id,
+            data,
+        }) => Some((relay, src_peer_id, data)),
+        _ => None,
+    }));
+    assert_eq!(relay, relay_peer_id);
+    assert_eq!(src, src_peer_id);
+    assert_eq!(&data[..], b"hello");
+}
+
 fn build_relay() -> Swarm<Relay> {
     build_relay_with_config(relay::Config {
         reservation_duration: Duration::from_secs(2),
@@ -527,7 +701,7 @@ async fn wait_for_reservation(
 
     loop {
         match client.select_next_some().await {
-            SwarmEvent::ExternalAddrConfirmed { address } if !is_renewal => {
+            SwarmEvent::ExternalAddrConfirmed { address, .. } if !is_renewal => {
                 assert_eq!(address, client_addr);
             }
             SwarmEvent::Behaviour(ClientEvent::Relay(
@@ -42,6 +42,10 @@ pub struct Behaviour {
     inner: libp2p_request_response::Behaviour<crate::codec::Codec>,
 
     registrations: Registrations,
+
+    discovers_served: u64,
+    discovers_rejected: u64,
+    registrations_rejected: u64,
 }
 
 pub struct Config {
@@ -81,10 +85,56 @@ impl Behaviour {
             ),
 
             registrations: Registrations::with_config(config),
+
+            discovers_served: 0,
+            discovers_rejected: 0,
+            registrations_rejected: 0,
+        }
+    }
+
+    /// Returns a snapshot of the usage statistics of this rendezvous point.
+    pub fn statistics(&self) -> Statistics {
+        Statistics {
+            registrations_per_namespace: self.registrations.count_per_namespace(),
+            discovers_served: self.discovers_served,
+            discovers_rejected: self.discovers_rejected,
+            registrations_rejected: self.registrations_rejected,
+        }
+    }
+
+    /// Returns the number of active registrations in the given namespace.
+    pub fn num_registrations(&self, namespace: &Namespace) -> usize {
+        self.registrations.count(namespace)
+    }
+
+    fn update_statistics(&mut self, event: &Event) {
+        match event {
+            Event::DiscoverServed { .. } => self.discovers_served += 1,
+            Event::DiscoverNotServed { .. } => self.discovers_rejected += 1,
+            Event::PeerNotRegistered { .. } => self.registrations_rejected += 1,
+            Event::PeerRegistered { .. }
+            | Event::PeerUnregistered { .. }
+            | Event::RegistrationExpired(_) => {}
         }
     }
 }
 
+/// Usage statistics of a rendezvous point, see [`Behaviour::statistics`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Statistics {
+    /// Number of active registrations per namespace.
+    ///
+    /// Namespaces without any active registration are not included.
+    pub registrations_per_namespace: HashMap<Namespace, usize>,
+    /// Number of discover requests served since the behaviour was created.
+    pub discovers_served: u64,
+    /// Number of discover requests rejected since the behaviour was created.
+    pub discovers_rejected: u64,
+    /// Number of registrations rejected since the behaviour was created.
+    pub registrations_rejected: u64,
+}
+
 #[derive(Debug)]
 #[allow(clippy::large_enum_variant)]
 pub enum Event {
@@ -185,6 +235,8 @@ impl NetworkBehaviour for Behaviour {
                         if let Some((event, response)) =
                             handle_request(peer_id, request, &mut self.registrations)
                         {
+                            self.update_statistics(&event);
+
                             if let Some(resp) = response {
                                 self.inner
                                     .send_response(channel, resp)
@@ -500,6 +552,23 @@ impl Registrations {
         Ok((registrations, new_cookie))
     }
 
+    /// Returns the number of active registrations in the given namespace.
+    pub fn count(&self, namespace: &Namespace) -> usize {
+        self.registrations
+            .values()
+            .filter(|registration| &registration.namespace == namespace)
+            .count()
+    }
+
+    /// Returns the number of active registrations for every namespace with at least one registration.
+    pub fn count_per_namespace(&self) -> HashMap<Namespace, usize> {
+        let mut counts = HashMap::new();
+        for registration in self.registrations.values() {
+            *counts.entry(registration.namespace.clone()).or_default() += 1;
+        }
+        counts
+    }
+
     fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ExpiredRegistration> {
         loop {
             let expired_registration = ready!(self.next_expiry.poll_next_unpin(cx)).expect(
@@ -554,6 +623,21 @@ mod tests {
         assert_eq!(subsequent_discover.count(), 0);
     }
 
+    #[test]
+    fn registrations_are_counted_per_namespace() {
+        let mut registrations = Registrations::default();
+        registrations.add(new_dummy_registration("foo")).unwrap();
+        registrations.add(new_dummy_registration("foo")).unwrap();
+        registrations.add(new_dummy_registration("bar")).unwrap();
+
+        let counts = registrations.count_per_namespace();
+
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[&Namespace::from_static("foo")], 2);
+        assert_eq!(counts[&Namespace::from_static("bar")], 1);
+        assert_eq!(registrations.count(&Namespace::from_static("baz")), 0);
+    }
+
     #[test]
     fn given_registrations_when_discover_all_then_all_are_returned() {
         let mut registrations = Registrations::default();
@@ -83,6 +83,11 @@ async fn given_successful_registration_then_successful_discovery() {
         },
         events => panic!("Unexpected events: {events:?}"),
     }
+
+    let statistics = robert.behaviour().statistics();
+    assert_eq!(statistics.registrations_per_namespace[&namespace], 1);
+    assert_eq!(statistics.discovers_served, 1);
+    assert_eq!(statistics.discovers_rejected, 0);
 }
 
 #[tokio::test]
@@ -250,6 +255,10 @@ async fn given_invalid_ttl_then_unsuccessful_registration() {
         }
         events => panic!("Unexpected events: {events:?}"),
     }
+
+    let statistics = robert.behaviour().statistics();
+    assert_eq!(statistics.registrations_rejected, 1);
+    assert_eq!(robert.behaviour().num_registrations(&namespace), 0);
 }
 
 #[tokio::test]
@@ -227,7 +227,7 @@ where
             .expect("negotiated a stream without a pending message");
 
         match error {
-            StreamUpgradeError::Timeout => {
+            StreamUpgradeError::Timeout | StreamUpgradeError::NegotiationTimeout => {
                 self.pending_events
                     .push_back(Event::OutboundTimeout(message.request_id));
             }
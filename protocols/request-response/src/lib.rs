@@ -39,6 +39,11 @@
 //! receiving a [`Message::Request`] via
 //! [`Event::Message`].
 //!
+//! ## Sticky routing
+//!
+//! The [`router::Router`] maps request keys onto one of several peers that replicate a
+//! service, failing over to other peers when requests to one of them keep failing.
+//!
 //! ## Predefined codecs
 //!
 //! In case your message types implement [`serde::Serialize`] and [`serde::Deserialize`],
@@ -72,6 +77,8 @@ mod codec;
 mod handler;
 #[cfg(feature = "json")]
 pub mod json;
+pub mod middleware;
+pub mod router;
 
 pub use codec::Codec;
 pub use handler::ProtocolSupport;
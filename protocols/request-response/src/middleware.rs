@@ -0,0 +1,433 @@
+// Copyright 2024 Protocol Labs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Reusable layers around the [`Codec`] of a request-response protocol.
+//!
+//! A [`Middleware`] hooks into every request and response right before it is written to and right
+//! after it is read from a stream, e.g. to attach and verify authentication tokens, to record
+//! tracing spans or to encrypt payloads. Since a middleware can be generic over the [`Codec`] it
+//! wraps, cross-cutting concerns like these are implemented once and reused across protocols.
+//!
+//! A middleware is applied to a codec via [`Layered`], which is itself a [`Codec`] and thus used
+//! with a [`Behaviour`](crate::Behaviour) like any other codec. Multiple middlewares are stacked
+//! via [`Layered::layer`]:
+//!
+//! ```
+//! # use libp2p_request_response::{middleware::{Layered, Middleware}, Codec};
+//! # use std::io;
+//! /// Logs all requests and responses.
+//! #[derive(Clone)]
+//! struct Log;
+//!
+//! impl<C: Codec> Middleware<C> for Log {
+//!     fn on_inbound_request(
+//!         &mut self,
+//!         protocol: &C::Protocol,
+//!         request: C::Request,
+//!     ) -> io::Result<C::Request> {
+//!         tracing::debug!(protocol = %protocol.as_ref(), "Received request");
+//!         Ok(request)
+//!     }
+//! }
+//!
+//! fn with_logging<C: Codec>(codec: C) -> Layered<C, Log> {
+//!     Layered::new(codec, Log)
+//! }
+//! ```
+
+use crate::Codec;
+use async_trait::async_trait;
+use futures::prelude::*;
+use std::io;
+
+/// Hooks invoked on the requests and responses of a [`Codec`], see the [module](self) docs.
+///
+/// Outbound messages pass the hooks before they are encoded, inbound messages after they are
+/// decoded. Returning an error fails the respective request like an I/O error of the codec
+/// would, i.e. with [`InboundFailure::Io`](crate::InboundFailure::Io) or
+/// [`OutboundFailure::Io`](crate::OutboundFailure::Io).
+///
+/// All hooks pass the message through unchanged by default.
+pub trait Middleware<C: Codec> {
+    /// Invoked on a request before it is sent to the remote.
+    fn on_outbound_request(
+        &mut self,
+        _protocol: &C::Protocol,
+        request: C::Request,
+    ) -> io::Result<C::Request> {
+        Ok(request)
+    }
+
+    /// Invoked on a request after it was received from the remote.
+    fn on_inbound_request(
+        &mut self,
+        _protocol: &C::Protocol,
+        request: C::Request,
+    ) -> io::Result<C::Request> {
+        Ok(request)
+    }
+
+    /// Invoked on a response before it is sent to the remote.
+    fn on_outbound_response(
+        &mut self,
+        _protocol: &C::Protocol,
+        response: C::Response,
+    ) -> io::Result<C::Response> {
+        Ok(response)
+    }
+
+    /// Invoked on a response after it was received from the remote.
+    fn on_inbound_response(
+        &mut self,
+        _protocol: &C::Protocol,
+        response: C::Response,
+    ) -> io::Result<C::Response> {
+        Ok(response)
+    }
+}
+
+/// Two [`Middleware`]s applied in order.
+///
+/// Outbound messages pass the `inner` middleware first, inbound messages pass the `outer` one
+/// first, such that the `outer` middleware is closest to the stream.
+#[derive(Debug, Clone)]
+pub struct Stack<Inner, Outer> {
+    inner: Inner,
+    outer: Outer,
+}
+
+impl<C, Inner, Outer> Middleware<C> for Stack<Inner, Outer>
+where
+    C: Codec,
+    Inner: Middleware<C>,
+    Outer: Middleware<C>,
+{
+    fn on_outbound_request(
+        &mut self,
+        protocol: &C::Protocol,
+        request: C::Request,
+    ) -> io::Result<C::Request> {
+        let request = self.inner.on_outbound_request(protocol, request)?;
+        self.outer.on_outbound_request(protocol, request)
+    }
+
+    fn on_inbound_request(
+        &mut self,
+        protocol: &C::Protocol,
+        request: C::Request,
+    ) -> io::Result<C::Request> {
+        let request = self.outer.on_inbound_request(protocol, request)?;
+        self.inner.on_inbound_request(protocol, request)
+    }
+
+    fn on_outbound_response(
+        &mut self,
+        protocol: &C::Protocol,
+        response: C::Response,
+    ) -> io::Result<C::Response> {
+        let response = self.inner.on_outbound_response(protocol, response)?;
+        self.outer.on_outbound_response(protocol, response)
+    }
+
+    fn on_inbound_response(
+        &mut self,
+        protocol: &C::Protocol,
+        response: C::Response,
+    ) -> io::Result<C::Response> {
+        let response = self.outer.on_inbound_response(protocol, response)?;
+        self.inner.on_inbound_response(protocol, response)
+    }
+}
+
+/// A [`Codec`] with a [`Middleware`] applied to all of its requests and responses.
+#[derive(Debug, Clone)]
+pub struct Layered<C, M> {
+    codec: C,
+    middleware: M,
+}
+
+impl<C, M> Layered<C, M> {
+    /// Applies `middleware` to `codec`.
+    pub fn new(codec: C, middleware: M) -> Self {
+        Self { codec, middleware }
+    }
+
+    /// Applies another middleware on top of the existing one, i.e. closer to the stream.
+    pub fn layer<Outer>(self, middleware: Outer) -> Layered<C, Stack<M, Outer>> {
+        Layered {
+            codec: self.codec,
+            middleware: Stack {
+                inner: self.middleware,
+                outer: middleware,
+            },
+        }
+    }
+
+    /// Returns a reference to the wrapped codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Returns a reference to the applied middleware.
+    pub fn middleware(&self) -> &M {
+        &self.middleware
+    }
+}
+
+#[async_trait]
+impl<C, M> Codec for Layered<C, M>
+where
+    C: Codec + Send,
+    C::Protocol: Sync,
+    M: Middleware<C> + Send,
+{
+    type Protocol = C::Protocol;
+    type Request = C::Request;
+    type Response = C::Response;
+
+    async fn read_request<T>(
+        &mut self,
+        protocol: &Self::Protocol,
+        io: &mut T,
+    ) -> io::Result<Self::Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let request = self.codec.read_request(protocol, io).await?;
+        self.middleware.on_inbound_request(protocol, request)
+    }
+
+    async fn read_response<T>(
+        &mut self,
+        protocol: &Self::Protocol,
+        io: &mut T,
+    ) -> io::Result<Self::Response>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let response = self.codec.read_response(protocol, io).await?;
+        self.middleware.on_inbound_response(protocol, response)
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        protocol: &Self::Protocol,
+        io: &mut T,
+        req: Self::Request,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let req = self.middleware.on_outbound_request(protocol, req)?;
+        self.codec.write_request(protocol, io, req).await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        protocol: &Self::Protocol,
+        io: &mut T,
+        res: Self::Response,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let res = self.middleware.on_outbound_response(protocol, res)?;
+        self.codec.write_response(protocol, io, res).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_ringbuf::Endpoint;
+    use libp2p_swarm::StreamProtocol;
+
+    /// Encodes requests and responses as raw bytes, terminated by the end of the stream.
+    #[derive(Clone, Default)]
+    struct BytesCodec;
+
+    #[async_trait]
+    impl Codec for BytesCodec {
+        type Protocol = StreamProtocol;
+        type Request = Vec<u8>;
+        type Response = Vec<u8>;
+
+        async fn read_request<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<Vec<u8>>
+        where
+            T: AsyncRead + Unpin + Send,
+        {
+            let mut buf = Vec::new();
+            io.read_to_end(&mut buf).await?;
+            Ok(buf)
+        }
+
+        async fn read_response<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<Vec<u8>>
+        where
+            T: AsyncRead + Unpin + Send,
+        {
+            let mut buf = Vec::new();
+            io.read_to_end(&mut buf).await?;
+            Ok(buf)
+        }
+
+        async fn write_request<T>(
+            &mut self,
+            _: &StreamProtocol,
+            io: &mut T,
+            req: Vec<u8>,
+        ) -> io::Result<()>
+        where
+            T: AsyncWrite + Unpin + Send,
+        {
+            io.write_all(&req).await
+        }
+
+        async fn write_response<T>(
+            &mut self,
+            _: &StreamProtocol,
+            io: &mut T,
+            res: Vec<u8>,
+        ) -> io::Result<()>
+        where
+            T: AsyncWrite + Unpin + Send,
+        {
+            io.write_all(&res).await
+        }
+    }
+
+    /// Prefixes requests with a token and rejects inbound requests without it.
+    #[derive(Clone)]
+    struct Auth(&'static [u8]);
+
+    impl<C: Codec<Request = Vec<u8>>> Middleware<C> for Auth {
+        fn on_outbound_request(
+            &mut self,
+            _: &C::Protocol,
+            request: Vec<u8>,
+        ) -> io::Result<Vec<u8>> {
+            Ok([self.0, &request[..]].concat())
+        }
+
+        fn on_inbound_request(&mut self, _: &C::Protocol, request: Vec<u8>) -> io::Result<Vec<u8>> {
+            match request.strip_prefix(self.0) {
+                Some(request) => Ok(request.to_vec()),
+                None => Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "invalid token",
+                )),
+            }
+        }
+    }
+
+    /// XORs all payloads with a key.
+    #[derive(Clone)]
+    struct Xor(u8);
+
+    impl Xor {
+        fn apply(&self, payload: Vec<u8>) -> Vec<u8> {
+            payload.into_iter().map(|b| b ^ self.0).collect()
+        }
+    }
+
+    impl<C: Codec<Request = Vec<u8>, Response = Vec<u8>>> Middleware<C> for Xor {
+        fn on_outbound_request(
+            &mut self,
+            _: &C::Protocol,
+            request: Vec<u8>,
+        ) -> io::Result<Vec<u8>> {
+            Ok(self.apply(request))
+        }
+
+        fn on_inbound_request(&mut self, _: &C::Protocol, request: Vec<u8>) -> io::Result<Vec<u8>> {
+            Ok(self.apply(request))
+        }
+
+        fn on_outbound_response(
+            &mut self,
+            _: &C::Protocol,
+            response: Vec<u8>,
+        ) -> io::Result<Vec<u8>> {
+            Ok(self.apply(response))
+        }
+
+        fn on_inbound_response(
+            &mut self,
+            _: &C::Protocol,
+            response: Vec<u8>,
+        ) -> io::Result<Vec<u8>> {
+            Ok(self.apply(response))
+        }
+    }
+
+    async fn send_request<C: Codec<Protocol = StreamProtocol>>(
+        sender: &mut C,
+        receiver: &mut C,
+        request: C::Request,
+    ) -> io::Result<C::Request> {
+        let protocol = StreamProtocol::new("/test/1");
+        let (mut a, mut b) = Endpoint::pair(124, 124);
+        sender.write_request(&protocol, &mut a, request).await?;
+        a.close().await?;
+        receiver.read_request(&protocol, &mut b).await
+    }
+
+    #[async_std::test]
+    async fn stacked_middlewares_apply_in_order() {
+        let mut codec = Layered::new(BytesCodec, Auth(b"token")).layer(Xor(0x42));
+
+        let protocol = StreamProtocol::new("/test/1");
+        let (mut a, mut b) = Endpoint::pair(124, 124);
+        codec
+            .write_request(&protocol, &mut a, b"request".to_vec())
+            .await
+            .unwrap();
+        a.close().await.unwrap();
+
+        // The token is added before the payload is encrypted.
+        let on_the_wire = BytesCodec.read_request(&protocol, &mut b).await.unwrap();
+        assert_eq!(on_the_wire, Xor(0x42).apply(b"tokenrequest".to_vec()));
+
+        let mut receiver = codec.clone();
+        let request = send_request(&mut codec, &mut receiver, b"request".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(request, b"request");
+
+        let (mut a, mut b) = Endpoint::pair(124, 124);
+        codec
+            .write_response(&protocol, &mut a, b"response".to_vec())
+            .await
+            .unwrap();
+        a.close().await.unwrap();
+        let response = receiver.read_response(&protocol, &mut b).await.unwrap();
+        assert_eq!(response, b"response");
+    }
+
+    #[async_std::test]
+    async fn middleware_errors_fail_the_request() {
+        let mut sender = Layered::new(BytesCodec, Auth(b"wrong"));
+        let mut receiver = Layered::new(BytesCodec, Auth(b"token"));
+
+        let error = send_request(&mut sender, &mut receiver, b"request".to_vec())
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
@@ -0,0 +1,363 @@
+// Copyright 2024 Protocol Labs
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Sticky routing of requests to one of several replicas of a service.
+//!
+//! A [`Router`] maps request keys onto a set of candidate peers using consistent hashing, so that
+//! requests for the same key keep going to the same peer and adding or removing a peer only moves
+//! a small share of the keys. Peers that repeatedly fail to answer requests are skipped for a
+//! configurable cooldown, during which their keys fail over to the next peer on the ring.
+//!
+//! The router is not a [`NetworkBehaviour`](libp2p_swarm::NetworkBehaviour) by itself. It sends
+//! requests through an existing [`Behaviour`] and learns about the health of peers from the
+//! [`Event`]s that behaviour emits:
+//!
+//! ```
+//! # use libp2p_identity::PeerId;
+//! # use libp2p_request_response::router::{Config, Router};
+//! let mut router = Router::new(Config::default());
+//! router.add_peer(PeerId::random());
+//! router.add_peer(PeerId::random());
+//!
+//! // Requests for the same key are routed to the same peer.
+//! assert_eq!(router.route("user-42"), router.route("user-42"));
+//!
+//! // Send requests via `router.send_request(&mut behaviour, "user-42", request)` and pass every
+//! // event of the behaviour to `router.on_event(&event)` to keep track of the peers' health.
+//! ```
+
+use crate::{Behaviour, Codec, Event, Message, OutboundRequestId};
+use libp2p_identity::PeerId;
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+use web_time::Instant;
+
+/// The configuration for a [`Router`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    virtual_nodes: usize,
+    max_failures: u32,
+    cooldown: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            virtual_nodes: 64,
+            max_failures: 3,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+impl Config {
+    /// Sets the number of points each peer occupies on the hash ring.
+    ///
+    /// More points spread the keys more evenly across peers at the cost of memory.
+    pub fn with_virtual_nodes(mut self, virtual_nodes: usize) -> Self {
+        self.virtual_nodes = virtual_nodes.max(1);
+        self
+    }
+
+    /// Sets the number of consecutive failed requests after which a peer is considered unhealthy.
+    pub fn with_max_failures(mut self, max_failures: u32) -> Self {
+        self.max_failures = max_failures.max(1);
+        self
+    }
+
+    /// Sets for how long an unhealthy peer is skipped before requests are routed to it again.
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+}
+
+/// Routes request keys to peers using consistent hashing, see the [module docs](self).
+#[derive(Debug)]
+pub struct Router {
+    config: Config,
+    ring: BTreeMap<u64, PeerId>,
+    peers: HashMap<PeerId, Health>,
+    pending_requests: HashMap<OutboundRequestId, PeerId>,
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    consecutive_failures: u32,
+    unhealthy_until: Option<Instant>,
+}
+
+impl Health {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.unhealthy_until.map_or(true, |until| now >= until)
+    }
+}
+
+impl Router {
+    /// Creates a new [`Router`] without any peers.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            ring: BTreeMap::new(),
+            peers: HashMap::new(),
+            pending_requests: HashMap::new(),
+        }
+    }
+
+    /// Adds a peer as a candidate for routing requests.
+    ///
+    /// Returns `false` if the peer was already known.
+    pub fn add_peer(&mut self, peer: PeerId) -> bool {
+        if self.peers.contains_key(&peer) {
+            return false;
+        }
+
+        for i in 0..self.config.virtual_nodes {
+            self.ring.insert(hash(&(peer, i)), peer);
+        }
+        self.peers.insert(peer, Health::default());
+
+        true
+    }
+
+    /// Removes a peer, moving its keys to the next peers on the ring.
+    ///
+    /// Returns `false` if the peer was not known.
+    pub fn remove_peer(&mut self, peer: &PeerId) -> bool {
+        if self.peers.remove(peer).is_none() {
+            return false;
+        }
+
+        self.ring.retain(|_, p| p != peer);
+        self.pending_requests.retain(|_, p| p != peer);
+
+        true
+    }
+
+    /// Returns an iterator over all known peers.
+    pub fn peers(&self) -> impl Iterator<Item = &PeerId> {
+        self.peers.keys()
+    }
+
+    /// Returns whether requests are currently routed to the given peer.
+    pub fn is_healthy(&self, peer: &PeerId) -> bool {
+        self.peers
+            .get(peer)
+            .map_or(false, |health| health.is_healthy(Instant::now()))
+    }
+
+    /// Returns all known peers in the order in which requests for the given key fail over to them,
+    /// regardless of their health.
+    pub fn candidates<K>(&self, key: &K) -> Vec<PeerId>
+    where
+        K: Hash + ?Sized,
+    {
+        let point = hash(key);
+        let mut candidates = Vec::with_capacity(self.peers.len());
+
+        for peer in self
+            .ring
+            .range(point..)
+            .chain(self.ring.range(..point))
+            .map(|(_, peer)| peer)
+        {
+            if candidates.len() == self.peers.len() {
+                break;
+            }
+            if !candidates.contains(peer) {
+                candidates.push(*peer);
+            }
+        }
+
+        candidates
+    }
+
+    /// Returns the peer requests for the given key should be sent to, i.e. the first healthy
+    /// candidate, or `None` if no peer is healthy.
+    pub fn route<K>(&self, key: &K) -> Option<PeerId>
+    where
+        K: Hash + ?Sized,
+    {
+        let now = Instant::now();
+
+        self.candidates(key)
+            .into_iter()
+            .find(|peer| self.peers[peer].is_healthy(now))
+    }
+
+    /// Sends a request for the given key through the given behaviour to the peer returned by
+    /// [`Router::route`].
+    ///
+    /// The outcome of the request is taken into account for the peer's health once the
+    /// corresponding event is passed to [`Router::on_event`]. Returns `None` and drops the
+    /// request if no peer is healthy.
+    pub fn send_request<TCodec, K>(
+        &mut self,
+        behaviour: &mut Behaviour<TCodec>,
+        key: &K,
+        request: TCodec::Request,
+    ) -> Option<(PeerId, OutboundRequestId)>
+    where
+        TCodec: Codec + Clone + Send + 'static,
+        K: Hash + ?Sized,
+    {
+        let peer = self.route(key)?;
+        let request_id = behaviour.send_request(&peer, request);
+        self.pending_requests.insert(request_id, peer);
+
+        Some((peer, request_id))
+    }
+
+    /// Updates the health of peers based on the outcome of requests sent through
+    /// [`Router::send_request`]. Events concerning other requests are ignored.
+    pub fn on_event<TRequest, TResponse, TChannelResponse>(
+        &mut self,
+        event: &Event<TRequest, TResponse, TChannelResponse>,
+    ) {
+        match event {
+            Event::Message {
+                message: Message::Response { request_id, .. },
+                ..
+            } => {
+                if let Some(peer) = self.pending_requests.remove(request_id) {
+                    self.report_success(&peer);
+                }
+            }
+            Event::OutboundFailure { request_id, .. } => {
+                if let Some(peer) = self.pending_requests.remove(request_id) {
+                    self.report_failure(&peer);
+                }
+            }
+            Event::Message { .. } | Event::InboundFailure { .. } | Event::ResponseSent { .. } => {}
+        }
+    }
+
+    /// Marks the given peer as healthy, e.g. after a successful application-level health check.
+    pub fn report_success(&mut self, peer: &PeerId) {
+        if let Some(health) = self.peers.get_mut(peer) {
+            *health = Health::default();
+        }
+    }
+
+    /// Records a failed request to the given peer, marking it unhealthy once
+    /// [`Config::with_max_failures`] consecutive failures have been reported.
+    pub fn report_failure(&mut self, peer: &PeerId) {
+        let Some(health) = self.peers.get_mut(peer) else {
+            return;
+        };
+
+        health.consecutive_failures += 1;
+        if health.consecutive_failures >= self.config.max_failures {
+            tracing::debug!(%peer, "Peer is unhealthy, failing over to other peers");
+            health.unhealthy_until = Some(Instant::now() + self.config.cooldown);
+        }
+    }
+}
+
+/// Hashes the given value onto the ring.
+///
+/// [`DefaultHasher::new`] always uses the same keys, so routing is stable for the lifetime of the
+/// process but may change across Rust releases.
+fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router_with_peers(n: usize) -> (Router, Vec<PeerId>) {
+        let mut router = Router::new(Config::default());
+        let peers = (0..n).map(|_| PeerId::random()).collect::<Vec<_>>();
+        for peer in &peers {
+            assert!(router.add_peer(*peer));
+        }
+
+        (router, peers)
+    }
+
+    #[test]
+    fn routes_keys_consistently() {
+        let (router, _) = router_with_peers(5);
+
+        for key in 0..100u32 {
+            assert_eq!(router.route(&key), router.route(&key));
+            assert_eq!(router.candidates(&key).len(), 5);
+        }
+    }
+
+    #[test]
+    fn removing_a_peer_only_moves_its_keys() {
+        let (mut router, peers) = router_with_peers(5);
+        let before = (0..1000u32)
+            .map(|key| router.route(&key).unwrap())
+            .collect::<Vec<_>>();
+
+        router.remove_peer(&peers[0]);
+
+        for (key, peer) in (0..1000u32).zip(before) {
+            let now = router.route(&key).unwrap();
+            if peer != peers[0] {
+                assert_eq!(now, peer);
+            } else {
+                assert_ne!(now, peers[0]);
+            }
+        }
+    }
+
+    #[test]
+    fn fails_over_to_next_candidate() {
+        let (mut router, _) = router_with_peers(3);
+        let key = "some-key";
+        let [primary, secondary, _] = router.candidates(key)[..] else {
+            panic!("expected three candidates");
+        };
+
+        for _ in 0..Config::default().max_failures {
+            assert_eq!(router.route(key), Some(primary));
+            router.report_failure(&primary);
+        }
+        assert!(!router.is_healthy(&primary));
+        assert_eq!(router.route(key), Some(secondary));
+
+        router.report_success(&primary);
+        assert_eq!(router.route(key), Some(primary));
+    }
+
+    #[test]
+    fn unhealthy_peers_are_retried_after_cooldown() {
+        let mut router = Router::new(
+            Config::default()
+                .with_max_failures(1)
+                .with_cooldown(Duration::ZERO),
+        );
+        let peer = PeerId::random();
+        router.add_peer(peer);
+
+        router.report_failure(&peer);
+
+        assert_eq!(router.route("key"), Some(peer));
+    }
+}
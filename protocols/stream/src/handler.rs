@@ -140,7 +140,8 @@ impl ConnectionHandler for Handler {
                 };
 
                 let error = match error {
-                    swarm::StreamUpgradeError::Timeout => {
+                    swarm::StreamUpgradeError::Timeout
+                    | swarm::StreamUpgradeError::NegotiationTimeout => {
                         OpenStreamError::Io(io::Error::from(io::ErrorKind::TimedOut))
                     }
                     swarm::StreamUpgradeError::Apply(v) => void::unreachable(v),
@@ -42,8 +42,8 @@ use libp2p_core::{
     Endpoint, Multiaddr,
 };
 use libp2p_swarm::{
-    derive_prelude::PeerId, dummy, ConnectionDenied, ConnectionId, ExpiredListenAddr, FromSwarm,
-    NetworkBehaviour, NewListenAddr, ToSwarm,
+    derive_prelude::PeerId, dummy, ConnectionDenied, ConnectionId, ExpiredListenAddr,
+    ExternalAddressSource, FromSwarm, NetworkBehaviour, NewListenAddr, ToSwarm,
 };
 
 /// The duration in seconds of a port mapping on the gateway.
@@ -434,9 +434,12 @@ impl NetworkBehaviour for Behaviour {
                                             protocol=%mapping.protocol,
                                             "successfully mapped UPnP for protocol"
                                         );
-                                        return Poll::Ready(ToSwarm::ExternalAddrConfirmed(
-                                            external_multiaddr,
-                                        ));
+                                        return Poll::Ready(
+                                            ToSwarm::ExternalAddrConfirmedWithSource {
+                                                address: external_multiaddr,
+                                                source: ExternalAddressSource::Upnp,
+                                            },
+                                        );
                                     }
                                     MappingState::Active(_) => {
                                         tracing::debug!(
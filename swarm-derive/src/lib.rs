@@ -23,7 +23,7 @@
 
 mod syn_ext;
 
-use crate::syn_ext::RequireStrLit;
+use crate::syn_ext::{RequireIntLit, RequireStrLit};
 use heck::ToUpperCamelCase;
 use proc_macro::TokenStream;
 use quote::quote;
@@ -60,7 +60,9 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
     let BehaviourAttributes {
         prelude_path,
         user_specified_out_event,
+        fair_poll,
     } = parse_attributes(ast)?;
+    let poll_budgets = parse_poll_budgets(data_struct, fair_poll)?;
 
     let multiaddr = quote! { #prelude_path::Multiaddr };
     let trait_to_impl = quote! { #prelude_path::NetworkBehaviour };
@@ -359,10 +361,10 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
         out_handler.unwrap_or(quote! {()}) // TODO: See test `empty`.
     };
 
-    // List of statements to put in `poll()`.
+    // The polling of each child, mapped to the output of the composed behaviour.
     //
     // We poll each child one by one and wrap around the output.
-    let poll_stmts = data_struct
+    let poll_children = data_struct
         .fields
         .iter()
         .enumerate()
@@ -397,12 +399,60 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
             let map_in_event = quote! { |event| #wrapped_event };
 
             quote! {
-                match #trait_to_impl::poll(&mut self.#field, cx) {
-                    std::task::Poll::Ready(e) => return std::task::Poll::Ready(e.map_out(#map_out_event).map_in(#map_in_event)),
-                    std::task::Poll::Pending => {},
+                #trait_to_impl::poll(&mut self.#field, cx).map(|e| e.map_out(#map_out_event).map_in(#map_in_event))
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // The body of `poll()`.
+    //
+    // By default, the children are polled in the order of the fields. With
+    // `#[behaviour(fair_poll)]`, polling starts after the child that returned the last event, or
+    // at the same child until it used up its `poll_budget`.
+    let poll_body = if fair_poll && !poll_children.is_empty() {
+        let num_children = poll_children.len();
+        let field_ns = 0..num_children;
+        quote! {
+            // Shared between all instances of the behaviour, as the derive can't add fields.
+            static NEXT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+            static SPENT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+            const BUDGETS: [usize; #num_children] = [#(#poll_budgets),*];
+
+            let start = NEXT.load(std::sync::atomic::Ordering::Relaxed) % #num_children;
+            for offset in 0..#num_children {
+                let field_n = (start + offset) % #num_children;
+                let poll: std::task::Poll<#network_behaviour_action<Self::ToSwarm, #t_handler_in_event<Self>>> = match field_n {
+                    #(#field_ns => #poll_children,)*
+                    _ => unreachable!("field index to be in range"),
+                };
+                if let std::task::Poll::Ready(event) = poll {
+                    let spent = if offset == 0 {
+                        SPENT.load(std::sync::atomic::Ordering::Relaxed) + 1
+                    } else {
+                        1
+                    };
+                    if spent >= BUDGETS[field_n] {
+                        NEXT.store(field_n + 1, std::sync::atomic::Ordering::Relaxed);
+                        SPENT.store(0, std::sync::atomic::Ordering::Relaxed);
+                    } else {
+                        NEXT.store(field_n, std::sync::atomic::Ordering::Relaxed);
+                        SPENT.store(spent, std::sync::atomic::Ordering::Relaxed);
+                    }
+                    return std::task::Poll::Ready(event);
                 }
             }
-        });
+            std::task::Poll::Pending
+        }
+    } else {
+        quote! {
+            #(
+                if let std::task::Poll::Ready(event) = #poll_children {
+                    return std::task::Poll::Ready(event);
+                }
+            )*
+            std::task::Poll::Pending
+        }
+    };
 
     let out_event_reference = if out_event_definition.is_some() {
         quote! { #out_event_name #ty_generics }
@@ -478,8 +528,7 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
             }
 
             fn poll(&mut self, cx: &mut std::task::Context) -> std::task::Poll<#network_behaviour_action<Self::ToSwarm, #t_handler_in_event<Self>>> {
-                #(#poll_stmts)*
-                std::task::Poll::Pending
+                #poll_body
             }
 
             fn on_swarm_event(&mut self, event: #from_swarm) {
@@ -494,6 +543,7 @@ fn build_struct(ast: &DeriveInput, data_struct: &DataStruct) -> syn::Result<Toke
 struct BehaviourAttributes {
     prelude_path: syn::Path,
     user_specified_out_event: Option<syn::Type>,
+    fair_poll: bool,
 }
 
 /// Parses the `value` of a key=value pair in the `#[behaviour]` attribute into the requested type.
@@ -501,6 +551,7 @@ fn parse_attributes(ast: &DeriveInput) -> syn::Result<BehaviourAttributes> {
     let mut attributes = BehaviourAttributes {
         prelude_path: syn::parse_quote! { ::libp2p::swarm::derive_prelude },
         user_specified_out_event: None,
+        fair_poll: false,
     };
 
     for attr in ast
@@ -526,8 +577,55 @@ fn parse_attributes(ast: &DeriveInput) -> syn::Result<BehaviourAttributes> {
 
                 continue;
             }
+
+            if meta.path().is_ident("fair_poll") {
+                meta.require_path_only()?;
+
+                attributes.fair_poll = true;
+
+                continue;
+            }
         }
     }
 
     Ok(attributes)
 }
+
+/// Parses the `#[behaviour(poll_budget = N)]` attributes of the fields, defaulting to 1.
+fn parse_poll_budgets(data_struct: &DataStruct, fair_poll: bool) -> syn::Result<Vec<usize>> {
+    let mut budgets = Vec::new();
+
+    for field in data_struct.fields.iter() {
+        let mut budget = 1;
+
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("behaviour"))
+        {
+            let nested = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+
+            for meta in nested {
+                if meta.path().is_ident("poll_budget") {
+                    if !fair_poll {
+                        return Err(syn::Error::new_spanned(
+                            meta,
+                            "`poll_budget` requires `#[behaviour(fair_poll)]` on the struct",
+                        ));
+                    }
+                    budget = meta.require_name_value()?.value.require_int_lit()?;
+                    if budget == 0 {
+                        return Err(syn::Error::new_spanned(
+                            meta,
+                            "`poll_budget` must be greater than zero",
+                        ));
+                    }
+                }
+            }
+        }
+
+        budgets.push(budget);
+    }
+
+    Ok(budgets)
+}
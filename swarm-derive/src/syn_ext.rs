@@ -4,6 +4,21 @@ pub(crate) trait RequireStrLit {
     fn require_str_lit(&self) -> syn::Result<String>;
 }
 
+pub(crate) trait RequireIntLit {
+    fn require_int_lit(&self) -> syn::Result<usize>;
+}
+
+impl RequireIntLit for Expr {
+    fn require_int_lit(&self) -> syn::Result<usize> {
+        match self {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(int), ..
+            }) => int.base10_parse(),
+            _ => Err(syn::Error::new_spanned(self, "expected an integer literal")),
+        }
+    }
+}
+
 impl RequireStrLit for Expr {
     fn require_str_lit(&self) -> syn::Result<String> {
         match self {
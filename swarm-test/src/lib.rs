@@ -20,9 +20,13 @@
 
 use async_trait::async_trait;
 use futures::future::{BoxFuture, Either};
-use futures::{FutureExt, StreamExt};
+use futures::{AsyncRead, AsyncWrite, FutureExt, StreamExt};
 use libp2p_core::{
-    multiaddr::Protocol, transport::MemoryTransport, upgrade::Version, Multiaddr, Transport,
+    multiaddr::Protocol,
+    muxing::StreamMuxerBox,
+    transport::{Boxed, MemoryTransport},
+    upgrade::Version,
+    Multiaddr, Transport,
 };
 use libp2p_identity::{Keypair, PeerId};
 use libp2p_plaintext as plaintext;
@@ -30,9 +34,97 @@ use libp2p_swarm::dial_opts::PeerCondition;
 use libp2p_swarm::{self as swarm, dial_opts::DialOpts, NetworkBehaviour, Swarm, SwarmEvent};
 use libp2p_yamux as yamux;
 use std::fmt::Debug;
-use std::future::IntoFuture;
+use std::future::{Future, IntoFuture};
 use std::time::Duration;
 
+/// An async runtime that a [`Swarm`] created through [`SwarmExt::new_ephemeral_on`] runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Runtime {
+    AsyncStd,
+    #[cfg(feature = "tokio")]
+    Tokio,
+}
+
+impl Runtime {
+    /// All runtimes enabled through the crate features.
+    pub const ALL: &'static [Runtime] = &[
+        Runtime::AsyncStd,
+        #[cfg(feature = "tokio")]
+        Runtime::Tokio,
+    ];
+
+    /// Runs the given future to completion on a new instance of this runtime.
+    pub fn block_on<F: Future>(self, future: F) -> F::Output {
+        match self {
+            Runtime::AsyncStd => async_std::task::block_on(future),
+            #[cfg(feature = "tokio")]
+            Runtime::Tokio => tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(future),
+        }
+    }
+}
+
+/// A transport through which [`SwarmExt::connect`] connects two [`Swarm`]s, depending on the
+/// external address added with [`ListenFuture::with_addr_external`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestTransport {
+    Memory,
+    Tcp,
+}
+
+impl TestTransport {
+    /// All transports.
+    pub const ALL: &'static [TestTransport] = &[TestTransport::Memory, TestTransport::Tcp];
+}
+
+/// Runs `test` once for every combination of [`Runtime::ALL`] and [`TestTransport::ALL`], on the
+/// respective runtime.
+///
+/// The [`Swarm`]s of the test must be created inside the returned future, with
+/// [`SwarmExt::new_ephemeral_on`]. See also [`matrix_test`].
+pub fn run_matrix<F, Fut>(test: F)
+where
+    F: Fn(Runtime, TestTransport) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    for runtime in Runtime::ALL {
+        for transport in TestTransport::ALL {
+            tracing::info!(?runtime, ?transport, "Running test");
+            runtime.block_on(test(*runtime, *transport));
+        }
+    }
+}
+
+/// Defines a `#[test]` that runs its body through [`run_matrix`].
+///
+/// ```
+/// # use libp2p_swarm::{dummy, Swarm};
+/// # use libp2p_swarm_test::SwarmExt;
+/// libp2p_swarm_test::matrix_test! {
+///     fn connects(runtime, transport) {
+///         let mut swarm1 = Swarm::new_ephemeral_on(runtime, |_| dummy::Behaviour);
+///         let mut swarm2 = Swarm::new_ephemeral_on(runtime, |_| dummy::Behaviour);
+///
+///         swarm1.listen().with_addr_external(transport).await;
+///         swarm2.connect(&mut swarm1).await;
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! matrix_test {
+    ($(#[$meta:meta])* fn $name:ident($runtime:ident, $transport:ident) $body:block) => {
+        $(#[$meta])*
+        #[test]
+        fn $name() {
+            $crate::run_matrix(|$runtime, $transport| async move $body);
+        }
+    };
+}
+
 /// An extension trait for [`Swarm`] that makes it easier to set up a network of [`Swarm`]s for tests.
 #[async_trait]
 pub trait SwarmExt {
@@ -47,6 +139,14 @@ pub trait SwarmExt {
     where
         Self: Sized;
 
+    /// Create a new [`Swarm`] with an ephemeral identity, running on the given [`Runtime`].
+    ///
+    /// Like [`SwarmExt::new_ephemeral`], but the TCP transport and the executor of the connection
+    /// tasks are those of `runtime`.
+    fn new_ephemeral_on(runtime: Runtime, behaviour_fn: impl FnOnce(Keypair) -> Self::NB) -> Self
+    where
+        Self: Sized;
+
     /// Establishes a connection to the given [`Swarm`], polling both of them until the connection is established.
     ///
     /// This will take addresses from the `other` [`Swarm`] via [`Swarm::external_addresses`].
@@ -201,26 +301,36 @@ where
     type NB = B;
 
     fn new_ephemeral(behaviour_fn: impl FnOnce(Keypair) -> Self::NB) -> Self
+    where
+        Self: Sized,
+    {
+        Self::new_ephemeral_on(Runtime::AsyncStd, behaviour_fn)
+    }
+
+    fn new_ephemeral_on(runtime: Runtime, behaviour_fn: impl FnOnce(Keypair) -> Self::NB) -> Self
     where
         Self: Sized,
     {
         let identity = Keypair::generate_ed25519();
         let peer_id = PeerId::from(identity.public());
 
-        let transport = MemoryTransport::default()
-            .or_transport(libp2p_tcp::async_io::Transport::default())
-            .upgrade(Version::V1)
-            .authenticate(plaintext::Config::new(&identity))
-            .multiplex(yamux::Config::default())
-            .timeout(Duration::from_secs(20))
-            .boxed();
+        let (transport, config) = match runtime {
+            Runtime::AsyncStd => (
+                ephemeral_transport(libp2p_tcp::async_io::Transport::default(), &identity),
+                swarm::Config::with_async_std_executor(),
+            ),
+            #[cfg(feature = "tokio")]
+            Runtime::Tokio => (
+                ephemeral_transport(libp2p_tcp::tokio::Transport::default(), &identity),
+                swarm::Config::with_tokio_executor(),
+            ),
+        };
 
         Swarm::new(
             transport,
             behaviour_fn(identity),
             peer_id,
-            swarm::Config::with_async_std_executor()
-                .with_idle_connection_timeout(Duration::from_secs(5)), // Some tests need connections to be kept alive beyond what the individual behaviour configures.,
+            config.with_idle_connection_timeout(Duration::from_secs(5)), // Some tests need connections to be kept alive beyond what the individual behaviour configures.,
         )
     }
 
@@ -339,6 +449,24 @@ where
     }
 }
 
+/// The transport of [`SwarmExt::new_ephemeral_on`], with the given TCP transport.
+fn ephemeral_transport<T>(tcp_transport: T, identity: &Keypair) -> Boxed<(PeerId, StreamMuxerBox)>
+where
+    T: Transport + Send + Unpin + 'static,
+    <T as Transport>::Error: Send + Sync + 'static,
+    <T as Transport>::Output: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    <T as Transport>::ListenerUpgrade: Send,
+    <T as Transport>::Dial: Send,
+{
+    MemoryTransport::default()
+        .or_transport(tcp_transport)
+        .upgrade(Version::V1)
+        .authenticate(plaintext::Config::new(identity))
+        .multiplex(yamux::Config::default())
+        .timeout(Duration::from_secs(20))
+        .boxed()
+}
+
 pub struct ListenFuture<S> {
     add_memory_external: bool,
     add_tcp_external: bool,
@@ -365,6 +493,15 @@ impl<S> ListenFuture<S> {
 
         self
     }
+
+    /// Adds the address of the given transport we are starting to listen on as an external address,
+    /// see [`ListenFuture::with_memory_addr_external`] and [`ListenFuture::with_tcp_addr_external`].
+    pub fn with_addr_external(self, transport: TestTransport) -> Self {
+        match transport {
+            TestTransport::Memory => self.with_memory_addr_external(),
+            TestTransport::Tcp => self.with_tcp_addr_external(),
+        }
+    }
 }
 
 impl<'s, B> IntoFuture for ListenFuture<&'s mut Swarm<B>>
@@ -0,0 +1,243 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Accounting of the bandwidth used per remote peer and protocol.
+//!
+//! Once enabled via [`Config::with_bandwidth_accounting`](crate::Config::with_bandwidth_accounting),
+//! the [`Swarm`](crate::Swarm) attributes every byte read from or written to a negotiated stream
+//! to the remote peer of the connection and the protocol negotiated on the stream.
+//! Bytes exchanged while negotiating the protocol and by the transport itself, e.g. for
+//! encryption and multiplexing, are not accounted.
+
+use crate::StreamProtocol;
+use libp2p_identity::PeerId;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// The number of bytes received and sent.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BandwidthUsage {
+    inbound: u64,
+    outbound: u64,
+}
+
+impl BandwidthUsage {
+    /// The number of bytes received from the remote.
+    pub fn inbound(&self) -> u64 {
+        self.inbound
+    }
+
+    /// The number of bytes sent to the remote.
+    pub fn outbound(&self) -> u64 {
+        self.outbound
+    }
+
+    fn add(&mut self, other: BandwidthUsage) {
+        self.inbound = self.inbound.saturating_add(other.inbound);
+        self.outbound = self.outbound.saturating_add(other.outbound);
+    }
+}
+
+/// A handle to the bandwidth accounting of a [`Swarm`](crate::Swarm).
+///
+/// Clones share the same counters, so a clone can be handed to the
+/// [`Config`](crate::Config) while another one is used to query the usage.
+#[derive(Debug, Default, Clone)]
+pub struct BandwidthAccounting {
+    counters: Arc<Mutex<HashMap<(PeerId, StreamProtocol), Arc<Counters>>>>,
+}
+
+impl BandwidthAccounting {
+    /// Creates a new [`BandwidthAccounting`] without any usage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<(PeerId, StreamProtocol), Arc<Counters>>> {
+        self.counters.lock().expect("lock not to be poisoned")
+    }
+
+    /// Returns the bandwidth used with `peer` for `protocol`.
+    pub fn usage(&self, peer: &PeerId, protocol: &StreamProtocol) -> BandwidthUsage {
+        self.lock()
+            .get(&(*peer, protocol.clone()))
+            .map(|c| c.usage())
+            .unwrap_or_default()
+    }
+
+    /// Returns the bandwidth used with `peer`, across all protocols.
+    pub fn peer_usage(&self, peer: &PeerId) -> BandwidthUsage {
+        self.sum(|p, _| p == peer)
+    }
+
+    /// Returns the bandwidth used for `protocol`, across all peers.
+    pub fn protocol_usage(&self, protocol: &StreamProtocol) -> BandwidthUsage {
+        self.sum(|_, p| p == protocol)
+    }
+
+    /// Returns the bandwidth used with each peer for each protocol.
+    pub fn snapshot(&self) -> Vec<(PeerId, StreamProtocol, BandwidthUsage)> {
+        self.lock()
+            .iter()
+            .map(|((peer, protocol), c)| (*peer, protocol.clone(), c.usage()))
+            .collect()
+    }
+
+    /// Forgets the bandwidth used with `peer`.
+    ///
+    /// Bytes exchanged on streams with `peer` that are still open are no longer accounted.
+    pub fn remove_peer(&self, peer: &PeerId) {
+        self.lock().retain(|(p, _), _| p != peer);
+    }
+
+    fn sum(&self, filter: impl Fn(&PeerId, &StreamProtocol) -> bool) -> BandwidthUsage {
+        let mut usage = BandwidthUsage::default();
+        for ((peer, protocol), c) in self.lock().iter() {
+            if filter(peer, protocol) {
+                usage.add(c.usage());
+            }
+        }
+        usage
+    }
+
+    /// Returns the counters of a new stream with `peer`, negotiated for `protocol`.
+    ///
+    /// Returns `None` if `protocol` is not a valid [`StreamProtocol`].
+    pub(crate) fn stream(&self, peer: PeerId, protocol: &str) -> Option<StreamBandwidth> {
+        let protocol = StreamProtocol::try_from_owned(protocol.to_owned()).ok()?;
+        let counters = self.lock().entry((peer, protocol)).or_default().clone();
+        Some(StreamBandwidth(counters))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    inbound: AtomicU64,
+    outbound: AtomicU64,
+}
+
+impl Counters {
+    fn usage(&self) -> BandwidthUsage {
+        BandwidthUsage {
+            inbound: self.inbound.load(Ordering::Relaxed),
+            outbound: self.outbound.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The bandwidth accounting of a single [`Stream`](crate::Stream).
+#[derive(Debug, Clone)]
+pub(crate) struct StreamBandwidth(Arc<Counters>);
+
+impl StreamBandwidth {
+    pub(crate) fn record_inbound(&self, bytes: usize) {
+        self.0.inbound.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_outbound(&self, bytes: usize) {
+        self.0.outbound.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+/// The bandwidth accounting of a connection to a single peer.
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectionBandwidth {
+    accounting: BandwidthAccounting,
+    peer: PeerId,
+}
+
+impl ConnectionBandwidth {
+    pub(crate) fn new(accounting: BandwidthAccounting, peer: PeerId) -> Self {
+        Self { accounting, peer }
+    }
+
+    pub(crate) fn stream(&self, protocol: &str) -> Option<StreamBandwidth> {
+        self.accounting.stream(self.peer, protocol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregates_usage_by_peer_and_protocol() {
+        let accounting = BandwidthAccounting::new();
+        let alice = PeerId::random();
+        let bob = PeerId::random();
+        let ping = StreamProtocol::new("/ping");
+        let kad = StreamProtocol::new("/kad");
+
+        let stream = accounting.stream(alice, "/ping").unwrap();
+        stream.record_inbound(10);
+        stream.record_outbound(20);
+        accounting
+            .stream(alice, "/kad")
+            .unwrap()
+            .record_inbound(100);
+        accounting.stream(bob, "/ping").unwrap().record_outbound(1);
+
+        assert_eq!(
+            accounting.usage(&alice, &ping),
+            BandwidthUsage {
+                inbound: 10,
+                outbound: 20
+            }
+        );
+        assert_eq!(
+            accounting.peer_usage(&alice),
+            BandwidthUsage {
+                inbound: 110,
+                outbound: 20
+            }
+        );
+        assert_eq!(
+            accounting.protocol_usage(&ping),
+            BandwidthUsage {
+                inbound: 10,
+                outbound: 21
+            }
+        );
+        assert_eq!(accounting.snapshot().len(), 3);
+
+        accounting.remove_peer(&alice);
+        assert_eq!(accounting.peer_usage(&alice), BandwidthUsage::default());
+        assert_eq!(accounting.usage(&alice, &kad), BandwidthUsage::default());
+        assert_eq!(accounting.snapshot().len(), 1);
+    }
+
+    #[test]
+    fn streams_of_same_peer_and_protocol_share_counters() {
+        let accounting = BandwidthAccounting::new();
+        let peer = PeerId::random();
+
+        accounting.stream(peer, "/ping").unwrap().record_inbound(1);
+        accounting.stream(peer, "/ping").unwrap().record_inbound(2);
+
+        assert_eq!(
+            accounting
+                .usage(&peer, &StreamProtocol::new("/ping"))
+                .inbound(),
+            3
+        );
+        assert!(accounting.stream(peer, "no-slash").is_none());
+    }
+}
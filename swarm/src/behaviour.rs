@@ -18,13 +18,17 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+pub mod dynamic;
 mod either;
 mod external_addresses;
 mod listen_addresses;
 mod peer_addresses;
 pub mod toggle;
 
-pub use external_addresses::ExternalAddresses;
+pub use external_addresses::{
+    rank_by_source_and_recency, AddressRanking, ExternalAddressSource, ExternalAddresses,
+    ScoredAddress,
+};
 pub use listen_addresses::ListenAddresses;
 pub use peer_addresses::PeerAddresses;
 
@@ -32,8 +36,8 @@ use crate::connection::ConnectionId;
 use crate::dial_opts::DialOpts;
 use crate::listen_opts::ListenOpts;
 use crate::{
-    ConnectionDenied, ConnectionError, ConnectionHandler, DialError, ListenError, THandler,
-    THandlerInEvent, THandlerOutEvent,
+    ConnectionDenied, ConnectionError, ConnectionExtensions, ConnectionHandler, DialError,
+    ListenError, THandler, THandlerInEvent, THandlerOutEvent, TagValue,
 };
 use libp2p_core::{
     transport::{ListenerId, PortUse},
@@ -122,6 +126,13 @@ use std::{task::Context, task::Poll};
 ///   }
 /// }
 /// ```
+///
+/// By default, the generated `poll` polls the `struct` members in the order of their declaration,
+/// so a member that always has an event ready can starve the ones declared after it. With
+/// `#[behaviour(fair_poll)]`, the members are polled round-robin instead: each call to `poll`
+/// starts with the member after the one that returned the last event. A member can be allowed to
+/// return several events in a row before yielding with `#[behaviour(poll_budget = N)]`.
+/// Note that the scheduling state is shared between all instances of the `struct`.
 pub trait NetworkBehaviour: 'static {
     /// Handler for all the protocols the network behaviour supports.
     type ConnectionHandler: ConnectionHandler;
@@ -294,8 +305,20 @@ pub enum ToSwarm<TOutEvent, TInEvent> {
     ///
     /// This is intended to be issued in response to a [`FromSwarm::NewExternalAddrCandidate`] if we are indeed externally reachable on this address.
     /// This address will be shared with all [`NetworkBehaviour`]s via [`FromSwarm::ExternalAddrConfirmed`].
+    /// The address is ranked as [`ExternalAddressSource::Confirmed`], use
+    /// [`ToSwarm::ExternalAddrConfirmedWithSource`] to state how it was confirmed.
     ExternalAddrConfirmed(Multiaddr),
 
+    /// Like [`ToSwarm::ExternalAddrConfirmed`], but states how the address was confirmed.
+    ///
+    /// Behaviours can use the [`ExternalAddressSource`] of the resulting
+    /// [`FromSwarm::ExternalAddrConfirmed`], e.g. to only advertise addresses confirmed via
+    /// AutoNAT.
+    ExternalAddrConfirmedWithSource {
+        address: Multiaddr,
+        source: ExternalAddressSource,
+    },
+
     /// Indicates to the [`Swarm`](crate::Swarm) that we are no longer externally reachable under the provided address.
     ///
     /// This expires an address that was earlier confirmed via [`ToSwarm::ExternalAddrConfirmed`].
@@ -318,6 +341,23 @@ pub enum ToSwarm<TOutEvent, TInEvent> {
 
     /// Reports external address of a remote peer to the [`Swarm`](crate::Swarm) and through that to other [`NetworkBehaviour`]s.
     NewExternalAddrOfPeer { peer_id: PeerId, address: Multiaddr },
+
+    /// Instructs the [`Swarm`](crate::Swarm) to set the tag `key` of an established connection
+    /// to `value`, see [`ConnectionTags`](crate::ConnectionTags).
+    ///
+    /// If the connection no longer exists, the tag is silently dropped.
+    TagConnection {
+        connection_id: ConnectionId,
+        key: String,
+        value: TagValue,
+    },
+
+    /// Instructs the [`Swarm`](crate::Swarm) to remove the tag `key` of an established
+    /// connection, see [`ConnectionTags`](crate::ConnectionTags).
+    UntagConnection {
+        connection_id: ConnectionId,
+        key: String,
+    },
 }
 
 impl<TOutEvent, TInEventOld> ToSwarm<TOutEvent, TInEventOld> {
@@ -349,6 +389,9 @@ impl<TOutEvent, TInEventOld> ToSwarm<TOutEvent, TInEventOld> {
             },
             ToSwarm::NewExternalAddrCandidate(addr) => ToSwarm::NewExternalAddrCandidate(addr),
             ToSwarm::ExternalAddrConfirmed(addr) => ToSwarm::ExternalAddrConfirmed(addr),
+            ToSwarm::ExternalAddrConfirmedWithSource { address, source } => {
+                ToSwarm::ExternalAddrConfirmedWithSource { address, source }
+            }
             ToSwarm::ExternalAddrExpired(addr) => ToSwarm::ExternalAddrExpired(addr),
             ToSwarm::NewExternalAddrOfPeer {
                 address: addr,
@@ -357,6 +400,18 @@ impl<TOutEvent, TInEventOld> ToSwarm<TOutEvent, TInEventOld> {
                 address: addr,
                 peer_id,
             },
+            ToSwarm::TagConnection {
+                connection_id,
+                key,
+                value,
+            } => ToSwarm::TagConnection {
+                connection_id,
+                key,
+                value,
+            },
+            ToSwarm::UntagConnection { connection_id, key } => {
+                ToSwarm::UntagConnection { connection_id, key }
+            }
         }
     }
 }
@@ -380,6 +435,9 @@ impl<TOutEvent, THandlerIn> ToSwarm<TOutEvent, THandlerIn> {
             },
             ToSwarm::NewExternalAddrCandidate(addr) => ToSwarm::NewExternalAddrCandidate(addr),
             ToSwarm::ExternalAddrConfirmed(addr) => ToSwarm::ExternalAddrConfirmed(addr),
+            ToSwarm::ExternalAddrConfirmedWithSource { address, source } => {
+                ToSwarm::ExternalAddrConfirmedWithSource { address, source }
+            }
             ToSwarm::ExternalAddrExpired(addr) => ToSwarm::ExternalAddrExpired(addr),
             ToSwarm::CloseConnection {
                 peer_id,
@@ -395,6 +453,18 @@ impl<TOutEvent, THandlerIn> ToSwarm<TOutEvent, THandlerIn> {
                 address: addr,
                 peer_id,
             },
+            ToSwarm::TagConnection {
+                connection_id,
+                key,
+                value,
+            } => ToSwarm::TagConnection {
+                connection_id,
+                key,
+                value,
+            },
+            ToSwarm::UntagConnection { connection_id, key } => {
+                ToSwarm::UntagConnection { connection_id, key }
+            }
         }
     }
 }
@@ -463,6 +533,11 @@ pub enum FromSwarm<'a> {
     ExternalAddrExpired(ExternalAddrExpired<'a>),
     /// Informs the behaviour that we have discovered a new external address for a remote peer.
     NewExternalAddrOfPeer(NewExternalAddrOfPeer<'a>),
+    /// Informs the behaviour that the [`Swarm`](crate::Swarm) is shutting down, see
+    /// [`Swarm::close_gracefully`](crate::Swarm::close_gracefully).
+    ///
+    /// All connections are being closed and no new connections are established.
+    ShutdownStarted,
 }
 
 /// [`FromSwarm`] variant that informs the behaviour about a newly established connection to a peer.
@@ -487,6 +562,7 @@ pub struct ConnectionClosed<'a> {
     pub endpoint: &'a ConnectedPoint,
     pub cause: Option<&'a ConnectionError>,
     pub remaining_established: usize,
+    pub extensions: &'a ConnectionExtensions,
 }
 
 /// [`FromSwarm`] variant that informs the behaviour that the [`ConnectedPoint`] of an existing
@@ -569,6 +645,8 @@ pub struct NewExternalAddrCandidate<'a> {
 #[derive(Debug, Clone, Copy)]
 pub struct ExternalAddrConfirmed<'a> {
     pub addr: &'a Multiaddr,
+    /// How the address was confirmed.
+    pub source: ExternalAddressSource,
 }
 
 /// [`FromSwarm`] variant that informs the behaviour that an external address was removed.
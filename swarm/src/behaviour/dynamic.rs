@@ -0,0 +1,1295 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! A [`NetworkBehaviour`] composed of other behaviours that can be added and removed at runtime.
+//!
+//! Unlike [`Toggle`](super::toggle::Toggle), which is enabled or disabled once at initialization,
+//! a [`BehaviourSet`] allows enabling protocols after the [`Swarm`](crate::Swarm) was built, e.g.
+//! for plugin-style applications.
+//!
+//! Each behaviour is stored under a key. The events of a behaviour are returned together with its
+//! key and converted into the common event type of the set.
+//!
+//! A behaviour added to the set is informed about the listen addresses, the confirmed external
+//! addresses and the established connections, as if it had been part of the set from the start.
+//! Its [`ConnectionHandler`]s are added to the existing connections. A behaviour removed from the
+//! set is dropped together with its [`ConnectionHandler`]s.
+
+use crate::behaviour::{
+    ConnectionEstablished, ExternalAddrConfirmed, ExternalAddresses, FromSwarm, NewListenAddr,
+    NotifyHandler,
+};
+use crate::connection::ConnectionId;
+use crate::handler::multi::{self, DuplicateProtonameError, MultiHandler};
+use crate::handler::{
+    AddressChange, ConnectionEvent, ConnectionHandler, ConnectionHandlerEvent, DialUpgradeError,
+    FullyNegotiatedInbound, FullyNegotiatedOutbound, ListenUpgradeError, SubstreamProtocol,
+};
+use crate::upgrade::{InboundUpgradeSend, OutboundUpgradeSend, UpgradeInfoSend};
+use crate::{
+    ConnectionDenied, NetworkBehaviour, Stream, THandler, THandlerInEvent, THandlerOutEvent,
+    ToSwarm,
+};
+use futures::future::BoxFuture;
+use futures::{FutureExt, TryFutureExt};
+use libp2p_core::transport::{ListenerId, PortUse};
+use libp2p_core::{ConnectedPoint, Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use rand::Rng;
+use std::any::Any;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{self, Debug};
+use std::hash::Hash;
+use std::task::{Context, Poll, Waker};
+
+/// A type-erased value passed between a behaviour and its [`ConnectionHandler`]s.
+type AnyBox = Box<dyn Any + Send>;
+
+/// A behaviour of the set, identified by its key and the generation it was added in.
+///
+/// The generation tells apart a behaviour from one it replaced under the same key.
+type Slot<K> = (K, u64);
+
+/// Downcasts a value boxed by the same behaviour or handler.
+fn downcast<T: 'static>(value: AnyBox) -> T {
+    *value
+        .downcast()
+        .expect("value to be boxed by the same behaviour or handler; QED")
+}
+
+/// A [`NetworkBehaviour`] composed of boxed behaviours that can be added and removed at runtime.
+///
+/// See the [module-level documentation](self) for details.
+pub struct BehaviourSet<K, TOut> {
+    behaviours: HashMap<K, Entry<TOut>>,
+    next_generation: u64,
+    /// The established connections with the behaviours that have handlers on them.
+    connections: HashMap<ConnectionId, Connection<K>>,
+    listen_addresses: Vec<(ListenerId, Multiaddr)>,
+    external_addresses: ExternalAddresses,
+    pending_events: VecDeque<ToSwarm<(K, TOut), HandlerIn<K>>>,
+    /// The waker of the task polling the set, woken when behaviours are added or removed.
+    waker: Option<Waker>,
+}
+
+struct Entry<TOut> {
+    generation: u64,
+    behaviour: Box<dyn ErasedBehaviour<TOut> + Send>,
+}
+
+struct Connection<K> {
+    peer_id: PeerId,
+    endpoint: ConnectedPoint,
+    slots: HashSet<Slot<K>>,
+}
+
+impl<K, TOut> Default for BehaviourSet<K, TOut> {
+    fn default() -> Self {
+        Self {
+            behaviours: HashMap::new(),
+            next_generation: 0,
+            connections: HashMap::new(),
+            listen_addresses: Vec::new(),
+            external_addresses: ExternalAddresses::default(),
+            pending_events: VecDeque::new(),
+            waker: None,
+        }
+    }
+}
+
+impl<K, TOut> BehaviourSet<K, TOut>
+where
+    K: Clone + Debug + Hash + Eq + Send + 'static,
+    TOut: Send + 'static,
+{
+    /// Creates an empty [`BehaviourSet`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a behaviour under the given key, replacing the behaviour with the same key, if any.
+    ///
+    /// Returns whether a behaviour was replaced.
+    ///
+    /// Connections on which the behaviour denies a handler, or on which one of the handlers of
+    /// the other behaviours already supports one of its protocols, remain without a handler of
+    /// the behaviour.
+    pub fn insert<B>(&mut self, key: K, behaviour: B) -> bool
+    where
+        B: NetworkBehaviour + Send,
+        B::ToSwarm: Into<TOut>,
+    {
+        let replaced = self.remove(&key);
+
+        let generation = self.next_generation;
+        self.next_generation += 1;
+        let mut behaviour: Box<dyn ErasedBehaviour<TOut> + Send> = Box::new(behaviour);
+
+        for (listener_id, addr) in &self.listen_addresses {
+            behaviour.on_swarm_event(FromSwarm::NewListenAddr(NewListenAddr {
+                listener_id: *listener_id,
+                addr,
+            }));
+        }
+        for scored in self.external_addresses.scored() {
+            behaviour.on_swarm_event(FromSwarm::ExternalAddrConfirmed(ExternalAddrConfirmed {
+                addr: scored.address(),
+                source: scored.source(),
+            }));
+        }
+
+        let mut established_per_peer = HashMap::<PeerId, usize>::new();
+        for (&connection_id, connection) in &mut self.connections {
+            let peer_id = connection.peer_id;
+            let handler = match &connection.endpoint {
+                ConnectedPoint::Dialer {
+                    address,
+                    role_override,
+                    port_use,
+                } => behaviour.handle_established_outbound_connection(
+                    connection_id,
+                    peer_id,
+                    address,
+                    *role_override,
+                    *port_use,
+                ),
+                ConnectedPoint::Listener {
+                    local_addr,
+                    send_back_addr,
+                } => behaviour.handle_established_inbound_connection(
+                    connection_id,
+                    peer_id,
+                    local_addr,
+                    send_back_addr,
+                ),
+            };
+            let handler = match handler {
+                Ok(handler) => handler,
+                Err(cause) => {
+                    tracing::debug!(
+                        peer=%peer_id,
+                        connection=?connection_id,
+                        key=?key,
+                        "Behaviour denied handler on existing connection: {cause}"
+                    );
+                    continue;
+                }
+            };
+
+            let other_established = established_per_peer.entry(peer_id).or_default();
+            behaviour.on_swarm_event(FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                peer_id,
+                connection_id,
+                endpoint: &connection.endpoint,
+                failed_addresses: &[],
+                other_established: *other_established,
+            }));
+            *other_established += 1;
+
+            let slot = (key.clone(), generation);
+            connection.slots.insert(slot.clone());
+            self.pending_events.push_back(ToSwarm::NotifyHandler {
+                peer_id,
+                handler: NotifyHandler::One(connection_id),
+                event: HandlerIn {
+                    command: Command::Insert(slot, handler),
+                },
+            });
+        }
+
+        self.behaviours.insert(
+            key,
+            Entry {
+                generation,
+                behaviour,
+            },
+        );
+        self.wake();
+
+        replaced
+    }
+
+    /// Removes the behaviour with the given key, dropping its handlers on all connections.
+    ///
+    /// Returns whether the set contained a behaviour with the key.
+    pub fn remove(&mut self, key: &K) -> bool {
+        let Some(entry) = self.behaviours.remove(key) else {
+            return false;
+        };
+
+        let slot = (key.clone(), entry.generation);
+        for (&connection_id, connection) in &mut self.connections {
+            if connection.slots.remove(&slot) {
+                self.pending_events.push_back(ToSwarm::NotifyHandler {
+                    peer_id: connection.peer_id,
+                    handler: NotifyHandler::One(connection_id),
+                    event: HandlerIn {
+                        command: Command::Remove(slot.clone()),
+                    },
+                });
+            }
+        }
+        self.wake();
+
+        true
+    }
+
+    /// Returns whether the set contains a behaviour with the given key.
+    pub fn contains(&self, key: &K) -> bool {
+        self.behaviours.contains_key(key)
+    }
+
+    /// Returns a reference to the behaviour with the given key, if it is of type `B`.
+    pub fn get<B: NetworkBehaviour>(&self, key: &K) -> Option<&B> {
+        self.behaviours.get(key)?.behaviour.as_any().downcast_ref()
+    }
+
+    /// Returns a mutable reference to the behaviour with the given key, if it is of type `B`.
+    pub fn get_mut<B: NetworkBehaviour>(&mut self, key: &K) -> Option<&mut B> {
+        self.behaviours
+            .get_mut(key)?
+            .behaviour
+            .as_any_mut()
+            .downcast_mut()
+    }
+
+    /// Returns an iterator over the keys of the behaviours in the set.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.behaviours.keys()
+    }
+
+    /// Forwards an event about an established connection to the behaviours with a handler on it.
+    fn forward_connection_event(&mut self, connection_id: ConnectionId, event: FromSwarm) {
+        let Some(connection) = self.connections.get(&connection_id) else {
+            return;
+        };
+        for (key, entry) in self.behaviours.iter_mut() {
+            if connection.slots.contains(&(key.clone(), entry.generation)) {
+                entry.behaviour.on_swarm_event(event);
+            }
+        }
+    }
+
+    /// Wakes the task polling the set, which has new behaviours or events to poll.
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn poll_entry(
+        key: &K,
+        entry: &mut Entry<TOut>,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<(K, TOut), HandlerIn<K>>> {
+        let generation = entry.generation;
+        entry.behaviour.poll(cx).map(|event| {
+            event
+                .map_out(|event| (key.clone(), event))
+                .map_in(|event| HandlerIn {
+                    command: Command::Event((key.clone(), generation), event),
+                })
+        })
+    }
+}
+
+impl<K, TOut> NetworkBehaviour for BehaviourSet<K, TOut>
+where
+    K: Clone + Debug + Hash + Eq + Send + 'static,
+    TOut: Send + 'static,
+{
+    type ConnectionHandler = Handler<K>;
+    type ToSwarm = (K, TOut);
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        for entry in self.behaviours.values_mut() {
+            entry.behaviour.handle_pending_inbound_connection(
+                connection_id,
+                local_addr,
+                remote_addr,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        let handlers = self
+            .behaviours
+            .iter_mut()
+            .map(|(key, entry)| {
+                let handler = entry.behaviour.handle_established_inbound_connection(
+                    connection_id,
+                    peer,
+                    local_addr,
+                    remote_addr,
+                )?;
+                Ok(((key.clone(), entry.generation), handler))
+            })
+            .collect::<Result<Vec<_>, ConnectionDenied>>()?;
+
+        Handler::try_from_iter(handlers).map_err(ConnectionDenied::new)
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        let mut combined_addresses = Vec::new();
+        for entry in self.behaviours.values_mut() {
+            combined_addresses.extend(entry.behaviour.handle_pending_outbound_connection(
+                connection_id,
+                maybe_peer,
+                addresses,
+                effective_role,
+            )?);
+        }
+
+        Ok(combined_addresses)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+        port_use: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        let handlers = self
+            .behaviours
+            .iter_mut()
+            .map(|(key, entry)| {
+                let handler = entry.behaviour.handle_established_outbound_connection(
+                    connection_id,
+                    peer,
+                    addr,
+                    role_override,
+                    port_use,
+                )?;
+                Ok(((key.clone(), entry.generation), handler))
+            })
+            .collect::<Result<Vec<_>, ConnectionDenied>>()?;
+
+        Handler::try_from_iter(handlers).map_err(ConnectionDenied::new)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        self.external_addresses.on_swarm_event(&event);
+
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                peer_id,
+                connection_id,
+                endpoint,
+                ..
+            }) => {
+                let slots = self
+                    .behaviours
+                    .iter()
+                    .map(|(key, entry)| (key.clone(), entry.generation))
+                    .collect();
+                self.connections.insert(
+                    connection_id,
+                    Connection {
+                        peer_id,
+                        endpoint: endpoint.clone(),
+                        slots,
+                    },
+                );
+            }
+            FromSwarm::AddressChange(change) => {
+                if let Some(connection) = self.connections.get_mut(&change.connection_id) {
+                    connection.endpoint = change.new.clone();
+                }
+            }
+            FromSwarm::NewListenAddr(NewListenAddr { listener_id, addr }) => {
+                self.listen_addresses.push((listener_id, addr.clone()));
+            }
+            FromSwarm::ExpiredListenAddr(expired) => {
+                self.listen_addresses.retain(|(listener_id, addr)| {
+                    *listener_id != expired.listener_id || addr != expired.addr
+                });
+            }
+            FromSwarm::ListenerClosed(closed) => {
+                self.listen_addresses
+                    .retain(|(listener_id, _)| *listener_id != closed.listener_id);
+            }
+            _ => {}
+        }
+
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished { connection_id, .. })
+            | FromSwarm::AddressChange(crate::behaviour::AddressChange { connection_id, .. }) => {
+                self.forward_connection_event(connection_id, event);
+            }
+            FromSwarm::ConnectionClosed(closed) => {
+                self.forward_connection_event(closed.connection_id, event);
+                self.connections.remove(&closed.connection_id);
+            }
+            _ => {
+                for entry in self.behaviours.values_mut() {
+                    entry.behaviour.on_swarm_event(event);
+                }
+            }
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        ((key, generation), event): THandlerOutEvent<Self>,
+    ) {
+        let Some(entry) = self.behaviours.get_mut(&key) else {
+            return;
+        };
+        // The behaviour might have been replaced while the event was in flight.
+        if entry.generation == generation {
+            entry
+                .behaviour
+                .on_connection_handler_event(peer_id, connection_id, event);
+        }
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        self.waker = Some(cx.waker().clone());
+
+        if let Some(event) = self.pending_events.pop_front() {
+            return Poll::Ready(event);
+        }
+
+        if self.behaviours.is_empty() {
+            return Poll::Pending;
+        }
+
+        // Not always polling behaviours in the same order should give anyone the chance to make progress.
+        let pos = rand::thread_rng().gen_range(0..self.behaviours.len());
+
+        for (key, entry) in self.behaviours.iter_mut().skip(pos) {
+            if let Poll::Ready(event) = Self::poll_entry(key, entry, cx) {
+                return Poll::Ready(event);
+            }
+        }
+
+        for (key, entry) in self.behaviours.iter_mut().take(pos) {
+            if let Poll::Ready(event) = Self::poll_entry(key, entry, cx) {
+                return Poll::Ready(event);
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+/// Object-safe counterpart of [`NetworkBehaviour`], with the handlers and their events boxed.
+trait ErasedBehaviour<TOut>: 'static {
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied>;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<BoxedHandler, ConnectionDenied>;
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied>;
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+        port_use: PortUse,
+    ) -> Result<BoxedHandler, ConnectionDenied>;
+
+    fn on_swarm_event(&mut self, event: FromSwarm);
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: AnyBox,
+    );
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ToSwarm<TOut, AnyBox>>;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<B, TOut> ErasedBehaviour<TOut> for B
+where
+    B: NetworkBehaviour,
+    B::ToSwarm: Into<TOut>,
+{
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        NetworkBehaviour::handle_pending_inbound_connection(
+            self,
+            connection_id,
+            local_addr,
+            remote_addr,
+        )
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<BoxedHandler, ConnectionDenied> {
+        NetworkBehaviour::handle_established_inbound_connection(
+            self,
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )
+        .map(BoxedHandler::new)
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        NetworkBehaviour::handle_pending_outbound_connection(
+            self,
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+        port_use: PortUse,
+    ) -> Result<BoxedHandler, ConnectionDenied> {
+        NetworkBehaviour::handle_established_outbound_connection(
+            self,
+            connection_id,
+            peer,
+            addr,
+            role_override,
+            port_use,
+        )
+        .map(BoxedHandler::new)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        NetworkBehaviour::on_swarm_event(self, event)
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: AnyBox,
+    ) {
+        NetworkBehaviour::on_connection_handler_event(
+            self,
+            peer_id,
+            connection_id,
+            downcast::<THandlerOutEvent<B>>(event),
+        )
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ToSwarm<TOut, AnyBox>> {
+        NetworkBehaviour::poll(self, cx).map(|event| {
+            event
+                .map_out(Into::into)
+                .map_in(|event| Box::new(event) as AnyBox)
+        })
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The [`ConnectionHandler`] of a [`BehaviourSet`], composed of the handlers of its behaviours.
+pub struct Handler<K> {
+    inner: MultiHandler<Slot<K>, BoxedHandler>,
+}
+
+/// The event sent by a [`BehaviourSet`] to its [`Handler`]s.
+#[derive(Debug)]
+pub struct HandlerIn<K> {
+    command: Command<K>,
+}
+
+#[derive(Debug)]
+enum Command<K> {
+    Event(Slot<K>, AnyBox),
+    Insert(Slot<K>, BoxedHandler),
+    Remove(Slot<K>),
+}
+
+impl<K> Handler<K>
+where
+    K: Clone + Debug + Hash + Eq + Send + 'static,
+{
+    fn try_from_iter(
+        handlers: impl IntoIterator<Item = (Slot<K>, BoxedHandler)>,
+    ) -> Result<Self, DuplicateProtonameError> {
+        Ok(Self {
+            inner: MultiHandler::try_from_iter(handlers)?,
+        })
+    }
+}
+
+impl<K> ConnectionHandler for Handler<K>
+where
+    K: Clone + Debug + Hash + Eq + Send + 'static,
+{
+    type FromBehaviour = HandlerIn<K>;
+    type ToBehaviour = (Slot<K>, AnyBox);
+    type InboundProtocol = multi::Upgrade<Slot<K>, BoxedInboundUpgrade>;
+    type OutboundProtocol = BoxedOutboundUpgrade;
+    type InboundOpenInfo = multi::Info<Slot<K>, AnyBox>;
+    type OutboundOpenInfo = (Slot<K>, AnyBox);
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        ConnectionHandler::listen_protocol(&self.inner)
+    }
+
+    fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
+        match event.command {
+            Command::Event(slot, event) => {
+                ConnectionHandler::on_behaviour_event(&mut self.inner, (slot, event))
+            }
+            Command::Insert(slot, handler) => {
+                if let Err(e) = self.inner.try_insert(slot, handler) {
+                    tracing::warn!("Not adding handler to existing connection: {e}");
+                }
+            }
+            Command::Remove(slot) => {
+                self.inner.remove(&slot);
+            }
+        }
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        ConnectionHandler::connection_keep_alive(&self.inner)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<
+        ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
+    > {
+        ConnectionHandler::poll(&mut self.inner, cx)
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::ToBehaviour>> {
+        ConnectionHandler::poll_close(&mut self.inner, cx)
+    }
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<
+            Self::InboundProtocol,
+            Self::OutboundProtocol,
+            Self::InboundOpenInfo,
+            Self::OutboundOpenInfo,
+        >,
+    ) {
+        ConnectionHandler::on_connection_event(&mut self.inner, event)
+    }
+}
+
+/// A boxed [`ConnectionHandler`] with its protocols, infos and events boxed as well.
+pub struct BoxedHandler {
+    inner: Box<dyn ErasedHandler>,
+}
+
+impl BoxedHandler {
+    fn new<H: ConnectionHandler>(handler: H) -> Self {
+        Self {
+            inner: Box::new(handler),
+        }
+    }
+}
+
+impl fmt::Debug for BoxedHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxedHandler").finish_non_exhaustive()
+    }
+}
+
+impl ConnectionHandler for BoxedHandler {
+    type FromBehaviour = AnyBox;
+    type ToBehaviour = AnyBox;
+    type InboundProtocol = BoxedInboundUpgrade;
+    type OutboundProtocol = BoxedOutboundUpgrade;
+    type InboundOpenInfo = AnyBox;
+    type OutboundOpenInfo = AnyBox;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        self.inner.listen_protocol()
+    }
+
+    fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
+        self.inner.on_behaviour_event(event)
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        self.inner.connection_keep_alive()
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<
+        ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
+    > {
+        self.inner.poll(cx)
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<Self::ToBehaviour>> {
+        self.inner.poll_close(cx)
+    }
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<
+            Self::InboundProtocol,
+            Self::OutboundProtocol,
+            Self::InboundOpenInfo,
+            Self::OutboundOpenInfo,
+        >,
+    ) {
+        self.inner.on_connection_event(event)
+    }
+}
+
+/// Object-safe counterpart of [`ConnectionHandler`], see [`BoxedHandler`].
+trait ErasedHandler: Send + 'static {
+    fn listen_protocol(&self) -> SubstreamProtocol<BoxedInboundUpgrade, AnyBox>;
+
+    fn on_behaviour_event(&mut self, event: AnyBox);
+
+    fn connection_keep_alive(&self) -> bool;
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ConnectionHandlerEvent<BoxedOutboundUpgrade, AnyBox, AnyBox>>;
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<AnyBox>>;
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<BoxedInboundUpgrade, BoxedOutboundUpgrade, AnyBox, AnyBox>,
+    );
+}
+
+impl<H: ConnectionHandler> ErasedHandler for H {
+    fn listen_protocol(&self) -> SubstreamProtocol<BoxedInboundUpgrade, AnyBox> {
+        ConnectionHandler::listen_protocol(self)
+            .map_upgrade(BoxedInboundUpgrade::new)
+            .map_info(|info| Box::new(info) as AnyBox)
+    }
+
+    fn on_behaviour_event(&mut self, event: AnyBox) {
+        ConnectionHandler::on_behaviour_event(self, downcast(event))
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        ConnectionHandler::connection_keep_alive(self)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ConnectionHandlerEvent<BoxedOutboundUpgrade, AnyBox, AnyBox>> {
+        ConnectionHandler::poll(self, cx).map(|event| {
+            event
+                .map_protocol(BoxedOutboundUpgrade::new)
+                .map_outbound_open_info(|info| Box::new(info) as AnyBox)
+                .map_custom(|event| Box::new(event) as AnyBox)
+        })
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Option<AnyBox>> {
+        ConnectionHandler::poll_close(self, cx).map(|event| event.map(|e| Box::new(e) as AnyBox))
+    }
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<BoxedInboundUpgrade, BoxedOutboundUpgrade, AnyBox, AnyBox>,
+    ) {
+        let event: ConnectionEvent<
+            H::InboundProtocol,
+            H::OutboundProtocol,
+            H::InboundOpenInfo,
+            H::OutboundOpenInfo,
+        > = match event {
+            ConnectionEvent::FullyNegotiatedInbound(FullyNegotiatedInbound { protocol, info }) => {
+                ConnectionEvent::FullyNegotiatedInbound(FullyNegotiatedInbound {
+                    protocol: downcast(protocol),
+                    info: downcast(info),
+                })
+            }
+            ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound {
+                protocol,
+                info,
+            }) => ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound {
+                protocol: downcast(protocol),
+                info: downcast(info),
+            }),
+            ConnectionEvent::AddressChange(AddressChange { new_address }) => {
+                ConnectionEvent::AddressChange(AddressChange { new_address })
+            }
+            ConnectionEvent::DialUpgradeError(DialUpgradeError { info, error }) => {
+                ConnectionEvent::DialUpgradeError(DialUpgradeError {
+                    info: downcast(info),
+                    error: error.map_upgrade_err(downcast),
+                })
+            }
+            ConnectionEvent::ListenUpgradeError(ListenUpgradeError { info, error }) => {
+                ConnectionEvent::ListenUpgradeError(ListenUpgradeError {
+                    info: downcast(info),
+                    error: downcast(error),
+                })
+            }
+            ConnectionEvent::LocalProtocolsChange(change) => {
+                ConnectionEvent::LocalProtocolsChange(change)
+            }
+            ConnectionEvent::RemoteProtocolsChange(change) => {
+                ConnectionEvent::RemoteProtocolsChange(change)
+            }
+            ConnectionEvent::RemoteActivity => ConnectionEvent::RemoteActivity,
+        };
+
+        ConnectionHandler::on_connection_event(self, event)
+    }
+}
+
+/// A boxed inbound upgrade of a [`BoxedHandler`].
+pub struct BoxedInboundUpgrade {
+    inner: Box<dyn ErasedInboundUpgrade>,
+}
+
+impl BoxedInboundUpgrade {
+    fn new<U: InboundUpgradeSend>(upgrade: U) -> Self {
+        Self {
+            inner: Box::new(upgrade),
+        }
+    }
+}
+
+impl UpgradeInfoSend for BoxedInboundUpgrade {
+    type Info = String;
+    type InfoIter = std::vec::IntoIter<String>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        self.inner.protocol_names().into_iter()
+    }
+}
+
+impl InboundUpgradeSend for BoxedInboundUpgrade {
+    type Output = AnyBox;
+    type Error = AnyBox;
+    type Future = BoxFuture<'static, Result<AnyBox, AnyBox>>;
+
+    fn upgrade_inbound(self, socket: Stream, info: String) -> Self::Future {
+        self.inner.upgrade(socket, &info)
+    }
+}
+
+trait ErasedInboundUpgrade: Send + 'static {
+    fn protocol_names(&self) -> Vec<String>;
+
+    fn upgrade(
+        self: Box<Self>,
+        socket: Stream,
+        protocol: &str,
+    ) -> BoxFuture<'static, Result<AnyBox, AnyBox>>;
+}
+
+impl<U: InboundUpgradeSend> ErasedInboundUpgrade for U {
+    fn protocol_names(&self) -> Vec<String> {
+        self.protocol_info()
+            .map(|info| info.as_ref().to_owned())
+            .collect()
+    }
+
+    fn upgrade(
+        self: Box<Self>,
+        socket: Stream,
+        protocol: &str,
+    ) -> BoxFuture<'static, Result<AnyBox, AnyBox>> {
+        let info = self
+            .protocol_info()
+            .find(|info| info.as_ref() == protocol)
+            .expect("negotiated protocol to be one of the upgrade; QED");
+
+        (*self)
+            .upgrade_inbound(socket, info)
+            .map_ok(|output| Box::new(output) as AnyBox)
+            .map_err(|error| Box::new(error) as AnyBox)
+            .boxed()
+    }
+}
+
+/// A boxed outbound upgrade of a [`BoxedHandler`].
+pub struct BoxedOutboundUpgrade {
+    inner: Box<dyn ErasedOutboundUpgrade>,
+}
+
+impl BoxedOutboundUpgrade {
+    fn new<U: OutboundUpgradeSend>(upgrade: U) -> Self {
+        Self {
+            inner: Box::new(upgrade),
+        }
+    }
+}
+
+impl UpgradeInfoSend for BoxedOutboundUpgrade {
+    type Info = String;
+    type InfoIter = std::vec::IntoIter<String>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        self.inner.protocol_names().into_iter()
+    }
+}
+
+impl OutboundUpgradeSend for BoxedOutboundUpgrade {
+    type Output = AnyBox;
+    type Error = AnyBox;
+    type Future = BoxFuture<'static, Result<AnyBox, AnyBox>>;
+
+    fn upgrade_outbound(self, socket: Stream, info: String) -> Self::Future {
+        self.inner.upgrade(socket, &info)
+    }
+}
+
+trait ErasedOutboundUpgrade: Send + 'static {
+    fn protocol_names(&self) -> Vec<String>;
+
+    fn upgrade(
+        self: Box<Self>,
+        socket: Stream,
+        protocol: &str,
+    ) -> BoxFuture<'static, Result<AnyBox, AnyBox>>;
+}
+
+impl<U: OutboundUpgradeSend> ErasedOutboundUpgrade for U {
+    fn protocol_names(&self) -> Vec<String> {
+        self.protocol_info()
+            .map(|info| info.as_ref().to_owned())
+            .collect()
+    }
+
+    fn upgrade(
+        self: Box<Self>,
+        socket: Stream,
+        protocol: &str,
+    ) -> BoxFuture<'static, Result<AnyBox, AnyBox>> {
+        let info = self
+            .protocol_info()
+            .find(|info| info.as_ref() == protocol)
+            .expect("negotiated protocol to be one of the upgrade; QED");
+
+        (*self)
+            .upgrade_outbound(socket, info)
+            .map_ok(|output| Box::new(output) as AnyBox)
+            .map_err(|error| Box::new(error) as AnyBox)
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dummy, Config, Swarm, SwarmEvent};
+    use futures::task::{noop_waker_ref, ArcWake};
+    use futures::StreamExt;
+    use libp2p_core::transport::dummy::DummyTransport;
+    use libp2p_core::transport::Transport;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use void::Void;
+
+    /// Records its listen addresses and emits `event` once polled.
+    #[derive(Default)]
+    struct Probe {
+        event: Option<u32>,
+        listen_addresses: Vec<Multiaddr>,
+    }
+
+    impl NetworkBehaviour for Probe {
+        type ConnectionHandler = dummy::ConnectionHandler;
+        type ToSwarm = u32;
+
+        fn handle_established_inbound_connection(
+            &mut self,
+            _: ConnectionId,
+            _: PeerId,
+            _: &Multiaddr,
+            _: &Multiaddr,
+        ) -> Result<THandler<Self>, ConnectionDenied> {
+            Ok(dummy::ConnectionHandler)
+        }
+
+        fn handle_established_outbound_connection(
+            &mut self,
+            _: ConnectionId,
+            _: PeerId,
+            _: &Multiaddr,
+            _: Endpoint,
+            _: PortUse,
+        ) -> Result<THandler<Self>, ConnectionDenied> {
+            Ok(dummy::ConnectionHandler)
+        }
+
+        fn on_swarm_event(&mut self, event: FromSwarm) {
+            if let FromSwarm::NewListenAddr(NewListenAddr { addr, .. }) = event {
+                self.listen_addresses.push(addr.clone());
+            }
+        }
+
+        fn on_connection_handler_event(
+            &mut self,
+            _: PeerId,
+            _: ConnectionId,
+            event: THandlerOutEvent<Self>,
+        ) {
+            void::unreachable(event)
+        }
+
+        fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<u32, THandlerInEvent<Self>>> {
+            match self.event.take() {
+                Some(event) => Poll::Ready(ToSwarm::GenerateEvent(event)),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct WakeFlag(AtomicBool);
+
+    impl WakeFlag {
+        fn take(&self) -> bool {
+            self.0.swap(false, Ordering::SeqCst)
+        }
+    }
+
+    impl ArcWake for WakeFlag {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn poll(set: &mut BehaviourSet<&'static str, Void>) -> Option<HandlerIn<&'static str>> {
+        match NetworkBehaviour::poll(set, &mut Context::from_waker(noop_waker_ref())) {
+            Poll::Ready(ToSwarm::NotifyHandler { event, .. }) => Some(event),
+            Poll::Ready(_) => panic!("Unexpected event"),
+            Poll::Pending => None,
+        }
+    }
+
+    #[test]
+    fn adds_and_removes_handlers_on_established_connections() {
+        let mut set = BehaviourSet::<&'static str, Void>::new();
+        let endpoint = ConnectedPoint::Listener {
+            local_addr: Multiaddr::empty(),
+            send_back_addr: Multiaddr::empty(),
+        };
+        NetworkBehaviour::on_swarm_event(
+            &mut set,
+            FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                peer_id: PeerId::random(),
+                connection_id: ConnectionId::new_unchecked(1),
+                endpoint: &endpoint,
+                failed_addresses: &[],
+                other_established: 0,
+            }),
+        );
+
+        assert!(!set.insert("dummy", dummy::Behaviour));
+        assert!(set.contains(&"dummy"));
+        assert!(set.get::<dummy::Behaviour>(&"dummy").is_some());
+        assert!(matches!(
+            poll(&mut set),
+            Some(HandlerIn {
+                command: Command::Insert(("dummy", 0), _)
+            })
+        ));
+
+        // Replacing the behaviour removes the handler of the previous one first.
+        assert!(set.insert("dummy", dummy::Behaviour));
+        assert!(matches!(
+            poll(&mut set),
+            Some(HandlerIn {
+                command: Command::Remove(("dummy", 0))
+            })
+        ));
+        assert!(matches!(
+            poll(&mut set),
+            Some(HandlerIn {
+                command: Command::Insert(("dummy", 1), _)
+            })
+        ));
+
+        assert!(set.remove(&"dummy"));
+        assert!(!set.remove(&"dummy"));
+        assert!(matches!(
+            poll(&mut set),
+            Some(HandlerIn {
+                command: Command::Remove(("dummy", 1))
+            })
+        ));
+        assert!(poll(&mut set).is_none());
+    }
+
+    #[test]
+    fn inserting_and_removing_wakes_the_task() {
+        let mut set = BehaviourSet::<&'static str, u32>::new();
+        let flag = Arc::new(WakeFlag::default());
+        let waker = futures::task::waker(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(NetworkBehaviour::poll(&mut set, &mut cx).is_pending());
+        assert!(!flag.take());
+
+        set.insert("probe", Probe::default());
+        assert!(flag.take());
+
+        assert!(NetworkBehaviour::poll(&mut set, &mut cx).is_pending());
+        set.remove(&"probe");
+        assert!(flag.take());
+    }
+
+    #[test]
+    fn behaviour_inserted_into_idle_swarm_is_polled() {
+        let mut swarm = Swarm::new(
+            DummyTransport::new().boxed(),
+            BehaviourSet::<&'static str, u32>::new(),
+            PeerId::random(),
+            Config::without_executor(),
+        );
+        let flag = Arc::new(WakeFlag::default());
+        let waker = futures::task::waker(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(swarm.poll_next_unpin(&mut cx).is_pending());
+
+        swarm.behaviour_mut().insert(
+            "probe",
+            Probe {
+                event: Some(7),
+                ..Default::default()
+            },
+        );
+        assert!(flag.take());
+        assert!(matches!(
+            swarm.poll_next_unpin(&mut cx),
+            Poll::Ready(Some(SwarmEvent::Behaviour(("probe", 7))))
+        ));
+    }
+
+    #[test]
+    fn inserted_behaviour_learns_existing_listen_addresses() {
+        let mut set = BehaviourSet::<&'static str, u32>::new();
+        let addr: Multiaddr = "/memory/1234".parse().unwrap();
+        NetworkBehaviour::on_swarm_event(
+            &mut set,
+            FromSwarm::NewListenAddr(NewListenAddr {
+                listener_id: ListenerId::next(),
+                addr: &addr,
+            }),
+        );
+
+        set.insert("probe", Probe::default());
+
+        assert_eq!(
+            set.get::<Probe>(&"probe").unwrap().listen_addresses,
+            vec![addr]
+        );
+    }
+
+    #[test]
+    fn removed_behaviour_is_no_longer_polled() {
+        let mut set = BehaviourSet::<&'static str, u32>::new();
+        set.insert(
+            "probe",
+            Probe {
+                event: Some(7),
+                ..Default::default()
+            },
+        );
+        assert!(set.remove(&"probe"));
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        assert!(NetworkBehaviour::poll(&mut set, &mut cx).is_pending());
+        assert_eq!(set.keys().count(), 0);
+    }
+}
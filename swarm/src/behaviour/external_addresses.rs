@@ -1,19 +1,143 @@
-use crate::behaviour::{ExternalAddrConfirmed, ExternalAddrExpired, FromSwarm};
+use crate::behaviour::{
+    ExternalAddrConfirmed, ExternalAddrExpired, FromSwarm, NewExternalAddrCandidate,
+};
 use libp2p_core::Multiaddr;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use web_time::Instant;
 
 /// The maximum number of local external addresses. When reached any
 /// further externally reported addresses are ignored. The behaviour always
 /// tracks all its listen addresses.
 const MAX_LOCAL_EXTERNAL_ADDRS: usize = 20;
 
+/// The maximum number of unconfirmed candidates whose observations are counted towards the
+/// confidence of the address once it is confirmed.
+const MAX_CANDIDATES: usize = 20;
+
+/// How an external address of the local node became known, see [`ScoredAddress::source`].
+///
+/// The sources are ordered by how much they are trusted, from [`ExternalAddressSource::Observed`]
+/// to [`ExternalAddressSource::Manual`]. An address confirmed by several sources keeps the most
+/// trusted one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ExternalAddressSource {
+    /// A remote observed the address, but it has not been confirmed yet.
+    Observed,
+    /// A [`NetworkBehaviour`](crate::NetworkBehaviour) confirmed the address without stating
+    /// how, via [`ToSwarm::ExternalAddrConfirmed`](crate::ToSwarm::ExternalAddrConfirmed).
+    Confirmed,
+    /// A port mapping was created on the gateway via UPnP.
+    Upnp,
+    /// AutoNAT confirmed the address via a successful dial-back.
+    AutoNat,
+    /// The address was added via [`Swarm::add_external_address`](crate::Swarm::add_external_address).
+    Manual,
+}
+
+impl ExternalAddressSource {
+    fn priority(&self) -> u8 {
+        match self {
+            ExternalAddressSource::Observed => 0,
+            ExternalAddressSource::Confirmed => 1,
+            ExternalAddressSource::Upnp => 2,
+            ExternalAddressSource::AutoNat => 3,
+            ExternalAddressSource::Manual => 4,
+        }
+    }
+}
+
+/// An external address of the local node together with the evidence for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoredAddress {
+    address: Multiaddr,
+    source: ExternalAddressSource,
+    confidence: u32,
+    last_confirmed: Option<Instant>,
+}
+
+impl ScoredAddress {
+    fn new(address: Multiaddr, source: ExternalAddressSource) -> Self {
+        Self {
+            address,
+            source,
+            confidence: 0,
+            last_confirmed: None,
+        }
+    }
+
+    /// The address.
+    pub fn address(&self) -> &Multiaddr {
+        &self.address
+    }
+
+    /// The most trusted source the address is known from.
+    pub fn source(&self) -> ExternalAddressSource {
+        self.source
+    }
+
+    /// How often the address was observed or confirmed.
+    pub fn confidence(&self) -> u32 {
+        self.confidence
+    }
+
+    /// When the address was last confirmed, if ever.
+    pub fn last_confirmed(&self) -> Option<Instant> {
+        self.last_confirmed
+    }
+}
+
+/// Orders [`ScoredAddress`]es by preference: [`Ordering::Less`] means the first address is
+/// advertised before the second.
+pub type AddressRanking = fn(&ScoredAddress, &ScoredAddress) -> Ordering;
+
+/// The default [`AddressRanking`]: manual addresses before confirmed ones, each ordered by the
+/// time they were last confirmed, most recent first.
+pub fn rank_by_source_and_recency(a: &ScoredAddress, b: &ScoredAddress) -> Ordering {
+    b.source
+        .priority()
+        .cmp(&a.source.priority())
+        .then_with(|| b.last_confirmed.cmp(&a.last_confirmed))
+}
+
 /// Utility struct for tracking the external addresses of a [`Swarm`](crate::Swarm).
-#[derive(Debug, Clone, Default)]
+///
+/// The confirmed addresses are ranked by an [`AddressRanking`], see
+/// [`ExternalAddresses::with_ranking`]. Observations of candidates are counted towards the
+/// confidence of an address once it is confirmed.
+#[derive(Debug, Clone)]
 pub struct ExternalAddresses {
+    /// The confirmed addresses, ranked.
     addresses: Vec<Multiaddr>,
+    /// The scores of `addresses`, in the same order.
+    scored: Vec<ScoredAddress>,
+    /// Unconfirmed candidates, oldest first.
+    candidates: VecDeque<ScoredAddress>,
+    ranking: AddressRanking,
+}
+
+impl Default for ExternalAddresses {
+    fn default() -> Self {
+        Self {
+            addresses: Vec::new(),
+            scored: Vec::new(),
+            candidates: VecDeque::new(),
+            ranking: rank_by_source_and_recency,
+        }
+    }
 }
 
 impl ExternalAddresses {
-    /// Returns an [`Iterator`] over all external addresses.
+    /// Ranks the external addresses with the given [`AddressRanking`] instead of
+    /// [`rank_by_source_and_recency`].
+    pub fn with_ranking(mut self, ranking: AddressRanking) -> Self {
+        self.ranking = ranking;
+        self.rank();
+        self
+    }
+
+    /// Returns an [`Iterator`] over all external addresses, in order of their rank.
     pub fn iter(&self) -> impl ExactSizeIterator<Item = &Multiaddr> {
         self.addresses.iter()
     }
@@ -22,54 +146,34 @@ impl ExternalAddresses {
         self.addresses.as_slice()
     }
 
+    /// Returns an [`Iterator`] over the scores of all external addresses, in order of their rank.
+    pub fn scored(&self) -> impl ExactSizeIterator<Item = &ScoredAddress> {
+        self.scored.iter()
+    }
+
+    /// Returns the score of an external address or of a candidate.
+    pub fn score(&self, address: &Multiaddr) -> Option<&ScoredAddress> {
+        self.scored
+            .iter()
+            .chain(self.candidates.iter())
+            .find(|s| s.address == *address)
+    }
+
     /// Feed a [`FromSwarm`] event to this struct.
     ///
     /// Returns whether the event changed our set of external addresses.
     pub fn on_swarm_event(&mut self, event: &FromSwarm) -> bool {
         match event {
-            FromSwarm::ExternalAddrConfirmed(ExternalAddrConfirmed { addr }) => {
-                if let Some(pos) = self
-                    .addresses
-                    .iter()
-                    .position(|candidate| candidate == *addr)
-                {
-                    // Refresh the existing confirmed address.
-                    self.addresses.remove(pos);
-                    self.push_front(addr);
-
-                    tracing::debug!(address=%addr, "Refreshed external address");
-
-                    return false; // No changes to our external addresses.
-                }
-
-                self.push_front(addr);
-
-                if self.addresses.len() > MAX_LOCAL_EXTERNAL_ADDRS {
-                    let expired = self.addresses.pop().expect("list to be not empty");
-
-                    tracing::debug!(
-                        external_address=%expired,
-                        address_limit=%MAX_LOCAL_EXTERNAL_ADDRS,
-                        "Removing previously confirmed external address because we reached the address limit"
-                    );
-                }
-
-                return true;
+            FromSwarm::NewExternalAddrCandidate(NewExternalAddrCandidate { addr }) => {
+                self.observe(addr);
+            }
+            FromSwarm::ExternalAddrConfirmed(ExternalAddrConfirmed { addr, source }) => {
+                return self.confirm(addr, *source);
             }
             FromSwarm::ExternalAddrExpired(ExternalAddrExpired {
                 addr: expired_addr, ..
             }) => {
-                let pos = match self
-                    .addresses
-                    .iter()
-                    .position(|candidate| candidate == *expired_addr)
-                {
-                    None => return false,
-                    Some(p) => p,
-                };
-
-                self.addresses.remove(pos);
-                return true;
+                return self.remove(expired_addr);
             }
             _ => {}
         }
@@ -77,8 +181,92 @@ impl ExternalAddresses {
         false
     }
 
-    fn push_front(&mut self, addr: &Multiaddr) {
-        self.addresses.insert(0, addr.clone()); // We have at most `MAX_LOCAL_EXTERNAL_ADDRS` so this isn't very expensive.
+    /// Records an observation of `addr`, increasing its confidence.
+    pub(crate) fn observe(&mut self, addr: &Multiaddr) {
+        if let Some(scored) = self.scored.iter_mut().find(|s| s.address == *addr) {
+            scored.confidence += 1;
+            self.rank();
+            return;
+        }
+
+        match self.candidates.iter_mut().find(|s| s.address == *addr) {
+            Some(candidate) => candidate.confidence += 1,
+            None => {
+                let mut candidate =
+                    ScoredAddress::new(addr.clone(), ExternalAddressSource::Observed);
+                candidate.confidence = 1;
+                self.candidates.push_back(candidate);
+                if self.candidates.len() > MAX_CANDIDATES {
+                    self.candidates.pop_front();
+                }
+            }
+        }
+    }
+
+    /// Records a confirmation of `addr` from `source`.
+    ///
+    /// Returns whether `addr` was added to our set of external addresses.
+    pub(crate) fn confirm(&mut self, addr: &Multiaddr, source: ExternalAddressSource) -> bool {
+        if let Some(pos) = self.scored.iter().position(|s| s.address == *addr) {
+            // Refresh the existing confirmed address.
+            let mut scored = self.scored.remove(pos);
+            scored.confidence += 1;
+            scored.last_confirmed = Some(Instant::now());
+            if source.priority() > scored.source.priority() {
+                scored.source = source;
+            }
+            self.scored.insert(0, scored);
+            self.rank();
+
+            tracing::debug!(address=%addr, "Refreshed external address");
+
+            return false; // No changes to our external addresses.
+        }
+
+        let mut scored = match self.candidates.iter().position(|s| s.address == *addr) {
+            Some(pos) => self.candidates.remove(pos).expect("position to be valid"),
+            None => ScoredAddress::new(addr.clone(), source),
+        };
+        scored.source = source;
+        scored.confidence += 1;
+        scored.last_confirmed = Some(Instant::now());
+        // We have at most `MAX_LOCAL_EXTERNAL_ADDRS` so this isn't very expensive.
+        self.scored.insert(0, scored);
+        self.rank();
+
+        if self.scored.len() > MAX_LOCAL_EXTERNAL_ADDRS {
+            let expired = self.scored.pop().expect("list to be not empty");
+            self.addresses.pop();
+
+            tracing::debug!(
+                external_address=%expired.address,
+                address_limit=%MAX_LOCAL_EXTERNAL_ADDRS,
+                "Removing lowest ranked external address because we reached the address limit"
+            );
+
+            if expired.address == *addr {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Removes `addr`, returning whether it was one of our external addresses.
+    pub(crate) fn remove(&mut self, addr: &Multiaddr) -> bool {
+        let Some(pos) = self.scored.iter().position(|s| s.address == *addr) else {
+            return false;
+        };
+
+        self.scored.remove(pos);
+        self.addresses.remove(pos);
+        true
+    }
+
+    fn rank(&mut self) {
+        // A stable sort, such that equally ranked addresses stay in the order of their insertion.
+        self.scored.sort_by(self.ranking);
+        self.addresses = self.scored.iter().map(|s| s.address.clone()).collect();
     }
 }
 
@@ -134,6 +322,7 @@ mod tests {
                 Multiaddr::empty().with(Protocol::Memory(rand::thread_rng().gen_range(0..1000)));
             addresses.on_swarm_event(&FromSwarm::ExternalAddrConfirmed(ExternalAddrConfirmed {
                 addr: &random_address,
+                source: ExternalAddressSource::Confirmed,
             }));
         }
 
@@ -157,15 +346,78 @@ mod tests {
         );
     }
 
+    #[test]
+    fn observations_count_towards_confidence() {
+        let mut addresses = ExternalAddresses::default();
+
+        addresses.on_swarm_event(&FromSwarm::NewExternalAddrCandidate(
+            NewExternalAddrCandidate {
+                addr: &MEMORY_ADDR_1000,
+            },
+        ));
+        let candidate = addresses.score(&MEMORY_ADDR_1000).unwrap();
+        assert_eq!(candidate.source(), ExternalAddressSource::Observed);
+        assert_eq!(candidate.confidence(), 1);
+        assert!(candidate.last_confirmed().is_none());
+        assert!(addresses.as_slice().is_empty());
+
+        addresses.on_swarm_event(&new_external_addr1());
+        addresses.on_swarm_event(&new_external_addr1());
+
+        let confirmed = addresses.score(&MEMORY_ADDR_1000).unwrap();
+        assert_eq!(confirmed.source(), ExternalAddressSource::Confirmed);
+        assert_eq!(confirmed.confidence(), 3);
+        assert!(confirmed.last_confirmed().is_some());
+    }
+
+    #[test]
+    fn manual_addresses_are_ranked_first() {
+        let mut addresses = ExternalAddresses::default();
+
+        addresses.confirm(&MEMORY_ADDR_1000, ExternalAddressSource::Manual);
+        addresses.on_swarm_event(&new_external_addr2());
+
+        assert_eq!(
+            addresses.as_slice(),
+            &[(*MEMORY_ADDR_1000).clone(), (*MEMORY_ADDR_2000).clone()]
+        );
+    }
+
+    #[test]
+    fn custom_ranking_orders_addresses() {
+        fn by_confidence(a: &ScoredAddress, b: &ScoredAddress) -> Ordering {
+            b.confidence().cmp(&a.confidence())
+        }
+        let mut addresses = ExternalAddresses::default().with_ranking(by_confidence);
+
+        addresses.on_swarm_event(&new_external_addr1());
+        addresses.on_swarm_event(&new_external_addr1());
+        addresses.on_swarm_event(&new_external_addr2());
+
+        assert_eq!(
+            addresses.as_slice(),
+            &[(*MEMORY_ADDR_1000).clone(), (*MEMORY_ADDR_2000).clone()]
+        );
+        assert_eq!(
+            addresses
+                .scored()
+                .map(ScoredAddress::confidence)
+                .collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+    }
+
     fn new_external_addr1() -> FromSwarm<'static> {
         FromSwarm::ExternalAddrConfirmed(ExternalAddrConfirmed {
             addr: &MEMORY_ADDR_1000,
+            source: ExternalAddressSource::Confirmed,
         })
     }
 
     fn new_external_addr2() -> FromSwarm<'static> {
         FromSwarm::ExternalAddrConfirmed(ExternalAddrConfirmed {
             addr: &MEMORY_ADDR_2000,
+            source: ExternalAddressSource::Confirmed,
         })
     }
 
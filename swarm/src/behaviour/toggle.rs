@@ -364,6 +364,11 @@ where
                     inner.on_connection_event(ConnectionEvent::RemoteProtocolsChange(change));
                 }
             }
+            ConnectionEvent::RemoteActivity => {
+                if let Some(inner) = self.inner.as_mut() {
+                    inner.on_connection_event(ConnectionEvent::RemoteActivity);
+                }
+            }
         }
     }
 
@@ -0,0 +1,482 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! A least-recently-used cache with optional expiration and a shared memory budget.
+//!
+//! Protocols keep caches whose size depends on the traffic of the network, e.g. the ids of seen
+//! gossipsub messages or the records of a Kademlia store. Any number of [`Cache`]s can be created
+//! with the same [`MemoryBudget`], which bounds their combined memory: once the budget is
+//! exceeded, the cache that is being inserted into evicts its least recently used entries.
+//!
+//! The budget also aggregates the hits, misses and evictions of all its caches, see
+//! [`MemoryBudget::stats`].
+
+use lru::LruCache;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use web_time::Instant;
+
+/// A bound on the combined memory of all [`Cache`]s created with it.
+///
+/// Cloning the budget yields another handle to the same budget.
+#[derive(Clone, Default)]
+pub struct MemoryBudget {
+    inner: Arc<BudgetInner>,
+}
+
+#[derive(Default)]
+struct BudgetInner {
+    limit: Option<usize>,
+    used: AtomicUsize,
+    counters: Counters,
+}
+
+impl MemoryBudget {
+    /// Creates a budget of `limit` bytes.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            inner: Arc::new(BudgetInner {
+                limit: Some(limit),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Creates a budget that is never exceeded, only tracking usage and statistics.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// The limit of the budget in bytes, if any.
+    pub fn limit(&self) -> Option<usize> {
+        self.inner.limit
+    }
+
+    /// The estimated number of bytes currently used by the caches of this budget.
+    pub fn used(&self) -> usize {
+        self.inner.used.load(Ordering::Relaxed)
+    }
+
+    /// The statistics aggregated over all caches of this budget.
+    pub fn stats(&self) -> CacheStats {
+        self.inner.counters.stats()
+    }
+
+    fn is_exceeded(&self) -> bool {
+        self.inner.limit.is_some_and(|limit| self.used() > limit)
+    }
+
+    fn acquire(&self, bytes: usize) {
+        self.inner.used.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn release(&self, bytes: usize) {
+        self.inner.used.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for MemoryBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryBudget")
+            .field("limit", &self.limit())
+            .field("used", &self.used())
+            .finish()
+    }
+}
+
+/// Statistics of a [`Cache`] or of all caches of a [`MemoryBudget`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// The number of lookups that found an entry.
+    pub hits: u64,
+    /// The number of lookups that found no entry, or only an expired one.
+    pub misses: u64,
+    /// The number of entries evicted to stay within the memory budget.
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    /// The fraction of lookups that found an entry, or `0.0` if there were no lookups.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / lookups as f64
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl Counters {
+    fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct Entry<V> {
+    value: V,
+    /// The estimated size of the entry in bytes.
+    size: usize,
+    /// Identifies the entry in [`Cache::expirations`].
+    id: u64,
+    expires: Option<Instant>,
+}
+
+impl<V> Entry<V> {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+/// A least-recently-used cache whose entries are accounted against a [`MemoryBudget`].
+///
+/// Entries are ordered by their last insertion or [`Cache::get_mut`]. [`Cache::get`] does not
+/// change the order, such that the cache can be read through a shared reference.
+///
+/// If the budget is exceeded after an insertion, the least recently used entries of this cache
+/// are evicted until the budget is met again, but the inserted entry itself is always kept.
+pub struct Cache<K, V> {
+    entries: LruCache<K, Entry<V>>,
+    /// The keys of entries with an expiration, ordered by it.
+    expirations: BTreeMap<(Instant, u64), K>,
+    next_id: u64,
+    ttl: Option<Duration>,
+    /// Estimates the size of an entry in bytes.
+    weigh: fn(&K, &V) -> usize,
+    budget: MemoryBudget,
+    /// The number of bytes of the budget used by this cache.
+    used: usize,
+    counters: Counters,
+}
+
+impl<K, V> Cache<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Creates an empty cache, estimating the size of its entries with `weigh`.
+    pub fn new(budget: MemoryBudget, weigh: fn(&K, &V) -> usize) -> Self {
+        Self {
+            entries: LruCache::unbounded(),
+            expirations: BTreeMap::new(),
+            next_id: 0,
+            ttl: None,
+            weigh,
+            budget,
+            used: 0,
+            counters: Counters::default(),
+        }
+    }
+
+    /// Configures the time after which entries inserted with [`Cache::insert`] expire.
+    ///
+    /// Entries don't expire by default.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Inserts an entry, replacing and returning the previous value of the key, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let expires = self.ttl.map(|ttl| Instant::now() + ttl);
+        self.insert_inner(key, value, expires)
+    }
+
+    /// Inserts an entry that expires at the given time, regardless of the configured ttl.
+    pub fn insert_until(&mut self, key: K, value: V, expires: Instant) -> Option<V> {
+        self.insert_inner(key, value, Some(expires))
+    }
+
+    fn insert_inner(&mut self, key: K, value: V, expires: Option<Instant>) -> Option<V> {
+        self.remove_expired(Instant::now());
+        let previous = self.remove(&key);
+
+        let size = (self.weigh)(&key, &value);
+        let id = self.next_id;
+        self.next_id += 1;
+        if let Some(expires) = expires {
+            self.expirations.insert((expires, id), key.clone());
+        }
+        self.entries.push(
+            key,
+            Entry {
+                value,
+                size,
+                id,
+                expires,
+            },
+        );
+        self.used += size;
+        self.budget.acquire(size);
+
+        while self.budget.is_exceeded() && self.entries.len() > 1 {
+            let Some((key, _)) = self.entries.peek_lru() else {
+                break;
+            };
+            let key = key.clone();
+            self.remove(&key);
+            self.counters.evictions.fetch_add(1, Ordering::Relaxed);
+            self.budget
+                .inner
+                .counters
+                .evictions
+                .fetch_add(1, Ordering::Relaxed);
+        }
+
+        previous
+    }
+
+    /// Returns the value of a key, without changing the order of entries.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let entry = self
+            .entries
+            .peek(key)
+            .filter(|e| !e.is_expired(Instant::now()));
+        self.record_lookup(entry.is_some());
+        entry.map(|e| &e.value)
+    }
+
+    /// Returns the value of a key, marking the entry as most recently used.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let now = Instant::now();
+        let found = self.entries.peek(key).is_some_and(|e| !e.is_expired(now));
+        self.record_lookup(found);
+        if !found {
+            return None;
+        }
+        self.entries.get_mut(key).map(|e| &mut e.value)
+    }
+
+    /// Returns the value of a key without changing the order of entries or the statistics.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.entries
+            .peek(key)
+            .filter(|e| !e.is_expired(Instant::now()))
+            .map(|e| &e.value)
+    }
+
+    /// Returns `true` if the cache contains an unexpired entry for the key.
+    pub fn contains(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns when the entry of a key expires, if it exists and has an expiration.
+    pub fn expiration(&self, key: &K) -> Option<Instant> {
+        self.entries
+            .peek(key)
+            .and_then(|e| e.expires)
+            .filter(|expires| *expires > Instant::now())
+    }
+
+    /// Removes the entry of a key, returning its value.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let entry = self.entries.pop(key)?;
+        if let Some(expires) = entry.expires {
+            self.expirations.remove(&(expires, entry.id));
+        }
+        self.used -= entry.size;
+        self.budget.release(entry.size);
+        Some(entry.value)
+    }
+
+    /// Retains only the entries satisfying a predicate.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let removed = self
+            .entries
+            .iter_mut()
+            .filter_map(|(k, e)| (!f(k, &mut e.value)).then(|| k.clone()))
+            .collect::<Vec<_>>();
+        for key in removed {
+            self.remove(&key);
+        }
+    }
+
+    /// Iterates over all unexpired entries, most recently used first.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.entries.iter(),
+            now: Instant::now(),
+        }
+    }
+
+    /// Iterates over all unexpired entries with an expiration, together with it.
+    pub fn expirations(&self) -> impl Iterator<Item = (&K, Instant)> {
+        let now = Instant::now();
+        self.expirations
+            .range((now, u64::MAX)..)
+            .map(|((expires, _), key)| (key, *expires))
+            .filter(move |(_, expires)| *expires > now)
+    }
+
+    /// The number of entries, including expired entries that have not been removed yet.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the cache has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The estimated number of bytes used by this cache.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// The statistics of this cache.
+    pub fn stats(&self) -> CacheStats {
+        self.counters.stats()
+    }
+
+    fn remove_expired(&mut self, now: Instant) {
+        while let Some(entry) = self.expirations.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            let key = entry.remove();
+            self.remove(&key);
+        }
+    }
+
+    fn record_lookup(&self, hit: bool) {
+        let (local, shared) = if hit {
+            (&self.counters.hits, &self.budget.inner.counters.hits)
+        } else {
+            (&self.counters.misses, &self.budget.inner.counters.misses)
+        };
+        local.fetch_add(1, Ordering::Relaxed);
+        shared.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl<K, V> Drop for Cache<K, V> {
+    fn drop(&mut self) {
+        self.budget.release(self.used);
+    }
+}
+
+impl<K: Hash + Eq, V> fmt::Debug for Cache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cache")
+            .field("len", &self.entries.len())
+            .field("used", &self.used)
+            .field("budget", &self.budget)
+            .finish()
+    }
+}
+
+/// Iterator over the entries of a [`Cache`], see [`Cache::iter`].
+pub struct Iter<'a, K, V> {
+    inner: lru::Iter<'a, K, Entry<V>>,
+    now: Instant,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let now = self.now;
+        self.inner
+            .by_ref()
+            .find(|(_, e)| !e.is_expired(now))
+            .map(|(k, e)| (k, &e.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weigh(_: &u32, size: &usize) -> usize {
+        *size
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entries() {
+        let budget = MemoryBudget::new(100);
+        let mut first = Cache::new(budget.clone(), weigh);
+        let mut second = Cache::new(budget.clone(), weigh);
+
+        first.insert(1, 40);
+        first.insert(2, 40);
+        assert!(first.get_mut(&1).is_some());
+
+        first.insert(3, 30);
+        assert!(first.contains(&1));
+        assert!(!first.contains(&2));
+        assert_eq!(budget.used(), 70);
+
+        // Exceeding the budget evicts from the cache that is inserted into.
+        second.insert(1, 20);
+        second.insert(2, 20);
+        assert_eq!(first.len(), 2);
+        assert_eq!(second.len(), 1);
+        assert_eq!(budget.used(), 90);
+
+        assert_eq!(first.stats().evictions, 1);
+        assert_eq!(
+            budget.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 1,
+                evictions: 2
+            }
+        );
+
+        drop(first);
+        assert_eq!(budget.used(), 20);
+    }
+
+    #[test]
+    fn entries_expire() {
+        let mut cache =
+            Cache::new(MemoryBudget::unlimited(), weigh).with_ttl(Duration::from_secs(10));
+        let now = Instant::now();
+
+        cache.insert(1, 10);
+        cache.insert_until(2, 10, now + Duration::from_millis(50));
+        assert_eq!(cache.expirations().count(), 2);
+        assert_eq!(cache.iter().count(), 2);
+
+        std::thread::sleep(Duration::from_millis(51));
+        assert!(cache.get(&2).is_none());
+        assert_eq!(cache.iter().map(|(k, _)| *k).collect::<Vec<_>>(), vec![1]);
+
+        cache.insert(3, 10);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.used(), 20);
+    }
+}
@@ -19,27 +19,38 @@
 // DEALINGS IN THE SOFTWARE.
 
 mod error;
+mod extensions;
 
 pub(crate) mod pool;
 mod supported_protocols;
+mod tags;
 
 pub use error::ConnectionError;
 pub(crate) use error::{
     PendingConnectionError, PendingInboundConnectionError, PendingOutboundConnectionError,
 };
+pub use extensions::ConnectionExtensions;
 use libp2p_core::transport::PortUse;
 pub use supported_protocols::SupportedProtocols;
+pub use tags::{ConnectionTags, TagValue};
 
+use crate::bandwidth::ConnectionBandwidth;
+use crate::disconnect::{self, DisconnectReason};
 use crate::handler::{
     AddressChange, ConnectionEvent, ConnectionHandler, DialUpgradeError, FullyNegotiatedInbound,
     FullyNegotiatedOutbound, ListenUpgradeError, ProtocolSupport, ProtocolsChange, UpgradeInfoSend,
 };
+use crate::keep_alive::ConnectionKeepAlive;
+use crate::negotiation_timeout::ConnectionNegotiationTimeout;
+use crate::pruning::ConnectionActivity;
+use crate::rate_limit::ConnectionRateLimiter;
 use crate::stream::ActiveStreamCounter;
+use crate::stream_metrics::StreamMetrics;
 use crate::upgrade::{InboundUpgradeSend, OutboundUpgradeSend};
 use crate::{
     ConnectionHandlerEvent, Stream, StreamProtocol, StreamUpgradeError, SubstreamProtocol,
 };
-use futures::future::BoxFuture;
+use futures::future::{self, BoxFuture};
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
 use futures::{stream, FutureExt};
@@ -105,6 +116,40 @@ pub(crate) enum Event<T> {
     Handler(T),
     /// Address of the remote has changed.
     AddressChange(Multiaddr),
+    /// The connection is idle and going to be closed after the given duration.
+    ///
+    /// Only reported if a [`KeepAlivePolicy`](crate::keep_alive::KeepAlivePolicy) is configured.
+    Idle(Duration),
+}
+
+/// The options of a [`Connection`], as configured on the [`Swarm`](crate::Swarm).
+#[derive(Default)]
+pub(crate) struct ConnectionConfig {
+    /// The substream upgrade protocol override, if any.
+    pub(crate) substream_upgrade_protocol_override: Option<upgrade::Version>,
+    /// The maximum number of inbound streams concurrently negotiating on the connection.
+    pub(crate) max_negotiating_inbound_streams: usize,
+    /// How long the connection is kept alive once idle.
+    pub(crate) idle_timeout: Duration,
+    /// Decides how long the connection is kept alive once idle, if enabled.
+    pub(crate) keep_alive: Option<ConnectionKeepAlive>,
+    /// The options applied to every stream of the connection.
+    pub(crate) streams: StreamConfig,
+}
+
+/// The options applied to the streams of a [`Connection`].
+#[derive(Default)]
+pub(crate) struct StreamConfig {
+    /// Accounts the bandwidth of negotiated streams, if enabled.
+    pub(crate) bandwidth: Option<ConnectionBandwidth>,
+    /// Limits the rate of data read from negotiated streams, if enabled.
+    pub(crate) rate_limiter: Option<ConnectionRateLimiter>,
+    /// Bounds the protocol negotiation of new streams, if enabled.
+    pub(crate) negotiation_timeout: Option<ConnectionNegotiationTimeout>,
+    /// Tracks the negotiated streams by protocol, if enabled.
+    pub(crate) stream_metrics: Option<StreamMetrics>,
+    /// Whether inbound streams may be negotiated for [`disconnect::PROTOCOL_NAME`].
+    pub(crate) accept_disconnect_reasons: bool,
 }
 
 /// A multiplexed connection to a peer with an associated [`ConnectionHandler`].
@@ -120,7 +165,7 @@ where
     negotiating_in: FuturesUnordered<
         StreamUpgrade<
             THandler::InboundOpenInfo,
-            <THandler::InboundProtocol as InboundUpgradeSend>::Output,
+            Inbound<<THandler::InboundProtocol as InboundUpgradeSend>::Output>,
             <THandler::InboundProtocol as InboundUpgradeSend>::Error,
         >,
     >,
@@ -160,6 +205,14 @@ where
 
     idle_timeout: Duration,
     stream_counter: ActiveStreamCounter,
+    /// The time of the last stream activity, see [`crate::pruning`].
+    activity: ConnectionActivity,
+    /// Decides how long the connection is kept alive once idle, if enabled.
+    keep_alive: Option<ConnectionKeepAlive>,
+    /// The options applied to every stream of the connection.
+    streams: StreamConfig,
+    /// The reason the remote gave for closing the connection, see [`crate::disconnect`].
+    disconnect_reason: Option<DisconnectReason>,
 }
 
 impl<THandler> fmt::Debug for Connection<THandler>
@@ -185,10 +238,16 @@ where
     pub(crate) fn new(
         muxer: StreamMuxerBox,
         mut handler: THandler,
-        substream_upgrade_protocol_override: Option<upgrade::Version>,
-        max_negotiating_inbound_streams: usize,
-        idle_timeout: Duration,
+        config: ConnectionConfig,
     ) -> Self {
+        let ConnectionConfig {
+            substream_upgrade_protocol_override,
+            max_negotiating_inbound_streams,
+            idle_timeout,
+            keep_alive,
+            streams,
+        } = config;
+
         let initial_protocols = gather_supported_protocols(&handler);
         let mut buffer = Vec::new();
 
@@ -215,14 +274,74 @@ where
             protocol_buffer: buffer,
             idle_timeout,
             stream_counter: ActiveStreamCounter::default(),
+            activity: ConnectionActivity::new(),
+            keep_alive,
+            streams,
+            disconnect_reason: None,
         }
     }
 
+    /// Returns a handle to the time of the last stream activity on this connection.
+    pub(crate) fn activity(&self) -> ConnectionActivity {
+        self.activity.clone()
+    }
+
     /// Notifies the connection handler of an event.
     pub(crate) fn on_behaviour_event(&mut self, event: THandler::FromBehaviour) {
         self.handler.on_behaviour_event(event);
     }
 
+    /// The reason the remote gave for closing the connection, if any.
+    pub(crate) fn disconnect_reason(&self) -> Option<DisconnectReason> {
+        self.disconnect_reason.clone()
+    }
+
+    /// Notifies the remote of the reason for closing the connection, waiting until the remote
+    /// received it or [`disconnect::NOTICE_TIMEOUT`] elapsed.
+    pub(crate) async fn send_disconnect_reason(&mut self, reason: &DisconnectReason) {
+        let muxing = &mut self.muxing;
+        let notice = async {
+            let stream = future::poll_fn(|cx| {
+                if poll_muxer(muxing, cx).is_ready() {
+                    return Poll::Ready(Err(io::ErrorKind::ConnectionAborted.into()));
+                }
+                muxing.poll_outbound_unpin(cx)
+            })
+            .await?;
+            let exchange = async {
+                let (_, stream) = multistream_select::dialer_select_proto(
+                    stream,
+                    [disconnect::PROTOCOL_NAME],
+                    upgrade::Version::V1,
+                )
+                .await
+                .map_err(io::Error::other)?;
+                disconnect::send(stream, reason).await
+            };
+
+            // The muxer has to be driven for the negotiation and the notice to make progress.
+            match future::select(
+                Box::pin(exchange),
+                future::poll_fn(|cx| poll_muxer(muxing, cx)),
+            )
+            .await
+            {
+                future::Either::Left((result, _)) => result,
+                future::Either::Right(((), _)) => Err(io::ErrorKind::ConnectionAborted.into()),
+            }
+        };
+
+        match future::select(Box::pin(notice), Delay::new(disconnect::NOTICE_TIMEOUT)).await {
+            future::Either::Left((Ok(()), _)) => {}
+            future::Either::Left((Err(error), _)) => {
+                tracing::debug!("Failed to send disconnect reason: {error}");
+            }
+            future::Either::Right(((), _)) => {
+                tracing::debug!("Sending disconnect reason timed out");
+            }
+        }
+    }
+
     /// Begins an orderly shutdown of the connection, returning a stream of final events and a `Future` that resolves when connection shutdown is complete.
     pub(crate) fn close(
         self,
@@ -263,10 +382,19 @@ where
             protocol_buffer,
             idle_timeout,
             stream_counter,
+            activity,
+            keep_alive,
+            streams,
+            disconnect_reason,
             ..
         } = self.get_mut();
 
         loop {
+            if stream_counter.take_remote_activity() {
+                activity.record();
+                handler.on_connection_event(ConnectionEvent::RemoteActivity);
+            }
+
             match requested_substreams.poll_next_unpin(cx) {
                 Poll::Ready(Some(Ok(()))) => continue,
                 Poll::Ready(Some(Err(info))) => {
@@ -341,12 +469,17 @@ where
             // make any more progress, poll the negotiating inbound streams.
             match negotiating_in.poll_next_unpin(cx) {
                 Poll::Pending | Poll::Ready(None) => {}
-                Poll::Ready(Some((info, Ok(protocol)))) => {
+                Poll::Ready(Some((info, Ok(Inbound::Handler(protocol))))) => {
                     handler.on_connection_event(ConnectionEvent::FullyNegotiatedInbound(
                         FullyNegotiatedInbound { protocol, info },
                     ));
                     continue;
                 }
+                Poll::Ready(Some((_, Ok(Inbound::Disconnect(reason))))) => {
+                    tracing::debug!(%reason, "Remote is closing the connection");
+                    *disconnect_reason = Some(reason);
+                    continue;
+                }
                 Poll::Ready(Some((info, Err(StreamUpgradeError::Apply(error))))) => {
                     handler.on_connection_event(ConnectionEvent::ListenUpgradeError(
                         ListenUpgradeError { info, error },
@@ -365,6 +498,10 @@ where
                     tracing::debug!("inbound stream upgrade timed out");
                     continue;
                 }
+                Poll::Ready(Some((_, Err(StreamUpgradeError::NegotiationTimeout)))) => {
+                    tracing::debug!("inbound stream negotiation timed out");
+                    continue;
+                }
             }
 
             // Check if the connection (and handler) should be shut down.
@@ -374,10 +511,24 @@ where
                 && requested_substreams.is_empty()
                 && stream_counter.has_no_active_streams()
             {
+                let keep_alive_requested = handler.connection_keep_alive()
+                    || keep_alive
+                        .as_ref()
+                        .is_some_and(|keep_alive| keep_alive.poll_is_pinned(cx));
+                let timeout = keep_alive.as_ref().map_or(*idle_timeout, |keep_alive| {
+                    keep_alive.idle_timeout(*idle_timeout, remote_supported_protocols)
+                });
+
                 if let Some(new_timeout) =
-                    compute_new_shutdown(handler.connection_keep_alive(), shutdown, *idle_timeout)
+                    compute_new_shutdown(keep_alive_requested, shutdown, timeout)
                 {
+                    let became_idle = !matches!(shutdown, Shutdown::Later(_))
+                        && matches!(new_timeout, Shutdown::Later(_));
                     *shutdown = new_timeout;
+
+                    if became_idle && keep_alive.is_some() {
+                        return Poll::Ready(Ok(Event::Idle(timeout)));
+                    }
                 }
 
                 match shutdown {
@@ -409,6 +560,7 @@ where
                     Poll::Pending => {}
                     Poll::Ready(substream) => {
                         let (user_data, timeout, upgrade) = requested_substream.extract();
+                        activity.record();
 
                         negotiating_out.push(StreamUpgrade::new_outbound(
                             substream,
@@ -417,6 +569,7 @@ where
                             upgrade,
                             *substream_upgrade_protocol_override,
                             stream_counter.clone(),
+                            streams,
                         ));
 
                         continue; // Go back to the top, handler can potentially make progress again.
@@ -429,11 +582,13 @@ where
                     Poll::Pending => {}
                     Poll::Ready(substream) => {
                         let protocol = handler.listen_protocol();
+                        activity.record();
 
                         negotiating_in.push(StreamUpgrade::new_inbound(
                             substream,
                             protocol,
                             stream_counter.clone(),
+                            streams,
                         ));
 
                         continue; // Go back to the top, handler can potentially make progress again.
@@ -464,6 +619,17 @@ where
     }
 }
 
+/// Drives the muxer while the [`ConnectionHandler`] is no longer polled, resolving once it fails.
+fn poll_muxer(muxing: &mut StreamMuxerBox, cx: &mut Context<'_>) -> Poll<()> {
+    loop {
+        match muxing.poll_unpin(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Ok(_)) => continue,
+            Poll::Ready(Err(_)) => return Poll::Ready(()),
+        }
+    }
+}
+
 fn gather_supported_protocols<C: ConnectionHandler>(
     handler: &C,
 ) -> HashMap<AsStrHashEq<<C::InboundProtocol as UpgradeInfoSend>::Info>, bool> {
@@ -540,6 +706,7 @@ impl<UserData, TOk, TErr> StreamUpgrade<UserData, TOk, TErr> {
         upgrade: Upgrade,
         version_override: Option<upgrade::Version>,
         counter: ActiveStreamCounter,
+        config: &StreamConfig,
     ) -> Self
     where
         Upgrade: OutboundUpgradeSend<Output = TOk, Error = TErr>,
@@ -556,22 +723,50 @@ impl<UserData, TOk, TErr> StreamUpgrade<UserData, TOk, TErr> {
             }
             _ => upgrade::Version::default(),
         };
-        let protocols = upgrade.protocol_info();
+        let protocols = upgrade.protocol_info().collect::<Vec<_>>();
+        let negotiation_timeout = config
+            .negotiation_timeout
+            .as_ref()
+            .and_then(|t| t.timeout(protocols.iter().map(|p| p.as_ref())));
+        let bandwidth = config.bandwidth.clone();
+        let rate_limiter = config.rate_limiter.clone();
+        let stream_metrics = config.stream_metrics.clone();
 
         Self {
             user_data: Some(user_data),
             timeout,
             upgrade: Box::pin(async move {
-                let (info, stream) = multistream_select::dialer_select_proto(
-                    substream,
-                    protocols,
-                    effective_version,
+                let offered = stream_metrics.as_ref().map(|_| {
+                    protocols
+                        .iter()
+                        .map(|p| p.as_ref().to_owned())
+                        .collect::<Vec<_>>()
+                });
+                let (info, stream) = with_negotiation_timeout(
+                    multistream_select::dialer_select_proto(
+                        substream,
+                        protocols,
+                        effective_version,
+                    ),
+                    negotiation_timeout,
                 )
                 .await
-                .map_err(to_stream_upgrade_error)?;
+                .map_err(|e| {
+                    if let (Some(metrics), Some(offered)) = (&stream_metrics, &offered) {
+                        metrics.outbound_negotiation_failed(offered.iter().map(String::as_str));
+                    }
+                    e
+                })?;
 
+                let bandwidth = bandwidth.and_then(|b| b.stream(info.as_ref()));
+                let rate_limit = rate_limiter.and_then(|r| r.stream(info.as_ref()));
+                let lifetime =
+                    stream_metrics.and_then(|m| m.stream_opened(info.as_ref(), Endpoint::Dialer));
                 let output = upgrade
-                    .upgrade_outbound(Stream::new(stream, counter), info)
+                    .upgrade_outbound(
+                        Stream::new(stream, counter, bandwidth, rate_limit, lifetime),
+                        info,
+                    )
                     .await
                     .map_err(StreamUpgradeError::Apply)?;
 
@@ -581,39 +776,119 @@ impl<UserData, TOk, TErr> StreamUpgrade<UserData, TOk, TErr> {
     }
 }
 
-impl<UserData, TOk, TErr> StreamUpgrade<UserData, TOk, TErr> {
+/// The output of an inbound [`StreamUpgrade`].
+enum Inbound<T> {
+    /// The stream was negotiated for a protocol of the [`ConnectionHandler`].
+    Handler(T),
+    /// The remote announced why it is closing the connection, see [`crate::disconnect`].
+    Disconnect(DisconnectReason),
+}
+
+/// A protocol accepted on inbound streams.
+#[derive(Debug, Clone)]
+enum InboundProtocol<P> {
+    Handler(P),
+    Disconnect,
+}
+
+impl<P: AsRef<str>> AsRef<str> for InboundProtocol<P> {
+    fn as_ref(&self) -> &str {
+        match self {
+            InboundProtocol::Handler(protocol) => protocol.as_ref(),
+            InboundProtocol::Disconnect => disconnect::PROTOCOL_NAME,
+        }
+    }
+}
+
+impl<UserData, TOk, TErr> StreamUpgrade<UserData, Inbound<TOk>, TErr> {
     fn new_inbound<Upgrade>(
         substream: SubstreamBox,
         protocol: SubstreamProtocol<Upgrade, UserData>,
         counter: ActiveStreamCounter,
+        config: &StreamConfig,
     ) -> Self
     where
         Upgrade: InboundUpgradeSend<Output = TOk, Error = TErr>,
     {
         let timeout = *protocol.timeout();
         let (upgrade, open_info) = protocol.into_upgrade();
-        let protocols = upgrade.protocol_info();
+        let protocols = upgrade.protocol_info().collect::<Vec<_>>();
+        let negotiation_timeout = config
+            .negotiation_timeout
+            .as_ref()
+            .and_then(|t| t.timeout(protocols.iter().map(|p| p.as_ref())));
+        let bandwidth = config.bandwidth.clone();
+        let rate_limiter = config.rate_limiter.clone();
+        let stream_metrics = config.stream_metrics.clone();
+        let protocols = protocols
+            .into_iter()
+            .map(InboundProtocol::Handler)
+            .chain(
+                config
+                    .accept_disconnect_reasons
+                    .then_some(InboundProtocol::Disconnect),
+            )
+            .collect::<Vec<_>>();
 
         Self {
             user_data: Some(open_info),
             timeout: Delay::new(timeout),
             upgrade: Box::pin(async move {
-                let (info, stream) =
-                    multistream_select::listener_select_proto(substream, protocols)
-                        .await
-                        .map_err(to_stream_upgrade_error)?;
+                let (info, stream) = with_negotiation_timeout(
+                    multistream_select::listener_select_proto(substream, protocols),
+                    negotiation_timeout,
+                )
+                .await
+                .map_err(|e| {
+                    if let Some(metrics) = &stream_metrics {
+                        metrics.inbound_negotiation_failed();
+                    }
+                    e
+                })?;
+                let info = match info {
+                    InboundProtocol::Handler(info) => info,
+                    InboundProtocol::Disconnect => {
+                        let reason = disconnect::receive(stream)
+                            .await
+                            .map_err(StreamUpgradeError::Io)?;
+                        return Ok(Inbound::Disconnect(reason));
+                    }
+                };
 
+                let bandwidth = bandwidth.and_then(|b| b.stream(info.as_ref()));
+                let rate_limit = rate_limiter.and_then(|r| r.stream(info.as_ref()));
+                let lifetime =
+                    stream_metrics.and_then(|m| m.stream_opened(info.as_ref(), Endpoint::Listener));
                 let output = upgrade
-                    .upgrade_inbound(Stream::new(stream, counter), info)
+                    .upgrade_inbound(
+                        Stream::new(stream, counter, bandwidth, rate_limit, lifetime),
+                        info,
+                    )
                     .await
                     .map_err(StreamUpgradeError::Apply)?;
 
-                Ok(output)
+                Ok(Inbound::Handler(output))
             }),
         }
     }
 }
 
+/// Negotiates the protocol of a stream, failing with [`StreamUpgradeError::NegotiationTimeout`]
+/// if `timeout` elapses first.
+async fn with_negotiation_timeout<T, TErr>(
+    negotiation: impl Future<Output = Result<T, NegotiationError>>,
+    timeout: Option<Duration>,
+) -> Result<T, StreamUpgradeError<TErr>> {
+    let Some(timeout) = timeout else {
+        return negotiation.await.map_err(to_stream_upgrade_error);
+    };
+
+    match future::select(std::pin::pin!(negotiation), Delay::new(timeout)).await {
+        future::Either::Left((result, _)) => result.map_err(to_stream_upgrade_error),
+        future::Either::Right(((), _)) => Err(StreamUpgradeError::NegotiationTimeout),
+    }
+}
+
 fn to_stream_upgrade_error<T>(e: NegotiationError) -> StreamUpgradeError<T> {
     match e {
         NegotiationError::Failed => StreamUpgradeError::NegotiationFailed,
@@ -795,9 +1070,10 @@ mod tests {
                     counter: alive_substream_counter.clone(),
                 }),
                 MockConnectionHandler::new(Duration::from_secs(10)),
-                None,
-                max_negotiating_inbound_streams,
-                Duration::ZERO,
+                ConnectionConfig {
+                    max_negotiating_inbound_streams,
+                    ..Default::default()
+                },
             );
 
             let result = connection.poll_noop_waker();
@@ -819,9 +1095,10 @@ mod tests {
         let mut connection = Connection::new(
             StreamMuxerBox::new(PendingStreamMuxer),
             MockConnectionHandler::new(upgrade_timeout),
-            None,
-            2,
-            Duration::ZERO,
+            ConnectionConfig {
+                max_negotiating_inbound_streams: 2,
+                ..Default::default()
+            },
         );
 
         connection.handler.open_new_outbound();
@@ -842,9 +1119,7 @@ mod tests {
         let mut connection = Connection::new(
             StreamMuxerBox::new(PendingStreamMuxer),
             ConfigurableProtocolConnectionHandler::default(),
-            None,
-            0,
-            Duration::ZERO,
+            ConnectionConfig::default(),
         );
 
         // First, start listening on a single protocol.
@@ -881,9 +1156,7 @@ mod tests {
         let mut connection = Connection::new(
             StreamMuxerBox::new(PendingStreamMuxer),
             ConfigurableProtocolConnectionHandler::default(),
-            None,
-            0,
-            Duration::ZERO,
+            ConnectionConfig::default(),
         );
 
         // First, remote supports a single protocol.
@@ -934,9 +1207,10 @@ mod tests {
         let mut connection = Connection::new(
             StreamMuxerBox::new(PendingStreamMuxer),
             dummy::ConnectionHandler,
-            None,
-            0,
-            idle_timeout,
+            ConnectionConfig {
+                idle_timeout,
+                ..Default::default()
+            },
         );
 
         assert!(connection.poll_noop_waker().is_pending());
@@ -1203,7 +1477,8 @@ mod tests {
                 ConnectionEvent::AddressChange(_)
                 | ConnectionEvent::ListenUpgradeError(_)
                 | ConnectionEvent::LocalProtocolsChange(_)
-                | ConnectionEvent::RemoteProtocolsChange(_) => {}
+                | ConnectionEvent::RemoteProtocolsChange(_)
+                | ConnectionEvent::RemoteActivity => {}
             }
         }
 
@@ -21,7 +21,7 @@
 use crate::transport::TransportError;
 use crate::Multiaddr;
 use crate::{ConnectedPoint, PeerId};
-use std::{fmt, io};
+use std::{any::Any, fmt, io};
 
 /// Errors that can occur in the context of an established `Connection`.
 #[derive(Debug)]
@@ -32,6 +32,28 @@ pub enum ConnectionError {
 
     /// The connection keep-alive timeout expired.
     KeepAliveTimeout,
+
+    /// The [`ConnectionHandler`](crate::ConnectionHandler) or one of its stream upgrades panicked
+    /// with the given message.
+    ///
+    /// Only the affected connection is closed, without polling
+    /// [`ConnectionHandler::poll_close`](crate::ConnectionHandler::poll_close).
+    HandlerPanic(String),
+}
+
+impl ConnectionError {
+    /// Creates a [`ConnectionError::HandlerPanic`] from the payload of a caught panic.
+    pub(crate) fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&'static str>() {
+                Ok(message) => (*message).to_owned(),
+                Err(_) => "unknown panic payload".to_owned(),
+            },
+        };
+
+        ConnectionError::HandlerPanic(message)
+    }
 }
 
 impl fmt::Display for ConnectionError {
@@ -41,6 +63,12 @@ impl fmt::Display for ConnectionError {
             ConnectionError::KeepAliveTimeout => {
                 write!(f, "Connection closed due to expired keep-alive timeout.")
             }
+            ConnectionError::HandlerPanic(message) => {
+                write!(
+                    f,
+                    "Connection closed due to a panic of its handler: {message}"
+                )
+            }
         }
     }
 }
@@ -50,6 +78,7 @@ impl std::error::Error for ConnectionError {
         match self {
             ConnectionError::IO(err) => Some(err),
             ConnectionError::KeepAliveTimeout => None,
+            ConnectionError::HandlerPanic(_) => None,
         }
     }
 }
@@ -0,0 +1,122 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::ConnectionTags;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Typed data and [`ConnectionTags`] attached to an established connection.
+///
+/// Holds at most one value per type. The data lives exactly as long as the connection
+/// within the [`Swarm`](crate::Swarm), see
+/// [`Swarm::connection_extensions`](crate::Swarm::connection_extensions). Once the connection is
+/// closed, it is passed to the [`NetworkBehaviour`](crate::NetworkBehaviour) in
+/// [`FromSwarm::ConnectionClosed`](crate::FromSwarm::ConnectionClosed) and returned in
+/// [`SwarmEvent::ConnectionClosed`](crate::SwarmEvent::ConnectionClosed).
+#[derive(Default)]
+pub struct ConnectionExtensions {
+    map: HashMap<TypeId, Box<dyn Any + Send>>,
+    tags: ConnectionTags,
+}
+
+impl ConnectionExtensions {
+    /// Attaches `value`, returning the previously attached value of the same type, if any.
+    pub fn insert<T: Send + 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|old| old.downcast().ok().map(|old| *old))
+    }
+
+    /// Returns a reference to the attached value of type `T`, if any.
+    pub fn get<T: Send + 'static>(&self) -> Option<&T> {
+        self.map.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Returns a mutable reference to the attached value of type `T`, if any.
+    pub fn get_mut<T: Send + 'static>(&mut self) -> Option<&mut T> {
+        self.map.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    /// Detaches and returns the attached value of type `T`, if any.
+    pub fn remove<T: Send + 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok().map(|value| *value))
+    }
+
+    /// Returns whether a value of type `T` is attached.
+    pub fn contains<T: Send + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Returns whether no data is attached.
+    ///
+    /// Does not consider the [`ConnectionTags`].
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the tags attached to the connection.
+    pub fn tags(&self) -> &ConnectionTags {
+        &self.tags
+    }
+
+    /// Returns the tags attached to the connection for modification.
+    ///
+    /// [`NetworkBehaviour`](crate::NetworkBehaviour)s tag connections via
+    /// [`ToSwarm::TagConnection`](crate::ToSwarm::TagConnection) instead.
+    pub fn tags_mut(&mut self) -> &mut ConnectionTags {
+        &mut self.tags
+    }
+}
+
+impl fmt::Debug for ConnectionExtensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionExtensions")
+            .field("len", &self.map.len())
+            .field("tags", &self.tags)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Label(&'static str);
+
+    #[test]
+    fn stores_one_value_per_type() {
+        let mut extensions = ConnectionExtensions::default();
+        assert!(extensions.is_empty());
+
+        assert_eq!(extensions.insert(Label("a")), None);
+        assert_eq!(extensions.insert(42u32), None);
+        assert_eq!(extensions.insert(Label("b")), Some(Label("a")));
+
+        assert_eq!(extensions.get::<Label>(), Some(&Label("b")));
+        *extensions.get_mut::<u32>().unwrap() += 1;
+        assert_eq!(extensions.remove::<u32>(), Some(43));
+        assert!(!extensions.contains::<u32>());
+        assert!(extensions.contains::<Label>());
+    }
+}
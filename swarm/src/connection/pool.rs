@@ -18,7 +18,17 @@
 // LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
-use crate::connection::{Connection, ConnectionId, PendingPoint};
+use crate::bandwidth::{BandwidthAccounting, ConnectionBandwidth};
+use crate::connection::{
+    Connection, ConnectionConfig, ConnectionExtensions, ConnectionId, PendingPoint, StreamConfig,
+};
+use crate::dial_opts::DialOpts;
+use crate::disconnect::DisconnectReason;
+use crate::keep_alive::KeepAlivePolicy;
+use crate::negotiation_timeout::NegotiationTimeouts;
+use crate::pruning::{ConnectionActivity, Pruner, PruningPolicy};
+use crate::rate_limit::{ConnectionRateLimiter, StreamRateLimiter};
+use crate::stream_metrics::StreamMetrics;
 use crate::{
     connection::{
         Connected, ConnectionError, IncomingInfo, PendingConnectionError,
@@ -37,9 +47,7 @@ use futures::{
     ready,
     stream::FuturesUnordered,
 };
-use libp2p_core::connection::Endpoint;
 use libp2p_core::muxing::{StreamMuxerBox, StreamMuxerExt};
-use libp2p_core::transport::PortUse;
 use std::task::Waker;
 use std::{
     collections::HashMap,
@@ -141,6 +149,27 @@ where
 
     /// How long a connection should be kept alive once it starts idling.
     idle_connection_timeout: Duration,
+
+    /// Accounts the bandwidth of streams on established connections, if enabled.
+    bandwidth_accounting: Option<BandwidthAccounting>,
+
+    /// Limits the rate of data read from streams on established connections, if enabled.
+    stream_rate_limiter: Option<StreamRateLimiter>,
+
+    /// Decides how long idle connections are kept alive, if enabled.
+    keep_alive_policy: Option<KeepAlivePolicy>,
+
+    /// Bounds the protocol negotiation of new streams, if enabled.
+    negotiation_timeouts: Option<NegotiationTimeouts>,
+
+    /// Tracks the streams on established connections by protocol, if enabled.
+    stream_metrics: Option<StreamMetrics>,
+
+    /// Whether established connections accept the disconnect reasons of remotes.
+    accept_disconnect_reasons: bool,
+
+    /// Prunes idle and least-recently-used connections, if enabled.
+    pruner: Pruner,
 }
 
 #[derive(Debug)]
@@ -148,9 +177,23 @@ pub(crate) struct EstablishedConnection<TInEvent> {
     endpoint: ConnectedPoint,
     /// Channel endpoint to send commands to the task.
     sender: mpsc::Sender<task::Command<TInEvent>>,
+    /// The time of the last stream activity on the connection.
+    activity: ConnectionActivity,
+    /// The data and tags attached to the connection.
+    extensions: ConnectionExtensions,
 }
 
 impl<TInEvent> EstablishedConnection<TInEvent> {
+    /// Returns the data attached to the connection.
+    pub(crate) fn extensions(&self) -> &ConnectionExtensions {
+        &self.extensions
+    }
+
+    /// Returns the data attached to the connection for modification.
+    pub(crate) fn extensions_mut(&mut self) -> &mut ConnectionExtensions {
+        &mut self.extensions
+    }
+
     /// (Asynchronously) sends an event to the connection handler.
     ///
     /// If the handler is not ready to receive the event, either because
@@ -186,9 +229,16 @@ impl<TInEvent> EstablishedConnection<TInEvent> {
     ///
     /// Has no effect if the connection is already closing.
     pub(crate) fn start_close(&mut self) {
+        self.start_close_with_reason(None)
+    }
+
+    /// Initiates a graceful close of the connection, notifying the remote of the `reason` first.
+    ///
+    /// Has no effect if the connection is already closing.
+    pub(crate) fn start_close_with_reason(&mut self, reason: Option<DisconnectReason>) {
         // Clone the sender so that we are guaranteed to have
         // capacity for the close command (every sender gets a slot).
-        match self.sender.clone().try_send(task::Command::Close) {
+        match self.sender.clone().try_send(task::Command::Close(reason)) {
             Ok(()) => {}
             Err(e) => assert!(e.is_disconnected(), "No capacity for close command."),
         };
@@ -261,6 +311,10 @@ pub(crate) enum PoolEvent<ToBehaviour> {
         /// The error that occurred, if any. If `None`, the connection
         /// was closed by the local peer.
         error: Option<ConnectionError>,
+        /// The reason the remote gave for closing the connection, if any.
+        reason: Option<DisconnectReason>,
+        /// The data and tags attached to the connection.
+        extensions: ConnectionExtensions,
         /// The remaining established connections to the same peer.
         remaining_established_connection_ids: Vec<ConnectionId>,
     },
@@ -304,6 +358,14 @@ pub(crate) enum PoolEvent<ToBehaviour> {
         /// The old endpoint.
         old_endpoint: ConnectedPoint,
     },
+
+    /// An idle connection is going to be closed, see [`KeepAlivePolicy`].
+    ConnectionIdle {
+        id: ConnectionId,
+        peer_id: PeerId,
+        /// How long until the connection is closed, unless it becomes active or is pinned.
+        closes_in: Duration,
+    },
 }
 
 impl<THandler> Pool<THandler>
@@ -328,6 +390,13 @@ where
             max_negotiating_inbound_streams: config.max_negotiating_inbound_streams,
             per_connection_event_buffer_size: config.per_connection_event_buffer_size,
             idle_connection_timeout: config.idle_connection_timeout,
+            bandwidth_accounting: config.bandwidth_accounting,
+            stream_rate_limiter: config.stream_rate_limiter,
+            keep_alive_policy: config.keep_alive_policy,
+            negotiation_timeouts: config.negotiation_timeouts,
+            stream_metrics: config.stream_metrics,
+            accept_disconnect_reasons: config.accept_disconnect_reasons,
+            pruner: Pruner::new(config.pruning_policy),
             executor,
             pending_connection_events_tx,
             pending_connection_events_rx,
@@ -342,6 +411,55 @@ where
         &self.counters
     }
 
+    /// Gets the bandwidth accounting of established connections, if enabled.
+    pub(crate) fn bandwidth_accounting(&self) -> Option<&BandwidthAccounting> {
+        self.bandwidth_accounting.as_ref()
+    }
+
+    /// Gets the stream metrics of established connections, if enabled.
+    pub(crate) fn stream_metrics(&self) -> Option<&StreamMetrics> {
+        self.stream_metrics.as_ref()
+    }
+
+    /// Gets the keep-alive policy of established connections, if enabled.
+    pub(crate) fn keep_alive_policy(&self) -> Option<&KeepAlivePolicy> {
+        self.keep_alive_policy.as_ref()
+    }
+
+    /// Protects the connections to `peer` from pruning, see [`Pruner::protect`].
+    pub(crate) fn protect_peer(&mut self, peer: PeerId, tag: &str) {
+        self.pruner.protect(peer, tag)
+    }
+
+    /// Removes a protection tag from `peer`, see [`Pruner::unprotect`].
+    pub(crate) fn unprotect_peer(&mut self, peer: PeerId, tag: &str) -> bool {
+        self.pruner.unprotect(peer, tag)
+    }
+
+    /// Returns whether the connections to `peer` are protected from pruning.
+    pub(crate) fn is_peer_protected(&self, peer: &PeerId) -> bool {
+        self.pruner.is_protected(peer)
+    }
+
+    /// Starts closing the connections selected by the configured [`PruningPolicy`].
+    fn prune_connections(&mut self) {
+        let connections = self.established.iter().flat_map(|(peer, conns)| {
+            conns
+                .iter()
+                .map(|(id, conn)| (*peer, *id, conn.activity.last(), conn.extensions.tags()))
+        });
+        for (peer, id) in self.pruner.select(Instant::now(), connections) {
+            if let Some(conn) = self
+                .established
+                .get_mut(&peer)
+                .and_then(|conns| conns.get_mut(&id))
+            {
+                tracing::debug!(%peer, connection=%id, "Pruning connection");
+                conn.start_close();
+            }
+        }
+    }
+
     /// Gets an established connection from the pool by ID.
     pub(crate) fn get_established(
         &mut self,
@@ -352,6 +470,14 @@ where
             .find_map(|connections| connections.get_mut(&id))
     }
 
+    /// Returns the data attached to an established connection.
+    pub(crate) fn connection_extensions(&self, id: ConnectionId) -> Option<&ConnectionExtensions> {
+        self.established
+            .values()
+            .find_map(|connections| connections.get(&id))
+            .map(EstablishedConnection::extensions)
+    }
+
     /// Returns true if we are connected to the given peer.
     ///
     /// This will return true only after a `NodeReached` event has been produced by `poll()`.
@@ -371,9 +497,19 @@ where
     /// closed asap and no more events from these connections are emitted
     /// by the pool effective immediately.
     pub(crate) fn disconnect(&mut self, peer: PeerId) {
+        self.disconnect_with_reason(peer, None)
+    }
+
+    /// Like [`Pool::disconnect`], but notifies the remote of the `reason` before closing the
+    /// established connections.
+    pub(crate) fn disconnect_with_reason(
+        &mut self,
+        peer: PeerId,
+        reason: Option<DisconnectReason>,
+    ) {
         if let Some(conns) = self.established.get_mut(&peer) {
             for (_, conn) in conns.iter_mut() {
-                conn.start_close();
+                conn.start_close_with_reason(reason.clone());
             }
         }
 
@@ -386,6 +522,22 @@ where
         }
     }
 
+    /// Aborts the pending outgoing connection with the given ID.
+    ///
+    /// Returns `false` if there is no such connection or it is already being aborted.
+    pub(crate) fn abort_dial(&mut self, id: ConnectionId) -> bool {
+        match self.pending.get_mut(&id) {
+            Some(connection)
+                if matches!(connection.endpoint, PendingPoint::Dialer { .. })
+                    && connection.abort_notifier.is_some() =>
+            {
+                connection.abort();
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Returns an iterator over all established connections of `peer`.
     pub(crate) fn iter_established_connections_of_peer(
         &mut self,
@@ -404,6 +556,17 @@ where
         })
     }
 
+    /// Returns the number of outgoing connection attempts to the given peer.
+    pub(crate) fn num_dialing(&self, peer: PeerId) -> usize {
+        self.pending
+            .values()
+            .filter(|info| {
+                matches!(info.endpoint, PendingPoint::Dialer { .. })
+                    && info.is_for_same_remote_as(peer)
+            })
+            .count()
+    }
+
     /// Returns an iterator over all connected peers, i.e. those that have
     /// at least one established connection in the pool.
     pub(crate) fn iter_connected(&self) -> impl Iterator<Item = &PeerId> {
@@ -412,26 +575,28 @@ where
 
     /// Adds a pending outgoing connection to the pool in the form of a `Future`
     /// that establishes and negotiates the connection.
+    ///
+    /// The dials are grouped into stages, which are dialed one after the other.
     pub(crate) fn add_outgoing(
         &mut self,
         dials: Vec<
-            BoxFuture<
-                'static,
-                (
-                    Multiaddr,
-                    Result<(PeerId, StreamMuxerBox), TransportError<std::io::Error>>,
-                ),
+            Vec<
+                BoxFuture<
+                    'static,
+                    (
+                        Multiaddr,
+                        Result<(PeerId, StreamMuxerBox), TransportError<std::io::Error>>,
+                    ),
+                >,
             >,
         >,
-        peer: Option<PeerId>,
-        role_override: Endpoint,
-        port_use: PortUse,
-        dial_concurrency_factor_override: Option<NonZeroU8>,
-        connection_id: ConnectionId,
+        dial_opts: &DialOpts,
     ) {
-        let concurrency_factor =
-            dial_concurrency_factor_override.unwrap_or(self.dial_concurrency_factor);
-        let span = tracing::debug_span!(parent: tracing::Span::none(), "new_outgoing_connection", %concurrency_factor, num_dials=%dials.len(), id = %connection_id);
+        let connection_id = dial_opts.connection_id();
+        let concurrency_factor = dial_opts
+            .dial_concurrency_override()
+            .unwrap_or(self.dial_concurrency_factor);
+        let span = tracing::debug_span!(parent: tracing::Span::none(), "new_outgoing_connection", %concurrency_factor, num_dials=%dials.iter().map(Vec::len).sum::<usize>(), num_stages=%dials.len(), id = %connection_id);
         span.follows_from(tracing::Span::current());
 
         let (abort_notifier, abort_receiver) = oneshot::channel();
@@ -441,21 +606,22 @@ where
                 connection_id,
                 ConcurrentDial::new(dials, concurrency_factor),
                 abort_receiver,
+                dial_opts.abort_handle(),
                 self.pending_connection_events_tx.clone(),
             )
             .instrument(span),
         );
 
         let endpoint = PendingPoint::Dialer {
-            role_override,
-            port_use,
+            role_override: dial_opts.role_override(),
+            port_use: dial_opts.port_use(),
         };
 
         self.counters.inc_pending(&endpoint);
         self.pending.insert(
             connection_id,
             PendingConnection {
-                peer_id: peer,
+                peer_id: dial_opts.get_peer_id(),
                 endpoint,
                 abort_notifier: Some(abort_notifier),
                 accepted_at: Instant::now(),
@@ -510,7 +676,36 @@ where
         connection: NewConnection,
         handler: THandler,
     ) {
-        let connection = connection.extract();
+        let connection = Connection::new(
+            connection.extract(),
+            handler,
+            ConnectionConfig {
+                substream_upgrade_protocol_override: self.substream_upgrade_protocol_override,
+                max_negotiating_inbound_streams: self.max_negotiating_inbound_streams,
+                idle_timeout: self.idle_connection_timeout,
+                keep_alive: self
+                    .keep_alive_policy
+                    .as_ref()
+                    .map(|policy| policy.connection(id, obtained_peer_id)),
+                streams: StreamConfig {
+                    bandwidth: self
+                        .bandwidth_accounting
+                        .clone()
+                        .map(|accounting| ConnectionBandwidth::new(accounting, obtained_peer_id)),
+                    rate_limiter: self
+                        .stream_rate_limiter
+                        .clone()
+                        .map(|limiter| ConnectionRateLimiter::new(limiter, obtained_peer_id)),
+                    negotiation_timeout: self
+                        .negotiation_timeouts
+                        .as_ref()
+                        .map(|timeouts| timeouts.connection(id)),
+                    stream_metrics: self.stream_metrics.clone(),
+                    accept_disconnect_reasons: self.accept_disconnect_reasons,
+                },
+            },
+        );
+
         let conns = self.established.entry(obtained_peer_id).or_default();
         self.counters.inc_established(endpoint);
 
@@ -522,6 +717,8 @@ where
             EstablishedConnection {
                 endpoint: endpoint.clone(),
                 sender: command_sender,
+                activity: connection.activity(),
+                extensions: ConnectionExtensions::default(),
             },
         );
         self.established_connection_events.push(event_receiver);
@@ -529,14 +726,6 @@ where
             waker.wake();
         }
 
-        let connection = Connection::new(
-            connection,
-            handler,
-            self.substream_upgrade_protocol_override,
-            self.max_negotiating_inbound_streams,
-            self.idle_connection_timeout,
-        );
-
         let span = tracing::debug_span!(parent: tracing::Span::none(), "new_established_connection", remote_addr = %endpoint.get_remote_address(), %id, peer = %obtained_peer_id);
         span.follows_from(tracing::Span::current());
 
@@ -559,6 +748,10 @@ where
         THandler: ConnectionHandler + 'static,
         <THandler as ConnectionHandler>::OutboundOpenInfo: Send,
     {
+        if self.pruner.poll_check(cx).is_ready() {
+            self.prune_connections();
+        }
+
         // Poll for events of established connections.
         //
         // Note that established connections are polled before pending connections, thus
@@ -595,13 +788,32 @@ where
                     old_endpoint,
                 });
             }
-            Poll::Ready(Some(task::EstablishedConnectionEvent::Closed { id, peer_id, error })) => {
+            Poll::Ready(Some(task::EstablishedConnectionEvent::Idle {
+                id,
+                peer_id,
+                closes_in,
+            })) => {
+                return Poll::Ready(PoolEvent::ConnectionIdle {
+                    id,
+                    peer_id,
+                    closes_in,
+                });
+            }
+            Poll::Ready(Some(task::EstablishedConnectionEvent::Closed {
+                id,
+                peer_id,
+                error,
+                reason,
+            })) => {
                 let connections = self
                     .established
                     .get_mut(&peer_id)
                     .expect("`Closed` event for established connection");
-                let EstablishedConnection { endpoint, .. } =
-                    connections.remove(&id).expect("Connection to be present");
+                let EstablishedConnection {
+                    endpoint,
+                    extensions,
+                    ..
+                } = connections.remove(&id).expect("Connection to be present");
                 self.counters.dec_established(&endpoint);
                 let remaining_established_connection_ids: Vec<ConnectionId> =
                     connections.keys().cloned().collect();
@@ -612,6 +824,8 @@ where
                     id,
                     connected: Connected { endpoint, peer_id },
                     error,
+                    reason,
+                    extensions,
                     remaining_established_connection_ids,
                 });
             }
@@ -974,6 +1188,20 @@ pub(crate) struct PoolConfig {
     pub(crate) dial_concurrency_factor: NonZeroU8,
     /// How long a connection should be kept alive once it is idling.
     pub(crate) idle_connection_timeout: Duration,
+    /// Accounts the bandwidth of streams on established connections, if enabled.
+    pub(crate) bandwidth_accounting: Option<BandwidthAccounting>,
+    /// Limits the rate of data read from streams on established connections, if enabled.
+    pub(crate) stream_rate_limiter: Option<StreamRateLimiter>,
+    /// Decides how long idle connections are kept alive, if enabled.
+    pub(crate) keep_alive_policy: Option<KeepAlivePolicy>,
+    /// Bounds the protocol negotiation of new streams, if enabled.
+    pub(crate) negotiation_timeouts: Option<NegotiationTimeouts>,
+    /// Tracks the streams on established connections by protocol, if enabled.
+    pub(crate) stream_metrics: Option<StreamMetrics>,
+    /// Whether established connections accept the disconnect reasons of remotes.
+    pub(crate) accept_disconnect_reasons: bool,
+    /// The policy for pruning established connections, if enabled.
+    pub(crate) pruning_policy: Option<PruningPolicy>,
     /// The configured override for substream protocol upgrades, if any.
     substream_upgrade_protocol_override: Option<libp2p_core::upgrade::Version>,
 
@@ -991,6 +1219,13 @@ impl PoolConfig {
             per_connection_event_buffer_size: 7,
             dial_concurrency_factor: NonZeroU8::new(8).expect("8 > 0"),
             idle_connection_timeout: Duration::ZERO,
+            bandwidth_accounting: None,
+            stream_rate_limiter: None,
+            keep_alive_policy: None,
+            negotiation_timeouts: None,
+            stream_metrics: None,
+            accept_disconnect_reasons: false,
+            pruning_policy: None,
             substream_upgrade_protocol_override: None,
             max_negotiating_inbound_streams: 128,
         }
@@ -42,29 +42,45 @@ type Dial = BoxFuture<
 
 pub(crate) struct ConcurrentDial {
     dials: FuturesUnordered<Dial>,
-    pending_dials: Box<dyn Iterator<Item = Dial> + Send>,
+    pending_dials: std::vec::IntoIter<Dial>,
+    /// Stages whose dials are only started once all dials of the current stage failed.
+    pending_stages: std::vec::IntoIter<Vec<Dial>>,
+    concurrency_factor: NonZeroU8,
     errors: Vec<(Multiaddr, TransportError<std::io::Error>)>,
 }
 
 impl Unpin for ConcurrentDial {}
 
 impl ConcurrentDial {
-    pub(crate) fn new(pending_dials: Vec<Dial>, concurrency_factor: NonZeroU8) -> Self {
-        let mut pending_dials = pending_dials.into_iter();
+    /// Dials the given stages one after the other, with up to `concurrency_factor` dials of a
+    /// stage at the same time.
+    pub(crate) fn new(stages: Vec<Vec<Dial>>, concurrency_factor: NonZeroU8) -> Self {
+        let mut dial = Self {
+            dials: FuturesUnordered::new(),
+            pending_dials: Vec::new().into_iter(),
+            pending_stages: stages.into_iter(),
+            concurrency_factor,
+            errors: Default::default(),
+        };
+        dial.start_next_stage();
+
+        dial
+    }
+
+    /// Starts the dials of the next non-empty stage, returning whether there was one.
+    fn start_next_stage(&mut self) -> bool {
+        let Some(stage) = self.pending_stages.find(|stage| !stage.is_empty()) else {
+            return false;
+        };
 
-        let dials = FuturesUnordered::new();
-        for dial in pending_dials.by_ref() {
-            dials.push(dial);
-            if dials.len() == concurrency_factor.get() as usize {
+        self.pending_dials = stage.into_iter();
+        for dial in self.pending_dials.by_ref() {
+            self.dials.push(dial);
+            if self.dials.len() == self.concurrency_factor.get() as usize {
                 break;
             }
         }
-
-        Self {
-            dials,
-            errors: Default::default(),
-            pending_dials: Box::new(pending_dials),
-        }
+        true
     }
 }
 
@@ -95,6 +111,10 @@ impl Future for ConcurrentDial {
                     }
                 }
                 None => {
+                    if self.start_next_stage() {
+                        tracing::debug!("All dials of the stage failed, falling back to the next");
+                        continue;
+                    }
                     return Poll::Ready(Err(std::mem::take(&mut self.errors)));
                 }
             }
@@ -27,16 +27,21 @@ use crate::{
         self, ConnectionError, ConnectionId, PendingInboundConnectionError,
         PendingOutboundConnectionError,
     },
+    dial_opts::DialAbortHandle,
+    disconnect::DisconnectReason,
     transport::TransportError,
     ConnectionHandler, Multiaddr, PeerId,
 };
 use futures::{
     channel::{mpsc, oneshot},
     future::{poll_fn, Either, Future},
-    SinkExt, StreamExt,
+    stream, SinkExt, Stream, StreamExt,
 };
 use libp2p_core::muxing::StreamMuxerBox;
+use std::panic::{self, AssertUnwindSafe};
 use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
 use void::Void;
 
 /// Commands that can be sent to a task driving an established connection.
@@ -45,8 +50,8 @@ pub(crate) enum Command<T> {
     /// Notify the connection handler of an event.
     NotifyHandler(T),
     /// Gracefully close the connection (active close) before
-    /// terminating the task.
-    Close,
+    /// terminating the task, notifying the remote of the reason if any.
+    Close(Option<DisconnectReason>),
 }
 
 pub(crate) enum PendingConnectionEvent {
@@ -79,6 +84,12 @@ pub(crate) enum EstablishedConnectionEvent<ToBehaviour> {
         peer_id: PeerId,
         event: ToBehaviour,
     },
+    /// The connection is idle and going to be closed after `closes_in`.
+    Idle {
+        id: ConnectionId,
+        peer_id: PeerId,
+        closes_in: Duration,
+    },
     /// A connection closed, possibly due to an error.
     ///
     /// If `error` is `None`, the connection has completed
@@ -87,6 +98,8 @@ pub(crate) enum EstablishedConnectionEvent<ToBehaviour> {
         id: ConnectionId,
         peer_id: PeerId,
         error: Option<ConnectionError>,
+        /// The reason the remote gave for closing the connection, if any.
+        reason: Option<DisconnectReason>,
     },
 }
 
@@ -94,10 +107,13 @@ pub(crate) async fn new_for_pending_outgoing_connection(
     connection_id: ConnectionId,
     dial: ConcurrentDial,
     abort_receiver: oneshot::Receiver<Void>,
+    abort_handle: DialAbortHandle,
     mut events: mpsc::Sender<PendingConnectionEvent>,
 ) {
-    match futures::future::select(abort_receiver, Box::pin(dial)).await {
-        Either::Left((Err(oneshot::Canceled), _)) => {
+    let abort = futures::future::select(abort_receiver, abort_handle.aborted());
+    match futures::future::select(abort, Box::pin(dial)).await {
+        Either::Left((Either::Left((Ok(v), _)), _)) => void::unreachable(v),
+        Either::Left(_) => {
             let _ = events
                 .send(PendingConnectionEvent::PendingFailed {
                     id: connection_id,
@@ -105,7 +121,6 @@ pub(crate) async fn new_for_pending_outgoing_connection(
                 })
                 .await;
         }
-        Either::Left((Ok(v), _)) => void::unreachable(v),
         Either::Right((Ok((address, output, errors)), _)) => {
             let _ = events
                 .send(PendingConnectionEvent::ConnectionEstablished {
@@ -176,20 +191,31 @@ pub(crate) async fn new_for_established_connection<THandler>(
     THandler: ConnectionHandler,
 {
     loop {
-        match futures::future::select(
+        let event = match futures::future::select(
             command_receiver.next(),
-            poll_fn(|cx| Pin::new(&mut connection).poll(cx)),
+            poll_fn(|cx| poll_connection(&mut connection, cx)),
         )
         .await
         {
             Either::Left((Some(command), _)) => match command {
-                Command::NotifyHandler(event) => connection.on_behaviour_event(event),
-                Command::Close => {
+                Command::NotifyHandler(event) => {
+                    match panic::catch_unwind(AssertUnwindSafe(|| {
+                        connection.on_behaviour_event(event)
+                    })) {
+                        Ok(()) => continue,
+                        Err(payload) => Err(ConnectionError::from_panic(payload)),
+                    }
+                }
+                Command::Close(reason) => {
                     command_receiver.close();
+                    if let Some(reason) = reason {
+                        connection.send_disconnect_reason(&reason).await;
+                    }
+                    let received_reason = connection.disconnect_reason();
                     let (remaining_events, closing_muxer) = connection.close();
 
                     let _ = events
-                        .send_all(&mut remaining_events.map(|event| {
+                        .send_all(&mut until_panic(remaining_events).map(|event| {
                             Ok(EstablishedConnectionEvent::Notify {
                                 id: connection_id,
                                 event,
@@ -205,6 +231,7 @@ pub(crate) async fn new_for_established_connection<THandler>(
                             id: connection_id,
                             peer_id,
                             error,
+                            reason: received_reason,
                         })
                         .await;
                     return;
@@ -214,52 +241,103 @@ pub(crate) async fn new_for_established_connection<THandler>(
             // The manager has disappeared; abort.
             Either::Left((None, _)) => return,
 
-            Either::Right((event, _)) => {
-                match event {
-                    Ok(connection::Event::Handler(event)) => {
-                        let _ = events
-                            .send(EstablishedConnectionEvent::Notify {
-                                id: connection_id,
-                                peer_id,
-                                event,
-                            })
-                            .await;
-                    }
-                    Ok(connection::Event::AddressChange(new_address)) => {
-                        let _ = events
-                            .send(EstablishedConnectionEvent::AddressChange {
-                                id: connection_id,
-                                peer_id,
-                                new_address,
-                            })
-                            .await;
-                    }
-                    Err(error) => {
-                        command_receiver.close();
-                        let (remaining_events, _closing_muxer) = connection.close();
+            Either::Right((event, _)) => event,
+        };
+
+        match event {
+            Ok(connection::Event::Handler(event)) => {
+                let _ = events
+                    .send(EstablishedConnectionEvent::Notify {
+                        id: connection_id,
+                        peer_id,
+                        event,
+                    })
+                    .await;
+            }
+            Ok(connection::Event::AddressChange(new_address)) => {
+                let _ = events
+                    .send(EstablishedConnectionEvent::AddressChange {
+                        id: connection_id,
+                        peer_id,
+                        new_address,
+                    })
+                    .await;
+            }
+            Ok(connection::Event::Idle(closes_in)) => {
+                let _ = events
+                    .send(EstablishedConnectionEvent::Idle {
+                        id: connection_id,
+                        peer_id,
+                        closes_in,
+                    })
+                    .await;
+            }
+            Err(error) => {
+                command_receiver.close();
+                let reason = connection.disconnect_reason();
 
-                        let _ = events
-                            .send_all(&mut remaining_events.map(|event| {
-                                Ok(EstablishedConnectionEvent::Notify {
-                                    id: connection_id,
-                                    event,
-                                    peer_id,
-                                })
-                            }))
-                            .await;
+                if let ConnectionError::HandlerPanic(message) = &error {
+                    // The handler may be left in an inconsistent state, don't poll it again.
+                    tracing::error!(peer=%peer_id, connection=%connection_id, "Connection handler panicked: {message}");
+                } else {
+                    let (remaining_events, _closing_muxer) = connection.close();
 
-                        // Terminate the task with the error, dropping the connection.
-                        let _ = events
-                            .send(EstablishedConnectionEvent::Closed {
+                    let _ = events
+                        .send_all(&mut until_panic(remaining_events).map(|event| {
+                            Ok(EstablishedConnectionEvent::Notify {
                                 id: connection_id,
+                                event,
                                 peer_id,
-                                error: Some(error),
                             })
-                            .await;
-                        return;
-                    }
+                        }))
+                        .await;
                 }
+
+                // Terminate the task with the error, dropping the connection.
+                let _ = events
+                    .send(EstablishedConnectionEvent::Closed {
+                        id: connection_id,
+                        peer_id,
+                        error: Some(error),
+                        reason,
+                    })
+                    .await;
+                return;
             }
         }
     }
 }
+
+/// Polls the connection, turning a panic of its [`ConnectionHandler`] or of one of its stream
+/// upgrades into a [`ConnectionError::HandlerPanic`].
+fn poll_connection<THandler>(
+    connection: &mut crate::connection::Connection<THandler>,
+    cx: &mut Context<'_>,
+) -> Poll<Result<connection::Event<THandler::ToBehaviour>, ConnectionError>>
+where
+    THandler: ConnectionHandler,
+{
+    match panic::catch_unwind(AssertUnwindSafe(|| Pin::new(&mut *connection).poll(cx))) {
+        Ok(poll) => poll,
+        Err(payload) => Poll::Ready(Err(ConnectionError::from_panic(payload))),
+    }
+}
+
+/// Ends the stream of events returned by [`ConnectionHandler::poll_close`] if the handler panics.
+fn until_panic<S: Stream>(events: S) -> impl Stream<Item = S::Item> {
+    let mut events = Box::pin(events);
+    let mut panicked = false;
+    stream::poll_fn(move |cx| {
+        if panicked {
+            return Poll::Ready(None);
+        }
+        match panic::catch_unwind(AssertUnwindSafe(|| events.poll_next_unpin(cx))) {
+            Ok(poll) => poll,
+            Err(_) => {
+                panicked = true;
+                tracing::error!("Connection handler panicked while closing");
+                Poll::Ready(None)
+            }
+        }
+    })
+}
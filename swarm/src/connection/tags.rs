@@ -0,0 +1,148 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The value of a tag attached to a connection, see [`ConnectionTags`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TagValue {
+    /// A textual value, e.g. `validator`.
+    Text(String),
+    /// An integer value, e.g. a score.
+    Int(i64),
+    /// A flag.
+    Bool(bool),
+}
+
+impl From<&str> for TagValue {
+    fn from(value: &str) -> Self {
+        TagValue::Text(value.to_owned())
+    }
+}
+
+impl From<String> for TagValue {
+    fn from(value: String) -> Self {
+        TagValue::Text(value)
+    }
+}
+
+impl From<i64> for TagValue {
+    fn from(value: i64) -> Self {
+        TagValue::Int(value)
+    }
+}
+
+impl From<bool> for TagValue {
+    fn from(value: bool) -> Self {
+        TagValue::Bool(value)
+    }
+}
+
+impl fmt::Display for TagValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagValue::Text(value) => write!(f, "{value}"),
+            TagValue::Int(value) => write!(f, "{value}"),
+            TagValue::Bool(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// Key-value tags attached to an established connection, e.g. `role=validator`.
+///
+/// Unlike the typed data of [`ConnectionExtensions`](crate::ConnectionExtensions), tags can be
+/// set by [`NetworkBehaviour`](crate::NetworkBehaviour)s via
+/// [`ToSwarm::TagConnection`](crate::ToSwarm::TagConnection) and are understood by the
+/// [`Swarm`](crate::Swarm) itself. They are part of the connection's extensions, see
+/// [`ConnectionExtensions::tags_mut`](crate::ConnectionExtensions::tags_mut), and can protect
+/// connections from pruning, see
+/// [`PruningPolicy::with_protected_tag`](crate::pruning::PruningPolicy::with_protected_tag).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ConnectionTags {
+    tags: BTreeMap<String, TagValue>,
+}
+
+impl ConnectionTags {
+    /// Sets the tag `key` to `value`, returning its previous value, if any.
+    pub fn insert(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<TagValue>,
+    ) -> Option<TagValue> {
+        self.tags.insert(key.into(), value.into())
+    }
+
+    /// Returns the value of the tag `key`, if set.
+    pub fn get(&self, key: &str) -> Option<&TagValue> {
+        self.tags.get(key)
+    }
+
+    /// Removes the tag `key`, returning its value, if it was set.
+    pub fn remove(&mut self, key: &str) -> Option<TagValue> {
+        self.tags.remove(key)
+    }
+
+    /// Returns whether the tag `key` is set to `value`.
+    pub fn contains(&self, key: &str, value: &TagValue) -> bool {
+        self.tags.get(key) == Some(value)
+    }
+
+    /// Iterates over all tags, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TagValue)> {
+        self.tags.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// The number of tags.
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Returns whether no tag is set.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holds_one_value_per_key() {
+        let mut tags = ConnectionTags::default();
+        assert!(tags.is_empty());
+
+        assert_eq!(tags.insert("role", "validator"), None);
+        assert_eq!(tags.insert("score", 7i64), None);
+        assert_eq!(
+            tags.insert("role", "archive"),
+            Some(TagValue::from("validator"))
+        );
+
+        assert!(tags.contains("role", &"archive".into()));
+        assert!(!tags.contains("score", &TagValue::Text("7".to_owned())));
+        assert_eq!(tags.remove("score"), Some(TagValue::Int(7)));
+        assert_eq!(
+            tags.iter().collect::<Vec<_>>(),
+            vec![("role", &TagValue::from("archive"))]
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{ConnectionDenied, ConnectionId};
+use libp2p_core::{ConnectedPoint, Multiaddr};
+use libp2p_identity::PeerId;
+
+/// A policy deciding which connections the [`Swarm`](crate::Swarm) may establish, see
+/// [`Config::with_connection_gater`](crate::Config::with_connection_gater).
+///
+/// Unlike a [`NetworkBehaviour`](crate::NetworkBehaviour), which only learns about connections it
+/// is composed with, the gater is consulted by the [`Swarm`](crate::Swarm) itself, before any
+/// behaviour at each intercept point:
+///
+/// 1. [`ConnectionGater::intercept_dial`] before dialing the addresses of an outbound connection.
+/// 2. [`ConnectionGater::intercept_accept`] when an inbound connection is received.
+/// 3. [`ConnectionGater::intercept_secured`] once the identity of the remote is authenticated.
+/// 4. [`ConnectionGater::intercept_upgraded`] once the connection is fully upgraded and the
+///    behaviours created its handler, right before it is established.
+///
+/// Denied connections are reported like connections denied by a behaviour, i.e. as
+/// [`DialError::Denied`](crate::DialError::Denied) or
+/// [`ListenError::Denied`](crate::ListenError::Denied).
+///
+/// All methods allow the connection by default.
+pub trait ConnectionGater: Send + 'static {
+    /// Decides whether to dial `addresses`, of `peer` if known.
+    fn intercept_dial(
+        &mut self,
+        _connection_id: ConnectionId,
+        _peer: Option<PeerId>,
+        _addresses: &[Multiaddr],
+    ) -> Result<(), ConnectionDenied> {
+        Ok(())
+    }
+
+    /// Decides whether to upgrade an inbound connection from `send_back_addr`.
+    fn intercept_accept(
+        &mut self,
+        _connection_id: ConnectionId,
+        _local_addr: &Multiaddr,
+        _send_back_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        Ok(())
+    }
+
+    /// Decides whether to accept a connection with the authenticated `peer`, before the
+    /// behaviours are asked for a handler.
+    fn intercept_secured(
+        &mut self,
+        _connection_id: ConnectionId,
+        _peer: PeerId,
+        _endpoint: &ConnectedPoint,
+    ) -> Result<(), ConnectionDenied> {
+        Ok(())
+    }
+
+    /// Decides whether to establish the fully upgraded connection with `peer`.
+    ///
+    /// The behaviours already accepted the connection. If it is denied, they are informed via
+    /// [`FromSwarm::DialFailure`](crate::behaviour::FromSwarm::DialFailure) or
+    /// [`FromSwarm::ListenFailure`](crate::behaviour::FromSwarm::ListenFailure).
+    fn intercept_upgraded(
+        &mut self,
+        _connection_id: ConnectionId,
+        _peer: PeerId,
+        _endpoint: &ConnectedPoint,
+    ) -> Result<(), ConnectionDenied> {
+        Ok(())
+    }
+}
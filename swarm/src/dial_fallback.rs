@@ -0,0 +1,134 @@
+use libp2p_core::multiaddr::Protocol;
+use libp2p_core::Multiaddr;
+
+/// A class of transports, determined by the protocols of an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TransportClass {
+    /// Addresses with a `/quic` or `/quic-v1` component, unless WebTransport.
+    Quic,
+    /// Addresses with a `/tcp` component, unless WebSocket.
+    Tcp,
+    /// Addresses with a `/ws`, `/wss` or `/tls/ws` component.
+    WebSocket,
+    /// Addresses with a `/webrtc-direct` component.
+    WebRtc,
+    /// Addresses with a `/webtransport` component.
+    WebTransport,
+    /// Addresses with a `/p2p-circuit` component, regardless of the transport to the relay.
+    Relay,
+}
+
+impl TransportClass {
+    /// Classifies the given address, returning `None` if it matches none of the classes.
+    pub fn of(address: &Multiaddr) -> Option<Self> {
+        if has(address, |p| matches!(p, Protocol::P2pCircuit)) {
+            Some(TransportClass::Relay)
+        } else if has(address, |p| matches!(p, Protocol::WebRTCDirect)) {
+            Some(TransportClass::WebRtc)
+        } else if has(address, |p| matches!(p, Protocol::WebTransport)) {
+            Some(TransportClass::WebTransport)
+        } else if has(address, |p| matches!(p, Protocol::Quic | Protocol::QuicV1)) {
+            Some(TransportClass::Quic)
+        } else if has(address, |p| matches!(p, Protocol::Ws(_) | Protocol::Wss(_))) {
+            Some(TransportClass::WebSocket)
+        } else if has(address, |p| matches!(p, Protocol::Tcp(_))) {
+            Some(TransportClass::Tcp)
+        } else {
+            None
+        }
+    }
+}
+
+fn has(address: &Multiaddr, f: impl Fn(Protocol<'_>) -> bool) -> bool {
+    address.iter().any(f)
+}
+
+/// The order in which transports are tried when dialing, see
+/// [`Config::with_dial_fallback`](crate::Config::with_dial_fallback).
+///
+/// The addresses of a dial are grouped into stages by their [`TransportClass`], in the order of
+/// the chain. The addresses of a stage are only dialed once all dials of the previous stage
+/// failed, and the dial completes as soon as any of them succeeds. Addresses of classes not in
+/// the chain are dialed in a last stage. If all dials fail,
+/// [`DialError::Transport`](crate::DialError::Transport) lists the error of every attempt in the
+/// order they were made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackChain {
+    stages: Vec<TransportClass>,
+}
+
+impl FallbackChain {
+    /// Creates a new [`FallbackChain`] trying the given classes in order.
+    pub fn new(stages: impl IntoIterator<Item = TransportClass>) -> Self {
+        let mut chain = Self { stages: Vec::new() };
+        for class in stages {
+            if !chain.stages.contains(&class) {
+                chain.stages.push(class);
+            }
+        }
+        chain
+    }
+
+    /// The classes in the order they are tried.
+    pub fn stages(&self) -> &[TransportClass] {
+        &self.stages
+    }
+
+    /// Groups `addresses` into the stages of the chain, keeping their order within a stage and
+    /// omitting empty stages.
+    pub(crate) fn group(&self, addresses: Vec<Multiaddr>) -> Vec<Vec<Multiaddr>> {
+        let mut stages = vec![Vec::new(); self.stages.len() + 1];
+        for address in addresses {
+            let stage = TransportClass::of(&address)
+                .and_then(|class| self.stages.iter().position(|c| *c == class))
+                .unwrap_or(self.stages.len());
+            stages[stage].push(address);
+        }
+        stages.retain(|stage| !stage.is_empty());
+
+        stages
+    }
+}
+
+impl Default for FallbackChain {
+    /// QUIC, then TCP, then relayed addresses.
+    fn default() -> Self {
+        Self::new([
+            TransportClass::Quic,
+            TransportClass::Tcp,
+            TransportClass::Relay,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(addresses: &[&str]) -> Vec<Multiaddr> {
+        addresses.iter().map(|a| a.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn groups_addresses_by_stage() {
+        let relayed = "/ip4/192.0.2.1/udp/4001/quic-v1/p2p/12D3KooWDpJ7As7BWAwRMfu1VU2WCqNjvq387JEYKDBj4kx6nXTN/p2p-circuit";
+        let addresses = addrs(&[
+            relayed,
+            "/ip4/192.0.2.2/tcp/4001",
+            "/ip4/192.0.2.2/tcp/4002/ws",
+            "/ip4/192.0.2.2/udp/4001/quic-v1",
+            "/ip6/::1/tcp/4001",
+        ]);
+
+        assert_eq!(
+            FallbackChain::default().group(addresses),
+            vec![
+                addrs(&["/ip4/192.0.2.2/udp/4001/quic-v1"]),
+                addrs(&["/ip4/192.0.2.2/tcp/4001", "/ip6/::1/tcp/4001"]),
+                addrs(&[relayed]),
+                addrs(&["/ip4/192.0.2.2/tcp/4002/ws"]),
+            ]
+        );
+    }
+}
@@ -19,13 +19,18 @@
 // FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::ConnectionId;
+use crate::{ConnectionId, RetryPolicy};
+use futures::task::AtomicWaker;
 use libp2p_core::connection::Endpoint;
 use libp2p_core::multiaddr::Protocol;
 use libp2p_core::transport::PortUse;
 use libp2p_core::Multiaddr;
 use libp2p_identity::PeerId;
+use std::future::Future;
 use std::num::NonZeroU8;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::Poll;
 
 macro_rules! fn_override_role {
     () => {
@@ -42,6 +47,16 @@ macro_rules! fn_override_role {
     };
 }
 
+macro_rules! fn_priority {
+    () => {
+        /// Set the [`DialPriority`] of the dial, deciding its position among the deferred dials.
+        pub fn priority(mut self, priority: DialPriority) -> Self {
+            self.priority = priority;
+            self
+        }
+    };
+}
+
 macro_rules! fn_allocate_new_port {
     () => {
         /// Enforce the allocation of a new port.
@@ -74,6 +89,10 @@ pub struct DialOpts {
     dial_concurrency_factor_override: Option<NonZeroU8>,
     connection_id: ConnectionId,
     port_use: PortUse,
+    retry_policy: Option<RetryPolicy>,
+    retry_attempt: u32,
+    abort_handle: DialAbortHandle,
+    priority: DialPriority,
 }
 
 impl DialOpts {
@@ -95,6 +114,8 @@ impl DialOpts {
             role_override: Endpoint::Dialer,
             dial_concurrency_factor_override: Default::default(),
             port_use: PortUse::Reuse,
+            retry_policy: None,
+            priority: DialPriority::default(),
         }
     }
 
@@ -135,6 +156,15 @@ impl DialOpts {
         self.connection_id
     }
 
+    /// Get a [`DialAbortHandle`] to cancel this dial, including its retries.
+    ///
+    /// The handle can be obtained before passing the [`DialOpts`] to
+    /// [`Swarm::dial`](crate::Swarm::dial) and used from anywhere, e.g. when the user action
+    /// that triggered the dial is cancelled.
+    pub fn abort_handle(&self) -> DialAbortHandle {
+        self.abort_handle.clone()
+    }
+
     pub(crate) fn get_addresses(&self) -> Vec<Multiaddr> {
         self.addresses.clone()
     }
@@ -158,6 +188,37 @@ impl DialOpts {
     pub(crate) fn port_use(&self) -> PortUse {
         self.port_use
     }
+
+    pub(crate) fn priority(&self) -> DialPriority {
+        self.priority
+    }
+
+    pub(crate) fn retry_policy(&self) -> Option<RetryPolicy> {
+        self.retry_policy
+    }
+
+    /// The number of the retry this dial is, `0` for the initial dial.
+    pub(crate) fn retry_attempt(&self) -> u32 {
+        self.retry_attempt
+    }
+
+    /// Creates the [`DialOpts`] of the next attempt of this dial, with a new [`ConnectionId`].
+    pub(crate) fn retry(&self, policy: RetryPolicy) -> DialOpts {
+        DialOpts {
+            peer_id: self.peer_id,
+            condition: self.condition,
+            addresses: self.addresses.clone(),
+            extend_addresses_through_behaviour: self.extend_addresses_through_behaviour,
+            role_override: self.role_override,
+            dial_concurrency_factor_override: self.dial_concurrency_factor_override,
+            connection_id: ConnectionId::next(),
+            port_use: self.port_use,
+            retry_policy: Some(policy),
+            retry_attempt: self.retry_attempt + 1,
+            abort_handle: self.abort_handle.clone(),
+            priority: self.priority,
+        }
+    }
 }
 
 impl From<Multiaddr> for DialOpts {
@@ -179,6 +240,8 @@ pub struct WithPeerId {
     role_override: Endpoint,
     dial_concurrency_factor_override: Option<NonZeroU8>,
     port_use: PortUse,
+    retry_policy: Option<RetryPolicy>,
+    priority: DialPriority,
 }
 
 impl WithPeerId {
@@ -195,6 +258,13 @@ impl WithPeerId {
         self
     }
 
+    /// Retry the dial according to the given [`RetryPolicy`] instead of the one of the
+    /// [`Config`](crate::Config).
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
     /// Specify a set of addresses to be used to dial the known peer.
     pub fn addresses(self, addresses: Vec<Multiaddr>) -> WithPeerIdWithAddresses {
         WithPeerIdWithAddresses {
@@ -205,11 +275,14 @@ impl WithPeerId {
             role_override: self.role_override,
             dial_concurrency_factor_override: self.dial_concurrency_factor_override,
             port_use: self.port_use,
+            retry_policy: self.retry_policy,
+            priority: self.priority,
         }
     }
 
     fn_override_role!();
     fn_allocate_new_port!();
+    fn_priority!();
 
     /// Build the final [`DialOpts`].
     pub fn build(self) -> DialOpts {
@@ -222,6 +295,10 @@ impl WithPeerId {
             dial_concurrency_factor_override: self.dial_concurrency_factor_override,
             connection_id: ConnectionId::next(),
             port_use: self.port_use,
+            retry_policy: self.retry_policy,
+            retry_attempt: 0,
+            abort_handle: DialAbortHandle::default(),
+            priority: self.priority,
         }
     }
 }
@@ -235,6 +312,8 @@ pub struct WithPeerIdWithAddresses {
     role_override: Endpoint,
     dial_concurrency_factor_override: Option<NonZeroU8>,
     port_use: PortUse,
+    retry_policy: Option<RetryPolicy>,
+    priority: DialPriority,
 }
 
 impl WithPeerIdWithAddresses {
@@ -251,8 +330,16 @@ impl WithPeerIdWithAddresses {
         self
     }
 
+    /// Retry the dial according to the given [`RetryPolicy`] instead of the one of the
+    /// [`Config`](crate::Config).
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
     fn_override_role!();
     fn_allocate_new_port!();
+    fn_priority!();
 
     /// Override
     /// Number of addresses concurrently dialed for a single outbound connection attempt.
@@ -272,6 +359,10 @@ impl WithPeerIdWithAddresses {
             dial_concurrency_factor_override: self.dial_concurrency_factor_override,
             connection_id: ConnectionId::next(),
             port_use: self.port_use,
+            retry_policy: self.retry_policy,
+            retry_attempt: 0,
+            abort_handle: DialAbortHandle::default(),
+            priority: self.priority,
         }
     }
 }
@@ -286,6 +377,7 @@ impl WithoutPeerId {
             address,
             role_override: Endpoint::Dialer,
             port_use: PortUse::Reuse,
+            priority: DialPriority::default(),
         }
     }
 }
@@ -295,11 +387,13 @@ pub struct WithoutPeerIdWithAddress {
     address: Multiaddr,
     role_override: Endpoint,
     port_use: PortUse,
+    priority: DialPriority,
 }
 
 impl WithoutPeerIdWithAddress {
     fn_override_role!();
     fn_allocate_new_port!();
+    fn_priority!();
 
     /// Build the final [`DialOpts`].
     pub fn build(self) -> DialOpts {
@@ -312,10 +406,58 @@ impl WithoutPeerIdWithAddress {
             dial_concurrency_factor_override: None,
             connection_id: ConnectionId::next(),
             port_use: self.port_use,
+            retry_policy: None,
+            retry_attempt: 0,
+            abort_handle: DialAbortHandle::default(),
+            priority: self.priority,
         }
     }
 }
 
+/// A handle to cancel a dial, obtained via [`DialOpts::abort_handle`].
+///
+/// Aborting a pending dial stops dialing its addresses and closes any connection that is still
+/// being negotiated. It is reported as [`DialError::Aborted`](crate::DialError::Aborted).
+/// Aborting a dial that is already established has no effect; close the connection via
+/// [`Swarm::close_connection`](crate::Swarm::close_connection) instead.
+#[derive(Debug, Clone, Default)]
+pub struct DialAbortHandle {
+    inner: Arc<AbortInner>,
+}
+
+#[derive(Debug, Default)]
+struct AbortInner {
+    aborted: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl DialAbortHandle {
+    /// Aborts the dial.
+    pub fn abort(&self) {
+        self.inner.aborted.store(true, Ordering::SeqCst);
+        self.inner.waker.wake();
+    }
+
+    /// Whether the dial has been aborted.
+    pub fn is_aborted(&self) -> bool {
+        self.inner.aborted.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the dial is aborted.
+    pub(crate) fn aborted(self) -> impl Future<Output = ()> + Send + Unpin {
+        futures::future::poll_fn(move |cx| {
+            if self.is_aborted() {
+                return Poll::Ready(());
+            }
+            self.inner.waker.register(cx.waker());
+            if self.is_aborted() {
+                return Poll::Ready(());
+            }
+            Poll::Pending
+        })
+    }
+}
+
 /// The available conditions under which a new dialing attempt to
 /// a known peer is initiated.
 ///
@@ -346,3 +488,30 @@ pub enum PeerCondition {
     /// configured connection limits.
     Always,
 }
+
+/// The priority of a dial, see [`WithPeerId::priority`].
+///
+/// Dials exceeding [`Config::with_max_concurrent_dials`](crate::Config::with_max_concurrent_dials)
+/// or [`Config::with_max_concurrent_dials_per_peer`](crate::Config::with_max_concurrent_dials_per_peer)
+/// are deferred. Once a dial completes, the deferred dial with the highest priority is started,
+/// the oldest first among dials of the same priority. Dials that are not deferred are not
+/// affected.
+///
+/// ```
+/// # use libp2p_swarm::dial_opts::{DialOpts, DialPriority};
+/// # use libp2p_identity::PeerId;
+/// #
+/// DialOpts::peer_id(PeerId::random())
+///    .priority(DialPriority::High)
+///    .build();
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DialPriority {
+    /// Background dials that can wait, e.g. to refresh a routing table.
+    Low,
+    /// The priority of dials that don't specify one.
+    #[default]
+    Normal,
+    /// Time-sensitive dials, e.g. to hole-punch or to make a relay reservation.
+    High,
+}
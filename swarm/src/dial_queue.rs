@@ -0,0 +1,173 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::dial_opts::DialOpts;
+use crate::ConnectionId;
+use libp2p_identity::PeerId;
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+
+/// Limits on the number of dials of the [`Swarm`](crate::Swarm) in flight at the same time.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct DialLimits {
+    pub(crate) max_dials: Option<NonZeroUsize>,
+    pub(crate) max_dials_per_peer: Option<NonZeroUsize>,
+}
+
+/// The dials deferred because a [`DialLimits`] was reached, ordered by their
+/// [`DialPriority`](crate::dial_opts::DialPriority) and then in the order they were made.
+pub(crate) struct DialQueue {
+    limits: DialLimits,
+    queued: VecDeque<DialOpts>,
+}
+
+impl DialQueue {
+    pub(crate) fn new(limits: DialLimits) -> Self {
+        Self {
+            limits,
+            queued: VecDeque::new(),
+        }
+    }
+
+    /// Whether a new dial has to be deferred, given the number of dials in flight overall and to
+    /// its peer.
+    pub(crate) fn must_defer(&self, num_dialing: usize, num_dialing_peer: usize) -> bool {
+        self.limits
+            .max_dials
+            .is_some_and(|max| num_dialing >= max.get())
+            || self
+                .limits
+                .max_dials_per_peer
+                .is_some_and(|max| num_dialing_peer >= max.get())
+    }
+
+    pub(crate) fn push(&mut self, opts: DialOpts) {
+        let priority = opts.priority();
+        let index = self
+            .queued
+            .iter()
+            .position(|queued| queued.priority() < priority)
+            .unwrap_or(self.queued.len());
+        self.queued.insert(index, opts);
+    }
+
+    /// Removes a deferred dial, e.g. to abort it.
+    pub(crate) fn remove(&mut self, connection_id: ConnectionId) -> Option<DialOpts> {
+        let index = self
+            .queued
+            .iter()
+            .position(|opts| opts.connection_id() == connection_id)?;
+        self.queued.remove(index)
+    }
+
+    /// Returns the deferred dial of the highest priority that no longer exceeds the limits, given
+    /// the number of dials in flight overall and per peer.
+    pub(crate) fn pop(
+        &mut self,
+        num_dialing: usize,
+        num_dialing_peer: impl Fn(PeerId) -> usize,
+    ) -> Option<DialOpts> {
+        if self.queued.is_empty() || self.must_defer(num_dialing, 0) {
+            return None;
+        }
+        let index = self.queued.iter().position(|opts| {
+            opts.get_peer_id()
+                .map_or(true, |peer| !self.must_defer(0, num_dialing_peer(peer)))
+        })?;
+        self.queued.remove(index)
+    }
+
+    /// Drops all deferred dials.
+    pub(crate) fn clear(&mut self) {
+        self.queued.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dial_opts::DialPriority;
+
+    fn limits(max_dials: usize, max_dials_per_peer: usize) -> DialLimits {
+        DialLimits {
+            max_dials: NonZeroUsize::new(max_dials),
+            max_dials_per_peer: NonZeroUsize::new(max_dials_per_peer),
+        }
+    }
+
+    #[test]
+    fn defers_dials_above_limits() {
+        let queue = DialQueue::new(limits(4, 1));
+
+        assert!(!queue.must_defer(3, 0));
+        assert!(queue.must_defer(4, 0));
+        assert!(queue.must_defer(0, 1));
+        assert!(!DialQueue::new(DialLimits::default()).must_defer(usize::MAX, usize::MAX));
+    }
+
+    #[test]
+    fn pops_oldest_dial_within_limits() {
+        let mut queue = DialQueue::new(limits(4, 1));
+        let (busy, idle) = (PeerId::random(), PeerId::random());
+        let first = DialOpts::peer_id(busy).build();
+        let second = DialOpts::peer_id(idle).build();
+        let second_id = second.connection_id();
+        queue.push(first);
+        queue.push(second);
+
+        let num_dialing_peer = |peer| usize::from(peer == busy);
+
+        // The global limit is reached.
+        assert!(queue.pop(4, num_dialing_peer).is_none());
+        // The first dial is still capped by its peer.
+        assert_eq!(
+            queue
+                .pop(1, num_dialing_peer)
+                .map(|opts| opts.connection_id()),
+            Some(second_id)
+        );
+        assert!(queue.pop(1, num_dialing_peer).is_none());
+        assert!(queue.pop(0, |_| 0).is_some());
+    }
+
+    #[test]
+    fn pops_dials_by_priority() {
+        let mut queue = DialQueue::new(limits(1, 0));
+        let dial = |priority| {
+            DialOpts::peer_id(PeerId::random())
+                .priority(priority)
+                .build()
+        };
+        let low = dial(DialPriority::Low);
+        let normal = dial(DialPriority::Normal);
+        let first_high = dial(DialPriority::High);
+        let second_high = dial(DialPriority::High);
+        let expected = [&first_high, &second_high, &normal, &low].map(DialOpts::connection_id);
+        queue.push(low);
+        queue.push(normal);
+        queue.push(first_high);
+        queue.push(second_high);
+
+        let popped = std::iter::from_fn(|| queue.pop(0, |_| 0))
+            .map(|opts| opts.connection_id())
+            .collect::<Vec<_>>();
+        assert_eq!(popped, expected);
+    }
+}
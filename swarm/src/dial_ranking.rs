@@ -0,0 +1,218 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use libp2p_core::multiaddr::Protocol;
+use libp2p_core::Multiaddr;
+use libp2p_identity::PeerId;
+use std::collections::HashMap;
+use std::time::Duration;
+use web_time::Instant;
+
+/// Orders and filters the addresses of an outbound connection before they are dialed, see
+/// [`Config::with_dial_ranker`](crate::Config::with_dial_ranker).
+///
+/// The ranker is applied after the addresses of the [`DialOpts`](crate::dial_opts::DialOpts) and
+/// of the [`NetworkBehaviour`](crate::NetworkBehaviour) are combined and deduplicated. Addresses
+/// are dialed in the resulting order, with up to
+/// [`Config::with_dial_concurrency_factor`](crate::Config::with_dial_concurrency_factor) at the
+/// same time. If the ranker removes all addresses, the dial fails with
+/// [`DialError::NoAddresses`](crate::DialError::NoAddresses).
+pub trait DialRanker: Send + 'static {
+    /// Reorders and filters the `addresses` of a dial, of `peer` if known.
+    fn rank(&mut self, peer: Option<PeerId>, addresses: &mut Vec<Multiaddr>);
+
+    /// Informs the ranker that dialing `address` failed on the transport layer.
+    fn on_dial_failure(&mut self, _peer: Option<PeerId>, _address: &Multiaddr) {}
+
+    /// Informs the ranker that a connection to `peer` was established by dialing `address`.
+    fn on_dial_success(&mut self, _peer: PeerId, _address: &Multiaddr) {}
+}
+
+impl<F> DialRanker for F
+where
+    F: FnMut(Option<PeerId>, &mut Vec<Multiaddr>) + Send + 'static,
+{
+    fn rank(&mut self, peer: Option<PeerId>, addresses: &mut Vec<Multiaddr>) {
+        self(peer, addresses)
+    }
+}
+
+/// A [`DialRanker`] covering common preferences.
+///
+/// Addresses are ordered by:
+///
+/// 1. Whether dialing them failed within the failure backoff, addresses that failed recently last.
+/// 2. Whether they are relayed, relayed addresses last if [`PreferenceRanker::with_relay_last`]
+///    is enabled.
+/// 3. Whether they use QUIC, QUIC addresses first if [`PreferenceRanker::with_prefer_quic`] is
+///    enabled.
+///
+/// The order is otherwise kept, i.e. the sort is stable.
+#[derive(Debug, Clone)]
+pub struct PreferenceRanker {
+    prefer_quic: bool,
+    relay_last: bool,
+    failure_backoff: Duration,
+    failures: HashMap<Multiaddr, Instant>,
+}
+
+impl Default for PreferenceRanker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PreferenceRanker {
+    /// Creates a new [`PreferenceRanker`], preferring QUIC, dialing relayed addresses last and
+    /// deprioritizing addresses that failed within the last 5 minutes.
+    pub fn new() -> Self {
+        Self {
+            prefer_quic: true,
+            relay_last: true,
+            failure_backoff: Duration::from_secs(5 * 60),
+            failures: HashMap::new(),
+        }
+    }
+
+    /// Sets whether to dial QUIC addresses before other addresses.
+    pub fn with_prefer_quic(mut self, enabled: bool) -> Self {
+        self.prefer_quic = enabled;
+        self
+    }
+
+    /// Sets whether to dial relayed addresses after direct addresses.
+    pub fn with_relay_last(mut self, enabled: bool) -> Self {
+        self.relay_last = enabled;
+        self
+    }
+
+    /// Sets for how long addresses are dialed last after dialing them failed.
+    ///
+    /// [`Duration::ZERO`] disables the deprioritization.
+    pub fn with_failure_backoff(mut self, backoff: Duration) -> Self {
+        self.failure_backoff = backoff;
+        self
+    }
+
+    fn failed_recently(&self, address: &Multiaddr, now: Instant) -> bool {
+        self.failures
+            .get(address)
+            .is_some_and(|failed_at| now.duration_since(*failed_at) < self.failure_backoff)
+    }
+}
+
+impl DialRanker for PreferenceRanker {
+    fn rank(&mut self, _: Option<PeerId>, addresses: &mut Vec<Multiaddr>) {
+        let now = Instant::now();
+        let backoff = self.failure_backoff;
+        self.failures
+            .retain(|_, failed_at| now.duration_since(*failed_at) < backoff);
+
+        addresses.sort_by_cached_key(|address| {
+            let address = without_peer_id(address);
+            (
+                self.failed_recently(&address, now),
+                self.relay_last && is_relayed(&address),
+                !(self.prefer_quic && is_quic(&address)),
+            )
+        });
+    }
+
+    fn on_dial_failure(&mut self, _: Option<PeerId>, address: &Multiaddr) {
+        if !self.failure_backoff.is_zero() {
+            self.failures
+                .insert(without_peer_id(address), Instant::now());
+        }
+    }
+
+    fn on_dial_success(&mut self, _: PeerId, address: &Multiaddr) {
+        self.failures.remove(&without_peer_id(address));
+    }
+}
+
+/// Removes a trailing `/p2p` component, which is appended to addresses when they are dialed.
+fn without_peer_id(address: &Multiaddr) -> Multiaddr {
+    let mut address = address.clone();
+    if let Some(Protocol::P2p(_)) = address.iter().last() {
+        address.pop();
+    }
+    address
+}
+
+fn is_relayed(address: &Multiaddr) -> bool {
+    address.iter().any(|p| p == Protocol::P2pCircuit)
+}
+
+fn is_quic(address: &Multiaddr) -> bool {
+    address
+        .iter()
+        .any(|p| matches!(p, Protocol::Quic | Protocol::QuicV1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(addresses: &[&str]) -> Vec<Multiaddr> {
+        addresses.iter().map(|a| a.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn prefers_quic_and_direct_addresses() {
+        let mut ranker = PreferenceRanker::new();
+        let mut addresses = addrs(&[
+            "/ip4/192.0.2.1/tcp/4001/p2p/12D3KooWDpJ7As7BWAwRMfu1VU2WCqNjvq387JEYKDBj4kx6nXTN/p2p-circuit",
+            "/ip4/192.0.2.1/tcp/4001",
+            "/ip4/192.0.2.1/udp/4001/quic-v1",
+        ]);
+
+        ranker.rank(None, &mut addresses);
+
+        assert_eq!(
+            addresses,
+            addrs(&[
+                "/ip4/192.0.2.1/udp/4001/quic-v1",
+                "/ip4/192.0.2.1/tcp/4001",
+                "/ip4/192.0.2.1/tcp/4001/p2p/12D3KooWDpJ7As7BWAwRMfu1VU2WCqNjvq387JEYKDBj4kx6nXTN/p2p-circuit",
+            ])
+        );
+    }
+
+    #[test]
+    fn deprioritizes_failed_addresses() {
+        let peer = PeerId::random();
+        let mut ranker = PreferenceRanker::new();
+        let mut addresses = addrs(&["/ip4/192.0.2.1/udp/4001/quic-v1", "/ip4/192.0.2.1/tcp/4001"]);
+
+        ranker.on_dial_failure(Some(peer), &addresses[0].clone().with_p2p(peer).unwrap());
+        ranker.rank(Some(peer), &mut addresses);
+        assert_eq!(
+            addresses,
+            addrs(&["/ip4/192.0.2.1/tcp/4001", "/ip4/192.0.2.1/udp/4001/quic-v1"])
+        );
+
+        ranker.on_dial_success(peer, &"/ip4/192.0.2.1/udp/4001/quic-v1".parse().unwrap());
+        ranker.rank(Some(peer), &mut addresses);
+        assert_eq!(
+            addresses,
+            addrs(&["/ip4/192.0.2.1/udp/4001/quic-v1", "/ip4/192.0.2.1/tcp/4001"])
+        );
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::dial_opts::DialOpts;
+use crate::{ConnectionId, DialError};
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
+use futures::{FutureExt, StreamExt};
+use futures_timer::Delay;
+use rand::Rng;
+use std::collections::HashMap;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Policy to retry failed dials of a known peer with an exponential backoff.
+///
+/// Applies to all dials via [`Config::with_dial_retry_policy`](crate::Config::with_dial_retry_policy),
+/// or to a single dial via [`WithPeerId::retry_policy`](crate::dial_opts::WithPeerId::retry_policy).
+/// Only dials that failed on the transport layer, i.e. with [`DialError::Transport`], are retried.
+///
+/// The `n`-th retry is scheduled `initial_backoff * 2^(n - 1)` after the failure, capped at
+/// `max_backoff`, and randomized by up to a factor of `jitter` in either direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_retries: u32,
+    jitter: f64,
+}
+
+impl RetryPolicy {
+    /// Creates a new [`RetryPolicy`], retrying a dial up to 5 times with a backoff starting at
+    /// 1 second, capped at 1 minute and randomized by 10%.
+    pub fn new() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_retries: 5,
+            jitter: 0.1,
+        }
+    }
+
+    /// Sets the backoff before the first retry.
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the maximum backoff between two attempts.
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Sets the maximum number of retries after the initial dial failed.
+    ///
+    /// `0` disables retries, e.g. to opt out of the policy of the [`Config`](crate::Config) for
+    /// a single dial.
+    pub fn with_max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Sets the fraction by which each backoff is randomly increased or decreased, such that
+    /// peers that failed at the same time are not redialed all at once.
+    ///
+    /// Clamped to `0.0..=1.0`.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The maximum number of retries after the initial dial failed.
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// The backoff before the given retry, starting at `1`, without jitter.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    fn jittered_backoff(&self, retry: u32) -> Duration {
+        let backoff = self.backoff(retry);
+        if self.jitter == 0.0 {
+            return backoff;
+        }
+        let factor = rand::thread_rng().gen_range(1.0 - self.jitter..=1.0 + self.jitter);
+        backoff.mul_f64(factor)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tracks the dials of the [`Swarm`](crate::Swarm) that are retried on failure.
+pub(crate) struct DialRetries {
+    /// The policy of dials that don't set their own.
+    policy: Option<RetryPolicy>,
+    /// Dials that are retried if they fail, by the [`ConnectionId`] of their latest attempt.
+    pending: HashMap<ConnectionId, (DialOpts, RetryPolicy)>,
+    /// Retries waiting for their backoff to elapse.
+    scheduled: FuturesUnordered<BoxFuture<'static, DialOpts>>,
+}
+
+impl DialRetries {
+    pub(crate) fn new(policy: Option<RetryPolicy>) -> Self {
+        Self {
+            policy,
+            pending: HashMap::new(),
+            scheduled: FuturesUnordered::new(),
+        }
+    }
+
+    /// Tracks a dial that was initiated, to retry it should it fail.
+    pub(crate) fn on_dial(&mut self, opts: DialOpts) {
+        if opts.get_peer_id().is_none() {
+            return;
+        }
+        let Some(policy) = opts.retry_policy().or(self.policy) else {
+            return;
+        };
+        if opts.retry_attempt() >= policy.max_retries {
+            return;
+        }
+        self.pending.insert(opts.connection_id(), (opts, policy));
+    }
+
+    /// Stops tracking a dial that succeeded.
+    pub(crate) fn on_connection_established(&mut self, connection_id: ConnectionId) {
+        self.pending.remove(&connection_id);
+    }
+
+    /// Schedules a retry of a failed dial, if any.
+    ///
+    /// Returns the number of the retry and the backoff before it.
+    pub(crate) fn on_dial_failure(
+        &mut self,
+        connection_id: ConnectionId,
+        error: &DialError,
+    ) -> Option<(u32, Duration)> {
+        let (opts, policy) = self.pending.remove(&connection_id)?;
+        if !matches!(error, DialError::Transport(_)) {
+            return None;
+        }
+
+        let retry = opts.retry(policy);
+        let attempt = retry.retry_attempt();
+        let backoff = policy.jittered_backoff(attempt);
+        self.scheduled
+            .push(Delay::new(backoff).map(move |()| retry).boxed());
+
+        Some((attempt, backoff))
+    }
+
+    /// Drops all pending and scheduled retries.
+    pub(crate) fn clear(&mut self) {
+        self.pending.clear();
+        self.scheduled.clear();
+    }
+
+    /// Polls for the next retry whose backoff elapsed.
+    pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<DialOpts> {
+        match self.scheduled.poll_next_unpin(cx) {
+            Poll::Ready(Some(opts)) => Poll::Ready(opts),
+            Poll::Ready(None) | Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_max() {
+        let policy = RetryPolicy::new()
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_max_backoff(Duration::from_secs(1));
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(4), Duration::from_millis(800));
+        assert_eq!(policy.backoff(5), Duration::from_secs(1));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let policy = RetryPolicy::new()
+            .with_initial_backoff(Duration::from_secs(10))
+            .with_jitter(0.5);
+
+        for _ in 0..100 {
+            let backoff = policy.jittered_backoff(1);
+            assert!(backoff >= Duration::from_secs(5));
+            assert!(backoff <= Duration::from_secs(15));
+        }
+    }
+}
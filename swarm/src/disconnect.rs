@@ -0,0 +1,149 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Application-level reasons for closing a connection.
+//!
+//! [`Swarm::disconnect_peer_with_reason`](crate::Swarm::disconnect_peer_with_reason) notifies
+//! the remote of a [`DisconnectReason`] before closing the connections to it. The notice is sent
+//! on a dedicated stream negotiated for [`PROTOCOL_NAME`], which connections accept next to the
+//! protocols of their [`ConnectionHandler`](crate::ConnectionHandler) if enabled via
+//! [`Config::with_disconnect_reasons`](crate::Config::with_disconnect_reasons). A received reason
+//! is reported in [`SwarmEvent::ConnectionClosed`](crate::SwarmEvent::ConnectionClosed).
+//!
+//! The notice consists of the code as a big-endian `u32`, followed by the length of the message
+//! as a big-endian `u16` and the UTF-8 encoded message. The receiver closes the stream once it
+//! read the notice, upon which the sender closes the connection. Remotes that don't support the
+//! protocol are disconnected without notice.
+
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use std::{fmt, io, time::Duration};
+
+/// The protocol of the stream a [`DisconnectReason`] is sent on.
+pub const PROTOCOL_NAME: &str = "/libp2p/disconnect/1.0.0";
+
+/// The maximum length of [`DisconnectReason::message`] in bytes, longer messages are truncated.
+pub const MAX_MESSAGE_LEN: usize = 256;
+
+/// How long closing a connection is delayed to notify the remote of the reason.
+pub(crate) const NOTICE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The reason the remote gave for closing a connection, see [`crate::disconnect`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DisconnectReason {
+    code: u32,
+    message: String,
+}
+
+impl DisconnectReason {
+    /// Creates a new [`DisconnectReason`] with an application-defined code and a human-readable
+    /// message, truncated to [`MAX_MESSAGE_LEN`] bytes.
+    pub fn new(code: u32, message: impl Into<String>) -> Self {
+        let mut message = message.into();
+        if message.len() > MAX_MESSAGE_LEN {
+            let mut end = MAX_MESSAGE_LEN;
+            while !message.is_char_boundary(end) {
+                end -= 1;
+            }
+            message.truncate(end);
+        }
+
+        Self { code, message }
+    }
+
+    /// The application-defined code.
+    pub fn code(&self) -> u32 {
+        self.code
+    }
+
+    /// The human-readable message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+/// Writes `reason` to `stream`, closes it and waits for the remote to close its side.
+pub(crate) async fn send<S>(mut stream: S, reason: &DisconnectReason) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let message = reason.message.as_bytes();
+    let mut notice = Vec::with_capacity(6 + message.len());
+    notice.extend_from_slice(&reason.code.to_be_bytes());
+    notice.extend_from_slice(&(message.len() as u16).to_be_bytes());
+    notice.extend_from_slice(message);
+    stream.write_all(&notice).await?;
+    stream.close().await?;
+
+    // The remote closes the stream once it read the notice.
+    let mut buffer = [0; 1];
+    let _ = stream.read(&mut buffer).await?;
+
+    Ok(())
+}
+
+/// Reads a [`DisconnectReason`] from `stream` and closes it.
+pub(crate) async fn receive<S>(mut stream: S) -> io::Result<DisconnectReason>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut header = [0; 6];
+    stream.read_exact(&mut header).await?;
+    let code = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let len = usize::from(u16::from_be_bytes([header[4], header[5]]));
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("disconnect message of {len} bytes exceeds {MAX_MESSAGE_LEN} bytes"),
+        ));
+    }
+
+    let mut message = vec![0; len];
+    stream.read_exact(&mut message).await?;
+    let message =
+        String::from_utf8(message).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    stream.close().await?;
+
+    Ok(DisconnectReason { code, message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+
+    #[test]
+    fn reason_survives_round_trip() {
+        let reason = DisconnectReason::new(42, "ü".repeat(MAX_MESSAGE_LEN));
+        assert_eq!(reason.message().len(), MAX_MESSAGE_LEN);
+
+        let mut stream = Cursor::new(Vec::new());
+        futures::executor::block_on(send(&mut stream, &reason)).unwrap();
+        stream.set_position(0);
+
+        let received = futures::executor::block_on(receive(stream)).unwrap();
+        assert_eq!(received, reason);
+    }
+}
@@ -105,7 +105,9 @@ impl crate::handler::ConnectionHandler for ConnectionHandler {
                 protocol, ..
             }) => void::unreachable(protocol),
             ConnectionEvent::DialUpgradeError(DialUpgradeError { info: _, error }) => match error {
-                StreamUpgradeError::Timeout => unreachable!(),
+                StreamUpgradeError::Timeout | StreamUpgradeError::NegotiationTimeout => {
+                    unreachable!()
+                }
                 StreamUpgradeError::Apply(e) => void::unreachable(e),
                 StreamUpgradeError::NegotiationFailed | StreamUpgradeError::Io(_) => {
                     unreachable!("Denied upgrade does not support any protocols")
@@ -114,7 +116,8 @@ impl crate::handler::ConnectionHandler for ConnectionHandler {
             ConnectionEvent::AddressChange(_)
             | ConnectionEvent::ListenUpgradeError(_)
             | ConnectionEvent::LocalProtocolsChange(_)
-            | ConnectionEvent::RemoteProtocolsChange(_) => {}
+            | ConnectionEvent::RemoteProtocolsChange(_)
+            | ConnectionEvent::RemoteActivity => {}
         }
     }
 }
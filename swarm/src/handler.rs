@@ -224,6 +224,12 @@ pub enum ConnectionEvent<'a, IP: InboundUpgradeSend, OP: OutboundUpgradeSend, IO
     LocalProtocolsChange(ProtocolsChange<'a>),
     /// The remote [`ConnectionHandler`] now supports a different set of protocols.
     RemoteProtocolsChange(ProtocolsChange<'a>),
+    /// Informs the handler that data was received from the remote on one of the streams of
+    /// this connection since the last time this event was reported.
+    ///
+    /// Streams that are [ignored for keep-alive](crate::Stream::ignore_for_keep_alive) don't
+    /// count as activity.
+    RemoteActivity,
 }
 
 impl<'a, IP, OP, IOI, OOI> fmt::Debug for ConnectionEvent<'a, IP, OP, IOI, OOI>
@@ -258,6 +264,7 @@ where
             ConnectionEvent::RemoteProtocolsChange(v) => {
                 f.debug_tuple("RemoteProtocolsChange").field(v).finish()
             }
+            ConnectionEvent::RemoteActivity => f.write_str("RemoteActivity"),
         }
     }
 }
@@ -275,6 +282,7 @@ impl<'a, IP: InboundUpgradeSend, OP: OutboundUpgradeSend, IOI, OOI>
             | ConnectionEvent::AddressChange(_)
             | ConnectionEvent::LocalProtocolsChange(_)
             | ConnectionEvent::RemoteProtocolsChange(_)
+            | ConnectionEvent::RemoteActivity
             | ConnectionEvent::ListenUpgradeError(_) => false,
         }
     }
@@ -289,6 +297,7 @@ impl<'a, IP: InboundUpgradeSend, OP: OutboundUpgradeSend, IOI, OOI>
             | ConnectionEvent::AddressChange(_)
             | ConnectionEvent::LocalProtocolsChange(_)
             | ConnectionEvent::RemoteProtocolsChange(_)
+            | ConnectionEvent::RemoteActivity
             | ConnectionEvent::DialUpgradeError(_) => false,
         }
     }
@@ -672,6 +681,9 @@ impl<TConnectionUpgrade, TOutboundOpenInfo, TCustom>
 pub enum StreamUpgradeError<TUpgrErr> {
     /// The opening attempt timed out before the negotiation was fully completed.
     Timeout,
+    /// The remote did not agree on a protocol within the negotiation timeout, see
+    /// [`NegotiationTimeouts`](crate::negotiation_timeout::NegotiationTimeouts).
+    NegotiationTimeout,
     /// The upgrade produced an error.
     Apply(TUpgrErr),
     /// No protocol could be agreed upon.
@@ -688,6 +700,7 @@ impl<TUpgrErr> StreamUpgradeError<TUpgrErr> {
     {
         match self {
             StreamUpgradeError::Timeout => StreamUpgradeError::Timeout,
+            StreamUpgradeError::NegotiationTimeout => StreamUpgradeError::NegotiationTimeout,
             StreamUpgradeError::Apply(e) => StreamUpgradeError::Apply(f(e)),
             StreamUpgradeError::NegotiationFailed => StreamUpgradeError::NegotiationFailed,
             StreamUpgradeError::Io(e) => StreamUpgradeError::Io(e),
@@ -704,6 +717,12 @@ where
             StreamUpgradeError::Timeout => {
                 write!(f, "Timeout error while opening a substream")
             }
+            StreamUpgradeError::NegotiationTimeout => {
+                write!(
+                    f,
+                    "Timeout error while negotiating the protocol of a substream"
+                )
+            }
             StreamUpgradeError::Apply(err) => {
                 write!(f, "Apply: ")?;
                 crate::print_error_chain(f, err)
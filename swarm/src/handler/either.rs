@@ -225,6 +225,14 @@ where
                     ConnectionEvent::RemoteProtocolsChange(supported_protocols),
                 ),
             },
+            ConnectionEvent::RemoteActivity => match self {
+                Either::Left(handler) => {
+                    handler.on_connection_event(ConnectionEvent::RemoteActivity)
+                }
+                Either::Right(handler) => {
+                    handler.on_connection_event(ConnectionEvent::RemoteActivity)
+                }
+            },
         }
     }
 }
@@ -81,6 +81,29 @@ where
         Ok(m)
     }
 
+    /// Adds a handler under the given key, returning the handler it replaced, if any.
+    ///
+    /// It is an error for the handler to share a protocol name with any of the other handlers.
+    pub(crate) fn try_insert(
+        &mut self,
+        key: K,
+        handler: H,
+    ) -> Result<Option<H>, DuplicateProtonameError> {
+        uniq_proto_names(
+            self.handlers
+                .iter()
+                .filter(|(k, _)| **k != key)
+                .map(|(_, h)| h.listen_protocol().into_upgrade().0)
+                .chain(iter::once(handler.listen_protocol().into_upgrade().0)),
+        )?;
+        Ok(self.handlers.insert(key, handler))
+    }
+
+    /// Removes the handler under the given key.
+    pub(crate) fn remove(&mut self, key: &K) -> Option<H> {
+        self.handlers.remove(key)
+    }
+
     fn on_listen_upgrade_error(
         &mut self,
         ListenUpgradeError {
@@ -217,6 +240,11 @@ where
                     ));
                 }
             }
+            ConnectionEvent::RemoteActivity => {
+                for h in self.handlers.values_mut() {
+                    h.on_connection_event(ConnectionEvent::RemoteActivity);
+                }
+            }
         }
     }
 
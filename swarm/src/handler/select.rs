@@ -345,6 +345,12 @@ where
                         supported_protocols,
                     ));
             }
+            ConnectionEvent::RemoteActivity => {
+                self.proto1
+                    .on_connection_event(ConnectionEvent::RemoteActivity);
+                self.proto2
+                    .on_connection_event(ConnectionEvent::RemoteActivity);
+            }
         }
     }
 }
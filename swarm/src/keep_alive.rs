@@ -0,0 +1,277 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Swarm-level policy for keeping idle connections alive.
+//!
+//! A connection is idle once it has no streams and its [`ConnectionHandler`](crate::ConnectionHandler)
+//! does not ask to keep it alive. Once enabled via
+//! [`Config::with_keep_alive_policy`](crate::Config::with_keep_alive_policy), a [`KeepAlivePolicy`]
+//! decides how long an idle connection is kept before it is closed, instead of
+//! [`Config::with_idle_connection_timeout`](crate::Config::with_idle_connection_timeout) alone:
+//!
+//! - A timeout configured for the remote peer takes precedence.
+//! - Otherwise the longest of the idle connection timeout and the timeouts configured for the
+//!   protocols the remote supports applies.
+//!
+//! Behaviours and the application can hold a [`ConnectionPin`] to keep a connection alive
+//! regardless of its handler, without having to coordinate through the handler. When an idle
+//! connection is scheduled to be closed, [`SwarmEvent::ConnectionIdle`](crate::SwarmEvent::ConnectionIdle)
+//! is reported, leaving time to pin the connection.
+//!
+//! Clones of a [`KeepAlivePolicy`] share their configuration and pins, thus a clone can be handed
+//! to behaviours before building the [`Swarm`](crate::Swarm).
+
+use crate::{ConnectionId, StreamProtocol};
+use libp2p_identity::PeerId;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Waker};
+use std::time::Duration;
+
+/// Decides how long idle connections are kept alive, see the [module documentation](self).
+///
+/// Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct KeepAlivePolicy {
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    peer_timeouts: HashMap<PeerId, Duration>,
+    protocol_timeouts: HashMap<StreamProtocol, Duration>,
+    connections: HashMap<ConnectionId, PinState>,
+}
+
+#[derive(Debug, Default)]
+struct PinState {
+    pins: usize,
+    /// Whether the connection is established, i.e. its [`ConnectionKeepAlive`] exists.
+    established: bool,
+    /// Waker of the connection task, woken when the connection is (un)pinned.
+    waker: Option<Waker>,
+}
+
+impl PinState {
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl KeepAlivePolicy {
+    /// Creates a new [`KeepAlivePolicy`] without any timeouts or pins.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        self.shared.lock().expect("lock not to be poisoned")
+    }
+
+    /// Sets how long idle connections to `peer` are kept alive, overriding all other timeouts.
+    ///
+    /// `None` removes the timeout. Applies to connections becoming idle afterwards.
+    pub fn set_peer_idle_timeout(&self, peer: PeerId, timeout: Option<Duration>) {
+        let mut shared = self.lock();
+        match timeout {
+            Some(timeout) => shared.peer_timeouts.insert(peer, timeout),
+            None => shared.peer_timeouts.remove(&peer),
+        };
+    }
+
+    /// Sets how long idle connections to peers supporting `protocol` are at least kept alive.
+    ///
+    /// `None` removes the timeout. Applies to connections becoming idle afterwards.
+    pub fn set_protocol_idle_timeout(&self, protocol: StreamProtocol, timeout: Option<Duration>) {
+        let mut shared = self.lock();
+        match timeout {
+            Some(timeout) => shared.protocol_timeouts.insert(protocol, timeout),
+            None => shared.protocol_timeouts.remove(&protocol),
+        };
+    }
+
+    /// Keeps the given connection alive until the returned [`ConnectionPin`] is dropped.
+    ///
+    /// A connection can be pinned several times and is kept alive as long as any pin exists.
+    /// Connections can be pinned before they are established, e.g. in
+    /// [`NetworkBehaviour::handle_established_inbound_connection`](crate::NetworkBehaviour::handle_established_inbound_connection).
+    /// Pinning a closed connection has no effect.
+    pub fn pin(&self, connection_id: ConnectionId) -> ConnectionPin {
+        let mut shared = self.lock();
+        let state = shared.connections.entry(connection_id).or_default();
+        state.pins += 1;
+        state.wake();
+
+        ConnectionPin {
+            policy: self.clone(),
+            connection_id,
+        }
+    }
+
+    /// Whether the given connection is currently pinned.
+    pub fn is_pinned(&self, connection_id: ConnectionId) -> bool {
+        self.lock()
+            .connections
+            .get(&connection_id)
+            .is_some_and(|state| state.pins > 0)
+    }
+
+    /// Registers a new connection with `peer`, tracking its pins until the returned
+    /// [`ConnectionKeepAlive`] is dropped.
+    pub(crate) fn connection(
+        &self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+    ) -> ConnectionKeepAlive {
+        self.lock()
+            .connections
+            .entry(connection_id)
+            .or_default()
+            .established = true;
+
+        ConnectionKeepAlive {
+            policy: self.clone(),
+            connection_id,
+            peer,
+        }
+    }
+}
+
+/// Keeps a connection alive as long as it is held, see [`KeepAlivePolicy::pin`].
+#[derive(Debug)]
+#[must_use = "the connection is unpinned when the pin is dropped"]
+pub struct ConnectionPin {
+    policy: KeepAlivePolicy,
+    connection_id: ConnectionId,
+}
+
+impl ConnectionPin {
+    /// The pinned connection.
+    pub fn connection_id(&self) -> ConnectionId {
+        self.connection_id
+    }
+}
+
+impl Drop for ConnectionPin {
+    fn drop(&mut self) {
+        let mut shared = self.policy.lock();
+        if let Some(state) = shared.connections.get_mut(&self.connection_id) {
+            state.pins = state.pins.saturating_sub(1);
+            state.wake();
+            if state.pins == 0 && !state.established {
+                shared.connections.remove(&self.connection_id);
+            }
+        }
+    }
+}
+
+/// The view of a single connection on the [`KeepAlivePolicy`].
+#[derive(Debug)]
+pub(crate) struct ConnectionKeepAlive {
+    policy: KeepAlivePolicy,
+    connection_id: ConnectionId,
+    peer: PeerId,
+}
+
+impl ConnectionKeepAlive {
+    /// Whether the connection is pinned, registering the current task to be woken when this changes.
+    pub(crate) fn poll_is_pinned(&self, cx: &mut Context<'_>) -> bool {
+        let mut shared = self.policy.lock();
+        let state = shared.connections.entry(self.connection_id).or_default();
+        state.waker = Some(cx.waker().clone());
+
+        state.pins > 0
+    }
+
+    /// Returns how long the connection is kept alive once idle.
+    pub(crate) fn idle_timeout(
+        &self,
+        default: Duration,
+        remote_protocols: &HashSet<StreamProtocol>,
+    ) -> Duration {
+        let shared = self.policy.lock();
+        if let Some(timeout) = shared.peer_timeouts.get(&self.peer) {
+            return *timeout;
+        }
+
+        shared
+            .protocol_timeouts
+            .iter()
+            .filter(|(protocol, _)| remote_protocols.contains(*protocol))
+            .map(|(_, timeout)| *timeout)
+            .fold(default, Duration::max)
+    }
+}
+
+impl Drop for ConnectionKeepAlive {
+    fn drop(&mut self) {
+        self.policy.lock().connections.remove(&self.connection_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peer_timeout_overrides_protocol_timeouts() {
+        let policy = KeepAlivePolicy::new();
+        let peer = PeerId::random();
+        let protocol = StreamProtocol::new("/test/1.0.0");
+        let remote_protocols = HashSet::from([protocol.clone()]);
+        let connection = policy.connection(ConnectionId::new_unchecked(0), peer);
+
+        assert_eq!(
+            connection.idle_timeout(Duration::from_secs(1), &remote_protocols),
+            Duration::from_secs(1)
+        );
+
+        policy.set_protocol_idle_timeout(protocol, Some(Duration::from_secs(30)));
+        assert_eq!(
+            connection.idle_timeout(Duration::from_secs(1), &remote_protocols),
+            Duration::from_secs(30)
+        );
+
+        policy.set_peer_idle_timeout(peer, Some(Duration::from_secs(5)));
+        assert_eq!(
+            connection.idle_timeout(Duration::from_secs(1), &remote_protocols),
+            Duration::from_secs(5)
+        );
+    }
+
+    #[test]
+    fn connection_is_pinned_while_pin_is_held() {
+        let policy = KeepAlivePolicy::new();
+        let connection_id = ConnectionId::new_unchecked(0);
+        let _connection = policy.connection(connection_id, PeerId::random());
+
+        let pin = policy.pin(connection_id);
+        let other_pin = policy.pin(connection_id);
+        assert!(policy.is_pinned(connection_id));
+
+        drop(pin);
+        assert!(policy.is_pinned(connection_id));
+
+        drop(other_pin);
+        assert!(!policy.is_pinned(connection_id));
+    }
+}
@@ -56,6 +56,9 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
 mod connection;
+mod connection_gater;
+mod dial_queue;
+mod dial_retry;
 mod executor;
 mod stream;
 mod stream_protocol;
@@ -63,11 +66,26 @@ mod stream_protocol;
 mod test;
 mod upgrade;
 
+pub mod bandwidth;
 pub mod behaviour;
+pub mod cache;
+pub mod dial_fallback;
 pub mod dial_opts;
+pub mod dial_ranking;
+pub mod disconnect;
 pub mod dummy;
 pub mod handler;
+pub mod keep_alive;
 mod listen_opts;
+mod listeners;
+mod local_addresses;
+pub mod negotiation_timeout;
+pub mod protocol_rules;
+pub mod pruning;
+pub mod rate_limit;
+pub mod resource_manager;
+pub mod stream_metrics;
+pub mod subscription;
 mod translation;
 
 /// Bundles all symbols required for the [`libp2p_swarm_derive::NetworkBehaviour`] macro.
@@ -108,13 +126,20 @@ pub mod derive_prelude {
 }
 
 pub use behaviour::{
-    AddressChange, CloseConnection, ConnectionClosed, DialFailure, ExpiredListenAddr,
-    ExternalAddrExpired, ExternalAddresses, FromSwarm, ListenAddresses, ListenFailure,
-    ListenerClosed, ListenerError, NetworkBehaviour, NewExternalAddrCandidate,
-    NewExternalAddrOfPeer, NewListenAddr, NotifyHandler, PeerAddresses, ToSwarm,
+    AddressChange, AddressRanking, CloseConnection, ConnectionClosed, DialFailure,
+    ExpiredListenAddr, ExternalAddrExpired, ExternalAddressSource, ExternalAddresses, FromSwarm,
+    ListenAddresses, ListenFailure, ListenerClosed, ListenerError, NetworkBehaviour,
+    NewExternalAddrCandidate, NewExternalAddrOfPeer, NewListenAddr, NotifyHandler, PeerAddresses,
+    ScoredAddress, ToSwarm,
 };
 pub use connection::pool::ConnectionCounters;
-pub use connection::{ConnectionError, ConnectionId, SupportedProtocols};
+pub use connection::{
+    ConnectionError, ConnectionExtensions, ConnectionId, ConnectionTags, SupportedProtocols,
+    TagValue,
+};
+pub use connection_gater::ConnectionGater;
+pub use dial_ranking::DialRanker;
+pub use dial_retry::RetryPolicy;
 pub use executor::Executor;
 pub use handler::{
     ConnectionHandler, ConnectionHandlerEvent, ConnectionHandlerSelect, OneShotHandler,
@@ -123,18 +148,33 @@ pub use handler::{
 #[cfg(feature = "macros")]
 pub use libp2p_swarm_derive::NetworkBehaviour;
 pub use listen_opts::ListenOpts;
+pub use listeners::{ExpiryReason, ListenAddress, ListenerInfo, ListenerState};
+pub use local_addresses::AddressSource;
 pub use stream::Stream;
 pub use stream_protocol::{InvalidProtocol, StreamProtocol};
 
+use crate::bandwidth::BandwidthAccounting;
 use crate::behaviour::ExternalAddrConfirmed;
+use crate::dial_fallback::FallbackChain;
+use crate::disconnect::DisconnectReason;
 use crate::handler::UpgradeInfoSend;
+use crate::keep_alive::KeepAlivePolicy;
+use crate::negotiation_timeout::NegotiationTimeouts;
+use crate::pruning::PruningPolicy;
+use crate::rate_limit::StreamRateLimiter;
+use crate::stream_metrics::StreamMetrics;
+use crate::subscription::Subscriptions;
 use connection::pool::{EstablishedConnection, Pool, PoolConfig, PoolEvent};
 use connection::IncomingInfo;
 use connection::{
     PendingConnectionError, PendingInboundConnectionError, PendingOutboundConnectionError,
 };
 use dial_opts::{DialOpts, PeerCondition};
-use futures::{prelude::*, stream::FusedStream};
+use dial_queue::{DialLimits, DialQueue};
+use dial_retry::DialRetries;
+use futures::{channel::mpsc, prelude::*, stream::FusedStream};
+use listeners::Listeners;
+use local_addresses::LocalAddresses;
 
 use libp2p_core::{
     connection::ConnectedPoint,
@@ -209,6 +249,11 @@ pub enum SwarmEvent<TBehaviourOutEvent> {
         /// Reason for the disconnection, if it was not a successful
         /// active close.
         cause: Option<ConnectionError>,
+        /// The reason the remote gave for closing the connection, if it did so via
+        /// [`Swarm::disconnect_peer_with_reason`].
+        reason: Option<DisconnectReason>,
+        /// The data and tags attached to the connection, see [`Swarm::connection_extensions`].
+        extensions: ConnectionExtensions,
     },
     /// A new connection arrived on a listener and is in the process of protocol negotiation.
     ///
@@ -300,11 +345,98 @@ pub enum SwarmEvent<TBehaviourOutEvent> {
     /// We have discovered a new candidate for an external address for us.
     NewExternalAddrCandidate { address: Multiaddr },
     /// An external address of the local node was confirmed.
-    ExternalAddrConfirmed { address: Multiaddr },
+    ExternalAddrConfirmed {
+        address: Multiaddr,
+        /// How the address was confirmed.
+        source: ExternalAddressSource,
+    },
     /// An external address of the local node expired, i.e. is no-longer confirmed.
     ExternalAddrExpired { address: Multiaddr },
     /// We have discovered a new address of a peer.
     NewExternalAddrOfPeer { peer_id: PeerId, address: Multiaddr },
+    /// The sources of an address of the local node changed, see [`Swarm::local_addresses`].
+    ///
+    /// Only reported if enabled via [`Config::with_local_address_events`].
+    LocalAddressChanged {
+        /// The local address.
+        address: Multiaddr,
+        /// All sources the address is now known from. Empty if the address is no longer known.
+        sources: Vec<AddressSource>,
+    },
+    /// A listener is bound to a new address, see [`Swarm::listeners_info`].
+    ///
+    /// Reported after the corresponding [`NewListenAddr`](SwarmEvent::NewListenAddr). Only
+    /// reported if enabled via [`Config::with_listener_events`].
+    ListenAddrBound {
+        /// The listener that is listening on the new address.
+        listener_id: ListenerId,
+        /// The new address, together with its transport and socket.
+        address: ListenAddress,
+    },
+    /// A listener is no longer listening on an address, see [`Swarm::listeners_info`].
+    ///
+    /// Reported after the corresponding [`ExpiredListenAddr`](SwarmEvent::ExpiredListenAddr)
+    /// or [`ListenerClosed`](SwarmEvent::ListenerClosed). Only reported if enabled via
+    /// [`Config::with_listener_events`].
+    ListenAddrRemoved {
+        /// The listener that is no longer listening on the address.
+        listener_id: ListenerId,
+        /// The removed address, together with its transport and socket.
+        address: ListenAddress,
+        /// Why the address is no longer listened on.
+        reason: ExpiryReason,
+    },
+    /// A failed dial will be retried according to its [`RetryPolicy`].
+    ///
+    /// Reported after the [`OutgoingConnectionError`](SwarmEvent::OutgoingConnectionError) of
+    /// the failed attempt.
+    DialRetryScheduled {
+        /// The peer that is redialed.
+        peer_id: PeerId,
+        /// Identifier of the failed attempt.
+        connection_id: ConnectionId,
+        /// The number of the retry, starting at `1`.
+        attempt: u32,
+        /// The time until the retry.
+        backoff: Duration,
+    },
+    /// A dial has been deferred because the limit of concurrent dials was reached, see
+    /// [`Config::with_max_concurrent_dials`] and [`Config::with_max_concurrent_dials_per_peer`].
+    ///
+    /// The dial is started once an ongoing dial completes. Its outcome is reported like the one of
+    /// other dials, i.e. as [`ConnectionEstablished`](SwarmEvent::ConnectionEstablished) or
+    /// [`OutgoingConnectionError`](SwarmEvent::OutgoingConnectionError).
+    DialDeferred {
+        /// Identity of the peer that we are connecting to.
+        peer_id: Option<PeerId>,
+        /// Identifier of the deferred connection.
+        connection_id: ConnectionId,
+    },
+    /// A failed dial has been retried.
+    ///
+    /// Like for [`Dialing`](SwarmEvent::Dialing), the outcome is reported as
+    /// [`ConnectionEstablished`](SwarmEvent::ConnectionEstablished) or
+    /// [`OutgoingConnectionError`](SwarmEvent::OutgoingConnectionError).
+    DialRetry {
+        /// The peer that is redialed.
+        peer_id: PeerId,
+        /// Identifier of the new attempt.
+        connection_id: ConnectionId,
+        /// The number of the retry, starting at `1`.
+        attempt: u32,
+    },
+    /// An idle connection is going to be closed, see [`KeepAlivePolicy`].
+    ///
+    /// The connection is kept if it becomes active or is pinned via [`KeepAlivePolicy::pin`]
+    /// before `closes_in` elapses. Only reported if enabled via [`Config::with_keep_alive_policy`].
+    ConnectionIdle {
+        /// Identity of the peer of the idle connection.
+        peer_id: PeerId,
+        /// Identifier of the idle connection.
+        connection_id: ConnectionId,
+        /// The time until the connection is closed.
+        closes_in: Duration,
+    },
 }
 
 impl<TBehaviourOutEvent> SwarmEvent<TBehaviourOutEvent> {
@@ -342,17 +474,57 @@ where
     /// List of protocols that the behaviour says it supports.
     supported_protocols: SmallVec<[Vec<u8>; 16]>,
 
-    confirmed_external_addr: HashSet<Multiaddr>,
+    /// The confirmed external addresses of the local node, ranked.
+    external_addresses: ExternalAddresses,
 
     /// Multiaddresses that our listeners are listening on,
     listened_addrs: HashMap<ListenerId, SmallVec<[Multiaddr; 1]>>,
 
+    /// Reconciled view of all addresses of the local node.
+    local_addresses: LocalAddresses,
+
+    /// The state and addresses of the active listeners.
+    listeners: Listeners,
+
+    /// Whether changes of the addresses of [`Swarm::listeners_info`] are reported as
+    /// [`SwarmEvent::ListenAddrBound`] and [`SwarmEvent::ListenAddrRemoved`].
+    listener_events: bool,
+
+    /// Whether changes of [`Swarm::local_addresses`] are reported as
+    /// [`SwarmEvent::LocalAddressChanged`].
+    local_address_events: bool,
+
+    /// Consulted before the behaviour whether to establish a connection.
+    connection_gater: Option<Box<dyn ConnectionGater>>,
+
+    /// Orders and filters the addresses of outbound connections before dialing, if any.
+    dial_ranker: Option<Box<dyn DialRanker>>,
+
+    /// The order in which the addresses of a dial are tried by transport, if any.
+    dial_fallback: Option<FallbackChain>,
+
+    /// The maximum number of events handled in a single call to [`Swarm::poll_next_event`]
+    /// before yielding to the executor, if any.
+    poll_budget: Option<NonZeroUsize>,
+
+    /// Whether [`Swarm::close_gracefully`] was called.
+    shutting_down: bool,
+
+    /// Dials that are retried on failure.
+    dial_retries: DialRetries,
+
+    /// Dials deferred because of the limits of concurrent dials.
+    dial_queue: DialQueue,
+
     /// Pending event to be delivered to connection handlers
     /// (or dropped if the peer disconnected) before the `behaviour`
     /// can be polled again.
     pending_handler_event: Option<(PeerId, PendingNotifyHandler, THandlerInEvent<TBehaviour>)>,
 
     pending_swarm_events: VecDeque<SwarmEvent<TBehaviour::ToSwarm>>,
+
+    /// Subscriptions to subsets of the events returned by the [`Swarm`].
+    subscriptions: Subscriptions<TBehaviour::ToSwarm>,
 }
 
 impl<TBehaviour> Unpin for Swarm<TBehaviour> where TBehaviour: NetworkBehaviour {}
@@ -377,10 +549,23 @@ where
             pool: Pool::new(local_peer_id, config.pool_config),
             behaviour,
             supported_protocols: Default::default(),
-            confirmed_external_addr: Default::default(),
+            external_addresses: ExternalAddresses::default()
+                .with_ranking(config.external_address_ranking),
             listened_addrs: HashMap::new(),
+            local_addresses: LocalAddresses::default(),
+            local_address_events: config.local_address_events,
+            listeners: Listeners::default(),
+            listener_events: config.listener_events,
+            connection_gater: config.connection_gater,
+            dial_ranker: config.dial_ranker,
+            dial_fallback: config.dial_fallback,
+            poll_budget: config.poll_budget,
+            shutting_down: false,
+            dial_retries: DialRetries::new(config.dial_retry_policy),
+            dial_queue: DialQueue::new(config.dial_limits),
             pending_handler_event: None,
             pending_swarm_events: VecDeque::default(),
+            subscriptions: Subscriptions::new(),
         }
     }
 
@@ -394,6 +579,24 @@ where
         }
     }
 
+    /// Returns the bandwidth used per remote peer and protocol, if enabled via
+    /// [`Config::with_bandwidth_accounting`].
+    pub fn bandwidth_accounting(&self) -> Option<&BandwidthAccounting> {
+        self.pool.bandwidth_accounting()
+    }
+
+    /// Returns the statistics of the streams per protocol, if enabled via
+    /// [`Config::with_stream_metrics`].
+    pub fn stream_metrics(&self) -> Option<&StreamMetrics> {
+        self.pool.stream_metrics()
+    }
+
+    /// Returns the policy keeping idle connections alive, if enabled via
+    /// [`Config::with_keep_alive_policy`].
+    pub fn keep_alive_policy(&self) -> Option<&KeepAlivePolicy> {
+        self.pool.keep_alive_policy()
+    }
+
     /// Starts listening on the given address.
     /// Returns an error if the address is not supported.
     ///
@@ -411,13 +614,80 @@ where
     /// Returns `true` if there was a listener with this ID, `false`
     /// otherwise.
     pub fn remove_listener(&mut self, listener_id: ListenerId) -> bool {
-        self.transport.remove_listener(listener_id)
+        if !self.transport.remove_listener(listener_id) {
+            return false;
+        }
+
+        self.listeners.on_remove(listener_id);
+        true
+    }
+
+    /// Lists the active listeners together with their state and the addresses they are bound
+    /// to.
+    pub fn listeners_info(&self) -> impl Iterator<Item = &ListenerInfo> {
+        self.listeners.iter()
+    }
+
+    /// Shuts down the [`Swarm`], e.g. before restarting a service.
+    ///
+    /// All listeners are removed and the behaviour is informed via
+    /// [`FromSwarm::ShutdownStarted`]. No new connections are established afterwards. All
+    /// connections are closed gracefully, allowing their handlers to flush outstanding streams in
+    /// [`ConnectionHandler::poll_close`].
+    ///
+    /// The [`Swarm`] is driven until all connections are closed, discarding its events. Resolves
+    /// to `true` once all connections are closed, or to `false` if connections were still open
+    /// when the `timeout` elapsed.
+    pub async fn close_gracefully(&mut self, timeout: Duration) -> bool {
+        if !self.shutting_down {
+            self.shutting_down = true;
+
+            let listener_ids = self
+                .listeners
+                .iter()
+                .map(ListenerInfo::id)
+                .collect::<Vec<_>>();
+            for listener_id in listener_ids {
+                self.remove_listener(listener_id);
+            }
+
+            self.behaviour.on_swarm_event(FromSwarm::ShutdownStarted);
+            self.dial_retries.clear();
+            self.dial_queue.clear();
+
+            let peers = self.pool.iter_connected().copied().collect::<Vec<_>>();
+            for peer_id in peers {
+                self.pool.disconnect(peer_id);
+            }
+        }
+
+        let mut deadline = futures_timer::Delay::new(timeout);
+        while self.pool.counters().num_connections() > 0 {
+            let timed_out = matches!(
+                futures::future::select(self.next(), &mut deadline).await,
+                futures::future::Either::Right(_)
+            );
+            if timed_out {
+                tracing::debug!(
+                    remaining=%self.pool.counters().num_connections(),
+                    "Timed out waiting for connections to close"
+                );
+                return false;
+            }
+        }
+
+        true
     }
 
     /// Dial a known or unknown peer.
     ///
     /// See also [`DialOpts`].
     ///
+    /// If the limits of [`Config::with_max_concurrent_dials`] or
+    /// [`Config::with_max_concurrent_dials_per_peer`] are reached, the dial is deferred and
+    /// reported as [`SwarmEvent::DialDeferred`]. Errors of deferred dials are only reported as
+    /// [`SwarmEvent::OutgoingConnectionError`].
+    ///
     /// ```
     /// # use libp2p_swarm::Swarm;
     /// # use libp2p_swarm::dial_opts::{DialOpts, PeerCondition};
@@ -448,6 +718,43 @@ where
         let condition = dial_opts.peer_condition();
         let connection_id = dial_opts.connection_id();
 
+        if self.shutting_down {
+            let error = DialError::Denied {
+                cause: ConnectionDenied::new(ShuttingDown),
+            };
+            self.behaviour
+                .on_swarm_event(FromSwarm::DialFailure(DialFailure {
+                    peer_id,
+                    error: &error,
+                    connection_id,
+                }));
+            return Err(error);
+        }
+
+        if dial_opts.abort_handle().is_aborted() {
+            let error = DialError::Aborted;
+            self.behaviour
+                .on_swarm_event(FromSwarm::DialFailure(DialFailure {
+                    peer_id,
+                    error: &error,
+                    connection_id,
+                }));
+            return Err(error);
+        }
+
+        let num_dialing = self.pool.counters().num_pending_outgoing() as usize;
+        let num_dialing_peer = peer_id.map_or(0, |peer| self.pool.num_dialing(peer));
+        if self.dial_queue.must_defer(num_dialing, num_dialing_peer) {
+            tracing::debug!(connection=%connection_id, "Deferring dial because of the limit of concurrent dials");
+            self.dial_queue.push(dial_opts);
+            self.pending_swarm_events
+                .push_back(SwarmEvent::DialDeferred {
+                    peer_id,
+                    connection_id,
+                });
+            return Ok(());
+        }
+
         let should_dial = match (condition, peer_id) {
             (_, None) => true,
             (PeerCondition::Always, _) => true,
@@ -515,6 +822,10 @@ where
                     && unique_addresses.insert(addr.clone())
             });
 
+            if let Some(ranker) = self.dial_ranker.as_mut() {
+                ranker.rank(peer_id, &mut addresses_from_opts);
+            }
+
             if addresses_from_opts.is_empty() {
                 let error = DialError::NoAddresses;
                 self.behaviour
@@ -529,43 +840,59 @@ where
             addresses_from_opts
         };
 
-        let dials = addresses
+        if let Some(gater) = self.connection_gater.as_mut() {
+            if let Err(cause) = gater.intercept_dial(connection_id, peer_id, &addresses) {
+                let error = DialError::Denied { cause };
+                self.behaviour
+                    .on_swarm_event(FromSwarm::DialFailure(DialFailure {
+                        peer_id,
+                        error: &error,
+                        connection_id,
+                    }));
+                return Err(error);
+            }
+        }
+
+        let stages = match self.dial_fallback.as_ref() {
+            Some(chain) => chain.group(addresses),
+            None => vec![addresses],
+        };
+        let dials = stages
             .into_iter()
-            .map(|a| match peer_id.map_or(Ok(a.clone()), |p| a.with_p2p(p)) {
-                Ok(address) => {
-                    let dial = self.transport.dial(
-                        address.clone(),
-                        transport::DialOpts {
-                            role: dial_opts.role_override(),
-                            port_use: dial_opts.port_use(),
-                        },
-                    );
-                    let span = tracing::debug_span!(parent: tracing::Span::none(), "Transport::dial", %address);
-                    span.follows_from(tracing::Span::current());
-                    match dial {
-                        Ok(fut) => fut
-                            .map(|r| (address, r.map_err(TransportError::Other)))
-                            .instrument(span)
-                            .boxed(),
-                        Err(err) => futures::future::ready((address, Err(err))).boxed(),
-                    }
-                }
-                Err(address) => futures::future::ready((
-                    address.clone(),
-                    Err(TransportError::MultiaddrNotSupported(address)),
-                ))
-                .boxed(),
+            .map(|stage| {
+                stage
+                    .into_iter()
+                    .map(|a| match peer_id.map_or(Ok(a.clone()), |p| a.with_p2p(p)) {
+                        Ok(address) => {
+                            let dial = self.transport.dial(
+                                address.clone(),
+                                transport::DialOpts {
+                                    role: dial_opts.role_override(),
+                                    port_use: dial_opts.port_use(),
+                                },
+                            );
+                            let span = tracing::debug_span!(parent: tracing::Span::none(), "Transport::dial", %address);
+                            span.follows_from(tracing::Span::current());
+                            match dial {
+                                Ok(fut) => fut
+                                    .map(|r| (address, r.map_err(TransportError::Other)))
+                                    .instrument(span)
+                                    .boxed(),
+                                Err(err) => futures::future::ready((address, Err(err))).boxed(),
+                            }
+                        }
+                        Err(address) => futures::future::ready((
+                            address.clone(),
+                            Err(TransportError::MultiaddrNotSupported(address)),
+                        ))
+                        .boxed(),
+                    })
+                    .collect()
             })
             .collect();
 
-        self.pool.add_outgoing(
-            dials,
-            peer_id,
-            dial_opts.role_override(),
-            dial_opts.port_use(),
-            dial_opts.dial_concurrency_override(),
-            connection_id,
-        );
+        self.pool.add_outgoing(dials, &dial_opts);
+        self.dial_retries.on_dial(dial_opts);
 
         Ok(())
     }
@@ -580,9 +907,41 @@ where
         &self.local_peer_id
     }
 
-    /// List all **confirmed** external address for the local node.
+    /// List all **confirmed** external address for the local node, in order of their rank, see
+    /// [`Config::with_external_address_ranking`].
     pub fn external_addresses(&self) -> impl Iterator<Item = &Multiaddr> {
-        self.confirmed_external_addr.iter()
+        self.external_addresses.iter()
+    }
+
+    /// List the scores of all **confirmed** external addresses for the local node, in order of
+    /// their rank.
+    ///
+    /// Each [`ScoredAddress`] states the most trusted [`ExternalAddressSource`] the address was
+    /// confirmed by.
+    pub fn scored_external_addresses(&self) -> impl Iterator<Item = &ScoredAddress> {
+        self.external_addresses.scored()
+    }
+
+    /// Lists all known addresses of the local node together with where they were learned from.
+    ///
+    /// This combines the addresses passed to [`Swarm::listen_on`], the addresses the listeners
+    /// are bound to, the external address candidates reported by the [`NetworkBehaviour`] and the
+    /// confirmed external addresses. An address known from several sources is listed once.
+    pub fn local_addresses(&self) -> impl Iterator<Item = (&Multiaddr, &[AddressSource])> {
+        self.local_addresses.iter()
+    }
+
+    fn report_local_address_changes(
+        &mut self,
+        changes: impl IntoIterator<Item = (Multiaddr, Vec<AddressSource>)>,
+    ) {
+        for (address, sources) in changes {
+            tracing::trace!(%address, ?sources, "Local address changed");
+            if self.local_address_events {
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::LocalAddressChanged { address, sources });
+            }
+        }
     }
 
     fn add_listener(&mut self, opts: ListenOpts) -> Result<(), TransportError<io::Error>> {
@@ -599,10 +958,15 @@ where
             return Err(e);
         }
 
+        self.listeners.add(listener_id, addr.clone());
         self.behaviour
             .on_swarm_event(FromSwarm::NewListener(behaviour::NewListener {
                 listener_id,
             }));
+        let changes = self
+            .local_addresses
+            .add(addr, AddressSource::Configured(listener_id));
+        self.report_local_address_changes(changes);
 
         Ok(())
     }
@@ -611,12 +975,37 @@ where
     ///
     /// This function should only be called with addresses that are guaranteed to be reachable.
     /// The address is broadcast to all [`NetworkBehaviour`]s via [`FromSwarm::ExternalAddrConfirmed`].
+    ///
+    /// The address is ranked as [`ExternalAddressSource::Manual`], see
+    /// [`Swarm::add_external_address_with_source`] to add it from another source.
     pub fn add_external_address(&mut self, a: Multiaddr) {
+        self.add_external_address_with_source(a, ExternalAddressSource::Manual);
+    }
+
+    /// Add a **confirmed** external address for the local node, stating how it was confirmed.
+    ///
+    /// Useful if the address was confirmed outside of the [`NetworkBehaviour`], e.g. by a port
+    /// mapping created by the application. The `source` is passed on to all
+    /// [`NetworkBehaviour`]s via [`FromSwarm::ExternalAddrConfirmed`].
+    pub fn add_external_address_with_source(
+        &mut self,
+        a: Multiaddr,
+        source: ExternalAddressSource,
+    ) {
         self.behaviour
             .on_swarm_event(FromSwarm::ExternalAddrConfirmed(ExternalAddrConfirmed {
                 addr: &a,
+                source,
             }));
-        self.confirmed_external_addr.insert(a);
+        let changes = self.local_addresses.add(&a, AddressSource::External);
+        self.report_local_address_changes(changes);
+        self.external_addresses.confirm(&a, source);
+    }
+
+    fn confirm_external_address(&mut self, address: Multiaddr, source: ExternalAddressSource) {
+        self.add_external_address_with_source(address.clone(), source);
+        self.pending_swarm_events
+            .push_back(SwarmEvent::ExternalAddrConfirmed { address, source });
     }
 
     /// Remove an external address for the local node.
@@ -625,7 +1014,9 @@ where
     pub fn remove_external_address(&mut self, addr: &Multiaddr) {
         self.behaviour
             .on_swarm_event(FromSwarm::ExternalAddrExpired(ExternalAddrExpired { addr }));
-        self.confirmed_external_addr.remove(addr);
+        let change = self.local_addresses.remove(addr, AddressSource::External);
+        self.report_local_address_changes(change);
+        self.external_addresses.remove(addr);
     }
 
     /// Add a new external address of a remote peer.
@@ -657,6 +1048,33 @@ where
         }
     }
 
+    /// Disconnects a peer like [`Swarm::disconnect_peer_id`], notifying it of the reason first.
+    ///
+    /// Before closing, each established connection sends the application-defined `code` and
+    /// `message` to the remote, which reports them in [`SwarmEvent::ConnectionClosed`]. Closing
+    /// is delayed until the remote received the reason, at most by a few seconds. Remotes not
+    /// supporting [`disconnect::PROTOCOL_NAME`], see [`Config::with_disconnect_reasons`], are
+    /// disconnected without notice.
+    ///
+    /// Returns `Ok(())` if there was one or more established connections to the peer.
+    #[allow(clippy::result_unit_err)]
+    pub fn disconnect_peer_with_reason(
+        &mut self,
+        peer_id: PeerId,
+        code: u32,
+        message: impl Into<String>,
+    ) -> Result<(), ()> {
+        let was_connected = self.pool.is_connected(peer_id);
+        self.pool
+            .disconnect_with_reason(peer_id, Some(DisconnectReason::new(code, message)));
+
+        if was_connected {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
     /// Attempt to gracefully close a connection.
     ///
     /// Closing a connection is asynchronous but this function will return immediately.
@@ -675,6 +1093,79 @@ where
         false
     }
 
+    /// Aborts a pending dial, closing the sockets of all its ongoing connection attempts.
+    ///
+    /// The dial fails with [`DialError::Aborted`], reported as
+    /// [`SwarmEvent::OutgoingConnectionError`]. Aborted dials are not retried.
+    /// To abort a dial without access to the [`Swarm`], use the
+    /// [`DialAbortHandle`](dial_opts::DialAbortHandle) of its [`DialOpts`].
+    ///
+    /// Returns `false` if there is no pending dial with the given ID.
+    pub fn abort_dial(&mut self, connection_id: ConnectionId) -> bool {
+        if let Some(opts) = self.dial_queue.remove(connection_id) {
+            let peer_id = opts.get_peer_id();
+            let error = DialError::Aborted;
+            self.behaviour
+                .on_swarm_event(FromSwarm::DialFailure(DialFailure {
+                    peer_id,
+                    error: &error,
+                    connection_id,
+                }));
+            self.pending_swarm_events
+                .push_back(SwarmEvent::OutgoingConnectionError {
+                    peer_id,
+                    connection_id,
+                    error,
+                });
+            return true;
+        }
+
+        self.pool.abort_dial(connection_id)
+    }
+
+    /// Protects the connections to `peer_id` from being pruned by the [`PruningPolicy`] until
+    /// `tag` is removed via [`Swarm::unprotect_peer`].
+    ///
+    /// A peer may be protected with multiple tags, e.g. by different components. It stays
+    /// protected until all of them are removed. Protection does not affect
+    /// [`Swarm::disconnect_peer_id`] or the keep-alive of connections.
+    pub fn protect_peer(&mut self, peer_id: PeerId, tag: &str) {
+        self.pool.protect_peer(peer_id, tag)
+    }
+
+    /// Removes the protection `tag` from `peer_id`, see [`Swarm::protect_peer`].
+    ///
+    /// Returns `false` if the peer wasn't protected with the given tag.
+    pub fn unprotect_peer(&mut self, peer_id: PeerId, tag: &str) -> bool {
+        self.pool.unprotect_peer(peer_id, tag)
+    }
+
+    /// Returns whether the connections to `peer_id` are protected from pruning.
+    pub fn is_peer_protected(&self, peer_id: &PeerId) -> bool {
+        self.pool.is_peer_protected(peer_id)
+    }
+
+    /// Subscribes to a subset of the events of the [`Swarm`].
+    ///
+    /// Every event is passed to `filter` before it is returned by the [`Swarm`]. The events it
+    /// maps to are delivered over a channel with the given capacity, see
+    /// [`subscription`] for common filters. Events are only delivered while the [`Swarm`] is
+    /// polled and dropped for this subscription if its channel is full.
+    ///
+    /// ```
+    /// # use libp2p_swarm::{dummy, subscription, Swarm};
+    /// # fn subscribe(swarm: &mut Swarm<dummy::Behaviour>) {
+    /// let connections = swarm.subscribe(16, subscription::connection_lifecycle);
+    /// # }
+    /// ```
+    pub fn subscribe<T, F>(&mut self, capacity: usize, filter: F) -> mpsc::Receiver<T>
+    where
+        T: Send + 'static,
+        F: FnMut(&SwarmEvent<TBehaviour::ToSwarm>) -> Option<T> + Send + 'static,
+    {
+        self.subscriptions.add(capacity, filter)
+    }
+
     /// Checks whether there is an established connection to a peer.
     pub fn is_connected(&self, peer_id: &PeerId) -> bool {
         self.pool.is_connected(*peer_id)
@@ -685,6 +1176,31 @@ where
         self.pool.iter_connected()
     }
 
+    /// Returns the data attached to an established connection.
+    ///
+    /// The data of a closed connection is reported in [`SwarmEvent::ConnectionClosed`] and
+    /// [`FromSwarm::ConnectionClosed`].
+    pub fn connection_extensions(
+        &self,
+        connection_id: ConnectionId,
+    ) -> Option<&ConnectionExtensions> {
+        self.pool.connection_extensions(connection_id)
+    }
+
+    /// Returns the data attached to an established connection for modification.
+    ///
+    /// Applications can use this to attach their own typed data to a connection, e.g. when handling
+    /// [`SwarmEvent::ConnectionEstablished`], without having to track the lifetime of the connection
+    /// themselves. See [`Swarm::connection_extensions`].
+    pub fn connection_extensions_mut(
+        &mut self,
+        connection_id: ConnectionId,
+    ) -> Option<&mut ConnectionExtensions> {
+        self.pool
+            .get_established(connection_id)
+            .map(|connection| connection.extensions_mut())
+    }
+
     /// Returns a reference to the provided [`NetworkBehaviour`].
     pub fn behaviour(&self) -> &TBehaviour {
         &self.behaviour
@@ -705,78 +1221,67 @@ where
                 concurrent_dial_errors,
                 established_in,
             } => {
-                let handler = match endpoint.clone() {
+                self.dial_retries.on_connection_established(id);
+
+                if let Some(ranker) = self.dial_ranker.as_mut() {
+                    for (address, _) in concurrent_dial_errors.iter().flatten() {
+                        ranker.on_dial_failure(Some(peer_id), address);
+                    }
+                    if let ConnectedPoint::Dialer { address, .. } = &endpoint {
+                        ranker.on_dial_success(peer_id, address);
+                    }
+                }
+
+                if self.shutting_down {
+                    let cause = ConnectionDenied::new(ShuttingDown);
+                    self.deny_established_connection(id, peer_id, endpoint, cause);
+                    return;
+                }
+
+                if let Some(gater) = self.connection_gater.as_mut() {
+                    if let Err(cause) = gater.intercept_secured(id, peer_id, &endpoint) {
+                        self.deny_established_connection(id, peer_id, endpoint, cause);
+                        return;
+                    }
+                }
+
+                let handler = match &endpoint {
                     ConnectedPoint::Dialer {
                         address,
                         role_override,
                         port_use,
-                    } => {
-                        match self.behaviour.handle_established_outbound_connection(
-                            id,
-                            peer_id,
-                            &address,
-                            role_override,
-                            port_use,
-                        ) {
-                            Ok(handler) => handler,
-                            Err(cause) => {
-                                let dial_error = DialError::Denied { cause };
-                                self.behaviour.on_swarm_event(FromSwarm::DialFailure(
-                                    DialFailure {
-                                        connection_id: id,
-                                        error: &dial_error,
-                                        peer_id: Some(peer_id),
-                                    },
-                                ));
-
-                                self.pending_swarm_events.push_back(
-                                    SwarmEvent::OutgoingConnectionError {
-                                        peer_id: Some(peer_id),
-                                        connection_id: id,
-                                        error: dial_error,
-                                    },
-                                );
-                                return;
-                            }
-                        }
-                    }
+                    } => self.behaviour.handle_established_outbound_connection(
+                        id,
+                        peer_id,
+                        address,
+                        *role_override,
+                        *port_use,
+                    ),
                     ConnectedPoint::Listener {
                         local_addr,
                         send_back_addr,
-                    } => {
-                        match self.behaviour.handle_established_inbound_connection(
-                            id,
-                            peer_id,
-                            &local_addr,
-                            &send_back_addr,
-                        ) {
-                            Ok(handler) => handler,
-                            Err(cause) => {
-                                let listen_error = ListenError::Denied { cause };
-                                self.behaviour.on_swarm_event(FromSwarm::ListenFailure(
-                                    ListenFailure {
-                                        local_addr: &local_addr,
-                                        send_back_addr: &send_back_addr,
-                                        error: &listen_error,
-                                        connection_id: id,
-                                        peer_id: Some(peer_id),
-                                    },
-                                ));
-
-                                self.pending_swarm_events.push_back(
-                                    SwarmEvent::IncomingConnectionError {
-                                        connection_id: id,
-                                        send_back_addr,
-                                        local_addr,
-                                        error: listen_error,
-                                    },
-                                );
-                                return;
-                            }
-                        }
+                    } => self.behaviour.handle_established_inbound_connection(
+                        id,
+                        peer_id,
+                        local_addr,
+                        send_back_addr,
+                    ),
+                };
+                let handler = match handler {
+                    Ok(handler) => handler,
+                    Err(cause) => {
+                        self.deny_established_connection(id, peer_id, endpoint, cause);
+                        return;
                     }
                 };
 
+                if let Some(gater) = self.connection_gater.as_mut() {
+                    if let Err(cause) = gater.intercept_upgraded(id, peer_id, &endpoint) {
+                        self.deny_established_connection(id, peer_id, endpoint, cause);
+                        return;
+                    }
+                }
+
                 let supported_protocols = handler
                     .listen_protocol()
                     .upgrade()
@@ -838,6 +1343,14 @@ where
             } => {
                 let error = error.into();
 
+                if let (Some(ranker), DialError::Transport(errors)) =
+                    (self.dial_ranker.as_mut(), &error)
+                {
+                    for (address, _) in errors {
+                        ranker.on_dial_failure(peer, address);
+                    }
+                }
+
                 self.behaviour
                     .on_swarm_event(FromSwarm::DialFailure(DialFailure {
                         peer_id: peer,
@@ -851,12 +1364,25 @@ where
                     tracing::debug!("Connection attempt to unknown peer failed with {:?}", error);
                 }
 
+                let retry = self.dial_retries.on_dial_failure(connection_id, &error);
+
                 self.pending_swarm_events
                     .push_back(SwarmEvent::OutgoingConnectionError {
                         peer_id: peer,
                         connection_id,
                         error,
                     });
+
+                if let (Some(peer_id), Some((attempt, backoff))) = (peer, retry) {
+                    tracing::debug!(%peer_id, %attempt, ?backoff, "Scheduling dial retry");
+                    self.pending_swarm_events
+                        .push_back(SwarmEvent::DialRetryScheduled {
+                            peer_id,
+                            connection_id,
+                            attempt,
+                            backoff,
+                        });
+                }
             }
             PoolEvent::PendingInboundConnectionError {
                 id,
@@ -887,6 +1413,8 @@ where
                 id,
                 connected,
                 error,
+                reason,
+                extensions,
                 remaining_established_connection_ids,
                 ..
             } => {
@@ -916,6 +1444,7 @@ where
                         endpoint: &endpoint,
                         cause: error.as_ref(),
                         remaining_established: num_established as usize,
+                        extensions: &extensions,
                     }));
                 self.pending_swarm_events
                     .push_back(SwarmEvent::ConnectionClosed {
@@ -924,6 +1453,8 @@ where
                         endpoint,
                         cause: error,
                         num_established,
+                        reason,
+                        extensions,
                     });
             }
             PoolEvent::ConnectionEvent { peer_id, id, event } => {
@@ -944,6 +1475,69 @@ where
                         new: &new_endpoint,
                     }));
             }
+            PoolEvent::ConnectionIdle {
+                id,
+                peer_id,
+                closes_in,
+            } => {
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::ConnectionIdle {
+                        peer_id,
+                        connection_id: id,
+                        closes_in,
+                    });
+            }
+        }
+    }
+
+    /// Reports an established connection that was denied by the behaviour or the
+    /// [`ConnectionGater`].
+    fn deny_established_connection(
+        &mut self,
+        id: ConnectionId,
+        peer_id: PeerId,
+        endpoint: ConnectedPoint,
+        cause: ConnectionDenied,
+    ) {
+        match endpoint {
+            ConnectedPoint::Dialer { .. } => {
+                let dial_error = DialError::Denied { cause };
+                self.behaviour
+                    .on_swarm_event(FromSwarm::DialFailure(DialFailure {
+                        connection_id: id,
+                        error: &dial_error,
+                        peer_id: Some(peer_id),
+                    }));
+
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::OutgoingConnectionError {
+                        peer_id: Some(peer_id),
+                        connection_id: id,
+                        error: dial_error,
+                    });
+            }
+            ConnectedPoint::Listener {
+                local_addr,
+                send_back_addr,
+            } => {
+                let listen_error = ListenError::Denied { cause };
+                self.behaviour
+                    .on_swarm_event(FromSwarm::ListenFailure(ListenFailure {
+                        local_addr: &local_addr,
+                        send_back_addr: &send_back_addr,
+                        error: &listen_error,
+                        connection_id: id,
+                        peer_id: Some(peer_id),
+                    }));
+
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::IncomingConnectionError {
+                        connection_id: id,
+                        send_back_addr,
+                        local_addr,
+                        error: listen_error,
+                    });
+            }
         }
     }
 
@@ -963,11 +1557,20 @@ where
             } => {
                 let connection_id = ConnectionId::next();
 
-                match self.behaviour.handle_pending_inbound_connection(
-                    connection_id,
-                    &local_addr,
-                    &send_back_addr,
-                ) {
+                let accepted = match self.connection_gater.as_mut() {
+                    _ if self.shutting_down => Err(ConnectionDenied::new(ShuttingDown)),
+                    Some(gater) => {
+                        gater.intercept_accept(connection_id, &local_addr, &send_back_addr)
+                    }
+                    None => Ok(()),
+                };
+                match accepted.and_then(|()| {
+                    self.behaviour.handle_pending_inbound_connection(
+                        connection_id,
+                        &local_addr,
+                        &send_back_addr,
+                    )
+                }) {
                     Ok(()) => {}
                     Err(cause) => {
                         let listen_error = ListenError::Denied { cause };
@@ -1026,11 +1629,23 @@ where
                         listener_id,
                         addr: &listen_addr,
                     }));
+                let changes = self
+                    .local_addresses
+                    .add(&listen_addr, AddressSource::Listener(listener_id));
+                let bound = self.listeners.add_address(listener_id, &listen_addr);
                 self.pending_swarm_events
                     .push_back(SwarmEvent::NewListenAddr {
                         listener_id,
                         address: listen_addr,
-                    })
+                    });
+                if let (true, Some(address)) = (self.listener_events, bound) {
+                    self.pending_swarm_events
+                        .push_back(SwarmEvent::ListenAddrBound {
+                            listener_id,
+                            address,
+                        });
+                }
+                self.report_local_address_changes(changes);
             }
             TransportEvent::AddressExpired {
                 listener_id,
@@ -1049,11 +1664,24 @@ where
                         listener_id,
                         addr: &listen_addr,
                     }));
+                let change = self
+                    .local_addresses
+                    .remove(&listen_addr, AddressSource::Listener(listener_id));
+                let removed = self.listeners.remove_address(listener_id, &listen_addr);
                 self.pending_swarm_events
                     .push_back(SwarmEvent::ExpiredListenAddr {
                         listener_id,
                         address: listen_addr,
-                    })
+                    });
+                if let (true, Some(address)) = (self.listener_events, removed) {
+                    self.pending_swarm_events
+                        .push_back(SwarmEvent::ListenAddrRemoved {
+                            listener_id,
+                            address,
+                            reason: ExpiryReason::Expired,
+                        });
+                }
+                self.report_local_address_changes(change);
             }
             TransportEvent::ListenerClosed {
                 listener_id,
@@ -1075,14 +1703,31 @@ where
                         listener_id,
                         reason: reason.as_ref().copied(),
                     }));
+                let (removed, expiry_reason) =
+                    self.listeners.on_closed(listener_id, reason.is_err());
                 self.pending_swarm_events
                     .push_back(SwarmEvent::ListenerClosed {
                         listener_id,
                         addresses: addrs.to_vec(),
                         reason,
-                    })
+                    });
+                if self.listener_events {
+                    self.pending_swarm_events
+                        .extend(
+                            removed
+                                .into_iter()
+                                .map(|address| SwarmEvent::ListenAddrRemoved {
+                                    listener_id,
+                                    address,
+                                    reason: expiry_reason,
+                                }),
+                        );
+                }
+                let changes = self.local_addresses.remove_listener(listener_id);
+                self.report_local_address_changes(changes);
             }
             TransportEvent::ListenerError { listener_id, error } => {
+                self.listeners.on_error(listener_id);
                 self.behaviour
                     .on_swarm_event(FromSwarm::ListenerError(ListenerError {
                         listener_id,
@@ -1094,6 +1739,67 @@ where
         }
     }
 
+    fn handle_dial_retry(&mut self, opts: DialOpts) {
+        let peer_id = opts
+            .get_peer_id()
+            .expect("only dials of known peers are retried");
+        let connection_id = opts.connection_id();
+        let attempt = opts.retry_attempt();
+
+        if self.pool.is_connected(peer_id) {
+            tracing::debug!(%peer_id, "Dropping dial retry because the peer is connected");
+            return;
+        }
+        if opts.abort_handle().is_aborted() {
+            tracing::debug!(%peer_id, "Dropping dial retry because the dial was aborted");
+            return;
+        }
+
+        match self.dial(opts) {
+            Ok(()) => self.pending_swarm_events.push_back(SwarmEvent::DialRetry {
+                peer_id,
+                connection_id,
+                attempt,
+            }),
+            Err(error) => {
+                self.pending_swarm_events
+                    .push_back(SwarmEvent::OutgoingConnectionError {
+                        peer_id: Some(peer_id),
+                        connection_id,
+                        error,
+                    })
+            }
+        }
+    }
+
+    /// Starts the deferred dial of the highest [`DialPriority`](dial_opts::DialPriority) that no
+    /// longer exceeds the limits of concurrent dials.
+    ///
+    /// Returns `false` if there is no such dial.
+    fn start_deferred_dial(&mut self) -> bool {
+        let num_dialing = self.pool.counters().num_pending_outgoing() as usize;
+        let pool = &self.pool;
+        let Some(opts) = self
+            .dial_queue
+            .pop(num_dialing, |peer| pool.num_dialing(peer))
+        else {
+            return false;
+        };
+
+        let peer_id = opts.get_peer_id();
+        let connection_id = opts.connection_id();
+        if let Err(error) = self.dial(opts) {
+            self.pending_swarm_events
+                .push_back(SwarmEvent::OutgoingConnectionError {
+                    peer_id,
+                    connection_id,
+                    error,
+                });
+        }
+
+        true
+    }
+
     fn handle_behaviour_event(
         &mut self,
         event: ToSwarm<TBehaviour::ToSwarm, THandlerInEvent<TBehaviour>>,
@@ -1144,13 +1850,17 @@ where
                     .on_swarm_event(FromSwarm::NewExternalAddrCandidate(
                         NewExternalAddrCandidate { addr: &addr },
                     ));
+                let changes = self.local_addresses.add(&addr, AddressSource::Observed);
+                self.external_addresses.observe(&addr);
                 self.pending_swarm_events
                     .push_back(SwarmEvent::NewExternalAddrCandidate { address: addr });
+                self.report_local_address_changes(changes);
             }
-            ToSwarm::ExternalAddrConfirmed(addr) => {
-                self.add_external_address(addr.clone());
-                self.pending_swarm_events
-                    .push_back(SwarmEvent::ExternalAddrConfirmed { address: addr });
+            ToSwarm::ExternalAddrConfirmed(address) => {
+                self.confirm_external_address(address, ExternalAddressSource::Confirmed);
+            }
+            ToSwarm::ExternalAddrConfirmedWithSource { address, source } => {
+                self.confirm_external_address(address, source);
             }
             ToSwarm::ExternalAddrExpired(addr) => {
                 self.remove_external_address(&addr);
@@ -1170,6 +1880,20 @@ where
                     self.pool.disconnect(peer_id);
                 }
             },
+            ToSwarm::TagConnection {
+                connection_id,
+                key,
+                value,
+            } => {
+                if let Some(connection) = self.pool.get_established(connection_id) {
+                    connection.extensions_mut().tags_mut().insert(key, value);
+                }
+            }
+            ToSwarm::UntagConnection { connection_id, key } => {
+                if let Some(connection) = self.pool.get_established(connection_id) {
+                    connection.extensions_mut().tags_mut().remove(&key);
+                }
+            }
             ToSwarm::NewExternalAddrOfPeer { peer_id, address } => {
                 self.behaviour
                     .on_swarm_event(FromSwarm::NewExternalAddrOfPeer(NewExternalAddrOfPeer {
@@ -1203,11 +1927,27 @@ where
         // (1) is polled before (2) to prioritize local work over work coming from a remote.
         //
         // (2) is polled before (3) to prioritize existing connections over upgrading new incoming connections.
+        let mut remaining_budget = this.poll_budget.map(NonZeroUsize::get);
+
         loop {
             if let Some(swarm_event) = this.pending_swarm_events.pop_front() {
+                this.subscriptions.deliver(&swarm_event);
+
                 return Poll::Ready(swarm_event);
             }
 
+            // Yield once the budget is used up, such that a burst of events handled internally
+            // doesn't starve other tasks of the executor.
+            match remaining_budget.as_mut() {
+                Some(0) => {
+                    tracing::trace!("Poll budget exhausted, yielding to the executor");
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+                Some(remaining) => *remaining -= 1,
+                None => {}
+            }
+
             match this.pending_handler_event.take() {
                 // Try to deliver the pending event emitted by the [`NetworkBehaviour`] in the previous
                 // iteration to the connection handler(s).
@@ -1253,6 +1993,20 @@ where
                 }
             }
 
+            // Start the deferred dials that no longer exceed the limits.
+            if this.start_deferred_dial() {
+                continue;
+            }
+
+            // Redial the peers whose retry backoff elapsed.
+            match this.dial_retries.poll(cx) {
+                Poll::Pending => {}
+                Poll::Ready(opts) => {
+                    this.handle_dial_retry(opts);
+                    continue;
+                }
+            }
+
             // Poll the listener(s) for new connections.
             match Pin::new(&mut this.transport).poll(cx) {
                 Poll::Pending => {}
@@ -1383,6 +2137,15 @@ where
 
 pub struct Config {
     pool_config: PoolConfig,
+    local_address_events: bool,
+    listener_events: bool,
+    connection_gater: Option<Box<dyn ConnectionGater>>,
+    dial_ranker: Option<Box<dyn DialRanker>>,
+    dial_fallback: Option<FallbackChain>,
+    poll_budget: Option<NonZeroUsize>,
+    dial_retry_policy: Option<RetryPolicy>,
+    dial_limits: DialLimits,
+    external_address_ranking: AddressRanking,
 }
 
 impl Config {
@@ -1391,6 +2154,15 @@ impl Config {
     pub fn with_executor(executor: impl Executor + Send + 'static) -> Self {
         Self {
             pool_config: PoolConfig::new(Some(Box::new(executor))),
+            local_address_events: false,
+            listener_events: false,
+            connection_gater: None,
+            dial_ranker: None,
+            dial_fallback: None,
+            poll_budget: None,
+            dial_retry_policy: None,
+            dial_limits: DialLimits::default(),
+            external_address_ranking: behaviour::rank_by_source_and_recency,
         }
     }
 
@@ -1399,6 +2171,15 @@ impl Config {
     pub fn without_executor() -> Self {
         Self {
             pool_config: PoolConfig::new(None),
+            local_address_events: false,
+            listener_events: false,
+            connection_gater: None,
+            dial_ranker: None,
+            dial_fallback: None,
+            poll_budget: None,
+            dial_retry_policy: None,
+            dial_limits: DialLimits::default(),
+            external_address_ranking: behaviour::rank_by_source_and_recency,
         }
     }
 
@@ -1492,21 +2273,199 @@ impl Config {
     /// connection. New inbound streams exceeding the limit are dropped and thus
     /// reset.
     ///
-    /// Note: This only enforces a limit on the number of concurrently
-    /// negotiating inbound streams. The total number of inbound streams on a
-    /// connection is the sum of negotiating and negotiated streams. A limit on
-    /// the total number of streams can be enforced at the
-    /// [`StreamMuxerBox`] level.
-    pub fn with_max_negotiating_inbound_streams(mut self, v: usize) -> Self {
-        self.pool_config = self.pool_config.with_max_negotiating_inbound_streams(v);
+    /// Note: This only enforces a limit on the number of concurrently
+    /// negotiating inbound streams. The total number of inbound streams on a
+    /// connection is the sum of negotiating and negotiated streams. A limit on
+    /// the total number of streams can be enforced at the
+    /// [`StreamMuxerBox`] level.
+    pub fn with_max_negotiating_inbound_streams(mut self, v: usize) -> Self {
+        self.pool_config = self.pool_config.with_max_negotiating_inbound_streams(v);
+        self
+    }
+
+    /// How long to keep a connection alive once it is idling.
+    ///
+    /// Defaults to 0.
+    pub fn with_idle_connection_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_config.idle_connection_timeout = timeout;
+        self
+    }
+
+    /// Attributes the bytes sent and received on streams to the remote peer and the negotiated
+    /// protocol in the given [`BandwidthAccounting`].
+    ///
+    /// The usage can be queried via a clone of `accounting` or [`Swarm::bandwidth_accounting`].
+    /// Disabled by default.
+    pub fn with_bandwidth_accounting(mut self, accounting: BandwidthAccounting) -> Self {
+        self.pool_config.bandwidth_accounting = Some(accounting);
+        self
+    }
+
+    /// Tracks the streams of all connections by negotiated protocol in the given
+    /// [`StreamMetrics`], i.e. the number of opened and closed streams, failed negotiations and
+    /// the lifetimes of streams.
+    ///
+    /// The statistics can be queried via a clone of `metrics` or [`Swarm::stream_metrics`].
+    /// Disabled by default.
+    pub fn with_stream_metrics(mut self, metrics: StreamMetrics) -> Self {
+        self.pool_config.stream_metrics = Some(metrics);
+        self
+    }
+
+    /// Limits the rate at which data is read from the streams of each remote peer, per protocol
+    /// and across all protocols, according to the given [`StreamRateLimiter`].
+    ///
+    /// Disabled by default.
+    pub fn with_stream_rate_limiter(mut self, limiter: StreamRateLimiter) -> Self {
+        self.pool_config.stream_rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Decides how long idle connections are kept alive according to the given
+    /// [`KeepAlivePolicy`], on top of [`Config::with_idle_connection_timeout`].
+    ///
+    /// Connections can be pinned via a clone of `policy` or [`Swarm::keep_alive_policy`] and
+    /// [`SwarmEvent::ConnectionIdle`] is reported before idle connections are closed.
+    /// Disabled by default.
+    pub fn with_keep_alive_policy(mut self, policy: KeepAlivePolicy) -> Self {
+        self.pool_config.keep_alive_policy = Some(policy);
+        self
+    }
+
+    /// Accepts the [`DisconnectReason`] remotes send via [`Swarm::disconnect_peer_with_reason`]
+    /// before closing a connection, reporting it in [`SwarmEvent::ConnectionClosed`].
+    ///
+    /// Disabled by default, i.e. [`disconnect::PROTOCOL_NAME`] is not negotiated on inbound
+    /// streams.
+    pub fn with_disconnect_reasons(mut self) -> Self {
+        self.pool_config.accept_disconnect_reasons = true;
+        self
+    }
+
+    /// Bounds the protocol negotiation of new streams according to the given
+    /// [`NegotiationTimeouts`], per connection and per protocol.
+    ///
+    /// Streams exceeding the timeout fail with [`StreamUpgradeError::NegotiationTimeout`].
+    /// Disabled by default, i.e. only the timeout of the [`SubstreamProtocol`] applies.
+    pub fn with_negotiation_timeouts(mut self, timeouts: NegotiationTimeouts) -> Self {
+        self.pool_config.negotiation_timeouts = Some(timeouts);
+        self
+    }
+
+    /// Closes idle and least-recently-used connections according to the given [`PruningPolicy`],
+    /// complementing hard connection limits and [`Config::with_idle_connection_timeout`].
+    ///
+    /// Connections to peers protected via [`Swarm::protect_peer`] and connections carrying a tag
+    /// given to [`PruningPolicy::with_protected_tag`] are never pruned.
+    /// Disabled by default.
+    pub fn with_pruning_policy(mut self, policy: PruningPolicy) -> Self {
+        self.pool_config.pruning_policy = Some(policy);
+        self
+    }
+
+    /// Whether to report changes of [`Swarm::local_addresses`] as
+    /// [`SwarmEvent::LocalAddressChanged`].
+    ///
+    /// Defaults to `false`.
+    pub fn with_local_address_events(mut self, enabled: bool) -> Self {
+        self.local_address_events = enabled;
+        self
+    }
+
+    /// Whether to report changes of the addresses of [`Swarm::listeners_info`] as
+    /// [`SwarmEvent::ListenAddrBound`] and [`SwarmEvent::ListenAddrRemoved`], including the
+    /// transport and socket of each address and why it was removed.
+    ///
+    /// Defaults to `false`.
+    pub fn with_listener_events(mut self, enabled: bool) -> Self {
+        self.listener_events = enabled;
+        self
+    }
+
+    /// Sets the [`ConnectionGater`] consulted before the [`NetworkBehaviour`] whether to
+    /// establish a connection.
+    pub fn with_connection_gater(mut self, gater: impl ConnectionGater) -> Self {
+        self.connection_gater = Some(Box::new(gater));
+        self
+    }
+
+    /// Sets the [`DialRanker`] ordering and filtering the addresses of outbound connections
+    /// after the [`NetworkBehaviour`] contributed its addresses, e.g. to prefer QUIC or to dial
+    /// relayed addresses last, see [`PreferenceRanker`](dial_ranking::PreferenceRanker).
+    ///
+    /// By default, addresses are dialed in the order they are provided.
+    pub fn with_dial_ranker(mut self, ranker: impl DialRanker) -> Self {
+        self.dial_ranker = Some(Box::new(ranker));
+        self
+    }
+
+    /// Tries the addresses of a dial in stages by transport according to the given
+    /// [`FallbackChain`], e.g. QUIC first, then TCP, then relayed addresses.
+    ///
+    /// The addresses of a stage are only dialed once all dials of the previous stage failed,
+    /// with up to [`Config::with_dial_concurrency_factor`] dials of a stage at the same time. If
+    /// all dials fail, [`DialError::Transport`] lists the error of every attempt.
+    ///
+    /// By default, all addresses are dialed in a single stage.
+    pub fn with_dial_fallback(mut self, chain: FallbackChain) -> Self {
+        self.dial_fallback = Some(chain);
+        self
+    }
+
+    /// The maximum number of events of the [`NetworkBehaviour`], the connections and the
+    /// listeners handled in a single poll of the [`Swarm`], before it yields to the executor.
+    ///
+    /// Events that are handled internally, e.g. a behaviour notifying many handlers during a
+    /// Kademlia bootstrap, would otherwise be processed in one go, delaying other tasks on
+    /// single-threaded executors such as the one of `wasm-bindgen`.
+    ///
+    /// Defaults to no limit.
+    pub fn with_poll_budget(mut self, budget: NonZeroUsize) -> Self {
+        self.poll_budget = Some(budget);
+        self
+    }
+
+    /// Retries failed dials of known peers according to the given [`RetryPolicy`], unless a dial
+    /// sets its own via [`WithPeerId::retry_policy`](dial_opts::WithPeerId::retry_policy).
+    ///
+    /// Each retry is reported as [`SwarmEvent::DialRetryScheduled`] and
+    /// [`SwarmEvent::DialRetry`]. Retries are dropped once the peer is connected.
+    ///
+    /// Defaults to no retries.
+    pub fn with_dial_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.dial_retry_policy = Some(policy);
+        self
+    }
+
+    /// Limits the number of dials in flight at the same time, across all peers.
+    ///
+    /// Dials exceeding the limit are deferred until an ongoing dial completes and reported as
+    /// [`SwarmEvent::DialDeferred`]. Deferred dials are started in the order of their
+    /// [`DialPriority`](dial_opts::DialPriority). This is independent of
+    /// [`Config::with_dial_concurrency_factor`], which limits the addresses dialed concurrently
+    /// within a single dial. Unlimited by default.
+    pub fn with_max_concurrent_dials(mut self, max: NonZeroUsize) -> Self {
+        self.dial_limits.max_dials = Some(max);
+        self
+    }
+
+    /// Limits the number of dials to the same peer in flight at the same time.
+    ///
+    /// Dials exceeding the limit are deferred like for [`Config::with_max_concurrent_dials`].
+    /// Unlimited by default.
+    pub fn with_max_concurrent_dials_per_peer(mut self, max: NonZeroUsize) -> Self {
+        self.dial_limits.max_dials_per_peer = Some(max);
         self
     }
 
-    /// How long to keep a connection alive once it is idling.
+    /// Ranks the confirmed external addresses returned by [`Swarm::external_addresses`].
     ///
-    /// Defaults to 0.
-    pub fn with_idle_connection_timeout(mut self, timeout: Duration) -> Self {
-        self.pool_config.idle_connection_timeout = timeout;
+    /// [`NetworkBehaviour`]s tracking external addresses via [`ExternalAddresses`] rank them
+    /// independently, see [`ExternalAddresses::with_ranking`].
+    ///
+    /// Defaults to [`rank_by_source_and_recency`](behaviour::rank_by_source_and_recency).
+    pub fn with_external_address_ranking(mut self, ranking: AddressRanking) -> Self {
+        self.external_address_ranking = ranking;
         self
     }
 }
@@ -1684,6 +2643,19 @@ impl error::Error for ListenError {
     }
 }
 
+/// A connection was denied because the [`Swarm`] is shutting down, see
+/// [`Swarm::close_gracefully`].
+#[derive(Debug, Clone, Copy)]
+pub struct ShuttingDown;
+
+impl fmt::Display for ShuttingDown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the swarm is shutting down")
+    }
+}
+
+impl error::Error for ShuttingDown {}
+
 /// A connection was denied.
 ///
 /// To figure out which [`NetworkBehaviour`] denied the connection, use [`ConnectionDenied::downcast`].
@@ -1837,6 +2809,56 @@ mod tests {
             && !swarm2.is_connected(swarm1.local_peer_id())
     }
 
+    /// Starts listening on a new memory address, returning it once the swarm reported it.
+    async fn listen<TBehaviour>(swarm: &mut Swarm<TBehaviour>) -> Multiaddr
+    where
+        TBehaviour: NetworkBehaviour,
+        TBehaviour::ToSwarm: fmt::Debug,
+    {
+        swarm.listen_on(multiaddr![Memory(0u64)]).unwrap();
+        match swarm.next().await.unwrap() {
+            SwarmEvent::NewListenAddr { address, .. } => address,
+            e => panic!("Unexpected network event: {e:?}"),
+        }
+    }
+
+    /// Connects `dialer` to `listener`, driving each of them until it reported the connection.
+    ///
+    /// Returns the id of the connection on the side of `dialer`.
+    async fn connect<TBehaviour1, TBehaviour2>(
+        dialer: &mut Swarm<TBehaviour1>,
+        listener: &mut Swarm<TBehaviour2>,
+    ) -> ConnectionId
+    where
+        TBehaviour1: NetworkBehaviour,
+        TBehaviour2: NetworkBehaviour,
+        TBehaviour2::ToSwarm: fmt::Debug,
+    {
+        let address = listen(listener).await;
+        dialer.dial(address).unwrap();
+
+        let mut dialer_connection = None;
+        let mut listener_connected = false;
+        loop {
+            match dialer_connection {
+                Some(connection_id) if listener_connected => return connection_id,
+                _ => {}
+            }
+            tokio::select! {
+                Some(event) = dialer.next(), if dialer_connection.is_none() => {
+                    if let SwarmEvent::ConnectionEstablished { connection_id, .. } = event {
+                        dialer_connection = Some(connection_id);
+                    }
+                }
+                Some(event) = listener.next(), if !listener_connected => {
+                    if let SwarmEvent::ConnectionEstablished { .. } = event {
+                        listener_connected = true;
+                    }
+                }
+            }
+        }
+    }
+
     /// Establishes multiple connections between two peers,
     /// after which one peer disconnects the other using [`Swarm::disconnect_peer_id`].
     ///
@@ -2327,6 +3349,575 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn abort_dial_surfaces_error() {
+        let mut dialer = new_test_swarm(Config::with_tokio_executor());
+        let mut listener = new_test_swarm(Config::with_tokio_executor());
+
+        let listener_peer_id = *listener.local_peer_id();
+        let listener_address = listen(&mut listener).await;
+
+        let opts = DialOpts::peer_id(listener_peer_id)
+            .addresses(vec![listener_address])
+            .build();
+        let connection_id = opts.connection_id();
+        dialer.dial(opts).unwrap();
+
+        assert!(dialer.abort_dial(connection_id));
+        assert!(!dialer.abort_dial(connection_id));
+
+        match dialer.next().await.unwrap() {
+            SwarmEvent::OutgoingConnectionError {
+                connection_id: id,
+                error: DialError::Aborted,
+                ..
+            } => assert_eq!(id, connection_id),
+            e => panic!("Unexpected swarm event {e:?}."),
+        }
+        assert!(!dialer.abort_dial(connection_id));
+    }
+
+    #[tokio::test]
+    async fn dial_abort_handle_aborts_pending_and_future_dials() {
+        let mut dialer = new_test_swarm(Config::with_tokio_executor());
+        let mut listener = new_test_swarm(Config::with_tokio_executor());
+
+        let listener_peer_id = *listener.local_peer_id();
+        let listener_address = listen(&mut listener).await;
+
+        let opts = DialOpts::peer_id(listener_peer_id)
+            .addresses(vec![listener_address.clone()])
+            .build();
+        let handle = opts.abort_handle();
+        dialer.dial(opts).unwrap();
+        handle.abort();
+
+        match dialer.next().await.unwrap() {
+            SwarmEvent::OutgoingConnectionError {
+                error: DialError::Aborted,
+                ..
+            } => {}
+            e => panic!("Unexpected swarm event {e:?}."),
+        }
+
+        let opts = DialOpts::peer_id(listener_peer_id)
+            .addresses(vec![listener_address])
+            .build();
+        opts.abort_handle().abort();
+        assert!(matches!(dialer.dial(opts), Err(DialError::Aborted)));
+    }
+
+    #[test]
+    fn yields_once_poll_budget_is_exhausted() {
+        /// Emits a burst of events that the [`Swarm`] handles internally.
+        struct Burst(usize);
+
+        impl NetworkBehaviour for Burst {
+            type ConnectionHandler = dummy::ConnectionHandler;
+            type ToSwarm = void::Void;
+
+            fn handle_established_inbound_connection(
+                &mut self,
+                _: ConnectionId,
+                _: PeerId,
+                _: &Multiaddr,
+                _: &Multiaddr,
+            ) -> Result<THandler<Self>, ConnectionDenied> {
+                Ok(dummy::ConnectionHandler)
+            }
+
+            fn handle_established_outbound_connection(
+                &mut self,
+                _: ConnectionId,
+                _: PeerId,
+                _: &Multiaddr,
+                _: Endpoint,
+                _: PortUse,
+            ) -> Result<THandler<Self>, ConnectionDenied> {
+                Ok(dummy::ConnectionHandler)
+            }
+
+            fn on_swarm_event(&mut self, _: FromSwarm) {}
+
+            fn on_connection_handler_event(
+                &mut self,
+                _: PeerId,
+                _: ConnectionId,
+                event: THandlerOutEvent<Self>,
+            ) {
+                void::unreachable(event)
+            }
+
+            fn poll(
+                &mut self,
+                _: &mut Context<'_>,
+            ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+                if self.0 == 0 {
+                    return Poll::Pending;
+                }
+                self.0 -= 1;
+                Poll::Ready(ToSwarm::CloseConnection {
+                    peer_id: PeerId::random(),
+                    connection: CloseConnection::All,
+                })
+            }
+        }
+
+        let id_keys = identity::Keypair::generate_ed25519();
+        let transport = transport::MemoryTransport::default()
+            .upgrade(upgrade::Version::V1)
+            .authenticate(plaintext::Config::new(&id_keys))
+            .multiplex(yamux::Config::default())
+            .boxed();
+        let mut swarm = Swarm::new(
+            transport,
+            Burst(10),
+            id_keys.public().to_peer_id(),
+            Config::without_executor().with_poll_budget(NonZeroUsize::new(4).unwrap()),
+        );
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+
+        assert!(swarm.poll_next_unpin(&mut cx).is_pending());
+        assert_eq!(swarm.behaviour().0, 6);
+        assert!(swarm.poll_next_unpin(&mut cx).is_pending());
+        assert_eq!(swarm.behaviour().0, 2);
+        assert!(swarm.poll_next_unpin(&mut cx).is_pending());
+        assert_eq!(swarm.behaviour().0, 0);
+    }
+
+    #[tokio::test]
+    async fn close_gracefully_closes_all_connections() {
+        let mut dialer = new_test_swarm(Config::with_tokio_executor());
+        let mut listener = new_test_swarm(Config::with_tokio_executor());
+
+        let listener_peer_id = *listener.local_peer_id();
+        connect(&mut dialer, &mut listener).await;
+        let listener_address = listener.listeners().next().unwrap().clone();
+        tokio::spawn(async move {
+            loop {
+                listener.next().await;
+            }
+        });
+
+        assert!(dialer.close_gracefully(Duration::from_secs(10)).await);
+        assert!(!dialer.is_connected(&listener_peer_id));
+        assert_eq!(
+            dialer
+                .network_info()
+                .connection_counters()
+                .num_connections(),
+            0
+        );
+
+        let error = dialer.dial(listener_address).unwrap_err();
+        let DialError::Denied { cause } = error else {
+            panic!("Unexpected dial error {error:?}");
+        };
+        assert!(cause.downcast::<ShuttingDown>().is_ok());
+    }
+
+    #[test]
+    fn manually_added_external_addresses_are_ranked_first() {
+        let mut swarm = new_test_swarm(Config::without_executor());
+        let manual: Multiaddr = multiaddr::Protocol::Memory(1000).into();
+        let confirmed: Multiaddr = multiaddr::Protocol::Memory(2000).into();
+
+        swarm.add_external_address(manual.clone());
+        swarm.handle_behaviour_event(ToSwarm::ExternalAddrConfirmed(confirmed.clone()));
+
+        assert_eq!(
+            swarm.external_addresses().collect::<Vec<_>>(),
+            vec![&manual, &confirmed]
+        );
+        assert_eq!(
+            swarm
+                .scored_external_addresses()
+                .map(ScoredAddress::source)
+                .collect::<Vec<_>>(),
+            vec![
+                ExternalAddressSource::Manual,
+                ExternalAddressSource::Confirmed
+            ]
+        );
+    }
+
+    #[test]
+    fn external_addresses_keep_most_trusted_source() {
+        let mut swarm = new_test_swarm(Config::without_executor());
+        let address: Multiaddr = multiaddr::Protocol::Memory(1000).into();
+
+        swarm.add_external_address_with_source(address.clone(), ExternalAddressSource::Upnp);
+        swarm.handle_behaviour_event(ToSwarm::ExternalAddrConfirmedWithSource {
+            address: address.clone(),
+            source: ExternalAddressSource::AutoNat,
+        });
+        swarm.handle_behaviour_event(ToSwarm::ExternalAddrConfirmed(address.clone()));
+
+        let scored = swarm.scored_external_addresses().collect::<Vec<_>>();
+        assert_eq!(scored.len(), 1);
+        assert_eq!(scored[0].source(), ExternalAddressSource::AutoNat);
+        assert!(matches!(
+            swarm.pending_swarm_events.front(),
+            Some(SwarmEvent::ExternalAddrConfirmed {
+                source: ExternalAddressSource::AutoNat,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn retries_failed_dials_with_backoff() {
+        let policy = RetryPolicy::new()
+            .with_initial_backoff(Duration::from_millis(10))
+            .with_max_retries(2)
+            .with_jitter(0.0);
+        let mut swarm =
+            new_test_swarm(Config::with_tokio_executor().with_dial_retry_policy(policy));
+
+        let peer_id = PeerId::random();
+        let unreachable: Multiaddr = multiaddr::Protocol::Memory(rand::random::<u64>()).into();
+        let opts = DialOpts::peer_id(peer_id)
+            .addresses(vec![unreachable])
+            .build();
+        let mut failed_connection_id = opts.connection_id();
+        swarm.dial(opts).unwrap();
+
+        for expected_attempt in 1..=2 {
+            match swarm.next().await.unwrap() {
+                SwarmEvent::OutgoingConnectionError { connection_id, .. } => {
+                    assert_eq!(connection_id, failed_connection_id)
+                }
+                e => panic!("Unexpected swarm event {e:?}"),
+            }
+            match swarm.next().await.unwrap() {
+                SwarmEvent::DialRetryScheduled {
+                    peer_id: p,
+                    connection_id,
+                    attempt,
+                    backoff,
+                } => {
+                    assert_eq!(p, peer_id);
+                    assert_eq!(connection_id, failed_connection_id);
+                    assert_eq!(attempt, expected_attempt);
+                    assert_eq!(backoff, policy.backoff(expected_attempt));
+                }
+                e => panic!("Unexpected swarm event {e:?}"),
+            }
+            match swarm.next().await.unwrap() {
+                SwarmEvent::DialRetry {
+                    peer_id: p,
+                    connection_id,
+                    attempt,
+                } => {
+                    assert_eq!(p, peer_id);
+                    assert_ne!(connection_id, failed_connection_id);
+                    assert_eq!(attempt, expected_attempt);
+                    failed_connection_id = connection_id;
+                }
+                e => panic!("Unexpected swarm event {e:?}"),
+            }
+        }
+
+        match swarm.next().await.unwrap() {
+            SwarmEvent::OutgoingConnectionError { connection_id, .. } => {
+                assert_eq!(connection_id, failed_connection_id)
+            }
+            e => panic!("Unexpected swarm event {e:?}"),
+        }
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), swarm.next())
+                .await
+                .is_err(),
+            "dial must not be retried more than `max_retries` times"
+        );
+    }
+
+    #[tokio::test]
+    async fn connection_gater_denies_connections() {
+        struct DenyPeer(PeerId);
+
+        impl ConnectionGater for DenyPeer {
+            fn intercept_dial(
+                &mut self,
+                _: ConnectionId,
+                peer: Option<PeerId>,
+                _: &[Multiaddr],
+            ) -> Result<(), ConnectionDenied> {
+                if peer == Some(self.0) {
+                    return Err(ConnectionDenied::new("peer is denied"));
+                }
+                Ok(())
+            }
+
+            fn intercept_secured(
+                &mut self,
+                _: ConnectionId,
+                peer: PeerId,
+                _: &ConnectedPoint,
+            ) -> Result<(), ConnectionDenied> {
+                if peer == self.0 {
+                    return Err(ConnectionDenied::new("peer is denied"));
+                }
+                Ok(())
+            }
+        }
+
+        let mut dialer = new_test_swarm(Config::with_tokio_executor());
+        let dialer_peer_id = *dialer.local_peer_id();
+        let mut listener = new_test_swarm(
+            Config::with_tokio_executor().with_connection_gater(DenyPeer(dialer_peer_id)),
+        );
+
+        let listener_address = listen(&mut listener).await;
+
+        // Outbound connections to the denied peer are never dialed.
+        let error = listener
+            .dial(
+                DialOpts::peer_id(dialer_peer_id)
+                    .addresses(vec![multiaddr![Memory(1u64)]])
+                    .build(),
+            )
+            .unwrap_err();
+        assert!(matches!(error, DialError::Denied { .. }));
+
+        // Inbound connections from the denied peer are closed once it is authenticated.
+        dialer.dial(listener_address).unwrap();
+        tokio::spawn(async move {
+            loop {
+                dialer.next().await;
+            }
+        });
+        loop {
+            match listener.next().await.unwrap() {
+                SwarmEvent::IncomingConnection { .. } => {}
+                SwarmEvent::IncomingConnectionError {
+                    error: ListenError::Denied { .. },
+                    ..
+                } => break,
+                e => panic!("Unexpected swarm event {e:?}."),
+            }
+        }
+        assert!(!listener.is_connected(&dialer_peer_id));
+        assert!(listener.behaviour().on_connection_established.is_empty());
+    }
+
+    #[tokio::test]
+    async fn connection_extensions_live_as_long_as_the_connection() {
+        #[derive(Debug, PartialEq)]
+        struct Tag(u32);
+
+        let mut dialer = new_test_swarm(Config::with_tokio_executor());
+        let mut listener = new_test_swarm(Config::with_tokio_executor());
+
+        let listener_peer_id = *listener.local_peer_id();
+        let connection_id = connect(&mut dialer, &mut listener).await;
+        tokio::spawn(async move {
+            loop {
+                listener.next().await;
+            }
+        });
+
+        let extensions = dialer.connection_extensions_mut(connection_id).unwrap();
+        assert!(extensions.is_empty());
+        extensions.insert(Tag(1));
+
+        assert_eq!(
+            dialer
+                .connection_extensions(connection_id)
+                .and_then(|e| e.get::<Tag>()),
+            Some(&Tag(1))
+        );
+
+        dialer.disconnect_peer_id(listener_peer_id).unwrap();
+        let extensions = loop {
+            if let SwarmEvent::ConnectionClosed { extensions, .. } = dialer.next().await.unwrap() {
+                break extensions;
+            }
+        };
+        assert_eq!(extensions.get::<Tag>(), Some(&Tag(1)));
+        assert!(dialer.connection_extensions(connection_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn connection_tags_are_reported_on_close() {
+        let mut dialer = new_test_swarm(Config::with_tokio_executor());
+        let mut listener = new_test_swarm(Config::with_tokio_executor());
+
+        let listener_peer_id = *listener.local_peer_id();
+        let connection_id = connect(&mut dialer, &mut listener).await;
+        tokio::spawn(async move {
+            loop {
+                listener.next().await;
+            }
+        });
+
+        let tags = dialer
+            .connection_extensions_mut(connection_id)
+            .unwrap()
+            .tags_mut();
+        assert!(tags.is_empty());
+        tags.insert("role", "validator");
+        dialer.handle_behaviour_event(ToSwarm::TagConnection {
+            connection_id,
+            key: "score".to_owned(),
+            value: TagValue::Int(3),
+        });
+        dialer.handle_behaviour_event(ToSwarm::UntagConnection {
+            connection_id,
+            key: "role".to_owned(),
+        });
+        assert_eq!(
+            dialer
+                .connection_extensions(connection_id)
+                .and_then(|extensions| extensions.tags().get("score")),
+            Some(&TagValue::Int(3))
+        );
+
+        dialer.disconnect_peer_id(listener_peer_id).unwrap();
+        let extensions = loop {
+            if let SwarmEvent::ConnectionClosed { extensions, .. } = dialer.next().await.unwrap() {
+                break extensions;
+            }
+        };
+        assert_eq!(
+            extensions.tags().iter().collect::<Vec<_>>(),
+            vec![("score", &TagValue::Int(3))]
+        );
+    }
+
+    #[tokio::test]
+    async fn handler_panic_closes_only_its_connection() {
+        #[derive(Clone)]
+        struct PanickingHandler;
+
+        impl ConnectionHandler for PanickingHandler {
+            type FromBehaviour = void::Void;
+            type ToBehaviour = void::Void;
+            type InboundProtocol = upgrade::DeniedUpgrade;
+            type OutboundProtocol = upgrade::DeniedUpgrade;
+            type InboundOpenInfo = ();
+            type OutboundOpenInfo = void::Void;
+
+            fn listen_protocol(
+                &self,
+            ) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+                SubstreamProtocol::new(upgrade::DeniedUpgrade, ())
+            }
+
+            fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
+                void::unreachable(event)
+            }
+
+            fn poll(
+                &mut self,
+                _: &mut Context<'_>,
+            ) -> Poll<
+                ConnectionHandlerEvent<
+                    Self::OutboundProtocol,
+                    Self::OutboundOpenInfo,
+                    Self::ToBehaviour,
+                >,
+            > {
+                panic!("handler bug")
+            }
+
+            fn on_connection_event(
+                &mut self,
+                _: handler::ConnectionEvent<
+                    Self::InboundProtocol,
+                    Self::OutboundProtocol,
+                    Self::InboundOpenInfo,
+                    Self::OutboundOpenInfo,
+                >,
+            ) {
+            }
+        }
+
+        let id_keys = identity::Keypair::generate_ed25519();
+        let transport = transport::MemoryTransport::default()
+            .upgrade(upgrade::Version::V1)
+            .authenticate(plaintext::Config::new(&id_keys))
+            .multiplex(yamux::Config::default())
+            .boxed();
+        let mut dialer = Swarm::new(
+            transport,
+            CallTraceBehaviour::new(MockBehaviour::<_, ()>::new(PanickingHandler)),
+            id_keys.public().to_peer_id(),
+            Config::with_tokio_executor(),
+        );
+        let mut listener = new_test_swarm(Config::with_tokio_executor());
+
+        let listener_peer_id = *listener.local_peer_id();
+        connect(&mut dialer, &mut listener).await;
+        tokio::spawn(async move {
+            loop {
+                listener.next().await;
+            }
+        });
+
+        let cause = loop {
+            if let SwarmEvent::ConnectionClosed { cause, .. } = dialer.next().await.unwrap() {
+                break cause;
+            }
+        };
+        assert!(matches!(
+            cause,
+            Some(ConnectionError::HandlerPanic(message)) if message == "handler bug"
+        ));
+        assert!(!dialer.is_connected(&listener_peer_id));
+        assert_eq!(dialer.behaviour().on_connection_closed.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn disconnect_reason_is_reported_to_remote() {
+        let mut dialer = new_test_swarm(Config::with_tokio_executor());
+        let mut listener = new_test_swarm(Config::with_tokio_executor().with_disconnect_reasons());
+
+        let listener_peer_id = *listener.local_peer_id();
+        connect(&mut dialer, &mut listener).await;
+
+        dialer
+            .disconnect_peer_with_reason(listener_peer_id, 7, "shutting down")
+            .unwrap();
+        tokio::spawn(async move {
+            loop {
+                dialer.next().await;
+            }
+        });
+
+        let reason = loop {
+            if let SwarmEvent::ConnectionClosed { reason, .. } = listener.next().await.unwrap() {
+                break reason;
+            }
+        };
+        assert_eq!(reason, Some(DisconnectReason::new(7, "shutting down")));
+    }
+
+    #[tokio::test]
+    async fn disconnect_reason_is_not_negotiated_by_default() {
+        let mut dialer = new_test_swarm(Config::with_tokio_executor());
+        let mut listener = new_test_swarm(Config::with_tokio_executor());
+
+        let listener_peer_id = *listener.local_peer_id();
+        connect(&mut dialer, &mut listener).await;
+
+        dialer
+            .disconnect_peer_with_reason(listener_peer_id, 7, "shutting down")
+            .unwrap();
+        tokio::spawn(async move {
+            loop {
+                dialer.next().await;
+            }
+        });
+
+        let reason = loop {
+            if let SwarmEvent::ConnectionClosed { reason, .. } = listener.next().await.unwrap() {
+                break reason;
+            }
+        };
+        assert_eq!(reason, None);
+    }
+
     #[test]
     fn dial_error_prints_sources() {
         // This constitutes a fairly typical error for chained transports.
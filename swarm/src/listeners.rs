@@ -0,0 +1,248 @@
+use crate::ListenerId;
+use libp2p_core::multiaddr::Protocol;
+use libp2p_core::Multiaddr;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+
+/// The state of a listener, see [`Swarm::listeners_info`](crate::Swarm::listeners_info).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ListenerState {
+    /// The listener was added but did not report an address yet.
+    Starting,
+    /// The listener reported at least one address.
+    Listening,
+    /// The listener was removed and is closing.
+    Closing,
+}
+
+/// Why an address of a listener is no longer listened on, see
+/// [`SwarmEvent::ListenAddrRemoved`](crate::SwarmEvent::ListenAddrRemoved).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExpiryReason {
+    /// The transport reported the address as expired, e.g. because the network interface it was
+    /// bound to went away.
+    Expired,
+    /// The listener closed because of a transport error.
+    TransportError,
+    /// The listener was removed via [`Swarm::remove_listener`](crate::Swarm::remove_listener),
+    /// by a [`NetworkBehaviour`](crate::NetworkBehaviour) or because the
+    /// [`Swarm`](crate::Swarm) shut down.
+    Removed,
+    /// The listener closed on its own, without an error.
+    ListenerClosed,
+}
+
+/// An address a listener is bound to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenAddress {
+    address: Multiaddr,
+    transport: String,
+    socket_addr: Option<SocketAddr>,
+}
+
+impl ListenAddress {
+    fn new(address: Multiaddr) -> Self {
+        let transport = address
+            .iter()
+            .filter(|p| !matches!(p, Protocol::P2p(_)))
+            .map(|p| format!("/{}", p.tag()))
+            .collect();
+        let socket_addr = socket_addr(&address);
+
+        Self {
+            address,
+            transport,
+            socket_addr,
+        }
+    }
+
+    /// The address as reported by the listener.
+    pub fn address(&self) -> &Multiaddr {
+        &self.address
+    }
+
+    /// The protocols of the transport backing the address, e.g. `/ip4/udp/quic-v1`.
+    pub fn transport(&self) -> &str {
+        &self.transport
+    }
+
+    /// The socket the listener is bound to, if the address is an IP address with a TCP or UDP
+    /// port.
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        self.socket_addr
+    }
+}
+
+/// An active listener, see [`Swarm::listeners_info`](crate::Swarm::listeners_info).
+#[derive(Debug, Clone)]
+pub struct ListenerInfo {
+    id: ListenerId,
+    configured: Multiaddr,
+    state: ListenerState,
+    addresses: Vec<ListenAddress>,
+    num_errors: usize,
+}
+
+impl ListenerInfo {
+    /// The ID of the listener.
+    pub fn id(&self) -> ListenerId {
+        self.id
+    }
+
+    /// The address the listener was created with, which may contain an unspecified IP address
+    /// or port 0.
+    pub fn configured_address(&self) -> &Multiaddr {
+        &self.configured
+    }
+
+    /// The state of the listener.
+    pub fn state(&self) -> ListenerState {
+        self.state
+    }
+
+    /// The addresses the listener is currently bound to.
+    pub fn addresses(&self) -> &[ListenAddress] {
+        &self.addresses
+    }
+
+    /// The number of non-fatal errors the listener reported.
+    pub fn num_errors(&self) -> usize {
+        self.num_errors
+    }
+}
+
+/// Tracks the state and addresses of the active listeners.
+#[derive(Debug, Default)]
+pub(crate) struct Listeners {
+    listeners: HashMap<ListenerId, ListenerInfo>,
+}
+
+impl Listeners {
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &ListenerInfo> {
+        self.listeners.values()
+    }
+
+    pub(crate) fn add(&mut self, id: ListenerId, configured: Multiaddr) {
+        self.listeners.insert(
+            id,
+            ListenerInfo {
+                id,
+                configured,
+                state: ListenerState::Starting,
+                addresses: Vec::new(),
+                num_errors: 0,
+            },
+        );
+    }
+
+    /// Records a new address of the listener, returning it unless it was already known.
+    pub(crate) fn add_address(
+        &mut self,
+        id: ListenerId,
+        address: &Multiaddr,
+    ) -> Option<ListenAddress> {
+        let listener = self.listeners.get_mut(&id)?;
+        if listener.addresses.iter().any(|a| &a.address == address) {
+            return None;
+        }
+        if listener.state == ListenerState::Starting {
+            listener.state = ListenerState::Listening;
+        }
+
+        let address = ListenAddress::new(address.clone());
+        listener.addresses.push(address.clone());
+        Some(address)
+    }
+
+    /// Removes an expired address of the listener, returning it if it was known.
+    pub(crate) fn remove_address(
+        &mut self,
+        id: ListenerId,
+        address: &Multiaddr,
+    ) -> Option<ListenAddress> {
+        let addresses = &mut self.listeners.get_mut(&id)?.addresses;
+        let index = addresses.iter().position(|a| &a.address == address)?;
+
+        Some(addresses.remove(index))
+    }
+
+    pub(crate) fn on_error(&mut self, id: ListenerId) {
+        if let Some(listener) = self.listeners.get_mut(&id) {
+            listener.num_errors += 1;
+        }
+    }
+
+    /// Marks the listener as being removed.
+    pub(crate) fn on_remove(&mut self, id: ListenerId) {
+        if let Some(listener) = self.listeners.get_mut(&id) {
+            listener.state = ListenerState::Closing;
+        }
+    }
+
+    /// Removes the closed listener, returning its remaining addresses and why they expired.
+    pub(crate) fn on_closed(
+        &mut self,
+        id: ListenerId,
+        failed: bool,
+    ) -> (Vec<ListenAddress>, ExpiryReason) {
+        let Some(listener) = self.listeners.remove(&id) else {
+            return (Vec::new(), ExpiryReason::ListenerClosed);
+        };
+
+        let reason = match (failed, listener.state) {
+            (true, _) => ExpiryReason::TransportError,
+            (false, ListenerState::Closing) => ExpiryReason::Removed,
+            (false, _) => ExpiryReason::ListenerClosed,
+        };
+        (listener.addresses, reason)
+    }
+}
+
+fn socket_addr(address: &Multiaddr) -> Option<SocketAddr> {
+    let mut iter = address.iter();
+    let ip = match iter.next()? {
+        Protocol::Ip4(ip) => IpAddr::from(ip),
+        Protocol::Ip6(ip) => IpAddr::from(ip),
+        _ => return None,
+    };
+    let (Protocol::Tcp(port) | Protocol::Udp(port)) = iter.next()? else {
+        return None;
+    };
+
+    Some(SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_listener_lifecycle() {
+        let mut listeners = Listeners::default();
+        let id = ListenerId::next();
+        let address: Multiaddr = "/ip4/127.0.0.1/udp/4001/quic-v1".parse().unwrap();
+
+        listeners.add(id, "/ip4/0.0.0.0/udp/0/quic-v1".parse().unwrap());
+        assert_eq!(
+            listeners.iter().next().unwrap().state(),
+            ListenerState::Starting
+        );
+
+        let added = listeners.add_address(id, &address).unwrap();
+        assert_eq!(added.transport(), "/ip4/udp/quic-v1");
+        assert_eq!(added.socket_addr(), Some("127.0.0.1:4001".parse().unwrap()));
+        assert!(listeners.add_address(id, &address).is_none());
+        assert_eq!(
+            listeners.iter().next().unwrap().state(),
+            ListenerState::Listening
+        );
+
+        listeners.on_remove(id);
+        let (addresses, reason) = listeners.on_closed(id, false);
+        assert_eq!(addresses, vec![added]);
+        assert_eq!(reason, ExpiryReason::Removed);
+        assert_eq!(listeners.iter().count(), 0);
+    }
+}
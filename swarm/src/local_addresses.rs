@@ -0,0 +1,183 @@
+use crate::ListenerId;
+use libp2p_core::Multiaddr;
+use smallvec::SmallVec;
+use std::collections::{HashMap, VecDeque};
+
+/// The maximum number of addresses that are only known through [`AddressSource::Observed`].
+///
+/// Observed addresses never expire on their own, hence the oldest one is forgotten once this
+/// limit is reached.
+const MAX_OBSERVED_ADDRESSES: usize = 16;
+
+/// Where an address of the local node was learned from, see [`Swarm::local_addresses`].
+///
+/// [`Swarm::local_addresses`]: crate::Swarm::local_addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AddressSource {
+    /// The address was passed to [`Swarm::listen_on`](crate::Swarm::listen_on). It may not be
+    /// dialable as-is, e.g. if it contains an unspecified IP address or port 0.
+    Configured(ListenerId),
+    /// The listener reported that it is bound to the address.
+    Listener(ListenerId),
+    /// A [`NetworkBehaviour`](crate::NetworkBehaviour) reported the address as a candidate for an
+    /// external address, usually because a remote observed it.
+    Observed,
+    /// The address was confirmed to be reachable from the outside.
+    External,
+}
+
+/// A change of the sources of a local address.
+pub(crate) type Change = (Multiaddr, Vec<AddressSource>);
+
+/// Reconciles configured, bound, observed and confirmed addresses of the local node.
+#[derive(Debug, Default)]
+pub(crate) struct LocalAddresses {
+    addresses: HashMap<Multiaddr, SmallVec<[AddressSource; 2]>>,
+    /// Addresses with an [`AddressSource::Observed`] source, oldest first.
+    observed: VecDeque<Multiaddr>,
+}
+
+impl LocalAddresses {
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&Multiaddr, &[AddressSource])> {
+        self.addresses
+            .iter()
+            .map(|(address, sources)| (address, sources.as_slice()))
+    }
+
+    /// Records `source` for `address`, returning the resulting changes.
+    pub(crate) fn add(&mut self, address: &Multiaddr, source: AddressSource) -> Vec<Change> {
+        let mut changes = Vec::new();
+
+        if source == AddressSource::Observed {
+            self.observed.retain(|a| a != address);
+            self.observed.push_back(address.clone());
+            if self.observed.len() > MAX_OBSERVED_ADDRESSES {
+                let oldest = self.observed.pop_front().expect("queue to be non-empty");
+                changes.extend(self.remove_source(&oldest, |s| *s == AddressSource::Observed));
+            }
+        }
+
+        let sources = self.addresses.entry(address.clone()).or_default();
+        if !sources.contains(&source) {
+            sources.push(source);
+            changes.push((address.clone(), sources.to_vec()));
+        }
+        changes
+    }
+
+    /// Removes `source` from `address`, returning the resulting change, if any.
+    pub(crate) fn remove(&mut self, address: &Multiaddr, source: AddressSource) -> Option<Change> {
+        self.remove_source(address, |s| *s == source)
+    }
+
+    /// Removes all sources of a closed listener, returning the resulting changes.
+    pub(crate) fn remove_listener(&mut self, listener_id: ListenerId) -> Vec<Change> {
+        let is_listener = |s: &AddressSource| match s {
+            AddressSource::Configured(id) | AddressSource::Listener(id) => *id == listener_id,
+            AddressSource::Observed | AddressSource::External => false,
+        };
+        let addresses = self
+            .addresses
+            .iter()
+            .filter(|(_, sources)| sources.iter().any(is_listener))
+            .map(|(address, _)| address.clone())
+            .collect::<Vec<_>>();
+
+        addresses
+            .iter()
+            .filter_map(|address| self.remove_source(address, is_listener))
+            .collect()
+    }
+
+    fn remove_source(
+        &mut self,
+        address: &Multiaddr,
+        predicate: impl Fn(&AddressSource) -> bool,
+    ) -> Option<Change> {
+        let sources = self.addresses.get_mut(address)?;
+        let len = sources.len();
+        sources.retain(|s| !predicate(s));
+        if sources.len() == len {
+            return None;
+        }
+
+        let sources = sources.to_vec();
+        if sources.is_empty() {
+            self.addresses.remove(address);
+        }
+        if !sources.contains(&AddressSource::Observed) {
+            self.observed.retain(|a| a != address);
+        }
+        Some((address.clone(), sources))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_sources_per_address() {
+        let mut addresses = LocalAddresses::default();
+        let listener_id = ListenerId::next();
+        let configured: Multiaddr = "/ip4/0.0.0.0/tcp/0".parse().unwrap();
+        let bound: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+
+        addresses.add(&configured, AddressSource::Configured(listener_id));
+        addresses.add(&bound, AddressSource::Listener(listener_id));
+        assert_eq!(
+            addresses.add(&bound, AddressSource::External),
+            vec![(
+                bound.clone(),
+                vec![
+                    AddressSource::Listener(listener_id),
+                    AddressSource::External
+                ]
+            )]
+        );
+        assert!(addresses.add(&bound, AddressSource::External).is_empty());
+
+        let changes = addresses.remove_listener(listener_id);
+        assert_eq!(changes.len(), 2);
+        assert!(changes.contains(&(configured, vec![])));
+        assert!(changes.contains(&(bound.clone(), vec![AddressSource::External])));
+
+        assert_eq!(
+            addresses.remove(&bound, AddressSource::External),
+            Some((bound, vec![]))
+        );
+        assert_eq!(addresses.iter().count(), 0);
+    }
+
+    #[test]
+    fn forgets_oldest_observed_address() {
+        let mut addresses = LocalAddresses::default();
+        let observed = (0..=MAX_OBSERVED_ADDRESSES)
+            .map(|i| {
+                format!("/ip4/1.2.3.4/tcp/{i}")
+                    .parse::<Multiaddr>()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        for address in &observed[..MAX_OBSERVED_ADDRESSES] {
+            addresses.add(address, AddressSource::Observed);
+        }
+        // Observing an address again makes it the most recent one.
+        addresses.add(&observed[0], AddressSource::Observed);
+
+        let changes = addresses.add(&observed[MAX_OBSERVED_ADDRESSES], AddressSource::Observed);
+        assert_eq!(
+            changes,
+            vec![
+                (observed[1].clone(), vec![]),
+                (
+                    observed[MAX_OBSERVED_ADDRESSES].clone(),
+                    vec![AddressSource::Observed]
+                ),
+            ]
+        );
+        assert_eq!(addresses.iter().count(), MAX_OBSERVED_ADDRESSES);
+    }
+}
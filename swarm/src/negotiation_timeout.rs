@@ -0,0 +1,156 @@
+use crate::{ConnectionId, StreamProtocol};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Timeouts for negotiating the protocol of new streams, see
+/// [`Config::with_negotiation_timeouts`](crate::Config::with_negotiation_timeouts).
+///
+/// The protocol negotiation is the first phase of a stream upgrade, bounded by the timeout of the
+/// [`SubstreamProtocol`](crate::SubstreamProtocol) as a whole. A negotiation timeout
+/// additionally bounds this phase, failing the upgrade with
+/// [`StreamUpgradeError::NegotiationTimeout`](crate::StreamUpgradeError::NegotiationTimeout)
+/// if it is exceeded. This allows to detect unresponsive remotes early, without shortening the
+/// time available to the upgrade itself.
+///
+/// The timeout of a stream is the first one that is set of:
+///
+/// 1. The timeout of its connection.
+/// 2. The longest timeout of the protocols offered on the stream.
+/// 3. The default timeout.
+///
+/// If none is set, only the timeout of the [`SubstreamProtocol`](crate::SubstreamProtocol)
+/// applies. Clones share the same timeouts, thus they can be adjusted after building the
+/// [`Swarm`](crate::Swarm). Changes apply to streams opened afterwards.
+#[derive(Debug, Clone, Default)]
+pub struct NegotiationTimeouts {
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    default: Option<Duration>,
+    protocols: HashMap<StreamProtocol, Duration>,
+    connections: HashMap<ConnectionId, Duration>,
+}
+
+impl NegotiationTimeouts {
+    /// Creates new [`NegotiationTimeouts`] without any timeouts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        self.shared.lock().expect("lock not to be poisoned")
+    }
+
+    /// Sets the timeout of streams without a protocol or connection specific timeout.
+    pub fn set_default_timeout(&self, timeout: Option<Duration>) {
+        self.lock().default = timeout;
+    }
+
+    /// Sets the timeout of streams offering `protocol`.
+    ///
+    /// `None` removes the timeout.
+    pub fn set_protocol_timeout(&self, protocol: StreamProtocol, timeout: Option<Duration>) {
+        let mut shared = self.lock();
+        match timeout {
+            Some(timeout) => shared.protocols.insert(protocol, timeout),
+            None => shared.protocols.remove(&protocol),
+        };
+    }
+
+    /// Sets the timeout of all streams on the given connection, overriding all other timeouts.
+    ///
+    /// `None` removes the timeout. The timeout is forgotten once the connection is closed.
+    pub fn set_connection_timeout(&self, connection_id: ConnectionId, timeout: Option<Duration>) {
+        let mut shared = self.lock();
+        match timeout {
+            Some(timeout) => shared.connections.insert(connection_id, timeout),
+            None => shared.connections.remove(&connection_id),
+        };
+    }
+
+    pub(crate) fn connection(&self, connection_id: ConnectionId) -> ConnectionNegotiationTimeout {
+        ConnectionNegotiationTimeout {
+            timeouts: self.clone(),
+            connection_id,
+        }
+    }
+}
+
+/// The view of a single connection on the [`NegotiationTimeouts`].
+#[derive(Debug)]
+pub(crate) struct ConnectionNegotiationTimeout {
+    timeouts: NegotiationTimeouts,
+    connection_id: ConnectionId,
+}
+
+impl ConnectionNegotiationTimeout {
+    /// Returns the negotiation timeout of a stream offering the given protocols, if any.
+    pub(crate) fn timeout<'a>(
+        &self,
+        protocols: impl IntoIterator<Item = &'a str>,
+    ) -> Option<Duration> {
+        let shared = self.timeouts.lock();
+        if let Some(timeout) = shared.connections.get(&self.connection_id) {
+            return Some(*timeout);
+        }
+
+        protocols
+            .into_iter()
+            .filter_map(|protocol| {
+                shared
+                    .protocols
+                    .iter()
+                    .find(|(p, _)| p.as_ref() == protocol)
+                    .map(|(_, timeout)| *timeout)
+            })
+            .max()
+            .or(shared.default)
+    }
+}
+
+impl Drop for ConnectionNegotiationTimeout {
+    fn drop(&mut self) {
+        self.timeouts.lock().connections.remove(&self.connection_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_timeout_overrides_protocol_timeouts() {
+        let timeouts = NegotiationTimeouts::new();
+        let connection_id = ConnectionId::new_unchecked(0);
+        let connection = timeouts.connection(connection_id);
+        let protocols = ["/a/1.0.0", "/b/1.0.0"];
+
+        assert_eq!(connection.timeout(protocols), None);
+
+        timeouts.set_default_timeout(Some(Duration::from_secs(1)));
+        assert_eq!(connection.timeout(protocols), Some(Duration::from_secs(1)));
+
+        timeouts.set_protocol_timeout(
+            StreamProtocol::new("/a/1.0.0"),
+            Some(Duration::from_secs(2)),
+        );
+        timeouts.set_protocol_timeout(
+            StreamProtocol::new("/b/1.0.0"),
+            Some(Duration::from_secs(3)),
+        );
+        assert_eq!(connection.timeout(protocols), Some(Duration::from_secs(3)));
+        assert_eq!(
+            connection.timeout(["/a/1.0.0"]),
+            Some(Duration::from_secs(2))
+        );
+
+        timeouts.set_connection_timeout(connection_id, Some(Duration::from_secs(5)));
+        assert_eq!(connection.timeout(protocols), Some(Duration::from_secs(5)));
+
+        drop(connection);
+        assert!(timeouts.lock().connections.is_empty());
+    }
+}
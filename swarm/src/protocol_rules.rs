@@ -0,0 +1,582 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Capability-driven connection management.
+//!
+//! The [`Behaviour`] in this module tracks which protocols connected peers support, as reported
+//! by other connection handlers, most notably the one of `libp2p-identify`. Rules can be
+//! registered per protocol:
+//!
+//! - [`Behaviour::watch_protocol`] emits an [`Event`] whenever a peer starts or stops supporting
+//!   the protocol, e.g. to hand the peer to the behaviour implementing that protocol.
+//! - [`Behaviour::keep_connected`] additionally keeps connections to such peers alive and
+//!   re-dials previously seen peers whenever fewer than the given number of them are connected.
+
+use crate::behaviour::{ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm};
+use crate::dial_opts::{DialOpts, PeerCondition};
+use crate::handler::{ConnectionEvent, ProtocolsChange};
+use crate::{
+    ConnectionDenied, ConnectionHandlerEvent, ConnectionId, NetworkBehaviour, StreamProtocol,
+    SubstreamProtocol, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use futures::FutureExt;
+use futures_timer::Delay;
+use libp2p_core::transport::PortUse;
+use libp2p_core::upgrade::DeniedUpgrade;
+use libp2p_core::{ConnectedPoint, Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use lru::LruCache;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::num::NonZeroUsize;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use void::Void;
+
+/// The configuration for [`Behaviour`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    check_interval: Duration,
+    max_candidates: NonZeroUsize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(30),
+            max_candidates: NonZeroUsize::new(64).expect("64 > 0"),
+        }
+    }
+}
+
+impl Config {
+    /// Sets the interval in which [`Behaviour::keep_connected`] rules are checked, in addition to
+    /// whenever a connection closes. The default is 30 seconds.
+    pub fn with_check_interval(mut self, interval: Duration) -> Self {
+        self.check_interval = interval;
+        self
+    }
+
+    /// Sets the number of previously seen peers that are remembered per protocol as dial
+    /// candidates for [`Behaviour::keep_connected`] rules. The default is 64.
+    pub fn with_max_candidates(mut self, max_candidates: NonZeroUsize) -> Self {
+        self.max_candidates = max_candidates;
+        self
+    }
+}
+
+/// Event emitted by the [`Behaviour`] for protocols with a registered rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A connected peer started supporting the protocol.
+    ProtocolSupported {
+        peer_id: PeerId,
+        protocol: StreamProtocol,
+    },
+    /// A peer stopped supporting the protocol or its last connection supporting it closed.
+    ProtocolUnsupported {
+        peer_id: PeerId,
+        protocol: StreamProtocol,
+    },
+}
+
+/// A [`NetworkBehaviour`] applying per-protocol rules to connected peers, see the
+/// [module docs](self).
+pub struct Behaviour {
+    config: Config,
+
+    /// Protocols for which [`Event`]s are emitted.
+    watched: HashSet<StreamProtocol>,
+    /// The minimum number of connected peers per protocol.
+    keep_connected: HashMap<StreamProtocol, usize>,
+
+    /// Protocols supported by the remote of each connection.
+    connections: HashMap<PeerId, HashMap<ConnectionId, ConnectionState>>,
+    /// Peers previously seen supporting a protocol with a [`Behaviour::keep_connected`] rule,
+    /// along with the address we dialed them on, if any.
+    candidates: HashMap<StreamProtocol, LruCache<PeerId, Option<Multiaddr>>>,
+    /// Peers we are currently dialing.
+    dialing: HashSet<PeerId>,
+
+    check_needed: bool,
+    next_check: Delay,
+
+    events: VecDeque<ToSwarm<Event, bool>>,
+}
+
+#[derive(Debug, Default)]
+struct ConnectionState {
+    protocols: HashSet<StreamProtocol>,
+    dialed_address: Option<Multiaddr>,
+    keep_alive: bool,
+}
+
+impl Behaviour {
+    /// Creates a new [`Behaviour`] without any rules.
+    pub fn new(config: Config) -> Self {
+        Self {
+            next_check: Delay::new(config.check_interval),
+            config,
+            watched: HashSet::new(),
+            keep_connected: HashMap::new(),
+            connections: HashMap::new(),
+            candidates: HashMap::new(),
+            dialing: HashSet::new(),
+            check_needed: false,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Emits [`Event`]s whenever a peer starts or stops supporting the given protocol.
+    ///
+    /// Peers that already support the protocol are reported right away.
+    pub fn watch_protocol(&mut self, protocol: StreamProtocol) {
+        if !self.watched.insert(protocol.clone()) {
+            return;
+        }
+
+        for peer_id in self
+            .peers_supporting(&protocol)
+            .copied()
+            .collect::<Vec<_>>()
+        {
+            self.events
+                .push_back(ToSwarm::GenerateEvent(Event::ProtocolSupported {
+                    peer_id,
+                    protocol: protocol.clone(),
+                }));
+        }
+    }
+
+    /// Keeps at least `min_peers` peers supporting the given protocol connected.
+    ///
+    /// Connections to peers supporting the protocol are kept alive. Whenever fewer than
+    /// `min_peers` such peers are connected, peers previously seen supporting the protocol are
+    /// dialed again. Implies [`Behaviour::watch_protocol`].
+    pub fn keep_connected(&mut self, protocol: StreamProtocol, min_peers: usize) {
+        self.watch_protocol(protocol.clone());
+        self.keep_connected.insert(protocol.clone(), min_peers);
+
+        let max_candidates = self.config.max_candidates;
+        let candidates = self
+            .candidates
+            .entry(protocol.clone())
+            .or_insert_with(|| LruCache::new(max_candidates));
+        for (peer_id, connections) in &self.connections {
+            for connection in connections.values() {
+                if connection.protocols.contains(&protocol) {
+                    candidates.put(*peer_id, connection.dialed_address.clone());
+                }
+            }
+        }
+
+        self.update_keep_alive();
+        self.check_needed = true;
+    }
+
+    /// Removes all rules for the given protocol.
+    pub fn remove_rules(&mut self, protocol: &StreamProtocol) {
+        self.watched.remove(protocol);
+        self.keep_connected.remove(protocol);
+        self.candidates.remove(protocol);
+
+        self.update_keep_alive();
+    }
+
+    /// Returns the connected peers that support the given protocol.
+    pub fn peers_supporting<'a>(
+        &'a self,
+        protocol: &'a StreamProtocol,
+    ) -> impl Iterator<Item = &'a PeerId> + 'a {
+        self.connections
+            .iter()
+            .filter(|(_, connections)| {
+                connections
+                    .values()
+                    .any(|connection| connection.protocols.contains(protocol))
+            })
+            .map(|(peer_id, _)| peer_id)
+    }
+
+    fn supports(&self, peer_id: &PeerId, protocol: &StreamProtocol) -> bool {
+        self.connections.get(peer_id).is_some_and(|connections| {
+            connections
+                .values()
+                .any(|connection| connection.protocols.contains(protocol))
+        })
+    }
+
+    /// Instructs all handlers whose keep-alive state changed.
+    fn update_keep_alive(&mut self) {
+        for (peer_id, connections) in &mut self.connections {
+            for (connection_id, connection) in connections {
+                let keep_alive = connection
+                    .protocols
+                    .iter()
+                    .any(|protocol| self.keep_connected.contains_key(protocol));
+                if keep_alive == connection.keep_alive {
+                    continue;
+                }
+
+                connection.keep_alive = keep_alive;
+                self.events.push_back(ToSwarm::NotifyHandler {
+                    peer_id: *peer_id,
+                    handler: crate::NotifyHandler::One(*connection_id),
+                    event: keep_alive,
+                });
+            }
+        }
+    }
+
+    fn on_protocols_added(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        protocols: Vec<StreamProtocol>,
+    ) {
+        for protocol in protocols {
+            let was_supported = self.supports(&peer_id, &protocol);
+            let Some(connection) = self
+                .connections
+                .get_mut(&peer_id)
+                .and_then(|connections| connections.get_mut(&connection_id))
+            else {
+                return;
+            };
+            connection.protocols.insert(protocol.clone());
+
+            if let Some(candidates) = self.candidates.get_mut(&protocol) {
+                candidates.put(peer_id, connection.dialed_address.clone());
+            }
+            if !was_supported && self.watched.contains(&protocol) {
+                self.events
+                    .push_back(ToSwarm::GenerateEvent(Event::ProtocolSupported {
+                        peer_id,
+                        protocol,
+                    }));
+            }
+        }
+
+        self.update_keep_alive();
+    }
+
+    fn on_protocols_removed(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        protocols: Vec<StreamProtocol>,
+    ) {
+        for protocol in protocols {
+            let Some(connection) = self
+                .connections
+                .get_mut(&peer_id)
+                .and_then(|connections| connections.get_mut(&connection_id))
+            else {
+                return;
+            };
+            if !connection.protocols.remove(&protocol) || self.supports(&peer_id, &protocol) {
+                continue;
+            }
+
+            // The peer explicitly stopped supporting the protocol, don't dial it for it anymore.
+            if let Some(candidates) = self.candidates.get_mut(&protocol) {
+                candidates.pop(&peer_id);
+            }
+            if self.watched.contains(&protocol) {
+                self.events
+                    .push_back(ToSwarm::GenerateEvent(Event::ProtocolUnsupported {
+                        peer_id,
+                        protocol,
+                    }));
+            }
+        }
+
+        self.update_keep_alive();
+        self.check_needed = true;
+    }
+
+    fn on_connection_established(
+        &mut self,
+        ConnectionEstablished {
+            peer_id,
+            connection_id,
+            endpoint,
+            ..
+        }: ConnectionEstablished,
+    ) {
+        self.dialing.remove(&peer_id);
+
+        let dialed_address = match endpoint {
+            ConnectedPoint::Dialer { address, .. } => Some(address.clone()),
+            ConnectedPoint::Listener { .. } => None,
+        };
+        self.connections.entry(peer_id).or_default().insert(
+            connection_id,
+            ConnectionState {
+                dialed_address,
+                ..Default::default()
+            },
+        );
+    }
+
+    fn on_connection_closed(
+        &mut self,
+        ConnectionClosed {
+            peer_id,
+            connection_id,
+            ..
+        }: ConnectionClosed,
+    ) {
+        let Some(connections) = self.connections.get_mut(&peer_id) else {
+            return;
+        };
+        let Some(connection) = connections.remove(&connection_id) else {
+            return;
+        };
+        if connections.is_empty() {
+            self.connections.remove(&peer_id);
+        }
+
+        for protocol in connection.protocols {
+            if self.watched.contains(&protocol) && !self.supports(&peer_id, &protocol) {
+                self.events
+                    .push_back(ToSwarm::GenerateEvent(Event::ProtocolUnsupported {
+                        peer_id,
+                        protocol,
+                    }));
+            }
+        }
+
+        self.check_needed = true;
+    }
+
+    /// Dials candidates for all protocols with fewer connected peers than required.
+    fn check_rules(&mut self) {
+        for (protocol, min_peers) in &self.keep_connected {
+            let Some(candidates) = self.candidates.get(protocol) else {
+                continue;
+            };
+
+            let connected = self
+                .connections
+                .iter()
+                .filter(|(_, connections)| {
+                    connections
+                        .values()
+                        .any(|connection| connection.protocols.contains(protocol))
+                })
+                .count();
+            let dialing = candidates
+                .iter()
+                .filter(|(peer_id, _)| self.dialing.contains(*peer_id))
+                .count();
+            let missing = min_peers.saturating_sub(connected + dialing);
+            if missing == 0 {
+                continue;
+            }
+
+            let to_dial = candidates
+                .iter()
+                .map(|(peer_id, _)| *peer_id)
+                .filter(|peer_id| {
+                    !self.connections.contains_key(peer_id) && !self.dialing.contains(peer_id)
+                })
+                .take(missing)
+                .collect::<Vec<_>>();
+
+            for peer_id in to_dial {
+                tracing::debug!(peer=%peer_id, %protocol, "Dialing peer to satisfy protocol rule");
+
+                self.dialing.insert(peer_id);
+                self.events.push_back(ToSwarm::Dial {
+                    opts: DialOpts::peer_id(peer_id)
+                        .condition(PeerCondition::DisconnectedAndNotDialing)
+                        .build(),
+                });
+            }
+        }
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = ConnectionHandler;
+    type ToSwarm = Event;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(ConnectionHandler::default())
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        _: &[Multiaddr],
+        _: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        let Some(peer_id) = maybe_peer else {
+            return Ok(vec![]);
+        };
+
+        let addresses = self
+            .candidates
+            .values()
+            .filter_map(|candidates| candidates.peek(&peer_id).cloned().flatten())
+            .collect::<HashSet<_>>();
+
+        Ok(addresses.into_iter().collect())
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: ConnectionId,
+        _: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+        _: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(ConnectionHandler::default())
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(e) => self.on_connection_established(e),
+            FromSwarm::ConnectionClosed(e) => self.on_connection_closed(e),
+            FromSwarm::DialFailure(DialFailure {
+                peer_id: Some(peer_id),
+                ..
+            }) => {
+                self.dialing.remove(&peer_id);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        match event {
+            HandlerEvent::ProtocolsAdded(protocols) => {
+                self.on_protocols_added(peer_id, connection_id, protocols)
+            }
+            HandlerEvent::ProtocolsRemoved(protocols) => {
+                self.on_protocols_removed(peer_id, connection_id, protocols)
+            }
+        }
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        if self.next_check.poll_unpin(cx).is_ready() {
+            self.next_check.reset(self.config.check_interval);
+            self.check_needed = true;
+        }
+
+        if std::mem::take(&mut self.check_needed) {
+            self.check_rules();
+        }
+
+        if let Some(event) = self.events.pop_front() {
+            return Poll::Ready(event);
+        }
+
+        Poll::Pending
+    }
+}
+
+/// Event reported by the [`ConnectionHandler`] to the [`Behaviour`].
+#[derive(Debug)]
+pub enum HandlerEvent {
+    ProtocolsAdded(Vec<StreamProtocol>),
+    ProtocolsRemoved(Vec<StreamProtocol>),
+}
+
+/// The [`ConnectionHandler`](crate::ConnectionHandler) of the [`Behaviour`], reporting changes to
+/// the protocols supported by the remote.
+///
+/// The behaviour instructs it whether to keep the connection alive.
+#[derive(Default)]
+pub struct ConnectionHandler {
+    keep_alive: bool,
+    events: VecDeque<HandlerEvent>,
+}
+
+impl crate::handler::ConnectionHandler for ConnectionHandler {
+    type FromBehaviour = bool;
+    type ToBehaviour = HandlerEvent;
+    type InboundProtocol = DeniedUpgrade;
+    type OutboundProtocol = DeniedUpgrade;
+    type InboundOpenInfo = ();
+    type OutboundOpenInfo = Void;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        SubstreamProtocol::new(DeniedUpgrade, ())
+    }
+
+    fn on_behaviour_event(&mut self, keep_alive: bool) {
+        self.keep_alive = keep_alive;
+    }
+
+    fn connection_keep_alive(&self) -> bool {
+        self.keep_alive
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<
+        ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
+    > {
+        if let Some(event) = self.events.pop_front() {
+            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(event));
+        }
+
+        Poll::Pending
+    }
+
+    fn on_connection_event(
+        &mut self,
+        event: ConnectionEvent<
+            Self::InboundProtocol,
+            Self::OutboundProtocol,
+            Self::InboundOpenInfo,
+            Self::OutboundOpenInfo,
+        >,
+    ) {
+        match event {
+            ConnectionEvent::RemoteProtocolsChange(ProtocolsChange::Added(protocols)) => self
+                .events
+                .push_back(HandlerEvent::ProtocolsAdded(protocols.cloned().collect())),
+            ConnectionEvent::RemoteProtocolsChange(ProtocolsChange::Removed(protocols)) => self
+                .events
+                .push_back(HandlerEvent::ProtocolsRemoved(protocols.cloned().collect())),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,314 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Pruning of idle and least-recently-used connections.
+//!
+//! Once enabled via [`Config::with_pruning_policy`](crate::Config::with_pruning_policy), the
+//! established connections are checked periodically. Connections without any stream activity for
+//! longer than [`PruningPolicy::with_max_idle`] are closed, regardless of whether their handlers
+//! want to keep them alive. While more connections than [`PruningPolicy::with_soft_cap`] are
+//! established, the least-recently-used ones are closed as well.
+//!
+//! Unlike hard connection limits, the soft cap never denies new connections. Connections to
+//! peers protected via [`Swarm::protect_peer`](crate::Swarm::protect_peer) are never pruned, nor
+//! are connections carrying one of the tags given to [`PruningPolicy::with_protected_tag`].
+//!
+//! Stream activity is the opening of a new stream or the receipt of data on a stream that isn't
+//! [ignored for keep-alive](crate::Stream::ignore_for_keep_alive).
+
+use crate::{ConnectionId, ConnectionTags, TagValue};
+use futures::FutureExt;
+use futures_timer::Delay;
+use libp2p_identity::PeerId;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use web_time::Instant;
+
+/// The policy for pruning established connections.
+#[derive(Debug, Clone)]
+pub struct PruningPolicy {
+    max_idle: Option<Duration>,
+    soft_cap: Option<usize>,
+    protected_tags: Vec<(String, TagValue)>,
+    check_interval: Duration,
+}
+
+impl Default for PruningPolicy {
+    fn default() -> Self {
+        Self {
+            max_idle: None,
+            soft_cap: None,
+            protected_tags: Vec::new(),
+            check_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl PruningPolicy {
+    /// Creates a new [`PruningPolicy`] that doesn't prune any connections.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Closes connections without stream activity for at least `max_idle`.
+    pub fn with_max_idle(mut self, max_idle: Duration) -> Self {
+        self.max_idle = Some(max_idle);
+        self
+    }
+
+    /// Closes the least-recently-used connections while more than `soft_cap` connections are
+    /// established.
+    pub fn with_soft_cap(mut self, soft_cap: usize) -> Self {
+        self.soft_cap = Some(soft_cap);
+        self
+    }
+
+    /// Never closes connections on which the tag `key` is set to `value`, see
+    /// [`ConnectionTags`].
+    ///
+    /// Can be called multiple times to protect several tags. Protected connections still count
+    /// towards the soft cap.
+    pub fn with_protected_tag(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<TagValue>,
+    ) -> Self {
+        self.protected_tags.push((key.into(), value.into()));
+        self
+    }
+
+    /// Sets how often connections are checked for pruning.
+    ///
+    /// Defaults to 10 seconds.
+    pub fn with_check_interval(mut self, interval: Duration) -> Self {
+        self.check_interval = interval;
+        self
+    }
+}
+
+/// The time of the last stream activity on a connection, shared with its task.
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectionActivity(Arc<Mutex<Instant>>);
+
+impl ConnectionActivity {
+    pub(crate) fn new() -> Self {
+        Self(Arc::new(Mutex::new(Instant::now())))
+    }
+
+    pub(crate) fn record(&self) {
+        *self.0.lock().expect("lock not to be poisoned") = Instant::now();
+    }
+
+    pub(crate) fn last(&self) -> Instant {
+        *self.0.lock().expect("lock not to be poisoned")
+    }
+}
+
+/// Applies a [`PruningPolicy`] to the established connections of the pool.
+#[derive(Debug)]
+pub(crate) struct Pruner {
+    policy: Option<PruningPolicy>,
+    /// The tags protecting each peer from pruning.
+    protected: HashMap<PeerId, HashSet<String>>,
+    next_check: Option<Delay>,
+}
+
+impl Pruner {
+    pub(crate) fn new(policy: Option<PruningPolicy>) -> Self {
+        Self {
+            next_check: policy
+                .as_ref()
+                .map(|policy| Delay::new(policy.check_interval)),
+            policy,
+            protected: Default::default(),
+        }
+    }
+
+    /// Protects `peer` from pruning until `tag` is removed via [`Pruner::unprotect`].
+    pub(crate) fn protect(&mut self, peer: PeerId, tag: &str) {
+        self.protected
+            .entry(peer)
+            .or_default()
+            .insert(tag.to_owned());
+    }
+
+    /// Removes `tag` from `peer`, returning whether the peer had the tag.
+    ///
+    /// The peer remains protected while it has other tags.
+    pub(crate) fn unprotect(&mut self, peer: PeerId, tag: &str) -> bool {
+        let Some(tags) = self.protected.get_mut(&peer) else {
+            return false;
+        };
+        let removed = tags.remove(tag);
+        if tags.is_empty() {
+            self.protected.remove(&peer);
+        }
+        removed
+    }
+
+    pub(crate) fn is_protected(&self, peer: &PeerId) -> bool {
+        self.protected.contains_key(peer)
+    }
+
+    /// Polls for the next time connections are to be checked for pruning.
+    pub(crate) fn poll_check(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let (Some(policy), Some(delay)) = (&self.policy, &mut self.next_check) else {
+            return Poll::Pending;
+        };
+        futures::ready!(delay.poll_unpin(cx));
+
+        *delay = Delay::new(policy.check_interval);
+        let _ = delay.poll_unpin(cx);
+        Poll::Ready(())
+    }
+
+    /// Selects the connections to prune out of all established `connections`, given with the time
+    /// of their last activity and their tags.
+    pub(crate) fn select<'a>(
+        &self,
+        now: Instant,
+        connections: impl IntoIterator<Item = (PeerId, ConnectionId, Instant, &'a ConnectionTags)>,
+    ) -> Vec<(PeerId, ConnectionId)> {
+        let Some(policy) = &self.policy else {
+            return Vec::new();
+        };
+
+        let mut remaining = 0;
+        let mut candidates = Vec::new();
+        for (peer, id, last_activity, tags) in connections {
+            remaining += 1;
+            let has_protected_tag = policy
+                .protected_tags
+                .iter()
+                .any(|(key, value)| tags.contains(key, value));
+            if !self.is_protected(&peer) && !has_protected_tag {
+                candidates.push((peer, id, last_activity));
+            }
+        }
+        // Least-recently-used first.
+        candidates.sort_by_key(|(_, _, last_activity)| *last_activity);
+
+        let mut pruned = Vec::new();
+        for (peer, id, last_activity) in candidates {
+            let is_idle = policy
+                .max_idle
+                .is_some_and(|max_idle| now.saturating_duration_since(last_activity) >= max_idle);
+            let is_above_cap = policy.soft_cap.is_some_and(|cap| remaining > cap);
+            if !is_idle && !is_above_cap {
+                break;
+            }
+            pruned.push((peer, id));
+            remaining -= 1;
+        }
+
+        pruned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prunes_idle_connections_of_unprotected_peers() {
+        let mut pruner = Pruner::new(Some(
+            PruningPolicy::new().with_max_idle(Duration::from_secs(60)),
+        ));
+        let now = Instant::now();
+        let idle = now - Duration::from_secs(90);
+        let (a, b, c) = (PeerId::random(), PeerId::random(), PeerId::random());
+
+        let untagged = ConnectionTags::default();
+        pruner.protect(b, "bootstrap");
+        let connections = [
+            (a, ConnectionId::new_unchecked(1), idle, &untagged),
+            (b, ConnectionId::new_unchecked(2), idle, &untagged),
+            (c, ConnectionId::new_unchecked(3), now, &untagged),
+        ];
+        assert_eq!(
+            pruner.select(now, connections),
+            vec![(a, ConnectionId::new_unchecked(1))]
+        );
+
+        assert!(pruner.unprotect(b, "bootstrap"));
+        assert!(!pruner.unprotect(b, "bootstrap"));
+        assert_eq!(pruner.select(now, connections).len(), 2);
+    }
+
+    #[test]
+    fn prunes_least_recently_used_connections_above_soft_cap() {
+        let mut pruner = Pruner::new(Some(PruningPolicy::new().with_soft_cap(2)));
+        let now = Instant::now();
+        let (a, b, c, d) = (
+            PeerId::random(),
+            PeerId::random(),
+            PeerId::random(),
+            PeerId::random(),
+        );
+
+        let ago = |secs| now - Duration::from_secs(secs);
+
+        let untagged = ConnectionTags::default();
+        pruner.protect(a, "relay");
+        let connections = [
+            (a, ConnectionId::new_unchecked(1), ago(40), &untagged),
+            (b, ConnectionId::new_unchecked(2), ago(30), &untagged),
+            (c, ConnectionId::new_unchecked(3), ago(20), &untagged),
+            (d, ConnectionId::new_unchecked(4), ago(10), &untagged),
+        ];
+
+        // Protected connections count towards the cap but are never pruned.
+        assert_eq!(
+            pruner.select(now, connections),
+            vec![
+                (b, ConnectionId::new_unchecked(2)),
+                (c, ConnectionId::new_unchecked(3))
+            ]
+        );
+    }
+
+    #[test]
+    fn never_prunes_connections_with_protected_tags() {
+        let pruner = Pruner::new(Some(
+            PruningPolicy::new()
+                .with_max_idle(Duration::from_secs(60))
+                .with_protected_tag("role", "validator"),
+        ));
+        let now = Instant::now();
+        let idle = now - Duration::from_secs(90);
+        let peer = PeerId::random();
+
+        let mut validator = ConnectionTags::default();
+        validator.insert("role", "validator");
+        let mut observer = ConnectionTags::default();
+        observer.insert("role", "observer");
+        let connections = [
+            (peer, ConnectionId::new_unchecked(1), idle, &validator),
+            (peer, ConnectionId::new_unchecked(2), idle, &observer),
+        ];
+
+        assert_eq!(
+            pruner.select(now, connections),
+            vec![(peer, ConnectionId::new_unchecked(2))]
+        );
+    }
+}
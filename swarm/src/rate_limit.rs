@@ -0,0 +1,299 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Rate limiting of the data received on negotiated streams.
+//!
+//! Once enabled via [`Config::with_stream_rate_limiter`](crate::Config::with_stream_rate_limiter),
+//! reads from a [`Stream`](crate::Stream) consume tokens of token buckets of its remote peer:
+//! one for the protocol negotiated on the stream and one shared by all protocols.
+//! A stream whose buckets are empty stops reading until they are refilled, exerting
+//! backpressure on the remote via the flow control of the stream multiplexer, while streams of
+//! other protocols keep making progress.
+//!
+//! The buckets of a peer are shared by all its connections and dropped once it has no
+//! rate-limited streams left.
+
+use crate::StreamProtocol;
+use futures::FutureExt;
+use futures_timer::Delay;
+use libp2p_identity::PeerId;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use web_time::Instant;
+
+/// The rate and burst of a token bucket, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    bytes_per_second: u64,
+    burst: u64,
+}
+
+impl RateLimit {
+    /// Allows `bytes_per_second` on average, with bursts of up to `burst` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_second` or `burst` is zero.
+    pub fn new(bytes_per_second: u64, burst: u64) -> Self {
+        assert!(bytes_per_second > 0, "rate must be positive");
+        assert!(burst > 0, "burst must be positive");
+        Self {
+            bytes_per_second,
+            burst,
+        }
+    }
+
+    /// The number of bytes allowed per second on average.
+    pub fn bytes_per_second(&self) -> u64 {
+        self.bytes_per_second
+    }
+
+    /// The maximum number of bytes allowed at once.
+    pub fn burst(&self) -> u64 {
+        self.burst
+    }
+}
+
+/// Limits the rate at which data is read from the streams of each remote peer.
+///
+/// Clones share the same buckets.
+#[derive(Debug, Default, Clone)]
+pub struct StreamRateLimiter {
+    per_protocol: HashMap<StreamProtocol, RateLimit>,
+    per_peer: Option<RateLimit>,
+    buckets: Arc<Mutex<HashMap<(PeerId, Option<StreamProtocol>), Arc<Mutex<Bucket>>>>>,
+}
+
+impl StreamRateLimiter {
+    /// Creates a new [`StreamRateLimiter`] without any limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the data each peer can send on streams of `protocol`.
+    pub fn with_protocol_limit(mut self, protocol: StreamProtocol, limit: RateLimit) -> Self {
+        self.per_protocol.insert(protocol, limit);
+        self
+    }
+
+    /// Limits the data each peer can send on streams of all protocols combined.
+    pub fn with_peer_limit(mut self, limit: RateLimit) -> Self {
+        self.per_peer = Some(limit);
+        self
+    }
+
+    fn lock(
+        &self,
+    ) -> MutexGuard<'_, HashMap<(PeerId, Option<StreamProtocol>), Arc<Mutex<Bucket>>>> {
+        self.buckets.lock().expect("lock not to be poisoned")
+    }
+
+    /// Returns the rate limit of a new stream with `peer`, negotiated for `protocol`.
+    ///
+    /// Returns `None` if no limit applies to the stream.
+    pub(crate) fn stream(&self, peer: PeerId, protocol: &str) -> Option<StreamRateLimit> {
+        let protocol_limit = self
+            .per_protocol
+            .iter()
+            .find(|(p, _)| p.as_ref() == protocol)
+            .map(|(p, limit)| (p.clone(), *limit));
+        if protocol_limit.is_none() && self.per_peer.is_none() {
+            return None;
+        }
+
+        let now = Instant::now();
+        let mut buckets = self.lock();
+        // Drop the buckets of peers without streams.
+        buckets.retain(|_, bucket| Arc::strong_count(bucket) > 1);
+
+        let mut stream_buckets = Vec::with_capacity(2);
+        if let Some((protocol, limit)) = protocol_limit {
+            stream_buckets.push(
+                buckets
+                    .entry((peer, Some(protocol)))
+                    .or_insert_with(|| Arc::new(Mutex::new(Bucket::new(limit, now))))
+                    .clone(),
+            );
+        }
+        if let Some(limit) = self.per_peer {
+            stream_buckets.push(
+                buckets
+                    .entry((peer, None))
+                    .or_insert_with(|| Arc::new(Mutex::new(Bucket::new(limit, now))))
+                    .clone(),
+            );
+        }
+
+        Some(StreamRateLimit {
+            buckets: stream_buckets,
+            delay: None,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    limit: RateLimit,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            refilled_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.limit.bytes_per_second as f64)
+            .min(self.limit.burst as f64);
+        self.refilled_at = now;
+    }
+
+    /// The time until at least one token is available.
+    fn time_until_available(&self) -> Duration {
+        Duration::from_secs_f64((1.0 - self.tokens).max(0.0) / self.limit.bytes_per_second as f64)
+    }
+}
+
+/// The rate limit of a single [`Stream`](crate::Stream).
+#[derive(Debug)]
+pub(crate) struct StreamRateLimit {
+    buckets: Vec<Arc<Mutex<Bucket>>>,
+    delay: Option<Delay>,
+}
+
+impl StreamRateLimit {
+    /// Polls for the number of bytes the stream may read, waiting until it is at least one.
+    pub(crate) fn poll_available(&mut self, cx: &mut Context<'_>) -> Poll<usize> {
+        loop {
+            if let Some(delay) = self.delay.as_mut() {
+                futures::ready!(delay.poll_unpin(cx));
+                self.delay = None;
+            }
+
+            let now = Instant::now();
+            let mut available = u64::MAX;
+            let mut wait = Duration::ZERO;
+            for bucket in &self.buckets {
+                let mut bucket = bucket.lock().expect("lock not to be poisoned");
+                bucket.refill(now);
+                available = available.min(bucket.tokens as u64);
+                wait = wait.max(bucket.time_until_available());
+            }
+
+            if available > 0 {
+                return Poll::Ready(usize::try_from(available).unwrap_or(usize::MAX));
+            }
+            self.delay = Some(Delay::new(wait));
+        }
+    }
+
+    /// Consumes the tokens of `bytes` that were read.
+    pub(crate) fn consume(&self, bytes: usize) {
+        for bucket in &self.buckets {
+            bucket.lock().expect("lock not to be poisoned").tokens -= bytes as f64;
+        }
+    }
+}
+
+/// The rate limiter of a connection to a single peer.
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectionRateLimiter {
+    limiter: StreamRateLimiter,
+    peer: PeerId,
+}
+
+impl ConnectionRateLimiter {
+    pub(crate) fn new(limiter: StreamRateLimiter, peer: PeerId) -> Self {
+        Self { limiter, peer }
+    }
+
+    pub(crate) fn stream(&self, protocol: &str) -> Option<StreamRateLimit> {
+        self.limiter.stream(self.peer, protocol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::task::noop_waker_ref;
+
+    #[test]
+    fn limits_reads_to_available_tokens() {
+        let limiter = StreamRateLimiter::new()
+            .with_protocol_limit(StreamProtocol::new("/ping"), RateLimit::new(1, 100));
+        let peer = PeerId::random();
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let mut stream = limiter.stream(peer, "/ping").unwrap();
+        assert_eq!(stream.poll_available(&mut cx), Poll::Ready(100));
+        stream.consume(60);
+
+        // Streams of the same peer and protocol share the bucket.
+        let mut other = limiter.stream(peer, "/ping").unwrap();
+        assert_eq!(other.poll_available(&mut cx), Poll::Ready(40));
+        other.consume(40);
+        assert!(other.poll_available(&mut cx).is_pending());
+
+        // Other peers and protocols are not affected.
+        assert!(limiter.stream(peer, "/kad").is_none());
+        let mut other_peer = limiter.stream(PeerId::random(), "/ping").unwrap();
+        assert_eq!(other_peer.poll_available(&mut cx), Poll::Ready(100));
+    }
+
+    #[test]
+    fn peer_limit_is_shared_across_protocols() {
+        let limiter = StreamRateLimiter::new()
+            .with_protocol_limit(StreamProtocol::new("/ping"), RateLimit::new(1, 100))
+            .with_peer_limit(RateLimit::new(1, 50));
+        let peer = PeerId::random();
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let mut ping = limiter.stream(peer, "/ping").unwrap();
+        assert_eq!(ping.poll_available(&mut cx), Poll::Ready(50));
+        ping.consume(30);
+
+        let mut kad = limiter.stream(peer, "/kad").unwrap();
+        assert_eq!(kad.poll_available(&mut cx), Poll::Ready(20));
+    }
+
+    #[test]
+    fn drops_buckets_of_peers_without_streams() {
+        let limiter = StreamRateLimiter::new().with_peer_limit(RateLimit::new(1, 10));
+        let peer = PeerId::random();
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let mut stream = limiter.stream(peer, "/ping").unwrap();
+        stream.consume(10);
+        assert!(stream.poll_available(&mut cx).is_pending());
+        drop(stream);
+
+        let mut stream = limiter.stream(PeerId::random(), "/ping").unwrap();
+        assert_eq!(stream.poll_available(&mut cx), Poll::Ready(10));
+        assert_eq!(limiter.lock().len(), 1);
+    }
+}
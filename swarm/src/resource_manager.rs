@@ -0,0 +1,652 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Limits on the resources used by the local node.
+//!
+//! Resources are accounted in three kinds of [`Scope`]s: the whole node, each remote peer and
+//! each protocol. A reservation only succeeds if it stays within the [`Limits`] of every scope
+//! it is accounted in.
+//!
+//! - The [`Behaviour`] in this module enforces limits on established connections and pending
+//!   dials and reports every exceeded limit as an [`Event`].
+//! - Connection handlers and other behaviours obtain a [`ResourceManager`] handle via
+//!   [`Behaviour::resource_manager`] and reserve streams and memory through it before using them.
+//!   Reserved resources are released once the returned [`Reservation`] is dropped.
+
+use crate::behaviour::{ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm};
+use crate::{
+    dummy, ConnectionDenied, ConnectionId, NetworkBehaviour, StreamProtocol, THandler,
+    THandlerInEvent, THandlerOutEvent, ToSwarm,
+};
+use libp2p_core::transport::PortUse;
+use libp2p_core::{Endpoint, Multiaddr};
+use libp2p_identity::PeerId;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+/// A resource whose usage is limited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resource {
+    /// Established connections.
+    Connections,
+    /// Outbound connections that are being established.
+    PendingDials,
+    /// Open streams.
+    Streams,
+    /// Memory in bytes, as reserved by connection handlers and behaviours.
+    Memory,
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Resource::Connections => write!(f, "connections"),
+            Resource::PendingDials => write!(f, "pending dials"),
+            Resource::Streams => write!(f, "streams"),
+            Resource::Memory => write!(f, "bytes of memory"),
+        }
+    }
+}
+
+/// The scope in which resources are accounted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Scope {
+    /// All resources used by the local node.
+    System,
+    /// The resources used for a remote peer.
+    Peer(PeerId),
+    /// The resources used by a protocol.
+    Protocol(StreamProtocol),
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::System => write!(f, "system"),
+            Scope::Peer(peer_id) => write!(f, "peer {peer_id}"),
+            Scope::Protocol(protocol) => write!(f, "protocol {protocol}"),
+        }
+    }
+}
+
+/// The limits of a single [`Scope`]. All resources are unlimited by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    connections: Option<usize>,
+    pending_dials: Option<usize>,
+    streams: Option<usize>,
+    memory: Option<usize>,
+}
+
+impl Limits {
+    /// Configures the maximum number of established connections.
+    pub fn with_max_connections(mut self, limit: Option<usize>) -> Self {
+        self.connections = limit;
+        self
+    }
+
+    /// Configures the maximum number of outbound connections being established.
+    pub fn with_max_pending_dials(mut self, limit: Option<usize>) -> Self {
+        self.pending_dials = limit;
+        self
+    }
+
+    /// Configures the maximum number of open streams.
+    pub fn with_max_streams(mut self, limit: Option<usize>) -> Self {
+        self.streams = limit;
+        self
+    }
+
+    /// Configures the maximum number of bytes of reserved memory.
+    pub fn with_max_memory(mut self, limit: Option<usize>) -> Self {
+        self.memory = limit;
+        self
+    }
+
+    fn get(&self, resource: Resource) -> Option<usize> {
+        match resource {
+            Resource::Connections => self.connections,
+            Resource::PendingDials => self.pending_dials,
+            Resource::Streams => self.streams,
+            Resource::Memory => self.memory,
+        }
+    }
+}
+
+/// The configuration for [`Behaviour`].
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    system: Limits,
+    peer: Limits,
+    protocols: HashMap<StreamProtocol, Limits>,
+    default_protocol: Limits,
+}
+
+impl Config {
+    /// Sets the limits of the [`Scope::System`] scope.
+    pub fn with_system_limits(mut self, limits: Limits) -> Self {
+        self.system = limits;
+        self
+    }
+
+    /// Sets the limits that apply to each [`Scope::Peer`] scope.
+    pub fn with_peer_limits(mut self, limits: Limits) -> Self {
+        self.peer = limits;
+        self
+    }
+
+    /// Sets the limits of the [`Scope::Protocol`] scope of the given protocol.
+    pub fn with_protocol_limits(mut self, protocol: StreamProtocol, limits: Limits) -> Self {
+        self.protocols.insert(protocol, limits);
+        self
+    }
+
+    /// Sets the limits that apply to the [`Scope::Protocol`] scopes of protocols without limits
+    /// of their own.
+    pub fn with_default_protocol_limits(mut self, limits: Limits) -> Self {
+        self.default_protocol = limits;
+        self
+    }
+
+    fn limits(&self, scope: &Scope) -> &Limits {
+        match scope {
+            Scope::System => &self.system,
+            Scope::Peer(_) => &self.peer,
+            Scope::Protocol(protocol) => self
+                .protocols
+                .get(protocol)
+                .unwrap_or(&self.default_protocol),
+        }
+    }
+}
+
+/// The resources currently used in a [`Scope`], see [`ResourceManager::usage`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// The number of established connections.
+    pub connections: usize,
+    /// The number of outbound connections being established.
+    pub pending_dials: usize,
+    /// The number of open streams.
+    pub streams: usize,
+    /// The number of bytes of reserved memory.
+    pub memory: usize,
+}
+
+impl Usage {
+    fn get_mut(&mut self, resource: Resource) -> &mut usize {
+        match resource {
+            Resource::Connections => &mut self.connections,
+            Resource::PendingDials => &mut self.pending_dials,
+            Resource::Streams => &mut self.streams,
+            Resource::Memory => &mut self.memory,
+        }
+    }
+}
+
+/// A reservation was denied because it would have exceeded the limit of a scope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitExceeded {
+    scope: Scope,
+    resource: Resource,
+    limit: usize,
+}
+
+impl LimitExceeded {
+    /// The scope whose limit would have been exceeded.
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+
+    /// The resource whose limit would have been exceeded.
+    pub fn resource(&self) -> Resource {
+        self.resource
+    }
+
+    /// The limit of the resource in the scope.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "resource limit exceeded: at most {} {} are allowed in the {} scope",
+            self.limit, self.resource, self.scope
+        )
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// Event emitted by the [`Behaviour`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A reservation was denied, either by the [`Behaviour`] itself or through a
+    /// [`ResourceManager`] handle.
+    LimitExceeded(LimitExceeded),
+}
+
+struct Shared {
+    config: Config,
+    system: Usage,
+    peers: HashMap<PeerId, Usage>,
+    protocols: HashMap<StreamProtocol, Usage>,
+    /// The number of denied reservations per resource.
+    num_exceeded: HashMap<Resource, u64>,
+    exceeded: VecDeque<LimitExceeded>,
+    waker: Option<Waker>,
+}
+
+impl Shared {
+    fn scopes(peer: Option<PeerId>, protocol: Option<&StreamProtocol>) -> Vec<Scope> {
+        std::iter::once(Scope::System)
+            .chain(peer.map(Scope::Peer))
+            .chain(protocol.cloned().map(Scope::Protocol))
+            .collect()
+    }
+
+    fn usage_mut(&mut self, scope: &Scope) -> &mut Usage {
+        match scope {
+            Scope::System => &mut self.system,
+            Scope::Peer(peer_id) => self.peers.entry(*peer_id).or_default(),
+            Scope::Protocol(protocol) => self.protocols.entry(protocol.clone()).or_default(),
+        }
+    }
+
+    fn usage(&self, scope: &Scope) -> Usage {
+        match scope {
+            Scope::System => self.system,
+            Scope::Peer(peer_id) => self.peers.get(peer_id).copied().unwrap_or_default(),
+            Scope::Protocol(protocol) => self.protocols.get(protocol).copied().unwrap_or_default(),
+        }
+    }
+
+    /// Checks whether `amount` of `resource` can be reserved in all `scopes`, reporting the
+    /// first exceeded limit otherwise.
+    fn check(
+        &mut self,
+        scopes: &[Scope],
+        resource: Resource,
+        amount: usize,
+    ) -> Result<(), LimitExceeded> {
+        for scope in scopes {
+            let Some(limit) = self.config.limits(scope).get(resource) else {
+                continue;
+            };
+            let mut usage = self.usage(scope);
+            if usage.get_mut(resource).saturating_add(amount) > limit {
+                let exceeded = LimitExceeded {
+                    scope: scope.clone(),
+                    resource,
+                    limit,
+                };
+                tracing::debug!("{exceeded}");
+                *self.num_exceeded.entry(resource).or_default() += 1;
+                self.exceeded.push_back(exceeded.clone());
+                if let Some(waker) = self.waker.take() {
+                    waker.wake();
+                }
+                return Err(exceeded);
+            }
+        }
+        Ok(())
+    }
+
+    fn acquire(&mut self, scopes: &[Scope], resource: Resource, amount: usize) {
+        for scope in scopes {
+            *self.usage_mut(scope).get_mut(resource) += amount;
+        }
+    }
+
+    fn release(&mut self, scopes: &[Scope], resource: Resource, amount: usize) {
+        for scope in scopes {
+            let usage = self.usage_mut(scope);
+            let used = usage.get_mut(resource);
+            *used = used.saturating_sub(amount);
+
+            if *usage == Usage::default() {
+                match scope {
+                    Scope::System => {}
+                    Scope::Peer(peer_id) => {
+                        self.peers.remove(peer_id);
+                    }
+                    Scope::Protocol(protocol) => {
+                        self.protocols.remove(protocol);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A handle to the resource accounting of a [`Behaviour`], to be shared with connection handlers
+/// and other behaviours.
+#[derive(Clone)]
+pub struct ResourceManager {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl ResourceManager {
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        self.shared.lock().expect("lock not to be poisoned")
+    }
+
+    /// Reserves a stream with `peer` for `protocol`.
+    pub fn reserve_stream(
+        &self,
+        peer: PeerId,
+        protocol: StreamProtocol,
+    ) -> Result<Reservation, LimitExceeded> {
+        self.reserve(Some(peer), Some(protocol), Resource::Streams, 1)
+    }
+
+    /// Reserves `bytes` of memory on behalf of `peer`, accounted to `protocol`, if any.
+    pub fn reserve_memory(
+        &self,
+        peer: PeerId,
+        protocol: Option<StreamProtocol>,
+        bytes: usize,
+    ) -> Result<Reservation, LimitExceeded> {
+        self.reserve(Some(peer), protocol, Resource::Memory, bytes)
+    }
+
+    /// Returns the resources currently used in `scope`.
+    pub fn usage(&self, scope: &Scope) -> Usage {
+        self.lock().usage(scope)
+    }
+
+    /// Returns how many reservations of `resource` have been denied so far.
+    pub fn num_exceeded(&self, resource: Resource) -> u64 {
+        self.lock()
+            .num_exceeded
+            .get(&resource)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn reserve(
+        &self,
+        peer: Option<PeerId>,
+        protocol: Option<StreamProtocol>,
+        resource: Resource,
+        amount: usize,
+    ) -> Result<Reservation, LimitExceeded> {
+        let scopes = Shared::scopes(peer, protocol.as_ref());
+        let mut shared = self.lock();
+        shared.check(&scopes, resource, amount)?;
+        shared.acquire(&scopes, resource, amount);
+        drop(shared);
+
+        Ok(Reservation {
+            manager: self.clone(),
+            scopes,
+            resource,
+            amount,
+        })
+    }
+}
+
+impl fmt::Debug for ResourceManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResourceManager").finish_non_exhaustive()
+    }
+}
+
+/// Resources reserved through a [`ResourceManager`]. They are released when this is dropped.
+#[derive(Debug)]
+pub struct Reservation {
+    manager: ResourceManager,
+    scopes: Vec<Scope>,
+    resource: Resource,
+    amount: usize,
+}
+
+impl Reservation {
+    /// The reserved resource.
+    pub fn resource(&self) -> Resource {
+        self.resource
+    }
+
+    /// The reserved amount of the resource.
+    pub fn amount(&self) -> usize {
+        self.amount
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.manager
+            .lock()
+            .release(&self.scopes, self.resource, self.amount);
+    }
+}
+
+/// A [`NetworkBehaviour`] enforcing resource limits, see the [module docs](self).
+pub struct Behaviour {
+    manager: ResourceManager,
+    /// The peer of each pending outbound connection, if known.
+    pending_dials: HashMap<ConnectionId, Option<PeerId>>,
+    /// The peer of each established connection.
+    connections: HashMap<ConnectionId, PeerId>,
+}
+
+impl Behaviour {
+    /// Creates a new [`Behaviour`] enforcing the limits of the given [`Config`].
+    pub fn new(config: Config) -> Self {
+        Self {
+            manager: ResourceManager {
+                shared: Arc::new(Mutex::new(Shared {
+                    config,
+                    system: Usage::default(),
+                    peers: HashMap::new(),
+                    protocols: HashMap::new(),
+                    num_exceeded: HashMap::new(),
+                    exceeded: VecDeque::new(),
+                    waker: None,
+                })),
+            },
+            pending_dials: HashMap::new(),
+            connections: HashMap::new(),
+        }
+    }
+
+    /// Returns a handle to reserve resources with, e.g. from within connection handlers.
+    pub fn resource_manager(&self) -> ResourceManager {
+        self.manager.clone()
+    }
+
+    fn check_connection(&self, peer: PeerId) -> Result<(), ConnectionDenied> {
+        self.manager
+            .lock()
+            .check(&Shared::scopes(Some(peer), None), Resource::Connections, 1)
+            .map_err(ConnectionDenied::new)
+    }
+
+    fn release_pending_dial(&mut self, connection_id: &ConnectionId) {
+        if let Some(peer) = self.pending_dials.remove(connection_id) {
+            self.manager
+                .lock()
+                .release(&Shared::scopes(peer, None), Resource::PendingDials, 1);
+        }
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = Event;
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        _: &[Multiaddr],
+        _: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        let scopes = Shared::scopes(maybe_peer, None);
+        let mut shared = self.manager.lock();
+        shared
+            .check(&scopes, Resource::PendingDials, 1)
+            .map_err(ConnectionDenied::new)?;
+        shared.acquire(&scopes, Resource::PendingDials, 1);
+        drop(shared);
+
+        self.pending_dials.insert(connection_id, maybe_peer);
+
+        Ok(vec![])
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: ConnectionId,
+        peer: PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.check_connection(peer)?;
+
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+        _: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.release_pending_dial(&connection_id);
+        self.check_connection(peer)?;
+
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionEstablished(ConnectionEstablished {
+                peer_id,
+                connection_id,
+                ..
+            }) => {
+                self.connections.insert(connection_id, peer_id);
+                self.manager.lock().acquire(
+                    &Shared::scopes(Some(peer_id), None),
+                    Resource::Connections,
+                    1,
+                );
+            }
+            FromSwarm::ConnectionClosed(ConnectionClosed { connection_id, .. }) => {
+                if let Some(peer_id) = self.connections.remove(&connection_id) {
+                    self.manager.lock().release(
+                        &Shared::scopes(Some(peer_id), None),
+                        Resource::Connections,
+                        1,
+                    );
+                }
+            }
+            FromSwarm::DialFailure(DialFailure { connection_id, .. }) => {
+                self.release_pending_dial(&connection_id);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _id: PeerId,
+        _: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        void::unreachable(event)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        let mut shared = self.manager.lock();
+        if let Some(exceeded) = shared.exceeded.pop_front() {
+            return Poll::Ready(ToSwarm::GenerateEvent(Event::LimitExceeded(exceeded)));
+        }
+        shared.waker = Some(cx.waker().clone());
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROTOCOL: StreamProtocol = StreamProtocol::new("/test/1.0.0");
+
+    #[test]
+    fn reservations_are_accounted_in_all_scopes() {
+        let behaviour = Behaviour::new(
+            Config::default()
+                .with_peer_limits(Limits::default().with_max_streams(Some(2)))
+                .with_protocol_limits(PROTOCOL, Limits::default().with_max_streams(Some(3))),
+        );
+        let manager = behaviour.resource_manager();
+        let peer1 = PeerId::random();
+        let peer2 = PeerId::random();
+
+        let first = manager.reserve_stream(peer1, PROTOCOL).unwrap();
+        let _second = manager.reserve_stream(peer1, PROTOCOL).unwrap();
+        let exceeded = manager.reserve_stream(peer1, PROTOCOL).unwrap_err();
+        assert_eq!(exceeded.scope(), &Scope::Peer(peer1));
+        assert_eq!(exceeded.resource(), Resource::Streams);
+        assert_eq!(exceeded.limit(), 2);
+
+        let _third = manager.reserve_stream(peer2, PROTOCOL).unwrap();
+        let exceeded = manager.reserve_stream(peer2, PROTOCOL).unwrap_err();
+        assert_eq!(exceeded.scope(), &Scope::Protocol(PROTOCOL));
+
+        assert_eq!(manager.usage(&Scope::System).streams, 3);
+        assert_eq!(manager.usage(&Scope::Peer(peer1)).streams, 2);
+        assert_eq!(manager.num_exceeded(Resource::Streams), 2);
+
+        drop(first);
+        assert_eq!(manager.usage(&Scope::Peer(peer1)).streams, 1);
+        assert!(manager.reserve_stream(peer2, PROTOCOL).is_ok());
+    }
+
+    #[test]
+    fn memory_is_reserved_in_bytes() {
+        let behaviour = Behaviour::new(
+            Config::default().with_system_limits(Limits::default().with_max_memory(Some(1024))),
+        );
+        let manager = behaviour.resource_manager();
+        let peer = PeerId::random();
+
+        let reservation = manager.reserve_memory(peer, None, 1000).unwrap();
+        assert_eq!(reservation.amount(), 1000);
+        assert!(manager.reserve_memory(peer, None, 25).is_err());
+        assert!(manager.reserve_memory(peer, None, 24).is_ok());
+
+        drop(reservation);
+        assert_eq!(manager.usage(&Scope::Peer(peer)), Usage::default());
+    }
+}
@@ -1,20 +1,37 @@
+use crate::bandwidth::StreamBandwidth;
+use crate::rate_limit::StreamRateLimit;
+use crate::stream_metrics::StreamLifetime;
 use futures::{AsyncRead, AsyncWrite};
 use libp2p_core::muxing::SubstreamBox;
 use libp2p_core::Negotiated;
 use std::{
     io::{IoSlice, IoSliceMut},
     pin::Pin,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
 };
 
 /// Counter for the number of active streams on a connection.
+///
+/// Also records whether any of these streams received data from the remote.
 #[derive(Debug, Clone)]
-pub(crate) struct ActiveStreamCounter(Arc<()>);
+pub(crate) struct ActiveStreamCounter(Arc<AtomicBool>);
 
 impl ActiveStreamCounter {
     pub(crate) fn default() -> Self {
-        Self(Arc::new(()))
+        Self(Arc::new(AtomicBool::new(false)))
+    }
+
+    /// Returns whether any stream received data since the last call, resetting the flag.
+    pub(crate) fn take_remote_activity(&self) -> bool {
+        self.0.swap(false, Ordering::Relaxed)
+    }
+
+    fn record_remote_activity(&self) {
+        self.0.store(true, Ordering::Relaxed);
     }
 
     pub(crate) fn has_no_active_streams(&self) -> bool {
@@ -30,13 +47,28 @@ impl ActiveStreamCounter {
 pub struct Stream {
     stream: Negotiated<SubstreamBox>,
     counter: Option<ActiveStreamCounter>,
+    bandwidth: Option<StreamBandwidth>,
+    rate_limit: Option<StreamRateLimit>,
+    /// Records the closure of the stream in the [`StreamMetrics`](crate::stream_metrics::StreamMetrics), if enabled.
+    ///
+    /// Never read, the closure is recorded when it is dropped together with the stream.
+    _lifetime: Option<StreamLifetime>,
 }
 
 impl Stream {
-    pub(crate) fn new(stream: Negotiated<SubstreamBox>, counter: ActiveStreamCounter) -> Self {
+    pub(crate) fn new(
+        stream: Negotiated<SubstreamBox>,
+        counter: ActiveStreamCounter,
+        bandwidth: Option<StreamBandwidth>,
+        rate_limit: Option<StreamRateLimit>,
+        lifetime: Option<StreamLifetime>,
+    ) -> Self {
         Self {
             stream,
             counter: Some(counter),
+            bandwidth,
+            rate_limit,
+            _lifetime: lifetime,
         }
     }
 
@@ -48,9 +80,32 @@ impl Stream {
     /// Some protocols like libp2p's [ping](https://github.com/libp2p/specs/blob/master/ping/ping.md)
     /// for example never complete and are of an auxiliary nature.
     /// These protocols should opt-out of the keep alive algorithm using this method.
+    ///
+    /// Data received on such streams is also not reported to the handler as
+    /// [`ConnectionEvent::RemoteActivity`](crate::handler::ConnectionEvent::RemoteActivity).
     pub fn ignore_for_keep_alive(&mut self) {
         self.counter.take();
     }
+
+    fn record_inbound(&self, poll: &Poll<std::io::Result<usize>>) {
+        if let (Poll::Ready(Ok(n)), Some(counter)) = (poll, &self.counter) {
+            if *n > 0 {
+                counter.record_remote_activity();
+            }
+        }
+        if let (Poll::Ready(Ok(n)), Some(bandwidth)) = (poll, &self.bandwidth) {
+            bandwidth.record_inbound(*n);
+        }
+        if let (Poll::Ready(Ok(n)), Some(rate_limit)) = (poll, &self.rate_limit) {
+            rate_limit.consume(*n);
+        }
+    }
+
+    fn record_outbound(&self, poll: &Poll<std::io::Result<usize>>) {
+        if let (Poll::Ready(Ok(n)), Some(bandwidth)) = (poll, &self.bandwidth) {
+            bandwidth.record_outbound(*n);
+        }
+    }
 }
 
 impl AsyncRead for Stream {
@@ -59,7 +114,18 @@ impl AsyncRead for Stream {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<std::io::Result<usize>> {
-        Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+        let this = self.get_mut();
+        let buf = match this.rate_limit.as_mut() {
+            Some(rate_limit) => {
+                let available = futures::ready!(rate_limit.poll_available(cx));
+                let len = buf.len().min(available);
+                &mut buf[..len]
+            }
+            None => buf,
+        };
+        let poll = Pin::new(&mut this.stream).poll_read(cx, buf);
+        this.record_inbound(&poll);
+        poll
     }
 
     fn poll_read_vectored(
@@ -67,7 +133,18 @@ impl AsyncRead for Stream {
         cx: &mut Context<'_>,
         bufs: &mut [IoSliceMut<'_>],
     ) -> Poll<std::io::Result<usize>> {
-        Pin::new(&mut self.get_mut().stream).poll_read_vectored(cx, bufs)
+        let this = self.get_mut();
+        if this.rate_limit.is_some() {
+            // Reads are limited to the available tokens, so fill the first non-empty buffer only.
+            let buf = bufs
+                .iter_mut()
+                .find(|b| !b.is_empty())
+                .map_or(&mut [][..], |b| &mut **b);
+            return Pin::new(this).poll_read(cx, buf);
+        }
+        let poll = Pin::new(&mut this.stream).poll_read_vectored(cx, bufs);
+        this.record_inbound(&poll);
+        poll
     }
 }
 
@@ -77,7 +154,10 @@ impl AsyncWrite for Stream {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<std::io::Result<usize>> {
-        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.stream).poll_write(cx, buf);
+        this.record_outbound(&poll);
+        poll
     }
 
     fn poll_write_vectored(
@@ -85,7 +165,10 @@ impl AsyncWrite for Stream {
         cx: &mut Context<'_>,
         bufs: &[IoSlice<'_>],
     ) -> Poll<std::io::Result<usize>> {
-        Pin::new(&mut self.get_mut().stream).poll_write_vectored(cx, bufs)
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.stream).poll_write_vectored(cx, bufs);
+        this.record_outbound(&poll);
+        poll
     }
 
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
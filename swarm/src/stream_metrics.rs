@@ -0,0 +1,253 @@
+use crate::StreamProtocol;
+use libp2p_core::Endpoint;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use web_time::Instant;
+
+/// The upper bounds in seconds of the buckets of a [`LifetimeHistogram`], excluding the last
+/// bucket which is unbounded.
+pub const LIFETIME_BUCKETS: [f64; 8] = [0.01, 0.1, 1.0, 10.0, 60.0, 300.0, 1800.0, 3600.0];
+
+/// A histogram of the lifetimes of closed streams, see [`LIFETIME_BUCKETS`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LifetimeHistogram {
+    buckets: [u64; LIFETIME_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl LifetimeHistogram {
+    /// The upper bound in seconds of each bucket together with the number of lifetimes that
+    /// fell into it, but not into any of the previous buckets.
+    ///
+    /// The upper bound of the last bucket is [`f64::INFINITY`].
+    pub fn buckets(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        LIFETIME_BUCKETS
+            .iter()
+            .copied()
+            .chain([f64::INFINITY])
+            .zip(self.buckets.iter().copied())
+    }
+
+    /// The sum of all lifetimes in seconds.
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// The number of lifetimes.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn observe(&mut self, lifetime: Duration) {
+        let seconds = lifetime.as_secs_f64();
+        let bucket = LIFETIME_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LIFETIME_BUCKETS.len());
+        self.buckets[bucket] += 1;
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// Statistics of the streams negotiated for a single protocol, see [`StreamMetrics`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ProtocolStreamStats {
+    opened_inbound: u64,
+    opened_outbound: u64,
+    closed: u64,
+    negotiation_failures: u64,
+    lifetimes: LifetimeHistogram,
+}
+
+impl ProtocolStreamStats {
+    /// The number of inbound streams negotiated for the protocol.
+    pub fn opened_inbound(&self) -> u64 {
+        self.opened_inbound
+    }
+
+    /// The number of outbound streams negotiated for the protocol.
+    pub fn opened_outbound(&self) -> u64 {
+        self.opened_outbound
+    }
+
+    /// The number of negotiated streams that were dropped.
+    pub fn closed(&self) -> u64 {
+        self.closed
+    }
+
+    /// The number of negotiated streams that are still open.
+    pub fn open(&self) -> u64 {
+        (self.opened_inbound + self.opened_outbound).saturating_sub(self.closed)
+    }
+
+    /// The number of outbound streams offering the protocol for which the negotiation failed or
+    /// timed out.
+    pub fn negotiation_failures(&self) -> u64 {
+        self.negotiation_failures
+    }
+
+    /// The lifetimes of the closed streams, from the end of the negotiation until the stream was
+    /// dropped.
+    pub fn lifetimes(&self) -> &LifetimeHistogram {
+        &self.lifetimes
+    }
+}
+
+/// A handle to the stream metrics of a [`Swarm`](crate::Swarm), see
+/// [`Config::with_stream_metrics`](crate::Config::with_stream_metrics).
+///
+/// Tracks the streams of all connections by negotiated protocol, without having to instrument
+/// the individual [`NetworkBehaviour`](crate::NetworkBehaviour)s. Clones share the same
+/// statistics, so a clone can be handed to the [`Config`](crate::Config) while another one is
+/// used to query them, e.g. by `libp2p-metrics`.
+#[derive(Debug, Default, Clone)]
+pub struct StreamMetrics {
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    protocols: HashMap<StreamProtocol, ProtocolStreamStats>,
+    inbound_negotiation_failures: u64,
+}
+
+impl StreamMetrics {
+    /// Creates new [`StreamMetrics`] without any streams.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        self.shared.lock().expect("lock not to be poisoned")
+    }
+
+    /// Returns the statistics of the streams negotiated for `protocol`.
+    pub fn protocol(&self, protocol: &StreamProtocol) -> ProtocolStreamStats {
+        self.lock()
+            .protocols
+            .get(protocol)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the statistics of each protocol.
+    pub fn snapshot(&self) -> Vec<(StreamProtocol, ProtocolStreamStats)> {
+        self.lock()
+            .protocols
+            .iter()
+            .map(|(protocol, stats)| (protocol.clone(), stats.clone()))
+            .collect()
+    }
+
+    /// The number of inbound streams for which the negotiation failed or timed out.
+    ///
+    /// These are not attributed to a protocol, as the negotiation failed because the protocol
+    /// requested by the remote is not supported.
+    pub fn inbound_negotiation_failures(&self) -> u64 {
+        self.lock().inbound_negotiation_failures
+    }
+
+    /// Records a stream negotiated for `protocol`, returning a guard recording its closure once
+    /// dropped.
+    ///
+    /// Returns `None` if `protocol` is not a valid [`StreamProtocol`].
+    pub(crate) fn stream_opened(
+        &self,
+        protocol: &str,
+        endpoint: Endpoint,
+    ) -> Option<StreamLifetime> {
+        let protocol = StreamProtocol::try_from_owned(protocol.to_owned()).ok()?;
+        let mut shared = self.lock();
+        let stats = shared.protocols.entry(protocol.clone()).or_default();
+        match endpoint {
+            Endpoint::Dialer => stats.opened_outbound += 1,
+            Endpoint::Listener => stats.opened_inbound += 1,
+        }
+        drop(shared);
+
+        Some(StreamLifetime {
+            metrics: self.clone(),
+            protocol,
+            opened: Instant::now(),
+        })
+    }
+
+    /// Records a failed negotiation of an outbound stream offering `protocols`.
+    pub(crate) fn outbound_negotiation_failed<'a>(
+        &self,
+        protocols: impl IntoIterator<Item = &'a str>,
+    ) {
+        let mut shared = self.lock();
+        for protocol in protocols {
+            let Ok(protocol) = StreamProtocol::try_from_owned(protocol.to_owned()) else {
+                continue;
+            };
+            shared
+                .protocols
+                .entry(protocol)
+                .or_default()
+                .negotiation_failures += 1;
+        }
+    }
+
+    /// Records a failed negotiation of an inbound stream.
+    pub(crate) fn inbound_negotiation_failed(&self) {
+        self.lock().inbound_negotiation_failures += 1;
+    }
+}
+
+/// Records the closure and lifetime of a negotiated [`Stream`](crate::Stream) once dropped.
+#[derive(Debug)]
+pub(crate) struct StreamLifetime {
+    metrics: StreamMetrics,
+    protocol: StreamProtocol,
+    opened: Instant,
+}
+
+impl Drop for StreamLifetime {
+    fn drop(&mut self) {
+        let mut shared = self.metrics.lock();
+        let stats = shared.protocols.entry(self.protocol.clone()).or_default();
+        stats.closed += 1;
+        stats.lifetimes.observe(self.opened.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_streams_by_protocol() {
+        let metrics = StreamMetrics::new();
+        let ping = StreamProtocol::new("/ping");
+
+        let inbound = metrics.stream_opened("/ping", Endpoint::Listener).unwrap();
+        let outbound = metrics.stream_opened("/ping", Endpoint::Dialer).unwrap();
+        metrics.outbound_negotiation_failed(["/ping", "/kad"]);
+        metrics.inbound_negotiation_failed();
+        assert!(metrics
+            .stream_opened("no-slash", Endpoint::Dialer)
+            .is_none());
+
+        let stats = metrics.protocol(&ping);
+        assert_eq!(stats.opened_inbound(), 1);
+        assert_eq!(stats.opened_outbound(), 1);
+        assert_eq!(stats.open(), 2);
+        assert_eq!(stats.negotiation_failures(), 1);
+        assert_eq!(metrics.inbound_negotiation_failures(), 1);
+
+        drop(inbound);
+        drop(outbound);
+
+        let stats = metrics.protocol(&ping);
+        assert_eq!(stats.closed(), 2);
+        assert_eq!(stats.open(), 0);
+        assert_eq!(stats.lifetimes().count(), 2);
+        assert_eq!(stats.lifetimes().buckets().map(|(_, n)| n).sum::<u64>(), 2);
+        assert_eq!(metrics.snapshot().len(), 2);
+    }
+}
@@ -0,0 +1,223 @@
+// Copyright 2024 Protocol Labs.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a
+// copy of this software and associated documentation files (the "Software"),
+// to deal in the Software without restriction, including without limitation
+// the rights to use, copy, modify, merge, publish, distribute, sublicense,
+// and/or sell copies of the Software, and to permit persons to whom the
+// Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS
+// OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+// FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Subscriptions to subsets of the events of a [`Swarm`](crate::Swarm).
+//!
+//! A subscription is created via [`Swarm::subscribe`](crate::Swarm::subscribe) with a filter,
+//! mapping each [`SwarmEvent`] to the event delivered to the subscriber, if any.
+//! This module provides filters for common subsets, e.g. [`connection_lifecycle`] and
+//! [`behaviour_events`].
+//!
+//! Events are delivered over bounded channels, before being returned by the [`Swarm`](crate::Swarm)
+//! itself. Events that don't fit into the channel of a subscriber are dropped for that
+//! subscriber. Subscriptions end once their receiver is dropped.
+
+use crate::{ConnectionId, SwarmEvent};
+use futures::channel::mpsc;
+use libp2p_core::ConnectedPoint;
+use libp2p_identity::PeerId;
+use std::num::NonZeroU32;
+
+/// A change in the lifecycle of a connection, see [`connection_lifecycle`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionLifecycle {
+    /// See [`SwarmEvent::Dialing`].
+    Dialing {
+        peer_id: Option<PeerId>,
+        connection_id: ConnectionId,
+    },
+    /// See [`SwarmEvent::ConnectionEstablished`].
+    Established {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        endpoint: ConnectedPoint,
+        num_established: NonZeroU32,
+    },
+    /// See [`SwarmEvent::OutgoingConnectionError`].
+    DialFailed {
+        peer_id: Option<PeerId>,
+        connection_id: ConnectionId,
+    },
+    /// See [`SwarmEvent::ConnectionClosed`].
+    Closed {
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        endpoint: ConnectedPoint,
+        num_established: u32,
+    },
+}
+
+/// Filters the events about dialing, establishing and closing connections.
+pub fn connection_lifecycle<TBehaviourOutEvent>(
+    event: &SwarmEvent<TBehaviourOutEvent>,
+) -> Option<ConnectionLifecycle> {
+    match event {
+        SwarmEvent::Dialing {
+            peer_id,
+            connection_id,
+        } => Some(ConnectionLifecycle::Dialing {
+            peer_id: *peer_id,
+            connection_id: *connection_id,
+        }),
+        SwarmEvent::ConnectionEstablished {
+            peer_id,
+            connection_id,
+            endpoint,
+            num_established,
+            ..
+        } => Some(ConnectionLifecycle::Established {
+            peer_id: *peer_id,
+            connection_id: *connection_id,
+            endpoint: endpoint.clone(),
+            num_established: *num_established,
+        }),
+        SwarmEvent::OutgoingConnectionError {
+            peer_id,
+            connection_id,
+            ..
+        } => Some(ConnectionLifecycle::DialFailed {
+            peer_id: *peer_id,
+            connection_id: *connection_id,
+        }),
+        SwarmEvent::ConnectionClosed {
+            peer_id,
+            connection_id,
+            endpoint,
+            num_established,
+            ..
+        } => Some(ConnectionLifecycle::Closed {
+            peer_id: *peer_id,
+            connection_id: *connection_id,
+            endpoint: endpoint.clone(),
+            num_established: *num_established,
+        }),
+        _ => None,
+    }
+}
+
+/// Filters the events of the [`NetworkBehaviour`](crate::NetworkBehaviour).
+///
+/// To subscribe to the events of a single behaviour composed via the
+/// [`NetworkBehaviour`](crate::NetworkBehaviour) derive macro, match on its variant instead.
+pub fn behaviour_events<TBehaviourOutEvent: Clone>(
+    event: &SwarmEvent<TBehaviourOutEvent>,
+) -> Option<TBehaviourOutEvent> {
+    match event {
+        SwarmEvent::Behaviour(event) => Some(event.clone()),
+        _ => None,
+    }
+}
+
+type Subscriber<TBehaviourOutEvent> =
+    Box<dyn FnMut(&SwarmEvent<TBehaviourOutEvent>) -> bool + Send>;
+
+/// The subscriptions to the events of a [`Swarm`](crate::Swarm).
+pub(crate) struct Subscriptions<TBehaviourOutEvent> {
+    subscribers: Vec<Subscriber<TBehaviourOutEvent>>,
+}
+
+impl<TBehaviourOutEvent> Subscriptions<TBehaviourOutEvent> {
+    pub(crate) fn new() -> Self {
+        Self {
+            subscribers: Vec::new(),
+        }
+    }
+
+    /// Adds a subscription to the events accepted by `filter`.
+    pub(crate) fn add<T, F>(&mut self, capacity: usize, mut filter: F) -> mpsc::Receiver<T>
+    where
+        T: Send + 'static,
+        F: FnMut(&SwarmEvent<TBehaviourOutEvent>) -> Option<T> + Send + 'static,
+    {
+        let (mut tx, rx) = mpsc::channel(capacity);
+        self.subscribers.push(Box::new(move |event| {
+            if tx.is_closed() {
+                return false;
+            }
+            let Some(event) = filter(event) else {
+                return true;
+            };
+            match tx.try_send(event) {
+                Ok(()) => true,
+                Err(e) if e.is_full() => {
+                    tracing::debug!("Dropping event for subscriber with a full channel");
+                    true
+                }
+                Err(_) => false,
+            }
+        }));
+        rx
+    }
+
+    /// Delivers `event` to all subscribers, dropping those whose receiver was dropped.
+    pub(crate) fn deliver(&mut self, event: &SwarmEvent<TBehaviourOutEvent>) {
+        self.subscribers.retain_mut(|subscriber| subscriber(event));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn delivers_filtered_events_until_receiver_is_dropped() {
+        let mut subscriptions = Subscriptions::<u32>::new();
+        let mut behaviour = subscriptions.add(8, behaviour_events);
+        let mut lifecycle = subscriptions.add(8, connection_lifecycle);
+
+        let connection_id = ConnectionId::new_unchecked(1);
+        subscriptions.deliver(&SwarmEvent::Behaviour(1));
+        subscriptions.deliver(&SwarmEvent::Dialing {
+            peer_id: None,
+            connection_id,
+        });
+
+        assert_eq!(behaviour.try_next().unwrap(), Some(1));
+        assert!(behaviour.try_next().is_err());
+        assert_eq!(
+            lifecycle.try_next().unwrap(),
+            Some(ConnectionLifecycle::Dialing {
+                peer_id: None,
+                connection_id
+            })
+        );
+
+        drop(lifecycle);
+        subscriptions.deliver(&SwarmEvent::Behaviour(2));
+        assert_eq!(subscriptions.subscribers.len(), 1);
+        assert_eq!(futures::executor::block_on(behaviour.next()), Some(2));
+    }
+
+    #[test]
+    fn drops_events_for_full_subscribers() {
+        let mut subscriptions = Subscriptions::<u32>::new();
+        let mut rx = subscriptions.add(0, behaviour_events);
+
+        for i in 0..3 {
+            subscriptions.deliver(&SwarmEvent::Behaviour(i));
+        }
+
+        // The channel has room for a single event of its only sender.
+        assert_eq!(rx.try_next().unwrap(), Some(0));
+        assert!(rx.try_next().is_err());
+        assert_eq!(subscriptions.subscribers.len(), 1);
+    }
+}
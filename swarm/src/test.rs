@@ -304,6 +304,7 @@ where
             endpoint,
             remaining_established,
             cause,
+            extensions,
         }: ConnectionClosed,
     ) {
         let mut other_closed_connections = self
@@ -354,6 +355,7 @@ where
                 endpoint,
                 remaining_established,
                 cause,
+                extensions,
             }));
     }
 }
@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     task::{Context, Poll},
 };
 
@@ -10,9 +10,9 @@ use libp2p_core::{
 };
 use libp2p_identity::PeerId;
 use libp2p_swarm::{
-    derive_prelude::NewListener, dummy, ConnectionDenied, ConnectionId, FromSwarm, ListenOpts,
-    ListenerClosed, ListenerError, NetworkBehaviour, NewListenAddr, Swarm, SwarmEvent, THandler,
-    THandlerInEvent, THandlerOutEvent, ToSwarm,
+    derive_prelude::NewListener, dummy, AddressSource, ConnectionDenied, ConnectionId, FromSwarm,
+    ListenOpts, ListenerClosed, ListenerError, NetworkBehaviour, NewListenAddr, Swarm, SwarmEvent,
+    THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
 };
 
 use libp2p_swarm_test::SwarmExt;
@@ -55,6 +55,36 @@ async fn behaviour_listener() {
         .await;
 }
 
+#[async_std::test]
+async fn local_addresses_are_reconciled() {
+    let mut swarm = Swarm::new_ephemeral(|_| Behaviour::default());
+    let addr: Multiaddr = Protocol::Memory(0).into();
+    let id = swarm.behaviour_mut().listen(addr.clone());
+
+    let address = swarm
+        .wait(|e| match e {
+            SwarmEvent::NewListenAddr { address, .. } => Some(address),
+            _ => None,
+        })
+        .await;
+    swarm.add_external_address(address.clone());
+
+    let local_addresses = swarm
+        .local_addresses()
+        .map(|(address, sources)| (address.clone(), sources.to_vec()))
+        .collect::<HashMap<_, _>>();
+    assert_eq!(
+        local_addresses,
+        HashMap::from([
+            (addr, vec![AddressSource::Configured(id)]),
+            (
+                address,
+                vec![AddressSource::Listener(id), AddressSource::External]
+            ),
+        ])
+    );
+}
+
 #[derive(Default)]
 struct Behaviour {
     events: VecDeque<ToSwarm<<Self as NetworkBehaviour>::ToSwarm, THandlerInEvent<Self>>>,
@@ -0,0 +1,82 @@
+use libp2p_identify as identify;
+use libp2p_swarm::{protocol_rules, NetworkBehaviour, StreamProtocol, Swarm, SwarmEvent};
+use libp2p_swarm_test::SwarmExt;
+
+const IDENTIFY: StreamProtocol = StreamProtocol::new("/ipfs/id/1.0.0");
+
+#[async_std::test]
+async fn redials_peers_supporting_protocol_with_rule() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+
+    let mut swarm1 = Swarm::new_ephemeral(|identity| {
+        let mut rules = protocol_rules::Behaviour::new(protocol_rules::Config::default());
+        rules.keep_connected(IDENTIFY, 1);
+        Behaviour::new(identity, rules)
+    });
+    let mut swarm2 = Swarm::new_ephemeral(|identity| {
+        Behaviour::new(
+            identity,
+            protocol_rules::Behaviour::new(protocol_rules::Config::default()),
+        )
+    });
+    let peer2 = *swarm2.local_peer_id();
+
+    swarm2.listen().with_memory_addr_external().await;
+    swarm1.connect(&mut swarm2).await;
+    async_std::task::spawn(swarm2.loop_on_next());
+
+    swarm1
+        .wait(|event| match event {
+            SwarmEvent::Behaviour(BehaviourEvent::Rules(
+                protocol_rules::Event::ProtocolSupported { peer_id, protocol },
+            )) if peer_id == peer2 && protocol == IDENTIFY => Some(()),
+            _ => None,
+        })
+        .await;
+    assert_eq!(
+        swarm1
+            .behaviour()
+            .rules
+            .peers_supporting(&IDENTIFY)
+            .collect::<Vec<_>>(),
+        vec![&peer2]
+    );
+
+    swarm1.disconnect_peer_id(peer2).unwrap();
+    swarm1
+        .wait(|event| match event {
+            SwarmEvent::Behaviour(BehaviourEvent::Rules(
+                protocol_rules::Event::ProtocolUnsupported { peer_id, .. },
+            )) if peer_id == peer2 => Some(()),
+            _ => None,
+        })
+        .await;
+
+    swarm1
+        .wait(|event| match event {
+            SwarmEvent::ConnectionEstablished { peer_id, .. } if peer_id == peer2 => Some(()),
+            _ => None,
+        })
+        .await;
+}
+
+#[derive(NetworkBehaviour)]
+#[behaviour(prelude = "libp2p_swarm::derive_prelude")]
+struct Behaviour {
+    identify: identify::Behaviour,
+    rules: protocol_rules::Behaviour,
+}
+
+impl Behaviour {
+    fn new(identity: libp2p_identity::Keypair, rules: protocol_rules::Behaviour) -> Self {
+        Self {
+            identify: identify::Behaviour::new(identify::Config::new(
+                "/test/1.0.0".to_owned(),
+                identity.public(),
+            )),
+            rules,
+        }
+    }
+}
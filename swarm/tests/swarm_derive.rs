@@ -611,6 +611,129 @@ fn custom_out_event_no_type_parameters() {
     require_net_behaviour::<Behaviour<()>>();
 }
 
+/// A behaviour which always has an event ready, tagged with its name.
+struct ChattyBehaviour(&'static str);
+
+impl NetworkBehaviour for ChattyBehaviour {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = &'static str;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _: libp2p_swarm::ConnectionId,
+        _: libp2p_identity::PeerId,
+        _: &Multiaddr,
+        _: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _: libp2p_swarm::ConnectionId,
+        _: libp2p_identity::PeerId,
+        _: &Multiaddr,
+        _: Endpoint,
+        _: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, _event: FromSwarm) {}
+
+    fn on_connection_handler_event(
+        &mut self,
+        _: libp2p_identity::PeerId,
+        _: libp2p_swarm::ConnectionId,
+        message: THandlerOutEvent<Self>,
+    ) {
+        void::unreachable(message);
+    }
+
+    fn poll(
+        &mut self,
+        _: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<libp2p_swarm::ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        std::task::Poll::Ready(libp2p_swarm::ToSwarm::GenerateEvent(self.0))
+    }
+}
+
+/// Polls `behaviour` `n` times and collects the emitted events.
+fn poll_events<B>(behaviour: &mut B, n: usize) -> Vec<B::ToSwarm>
+where
+    B: NetworkBehaviour,
+{
+    let mut cx = std::task::Context::from_waker(futures::task::noop_waker_ref());
+
+    (0..n)
+        .map(|_| match behaviour.poll(&mut cx) {
+            std::task::Poll::Ready(libp2p_swarm::ToSwarm::GenerateEvent(event)) => event,
+            _ => panic!("Expected an event"),
+        })
+        .collect()
+}
+
+#[test]
+fn fair_poll_alternates_between_fields() {
+    #[derive(NetworkBehaviour)]
+    #[behaviour(
+        to_swarm = "&'static str",
+        fair_poll,
+        prelude = "libp2p_swarm::derive_prelude"
+    )]
+    struct Foo {
+        a: ChattyBehaviour,
+        b: ChattyBehaviour,
+    }
+
+    let mut unfair = {
+        #[derive(NetworkBehaviour)]
+        #[behaviour(to_swarm = "&'static str", prelude = "libp2p_swarm::derive_prelude")]
+        struct Bar {
+            a: ChattyBehaviour,
+            b: ChattyBehaviour,
+        }
+
+        Bar {
+            a: ChattyBehaviour("a"),
+            b: ChattyBehaviour("b"),
+        }
+    };
+    assert_eq!(poll_events(&mut unfair, 4), ["a", "a", "a", "a"]);
+
+    let mut fair = Foo {
+        a: ChattyBehaviour("a"),
+        b: ChattyBehaviour("b"),
+    };
+    assert_eq!(poll_events(&mut fair, 4), ["a", "b", "a", "b"]);
+}
+
+#[test]
+fn fair_poll_respects_poll_budget() {
+    #[derive(NetworkBehaviour)]
+    #[behaviour(
+        to_swarm = "&'static str",
+        fair_poll,
+        prelude = "libp2p_swarm::derive_prelude"
+    )]
+    struct Foo {
+        #[behaviour(poll_budget = 2)]
+        a: ChattyBehaviour,
+        b: ChattyBehaviour,
+        c: ChattyBehaviour,
+    }
+
+    let mut behaviour = Foo {
+        a: ChattyBehaviour("a"),
+        b: ChattyBehaviour("b"),
+        c: ChattyBehaviour("c"),
+    };
+    assert_eq!(
+        poll_events(&mut behaviour, 8),
+        ["a", "a", "b", "c", "a", "a", "b", "c"]
+    );
+}
+
 #[test]
 fn ui() {
     let t = trybuild::TestCases::new();
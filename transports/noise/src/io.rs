@@ -27,6 +27,7 @@ use bytes::Bytes;
 use framed::{Codec, MAX_FRAME_LEN};
 use futures::prelude::*;
 use futures::ready;
+use libp2p_core::upgrade::SelectedMuxer;
 use std::{
     cmp::min,
     fmt, io,
@@ -43,6 +44,7 @@ pub struct Output<T> {
     recv_offset: usize,
     send_buffer: Vec<u8>,
     send_offset: usize,
+    stream_muxer: Option<String>,
 }
 
 impl<T> fmt::Debug for Output<T> {
@@ -52,17 +54,24 @@ impl<T> fmt::Debug for Output<T> {
 }
 
 impl<T> Output<T> {
-    fn new(io: Framed<T, Codec<snow::TransportState>>) -> Self {
+    fn new(io: Framed<T, Codec<snow::TransportState>>, stream_muxer: Option<String>) -> Self {
         Output {
             io,
             recv_buffer: Bytes::new(),
             recv_offset: 0,
             send_buffer: Vec::new(),
             send_offset: 0,
+            stream_muxer,
         }
     }
 }
 
+impl<T> SelectedMuxer for Output<T> {
+    fn selected_muxer(&self) -> Option<&str> {
+        self.stream_muxer.as_deref()
+    }
+}
+
 impl<T: AsyncRead + Unpin> AsyncRead for Output<T> {
     fn poll_read(
         mut self: Pin<&mut Self>,
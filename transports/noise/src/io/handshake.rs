@@ -55,6 +55,8 @@ pub(crate) struct State<T> {
     id_remote_pubkey: Option<identity::PublicKey>,
     /// The WebTransport certhashes of the responder, if any.
     responder_webtransport_certhashes: Option<HashSet<Multihash<64>>>,
+    /// The local stream multiplexers, in order of preference.
+    stream_muxers: Vec<String>,
     /// The received extensions of the remote, if any.
     remote_extensions: Option<Extensions>,
 }
@@ -62,6 +64,7 @@ pub(crate) struct State<T> {
 /// Extensions
 struct Extensions {
     webtransport_certhashes: HashSet<Multihash<64>>,
+    stream_muxers: Vec<String>,
 }
 
 impl<T> State<T>
@@ -80,6 +83,7 @@ where
         identity: KeypairIdentity,
         expected_remote_key: Option<identity::PublicKey>,
         responder_webtransport_certhashes: Option<HashSet<Multihash<64>>>,
+        stream_muxers: Vec<String>,
     ) -> Self {
         Self {
             identity,
@@ -87,6 +91,7 @@ where
             dh_remote_pubkey_sig: None,
             id_remote_pubkey: expected_remote_key,
             responder_webtransport_certhashes,
+            stream_muxers,
             remote_extensions: None,
         }
     }
@@ -115,6 +120,22 @@ where
             return Err(Error::BadSignature);
         }
 
+        // Select the first stream multiplexer of the initiator that the responder supports.
+        let remote_muxers = self
+            .remote_extensions
+            .as_ref()
+            .map(|ext| ext.stream_muxers.as_slice())
+            .unwrap_or_default();
+        let (initiator_muxers, responder_muxers) = if is_initiator {
+            (self.stream_muxers.as_slice(), remote_muxers)
+        } else {
+            (remote_muxers, self.stream_muxers.as_slice())
+        };
+        let stream_muxer = initiator_muxers
+            .iter()
+            .find(|muxer| responder_muxers.contains(muxer))
+            .cloned();
+
         // Check WebTransport certhashes that responder reported back to us.
         if is_initiator {
             // We check only if we care (i.e. Config::with_webtransport_certhashes was used).
@@ -139,7 +160,7 @@ where
             }
         }
 
-        Ok((id_pk, Output::new(framed)))
+        Ok((id_pk, Output::new(framed, stream_muxer)))
     }
 }
 
@@ -175,6 +196,7 @@ impl From<proto::NoiseExtensions> for Extensions {
                 .into_iter()
                 .filter_map(|bytes| Multihash::read(&bytes[..]).ok())
                 .collect(),
+            stream_muxers: value.stream_muxers,
         }
     }
 }
@@ -261,6 +283,13 @@ where
         }
     }
 
+    if !state.stream_muxers.is_empty() {
+        pb.extensions
+            .get_or_insert_with(proto::NoiseExtensions::default)
+            .stream_muxers
+            .clone_from(&state.stream_muxers);
+    }
+
     state.io.send(&pb).await?;
 
     Ok(())
@@ -81,6 +81,7 @@ pub struct Config {
     dh_keys: AuthenticKeypair,
     params: NoiseParams,
     webtransport_certhashes: Option<HashSet<Multihash<64>>>,
+    stream_muxers: Vec<String>,
 
     /// Prologue to use in the noise handshake.
     ///
@@ -100,6 +101,7 @@ impl Config {
             dh_keys: noise_keys,
             params: PARAMS_XX.clone(),
             webtransport_certhashes: None,
+            stream_muxers: vec![],
             prologue: vec![],
         })
     }
@@ -121,6 +123,17 @@ impl Config {
         self
     }
 
+    /// Set the stream multiplexers to select from during the handshake, in order of preference.
+    ///
+    /// If both peers offer stream multiplexers, the first one of the initiator that is also
+    /// offered by the responder is selected, see [`Output`]'s
+    /// [`SelectedMuxer`](libp2p_core::upgrade::SelectedMuxer) implementation. This saves the
+    /// round-trip of negotiating the multiplexer via multistream-select after the handshake.
+    pub fn with_stream_muxers(mut self, muxers: Vec<String>) -> Self {
+        self.stream_muxers = muxers;
+        self
+    }
+
     fn into_responder<S: AsyncRead + AsyncWrite>(self, socket: S) -> Result<State<S>, Error> {
         let session = noise_params_into_builder(
             self.params,
@@ -136,6 +149,7 @@ impl Config {
             self.dh_keys.identity,
             None,
             self.webtransport_certhashes,
+            self.stream_muxers,
         );
 
         Ok(state)
@@ -156,6 +170,7 @@ impl Config {
             self.dh_keys.identity,
             None,
             self.webtransport_certhashes,
+            self.stream_muxers,
         );
 
         Ok(state)
@@ -0,0 +1,65 @@
+use libp2p_core::upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade, SelectedMuxer};
+use libp2p_identity as identity;
+use libp2p_noise as noise;
+
+const YAMUX: &str = "/yamux/1.0.0";
+const MPLEX: &str = "/mplex/6.7.0";
+
+#[test]
+fn selects_common_stream_muxer() {
+    assert_eq!(
+        handshake_with_stream_muxers(&[YAMUX], &[YAMUX]),
+        Some(YAMUX.to_owned())
+    );
+}
+
+#[test]
+fn prefers_stream_muxer_of_initiator() {
+    assert_eq!(
+        handshake_with_stream_muxers(&[MPLEX, YAMUX], &[YAMUX, MPLEX]),
+        Some(MPLEX.to_owned())
+    );
+}
+
+#[test]
+fn no_common_stream_muxer() {
+    assert_eq!(handshake_with_stream_muxers(&[MPLEX], &[YAMUX]), None);
+}
+
+#[test]
+fn remote_without_stream_muxers() {
+    assert_eq!(handshake_with_stream_muxers(&[YAMUX], &[]), None);
+    assert_eq!(handshake_with_stream_muxers(&[], &[YAMUX]), None);
+}
+
+// Returns the stream multiplexer selected by the server, after asserting that the client selected
+// the same one.
+fn handshake_with_stream_muxers(client_muxers: &[&str], server_muxers: &[&str]) -> Option<String> {
+    let client_id = identity::Keypair::generate_ed25519();
+    let server_id = identity::Keypair::generate_ed25519();
+
+    let (client, server) = futures_ringbuf::Endpoint::pair(100, 100);
+
+    futures::executor::block_on(async move {
+        let client_config = noise::Config::new(&client_id)
+            .unwrap()
+            .with_stream_muxers(client_muxers.iter().map(|m| m.to_string()).collect());
+        let server_config = noise::Config::new(&server_id)
+            .unwrap()
+            .with_stream_muxers(server_muxers.iter().map(|m| m.to_string()).collect());
+
+        let ((_, server_session), (_, client_session)) = futures::future::try_join(
+            server_config.upgrade_inbound(server, ""),
+            client_config.upgrade_outbound(client, ""),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            client_session.selected_muxer(),
+            server_session.selected_muxer()
+        );
+
+        server_session.selected_muxer().map(ToOwned::to_owned)
+    })
+}
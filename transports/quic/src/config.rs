@@ -60,6 +60,28 @@ pub struct Config {
     /// As client the version is chosen based on the remote's address.
     pub support_draft_29: bool,
 
+    /// Whether to validate the address of every remote before accepting its connection.
+    ///
+    /// If enabled, a QUIC Retry packet carrying an address validation token is sent in response
+    /// to every initial packet from a remote whose address has not been validated yet, costing an
+    /// additional round-trip for honest peers but preventing spoofed-source amplification attacks.
+    ///
+    /// Each issued retry is reported as a [`Error::AddressValidationRetry`](crate::Error::AddressValidationRetry)
+    /// listener error. Disabled by default.
+    pub require_address_validation: bool,
+
+    /// Duration after which an address validation token issued in a QUIC Retry packet expires.
+    ///
+    /// See [`quinn::ServerConfig::retry_token_lifetime`] for more info.
+    pub retry_token_lifetime: Duration,
+
+    /// Maximum number of incoming connection attempts that may be pending on a listener
+    /// at the same time, i.e. that have been received but not yet accepted or refused.
+    ///
+    /// Connection attempts beyond this limit are refused right away.
+    /// See [`quinn::ServerConfig::max_incoming`] for more info.
+    pub max_incoming_connection_attempts: usize,
+
     /// TLS client config for the inner [`quinn::ClientConfig`].
     client_tls_config: Arc<QuicClientConfig>,
     /// TLS server config for the inner [`quinn::ServerConfig`].
@@ -85,6 +107,9 @@ impl Config {
             client_tls_config,
             server_tls_config,
             support_draft_29: false,
+            require_address_validation: false,
+            retry_token_lifetime: Duration::from_secs(15),
+            max_incoming_connection_attempts: 1 << 16,
             handshake_timeout: Duration::from_secs(5),
             max_idle_timeout: 10 * 1000,
             max_concurrent_stream_limit: 256,
@@ -132,6 +157,9 @@ impl From<Config> for QuinnConfig {
             max_connection_data,
             max_stream_data,
             support_draft_29,
+            require_address_validation: _,
+            retry_token_lifetime,
+            max_incoming_connection_attempts,
             handshake_timeout: _,
             keypair,
             mtu_discovery_config,
@@ -156,6 +184,8 @@ impl From<Config> for QuinnConfig {
         // Long-term this should be enabled, however we then need to handle address change
         // on connections in the `Connection`.
         server_config.migration(false);
+        server_config.retry_token_lifetime(retry_token_lifetime);
+        server_config.max_incoming(max_incoming_connection_attempts);
 
         let mut client_config = quinn::ClientConfig::new(client_tls_config);
         client_config.transport_config(transport);
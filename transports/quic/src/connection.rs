@@ -28,6 +28,7 @@ use crate::{ConnectionError, Error};
 
 use futures::{future::BoxFuture, FutureExt};
 use libp2p_core::muxing::{StreamMuxer, StreamMuxerEvent};
+use quinn::rustls::pki_types::CertificateDer;
 use std::{
     pin::Pin,
     task::{Context, Poll},
@@ -62,6 +63,48 @@ impl Connection {
             closing: None,
         }
     }
+
+    /// Returns details about the TLS handshake of this connection.
+    ///
+    /// The peer's certificate has already been verified against its [`PeerId`] when the
+    /// connection was established. Applications with additional requirements can audit the
+    /// connection e.g. by mapping the transport with [`Transport::map`] before the
+    /// [`Connection`] is boxed.
+    ///
+    /// [`PeerId`]: libp2p_identity::PeerId
+    /// [`Transport::map`]: libp2p_core::Transport::map
+    pub fn handshake_info(&self) -> HandshakeInfo {
+        let handshake_data = self
+            .connection
+            .handshake_data()
+            .and_then(|data| data.downcast::<quinn::crypto::rustls::HandshakeData>().ok());
+        let peer_certificates = self
+            .connection
+            .peer_identity()
+            .and_then(|identity| identity.downcast::<Vec<CertificateDer<'static>>>().ok())
+            .map(|certificates| *certificates)
+            .unwrap_or_default();
+
+        HandshakeInfo {
+            alpn_protocol: handshake_data
+                .as_ref()
+                .and_then(|data| data.protocol.clone()),
+            server_name: handshake_data.and_then(|data| data.server_name),
+            peer_certificates,
+        }
+    }
+}
+
+/// Details about the TLS handshake of a QUIC [`Connection`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct HandshakeInfo {
+    /// The application protocol negotiated via ALPN.
+    pub alpn_protocol: Option<Vec<u8>>,
+    /// The server name sent by the dialer. Only known to the listener.
+    pub server_name: Option<String>,
+    /// The DER-encoded certificate chain presented by the peer, end-entity certificate first.
+    pub peer_certificates: Vec<CertificateDer<'static>>,
 }
 
 impl StreamMuxer for Connection {
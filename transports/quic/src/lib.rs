@@ -63,10 +63,11 @@ mod hole_punching;
 mod provider;
 mod transport;
 
+use libp2p_core::transport::ListenerId;
 use std::net::SocketAddr;
 
 pub use config::Config;
-pub use connection::{Connecting, Connection, Stream};
+pub use connection::{Connecting, Connection, HandshakeInfo, Stream};
 
 #[cfg(feature = "async-std")]
 pub use provider::async_std;
@@ -101,6 +102,17 @@ pub enum Error {
     /// Error when holepunching for a remote is already in progress
     #[error("Already punching hole for {0}).")]
     HolePunchInProgress(SocketAddr),
+
+    /// A QUIC Retry packet was sent to a remote whose address has not been validated yet.
+    ///
+    /// Only reported if [`Config::require_address_validation`] is enabled. The remote is
+    /// expected to retry the connection attempt with the address validation token.
+    #[error("Sent address validation retry to {0}.")]
+    AddressValidationRetry(SocketAddr),
+
+    /// The transport has no active listener with the given [`ListenerId`].
+    #[error("No active listener with id {0}.")]
+    UnknownListener(ListenerId),
 }
 
 /// Dialing a remote peer failed.
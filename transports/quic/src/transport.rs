@@ -41,7 +41,7 @@ use libp2p_core::{
 use libp2p_identity::PeerId;
 use socket2::{Domain, Socket, Type};
 use std::collections::hash_map::{DefaultHasher, Entry};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::{Hash, Hasher};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket};
 use std::time::Duration;
@@ -71,6 +71,8 @@ pub struct GenTransport<P: Provider> {
     handshake_timeout: Duration,
     /// Whether draft-29 is supported for dialing and listening.
     support_draft_29: bool,
+    /// Whether listeners require remotes to validate their address before accepting connections.
+    require_address_validation: bool,
     /// Streams of active [`Listener`]s.
     listeners: SelectAll<Listener<P>>,
     /// Dialer for each socket family if no matching listener exists.
@@ -86,6 +88,7 @@ impl<P: Provider> GenTransport<P> {
     pub fn new(config: Config) -> Self {
         let handshake_timeout = config.handshake_timeout;
         let support_draft_29 = config.support_draft_29;
+        let require_address_validation = config.require_address_validation;
         let quinn_config = config.into();
         Self {
             listeners: SelectAll::new(),
@@ -94,6 +97,7 @@ impl<P: Provider> GenTransport<P> {
             dialer: HashMap::new(),
             waker: None,
             support_draft_29,
+            require_address_validation,
             hole_punch_attempts: Default::default(),
         }
     }
@@ -183,31 +187,56 @@ impl<P: Provider> GenTransport<P> {
         }
     }
 
-    fn create_socket(&self, socket_addr: SocketAddr) -> io::Result<UdpSocket> {
-        let socket = Socket::new(
-            Domain::for_address(socket_addr),
-            Type::DGRAM,
-            Some(socket2::Protocol::UDP),
-        )?;
-        if socket_addr.is_ipv6() {
-            socket.set_only_v6(true)?;
+    /// Moves the listener with the given [`ListenerId`] to a fresh UDP socket on the same IP
+    /// address, e.g. to obtain a new port mapping for hole punching or after the NAT binding of the
+    /// current port went stale. Use port `0` to let the operating system pick the port.
+    ///
+    /// Outbound connections of the listener's endpoint migrate to the new socket. Inbound
+    /// connections are lost, since QUIC servers cannot migrate. The listener reports its old
+    /// addresses through [`TransportEvent::AddressExpired`] and the new ones through
+    /// [`TransportEvent::NewAddress`].
+    ///
+    /// Returns the address of the new socket.
+    pub fn rebind_listener(
+        &mut self,
+        listener_id: ListenerId,
+        port: u16,
+    ) -> Result<SocketAddr, Error> {
+        let listener = self
+            .listeners
+            .iter_mut()
+            .find(|l| l.listener_id == listener_id && !l.is_closed)
+            .ok_or(Error::UnknownListener(listener_id))?;
+        let socket_addr = SocketAddr::new(listener.socket_addr().ip(), port);
+        let socket = create_socket(socket_addr)?;
+        let socket_addr = listener.rebind(socket)?;
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
         }
 
-        socket.bind(&socket_addr.into())?;
+        Ok(socket_addr)
+    }
 
-        Ok(socket.into())
+    /// Moves the endpoints used for dialing when no listener exists to fresh UDP sockets with
+    /// ports picked by the operating system.
+    ///
+    /// Connections established through these endpoints migrate to the new sockets, see
+    /// [`GenTransport::rebind_listener`] for listeners.
+    pub fn rebind_dialers(&mut self) -> Result<(), Error> {
+        for (socket_family, endpoint) in &self.dialer {
+            let socket = UdpSocket::bind(socket_family.unspecified_socket_addr())?;
+            endpoint.rebind(socket)?;
+        }
+        Ok(())
     }
 
     fn bound_socket(&mut self, socket_addr: SocketAddr) -> Result<quinn::Endpoint, Error> {
-        let socket_family = socket_addr.ip().into();
+        let socket_family: SocketFamily = socket_addr.ip().into();
         if let Some(waker) = self.waker.take() {
             waker.wake();
         }
-        let listen_socket_addr = match socket_family {
-            SocketFamily::Ipv4 => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0),
-            SocketFamily::Ipv6 => SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0),
-        };
-        let socket = UdpSocket::bind(listen_socket_addr)?;
+        let socket = UdpSocket::bind(socket_family.unspecified_socket_addr())?;
         let endpoint_config = self.quinn_config.endpoint_config.clone();
         let endpoint = Self::new_endpoint(endpoint_config, None, socket)?;
         Ok(endpoint)
@@ -228,7 +257,7 @@ impl<P: Provider> Transport for GenTransport<P> {
         let (socket_addr, version, _peer_id) = self.remote_multiaddr_to_socketaddr(addr, false)?;
         let endpoint_config = self.quinn_config.endpoint_config.clone();
         let server_config = self.quinn_config.server_config.clone();
-        let socket = self.create_socket(socket_addr).map_err(Self::Error::from)?;
+        let socket = create_socket(socket_addr).map_err(Self::Error::from)?;
 
         let socket_c = socket.try_clone().map_err(Self::Error::from)?;
         let endpoint = Self::new_endpoint(endpoint_config, Some(server_config), socket)?;
@@ -237,6 +266,7 @@ impl<P: Provider> Transport for GenTransport<P> {
             socket_c,
             endpoint,
             self.handshake_timeout,
+            self.require_address_validation,
             version,
         )?;
         self.listeners.push(listener);
@@ -412,6 +442,21 @@ impl<P: Provider> Transport for GenTransport<P> {
     }
 }
 
+fn create_socket(socket_addr: SocketAddr) -> io::Result<UdpSocket> {
+    let socket = Socket::new(
+        Domain::for_address(socket_addr),
+        Type::DGRAM,
+        Some(socket2::Protocol::UDP),
+    )?;
+    if socket_addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+
+    socket.bind(&socket_addr.into())?;
+
+    Ok(socket.into())
+}
+
 impl From<Error> for TransportError<Error> {
     fn from(err: Error) -> Self {
         TransportError::Other(err)
@@ -437,6 +482,9 @@ struct Listener<P: Provider> {
     /// Timeout for connection establishment on inbound connections.
     handshake_timeout: Duration,
 
+    /// Whether a retry is sent to remotes whose address has not been validated yet.
+    require_address_validation: bool,
+
     /// Watcher for network interface changes.
     ///
     /// None if we are only listening on a single interface.
@@ -445,8 +493,8 @@ struct Listener<P: Provider> {
     /// Whether the listener was closed and the stream should terminate.
     is_closed: bool,
 
-    /// Pending event to reported.
-    pending_event: Option<<Self as Stream>::Item>,
+    /// Pending events to be reported.
+    pending_events: VecDeque<<Self as Stream>::Item>,
 
     /// The stream must be awaken after it has been closed to deliver the last event.
     close_listener_waker: Option<Waker>,
@@ -460,20 +508,20 @@ impl<P: Provider> Listener<P> {
         socket: UdpSocket,
         endpoint: quinn::Endpoint,
         handshake_timeout: Duration,
+        require_address_validation: bool,
         version: ProtocolVersion,
     ) -> Result<Self, Error> {
         let if_watcher;
-        let pending_event;
+        let mut pending_events = VecDeque::new();
         let mut listening_addresses = HashSet::new();
         let local_addr = socket.local_addr()?;
         if local_addr.ip().is_unspecified() {
             if_watcher = Some(P::new_if_watcher()?);
-            pending_event = None;
         } else {
             if_watcher = None;
             listening_addresses.insert(local_addr.ip());
             let ma = socketaddr_to_multiaddr(&local_addr, version);
-            pending_event = Some(TransportEvent::NewAddress {
+            pending_events.push_back(TransportEvent::NewAddress {
                 listener_id,
                 listen_addr: ma,
             })
@@ -489,9 +537,10 @@ impl<P: Provider> Listener<P> {
             listener_id,
             version,
             handshake_timeout,
+            require_address_validation,
             if_watcher,
             is_closed: false,
-            pending_event,
+            pending_events,
             close_listener_waker: None,
             listening_addresses,
         })
@@ -504,10 +553,11 @@ impl<P: Provider> Listener<P> {
             return;
         }
         self.endpoint.close(From::from(0u32), &[]);
-        self.pending_event = Some(TransportEvent::ListenerClosed {
-            listener_id: self.listener_id,
-            reason,
-        });
+        self.pending_events
+            .push_back(TransportEvent::ListenerClosed {
+                listener_id: self.listener_id,
+                reason,
+            });
         self.is_closed = true;
 
         // Wake the stream to deliver the last event.
@@ -516,6 +566,42 @@ impl<P: Provider> Listener<P> {
         }
     }
 
+    /// Switch the endpoint to the given socket and report the changed listen addresses.
+    fn rebind(&mut self, socket: UdpSocket) -> Result<SocketAddr, Error> {
+        let old_addr = self.socket_addr();
+        let socket_c = socket.try_clone()?;
+        self.endpoint.rebind(socket)?;
+        self.socket = socket_c;
+        let new_addr = self.socket_addr();
+        tracing::debug!(old=%old_addr, new=%new_addr, "Rebound listener");
+
+        for ip in &self.listening_addresses {
+            self.pending_events
+                .push_back(TransportEvent::AddressExpired {
+                    listener_id: self.listener_id,
+                    listen_addr: socketaddr_to_multiaddr(
+                        &SocketAddr::new(*ip, old_addr.port()),
+                        self.version,
+                    ),
+                });
+        }
+        for ip in &self.listening_addresses {
+            self.pending_events.push_back(TransportEvent::NewAddress {
+                listener_id: self.listener_id,
+                listen_addr: socketaddr_to_multiaddr(
+                    &SocketAddr::new(*ip, new_addr.port()),
+                    self.version,
+                ),
+            });
+        }
+
+        if let Some(waker) = self.close_listener_waker.take() {
+            waker.wake();
+        }
+
+        Ok(new_addr)
+    }
+
     /// Clone underlying socket (for hole punching).
     fn try_clone_socket(&self) -> std::io::Result<UdpSocket> {
         self.socket.try_clone()
@@ -580,7 +666,7 @@ impl<P: Provider> Stream for Listener<P> {
     type Item = TransportEvent<<GenTransport<P> as Transport>::ListenerUpgrade, Error>;
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         loop {
-            if let Some(event) = self.pending_event.take() {
+            if let Some(event) = self.pending_events.pop_front() {
                 return Poll::Ready(Some(event));
             }
             if self.is_closed {
@@ -591,10 +677,27 @@ impl<P: Provider> Stream for Listener<P> {
             }
 
             match self.accept.poll_unpin(cx) {
-                Poll::Ready(Some(incoming)) => {
+                Poll::Ready(Some(mut incoming)) => {
                     let endpoint = self.endpoint.clone();
                     self.accept = async move { endpoint.accept().await }.boxed();
 
+                    if self.require_address_validation && !incoming.remote_address_validated() {
+                        let remote_addr = incoming.remote_address();
+                        match incoming.retry() {
+                            Ok(()) => {
+                                tracing::debug!(
+                                    remote=%remote_addr,
+                                    "Sent address validation retry"
+                                );
+                                return Poll::Ready(Some(TransportEvent::ListenerError {
+                                    listener_id: self.listener_id,
+                                    error: Error::AddressValidationRetry(remote_addr),
+                                }));
+                            }
+                            Err(e) => incoming = e.into_incoming(),
+                        }
+                    }
+
                     let connecting = match incoming.accept() {
                         Ok(connecting) => connecting,
                         Err(error) => {
@@ -636,8 +739,12 @@ impl<P: Provider> fmt::Debug for Listener<P> {
         f.debug_struct("Listener")
             .field("listener_id", &self.listener_id)
             .field("handshake_timeout", &self.handshake_timeout)
+            .field(
+                "require_address_validation",
+                &self.require_address_validation,
+            )
             .field("is_closed", &self.is_closed)
-            .field("pending_event", &self.pending_event)
+            .field("pending_events", &self.pending_events)
             .finish()
     }
 }
@@ -655,6 +762,13 @@ pub(crate) enum SocketFamily {
 }
 
 impl SocketFamily {
+    fn unspecified_socket_addr(&self) -> SocketAddr {
+        match self {
+            SocketFamily::Ipv4 => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0),
+            SocketFamily::Ipv6 => SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0),
+        }
+    }
+
     fn is_same(a: &IpAddr, b: &IpAddr) -> bool {
         matches!(
             (a, b),
@@ -476,6 +476,48 @@ async fn test_local_listener_reuse() {
     assert_eq!(send_back_addr, a_listen_addr);
 }
 
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn rebind_listener() {
+    let keypair = generate_tls_keypair();
+    let mut a_transport =
+        quic::GenTransport::<quic::tokio::Provider>::new(quic::Config::new(&keypair));
+    let listener_id = ListenerId::next();
+    a_transport
+        .listen_on(listener_id, "/ip4/127.0.0.1/udp/0/quic-v1".parse().unwrap())
+        .unwrap();
+    let old_addr = poll_fn(|cx| Pin::new(&mut a_transport).poll(cx))
+        .await
+        .into_new_address()
+        .unwrap();
+
+    assert!(matches!(
+        a_transport.rebind_listener(ListenerId::next(), 0),
+        Err(quic::Error::UnknownListener(_))
+    ));
+    let socket_addr = a_transport.rebind_listener(listener_id, 0).unwrap();
+
+    match poll_fn(|cx| Pin::new(&mut a_transport).poll(cx)).await {
+        TransportEvent::AddressExpired { listen_addr, .. } => assert_eq!(listen_addr, old_addr),
+        e => panic!("{e:?}"),
+    }
+    let new_addr = poll_fn(|cx| Pin::new(&mut a_transport).poll(cx))
+        .await
+        .into_new_address()
+        .unwrap();
+    assert_ne!(new_addr, old_addr);
+    assert!(new_addr
+        .iter()
+        .any(|p| p == Protocol::Udp(socket_addr.port())));
+
+    // The listener accepts connections on its new address.
+    let mut a_transport = a_transport
+        .map(|(p, c), _| (p, StreamMuxerBox::new(c)))
+        .boxed();
+    let (_, mut b_transport) = create_default_transport::<quic::tokio::Provider>();
+    connect(&mut a_transport, &mut b_transport, new_addr).await;
+}
+
 async fn smoke<P: Provider>() {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
@@ -554,6 +596,84 @@ macro_rules! swap_protocol {
     };
 }
 
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn handshake_info() {
+    let handshakes = Arc::new(Mutex::new(Vec::new()));
+    let new_transport = || {
+        let keypair = generate_tls_keypair();
+        let handshakes = handshakes.clone();
+        quic::tokio::Transport::new(quic::Config::new(&keypair))
+            .map(move |(peer_id, connection), _| {
+                handshakes
+                    .lock()
+                    .unwrap()
+                    .push((peer_id, connection.handshake_info()));
+                (peer_id, StreamMuxerBox::new(connection))
+            })
+            .boxed()
+    };
+    let mut a_transport = new_transport();
+    let mut b_transport = new_transport();
+
+    let a_addr = start_listening(&mut a_transport, "/ip4/127.0.0.1/udp/0/quic-v1").await;
+    connect(&mut a_transport, &mut b_transport, a_addr).await;
+
+    let handshakes = handshakes.lock().unwrap();
+    assert_eq!(handshakes.len(), 2);
+    for (peer_id, info) in handshakes.iter() {
+        assert_eq!(info.alpn_protocol.as_deref(), Some(&b"libp2p"[..]));
+        assert_eq!(info.peer_certificates.len(), 1);
+        let certificate = libp2p_tls::certificate::parse(&info.peer_certificates[0]).unwrap();
+        assert_eq!(certificate.peer_id(), *peer_id);
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn address_validation() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .try_init();
+
+    let (a_peer_id, mut a_transport) = create_transport::<quic::tokio::Provider>(|config| {
+        config.require_address_validation = true;
+    });
+    let (b_peer_id, mut b_transport) = create_default_transport::<quic::tokio::Provider>();
+
+    let addr = start_listening(&mut a_transport, "/ip4/127.0.0.1/udp/0/quic-v1").await;
+    let ((a_connected, _), (b_connected, _)) = future::join(
+        async {
+            // The first connection attempt of the dialer is answered with a retry.
+            match a_transport.select_next_some().await {
+                TransportEvent::ListenerError { error, .. } => {
+                    let error = error
+                        .get_ref()
+                        .and_then(|e| e.downcast_ref::<quic::Error>())
+                        .unwrap();
+                    assert!(matches!(
+                        error,
+                        quic::Error::AddressValidationRetry(remote) if remote.ip().is_loopback()
+                    ));
+                }
+                e => panic!("Unexpected event: {e:?}"),
+            }
+
+            let (upgrade, _) = a_transport
+                .select_next_some()
+                .await
+                .into_incoming()
+                .unwrap();
+            upgrade.await.unwrap()
+        },
+        async { dial(&mut b_transport, addr).await.unwrap() },
+    )
+    .await;
+
+    assert_eq!(a_connected, b_peer_id);
+    assert_eq!(b_connected, a_peer_id);
+}
+
 fn generate_tls_keypair() -> libp2p_identity::Keypair {
     libp2p_identity::Keypair::generate_ed25519()
 }
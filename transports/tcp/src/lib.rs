@@ -36,6 +36,8 @@ pub use provider::async_io;
 #[cfg(feature = "tokio")]
 pub use provider::tokio;
 
+pub use socket2::Socket;
+
 use futures::{future::Ready, prelude::*, stream::SelectAll};
 use futures_timer::Delay;
 use if_watch::IfEvent;
@@ -44,11 +46,12 @@ use libp2p_core::{
     transport::{DialOpts, ListenerId, PortUse, TransportError, TransportEvent},
 };
 use provider::{Incoming, Provider};
-use socket2::{Domain, Socket, Type};
+use socket2::{Domain, Type};
 use std::{
     collections::{HashSet, VecDeque},
-    io,
+    fmt, io,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener},
+    ops::RangeInclusive,
     pin::Pin,
     sync::{Arc, RwLock},
     task::{Context, Poll, Waker},
@@ -64,10 +67,24 @@ pub struct Config {
     nodelay: Option<bool>,
     /// Size of the listen backlog for listen sockets.
     backlog: u32,
+    /// Ports to choose from when listening on port `0`, or `None` to let the OS pick one.
+    listen_port_range: Option<RangeInclusive<Port>>,
+    /// Callback to configure new sockets, see [`Config::socket_config`].
+    socket_config: Option<SocketConfig>,
 }
 
 type Port = u16;
 
+/// A callback configuring new sockets, see [`Config::socket_config`].
+#[derive(Clone)]
+struct SocketConfig(Arc<dyn Fn(&Socket) -> io::Result<()> + Send + Sync>);
+
+impl fmt::Debug for SocketConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SocketConfig").finish_non_exhaustive()
+    }
+}
+
 /// The configuration for port reuse of listening sockets.
 #[derive(Debug, Clone, Default)]
 struct PortReuse {
@@ -139,11 +156,17 @@ impl Config {
     ///     See [`Config::ttl`].
     ///   * The size of the listen backlog for new listening sockets is `1024`.
     ///     See [`Config::listen_backlog`].
+    ///   * Listening on port `0` binds to an ephemeral port chosen by the OS.
+    ///     See [`Config::listen_port_range`].
+    ///   * No further socket options are set.
+    ///     See [`Config::socket_config`].
     pub fn new() -> Self {
         Self {
             ttl: None,
             nodelay: Some(false), // Disable Nagle's algorithm by default
             backlog: 1024,
+            listen_port_range: None,
+            socket_config: None,
         }
     }
 
@@ -165,6 +188,34 @@ impl Config {
         self
     }
 
+    /// Configures the range of ports to choose from when listening on port `0`.
+    ///
+    /// Instead of binding to an ephemeral port chosen by the OS, the ports of the range are
+    /// tried in ascending order and the first one that is not in use is bound to. Listening
+    /// fails with [`io::ErrorKind::AddrInUse`] if all ports of the range are in use.
+    ///
+    /// This is useful in environments where a firewall only permits a window of ports.
+    /// Listening on a fixed, non-zero port is unaffected by this option.
+    pub fn listen_port_range(mut self, range: RangeInclusive<u16>) -> Self {
+        self.listen_port_range = Some(range);
+        self
+    }
+
+    /// Configures a callback that is invoked with every new socket before it is bound or
+    /// connected, e.g. to set options such as `IP_TOS`, `SO_BINDTODEVICE` or the buffer sizes
+    /// that aren't exposed by [`Config`].
+    ///
+    /// The callback is invoked for listening and dialing sockets alike, after the options of
+    /// the [`Config`] were applied, which it can thus override. If it returns an error, the
+    /// socket is dropped and the dial or listen attempt fails with that error.
+    pub fn socket_config(
+        mut self,
+        f: impl Fn(&Socket) -> io::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.socket_config = Some(SocketConfig(Arc::new(f)));
+        self
+    }
+
     /// Configures port reuse for local sockets, which implies
     /// reuse of listening ports for outgoing connections to
     /// enhance NAT traversal capabilities.
@@ -214,10 +265,54 @@ impl Config {
         #[cfg(not(all(unix, not(any(target_os = "solaris", target_os = "illumos")))))]
         let _ = port_use; // silence the unused warning on non-unix platforms (i.e. Windows)
 
+        if let Some(SocketConfig(f)) = &self.socket_config {
+            f(&socket)?;
+        }
+
         socket.set_nonblocking(true)?;
 
         Ok(socket)
     }
+
+    /// Binds a new listening socket to the given address.
+    fn bind_listener(&self, socket_addr: SocketAddr) -> io::Result<TcpListener> {
+        let socket = self.create_socket(socket_addr, PortUse::Reuse)?;
+        socket.bind(&socket_addr.into())?;
+        socket.listen(self.backlog as _)?;
+        socket.set_nonblocking(true)?;
+        Ok(socket.into())
+    }
+
+    /// Binds a new listening socket to the first free port of the given range.
+    fn bind_listener_in_range(
+        &self,
+        socket_addr: SocketAddr,
+        range: RangeInclusive<Port>,
+    ) -> io::Result<TcpListener> {
+        let mut last_err = io::Error::new(io::ErrorKind::AddrInUse, "empty listen port range");
+        for port in range.filter(|port| *port != 0) {
+            let socket_addr = SocketAddr::new(socket_addr.ip(), port);
+
+            // Listening sockets set `SO_REUSEPORT`, which would allow us to bind to a port that
+            // is already used by another listener with `SO_REUSEPORT`. Check that the port is
+            // free with a socket that doesn't set it first.
+            let probe = self
+                .create_socket(socket_addr, PortUse::New)
+                .and_then(|socket| socket.bind(&socket_addr.into()));
+            if let Err(e) = probe {
+                tracing::trace!(port=%port, "Port of listen port range is not available: {e}");
+                last_err = e;
+                continue;
+            }
+
+            match self.bind_listener(socket_addr) {
+                Ok(listener) => return Ok(listener),
+                Err(e) => last_err = e,
+            }
+        }
+
+        Err(last_err)
+    }
 }
 
 impl Default for Config {
@@ -273,11 +368,12 @@ where
         id: ListenerId,
         socket_addr: SocketAddr,
     ) -> io::Result<ListenStream<T>> {
-        let socket = self.config.create_socket(socket_addr, PortUse::Reuse)?;
-        socket.bind(&socket_addr.into())?;
-        socket.listen(self.config.backlog as _)?;
-        socket.set_nonblocking(true)?;
-        let listener: TcpListener = socket.into();
+        let listener = match self.config.listen_port_range.clone() {
+            Some(range) if socket_addr.port() == 0 => {
+                self.config.bind_listener_in_range(socket_addr, range)?
+            }
+            _ => self.config.bind_listener(socket_addr)?,
+        };
         let local_addr = listener.local_addr()?;
 
         if local_addr.ip().is_unspecified() {
@@ -1187,6 +1283,99 @@ mod tests {
         }
     }
 
+    #[test]
+    fn listen_port_range_skips_ports_in_use() {
+        fn test<T: Provider>() {
+            let occupied = TcpListener::bind("127.0.0.1:0").unwrap();
+            let port = occupied.local_addr().unwrap().port();
+
+            let mut tcp = Transport::<T>::new(Config::new().listen_port_range(port..=port));
+            let err = tcp
+                .do_listen(ListenerId::next(), "127.0.0.1:0".parse().unwrap())
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+
+            drop(occupied);
+            let listener = tcp
+                .do_listen(ListenerId::next(), "127.0.0.1:0".parse().unwrap())
+                .unwrap();
+            assert_eq!(listener.listen_addr.port(), port);
+        }
+        #[cfg(feature = "async-io")]
+        {
+            async_std::task::block_on(async {
+                test::<async_io::Tcp>();
+            })
+        }
+        #[cfg(feature = "tokio")]
+        {
+            let rt = ::tokio::runtime::Builder::new_current_thread()
+                .enable_io()
+                .build()
+                .unwrap();
+            rt.block_on(async {
+                test::<tokio::Tcp>();
+            });
+        }
+    }
+
+    #[test]
+    fn socket_config_is_applied_to_new_sockets() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        fn test<T: Provider>() {
+            let configured = Arc::new(AtomicUsize::new(0));
+            let config = Config::new().socket_config({
+                let configured = configured.clone();
+                move |socket| {
+                    configured.fetch_add(1, Ordering::SeqCst);
+                    socket.set_recv_buffer_size(64 * 1024)
+                }
+            });
+            let mut tcp = Transport::<T>::new(config);
+            tcp.do_listen(ListenerId::next(), "127.0.0.1:0".parse().unwrap())
+                .unwrap();
+            assert_eq!(configured.load(Ordering::SeqCst), 1);
+
+            let mut tcp =
+                Transport::<T>::new(Config::new().socket_config(|_| {
+                    Err(io::Error::new(io::ErrorKind::PermissionDenied, "denied"))
+                }));
+            let err = tcp
+                .do_listen(ListenerId::next(), "127.0.0.1:0".parse().unwrap())
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+            assert!(matches!(
+                tcp.dial(
+                    "/ip4/127.0.0.1/tcp/4001".parse().unwrap(),
+                    DialOpts {
+                        role: Endpoint::Dialer,
+                        port_use: PortUse::New,
+                    },
+                ),
+                Err(TransportError::Other(e)) if e.kind() == io::ErrorKind::PermissionDenied
+            ));
+        }
+        #[cfg(feature = "async-io")]
+        {
+            async_std::task::block_on(async {
+                test::<async_io::Tcp>();
+            })
+        }
+        #[cfg(feature = "tokio")]
+        {
+            let rt = ::tokio::runtime::Builder::new_current_thread()
+                .enable_io()
+                .build()
+                .unwrap();
+            rt.block_on(async {
+                test::<tokio::Tcp>();
+            });
+        }
+    }
+
     #[test]
     fn test_listens_ipv4_ipv6_separately() {
         fn test<T: Provider>() {
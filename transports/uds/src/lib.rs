@@ -30,6 +30,12 @@
 //!
 //! The `UdsConfig` structs implements the `Transport` trait of the `core` library. See the
 //! documentation of `core` and of libp2p in general to learn how to use the `Transport` trait.
+//!
+//! # Socket permissions
+//!
+//! On multi-user hosts, access to the socket files created when listening can be restricted via
+//! `with_mode` and `with_owner`, e.g. to only allow a given service account to connect. Socket
+//! files are left behind once a listener closes, unless `with_cleanup` is enabled.
 
 #![cfg(all(
     unix,
@@ -50,9 +56,11 @@ use libp2p_core::{
     Transport,
 };
 use std::collections::VecDeque;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use std::{io, path::PathBuf};
+use std::{fs, io, path::PathBuf};
 
 pub type Listener<T> = BoxStream<
     'static,
@@ -67,6 +75,7 @@ macro_rules! codegen {
         /// Represents the configuration for a Unix domain sockets transport capability for libp2p.
         pub struct $uds_config {
             listeners: VecDeque<(ListenerId, Listener<Self>)>,
+            socket_config: SocketConfig,
         }
 
         impl $uds_config {
@@ -74,8 +83,37 @@ macro_rules! codegen {
             pub fn new() -> $uds_config {
                 $uds_config {
                     listeners: VecDeque::new(),
+                    socket_config: SocketConfig::default(),
                 }
             }
+
+            /// Sets the file mode of the socket files created when listening, e.g. `0o660`.
+            ///
+            /// The mode is applied right after binding the socket. Until then, the socket file
+            /// has the permissions given by the process' umask.
+            pub fn with_mode(mut self, mode: u32) -> Self {
+                self.socket_config.mode = Some(mode);
+                self
+            }
+
+            /// Sets the owner and group of the socket files created when listening.
+            ///
+            /// `None` leaves the owner or group unchanged. Changing the owner usually requires
+            /// elevated privileges, and listening fails if the ownership can't be changed.
+            pub fn with_owner(mut self, uid: Option<u32>, gid: Option<u32>) -> Self {
+                self.socket_config.uid = uid;
+                self.socket_config.gid = gid;
+                self
+            }
+
+            /// Whether to remove the socket file once its listener is closed, e.g. via
+            /// [`Transport::remove_listener`] or by dropping the transport.
+            ///
+            /// Disabled by default.
+            pub fn with_cleanup(mut self, cleanup: bool) -> Self {
+                self.socket_config.cleanup = cleanup;
+                self
+            }
         }
 
         impl Default for $uds_config {
@@ -96,10 +134,18 @@ macro_rules! codegen {
                 addr: Multiaddr,
             ) -> Result<(), TransportError<Self::Error>> {
                 if let Ok(path) = multiaddr_to_path(&addr) {
+                    let socket_config = self.socket_config.clone();
                     #[allow(clippy::redundant_closure_call)]
-                    let listener = $build_listener(path)
+                    let listener = $build_listener(path.clone())
+                        .and_then(move |listener| {
+                            future::ready(
+                                socket_config
+                                    .prepare(&path)
+                                    .map(|socket_file| (listener, socket_file)),
+                            )
+                        })
                         .map_err(Err)
-                        .map_ok(move |listener| {
+                        .map_ok(move |(listener, socket_file)| {
                             stream::once({
                                 let addr = addr.clone();
                                 async move {
@@ -111,8 +157,8 @@ macro_rules! codegen {
                                 }
                             })
                             .chain(stream::unfold(
-                                listener,
-                                move |listener| {
+                                (listener, socket_file),
+                                move |(listener, socket_file)| {
                                     let addr = addr.clone();
                                     async move {
                                         let event = match listener.accept().await {
@@ -130,7 +176,7 @@ macro_rules! codegen {
                                                 error,
                                             },
                                         };
-                                        Some((Ok(event), listener))
+                                        Some((Ok(event), (listener, socket_file)))
                                     }
                                 },
                             ))
@@ -217,6 +263,45 @@ codegen!(
     tokio::net::UnixStream,
 );
 
+/// The permissions, ownership and cleanup of the socket files created when listening.
+#[derive(Debug, Clone, Default)]
+struct SocketConfig {
+    mode: Option<u32>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    cleanup: bool,
+}
+
+impl SocketConfig {
+    /// Applies the permissions and ownership to the socket file bound at `path`.
+    ///
+    /// Returns the guard removing the socket file when dropped, if cleanup is enabled.
+    fn prepare(&self, path: &Path) -> io::Result<Option<SocketFile>> {
+        // Created first, to remove the socket file if it can't be prepared.
+        let socket_file = self.cleanup.then(|| SocketFile(path.to_owned()));
+
+        if let Some(mode) = self.mode {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        }
+        if self.uid.is_some() || self.gid.is_some() {
+            std::os::unix::fs::chown(path, self.uid, self.gid)?;
+        }
+
+        Ok(socket_file)
+    }
+}
+
+/// Removes the socket file at the contained path when dropped.
+struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_file(&self.0) {
+            tracing::debug!(path=%self.0.display(), "Failed to remove socket file: {error}");
+        }
+    }
+}
+
 /// Turns a `Multiaddr` containing a single `Unix` component into a path.
 ///
 /// Also returns an error if the path is not absolute, as we don't want to dial/listen on relative
@@ -245,9 +330,10 @@ mod tests {
     use futures::{channel::oneshot, prelude::*};
     use libp2p_core::{
         multiaddr::{Multiaddr, Protocol},
-        transport::{DialOpts, ListenerId, PortUse},
+        transport::{DialOpts, ListenerId, PortUse, TransportEvent},
         Endpoint, Transport,
     };
+    use std::os::unix::fs::PermissionsExt;
     use std::{borrow::Cow, path::Path};
 
     #[test]
@@ -318,6 +404,36 @@ mod tests {
         });
     }
 
+    #[test]
+    fn applies_mode_and_removes_socket_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket = temp_dir.path().join("socket");
+        let addr = Multiaddr::from(Protocol::Unix(Cow::Owned(
+            socket.to_string_lossy().into_owned(),
+        )));
+
+        async_std::task::block_on(async move {
+            let mut transport = UdsConfig::new().with_mode(0o600).with_cleanup(true).boxed();
+            let id = ListenerId::next();
+            transport.listen_on(id, addr).unwrap();
+
+            transport
+                .select_next_some()
+                .await
+                .into_new_address()
+                .expect("listen address");
+            let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+
+            assert!(transport.remove_listener(id));
+            match transport.select_next_some().await {
+                TransportEvent::ListenerClosed { listener_id, .. } => assert_eq!(listener_id, id),
+                _ => panic!("Unexpected event"),
+            }
+            assert!(!socket.exists());
+        });
+    }
+
     #[test]
     #[ignore] // TODO: for the moment unix addresses fail to parse
     fn larger_addr_denied() {
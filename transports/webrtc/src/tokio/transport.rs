@@ -46,11 +46,21 @@ use crate::tokio::{
 };
 
 /// A WebRTC transport with direct p2p communication (without a STUN server).
+///
+/// All connections of a listener, inbound and outbound, are multiplexed over the single UDP socket
+/// of that listener, so only the port passed to [`listen_on`](libp2p_core::Transport::listen_on)
+/// has to be reachable. Dialing uses the socket of a listener with the same IP version as the
+/// remote, which is why at least one listener is required.
+///
+/// To use the same UDP port for all listeners, e.g. to allow it through a firewall, see
+/// [`Transport::with_udp_mux_port`].
 pub struct Transport {
     /// The config which holds this peer's keys and certificate.
     config: Config,
     /// All the active listeners.
     listeners: SelectAll<ListenStream>,
+    /// The port the UDP sockets of all listeners are bound to, if configured.
+    udp_mux_port: Option<u16>,
 }
 
 impl Transport {
@@ -70,8 +80,48 @@ impl Transport {
         Self {
             config: Config::new(id_keys, certificate),
             listeners: SelectAll::new(),
+            udp_mux_port: None,
         }
     }
+
+    /// Binds the UDP socket of every listener to `port`, over which all its inbound and outbound
+    /// connections are multiplexed.
+    ///
+    /// Listen addresses with port `0` use `port` instead of an ephemeral port, and listening on
+    /// any other port fails with [`TransportError::MultiaddrNotSupported`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use libp2p_identity as identity;
+    /// use rand::thread_rng;
+    /// use libp2p_webrtc::tokio::{Transport, Certificate};
+    ///
+    /// let id_keys = identity::Keypair::generate_ed25519();
+    /// let transport = Transport::new(id_keys, Certificate::generate(&mut thread_rng()).unwrap())
+    ///     .with_udp_mux_port(9090);
+    /// ```
+    pub fn with_udp_mux_port(mut self, port: u16) -> Self {
+        self.udp_mux_port = Some(port);
+        self
+    }
+}
+
+impl Transport {
+    /// Selects the listener whose UDP socket is used to dial `remote`.
+    ///
+    /// Prefers a listener with the same IP version as `remote` that is bound to a loopback address
+    /// if and only if `remote` is a loopback address.
+    fn eligible_listener(&self, remote: &SocketAddr) -> Option<&ListenStream> {
+        let same_version = |l: &&ListenStream| l.listen_addr.is_ipv4() == remote.is_ipv4();
+
+        self.listeners
+            .iter()
+            .filter(same_version)
+            .find(|l| l.listen_addr.ip().is_loopback() == remote.ip().is_loopback())
+            .or_else(|| self.listeners.iter().find(same_version))
+            .or_else(|| self.listeners.iter().next())
+    }
 }
 
 impl libp2p_core::Transport for Transport {
@@ -85,8 +135,15 @@ impl libp2p_core::Transport for Transport {
         id: ListenerId,
         addr: Multiaddr,
     ) -> Result<(), TransportError<Self::Error>> {
-        let socket_addr =
-            parse_webrtc_listen_addr(&addr).ok_or(TransportError::MultiaddrNotSupported(addr))?;
+        let mut socket_addr = parse_webrtc_listen_addr(&addr)
+            .ok_or_else(|| TransportError::MultiaddrNotSupported(addr.clone()))?;
+        if let Some(port) = self.udp_mux_port {
+            match socket_addr.port() {
+                0 => socket_addr.set_port(port),
+                p if p == port => {}
+                _ => return Err(TransportError::MultiaddrNotSupported(addr)),
+            }
+        }
         let udp_mux = UDPMuxNewAddr::listen_on(socket_addr)
             .map_err(|io| TransportError::Other(Error::Io(io)))?;
 
@@ -140,9 +197,7 @@ impl libp2p_core::Transport for Transport {
         let config = self.config.clone();
         let client_fingerprint = self.config.fingerprint;
         let udp_mux = self
-            .listeners
-            .iter()
-            .next()
+            .eligible_listener(&sock_addr)
             .ok_or(TransportError::Other(Error::NoListeners))?
             .udp_mux
             .udp_mux_handle();
@@ -488,6 +543,86 @@ mod tests {
         assert!(maybe_addr.is_none())
     }
 
+    #[tokio::test]
+    async fn dial_uses_listener_of_same_ip_version() {
+        let id_keys = identity::Keypair::generate_ed25519();
+        let mut transport =
+            Transport::new(id_keys, Certificate::generate(&mut thread_rng()).unwrap());
+
+        let remote_v4: SocketAddr = "1.2.3.4:5678".parse().unwrap();
+        let remote_v6: SocketAddr = "[2001:db8::1]:5678".parse().unwrap();
+        assert!(transport.eligible_listener(&remote_v4).is_none());
+
+        transport
+            .listen_on(
+                ListenerId::next(),
+                "/ip4/127.0.0.1/udp/0/webrtc-direct".parse().unwrap(),
+            )
+            .unwrap();
+        transport
+            .listen_on(
+                ListenerId::next(),
+                "/ip6/::1/udp/0/webrtc-direct".parse().unwrap(),
+            )
+            .unwrap();
+
+        assert!(transport
+            .eligible_listener(&remote_v4)
+            .unwrap()
+            .listen_addr
+            .is_ipv4());
+        assert!(transport
+            .eligible_listener(&remote_v6)
+            .unwrap()
+            .listen_addr
+            .is_ipv6());
+    }
+
+    #[tokio::test]
+    async fn listeners_share_the_configured_udp_mux_port() {
+        let port = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let id_keys = identity::Keypair::generate_ed25519();
+        let mut transport =
+            Transport::new(id_keys, Certificate::generate(&mut thread_rng()).unwrap())
+                .with_udp_mux_port(port);
+
+        transport
+            .listen_on(
+                ListenerId::next(),
+                "/ip4/127.0.0.1/udp/0/webrtc-direct".parse().unwrap(),
+            )
+            .unwrap();
+        let other_port = if port == u16::MAX { port - 1 } else { port + 1 };
+        assert!(matches!(
+            transport.listen_on(
+                ListenerId::next(),
+                format!("/ip4/127.0.0.1/udp/{other_port}/webrtc-direct")
+                    .parse()
+                    .unwrap(),
+            ),
+            Err(TransportError::MultiaddrNotSupported(_))
+        ));
+
+        match poll_fn(|cx| Pin::new(&mut transport).as_mut().poll(cx)).await {
+            TransportEvent::NewAddress { listen_addr, .. } => {
+                assert!(listen_addr.iter().any(|p| p == Protocol::Udp(port)));
+            }
+            e => panic!("Unexpected event: {e:?}"),
+        }
+        assert_eq!(
+            transport
+                .eligible_listener(&"1.2.3.4:5678".parse().unwrap())
+                .unwrap()
+                .listen_addr
+                .port(),
+            port
+        );
+    }
+
     #[tokio::test]
     async fn close_listener() {
         let id_keys = identity::Keypair::generate_ed25519();
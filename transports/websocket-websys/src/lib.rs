@@ -69,6 +69,25 @@ pub struct Transport {
 /// Arbitrary, maximum amount we are willing to buffer before we throttle our user.
 const MAX_BUFFER: usize = 1024 * 1024;
 
+/// Checks that browsers accept the given code and reason for closing a WebSocket.
+fn validate_close_code_and_reason(code: u16, reason: &str) -> Result<(), Error> {
+    if code != REGULAR_CLOSE && !(3000..=4999).contains(&code) {
+        return Err(Error::invalid_close_code(code));
+    }
+    if reason.len() > MAX_CLOSE_REASON_LEN {
+        return Err(Error::close_reason_too_long(reason));
+    }
+
+    Ok(())
+}
+
+/// Status code of a normal closure. See <https://www.rfc-editor.org/rfc/rfc6455.html#section-7.4.1>.
+const REGULAR_CLOSE: u16 = 1000;
+
+/// Maximum length in bytes of the reason of a close frame.
+/// See <https://websockets.spec.whatwg.org/#dom-websocket-close>.
+const MAX_CLOSE_REASON_LEN: usize = 123;
+
 impl libp2p_core::Transport for Transport {
     type Output = Connection;
     type Error = Error;
@@ -151,14 +170,93 @@ fn extract_websocket_url(addr: &Multiaddr) -> Option<String> {
 #[error("{msg}")]
 pub struct Error {
     msg: String,
+    close_reason: Option<CloseReason>,
 }
 
 impl Error {
     fn invalid_websocket_url(url: &str) -> Self {
         Self {
             msg: format!("Invalid websocket url: {url}"),
+            close_reason: None,
+        }
+    }
+
+    fn invalid_close_code(code: u16) -> Self {
+        Self {
+            msg: format!("Invalid close code: {code}, expected 1000 or 3000-4999"),
+            close_reason: None,
+        }
+    }
+
+    fn close_reason_too_long(reason: &str) -> Self {
+        Self {
+            msg: format!(
+                "Close reason of {} bytes exceeds {MAX_CLOSE_REASON_LEN} bytes",
+                reason.len()
+            ),
+            close_reason: None,
         }
     }
+
+    fn closed(close_reason: CloseReason) -> Self {
+        Self {
+            msg: format!(
+                "WebSocket closed with code {}: {:?}",
+                close_reason.code, close_reason.reason
+            ),
+            close_reason: Some(close_reason),
+        }
+    }
+
+    /// Returns the code and reason the WebSocket was closed with, if the error
+    /// was caused by the WebSocket being closed.
+    ///
+    /// Errors returned by the [`AsyncRead`] and [`AsyncWrite`] implementations of [`Connection`]
+    /// are [`io::Error`]s, which wrap this error if the close code is known:
+    ///
+    /// ```
+    /// # use std::io;
+    /// fn close_reason(error: &io::Error) -> Option<&libp2p_websocket_websys::CloseReason> {
+    ///     error
+    ///         .get_ref()?
+    ///         .downcast_ref::<libp2p_websocket_websys::Error>()?
+    ///         .close_reason()
+    /// }
+    /// ```
+    pub fn close_reason(&self) -> Option<&CloseReason> {
+        self.close_reason.as_ref()
+    }
+}
+
+/// The code and reason a WebSocket was closed with.
+///
+/// See <https://developer.mozilla.org/en-US/docs/Web/API/CloseEvent>.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseReason {
+    code: u16,
+    reason: String,
+    was_clean: bool,
+}
+
+impl CloseReason {
+    /// The status code of the close frame.
+    ///
+    /// See <https://www.rfc-editor.org/rfc/rfc6455.html#section-7.4> for the defined codes.
+    /// E.g. `1008` signals a policy violation, whereas `1006` signals that the connection
+    /// was closed abnormally without a close frame, e.g. due to a network failure.
+    pub fn code(&self) -> u16 {
+        self.code
+    }
+
+    /// The reason of the close frame, if any.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// Whether the connection was closed cleanly, i.e. with a closing handshake.
+    pub fn was_clean(&self) -> bool {
+        self.was_clean
+    }
 }
 
 /// A Websocket connection created by the [`Transport`].
@@ -184,6 +282,12 @@ struct Inner {
     /// Whether the connection errored.
     errored: Rc<AtomicBool>,
 
+    /// The code and reason the WebSocket was closed with, once closed.
+    close_reason: Rc<Mutex<Option<CloseReason>>>,
+
+    /// The code and reason to close the WebSocket with.
+    local_close: (u16, String),
+
     // Store the closures for proper garbage collection.
     // These are wrapped in an [`Rc`] so we can implement [`Clone`].
     _on_open_closure: Rc<Closure<dyn FnMut(Event)>>,
@@ -212,19 +316,28 @@ impl Inner {
                 Poll::Pending
             }
             ReadyState::Open => Poll::Ready(Ok(())),
-            ReadyState::Closed | ReadyState::Closing => {
-                Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
-            }
+            ReadyState::Closed | ReadyState::Closing => Poll::Ready(Err(self.closed_error())),
         }
     }
 
     fn error_barrier(&self) -> io::Result<()> {
         if self.errored.load(Ordering::SeqCst) {
-            return Err(io::ErrorKind::BrokenPipe.into());
+            return Err(self.closed_error());
         }
 
         Ok(())
     }
+
+    /// Returns the error to report once the WebSocket is unusable, carrying the
+    /// close code and reason if the WebSocket has been closed already.
+    fn closed_error(&self) -> io::Error {
+        match self.close_reason.lock().unwrap().clone() {
+            Some(close_reason) => {
+                io::Error::new(io::ErrorKind::BrokenPipe, Error::closed(close_reason))
+            }
+            None => io::ErrorKind::BrokenPipe.into(),
+        }
+    }
 }
 
 /// The state of the WebSocket.
@@ -252,9 +365,16 @@ impl Connection {
         socket.set_onopen(Some(onopen_closure.as_ref().unchecked_ref()));
 
         let close_waker = Rc::new(AtomicWaker::new());
+        let close_reason = Rc::new(Mutex::new(None));
         let onclose_closure = Closure::<dyn FnMut(_)>::new({
             let close_waker = close_waker.clone();
-            move |_| {
+            let close_reason = close_reason.clone();
+            move |e: CloseEvent| {
+                *close_reason.lock().unwrap() = Some(CloseReason {
+                    code: e.code(),
+                    reason: e.reason(),
+                    was_clean: e.was_clean(),
+                });
                 close_waker.wake();
             }
         });
@@ -321,6 +441,8 @@ impl Connection {
                 write_waker,
                 close_waker,
                 errored,
+                close_reason,
+                local_close: (REGULAR_CLOSE, "user initiated".to_owned()),
                 _on_open_closure: Rc::new(onopen_closure),
                 _on_buffered_amount_low_closure: Rc::new(on_buffered_amount_low_closure),
                 _on_close_closure: Rc::new(onclose_closure),
@@ -334,6 +456,28 @@ impl Connection {
     fn buffered_amount(&self) -> usize {
         self.inner.socket.buffered_amount() as usize
     }
+
+    /// Sets the code and reason to send to the remote when closing the connection.
+    ///
+    /// Browsers only allow the code `1000` or a code in the range `3000-4999` and a reason
+    /// of at most 123 bytes. Defaults to `1000` with the reason `"user initiated"`.
+    pub fn set_close_code_and_reason(
+        &mut self,
+        code: u16,
+        reason: impl Into<String>,
+    ) -> Result<(), Error> {
+        let reason = reason.into();
+        validate_close_code_and_reason(code, &reason)?;
+
+        self.inner.local_close = (code, reason);
+
+        Ok(())
+    }
+
+    /// Returns the code and reason the WebSocket was closed with, once it is closed.
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        self.inner.close_reason.lock().unwrap().clone()
+    }
 }
 
 impl AsyncRead for Connection {
@@ -411,8 +555,6 @@ impl AsyncWrite for Connection {
     }
 
     fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        const REGULAR_CLOSE: u16 = 1000; // See https://www.rfc-editor.org/rfc/rfc6455.html#section-7.4.1.
-
         if self.inner.ready_state() == ReadyState::Closed {
             return Poll::Ready(Ok(()));
         }
@@ -420,10 +562,8 @@ impl AsyncWrite for Connection {
         self.inner.error_barrier()?;
 
         if self.inner.ready_state() != ReadyState::Closing {
-            let _ = self
-                .inner
-                .socket
-                .close_with_code_and_reason(REGULAR_CLOSE, "user initiated");
+            let (code, reason) = &self.inner.local_close;
+            let _ = self.inner.socket.close_with_code_and_reason(*code, reason);
         }
 
         self.inner.close_waker.register(cx.waker());
@@ -439,9 +579,7 @@ impl Drop for Connection {
         self.inner.socket.set_onopen(None);
         self.inner.socket.set_onmessage(None);
 
-        // In browsers, userland code is not allowed to use any other status code than 1000: https://websockets.spec.whatwg.org/#dom-websocket-close
-        const REGULAR_CLOSE: u16 = 1000; // See https://www.rfc-editor.org/rfc/rfc6455.html#section-7.4.1.
-
+        // In browsers, userland code is only allowed to use 1000 or 3000-4999: https://websockets.spec.whatwg.org/#dom-websocket-close
         if let ReadyState::Connecting | ReadyState::Open = self.inner.ready_state() {
             let _ = self
                 .inner
@@ -553,4 +691,36 @@ mod tests {
         let addr = "/ip4/127.0.0.1/tcp/2222".parse::<Multiaddr>().unwrap();
         assert!(extract_websocket_url(&addr).is_none());
     }
+
+    #[test]
+    fn close_code_and_reason() {
+        for code in [1000, 3000, 4999] {
+            assert!(validate_close_code_and_reason(code, "").is_ok());
+        }
+        for code in [1001, 1008, 2999, 5000] {
+            assert!(validate_close_code_and_reason(code, "").is_err());
+        }
+
+        assert!(validate_close_code_and_reason(1000, &"a".repeat(MAX_CLOSE_REASON_LEN)).is_ok());
+        assert!(
+            validate_close_code_and_reason(1000, &"a".repeat(MAX_CLOSE_REASON_LEN + 1)).is_err()
+        );
+    }
+
+    #[test]
+    fn close_reason_of_io_error() {
+        let close_reason = CloseReason {
+            code: 1008,
+            reason: "policy violation".to_owned(),
+            was_clean: true,
+        };
+        let error = io::Error::new(
+            io::ErrorKind::BrokenPipe,
+            Error::closed(close_reason.clone()),
+        );
+
+        let error = error.get_ref().unwrap().downcast_ref::<Error>().unwrap();
+        assert_eq!(error.close_reason(), Some(&close_reason));
+        assert_eq!(Error::invalid_websocket_url("foo").close_reason(), None);
+    }
 }
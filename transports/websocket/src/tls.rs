@@ -19,7 +19,8 @@
 // DEALINGS IN THE SOFTWARE.
 
 use futures_rustls::{rustls, TlsAcceptor, TlsConnector};
-use std::{fmt, io, sync::Arc};
+use parking_lot::RwLock;
+use std::{fmt, fs, io, path::Path, sync::Arc};
 
 /// TLS configuration.
 #[derive(Clone)]
@@ -135,6 +136,19 @@ impl Builder {
         Ok(self)
     }
 
+    /// Set a server certificate that can be replaced at runtime through the given
+    /// [`CertificateReloader`].
+    pub fn reloadable_server(&mut self, reloader: &CertificateReloader) -> &mut Self {
+        let provider = rustls::crypto::ring::default_provider();
+        let server = rustls::ServerConfig::builder_with_provider(provider.into())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::new(reloader.clone()));
+        self.server = Some(server);
+        self
+    }
+
     /// Add an additional trust anchor.
     pub fn add_trust(&mut self, cert: &Certificate) -> Result<&mut Self, Error> {
         self.client_root_store
@@ -159,6 +173,92 @@ impl Builder {
     }
 }
 
+/// Handle to replace the server key and certificate chain of a [`Config`] at runtime, e.g. to
+/// rotate certificates before they expire.
+///
+/// Established connections are not affected, only subsequent TLS handshakes use the new
+/// certificate. See [`Builder::reloadable_server`].
+#[derive(Clone)]
+pub struct CertificateReloader {
+    current: Arc<RwLock<Arc<rustls::sign::CertifiedKey>>>,
+}
+
+impl CertificateReloader {
+    /// Create a new reloader serving the given key and certificate chain.
+    pub fn new<I>(key: PrivateKey, certs: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = Certificate>,
+    {
+        Ok(Self {
+            current: Arc::new(RwLock::new(certified_key(key, certs)?)),
+        })
+    }
+
+    /// Replace the served key and certificate chain.
+    pub fn reload<I>(&self, key: PrivateKey, certs: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = Certificate>,
+    {
+        let key = certified_key(key, certs)?;
+        *self.current.write() = key;
+        Ok(())
+    }
+
+    /// Replace the served key and certificate chain with the ones in the given PEM files.
+    ///
+    /// The key file must contain a PKCS#8, PKCS#1 or SEC1 private key, the certificates file the
+    /// certificate chain, starting with the end-entity certificate.
+    pub fn reload_from_pem_files(
+        &self,
+        key_path: impl AsRef<Path>,
+        certs_path: impl AsRef<Path>,
+    ) -> Result<(), Error> {
+        let key = rustls_pemfile::private_key(&mut io::BufReader::new(fs::File::open(key_path)?))?
+            .ok_or_else(|| Error::Tls("no private key found".into()))?;
+        let certs = rustls_pemfile::certs(&mut io::BufReader::new(fs::File::open(certs_path)?))
+            .map(|cert| cert.map(Certificate))
+            .collect::<Result<Vec<_>, _>>()?;
+        if certs.is_empty() {
+            return Err(Error::Tls("no certificate found".into()));
+        }
+        self.reload(PrivateKey(key), certs)
+    }
+
+    fn current(&self) -> Arc<rustls::sign::CertifiedKey> {
+        self.current.read().clone()
+    }
+}
+
+impl fmt::Debug for CertificateReloader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CertificateReloader")
+    }
+}
+
+impl rustls::server::ResolvesServerCert for CertificateReloader {
+    fn resolve(
+        &self,
+        _: rustls::server::ClientHello<'_>,
+    ) -> Option<Arc<rustls::sign::CertifiedKey>> {
+        Some(self.current())
+    }
+}
+
+fn certified_key<I>(key: PrivateKey, certs: I) -> Result<Arc<rustls::sign::CertifiedKey>, Error>
+where
+    I: IntoIterator<Item = Certificate>,
+{
+    let certs = certs.into_iter().map(|c| c.0).collect();
+    let signing_key = rustls::crypto::ring::default_provider()
+        .key_provider
+        .load_private_key(key.0)
+        .map_err(|e| Error::Tls(Box::new(e)))?;
+    Ok(Arc::new(rustls::sign::CertifiedKey::new(
+        certs,
+        signing_key,
+    )))
+}
+
 pub(crate) fn dns_name_ref(name: &str) -> Result<rustls::pki_types::ServerName<'static>, Error> {
     rustls::pki_types::ServerName::try_from(String::from(name))
         .map_err(|_| Error::InvalidDnsName(name.into()))
@@ -203,3 +303,45 @@ impl From<io::Error> for Error {
         Error::Io(e)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::generate_simple_self_signed;
+
+    #[test]
+    fn reload_certificate() {
+        let first = generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let second = generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+
+        let reloader = CertificateReloader::new(
+            PrivateKey::new(first.serialize_private_key_der()),
+            vec![Certificate::new(first.serialize_der().unwrap())],
+        )
+        .unwrap();
+        let mut builder = Config::builder();
+        builder.reloadable_server(&reloader);
+        let config = builder.finish();
+        assert!(config.server.is_some());
+        assert_eq!(
+            reloader.current().end_entity_cert().unwrap().as_ref(),
+            first.serialize_der().unwrap()
+        );
+
+        let dir = std::env::temp_dir();
+        let key_path = dir.join(format!("libp2p-websocket-{}.key", std::process::id()));
+        let cert_path = dir.join(format!("libp2p-websocket-{}.crt", std::process::id()));
+        fs::write(&key_path, second.serialize_private_key_pem()).unwrap();
+        fs::write(&cert_path, second.serialize_pem().unwrap()).unwrap();
+
+        let result = reloader.reload_from_pem_files(&key_path, &cert_path);
+        fs::remove_file(key_path).unwrap();
+        fs::remove_file(cert_path).unwrap();
+        result.unwrap();
+
+        assert_eq!(
+            reloader.current().end_entity_cert().unwrap().as_ref(),
+            second.serialize_der().unwrap()
+        );
+    }
+}